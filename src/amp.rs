@@ -0,0 +1,148 @@
+//! Integration with the installed `amp` CLI, used to have Amp itself check a
+//! settings file.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+/// Outcome of asking Amp to load a settings file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmpCheck {
+    /// Whether amp exited successfully.
+    pub ok: bool,
+    /// Warnings and errors amp printed while loading the file.
+    pub messages: Vec<String>,
+}
+
+/// Locates the `amp` binary on `PATH`.
+pub fn find_amp() -> Option<PathBuf> {
+    find_on_path("amp")
+}
+
+/// Locates an executable on `PATH`, honoring Windows executable extensions.
+pub fn find_on_path(program: &str) -> Option<PathBuf> {
+    let candidate = Path::new(program);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    let paths = env::var_os("PATH")?;
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+    env::split_paths(&paths).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{program}{ext}")))
+            .find(|p| p.is_file())
+    })
+}
+
+/// Has the given amp binary load `settings` and list the permission rules it
+/// resolved, collecting everything it complains about.
+///
+/// Returns `Ok(None)` when the binary doesn't support `--settings-file`.
+pub fn check_settings(amp: &Path, settings: &Path) -> Result<Option<AmpCheck>> {
+    let help = Command::new(amp)
+        .arg("--help")
+        .output()
+        .with_context(|| format!("running {} --help", amp.display()))?;
+    let help_text = String::from_utf8_lossy(&help.stdout);
+    if !help_text.contains("--settings-file") {
+        return Ok(None);
+    }
+
+    let output = Command::new(amp)
+        .arg("--settings-file")
+        .arg(settings)
+        .args(["permissions", "list"])
+        .output()
+        .with_context(|| format!("running {}", amp.display()))?;
+
+    let messages = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    Ok(Some(AmpCheck {
+        ok: output.status.success(),
+        messages,
+    }))
+}
+
+/// Writes an `amp` shell script into `dir` that runs `script`.
+#[cfg(all(test, unix))]
+pub fn fake_amp(dir: &Path, script: &str) -> PathBuf {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join("amp");
+    fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_settings_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let amp = fake_amp(
+            dir.path(),
+            r#"[ "$1" = "--help" ] && echo "  --settings-file <path>"; exit 0"#,
+        );
+        let check = check_settings(&amp, Path::new("/tmp/settings.json"))
+            .unwrap()
+            .unwrap();
+        assert!(check.ok);
+        assert!(check.messages.is_empty());
+    }
+
+    #[test]
+    fn test_check_settings_reports_complaints() {
+        let dir = tempfile::tempdir().unwrap();
+        let amp = fake_amp(
+            dir.path(),
+            r#"[ "$1" = "--help" ] && { echo "--settings-file"; exit 0; }
+echo "warning: unknown key amp.foo" >&2
+echo "" >&2
+echo "error: amp.permissions[0] is invalid" >&2
+exit 1"#,
+        );
+        let check = check_settings(&amp, Path::new("/tmp/settings.json"))
+            .unwrap()
+            .unwrap();
+        assert!(!check.ok);
+        assert_eq!(
+            check.messages,
+            vec![
+                "warning: unknown key amp.foo",
+                "error: amp.permissions[0] is invalid"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_settings_unsupported() {
+        let dir = tempfile::tempdir().unwrap();
+        let amp = fake_amp(dir.path(), "echo 'usage: amp'");
+        assert!(check_settings(&amp, Path::new("/tmp/settings.json"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_on_path_with_explicit_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let amp = fake_amp(dir.path(), "exit 0");
+        assert_eq!(find_on_path(amp.to_str().unwrap()), Some(amp));
+        assert!(find_on_path("/definitely/not/here/amp").is_none());
+    }
+}
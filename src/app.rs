@@ -1,7 +1,47 @@
 //! Application state and logic for the Volt TUI.
 
-use crate::config::Config;
-use crate::settings::{self, Section, SettingType};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+
+use crate::amp;
+use crate::backup;
+use crate::cli;
+use crate::clipboard;
+use crate::config::{self, Config, Conflict};
+use crate::diff::{self, DiffLine};
+use crate::editor::EditSession;
+use crate::events::{TaskResult, Tasks};
+use crate::fuzzy;
+use crate::git;
+use crate::keymap::{Action, KeyBinding, Keymap};
+use crate::lint;
+use crate::macros::{ActionLog, Recorder};
+use crate::matcher;
+use crate::mcp_client::{self, Health};
+use crate::mcp_import::{self, Source};
+use crate::messages::MessageLog;
+use crate::notes::Notes;
+use crate::permissions::{self, Evaluation, Shadowing};
+use crate::prefs::{self, Sidebar};
+use crate::presets::{self, Preset};
+use crate::profile;
+use crate::prompt::{Completion, PromptHistory};
+use crate::registry;
+use crate::rule_form::RuleForm;
+use crate::server_form::{self, ServerForm};
+use crate::session::Session;
+use crate::settings::{self, FieldDef, NumberSpec, ObjectSchema, Section, SettingType};
+use crate::textarea::{self, TextArea, TextInput};
+use crate::theme::Theme;
+use crate::tools;
+use crate::trash::{Removed, Trashed};
+use crate::tree::{self, Step, TreeRow, TreeView};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 /// Which panel currently has focus.
@@ -22,6 +62,10 @@ pub enum InputMode {
     EnteringKeyName,
     /// Selecting a value type for a new custom key.
     SelectingType,
+    /// Picking one of a StringEnum setting's options from a list.
+    SelectingEnum,
+    /// Typing in the command palette to find an action to run.
+    CommandPalette,
     /// Entering a value for a new custom key (string/number).
     EnteringCustomValue,
     /// Entering the tool name for a new permission rule.
@@ -42,6 +86,115 @@ pub enum InputMode {
     ConfirmMcpEdit,
     /// Entering the server name for a new MCP server config.
     EnteringMcpServerName,
+    /// Confirming whether to create a starter settings file when none exists.
+    ConfirmScaffold,
+    /// Picking another Amp config file to open.
+    SelectingFile,
+    /// Viewing a read-only report overlay (e.g. amp validation results).
+    ViewingReport,
+    /// Reviewing the history of status messages.
+    ViewingMessages,
+    /// Editing the free-text note attached to the selected setting.
+    EditingNote,
+    /// Typing a fuzzy search query across all settings.
+    Searching,
+    /// Picking a backup to restore.
+    SelectingBackup,
+    /// Filling in the form for a new MCP server.
+    EditingServer,
+    /// Editing a permission rule in the form overlay.
+    EditingRule,
+    /// Trying a tool call against the permission rules.
+    TestingPermissions,
+    /// Browsing the env variables of an MCP server.
+    EditingEnv,
+    /// Typing the name of a new env variable.
+    EnteringEnvKey,
+    /// Typing the value of an env variable.
+    EnteringEnvValue,
+    /// Picking a profile to load, or choosing to save a new one.
+    SelectingProfile,
+    /// Typing the name to save the settings as a profile under.
+    EnteringProfileName,
+    /// Typing where to write a redacted copy of the settings.
+    EnteringExportPath,
+    EnteringCommitMessage,
+    /// Picking a permission preset to append or replace the rules with, or
+    /// choosing to save the rules as a new one.
+    SelectingPreset,
+    /// Typing the name to save the permission rules as a preset under.
+    EnteringPresetName,
+    /// Picking an MCP server from the catalog to add.
+    BrowsingRegistry,
+    /// Picking something deleted this session to restore.
+    ViewingTrash,
+    /// Picking a recently changed setting to go to.
+    ViewingRecent,
+    /// Asking whether to reset a section, or every setting, to defaults.
+    ConfirmReset,
+    /// Reviewing the diff of unsaved changes.
+    ViewingDiff,
+    /// Choosing how to handle a settings file changed by another program
+    /// while there are unsaved local changes.
+    ResolvingExternalChange,
+    /// Picking the file's or the local value for each key both changed.
+    ResolvingConflicts,
+    /// Editing an object or array in the inline JSON editor.
+    EditingJson,
+    /// Asking whether to save, discard, or keep editing when quitting with
+    /// unsaved changes.
+    ConfirmQuit,
+    /// Asking whether to reopen `$EDITOR` on an edit that didn't parse.
+    ConfirmReopenEditor,
+    /// Picking which tool to import MCP servers from.
+    SelectingImportSource,
+    /// Checking off which of another tool's MCP servers to import.
+    SelectingImportServers,
+    /// Picking what to copy to the clipboard.
+    SelectingCopyFormat,
+    /// Checking off which tools `amp.tools.disable` turns off.
+    SelectingDisabledTools,
+    /// Typing a tool name to add to the disabled tools checklist.
+    EnteringCustomTool,
+    /// Browsing the entries of an object setting in its form.
+    EditingObject,
+    /// Typing the key of a new entry in the object form.
+    EnteringObjectKey,
+    /// Typing an entry's value in the object form.
+    EnteringObjectValue,
+    /// Moving through an object or array opened as a tree under its row.
+    BrowsingTree,
+    /// Typing a new value for a leaf of the open tree.
+    EditingTreeValue,
+}
+
+impl InputMode {
+    /// Whether this mode types into the one-line prompt in `edit_buffer`.
+    pub fn is_text_prompt(&self) -> bool {
+        matches!(
+            self,
+            InputMode::EditingValue
+                | InputMode::EnteringKeyName
+                | InputMode::EnteringCustomValue
+                | InputMode::EnteringPermissionTool
+                | InputMode::EnteringDelegateTo
+                | InputMode::EnteringMcpMatchField
+                | InputMode::EnteringMcpMatchValue
+                | InputMode::EnteringMcpServerName
+                | InputMode::EditingNote
+                | InputMode::Searching
+                | InputMode::EnteringEnvKey
+                | InputMode::EnteringEnvValue
+                | InputMode::EnteringProfileName
+                | InputMode::EnteringExportPath
+                | InputMode::EnteringCommitMessage
+                | InputMode::EnteringPresetName
+                | InputMode::EnteringCustomTool
+                | InputMode::EnteringObjectKey
+                | InputMode::EnteringObjectValue
+                | InputMode::EditingTreeValue
+        )
+    }
 }
 
 /// Value type choices for custom keys in the Advanced section.
@@ -126,6 +279,26 @@ pub enum McpFocus {
     Permissions,
 }
 
+/// The selection state of one settings pane, used for the split view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneState {
+    pub section: usize,
+    pub selected_setting: usize,
+    pub mcp_focus: McpFocus,
+    pub selected_mcp_permission: usize,
+    /// First visible row of the main list.
+    pub setting_offset: usize,
+    /// First visible row of the MCP permissions list.
+    pub mcp_permission_offset: usize,
+}
+
+/// Which side of the split view holds the active pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitSide {
+    Left,
+    Right,
+}
+
 /// A request to open an external editor, returned from app methods.
 #[derive(Debug, Clone)]
 pub struct EditorRequest {
@@ -139,6 +312,316 @@ pub struct EditorRequest {
     pub object_key: Option<String>,
 }
 
+/// The inline JSON editor's buffer and what it will be written back to.
+#[derive(Debug, Clone)]
+pub struct JsonEdit {
+    pub request: EditorRequest,
+    pub text: TextArea,
+    /// Why the buffer doesn't parse, if it doesn't.
+    pub error: Option<JsonError>,
+}
+
+/// A JSON parse error located in the inline editor's buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    /// Zero-based line of the error.
+    pub line: usize,
+    pub message: String,
+}
+
+impl JsonError {
+    fn check(text: &str) -> Option<JsonError> {
+        serde_json::from_str::<Value>(text)
+            .err()
+            .map(|e| JsonError {
+                line: e.line().saturating_sub(1),
+                message: e.to_string(),
+            })
+    }
+}
+
+/// An `$EDITOR` edit that didn't parse, kept so it can be reopened.
+#[derive(Debug)]
+pub struct EditorDraft {
+    pub request: EditorRequest,
+    pub session: EditSession,
+    /// The JSON parse error.
+    pub error: String,
+}
+
+/// A tool call typed into the permission tester.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionTest {
+    pub tool: String,
+    /// A JSON object, or for `Bash` the command itself.
+    pub args: String,
+    /// Whether typing goes to the arguments rather than the tool name.
+    pub editing_args: bool,
+}
+
+/// An object setting being edited field by field, per its schema. Object
+/// fields with schemas of their own open as nested panels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectForm {
+    pub key: &'static str,
+    /// The schema of the whole setting.
+    pub schema: ObjectSchema,
+    /// Keys from the setting down to the object shown.
+    pub path: Vec<String>,
+    /// How deep the form was opened; backing out above this closes it.
+    top: usize,
+    pub selected: usize,
+    /// The selection in each panel above the one shown.
+    parents: Vec<usize>,
+    /// The entry whose value is being typed.
+    pub pending_key: Option<String>,
+}
+
+impl ObjectForm {
+    /// The schema of the object shown.
+    pub fn schema_here(&self) -> Option<&ObjectSchema> {
+        self.schema.descend(&self.path)
+    }
+}
+
+/// One row of the object form: a field the schema declares (set or not),
+/// a map entry, or a key the schema doesn't know.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectRow {
+    pub key: String,
+    /// `None` when the field isn't set and Amp uses its default.
+    pub value: Option<Value>,
+    pub field: Option<FieldDef>,
+}
+
+/// Stands in for sensitive values on screen.
+pub const SECRET_MASK: &str = "••••••";
+
+/// What `Y` copies for the selected setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFormat {
+    Key,
+    Value,
+    /// A `"key": value` line ready to paste into a settings file.
+    Snippet,
+    /// The current section's keys that are set, as a JSON object.
+    Section,
+    /// Every key in the file, as a JSON object.
+    All,
+}
+
+impl CopyFormat {
+    pub const ALL: &[CopyFormat] = &[
+        CopyFormat::Key,
+        CopyFormat::Value,
+        CopyFormat::Snippet,
+        CopyFormat::Section,
+        CopyFormat::All,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyFormat::Key => "Key",
+            CopyFormat::Value => "Value",
+            CopyFormat::Snippet => "JSON snippet",
+            CopyFormat::Section => "Section JSON",
+            CopyFormat::All => "All settings JSON",
+        }
+    }
+}
+
+/// An MCP server offered for import.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportCandidate {
+    pub name: String,
+    /// The server converted to Amp's format.
+    pub server: Value,
+    pub checked: bool,
+    /// Whether a server with this name is already configured.
+    pub exists: bool,
+}
+
+/// The setting edited with the tool checklist rather than item by item.
+const DISABLED_TOOLS_KEY: &str = "amp.tools.disable";
+
+/// A row of the `amp.tools.disable` checklist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolChoice {
+    pub name: String,
+    pub checked: bool,
+    /// Not one of Amp's built-in tools, e.g. an MCP tool.
+    pub custom: bool,
+}
+
+/// Which settings layer is being edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The user-wide settings file.
+    Global,
+    /// The `.amp/settings.json` of the current project, which overrides global keys.
+    Project,
+}
+
+impl Scope {
+    pub fn label(self) -> &'static str {
+        match self {
+            Scope::Global => "global",
+            Scope::Project => "project",
+        }
+    }
+}
+
+/// What a bulk reset covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetScope {
+    Section(Section),
+    All,
+}
+
+/// A group of settings in a section's list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupHeader {
+    pub name: String,
+    /// The index of its first shown setting.
+    pub first: usize,
+    /// How many shown settings it has, hidden ones included.
+    pub count: usize,
+    /// Whether its settings are hidden behind its first one's row.
+    pub collapsed: bool,
+}
+
+/// Which value a merge keeps for a conflicting key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeSide {
+    /// The value on disk.
+    Theirs,
+    /// The unsaved local value.
+    Mine,
+}
+
+/// Where a scrollable list sits on screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListArea {
+    /// The list's panel, including its border.
+    pub area: Rect,
+    /// Screen row of the first visible item.
+    pub first_row: u16,
+    /// How many items fit.
+    pub visible_rows: u16,
+}
+
+/// Where the panels are drawn, used to hit-test mouse events and to keep the
+/// selection scrolled into view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScreenLayout {
+    pub sidebar: Rect,
+    /// Whether the sidebar is a row of tabs above the settings, as on
+    /// narrow terminals.
+    pub sidebar_stacked: bool,
+    /// The active settings pane.
+    pub pane: Rect,
+    /// The inactive pane in split view.
+    pub other_pane: Option<Rect>,
+    /// The active pane's main list (in the MCPs section, the server configs).
+    pub list: ListArea,
+    /// The MCP permissions sub-panel, in the MCPs section.
+    pub mcp_permissions: Option<ListArea>,
+    /// How many characters the selected row's value can scroll sideways
+    /// before its end is in view.
+    pub value_overflow: usize,
+}
+
+impl ScreenLayout {
+    /// The section whose sidebar entry is at `pos`, if any.
+    pub fn section_at(&self, pos: Position) -> Option<usize> {
+        if !self.sidebar.contains(pos) {
+            return None;
+        }
+        if self.sidebar_stacked {
+            return section_tabs(self.sidebar)
+                .iter()
+                .position(|tab| tab.contains(pos));
+        }
+        let index = pos.y.checked_sub(self.sidebar.y + 1)? as usize;
+        (index < Section::ALL.len()).then_some(index)
+    }
+}
+
+/// Splits the inside of a stacked sidebar into one tab per section.
+pub fn section_tabs(bar: Rect) -> Vec<Rect> {
+    let inner = Rect::new(
+        bar.x + 1,
+        bar.y + 1,
+        bar.width.saturating_sub(2),
+        bar.height.saturating_sub(2).min(1),
+    );
+    let count = Section::ALL.len() as u32;
+    let constraints = Section::ALL.iter().map(|_| Constraint::Ratio(1, count));
+    Layout::horizontal(constraints).split(inner).to_vec()
+}
+
+/// Two clicks on the same cell within this interval count as a double-click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// How long a status message stays up when no key is pressed.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long after the last change `--autosave` writes the files.
+pub const AUTOSAVE_DELAY: Duration = Duration::from_secs(3);
+
+/// The file a redacted export is suggested to go to.
+const EXPORT_FILE: &str = "settings.redacted.json";
+
+/// How many settings the recent changes overlay lists.
+const RECENT_LIMIT: usize = 20;
+
+/// How many characters a value scrolls sideways per key press.
+const VALUE_SCROLL_STEP: usize = 8;
+
+/// The permission rule fields the rules table can be sorted by.
+pub const RULE_SORT_COLUMNS: &[&str] = &["tool", "action"];
+
+/// A setting key matched by the search overlay, with the section it lives in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub key: String,
+    pub section: Section,
+}
+
+/// Something the command palette can do: any action, or going to a section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteCommand {
+    Run(Action),
+    GoTo(Section),
+}
+
+impl PaletteCommand {
+    /// Every command, in the order listed before anything is typed.
+    fn all() -> Vec<PaletteCommand> {
+        let actions = Action::ALL
+            .iter()
+            .filter(|a| **a != Action::CommandPalette)
+            .map(|&a| PaletteCommand::Run(a));
+        let sections = Section::ALL.iter().map(|&s| PaletteCommand::GoTo(s));
+        actions.chain(sections).collect()
+    }
+
+    /// The text the palette shows and matches the query against.
+    pub fn label(self) -> String {
+        match self {
+            PaletteCommand::Run(action) => action.description().to_string(),
+            PaletteCommand::GoTo(section) => format!("Go to {}", section.label()),
+        }
+    }
+}
+
+/// A titled list of lines shown in a read-only overlay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
 /// Application state.
 pub struct App {
     pub config: Config,
@@ -150,11 +633,19 @@ pub struct App {
     /// Current input mode.
     pub input_mode: InputMode,
     /// Buffer for inline text editing.
-    pub edit_buffer: String,
+    pub edit_buffer: TextInput,
+    /// What was entered at the key, tool, and server name prompts.
+    prompt_history: PromptHistory,
+    /// `Tab` completion in progress at one of those prompts.
+    completion: Option<Completion>,
     /// Pending custom key name (used during Advanced add flow).
     pub pending_custom_key: Option<String>,
     /// Selected type index during type selection.
     pub selected_type: usize,
+    /// The StringEnum setting being picked for, and the options offered.
+    pub pending_enum: Option<(String, Vec<String>)>,
+    /// Selected option index in the enum picker.
+    pub selected_option: usize,
     /// Pending tool name for permission add flow.
     pub pending_permission_tool: Option<String>,
     /// Selected permission level index during permission add flow.
@@ -169,22 +660,229 @@ pub struct App {
     pub pending_mcp_match_field: Option<String>,
     /// Pending match value for MCP permission add flow.
     pub pending_mcp_match_value: Option<String>,
+    /// Config of the MCP server being duplicated, used as the starting point
+    /// once the copy is named.
+    pub pending_mcp_server_copy: Option<Value>,
+    /// User-installed theme names discovered next to the settings file.
+    pub custom_themes: Vec<String>,
+    /// Path of the main settings file volt was launched with.
+    pub settings_path: PathBuf,
+    /// Candidate files shown in the file switcher.
+    pub file_candidates: Vec<PathBuf>,
+    /// Selected index in the file switcher.
+    pub selected_file: usize,
+    /// Report shown while in `InputMode::ViewingReport`.
+    pub report: Option<Report>,
+    /// The name of the MCP server whose connectivity test is running in the
+    /// background.
+    pub mcp_probe: Option<String>,
+    /// Health checks of the MCP servers: the config each was checked with,
+    /// and the result once it's in.
+    pub mcp_health: BTreeMap<String, (Value, Option<Health>)>,
+    /// Starts background work; its results come back through `finish_task`.
+    pub tasks: Tasks,
+    /// Finished background tasks waiting for the open overlay to close.
+    finished_tasks: VecDeque<TaskResult>,
+    /// When the current status message first appeared, for expiring it.
+    status_shown_at: Option<Instant>,
+    /// Whether the footer shows file statistics.
+    pub show_stats: bool,
+    /// Which keys each section lists; sections not here list all of them.
+    filters: HashMap<Section, SettingsFilter>,
+    /// The groups of General that are collapsed to their header.
+    pub collapsed_groups: BTreeSet<String>,
+    /// The keys pinned to Favorites, in the order they were pinned.
+    pub favorites: Vec<String>,
+    /// Whether changes are saved shortly after they're made, and on quit
+    /// instead of asking.
+    pub autosave: bool,
+    /// When the pending autosave is due.
+    autosave_due: Option<Instant>,
+    /// The combined revision of the open files when changes were last
+    /// noticed.
+    seen_revision: u64,
+    /// Notes attached to settings in the open file.
+    pub notes: Notes,
+    /// The inactive pane while the split view is open.
+    pub split: Option<PaneState>,
+    /// Which side the active pane is rendered on in the split view.
+    pub split_side: SplitSide,
+    /// First visible row of the active pane's main list.
+    pub setting_offset: usize,
+    /// First visible row of the MCP permissions list.
+    pub mcp_permission_offset: usize,
+    /// Panel geometry from the last draw.
+    pub layout: ScreenLayout,
+    /// Time and position of the last left click, for double-click detection.
+    last_click: Option<(Instant, u16, u16)>,
+    /// Whether the help pane describing the selected setting is shown.
+    pub show_help: bool,
+    /// Whether the selected value is shown as JSON beside the settings.
+    pub show_preview: bool,
+    /// Key bindings for normal mode.
+    pub keymap: Keymap,
+    /// Colors used to draw the UI.
+    pub theme: Theme,
+    /// Width of the section sidebar, and whether it's collapsed.
+    pub sidebar: Sidebar,
+    /// The `editor` preference, tried before `$VISUAL` and `$EDITOR`.
+    pub editor: Option<String>,
+    /// Whether a dotted custom key goes inside the object setting a prefix
+    /// of it names, rather than being added as it's written.
+    pub nested_keys: bool,
+    /// Where sidebar changes are remembered; `None` keeps them to this run.
+    pub prefs_path: Option<PathBuf>,
+    /// Which layer `config` holds.
+    pub scope: Scope,
+    /// The other layer while a project settings file is present.
+    pub other_scope_config: Option<Config>,
+    /// Selected index in the copy format picker.
+    pub selected_copy_format: usize,
+    /// Selected index in the MCP import source picker.
+    pub selected_import_source: usize,
+    /// Where each of `Source::ALL` keeps its MCP config; `None` when it has
+    /// no known location.
+    pub import_paths: Vec<Option<PathBuf>>,
+    /// Servers offered by the chosen import source.
+    pub import_candidates: Vec<ImportCandidate>,
+    /// Selected index in the MCP import checklist.
+    pub selected_import: usize,
+    /// The `amp.tools.disable` checklist while picking disabled tools.
+    pub tool_choices: Vec<ToolChoice>,
+    /// Selected index in the disabled tools checklist.
+    pub selected_tool_choice: usize,
+    /// An invalid `$EDITOR` edit awaiting a decision to reopen or discard it.
+    pub editor_draft: Option<EditorDraft>,
+    /// The inline JSON editor while in `InputMode::EditingJson`.
+    pub json_edit: Option<JsonEdit>,
+    /// The new server being filled in while in `InputMode::EditingServer`.
+    pub server_form: Option<ServerForm>,
+    /// The rule being edited while in `InputMode::EditingRule`.
+    pub rule_form: Option<RuleForm>,
+    /// The call being tried while in `InputMode::TestingPermissions`.
+    pub permission_test: Option<PermissionTest>,
+    /// The object setting being edited while in `InputMode::EditingObject`.
+    pub object_form: Option<ObjectForm>,
+    /// The object or array opened in place while in `InputMode::BrowsingTree`.
+    pub tree: Option<TreeView>,
+    /// Diff of unsaved changes shown while in `InputMode::ViewingDiff`.
+    pub diff: Vec<DiffLine>,
+    /// First visible line of the diff overlay.
+    pub diff_scroll: usize,
+    /// Whether the diff overlay shows changes since the last git commit
+    /// rather than unsaved ones.
+    pub diff_from_head: bool,
+    /// The settings file's git status; `None` when it isn't in a repository.
+    pub git_status: Option<git::Status>,
+    /// Backups shown in the restore picker, newest first.
+    pub backup_candidates: Vec<PathBuf>,
+    /// Selected index in the restore picker.
+    pub selected_backup: usize,
+    /// The MCP server whose env variables are being edited.
+    pub env_server: Option<String>,
+    /// Selected row in the env editor.
+    pub selected_env: usize,
+    /// Variable whose value is being typed in the env editor.
+    pub pending_env_key: Option<String>,
+    /// Whether sensitive values are shown instead of masked.
+    pub reveal_secrets: bool,
+    /// Where profiles are stored; `None` when there is no home directory.
+    pub profiles_dir: Option<PathBuf>,
+    /// Profiles shown in the profile picker. The row after the last one
+    /// saves a new profile.
+    pub profile_candidates: Vec<String>,
+    /// Selected index in the profile picker.
+    pub selected_profile: usize,
+    /// Where user permission presets are stored; `None` when there is no
+    /// home directory.
+    pub presets_dir: Option<PathBuf>,
+    /// Presets shown in the preset picker. The row after the last one
+    /// saves the rules as a new preset.
+    pub preset_candidates: Vec<Preset>,
+    /// Selected index in the preset picker.
+    pub selected_preset: usize,
+    /// Servers shown in the MCP catalog browser.
+    pub registry: Vec<registry::Entry>,
+    /// Selected index in the MCP catalog browser.
+    pub selected_registry: usize,
+    /// Where a refreshed catalog is cached; `None` when there is no home
+    /// directory.
+    pub registry_cache: Option<PathBuf>,
+    /// Where refreshing downloads the catalog from.
+    pub registry_url: String,
+    /// Whether a catalog download is running in the background.
+    pub registry_refreshing: bool,
+    /// What the reset being confirmed covers.
+    pub pending_reset: Option<ResetScope>,
+    /// Keys, servers, and array items removed this session, oldest first.
+    pub trash: Vec<Trashed>,
+    /// Selected index in the trash overlay, which lists the newest first.
+    pub selected_trash: usize,
+    /// When each key was last saved with a change, kept in the state file.
+    pub modified: BTreeMap<String, SystemTime>,
+    /// Selected index in the recent changes overlay.
+    pub selected_recent: usize,
+    /// Keys changed both locally and on disk, with the side picked for each.
+    pub merge_conflicts: Vec<(Conflict, MergeSide)>,
+    /// Selected index in the merge view.
+    pub selected_conflict: usize,
+    /// Matches for the current search query, best first.
+    pub search_results: Vec<SearchHit>,
+    /// Selected index in the search results.
+    pub selected_search_result: usize,
+    /// Set when the whole settings file should be opened in `$EDITOR`,
+    /// which the event loop does next.
+    file_edit_requested: bool,
+    /// The macro being recorded and the last one recorded.
+    pub macros: Recorder,
+    /// Where dispatched actions are logged, with `--action-log`.
+    pub action_log: Option<ActionLog>,
+    /// Commands matching the palette's query, best first.
+    pub palette_results: Vec<PaletteCommand>,
+    /// Selected index in the palette's results.
+    pub selected_palette_result: usize,
+    /// Status messages shown so far, for review after they're cleared.
+    pub messages: MessageLog,
+    /// The status message most recently added to `messages`, so one that
+    /// stays up across several redraws is logged once.
+    logged_status: Option<String>,
+    /// How far the message history overlay is scrolled, from the newest.
+    pub messages_scroll: usize,
+    /// The keys typed so far of a multi-key binding such as `g m`.
+    pub pending_keys: Vec<KeyBinding>,
+    /// The column the permission rules table is sorted by for reading. The
+    /// file keeps its order until the sort is applied.
+    pub rule_sort: Option<&'static str>,
+    /// The key whose value is scrolled sideways, and by how many characters.
+    /// Selecting another row shows values from the start again.
+    value_scroll: Option<(String, usize)>,
 }
 
 impl App {
     /// Creates a new App from a loaded config.
     pub fn new(config: Config) -> Self {
+        let custom_themes = config.custom_themes();
+        let settings_path = config.path().to_path_buf();
+        let (notes, status_message) = match Notes::load(config.path()) {
+            Ok(notes) => (notes, load_notice(&config)),
+            Err(e) => (Notes::default(), Some(format!("Notes unavailable: {e:#}"))),
+        };
         Self {
             config,
-            selected_section: 0,
+            // Favorites is first, but General is where a fresh start begins.
+            selected_section: 1,
             selected_setting: 0,
             focus: Focus::Sidebar,
             should_quit: false,
-            status_message: None,
+            status_message,
             input_mode: InputMode::Normal,
-            edit_buffer: String::new(),
+            edit_buffer: TextInput::default(),
+            prompt_history: PromptHistory::default(),
+            completion: None,
             pending_custom_key: None,
             selected_type: 0,
+            pending_enum: None,
+            selected_option: 0,
             pending_permission_tool: None,
             selected_permission_level: 0,
             mcp_focus: McpFocus::Configs,
@@ -192,6 +890,97 @@ impl App {
             selected_mcp_permission_level: 0,
             pending_mcp_match_field: None,
             pending_mcp_match_value: None,
+            pending_mcp_server_copy: None,
+            custom_themes,
+            settings_path,
+            file_candidates: Vec::new(),
+            selected_file: 0,
+            report: None,
+            mcp_probe: None,
+            mcp_health: BTreeMap::new(),
+            // Results are dropped until the event loop connects its own channel.
+            tasks: Tasks::new(mpsc::channel().0),
+            finished_tasks: VecDeque::new(),
+            status_shown_at: None,
+            show_stats: false,
+            filters: HashMap::new(),
+            collapsed_groups: BTreeSet::new(),
+            favorites: Vec::new(),
+            autosave: false,
+            autosave_due: None,
+            seen_revision: 0,
+            notes,
+            split: None,
+            split_side: SplitSide::Left,
+            setting_offset: 0,
+            mcp_permission_offset: 0,
+            layout: ScreenLayout::default(),
+            last_click: None,
+            show_help: false,
+            show_preview: false,
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            sidebar: Sidebar::default(),
+            editor: None,
+            nested_keys: false,
+            prefs_path: None,
+            scope: Scope::Global,
+            other_scope_config: None,
+            selected_copy_format: 0,
+            selected_import_source: 0,
+            import_paths: Source::ALL.iter().map(|s| s.default_path()).collect(),
+            import_candidates: Vec::new(),
+            selected_import: 0,
+            tool_choices: Vec::new(),
+            selected_tool_choice: 0,
+            editor_draft: None,
+            json_edit: None,
+            server_form: None,
+            rule_form: None,
+            permission_test: None,
+            object_form: None,
+            tree: None,
+            diff: Vec::new(),
+            diff_scroll: 0,
+            diff_from_head: false,
+            git_status: None,
+            backup_candidates: Vec::new(),
+            selected_backup: 0,
+            env_server: None,
+            selected_env: 0,
+            pending_env_key: None,
+            reveal_secrets: false,
+            profiles_dir: profile::default_dir().ok(),
+            profile_candidates: Vec::new(),
+            selected_profile: 0,
+            presets_dir: presets::default_dir().ok(),
+            preset_candidates: Vec::new(),
+            selected_preset: 0,
+            registry: Vec::new(),
+            selected_registry: 0,
+            pending_reset: None,
+            trash: Vec::new(),
+            modified: BTreeMap::new(),
+            selected_recent: 0,
+            selected_trash: 0,
+            merge_conflicts: Vec::new(),
+            selected_conflict: 0,
+            registry_cache: registry::default_cache().ok(),
+            registry_url: registry::DEFAULT_URL.to_string(),
+            registry_refreshing: false,
+            search_results: Vec::new(),
+            selected_search_result: 0,
+            file_edit_requested: false,
+            macros: Recorder::default(),
+            action_log: None,
+            palette_results: Vec::new(),
+            selected_palette_result: 0,
+            messages: MessageLog::default(),
+            logged_status: None,
+            messages_scroll: 0,
+            pending_keys: Vec::new(),
+            rule_sort: None,
+            value_scroll: None,
         }
     }
 
@@ -205,14 +994,249 @@ impl App {
         Section::ALL[self.selected_section]
     }
 
-    /// Returns the settings list for the current section.
+    /// Returns the settings list for the current section, as shown.
     pub fn current_settings(&self) -> Vec<SettingEntry> {
+        self.shown_settings(self.current_section())
+    }
+
+    /// Returns the settings a section lists, narrowed by its filter. A
+    /// collapsed group is listed as its first setting, which stands for it.
+    pub fn shown_settings(&self, section: Section) -> Vec<SettingEntry> {
+        self.grouped_settings(section).0
+    }
+
+    /// The group headers in a section's list, in order.
+    pub fn group_headers(&self, section: Section) -> Vec<GroupHeader> {
+        self.grouped_settings(section).1
+    }
+
+    /// The settings a section lists and the headers above them. Only General
+    /// is grouped: keys without a group first, then each group in name order.
+    fn grouped_settings(&self, section: Section) -> (Vec<SettingEntry>, Vec<GroupHeader>) {
+        let mut entries = self.settings_for(section);
+        let filter = self.filter(section);
+        if filter != SettingsFilter::All {
+            entries.retain(|entry| filter.keeps(self.key_state(entry)));
+        }
+        if section != Section::General {
+            return (entries, Vec::new());
+        }
+        entries.sort_by_key(|entry| settings::group_for_key(entry_key(entry)));
+        let mut shown = Vec::with_capacity(entries.len());
+        let mut headers: Vec<GroupHeader> = Vec::new();
+        for entry in entries {
+            let Some(name) = settings::group_for_key(entry_key(&entry)) else {
+                shown.push(entry);
+                continue;
+            };
+            match headers.last_mut().filter(|h| h.name == name) {
+                Some(header) => {
+                    header.count += 1;
+                    if header.collapsed {
+                        continue;
+                    }
+                }
+                None => headers.push(GroupHeader {
+                    collapsed: self.collapsed_groups.contains(&name),
+                    name,
+                    first: shown.len(),
+                    count: 1,
+                }),
+            }
+            shown.push(entry);
+        }
+        (shown, headers)
+    }
+
+    /// The collapsed group the selected row stands for, if it does.
+    pub fn selected_collapsed_group(&self) -> Option<String> {
+        if self.focus != Focus::Settings {
+            return None;
+        }
+        self.group_headers(self.current_section())
+            .into_iter()
+            .find(|h| h.collapsed && h.first == self.selected_setting)
+            .map(|h| h.name)
+    }
+
+    /// Pins the selected setting to Favorites, or unpins it if it's there.
+    pub fn toggle_favorite(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        if let Some(at) = self.favorites.iter().position(|k| *k == key) {
+            self.favorites.remove(at);
+            self.clamp_selection();
+            self.status_message = Some(format!("Unpinned {key} from Favorites"));
+        } else {
+            self.status_message = Some(format!("Pinned {key} to Favorites"));
+            self.favorites.push(key);
+        }
+    }
+
+    /// Collapses the selected setting's group to its header, or expands it
+    /// again, keeping the selection on the group.
+    pub fn toggle_group(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        let Some(name) =
+            settings::group_for_key(&key).filter(|_| self.current_section() == Section::General)
+        else {
+            self.status_message = Some(format!("{key} isn't in a group."));
+            return;
+        };
+        if !self.collapsed_groups.remove(&name) {
+            self.collapsed_groups.insert(name.clone());
+        }
+        if let Some(header) = self
+            .group_headers(Section::General)
+            .into_iter()
+            .find(|h| h.name == name)
+        {
+            self.selected_setting = header.first;
+        }
+    }
+
+    /// Which keys a section lists.
+    pub fn filter(&self, section: Section) -> SettingsFilter {
+        self.filters.get(&section).copied().unwrap_or_default()
+    }
+
+    /// Where the user is, to be restored on the next launch.
+    pub fn session(&self) -> Session {
+        let section = self.current_section();
+        let lists_keys = !section.is_single_key() && !section.is_split_panel();
+        Session {
+            section: Some(section.label().to_string()),
+            key: self.selected_key().filter(|_| lists_keys),
+            row: self.selected_setting,
+            settings_focused: self.focus == Focus::Settings,
+            filters: self
+                .filters
+                .iter()
+                .map(|(section, filter)| (section.label().to_string(), *filter))
+                .collect(),
+            collapsed_groups: self.collapsed_groups.iter().cloned().collect(),
+            favorites: self.favorites.clone(),
+            modified: self
+                .modified
+                .iter()
+                .filter_map(|(key, at)| {
+                    let secs = at.duration_since(UNIX_EPOCH).ok()?.as_secs();
+                    Some((key.clone(), secs))
+                })
+                .collect(),
+        }
+    }
+
+    /// Takes the keys pinned to Favorites and when keys were changed from a
+    /// saved session; a fresh start keeps these too.
+    pub fn restore_history(&mut self, session: &Session) {
+        self.favorites = session.favorites.clone();
+        self.modified = session
+            .modified
+            .iter()
+            .map(|(key, secs)| (key.clone(), UNIX_EPOCH + Duration::from_secs(*secs)))
+            .collect();
+    }
+
+    /// Returns to where a saved session left off, as far as the settings
+    /// still allow: a section or key that's gone is skipped.
+    pub fn restore_session(&mut self, session: &Session) {
+        self.filters = session
+            .filters
+            .iter()
+            .filter_map(|(label, filter)| Some((Section::from_label(label)?, *filter)))
+            .filter(|(_, filter)| *filter != SettingsFilter::All)
+            .collect();
+        self.collapsed_groups = session.collapsed_groups.iter().cloned().collect();
+        self.restore_history(session);
+        if let Some(index) = session
+            .section
+            .as_deref()
+            .and_then(Section::from_label)
+            .and_then(|s| Section::ALL.iter().position(|x| *x == s))
+        {
+            self.select_section(index);
+        }
+        self.selected_setting = session
+            .key
+            .as_ref()
+            .and_then(|key| {
+                self.current_settings()
+                    .iter()
+                    .position(|entry| entry_key(entry) == *key)
+            })
+            .unwrap_or(session.row);
+        self.clamp_selection();
+        if session.settings_focused {
+            self.focus = Focus::Settings;
+        }
+    }
+
+    /// Moves the current section on to the next filter: all settings, only
+    /// the ones set in the file, only the ones left at their defaults.
+    pub fn cycle_filter(&mut self) {
         let section = self.current_section();
+        if section.is_single_key() || section.is_split_panel() {
+            self.status_message = Some(format!(
+                "{} has no settings list to filter.",
+                section.label()
+            ));
+            return;
+        }
+        let key = self.selected_key();
+        let filter = self.filter(section).next();
+        if filter == SettingsFilter::All {
+            self.filters.remove(&section);
+        } else {
+            self.filters.insert(section, filter);
+        }
+        self.status_message = Some(format!(
+            "{}: showing {}",
+            section.label(),
+            filter.description()
+        ));
+        // Stay on the same key when it's still listed.
+        self.selected_setting = key
+            .and_then(|key| {
+                self.current_settings()
+                    .iter()
+                    .position(|entry| entry_key(entry) == key)
+            })
+            .unwrap_or(self.selected_setting);
+        self.clamp_selection();
+    }
+
+    /// Tells whether a settings entry is set in the file and differs from
+    /// its default.
+    pub fn key_state(&self, entry: &SettingEntry) -> KeyState {
+        match entry {
+            SettingEntry::Known(def) => match self.config.get_raw(def.key) {
+                None => KeyState::Unset,
+                Some(value) if *value == def.default => KeyState::SetToDefault,
+                Some(_) => KeyState::Changed,
+            },
+            SettingEntry::Unknown(_) => KeyState::Changed,
+        }
+    }
+
+    /// Returns the settings list for any section.
+    pub fn settings_for(&self, section: Section) -> Vec<SettingEntry> {
         match section {
+            Section::Favorites => self
+                .favorites
+                .iter()
+                .map(|key| match settings::get_setting_def(key) {
+                    Some(def) => SettingEntry::Known(Box::new(def)),
+                    None => SettingEntry::Unknown(key.clone()),
+                })
+                .collect(),
             Section::Advanced => self.advanced_entries(),
             _ => settings::settings_for_section(section)
                 .into_iter()
-                .map(SettingEntry::Known)
+                .map(|def| SettingEntry::Known(Box::new(def)))
                 .collect(),
         }
     }
@@ -273,49 +1297,524 @@ impl App {
             .map_or(0, |a| a.len())
     }
 
-    /// Moves selection up in the current panel.
-    pub fn move_up(&mut self) {
-        match self.focus {
-            Focus::Sidebar => {
-                if self.selected_section > 0 {
-                    self.selected_section -= 1;
-                    self.selected_setting = 0;
-                    self.mcp_focus = McpFocus::Configs;
-                    self.selected_mcp_permission = 0;
-                }
+    /// Returns whether a permission rule names a tool that is neither an Amp
+    /// built-in nor exposed by a configured MCP server. Such rules never match.
+    pub fn permission_rule_has_unknown_tool(&self, rule: &Value) -> bool {
+        let Some(tool) = rule.get("tool").and_then(Value::as_str) else {
+            return false;
+        };
+        !tools::is_known_tool(tool, &self.mcp_server_names())
+    }
+
+    /// Why each permission rule can never apply, in file order.
+    pub fn rule_shadowing(&self) -> Vec<Option<Shadowing>> {
+        let rules = self.config.get("amp.permissions");
+        permissions::shadowing(rules.as_array().map(Vec::as_slice).unwrap_or_default())
+    }
+
+    /// Explains why the selected permission rule can never apply, with the
+    /// key that removes such rules.
+    pub fn selected_rule_shadowing(&self) -> Option<String> {
+        if self.focus != Focus::Settings || self.current_section() != Section::Permissions {
+            return None;
+        }
+        let shadowing = (*self.rule_shadowing().get(self.selected_setting)?)?;
+        Some(format!(
+            "Rule {} {}; press {} to remove shadowed rules",
+            self.selected_setting,
+            shadowing.describe(),
+            self.keymap.label(Action::RemoveShadowedRules)
+        ))
+    }
+
+    /// Drops every permission rule an earlier rule shadows or duplicates.
+    pub fn remove_shadowed_rules(&mut self) {
+        let shadowing = self.rule_shadowing();
+        let removed = shadowing.iter().filter(|s| s.is_some()).count();
+        if removed == 0 {
+            self.status_message = Some("No shadowed or duplicate permission rules.".into());
+            return;
+        }
+        let rules = self.config.get("amp.permissions");
+        let kept: Vec<Value> = rules
+            .as_array()
+            .into_iter()
+            .flatten()
+            .zip(&shadowing)
+            .filter(|(_, s)| s.is_none())
+            .map(|(rule, _)| rule.clone())
+            .collect();
+        if self.current_section() == Section::Permissions {
+            self.selected_setting = self.selected_setting.min(kept.len().saturating_sub(1));
+        }
+        self.config.set("amp.permissions", Value::Array(kept));
+        self.status_message = Some(format!(
+            "Removed {removed} shadowed or duplicate permission rule{}.",
+            if removed == 1 { "" } else { "s" }
+        ));
+    }
+
+    /// Describes the first invalid glob or regex in the selected permission
+    /// rule (Permissions, or MCP permissions), for the status line.
+    pub fn selected_pattern_error(&self) -> Option<String> {
+        if self.focus != Focus::Settings {
+            return None;
+        }
+        let section = self.current_section();
+        let (key, index) = if section == Section::Permissions {
+            ("amp.permissions", self.selected_setting)
+        } else if section.is_split_panel() && self.mcp_focus == McpFocus::Permissions {
+            ("amp.mcpPermissions", self.selected_mcp_permission)
+        } else {
+            return None;
+        };
+        matcher::rule_error(self.config.get(key).get(index)?)
+    }
+
+    /// What `Tab` can complete at the current prompt: setting keys not yet
+    /// set, tools Amp ships or rules already name, servers rules refer to
+    /// that aren't configured, and fields MCP rules match on.
+    pub fn prompt_candidates(&self) -> Vec<String> {
+        let rule_tools = || -> Vec<String> {
+            self.config
+                .get("amp.permissions")
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|rule| rule.get("tool")?.as_str().map(str::to_string))
+                .collect()
+        };
+        match self.input_mode {
+            InputMode::EnteringKeyName => settings::known_settings()
+                .into_iter()
+                .filter(|def| self.config.get_raw(def.key).is_none())
+                .map(|def| def.key.to_string())
+                .collect(),
+            InputMode::EnteringPermissionTool => tools::BUILTIN_TOOLS
+                .iter()
+                .map(|t| t.to_string())
+                .chain(rule_tools())
+                .chain(
+                    self.mcp_server_names()
+                        .into_iter()
+                        .map(|name| format!("mcp__{name}__*")),
+                )
+                .collect(),
+            InputMode::EnteringCustomTool => rule_tools()
+                .into_iter()
+                .filter(|tool| !tool.contains(['*', '?', '[', '/']))
+                .chain(
+                    self.mcp_server_names()
+                        .into_iter()
+                        .map(|name| format!("mcp__{name}__")),
+                )
+                .collect(),
+            InputMode::EnteringMcpServerName => {
+                let configured = self.mcp_server_names();
+                rule_tools()
+                    .iter()
+                    .filter_map(|tool| tools::mcp_server_of(tool))
+                    .filter(|name| !configured.iter().any(|c| c == name))
+                    .map(str::to_string)
+                    .collect()
             }
-            Focus::Settings => {
-                if self.current_section().is_split_panel() {
-                    match self.mcp_focus {
-                        McpFocus::Configs => {
-                            if self.selected_setting > 0 {
-                                self.selected_setting -= 1;
-                            }
-                        }
-                        McpFocus::Permissions => {
-                            if self.selected_mcp_permission > 0 {
-                                self.selected_mcp_permission -= 1;
-                            } else {
-                                // Move focus to configs panel
-                                self.mcp_focus = McpFocus::Configs;
-                                let count = self.mcp_config_count();
-                                self.selected_setting = if count > 0 { count - 1 } else { 0 };
-                            }
-                        }
-                    }
-                } else if self.selected_setting > 0 {
-                    self.selected_setting -= 1;
-                }
+            InputMode::EnteringMcpMatchField => ["command", "url"]
+                .map(String::from)
+                .into_iter()
+                .chain(
+                    self.config
+                        .get("amp.mcpPermissions")
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|rule| rule.get("matches")?.as_object())
+                        .flat_map(|m| m.keys().cloned()),
+                )
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether the current prompt offers completion and history.
+    pub fn prompt_has_assist(&self) -> bool {
+        matches!(
+            self.input_mode,
+            InputMode::EnteringKeyName
+                | InputMode::EnteringPermissionTool
+                | InputMode::EnteringMcpServerName
+                | InputMode::EnteringMcpMatchField
+                | InputMode::EnteringCustomTool
+        )
+    }
+
+    /// Completes the prompt to the next candidate (or previous, with
+    /// `Shift+Tab`) starting with what was typed.
+    pub fn complete_prompt(&mut self, forward: bool) {
+        match &mut self.completion {
+            Some(c) if c.continues(&self.input_mode, &self.edit_buffer) => c.step(forward),
+            _ => {
+                self.completion = Completion::start(
+                    &self.input_mode,
+                    &self.edit_buffer,
+                    self.prompt_candidates(),
+                    forward,
+                );
             }
         }
+        match &self.completion {
+            Some(c) => self.edit_buffer.set(c.current()),
+            None => self.status_message = Some("No completions.".into()),
+        }
     }
 
-    /// Moves selection down in the current panel.
-    pub fn move_down(&mut self) {
-        match self.focus {
-            Focus::Sidebar => {
-                if self.selected_section < Section::ALL.len() - 1 {
-                    self.selected_section += 1;
+    /// The shown completion's position among the matches, while cycling.
+    pub fn completion_position(&self) -> Option<(usize, usize)> {
+        self.completion
+            .as_ref()
+            .filter(|c| c.continues(&self.input_mode, &self.edit_buffer))
+            .map(Completion::position)
+    }
+
+    /// Replaces the prompt with an earlier entry, or a later one.
+    pub fn recall_prompt(&mut self, older: bool) {
+        let recalled = if older {
+            self.prompt_history
+                .older(&self.input_mode, &self.edit_buffer)
+        } else {
+            self.prompt_history
+                .newer(&self.input_mode, &self.edit_buffer)
+        };
+        if let Some(text) = recalled {
+            self.edit_buffer.set(text);
+        }
+    }
+
+    /// Remembers the prompt's text for `↑` recall.
+    fn record_prompt(&mut self) {
+        self.prompt_history
+            .record(&self.input_mode, &self.edit_buffer);
+        self.completion = None;
+    }
+
+    /// Checks the tool or match pattern being typed, so the prompt can flag
+    /// bad syntax before it's committed.
+    pub fn pattern_input_error(&self) -> Option<String> {
+        let pattern = self.edit_buffer.trim();
+        match self.input_mode {
+            InputMode::EnteringPermissionTool | InputMode::EnteringMcpMatchValue
+                if !pattern.is_empty() =>
+            {
+                matcher::check(pattern).err()
+            }
+            _ => None,
+        }
+    }
+
+    /// Describes how the selected setting's stored value has the wrong type
+    /// and whether `fix_type` can convert it.
+    pub fn selected_type_issue(&self) -> Option<String> {
+        if self.focus != Focus::Settings {
+            return None;
+        }
+        let key = self.selected_key()?;
+        let def = settings::get_setting_def(&key)?;
+        let value = self.config.get_raw(&key)?;
+        if config::type_matches(def.setting_type, value) {
+            return None;
+        }
+        let expected = config::type_name(def.setting_type);
+        let fix = self.keymap.label(Action::FixType);
+        Some(match Config::coerce_value(&key, value) {
+            Some(coerced) => format!(
+                "{key} is stored as {}, not a {expected}; press {fix} to coerce to {expected} {}",
+                json_kind(value),
+                serde_json::to_string(&coerced).unwrap_or_default()
+            ),
+            None => format!(
+                "{key} is stored as {}, not a {expected}; edit it or reset it",
+                json_kind(value)
+            ),
+        })
+    }
+
+    /// For a number being typed at the edit prompt: its setting's range
+    /// and unit, and what's wrong with the text so far, if anything.
+    pub fn number_prompt(&self) -> Option<(NumberSpec, Option<String>)> {
+        if self.input_mode != InputMode::EditingValue {
+            return None;
+        }
+        let def = settings::get_setting_def(&self.selected_key()?)?;
+        if def.setting_type != SettingType::Number {
+            return None;
+        }
+        let spec = def.number_spec();
+        let text = self.edit_buffer.trim();
+        let error = if text.is_empty() {
+            None
+        } else {
+            match settings::parse_number(text).and_then(|n| n.as_f64()) {
+                Some(n) => spec.check(n).err(),
+                None => Some("not a number".to_string()),
+            }
+        };
+        Some((spec, error))
+    }
+
+    /// Moves the selected number setting one step up or down, within its
+    /// range.
+    pub fn step_number(&mut self, up: bool) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        let spec = match settings::get_setting_def(&key) {
+            Some(def) if def.setting_type == SettingType::Number => def.number_spec(),
+            Some(_) => {
+                self.status_message = Some(format!("{key} isn't a number."));
+                return;
+            }
+            None if self.config.get(&key).is_number() => NumberSpec::default(),
+            None => {
+                self.status_message = Some(format!("{key} isn't a number."));
+                return;
+            }
+        };
+        let Some(current) = self.effective_value(&key).as_f64() else {
+            self.status_message = Some(format!(
+                "{key} isn't stored as a number; press {} to fix its type.",
+                self.keymap.label(Action::FixType)
+            ));
+            return;
+        };
+        let next = spec.stepped(current, up);
+        if next == current {
+            let bound = if up { "maximum" } else { "minimum" };
+            self.status_message = Some(format!(
+                "{key} is already at its {bound} ({})",
+                settings::format_number(current)
+            ));
+            return;
+        }
+        let Some(number) = settings::number_from_f64(next) else {
+            return;
+        };
+        self.config.set(&key, Value::Number(number));
+        let unit = spec.unit.map(|u| format!(" {u}")).unwrap_or_default();
+        self.status_message = Some(format!("{key} = {}{unit}", settings::format_number(next)));
+    }
+
+    /// Steps a boolean through unset → true → false → unset, so a key can be
+    /// cleared back to its default as well as flipped.
+    fn cycle_boolean(&mut self, key: &str) {
+        match self.config.get_raw(key) {
+            Some(Value::Bool(true)) => self.config.set(key, Value::Bool(false)),
+            Some(Value::Bool(false)) => {
+                self.config.remove(key);
+                let mark = if self.effective_value(key).as_bool().unwrap_or(false) {
+                    "✓"
+                } else {
+                    "✗"
+                };
+                let source = if self.uses_default(key) {
+                    "default"
+                } else {
+                    "global value"
+                };
+                self.status_message = Some(format!("{key} unset; using the {source} ({mark})"));
+            }
+            _ => self.config.set(key, Value::Bool(true)),
+        }
+    }
+
+    /// Whether `key` isn't set in this file or inherited from another
+    /// layer, so it shows its default.
+    pub fn uses_default(&self, key: &str) -> bool {
+        self.config.get_raw(key).is_none()
+            && !(self.scope == Scope::Project
+                && self.layers().is_some_and(|(g, _)| g.get_raw(key).is_some()))
+    }
+
+    /// Converts the selected setting's mistyped value to the type it
+    /// should have, when that's unambiguous.
+    pub fn fix_type(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        let (Some(def), Some(value)) = (settings::get_setting_def(&key), self.config.get_raw(&key))
+        else {
+            self.status_message = Some(format!("{key} has no stored value to fix."));
+            return;
+        };
+        if config::type_matches(def.setting_type, value) {
+            self.status_message = Some(format!("{key} already has the right type."));
+            return;
+        }
+        match Config::coerce_value(&key, value) {
+            Some(coerced) => {
+                self.status_message = Some(format!(
+                    "Coerced {key} to {}",
+                    serde_json::to_string(&coerced).unwrap_or_default()
+                ));
+                self.config.set(&key, coerced);
+            }
+            None => {
+                self.status_message = Some(format!(
+                    "Can't turn {} into a {}; edit it or reset it.",
+                    format_json_short(value),
+                    config::type_name(def.setting_type)
+                ));
+            }
+        }
+    }
+
+    /// Describes the selected setting's replacement when it's deprecated.
+    pub fn selected_deprecation(&self) -> Option<String> {
+        if self.focus != Focus::Settings {
+            return None;
+        }
+        let key = self.selected_key()?;
+        let migration = settings::migration_for(&key)?;
+        Some(format!(
+            "{key} is deprecated, {}; press {} to migrate all",
+            migration.describe(),
+            self.keymap.label(Action::MigrateAll)
+        ))
+    }
+
+    /// Rewrites every deprecated setting to its replacement and reports
+    /// what changed.
+    pub fn migrate_all(&mut self) {
+        let steps = self.config.migrate();
+        if steps.is_empty() {
+            self.status_message = Some("No deprecated settings to migrate.".into());
+            return;
+        }
+        self.clamp_selection();
+        let applied = steps.iter().filter(|s| s.applied).count();
+        let lines = steps
+            .iter()
+            .map(|s| format!("{}: {}", s.old, s.note))
+            .collect();
+        self.show_report(" Migrated settings ", lines);
+        self.status_message = Some(if applied == steps.len() {
+            format!("Migrated {applied} deprecated setting(s) — Ctrl+S to save")
+        } else {
+            format!(
+                "Migrated {applied} of {} deprecated settings; the rest need editing — Ctrl+S to save",
+                steps.len()
+            )
+        });
+    }
+
+    /// Returns whether a known setting's stored value has the wrong type.
+    pub fn has_wrong_type(&self, key: &str) -> bool {
+        match (settings::get_setting_def(key), self.config.get_raw(key)) {
+            (Some(def), Some(value)) => !config::type_matches(def.setting_type, value),
+            _ => false,
+        }
+    }
+
+    /// Returns the setting key the selection currently refers to. Rows inside
+    /// array and object editors resolve to the setting that holds them.
+    pub fn selected_key(&self) -> Option<String> {
+        let section = self.current_section();
+        if section.is_split_panel() {
+            return Some(match self.mcp_focus {
+                McpFocus::Configs => "amp.mcpServers".to_string(),
+                McpFocus::Permissions => "amp.mcpPermissions".to_string(),
+            });
+        }
+        let entries = self.current_settings();
+        let entry = if section.is_single_key() {
+            entries.first()
+        } else {
+            entries.get(self.selected_setting)
+        }?;
+        Some(match entry {
+            SettingEntry::Known(def) => def.key.to_string(),
+            SettingEntry::Unknown(key) => key.clone(),
+        })
+    }
+
+    /// Moves selection up in the current panel.
+    pub fn move_up(&mut self) {
+        match self.focus {
+            Focus::Sidebar => {
+                if self.selected_section > 0 {
+                    self.selected_section -= 1;
+                    self.selected_setting = 0;
+                    self.mcp_focus = McpFocus::Configs;
+                    self.selected_mcp_permission = 0;
+                }
+            }
+            Focus::Settings => {
+                if self.current_section().is_split_panel() {
+                    match self.mcp_focus {
+                        McpFocus::Configs => {
+                            if self.selected_setting > 0 {
+                                self.selected_setting -= 1;
+                            }
+                        }
+                        McpFocus::Permissions => {
+                            if self.selected_mcp_permission > 0 {
+                                self.selected_mcp_permission -= 1;
+                            } else {
+                                // Move focus to configs panel
+                                self.mcp_focus = McpFocus::Configs;
+                                let count = self.mcp_config_count();
+                                self.selected_setting = if count > 0 { count - 1 } else { 0 };
+                            }
+                        }
+                    }
+                } else {
+                    let row = self.display_row(self.selected_setting);
+                    if row > 0 {
+                        self.selected_setting = self.row_index(row - 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collapses the sidebar to section initials, or expands it again.
+    pub fn toggle_sidebar(&mut self) {
+        self.sidebar.collapsed = !self.sidebar.collapsed;
+        self.remember_sidebar();
+    }
+
+    /// Widens or narrows the sidebar by a column.
+    pub fn resize_sidebar(&mut self, wider: bool) {
+        self.sidebar.resize(wider);
+        self.remember_sidebar();
+    }
+
+    fn remember_sidebar(&mut self) {
+        let Some(path) = &self.prefs_path else {
+            return;
+        };
+        if let Err(e) = prefs::save_sidebar(path, self.sidebar) {
+            self.status_message = Some(format!("Sidebar layout not saved: {e:#}"));
+        }
+    }
+
+    /// In the MCPs section, moves focus between the Configs and
+    /// Permissions panels, keeping each one's selection.
+    pub fn switch_mcp_panel(&mut self) {
+        if !self.current_section().is_split_panel() {
+            return;
+        }
+        self.focus = Focus::Settings;
+        self.mcp_focus = match self.mcp_focus {
+            McpFocus::Configs => McpFocus::Permissions,
+            McpFocus::Permissions => McpFocus::Configs,
+        };
+    }
+
+    /// Moves selection down in the current panel.
+    pub fn move_down(&mut self) {
+        match self.focus {
+            Focus::Sidebar => {
+                if self.selected_section < Section::ALL.len() - 1 {
+                    self.selected_section += 1;
                     self.selected_setting = 0;
                     self.mcp_focus = McpFocus::Configs;
                     self.selected_mcp_permission = 0;
@@ -343,14 +1842,253 @@ impl App {
                     }
                 } else {
                     let count = self.current_item_count();
-                    if count > 0 && self.selected_setting < count - 1 {
-                        self.selected_setting += 1;
+                    let row = self.display_row(self.selected_setting);
+                    if count > 0 && row < count - 1 {
+                        self.selected_setting = self.row_index(row + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the selection state of the active pane.
+    pub fn pane_state(&self) -> PaneState {
+        PaneState {
+            section: self.selected_section,
+            selected_setting: self.selected_setting,
+            mcp_focus: self.mcp_focus,
+            selected_mcp_permission: self.selected_mcp_permission,
+            setting_offset: self.setting_offset,
+            mcp_permission_offset: self.mcp_permission_offset,
+        }
+    }
+
+    /// Restores the active pane from a saved selection state.
+    fn set_pane_state(&mut self, state: PaneState) {
+        self.selected_section = state.section;
+        self.selected_setting = state.selected_setting;
+        self.mcp_focus = state.mcp_focus;
+        self.selected_mcp_permission = state.selected_mcp_permission;
+        self.setting_offset = state.setting_offset;
+        self.mcp_permission_offset = state.mcp_permission_offset;
+    }
+
+    /// Opens or closes the split view. The second pane starts on the next section.
+    pub fn toggle_split(&mut self) {
+        if self.split.take().is_some() {
+            return;
+        }
+        self.split = Some(PaneState {
+            section: (self.selected_section + 1) % Section::ALL.len(),
+            selected_setting: 0,
+            mcp_focus: McpFocus::Configs,
+            selected_mcp_permission: 0,
+            setting_offset: 0,
+            mcp_permission_offset: 0,
+        });
+        self.split_side = SplitSide::Left;
+    }
+
+    /// Makes the other split pane active, keeping each pane's selection.
+    pub fn swap_split_pane(&mut self) {
+        let Some(other) = self.split else {
+            return;
+        };
+        self.split = Some(self.pane_state());
+        self.set_pane_state(other);
+        self.split_side = match self.split_side {
+            SplitSide::Left => SplitSide::Right,
+            SplitSide::Right => SplitSide::Left,
+        };
+    }
+
+    /// Selects a section from the sidebar, resetting the selection within it.
+    fn select_section(&mut self, index: usize) {
+        if index != self.selected_section {
+            self.selected_section = index;
+            self.selected_setting = 0;
+            self.mcp_focus = McpFocus::Configs;
+            self.selected_mcp_permission = 0;
+        }
+    }
+
+    /// Handles a left click: selects the section or row under the cursor, and
+    /// activates it on double-click.
+    pub fn click(&mut self, column: u16, row: u16, now: Instant) -> Option<EditorRequest> {
+        let double = self.last_click.is_some_and(|(at, c, r)| {
+            c == column && r == row && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL
+        });
+        // A double-click consumes the pair, so a third click starts over.
+        self.last_click = if double {
+            None
+        } else {
+            Some((now, column, row))
+        };
+
+        let pos = Position::new(column, row);
+        let layout = self.layout;
+
+        if layout.sidebar.contains(pos) {
+            if let Some(index) = layout.section_at(pos) {
+                self.select_section(index);
+                self.focus = Focus::Sidebar;
+                if double {
+                    self.focus = Focus::Settings;
+                }
+            }
+            return None;
+        }
+
+        if layout.other_pane.is_some_and(|r| r.contains(pos)) {
+            self.swap_split_pane();
+            self.focus = Focus::Settings;
+            return None;
+        }
+
+        if !layout.pane.contains(pos) {
+            return None;
+        }
+        self.focus = Focus::Settings;
+
+        let hit = match layout.mcp_permissions {
+            Some(list) if list.area.contains(pos) => {
+                self.mcp_focus = McpFocus::Permissions;
+                let index = row
+                    .checked_sub(list.first_row)
+                    .map(|i| usize::from(i) + self.mcp_permission_offset);
+                match index {
+                    Some(i) if i < self.mcp_permission_item_count() => {
+                        self.selected_mcp_permission = i;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            mcp => {
+                if mcp.is_some() {
+                    self.mcp_focus = McpFocus::Configs;
+                }
+                let index = row
+                    .checked_sub(layout.list.first_row)
+                    .map(|i| usize::from(i) + self.setting_offset);
+                match index {
+                    Some(i) if i < self.list_row_count() => {
+                        self.selected_setting = self.screen_index(i);
+                        true
                     }
+                    _ => false,
                 }
             }
+        };
+
+        if hit && double {
+            self.activate_setting()
+        } else {
+            None
+        }
+    }
+
+    /// Handles the scroll wheel by moving the selection in the panel under the cursor.
+    pub fn scroll(&mut self, column: u16, row: u16, down: bool) {
+        let pos = Position::new(column, row);
+        if self.layout.sidebar.contains(pos) {
+            self.focus = Focus::Sidebar;
+        } else if self.layout.pane.contains(pos) {
+            self.focus = Focus::Settings;
+        } else {
+            return;
+        }
+        if down {
+            self.move_down();
+        } else {
+            self.move_up();
+        }
+    }
+
+    /// Adjusts scroll offsets so the selection stays on screen, using the
+    /// current layout's viewport sizes.
+    pub fn keep_selection_visible(&mut self) {
+        let main_count = self.list_row_count();
+        self.setting_offset = scroll_offset(
+            self.setting_offset,
+            self.screen_row(self.selected_setting),
+            main_count,
+            self.layout.list.visible_rows,
+        );
+        if let Some(list) = self.layout.mcp_permissions {
+            self.mcp_permission_offset = scroll_offset(
+                self.mcp_permission_offset,
+                self.selected_mcp_permission,
+                self.mcp_permission_item_count(),
+                list.visible_rows,
+            );
+        }
+    }
+
+    /// Returns the number of rows a page jump moves in the focused list.
+    fn page_size(&self) -> usize {
+        let rows = match (self.mcp_focus, self.layout.mcp_permissions) {
+            (McpFocus::Permissions, Some(list)) if self.current_section().is_split_panel() => {
+                list.visible_rows
+            }
+            _ => self.layout.list.visible_rows,
+        };
+        usize::from(rows).max(1)
+    }
+
+    /// Moves the selection in the focused list to `index`, clamped to its bounds.
+    fn select_index(&mut self, index: usize) {
+        match self.focus {
+            Focus::Sidebar => self.select_section(index.min(Section::ALL.len() - 1)),
+            Focus::Settings => {
+                let last = self.current_item_count().saturating_sub(1);
+                if self.current_section().is_split_panel()
+                    && self.mcp_focus == McpFocus::Permissions
+                {
+                    self.selected_mcp_permission = index.min(last);
+                } else {
+                    self.selected_setting = self.row_index(index.min(last));
+                }
+            }
+        }
+    }
+
+    /// Returns the selected index in the focused list.
+    fn focused_index(&self) -> usize {
+        match self.focus {
+            Focus::Sidebar => self.selected_section,
+            Focus::Settings
+                if self.current_section().is_split_panel()
+                    && self.mcp_focus == McpFocus::Permissions =>
+            {
+                self.selected_mcp_permission
+            }
+            Focus::Settings => self.display_row(self.selected_setting),
         }
     }
 
+    /// Moves the selection up by one page.
+    pub fn page_up(&mut self) {
+        let index = self.focused_index().saturating_sub(self.page_size());
+        self.select_index(index);
+    }
+
+    /// Moves the selection down by one page.
+    pub fn page_down(&mut self) {
+        let index = self.focused_index() + self.page_size();
+        self.select_index(index);
+    }
+
+    /// Selects the first item in the focused list.
+    pub fn select_first(&mut self) {
+        self.select_index(0);
+    }
+
+    /// Selects the last item in the focused list.
+    pub fn select_last(&mut self) {
+        self.select_index(usize::MAX);
+    }
+
     /// Toggles focus between sidebar and settings panel.
     pub fn toggle_focus(&mut self) {
         self.focus = match self.focus {
@@ -362,6 +2100,10 @@ impl App {
     /// Handles Enter key on the currently selected setting.
     /// Returns an `EditorRequest` if the setting needs to be opened in `$EDITOR`.
     pub fn activate_setting(&mut self) -> Option<EditorRequest> {
+        if self.config.is_read_only() {
+            self.inspect_selected();
+            return None;
+        }
         if self.current_section().is_single_key() {
             return self.activate_single_key_item();
         }
@@ -373,34 +2115,49 @@ impl App {
         let entries = self.current_settings();
         let entry = entries.get(self.selected_setting)?;
 
+        // Keys with a panel of their own are edited there.
+        if self.current_section() == Section::Favorites {
+            let key = entry_key(entry).to_string();
+            if settings::section_for_key(&key)
+                .is_some_and(|s| s.is_single_key() || s.is_split_panel())
+            {
+                self.go_to_key(&key);
+                return None;
+            }
+        }
+
         match entry {
             SettingEntry::Known(def) => match def.setting_type {
                 SettingType::Boolean => {
-                    let current = self.config.get(def.key);
-                    let toggled = !current.as_bool().unwrap_or(false);
-                    self.config.set(def.key, Value::Bool(toggled));
+                    self.cycle_boolean(def.key);
                     None
                 }
                 SettingType::String | SettingType::Number => {
                     self.input_mode = InputMode::EditingValue;
                     let current = self.config.get(def.key);
-                    self.edit_buffer = match &current {
+                    self.edit_buffer.set(match &current {
                         Value::String(s) => s.clone(),
                         Value::Number(n) => n.to_string(),
                         _ => String::new(),
-                    };
+                    });
                     None
                 }
                 SettingType::StringEnum => {
-                    self.cycle_enum(def);
+                    self.start_enum_select(def);
                     None
                 }
-                SettingType::Object => Some(EditorRequest {
-                    key: def.key.to_string(),
-                    value: self.config.get(def.key),
-                    array_index: None,
-                    object_key: None,
-                }),
+                SettingType::Object => match def.object_schema.clone() {
+                    Some(schema) if self.config.get(def.key).is_object() => {
+                        self.start_object_form(def.key, schema, Vec::new());
+                        None
+                    }
+                    _ => Some(EditorRequest {
+                        key: def.key.to_string(),
+                        value: self.config.get(def.key),
+                        array_index: None,
+                        object_key: None,
+                    }),
+                },
                 SettingType::ArrayObject => {
                     let arr = self.config.get(def.key);
                     let items = arr.as_array().cloned().unwrap_or_default();
@@ -418,6 +2175,10 @@ impl App {
                         })
                     }
                 }
+                SettingType::ArrayString if def.key == DISABLED_TOOLS_KEY => {
+                    self.start_tool_picker();
+                    None
+                }
                 SettingType::ArrayString => {
                     self.status_message =
                         Some("Press 'a' to add, 'd' to delete items.".to_string());
@@ -433,11 +2194,11 @@ impl App {
                     }
                     Value::String(_) | Value::Number(_) => {
                         self.input_mode = InputMode::EditingValue;
-                        self.edit_buffer = match &value {
+                        self.edit_buffer.set(match &value {
                             Value::String(s) => s.clone(),
                             Value::Number(n) => n.to_string(),
                             _ => String::new(),
-                        };
+                        });
                         None
                     }
                     Value::Array(_) => {
@@ -458,7 +2219,9 @@ impl App {
     }
 
     /// Activates the selected array item in a single-key section.
-    fn activate_single_key_item(&self) -> Option<EditorRequest> {
+    /// Permission rules open in the form; anything else (or a rule that
+    /// isn't an object) goes to `$EDITOR`.
+    fn activate_single_key_item(&mut self) -> Option<EditorRequest> {
         let entries = self.current_settings();
         let def = match entries.first() {
             Some(SettingEntry::Known(def)) => def,
@@ -467,6 +2230,11 @@ impl App {
         let arr = self.config.get(def.key);
         let items = arr.as_array().cloned().unwrap_or_default();
         let item = items.get(self.selected_setting)?;
+        if let (true, Some(rule)) = (def.key == "amp.permissions", item.as_object()) {
+            self.rule_form = Some(RuleForm::new(self.selected_setting, rule));
+            self.input_mode = InputMode::EditingRule;
+            return None;
+        }
         Some(EditorRequest {
             key: def.key.to_string(),
             value: item.clone(),
@@ -483,12 +2251,19 @@ impl App {
                 let name = server_names.get(self.selected_setting)?;
                 let servers = self.config.get("amp.mcpServers");
                 let server_config = servers.get(name)?.clone();
-                Some(EditorRequest {
-                    key: "amp.mcpServers".to_string(),
-                    value: server_config,
-                    array_index: None,
-                    object_key: Some(name.clone()),
-                })
+                let schema = settings::get_setting_def("amp.mcpServers")?.object_schema;
+                match schema {
+                    Some(schema) if server_config.is_object() => {
+                        self.start_object_form("amp.mcpServers", schema, vec![name.clone()]);
+                        None
+                    }
+                    _ => Some(EditorRequest {
+                        key: "amp.mcpServers".to_string(),
+                        value: server_config,
+                        array_index: None,
+                        object_key: Some(name.clone()),
+                    }),
+                }
             }
             McpFocus::Permissions => {
                 let arr = self.config.get("amp.mcpPermissions");
@@ -585,6 +2360,474 @@ impl App {
         }
     }
 
+    /// Holds on to an `$EDITOR` edit that didn't parse and asks whether to
+    /// reopen it.
+    pub fn keep_invalid_edit(
+        &mut self,
+        request: EditorRequest,
+        session: EditSession,
+        error: String,
+    ) {
+        self.editor_draft = Some(EditorDraft {
+            request,
+            session,
+            error,
+        });
+        self.input_mode = InputMode::ConfirmReopenEditor;
+    }
+
+    /// Reopens the kept draft in `$EDITOR`.
+    pub fn confirm_reopen_editor(&mut self) -> Option<EditorRequest> {
+        self.input_mode = InputMode::Normal;
+        self.editor_draft.as_ref().map(|d| d.request.clone())
+    }
+
+    /// Throws away the kept draft.
+    pub fn decline_reopen_editor(&mut self) {
+        self.cancel_edit();
+        self.status_message = Some("Discarded the invalid edit".into());
+    }
+
+    /// Takes the kept draft's temp file so the editor reopens it.
+    pub fn take_editor_draft(&mut self) -> Option<EditSession> {
+        self.editor_draft.take().map(|d| d.session)
+    }
+
+    /// Opens the picker for copying the selected setting's key or value,
+    /// or the settings of its section or the whole file.
+    pub fn start_copy(&mut self) {
+        self.selected_copy_format = 0;
+        self.input_mode = InputMode::SelectingCopyFormat;
+    }
+
+    /// Moves copy format selection up.
+    pub fn copy_format_up(&mut self) {
+        if self.selected_copy_format > 0 {
+            self.selected_copy_format -= 1;
+        }
+    }
+
+    /// Moves copy format selection down.
+    pub fn copy_format_down(&mut self) {
+        if self.selected_copy_format + 1 < CopyFormat::ALL.len() {
+            self.selected_copy_format += 1;
+        }
+    }
+
+    /// Returns the text `Y` would copy in the given format. Copies of a
+    /// section or the whole file have their secrets redacted, so they can
+    /// go into a bug report.
+    pub fn copy_text(&self, format: CopyFormat) -> Option<String> {
+        let keys = match format {
+            CopyFormat::Section => self
+                .settings_for(self.current_section())
+                .iter()
+                .map(|entry| entry_key(entry).to_string())
+                .collect(),
+            CopyFormat::All => self.config.entries().map(|(key, _)| key.clone()).collect(),
+            CopyFormat::Key | CopyFormat::Value | CopyFormat::Snippet => {
+                return self.selected_copy_text(format);
+            }
+        };
+        serde_json::to_string_pretty(&self.redacted_settings(keys)).ok()
+    }
+
+    /// The selected setting's key, value, or `"key": value` line.
+    fn selected_copy_text(&self, format: CopyFormat) -> Option<String> {
+        let key = self.selected_key()?;
+        let value = self.config.get(&key);
+        let pretty = serde_json::to_string_pretty(&value).ok()?;
+        Some(match format {
+            CopyFormat::Key => key,
+            CopyFormat::Value => match value {
+                Value::String(s) => s,
+                _ => pretty,
+            },
+            _ => format!("{}: {pretty}", Value::String(key)),
+        })
+    }
+
+    /// The file's values for those of `keys` that are set, as a JSON object
+    /// with secrets redacted.
+    fn redacted_settings(&self, keys: Vec<String>) -> Value {
+        Value::Object(
+            keys.into_iter()
+                .filter_map(|key| {
+                    let value = settings::redact(&key, self.config.get_raw(&key)?);
+                    Some((key, value))
+                })
+                .collect(),
+        )
+    }
+
+    /// Copies the chosen text to the system clipboard.
+    pub fn commit_copy(&mut self) {
+        let format = CopyFormat::ALL[self.selected_copy_format];
+        self.cancel_edit();
+        let Some(text) = self.copy_text(format) else {
+            self.status_message = Some("No setting is selected to copy.".into());
+            return;
+        };
+        self.status_message = Some(match clipboard::copy(&text) {
+            Ok(via) => format!("Copied {} ({via})", format.label().to_lowercase()),
+            Err(e) => format!("Copy failed: {e:#}"),
+        });
+    }
+
+    /// Inserts text pasted into the terminal into whatever is being typed.
+    /// Outside a text field it's ignored, rather than run as keys.
+    pub fn insert_pasted(&mut self, text: &str) {
+        match self.input_mode {
+            InputMode::EditingJson => self.edit_json(|t| t.insert_str(text)),
+            InputMode::EditingServer => self.edit_server_form(|f| {
+                if let Some(field) = f.text_mut() {
+                    field.push_str(&textarea::single_line(text));
+                }
+            }),
+            InputMode::EditingRule => self.edit_rule_form(|f| {
+                if let Some(field) = f.text_mut() {
+                    field.push_str(&textarea::single_line(text));
+                }
+            }),
+            InputMode::TestingPermissions => {
+                self.edit_permission_test(|field| field.push_str(&textarea::single_line(text)))
+            }
+            InputMode::Searching => {
+                self.edit_buffer.insert_str(text);
+                self.update_search();
+            }
+            ref mode if mode.is_text_prompt() => self.edit_buffer.insert_str(text),
+            _ => {}
+        }
+    }
+
+    /// Sets the selected setting from the clipboard.
+    pub fn paste(&mut self) {
+        match clipboard::paste() {
+            Ok(text) => self.paste_text(&text),
+            Err(e) => self.status_message = Some(format!("Paste failed: {e:#}")),
+        }
+    }
+
+    /// Sets the selected setting from pasted text: a JSON value, a snippet
+    /// copied with `Y`, or bare text for string settings. The value must
+    /// have the setting's type.
+    fn paste_text(&mut self, text: &str) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        let mut raw = text.trim();
+        let prefix = format!("{}:", Value::String(key.clone()));
+        if let Some(rest) = raw.strip_prefix(&prefix) {
+            raw = rest.trim().trim_end_matches(',').trim_end();
+        }
+        let value = match serde_json::from_str::<Value>(raw) {
+            Ok(Value::String(s)) => Ok(Value::String(s)),
+            _ => cli::parse_value(&key, raw),
+        };
+        match value.and_then(|v| Config::validate_value(&key, &v).map(|()| v)) {
+            Ok(value) => {
+                self.config.set(&key, value);
+                self.status_message = Some(format!("Pasted into {key}"));
+            }
+            Err(e) => self.status_message = Some(format!("Paste rejected: {e:#}")),
+        }
+    }
+
+    /// Opens the picker for importing MCP servers from another tool.
+    pub fn start_mcp_import(&mut self) {
+        if !self.current_section().is_split_panel() {
+            self.status_message = Some("Import MCP servers from the MCPs section.".into());
+            return;
+        }
+        self.selected_import_source = 0;
+        self.input_mode = InputMode::SelectingImportSource;
+    }
+
+    /// Moves import source selection up.
+    pub fn import_source_up(&mut self) {
+        if self.selected_import_source > 0 {
+            self.selected_import_source -= 1;
+        }
+    }
+
+    /// Moves import source selection down.
+    pub fn import_source_down(&mut self) {
+        if self.selected_import_source + 1 < Source::ALL.len() {
+            self.selected_import_source += 1;
+        }
+    }
+
+    /// Reads the chosen tool's config from its usual location and lists its
+    /// servers for checking off.
+    pub fn commit_import_source(&mut self) {
+        let source = Source::ALL[self.selected_import_source];
+        match self
+            .import_paths
+            .get(self.selected_import_source)
+            .cloned()
+            .flatten()
+        {
+            Some(path) if path.exists() => self.load_import_candidates(source, &path),
+            Some(path) => {
+                self.cancel_edit();
+                self.status_message = Some(format!(
+                    "No {} config at {}",
+                    source.label(),
+                    path.display()
+                ));
+            }
+            None => {
+                self.cancel_edit();
+                self.status_message = Some(format!("No {} config location", source.label()));
+            }
+        }
+    }
+
+    /// Lists the servers in a config file, pre-checking those not already
+    /// configured.
+    fn load_import_candidates(&mut self, source: Source, path: &Path) {
+        let servers = match mcp_import::read(source, path) {
+            Ok(servers) => servers,
+            Err(e) => {
+                self.cancel_edit();
+                self.status_message = Some(format!("Import failed: {e:#}"));
+                return;
+            }
+        };
+        if servers.is_empty() {
+            self.cancel_edit();
+            self.status_message = Some(format!("{} has no MCP servers", path.display()));
+            return;
+        }
+        let existing = self.mcp_server_names();
+        self.import_candidates = servers
+            .into_iter()
+            .map(|(name, server)| {
+                let exists = existing.contains(&name);
+                ImportCandidate {
+                    name,
+                    server,
+                    checked: !exists,
+                    exists,
+                }
+            })
+            .collect();
+        self.selected_import = 0;
+        self.input_mode = InputMode::SelectingImportServers;
+    }
+
+    /// Moves import checklist selection up.
+    pub fn import_select_up(&mut self) {
+        if self.selected_import > 0 {
+            self.selected_import -= 1;
+        }
+    }
+
+    /// Moves import checklist selection down.
+    pub fn import_select_down(&mut self) {
+        if self.selected_import + 1 < self.import_candidates.len() {
+            self.selected_import += 1;
+        }
+    }
+
+    /// Checks or unchecks the selected server.
+    pub fn toggle_import_candidate(&mut self) {
+        if let Some(candidate) = self.import_candidates.get_mut(self.selected_import) {
+            candidate.checked = !candidate.checked;
+        }
+    }
+
+    /// Opens the checklist of tools to disable: Amp's built-in tools, then
+    /// any other names already in `amp.tools.disable`.
+    fn start_tool_picker(&mut self) {
+        let disabled: Vec<String> = self
+            .config
+            .get(DISABLED_TOOLS_KEY)
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect();
+        self.tool_choices = tools::BUILTIN_TOOLS
+            .iter()
+            .map(|name| ToolChoice {
+                name: name.to_string(),
+                checked: disabled.iter().any(|d| d == name),
+                custom: false,
+            })
+            .chain(
+                disabled
+                    .iter()
+                    .filter(|d| !tools::BUILTIN_TOOLS.contains(&d.as_str()))
+                    .map(|name| ToolChoice {
+                        name: name.clone(),
+                        checked: true,
+                        custom: true,
+                    }),
+            )
+            .collect();
+        self.selected_tool_choice = 0;
+        self.input_mode = InputMode::SelectingDisabledTools;
+    }
+
+    /// Moves the disabled tools checklist selection up.
+    pub fn tool_choice_up(&mut self) {
+        self.selected_tool_choice = self.selected_tool_choice.saturating_sub(1);
+    }
+
+    /// Moves the disabled tools checklist selection down.
+    pub fn tool_choice_down(&mut self) {
+        if self.selected_tool_choice + 1 < self.tool_choices.len() {
+            self.selected_tool_choice += 1;
+        }
+    }
+
+    /// Checks or unchecks the selected tool.
+    pub fn toggle_tool_choice(&mut self) {
+        if let Some(choice) = self.tool_choices.get_mut(self.selected_tool_choice) {
+            choice.checked = !choice.checked;
+        }
+    }
+
+    /// Asks for a tool name that isn't in the checklist.
+    pub fn start_add_custom_tool(&mut self) {
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::EnteringCustomTool;
+    }
+
+    /// Adds the typed tool to the checklist, checked, or checks it if it's
+    /// already listed.
+    pub fn commit_custom_tool(&mut self) {
+        let name = self.edit_buffer.trim().to_string();
+        if name.is_empty() {
+            self.status_message = Some("Tool name cannot be empty.".to_string());
+            return;
+        }
+        self.record_prompt();
+        self.edit_buffer.clear();
+        match self.tool_choices.iter().position(|c| c.name == name) {
+            Some(i) => {
+                self.tool_choices[i].checked = true;
+                self.selected_tool_choice = i;
+            }
+            None => {
+                self.tool_choices.push(ToolChoice {
+                    name,
+                    checked: true,
+                    custom: true,
+                });
+                self.selected_tool_choice = self.tool_choices.len() - 1;
+            }
+        }
+        self.input_mode = InputMode::SelectingDisabledTools;
+    }
+
+    /// Returns from typing a custom tool to the checklist.
+    pub fn cancel_custom_tool(&mut self) {
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::SelectingDisabledTools;
+    }
+
+    /// Writes the checked tools to `amp.tools.disable`. Tools that were
+    /// already disabled keep their place; newly checked ones follow in
+    /// checklist order.
+    pub fn commit_tool_picker(&mut self) {
+        let current = self.config.get(DISABLED_TOOLS_KEY);
+        let checked: Vec<&str> = self
+            .tool_choices
+            .iter()
+            .filter(|c| c.checked)
+            .map(|c| c.name.as_str())
+            .collect();
+        let mut disabled: Vec<Value> = current
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|v| v.as_str().is_some_and(|name| checked.contains(&name)))
+            .cloned()
+            .collect();
+        for name in checked {
+            if !disabled.iter().any(|v| v == name) {
+                disabled.push(Value::String(name.to_string()));
+            }
+        }
+        let count = disabled.len();
+        let changed = current.as_array() != Some(&disabled);
+        self.cancel_edit();
+        if changed {
+            self.config.set(DISABLED_TOOLS_KEY, Value::Array(disabled));
+            self.status_message = Some(format!("{count} tool(s) disabled — Ctrl+S to save"));
+        }
+    }
+
+    /// Merges the checked servers into `amp.mcpServers`.
+    pub fn commit_mcp_import(&mut self) {
+        let servers: Vec<(String, Value)> = self
+            .import_candidates
+            .iter()
+            .filter(|c| c.checked)
+            .map(|c| (c.name.clone(), c.server.clone()))
+            .collect();
+        self.cancel_edit();
+        if servers.is_empty() {
+            self.status_message = Some("No servers selected.".into());
+            return;
+        }
+        mcp_import::merge(&mut self.config, &servers);
+        self.status_message = Some(format!(
+            "Imported {} MCP server(s) — Ctrl+S to save",
+            servers.len()
+        ));
+    }
+
+    /// Opens the selected object or array in the inline JSON editor, the
+    /// in-TUI alternative to `force_editor`.
+    pub fn start_json_edit(&mut self) {
+        let Some(request) = self.force_editor() else {
+            return;
+        };
+        if !(request.value.is_object() || request.value.is_array()) {
+            self.status_message = Some("The inline JSON editor is for objects and arrays.".into());
+            return;
+        }
+        let text = serde_json::to_string_pretty(&request.value).unwrap_or_default();
+        self.json_edit = Some(JsonEdit {
+            request,
+            text: TextArea::new(&text),
+            error: None,
+        });
+        self.input_mode = InputMode::EditingJson;
+    }
+
+    /// Applies an edit to the inline JSON editor's buffer and re-checks
+    /// whether it parses.
+    pub fn edit_json(&mut self, f: impl FnOnce(&mut TextArea)) {
+        if let Some(edit) = &mut self.json_edit {
+            f(&mut edit.text);
+            edit.error = JsonError::check(&edit.text.text());
+        }
+    }
+
+    /// Writes the inline editor's buffer back to the setting, or keeps the
+    /// editor open if it doesn't parse.
+    pub fn commit_json_edit(&mut self) {
+        let Some(edit) = &mut self.json_edit else {
+            return;
+        };
+        match serde_json::from_str::<Value>(&edit.text.text()) {
+            Ok(value) => {
+                let request = edit.request.clone();
+                self.cancel_edit();
+                self.apply_editor_result(&request, value);
+            }
+            Err(e) => {
+                edit.error = JsonError::check(&edit.text.text());
+                self.status_message = Some(format!("Invalid JSON: {e}"));
+            }
+        }
+    }
+
     /// Adds an item to a string array setting (prompts for value via edit buffer).
     pub fn add_array_item(&mut self) {
         if self.current_section() == Section::Advanced {
@@ -659,12 +2902,13 @@ impl App {
                 .as_array()
                 .cloned()
                 .unwrap_or_default();
-            if arr.is_empty() {
-                self.status_message = Some("Array is already empty.".to_string());
-            } else {
-                arr.pop();
+            if let Some(value) = arr.pop() {
+                let index = arr.len();
                 self.config.set(&key, Value::Array(arr));
                 self.status_message = Some(format!("Removed last item from {key}"));
+                self.trash_removed(Removed::Item { key, index, value });
+            } else {
+                self.status_message = Some("Array is already empty.".to_string());
             }
             return;
         }
@@ -686,22 +2930,265 @@ impl App {
                     self.status_message = Some("Array is already empty.".to_string());
                 } else if section.is_single_key() {
                     let idx = self.selected_setting.min(arr.len() - 1);
-                    arr.remove(idx);
+                    let value = arr.remove(idx);
                     self.config.set(def.key, Value::Array(arr.clone()));
                     self.status_message = Some(format!("Removed item {} from {}", idx, def.key));
+                    self.trash_removed(Removed::Item {
+                        key: def.key.to_string(),
+                        index: idx,
+                        value,
+                    });
                     if !arr.is_empty() && self.selected_setting >= arr.len() {
                         self.selected_setting = arr.len() - 1;
                     }
-                } else {
-                    arr.pop();
+                } else if let Some(value) = arr.pop() {
+                    let index = arr.len();
                     self.config.set(def.key, Value::Array(arr));
                     self.status_message = Some(format!("Removed last item from {}", def.key));
+                    self.trash_removed(Removed::Item {
+                        key: def.key.to_string(),
+                        index,
+                        value,
+                    });
                 }
             }
             _ => {}
         }
     }
 
+    /// Duplicates the selected array item or MCP server. Array items are
+    /// copied in place below the original; an MCP server copy is named first
+    /// and then opened in `$EDITOR`.
+    pub fn duplicate_item(&mut self) {
+        let section = self.current_section();
+        if section.is_split_panel() && self.mcp_focus == McpFocus::Configs {
+            let names = self.mcp_server_names();
+            let Some(name) = names.get(self.selected_setting) else {
+                return;
+            };
+            self.pending_mcp_server_copy = Some(self.config.get("amp.mcpServers")[name].clone());
+            self.edit_buffer.set(format!("{name}-copy"));
+            self.input_mode = InputMode::EnteringMcpServerName;
+            return;
+        }
+
+        let (key, idx) = if section.is_split_panel() {
+            (
+                "amp.mcpPermissions".to_string(),
+                self.selected_mcp_permission,
+            )
+        } else if section.is_single_key() {
+            match self.selected_array_def() {
+                Some(def) => (def.key.to_string(), self.selected_setting),
+                None => return,
+            }
+        } else {
+            self.status_message = Some("Only list items and MCP servers can be duplicated.".into());
+            return;
+        };
+
+        let mut arr = self
+            .config
+            .get(&key)
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let Some(item) = arr.get(idx).cloned() else {
+            return;
+        };
+        arr.insert(idx + 1, item);
+        self.config.set(&key, Value::Array(arr));
+        if section.is_split_panel() {
+            self.selected_mcp_permission = idx + 1;
+        } else {
+            self.selected_setting = idx + 1;
+        }
+        self.status_message = Some(format!("Duplicated item {idx} of {key}"));
+    }
+
+    /// Steps the permission rules table through sorting by each of
+    /// [`RULE_SORT_COLUMNS`] and back to file order. Only the view changes;
+    /// the selection stays on the same rule.
+    pub fn cycle_rule_sort(&mut self) {
+        if !self.current_section().is_single_key() {
+            self.status_message = Some("Only permission rules can be sorted.".into());
+            return;
+        }
+        let next = match self.rule_sort {
+            None => 0,
+            Some(column) => {
+                RULE_SORT_COLUMNS
+                    .iter()
+                    .position(|c| *c == column)
+                    .unwrap_or(0)
+                    + 1
+            }
+        };
+        self.rule_sort = RULE_SORT_COLUMNS.get(next).copied();
+        self.status_message = Some(match self.rule_sort {
+            Some(column) => format!(
+                "Showing rules sorted by {column}; press {} to reorder the file this way.",
+                self.keymap.label(Action::ApplySort)
+            ),
+            None => "Showing rules in file order.".into(),
+        });
+    }
+
+    /// The permission rules' indices in the order the table shows them:
+    /// sorted by the [`App::rule_sort`] column, with rules of equal (or no)
+    /// value keeping their file order.
+    pub fn rule_rows(&self) -> Vec<usize> {
+        let rules = self.config.get("amp.permissions");
+        let rules = rules.as_array().map(Vec::as_slice).unwrap_or_default();
+        let mut rows: Vec<usize> = (0..rules.len()).collect();
+        if let Some(column) = self.rule_sort {
+            let text = |i: usize| rules[i].get(column).and_then(Value::as_str);
+            rows.sort_by(|&a, &b| match (text(a), text(b)) {
+                (Some(a), Some(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+                (a, b) => a.is_none().cmp(&b.is_none()),
+            });
+        }
+        rows
+    }
+
+    /// Where the item at `index` is shown in the current section's list.
+    /// Only a sorted permission rules table differs from file order.
+    fn display_row(&self, index: usize) -> usize {
+        if self.rule_sort.is_none() || !self.current_section().is_single_key() {
+            return index;
+        }
+        self.rule_rows()
+            .iter()
+            .position(|&i| i == index)
+            .unwrap_or(index)
+    }
+
+    /// The index of the item shown at `row` of the current section's list.
+    fn row_index(&self, row: usize) -> usize {
+        if self.rule_sort.is_none() || !self.current_section().is_single_key() {
+            return row;
+        }
+        self.rule_rows().get(row).copied().unwrap_or(row)
+    }
+
+    /// The line of the current section's list the item at `index` is on,
+    /// counting the group headers above it.
+    fn screen_row(&self, index: usize) -> usize {
+        let headers = self.group_headers(self.current_section());
+        self.display_row(index) + expanded_headers_through(&headers, index)
+    }
+
+    /// The index of the item on `line` of the current section's list; a
+    /// group header's line gives its first setting.
+    fn screen_index(&self, line: usize) -> usize {
+        let headers = self.group_headers(self.current_section());
+        if headers.is_empty() {
+            return self.row_index(line);
+        }
+        (0..self.current_item_count())
+            .find(|&i| i + expanded_headers_through(&headers, i) >= line)
+            .unwrap_or(line)
+    }
+
+    /// How many rows the current section's main list takes, headers
+    /// included.
+    fn list_row_count(&self) -> usize {
+        let section = self.current_section();
+        if section.is_split_panel() {
+            self.mcp_config_count()
+        } else if section.is_single_key() {
+            self.single_key_item_count()
+        } else {
+            let (entries, headers) = self.grouped_settings(section);
+            entries.len() + expanded_headers_through(&headers, usize::MAX)
+        }
+    }
+
+    /// Rewrites `amp.permissions` in the order the table is sorted by, then
+    /// shows file order again. Amp applies the first rule that matches, so
+    /// this can change which rule wins.
+    pub fn apply_rule_sort(&mut self) {
+        let Some(column) = self
+            .rule_sort
+            .filter(|_| self.current_section().is_single_key())
+        else {
+            self.status_message = Some(format!(
+                "Sort the permission rules with {} first.",
+                self.keymap.label(Action::SortRows)
+            ));
+            return;
+        };
+        let rows = self.rule_rows();
+        let rules = self.config.get("amp.permissions");
+        let Some(rules) = rules.as_array() else {
+            return;
+        };
+        let sorted: Vec<Value> = rows.iter().map(|&i| rules[i].clone()).collect();
+        self.rule_sort = None;
+        if sorted == *rules {
+            self.status_message = Some(format!("Rules were already in {column} order."));
+            return;
+        }
+        self.selected_setting = rows
+            .iter()
+            .position(|&i| i == self.selected_setting)
+            .unwrap_or(0);
+        self.config.set("amp.permissions", Value::Array(sorted));
+        self.status_message = Some(format!(
+            "Reordered the rules by {column}. The first matching rule wins, so check the result."
+        ));
+    }
+
+    /// Moves the selected permission rule one place up or down. Amp applies
+    /// the first rule that matches, so order matters; the selection follows
+    /// the moved rule.
+    pub fn move_array_item(&mut self, up: bool) {
+        let section = self.current_section();
+        let (key, idx) = if section.is_split_panel() && self.mcp_focus == McpFocus::Permissions {
+            (
+                "amp.mcpPermissions".to_string(),
+                self.selected_mcp_permission,
+            )
+        } else if section.is_single_key() {
+            if let Some(column) = self.rule_sort {
+                self.status_message = Some(format!(
+                    "Rules are shown sorted by {column}; press {} until they're unsorted to move them.",
+                    self.keymap.label(Action::SortRows)
+                ));
+                return;
+            }
+            match self.selected_array_def() {
+                Some(def) => (def.key.to_string(), self.selected_setting),
+                None => return,
+            }
+        } else {
+            self.status_message = Some("Only permission rules can be reordered.".into());
+            return;
+        };
+
+        let mut arr = self
+            .config
+            .get(&key)
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let target = if up {
+            idx.checked_sub(1)
+        } else {
+            Some(idx + 1).filter(|&i| i < arr.len())
+        };
+        let Some(target) = target.filter(|_| idx < arr.len()) else {
+            return;
+        };
+        arr.swap(idx, target);
+        self.config.set(&key, Value::Array(arr));
+        if section.is_split_panel() {
+            self.selected_mcp_permission = target;
+        } else {
+            self.selected_setting = target;
+        }
+    }
+
     /// Returns the SettingDef for the currently selected array setting.
     /// In single-key sections, returns the section's only setting.
     /// In multi-key sections, returns the selected setting if it's an array type.
@@ -719,7 +3206,7 @@ impl App {
                     SettingType::ArrayString | SettingType::ArrayObject
                 ) =>
             {
-                Some(def.clone())
+                Some((**def).clone())
             }
             _ => None,
         }
@@ -742,25 +3229,80 @@ impl App {
         self.edit_buffer.clear();
     }
 
-    /// Cycles through enum options for a StringEnum setting.
-    fn cycle_enum(&mut self, def: &settings::SettingDef) {
+    /// Returns the selectable options for a StringEnum setting. For
+    /// `amp.terminal.theme`, user-installed themes are listed before "Custom".
+    pub fn enum_options(&self, def: &settings::SettingDef) -> Vec<String> {
         let Some(options) = def.enum_options else {
-            return;
-        };
-        let current = self.config.get(def.key);
-        let current_str = current.as_str().unwrap_or("");
-        let current_idx = options.iter().position(|o| *o == current_str);
-        let next_idx = match current_idx {
-            Some(i) => (i + 1) % options.len(),
-            None => 0,
+            return Vec::new();
         };
-        let next_value = options[next_idx];
-        if next_value == "Custom" && def.allows_custom {
-            self.input_mode = InputMode::EditingValue;
-            self.edit_buffer.clear();
-        } else {
-            self.config
-                .set(def.key, Value::String(next_value.to_string()));
+        let mut result: Vec<String> = options
+            .iter()
+            .filter(|o| **o != "Custom")
+            .map(|o| o.to_string())
+            .collect();
+        if def.key == "amp.terminal.theme" {
+            result.extend(self.custom_themes.iter().cloned());
+        }
+        if options.contains(&"Custom") {
+            result.push("Custom".to_string());
+        }
+        result
+    }
+
+    /// Opens the picker of a StringEnum setting's options, starting on the
+    /// current value (or `Custom` when the value is one of the user's own).
+    fn start_enum_select(&mut self, def: &settings::SettingDef) {
+        let options = self.enum_options(def);
+        if options.is_empty() {
+            return;
+        }
+        let current = self.effective_value(def.key);
+        let current = current.as_str().unwrap_or("");
+        self.selected_option = options
+            .iter()
+            .position(|o| o == current)
+            .or_else(|| {
+                let custom = def.allows_custom && !current.is_empty();
+                custom.then(|| options.iter().position(|o| o == "Custom"))?
+            })
+            .unwrap_or(0);
+        self.pending_enum = Some((def.key.to_string(), options));
+        self.input_mode = InputMode::SelectingEnum;
+    }
+
+    /// Moves the enum picker's selection up.
+    pub fn enum_select_up(&mut self) {
+        self.selected_option = self.selected_option.saturating_sub(1);
+    }
+
+    /// Moves the enum picker's selection down.
+    pub fn enum_select_down(&mut self) {
+        let count = self.pending_enum.as_ref().map_or(0, |(_, o)| o.len());
+        if self.selected_option + 1 < count {
+            self.selected_option += 1;
+        }
+    }
+
+    /// Sets the picked option, or asks for a value when it's `Custom`.
+    pub fn commit_enum_selection(&mut self) {
+        self.input_mode = InputMode::Normal;
+        let Some((key, options)) = self.pending_enum.take() else {
+            return;
+        };
+        let Some(choice) = options.get(self.selected_option).cloned() else {
+            return;
+        };
+        let allows_custom = settings::get_setting_def(&key).is_some_and(|d| d.allows_custom);
+        if choice == "Custom" && allows_custom {
+            // Start from the custom value already set, if there is one.
+            let current = self.effective_value(&key);
+            let current = current.as_str().unwrap_or("");
+            let custom = !current.is_empty() && !options.iter().any(|o| o == current);
+            self.edit_buffer
+                .set(if custom { current } else { "" }.to_string());
+            self.input_mode = InputMode::EditingValue;
+        } else {
+            self.config.set(&key, Value::String(choice));
         }
     }
 
@@ -792,7 +3334,7 @@ impl App {
                                 .as_array()
                                 .cloned()
                                 .unwrap_or_default();
-                            arr.push(Value::String(self.edit_buffer.clone()));
+                            arr.push(Value::String(self.edit_buffer.to_string()));
                             self.config.set(def.key, Value::Array(arr));
                             self.status_message = Some(format!("Added item to {}", def.key));
                         }
@@ -830,22 +3372,14 @@ impl App {
                 }
 
                 let value = match def.setting_type {
-                    SettingType::Number => {
-                        if let Ok(n) = self.edit_buffer.parse::<i64>() {
-                            Value::Number(n.into())
-                        } else if let Ok(n) = self.edit_buffer.parse::<f64>() {
-                            if let Some(n) = serde_json::Number::from_f64(n) {
-                                Value::Number(n)
-                            } else {
-                                self.status_message = Some("Invalid number".to_string());
-                                return;
-                            }
-                        } else {
+                    SettingType::Number => match settings::parse_number(&self.edit_buffer) {
+                        Some(n) => Value::Number(n),
+                        None => {
                             self.status_message = Some("Invalid number".to_string());
                             return;
                         }
-                    }
-                    _ => Value::String(self.edit_buffer.clone()),
+                    },
+                    _ => Value::String(self.edit_buffer.to_string()),
                 };
 
                 if let Err(e) = Config::validate_value(def.key, &value) {
@@ -861,7 +3395,7 @@ impl App {
                     Value::Array(_) => {
                         if !self.edit_buffer.is_empty() {
                             let mut arr = current.as_array().cloned().unwrap_or_default();
-                            arr.push(Value::String(self.edit_buffer.clone()));
+                            arr.push(Value::String(self.edit_buffer.to_string()));
                             self.config.set(key, Value::Array(arr));
                             self.status_message = Some(format!("Added item to {key}"));
                         }
@@ -885,7 +3419,7 @@ impl App {
                                     return;
                                 }
                             }
-                            _ => Value::String(self.edit_buffer.clone()),
+                            _ => Value::String(self.edit_buffer.to_string()),
                         };
                         self.config.set(key, value);
                     }
@@ -904,6 +3438,49 @@ impl App {
         self.edit_buffer.clear();
     }
 
+    /// Where a new custom key would be stored: the setting holding it and
+    /// the path inside, when nested keys are on and a prefix names an
+    /// object. `None` stores it as written.
+    pub fn nested_key_target(&self, key: &str) -> Option<(String, Vec<String>)> {
+        if !self.nested_keys {
+            return None;
+        }
+        settings::split_nested_key(key, |k| {
+            self.config.get_raw(k).is_some_and(Value::is_object)
+        })
+    }
+
+    /// Stores a new custom key's value, inside its object setting for a
+    /// nested key, creating the objects on the way. Returns how to refer
+    /// to the key in messages.
+    fn store_custom_key(&mut self, key: &str, value: Value) -> Result<String, String> {
+        let Some((top, path)) = self.nested_key_target(key) else {
+            self.config.set(key, value);
+            return Ok(format!("'{key}'"));
+        };
+        let mut root = self.config.get(&top);
+        let mut node = &mut root;
+        for (depth, segment) in path.iter().enumerate() {
+            if node.is_null() {
+                *node = Value::Object(serde_json::Map::new());
+            }
+            let Value::Object(map) = node else {
+                let at = std::iter::once(top.as_str())
+                    .chain(path[..depth].iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(".");
+                return Err(format!(
+                    "{at} isn't an object, so '{key}' can't go inside it."
+                ));
+            };
+            node = map.entry(segment.clone()).or_insert(Value::Null);
+        }
+        *node = value;
+        Config::validate_value(&top, &root).map_err(|e| e.to_string())?;
+        self.config.set(&top, root);
+        Ok(format!("'{}' in {top}", path.join(".")))
+    }
+
     /// Commits the key name entry and moves to type selection.
     pub fn commit_key_name(&mut self) {
         if self.edit_buffer.trim().is_empty() {
@@ -911,10 +3488,18 @@ impl App {
             return;
         }
         let key = self.edit_buffer.trim().to_string();
-        if self.config.get_raw(&key).is_some() {
+        let exists = match self.nested_key_target(&key) {
+            Some((top, path)) => path
+                .iter()
+                .try_fold(&self.config.get(&top), |node, segment| node.get(segment))
+                .is_some_and(|node| !node.is_null()),
+            None => self.config.get_raw(&key).is_some(),
+        };
+        if exists {
             self.status_message = Some(format!("Key '{}' already exists.", key));
             return;
         }
+        self.record_prompt();
         self.pending_custom_key = Some(key);
         self.edit_buffer.clear();
         self.selected_type = 0;
@@ -929,8 +3514,10 @@ impl App {
 
         match chosen {
             CustomKeyType::Boolean => {
-                self.config.set(&key, Value::Bool(false));
-                self.status_message = Some(format!("Added '{}' = false", key));
+                self.status_message = Some(match self.store_custom_key(&key, Value::Bool(false)) {
+                    Ok(name) => format!("Added {name} = false"),
+                    Err(e) => e,
+                });
                 self.pending_custom_key = None;
                 self.input_mode = InputMode::Normal;
                 None
@@ -946,8 +3533,23 @@ impl App {
                 None
             }
             CustomKeyType::Array => {
-                self.config.set(&key, Value::Array(vec![]));
-                self.status_message = Some(format!("Added '{}' = []", key));
+                self.status_message =
+                    Some(match self.store_custom_key(&key, Value::Array(vec![])) {
+                        Ok(name) => format!("Added {name} = []"),
+                        Err(e) => e,
+                    });
+                self.pending_custom_key = None;
+                self.input_mode = InputMode::Normal;
+                None
+            }
+            CustomKeyType::Object if self.nested_key_target(&key).is_some() => {
+                // Nested objects start empty; there's no one setting to open.
+                self.status_message = Some(
+                    match self.store_custom_key(&key, Value::Object(serde_json::Map::new())) {
+                        Ok(name) => format!("Added {name} = {{}}"),
+                        Err(e) => e,
+                    },
+                );
                 self.pending_custom_key = None;
                 self.input_mode = InputMode::Normal;
                 None
@@ -973,20 +3575,22 @@ impl App {
             return;
         };
         let chosen = CustomKeyType::ALL[self.selected_type];
+        let store = |app: &mut Self, value: Value| {
+            app.status_message = Some(match app.store_custom_key(&key, value) {
+                Ok(name) => format!("Added {name}"),
+                Err(e) => e,
+            });
+        };
         match chosen {
             CustomKeyType::String => {
-                self.config
-                    .set(&key, Value::String(self.edit_buffer.clone()));
-                self.status_message = Some(format!("Added '{}'", key));
+                store(self, Value::String(self.edit_buffer.to_string()));
             }
             CustomKeyType::Number => {
                 if let Ok(n) = self.edit_buffer.parse::<i64>() {
-                    self.config.set(&key, Value::Number(n.into()));
-                    self.status_message = Some(format!("Added '{}'", key));
+                    store(self, Value::Number(n.into()));
                 } else if let Ok(n) = self.edit_buffer.parse::<f64>() {
                     if let Some(n) = serde_json::Number::from_f64(n) {
-                        self.config.set(&key, Value::Number(n));
-                        self.status_message = Some(format!("Added '{}'", key));
+                        store(self, Value::Number(n));
                     } else {
                         self.status_message = Some("Invalid number.".to_string());
                         self.pending_custom_key = Some(key);
@@ -1010,6 +3614,11 @@ impl App {
             self.status_message = Some("Tool name cannot be empty.".to_string());
             return;
         }
+        if let Some(e) = self.pattern_input_error() {
+            self.status_message = Some(format!("Invalid tool pattern: {e}"));
+            return;
+        }
+        self.record_prompt();
         self.pending_permission_tool = Some(self.edit_buffer.trim().to_string());
         self.edit_buffer.clear();
         self.selected_permission_level = 0;
@@ -1139,11 +3748,48 @@ impl App {
         self.edit_buffer.clear();
         self.pending_custom_key = None;
         self.selected_type = 0;
+        self.pending_enum = None;
         self.pending_permission_tool = None;
         self.selected_permission_level = 0;
         self.pending_mcp_match_field = None;
         self.pending_mcp_match_value = None;
+        self.pending_mcp_server_copy = None;
         self.selected_mcp_permission_level = 0;
+        self.file_candidates.clear();
+        self.report = None;
+        self.backup_candidates.clear();
+        self.profile_candidates.clear();
+        self.selected_profile = 0;
+        self.preset_candidates.clear();
+        self.selected_preset = 0;
+        self.registry.clear();
+        self.selected_registry = 0;
+        self.merge_conflicts.clear();
+        self.selected_conflict = 0;
+        self.selected_trash = 0;
+        self.selected_recent = 0;
+        self.pending_reset = None;
+        self.env_server = None;
+        self.selected_env = 0;
+        self.pending_env_key = None;
+        self.json_edit = None;
+        self.server_form = None;
+        self.rule_form = None;
+        self.permission_test = None;
+        self.object_form = None;
+        self.tree = None;
+        self.editor_draft = None;
+        self.selected_copy_format = 0;
+        self.selected_import_source = 0;
+        self.import_candidates.clear();
+        self.selected_import = 0;
+        self.tool_choices.clear();
+        self.selected_tool_choice = 0;
+        self.diff.clear();
+        self.diff_scroll = 0;
+        self.search_results.clear();
+        self.selected_search_result = 0;
+        self.messages_scroll = 0;
     }
 
     /// Resets the currently selected setting to its default.
@@ -1159,7 +3805,12 @@ impl App {
                             .as_object()
                             .cloned()
                             .unwrap_or_default();
-                        obj.remove(name);
+                        if let Some(config) = obj.shift_remove(name) {
+                            self.trash_removed(Removed::Server {
+                                name: name.clone(),
+                                config,
+                            });
+                        }
                         self.config
                             .set("amp.mcpServers", Value::Object(obj.clone()));
                         self.status_message = Some(format!("Removed server '{}'", name));
@@ -1170,6 +3821,7 @@ impl App {
                     }
                 }
                 McpFocus::Permissions => {
+                    self.trash_key("amp.mcpPermissions");
                     self.config.remove("amp.mcpPermissions");
                     self.status_message = Some("Reset amp.mcpPermissions to default".to_string());
                     self.selected_mcp_permission = 0;
@@ -1190,6 +3842,7 @@ impl App {
 
         match entry {
             SettingEntry::Known(def) => {
+                self.trash_key(def.key);
                 self.config.remove(def.key);
                 self.status_message = Some(format!("Reset {} to default", def.key));
                 if self.current_section().is_single_key() {
@@ -1197,6 +3850,7 @@ impl App {
                 }
             }
             SettingEntry::Unknown(key) => {
+                self.trash_key(key);
                 self.config.remove(key);
                 self.status_message = Some(format!("Removed {}", key));
                 // Adjust selection if needed
@@ -1208,13 +3862,210 @@ impl App {
         }
     }
 
+    /// The keys set in the file that a reset covers: the known ones, and
+    /// the unknown ones (which Advanced lists) separately.
+    pub fn reset_candidates(&self, scope: ResetScope) -> (Vec<String>, Vec<String>) {
+        let (mut known, mut unknown) = (Vec::new(), Vec::new());
+        for key in self.config.entries().map(|(key, _)| key.clone()) {
+            match settings::section_for_key(&key) {
+                Some(section)
+                    if scope == ResetScope::All || scope == ResetScope::Section(section) =>
+                {
+                    known.push(key)
+                }
+                None if matches!(
+                    scope,
+                    ResetScope::All | ResetScope::Section(Section::Advanced)
+                ) =>
+                {
+                    unknown.push(key)
+                }
+                _ => {}
+            }
+        }
+        (known, unknown)
+    }
+
+    /// Asks to reset a section, or everything, to defaults.
+    pub fn start_reset(&mut self, scope: ResetScope) {
+        let (known, unknown) = self.reset_candidates(scope);
+        if known.is_empty() && unknown.is_empty() {
+            self.status_message = Some(match scope {
+                ResetScope::Section(section) => format!("Nothing in {} is set.", section.label()),
+                ResetScope::All => "Nothing is set.".to_string(),
+            });
+            return;
+        }
+        self.pending_reset = Some(scope);
+        self.input_mode = InputMode::ConfirmReset;
+    }
+
+    /// Removes the keys the pending reset covers, and the unknown ones too
+    /// when `include_unknown`. They go to the trash, so `u` brings them back.
+    pub fn confirm_reset(&mut self, include_unknown: bool) {
+        let Some(scope) = self.pending_reset else {
+            return;
+        };
+        self.cancel_edit();
+        let (mut keys, unknown) = self.reset_candidates(scope);
+        if include_unknown {
+            keys.extend(unknown);
+        }
+        for key in &keys {
+            self.trash_key(key);
+            self.config.remove(key);
+        }
+        self.selected_setting = 0;
+        self.selected_mcp_permission = 0;
+        let what = match scope {
+            ResetScope::Section(section) => format!(" in {}", section.label()),
+            ResetScope::All => String::new(),
+        };
+        self.status_message = Some(format!(
+            "Reset {} setting(s){what} to defaults (u restores them one at a time)",
+            keys.len()
+        ));
+    }
+
+    /// Keeps something just removed so it can be restored.
+    fn trash_removed(&mut self, removed: Removed) {
+        self.trash.push(Trashed {
+            path: self.config.path().to_path_buf(),
+            removed,
+        });
+    }
+
+    /// Keeps a key that's about to be removed, if it's set.
+    fn trash_key(&mut self, key: &str) {
+        if let Some(value) = self.config.get_raw(key).cloned() {
+            self.trash_removed(Removed::Key {
+                key: key.to_string(),
+                value,
+            });
+        }
+    }
+
+    /// Opens the trash overlay, newest removal first.
+    pub fn start_trash(&mut self) {
+        if self.trash.is_empty() {
+            self.status_message = Some("Nothing was deleted this session.".into());
+            return;
+        }
+        self.selected_trash = 0;
+        self.input_mode = InputMode::ViewingTrash;
+    }
+
+    pub fn trash_select_up(&mut self) {
+        self.selected_trash = self.selected_trash.saturating_sub(1);
+    }
+
+    pub fn trash_select_down(&mut self) {
+        if self.selected_trash + 1 < self.trash.len() {
+            self.selected_trash += 1;
+        }
+    }
+
+    /// Restores the removal selected in the trash overlay.
+    pub fn commit_trash_restore(&mut self) {
+        let index = self.trash.len().saturating_sub(self.selected_trash + 1);
+        self.cancel_edit();
+        self.restore_trashed(index);
+    }
+
+    /// Restores the most recent removal.
+    pub fn restore_last(&mut self) {
+        if self.trash.is_empty() {
+            self.status_message = Some("Nothing was deleted this session.".into());
+            return;
+        }
+        self.restore_trashed(self.trash.len() - 1);
+    }
+
+    /// Puts back the removal at `index` in the trash, which keeps it if
+    /// that fails.
+    fn restore_trashed(&mut self, index: usize) {
+        if index >= self.trash.len() {
+            return;
+        }
+        let trashed = self.trash.remove(index);
+        match trashed.restore(&mut self.config) {
+            Ok(message) => {
+                self.clamp_selection();
+                self.status_message = Some(message);
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Can't restore {}: {e:#}", trashed.describe()));
+                self.trash.insert(index, trashed);
+            }
+        }
+    }
+
+    /// The settings changed most recently, newest first: unsaved changes
+    /// (with no time), then the keys saved with a change.
+    pub fn recent_changes(&self) -> Vec<(String, Option<SystemTime>)> {
+        let unsaved = self.config.unsaved_keys();
+        let mut saved: Vec<_> = self
+            .modified
+            .iter()
+            .filter(|(key, _)| !unsaved.contains(key))
+            .map(|(key, at)| (key.clone(), Some(*at)))
+            .collect();
+        saved.sort_by_key(|(_, at)| std::cmp::Reverse(*at));
+        unsaved
+            .into_iter()
+            .map(|key| (key, None))
+            .chain(saved)
+            .take(RECENT_LIMIT)
+            .collect()
+    }
+
+    /// Records that `keys` were saved with a change at `at`.
+    fn note_saved(&mut self, keys: Vec<String>, at: SystemTime) {
+        for key in keys {
+            self.modified.insert(key, at);
+        }
+    }
+
+    /// Opens the recent changes overlay.
+    pub fn start_recent(&mut self) {
+        if self.recent_changes().is_empty() {
+            self.status_message =
+                Some("Nothing changed yet; settings you change show up here.".into());
+            return;
+        }
+        self.selected_recent = 0;
+        self.input_mode = InputMode::ViewingRecent;
+    }
+
+    pub fn recent_select_up(&mut self) {
+        self.selected_recent = self.selected_recent.saturating_sub(1);
+    }
+
+    pub fn recent_select_down(&mut self) {
+        if self.selected_recent + 1 < self.recent_changes().len() {
+            self.selected_recent += 1;
+        }
+    }
+
+    /// Goes to the setting selected in the recent changes overlay.
+    pub fn commit_recent(&mut self) {
+        let Some((key, _)) = self.recent_changes().into_iter().nth(self.selected_recent) else {
+            return;
+        };
+        self.cancel_edit();
+        if !self.go_to_key(&key) {
+            self.status_message = Some(format!("{key} is no longer in the file."));
+        }
+    }
+
     /// Starts the "add MCP server" flow.
     fn start_add_mcp_server(&mut self) {
         self.input_mode = InputMode::EnteringMcpServerName;
         self.edit_buffer.clear();
     }
 
-    /// Commits the server name and opens `$EDITOR` for the new server config.
+    /// Commits the server name and opens the form for the new server, or
+    /// `$EDITOR` on a copy when duplicating.
     pub fn commit_mcp_server_name(&mut self) -> Option<EditorRequest> {
         let name = self.edit_buffer.trim().to_string();
         if name.is_empty() {
@@ -1226,16 +4077,142 @@ impl App {
             self.status_message = Some(format!("Server '{}' already exists.", name));
             return None;
         }
+        self.record_prompt();
         self.edit_buffer.clear();
+        let Some(value) = self.pending_mcp_server_copy.take() else {
+            self.server_form = Some(ServerForm::new(name));
+            self.input_mode = InputMode::EditingServer;
+            return None;
+        };
         self.input_mode = InputMode::Normal;
         Some(EditorRequest {
             key: "amp.mcpServers".to_string(),
-            value: Value::Object(serde_json::Map::new()),
+            value,
             array_index: None,
             object_key: Some(name),
         })
     }
 
+    /// Applies a change to the new server form.
+    pub fn edit_server_form(&mut self, f: impl FnOnce(&mut ServerForm)) {
+        if let Some(form) = &mut self.server_form {
+            f(form);
+        }
+    }
+
+    /// Opens the MCP server catalog: the cached download if there is one,
+    /// or else the catalog volt ships with.
+    pub fn start_registry(&mut self) {
+        let (entries, error) = registry::load(self.registry_cache.as_deref());
+        if let Some(error) = error {
+            self.status_message = Some(format!("Using the bundled catalog: {error}"));
+        }
+        self.registry = entries;
+        self.selected_registry = 0;
+        self.input_mode = InputMode::BrowsingRegistry;
+    }
+
+    /// Moves catalog selection up.
+    pub fn registry_select_up(&mut self) {
+        self.selected_registry = self.selected_registry.saturating_sub(1);
+    }
+
+    /// Moves catalog selection down.
+    pub fn registry_select_down(&mut self) {
+        if self.selected_registry + 1 < self.registry.len() {
+            self.selected_registry += 1;
+        }
+    }
+
+    /// Opens the new-server form filled in from the selected catalog entry,
+    /// in the MCPs section, with the first env value to fill in focused.
+    /// A server already configured under the entry's name gets a numbered
+    /// name instead.
+    pub fn commit_registry_selection(&mut self) {
+        let Some(entry) = self.registry.get(self.selected_registry).cloned() else {
+            return;
+        };
+        let servers = self.config.get("amp.mcpServers");
+        let name = std::iter::once(entry.name.clone())
+            .chain((2..).map(|n| format!("{}-{n}", entry.name)))
+            .find(|name| servers.get(name).is_none())
+            .unwrap_or_default();
+        let mut form = entry.to_form(name);
+        form.field = if !form.env.is_empty() {
+            server_form::Field::EnvValue(0)
+        } else if entry.command.is_some() {
+            server_form::Field::Command
+        } else {
+            server_form::Field::Url
+        };
+        self.cancel_edit();
+        self.go_to_section(Section::Mcps);
+        self.status_message = Some(match form.env.len() {
+            0 => format!("Review {}, then Enter to add it", form.name),
+            n => format!(
+                "Fill in {}'s {n} env values, then Enter to add it",
+                form.name
+            ),
+        });
+        self.server_form = Some(form);
+        self.input_mode = InputMode::EditingServer;
+    }
+
+    /// Downloads the latest catalog in the background and caches it.
+    pub fn refresh_registry(&mut self) {
+        if self.registry_refreshing {
+            self.status_message = Some("Still refreshing the catalog…".into());
+            return;
+        }
+        let Some(cache) = self.registry_cache.clone() else {
+            self.status_message = Some("Refreshing the catalog needs a home directory.".into());
+            return;
+        };
+        let url = self.registry_url.clone();
+        self.tasks
+            .spawn(move || TaskResult::RegistryRefresh(registry::refresh(&url, &cache)));
+        self.registry_refreshing = true;
+        self.status_message = Some("Refreshing the MCP catalog…".into());
+    }
+
+    /// Adds the server from the form, or focuses the first invalid field.
+    pub fn commit_server_form(&mut self) {
+        let Some(form) = &mut self.server_form else {
+            return;
+        };
+        if let Some((field, error)) = form.first_error() {
+            form.field = field;
+            self.status_message = Some(format!("Can't add the server: {error}"));
+            return;
+        }
+        let (name, config) = (form.name.clone(), form.to_config());
+        self.cancel_edit();
+        let mut servers = self
+            .config
+            .get("amp.mcpServers")
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        servers.insert(name.clone(), config);
+        self.config.set("amp.mcpServers", Value::Object(servers));
+        if let Some(i) = self.mcp_server_names().iter().position(|n| *n == name) {
+            self.selected_setting = i;
+        }
+        self.status_message = Some(format!("Added server '{name}'"));
+    }
+
+    /// Leaves the form for `$EDITOR`, carrying over what's been typed.
+    pub fn server_form_to_editor(&mut self) -> Option<EditorRequest> {
+        let form = self.server_form.take()?;
+        self.cancel_edit();
+        Some(EditorRequest {
+            key: "amp.mcpServers".to_string(),
+            value: form.to_config(),
+            array_index: None,
+            object_key: Some(form.name),
+        })
+    }
+
     /// Deletes the selected MCP server config.
     fn delete_mcp_config_item(&mut self) {
         let server_names = self.mcp_server_names();
@@ -1251,7 +4228,12 @@ impl App {
             .as_object()
             .cloned()
             .unwrap_or_default();
-        obj.remove(name);
+        if let Some(config) = obj.shift_remove(name) {
+            self.trash_removed(Removed::Server {
+                name: name.clone(),
+                config,
+            });
+        }
         self.status_message = Some(format!("Removed server '{}'", name));
         self.config
             .set("amp.mcpServers", Value::Object(obj.clone()));
@@ -1266,6 +4248,43 @@ impl App {
         self.edit_buffer.clear();
     }
 
+    /// The server selected in MCP Configs and the `amp.mcpPermissions`
+    /// rules that apply to it, the deciding one first.
+    pub fn selected_server_rules(&self) -> Option<(String, Vec<usize>)> {
+        if !self.current_section().is_split_panel() || self.mcp_focus != McpFocus::Configs {
+            return None;
+        }
+        let name = self
+            .mcp_server_names()
+            .into_iter()
+            .nth(self.selected_setting)?;
+        let server = self.config.get("amp.mcpServers")[&name].clone();
+        let rules = self.config.get("amp.mcpPermissions");
+        let rules = rules.as_array().map(Vec::as_slice).unwrap_or_default();
+        Some((name, permissions::mcp_rules_for(rules, &server)))
+    }
+
+    /// Starts adding an MCP permission rule for the server selected in MCP
+    /// Configs, with its command (or URL) filled in as the match value.
+    pub fn start_server_rule(&mut self) {
+        let Some((name, _)) = self.selected_server_rules() else {
+            self.status_message = Some("Select an MCP server config to add a rule for.".into());
+            return;
+        };
+        let server = self.config.get("amp.mcpServers")[&name].clone();
+        let (field, value) = match (server.get("command"), server.get("url")) {
+            (Some(Value::String(command)), _) => ("command", command),
+            (_, Some(Value::String(url))) => ("url", url),
+            _ => {
+                self.status_message = Some(format!("{name} has neither a command nor a url."));
+                return;
+            }
+        };
+        self.pending_mcp_match_field = Some(field.to_string());
+        self.edit_buffer.set(value);
+        self.input_mode = InputMode::EnteringMcpMatchValue;
+    }
+
     /// Commits the match field name (e.g. "command", "url") for an MCP permission rule.
     pub fn commit_mcp_match_field(&mut self) {
         let field = self.edit_buffer.trim().to_string();
@@ -1273,6 +4292,7 @@ impl App {
             self.status_message = Some("Match field cannot be empty.".to_string());
             return;
         }
+        self.record_prompt();
         self.pending_mcp_match_field = Some(field);
         self.edit_buffer.clear();
         self.input_mode = InputMode::EnteringMcpMatchValue;
@@ -1284,6 +4304,10 @@ impl App {
             self.status_message = Some("Match value cannot be empty.".to_string());
             return;
         }
+        if let Some(e) = self.pattern_input_error() {
+            self.status_message = Some(format!("Invalid match pattern: {e}"));
+            return;
+        }
         self.pending_mcp_match_value = Some(self.edit_buffer.trim().to_string());
         self.edit_buffer.clear();
         self.selected_mcp_permission_level = 0;
@@ -1379,7 +4403,12 @@ impl App {
             return;
         }
         let idx = self.selected_mcp_permission.min(arr.len() - 1);
-        arr.remove(idx);
+        let value = arr.remove(idx);
+        self.trash_removed(Removed::Item {
+            key: "amp.mcpPermissions".to_string(),
+            index: idx,
+            value,
+        });
         self.config
             .set("amp.mcpPermissions", Value::Array(arr.clone()));
         self.status_message = Some(format!("Removed MCP permission item {}", idx));
@@ -1388,1313 +4417,6459 @@ impl App {
         }
     }
 
-    /// Saves the configuration to disk.
-    pub fn save(&mut self) {
-        match self.config.save() {
-            Ok(()) => self.status_message = Some("Saved!".to_string()),
-            Err(e) => self.status_message = Some(format!("Save failed: {e}")),
+    /// Offers to scaffold a starter settings file if none exists yet.
+    pub fn offer_scaffold(&mut self) {
+        if !self.config.exists() && !self.config.is_read_only() {
+            self.input_mode = InputMode::ConfirmScaffold;
         }
     }
-}
-
-/// An entry in the settings list — either a known setting or an unknown key.
-#[derive(Debug, Clone)]
-pub enum SettingEntry {
-    Known(settings::SettingDef),
-    Unknown(String),
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
 
-    fn test_app() -> App {
-        let mut f = NamedTempFile::new().unwrap();
-        write!(
-            f,
-            r#"{{
-    "amp.showCosts": true,
-    "amp.notifications.enabled": false,
-    "amp.experimental.modes": ["bombadil"]
-}}"#
-        )
-        .unwrap();
-        let config = Config::load(f.path()).unwrap();
-        App::new(config)
+    /// Writes the starter settings file.
+    pub fn confirm_scaffold(&mut self) {
+        self.input_mode = InputMode::Normal;
+        match self.config.write_scaffold() {
+            Ok(()) => {
+                self.status_message = Some("Created settings file with commented defaults".into())
+            }
+            Err(e) => self.status_message = Some(format!("Scaffold failed: {e}")),
+        }
     }
 
-    #[test]
-    fn test_initial_state() {
-        let app = test_app();
-        assert_eq!(app.current_section(), Section::General);
-        assert_eq!(app.selected_setting, 0);
-        assert_eq!(app.focus, Focus::Sidebar);
-        assert!(!app.should_quit);
-        assert_eq!(app.input_mode, InputMode::Normal);
+    /// Declines scaffolding and starts from an empty config.
+    pub fn decline_scaffold(&mut self) {
+        self.input_mode = InputMode::Normal;
     }
 
-    #[test]
-    fn test_navigate_sections() {
-        let mut app = test_app();
-        assert_eq!(app.current_section(), Section::General);
-
-        app.move_down();
-        assert_eq!(app.current_section(), Section::Permissions);
+    /// Returns whether the open file is the global or project settings file.
+    pub fn is_settings_file(&self) -> bool {
+        self.scope == Scope::Project || self.config.path() == self.settings_path
+    }
 
-        app.move_down();
-        assert_eq!(app.current_section(), Section::Tools);
+    /// Adds a project settings layer alongside the global one.
+    pub fn attach_project(&mut self, project: Config) {
+        self.other_scope_config = Some(project);
+    }
 
-        app.move_up();
-        assert_eq!(app.current_section(), Section::Permissions);
+    /// Returns whether a project settings layer is loaded.
+    pub fn has_project(&self) -> bool {
+        self.other_scope_config.is_some()
     }
 
-    #[test]
-    fn test_toggle_focus() {
-        let mut app = test_app();
-        assert_eq!(app.focus, Focus::Sidebar);
-        app.toggle_focus();
-        assert_eq!(app.focus, Focus::Settings);
-        app.toggle_focus();
-        assert_eq!(app.focus, Focus::Sidebar);
+    /// Returns the (global, project) layers, if a project layer is loaded.
+    fn layers(&self) -> Option<(&Config, &Config)> {
+        let other = self.other_scope_config.as_ref()?;
+        Some(match self.scope {
+            Scope::Global => (&self.config, other),
+            Scope::Project => (other, &self.config),
+        })
     }
 
-    #[test]
-    fn test_toggle_boolean() {
-        let mut app = test_app();
-        app.focus = Focus::Settings;
-        // First setting in General is amp.anthropic.thinking.enabled (default true)
-        app.activate_setting();
-        assert_eq!(
-            app.config.get("amp.anthropic.thinking.enabled"),
-            Value::Bool(false)
-        );
-        app.activate_setting();
-        assert_eq!(
-            app.config.get("amp.anthropic.thinking.enabled"),
-            Value::Bool(true)
-        );
+    /// Returns which layer supplies the effective value of a key, or `None`
+    /// when no project layer is loaded or neither layer sets it.
+    pub fn key_scope(&self, key: &str) -> Option<Scope> {
+        let (global, project) = self.layers()?;
+        if project.get_raw(key).is_some() {
+            Some(Scope::Project)
+        } else if global.get_raw(key).is_some() {
+            Some(Scope::Global)
+        } else {
+            None
+        }
     }
 
-    #[test]
-    fn test_cycle_enum() {
-        let mut app = test_app();
-        app.focus = Focus::Settings;
-        // Navigate to amp.terminal.theme (a StringEnum)
+    /// Describes where the effective value of a key comes from, for the help
+    /// pane, or `None` when there's only one layer.
+    pub fn provenance(&self, key: &str) -> Option<String> {
+        if let Some(env) = self.config.env_override(key) {
+            return Some(format!(
+                "the environment, {}={}; the file's value is ignored while it's set",
+                env.var, env.value
+            ));
+        }
+        let (global, project) = self.layers()?;
+        Some(match self.key_scope(key) {
+            Some(scope) => {
+                let path = match scope {
+                    Scope::Global => global.path(),
+                    Scope::Project => project.path(),
+                };
+                let there = if scope == self.scope {
+                    String::new()
+                } else {
+                    " (g l to edit it there)".to_string()
+                };
+                format!("the {} file, {}{there}", scope.label(), path.display())
+            }
+            None => "the default; neither file sets it".to_string(),
+        })
+    }
+
+    /// Switches to the layer that provides the selected key's effective
+    /// value, keeping the key selected.
+    pub fn edit_at_source(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        if let Some(env) = self.config.env_override(&key) {
+            self.status_message = Some(format!(
+                "{key} comes from ${}, which volt can't change; unset it to use the file.",
+                env.var
+            ));
+            return;
+        }
+        match self.key_scope(&key) {
+            Some(scope) if scope != self.scope => {
+                self.toggle_scope();
+                if self.scope == scope {
+                    self.go_to_key(&key);
+                    self.status_message =
+                        Some(format!("Editing {key} in the {} settings", scope.label()));
+                }
+            }
+            Some(scope) => {
+                self.status_message = Some(format!(
+                    "{key} already comes from the {} settings being edited.",
+                    scope.label()
+                ));
+            }
+            None if self.has_project() => {
+                self.status_message = Some(format!(
+                    "{key} isn't set in either file; it uses its default."
+                ));
+            }
+            None => {
+                self.status_message =
+                    Some("No project settings (.amp/settings.json) found.".into());
+            }
+        }
+    }
+
+    /// Returns the value shown for a key. In project scope, keys the project
+    /// doesn't set show the global value they inherit.
+    pub fn effective_value(&self, key: &str) -> Value {
+        if self.config.get_raw(key).is_none() && self.scope == Scope::Project {
+            if let Some(inherited) = self.layers().and_then(|(g, _)| g.get_raw(key)) {
+                return inherited.clone();
+            }
+        }
+        self.config.get(key)
+    }
+
+    /// Switches between editing the global and the project settings file.
+    pub fn toggle_scope(&mut self) {
+        if !self.is_settings_file() {
+            self.status_message =
+                Some("Return to the settings file before switching scope.".into());
+            return;
+        }
+        let Some(other) = self.other_scope_config.take() else {
+            self.status_message = Some("No project settings (.amp/settings.json) found.".into());
+            return;
+        };
+        self.other_scope_config = Some(std::mem::replace(&mut self.config, other));
+        self.scope = match self.scope {
+            Scope::Global => Scope::Project,
+            Scope::Project => Scope::Global,
+        };
+        self.notes = Notes::load(self.config.path()).unwrap_or_default();
+        self.refresh_git();
+        self.selected_setting = 0;
+        self.mcp_focus = McpFocus::Configs;
+        self.selected_mcp_permission = 0;
+        self.status_message = Some(format!("Editing {} settings", self.scope.label()));
+    }
+
+    /// Opens the file switcher listing Amp's config files.
+    pub fn start_file_switch(&mut self) {
+        if self.scope == Scope::Project {
+            self.status_message = Some("Switch to global scope before opening other files.".into());
+            return;
+        }
+        if self.config.is_dirty() {
+            self.status_message = Some("Save or reset changes before switching files.".into());
+            return;
+        }
+        let base = Config::load(&self.settings_path)
+            .map(|c| c.sibling_files())
+            .unwrap_or_else(|_| vec![self.settings_path.clone()]);
+        self.selected_file = base
+            .iter()
+            .position(|p| p == self.config.path())
+            .unwrap_or(0);
+        self.file_candidates = base;
+        self.input_mode = InputMode::SelectingFile;
+    }
+
+    /// Moves file switcher selection up.
+    pub fn file_select_up(&mut self) {
+        if self.selected_file > 0 {
+            self.selected_file -= 1;
+        }
+    }
+
+    /// Moves file switcher selection down.
+    pub fn file_select_down(&mut self) {
+        if self.selected_file + 1 < self.file_candidates.len() {
+            self.selected_file += 1;
+        }
+    }
+
+    /// Opens the selected file. Files other than the main settings file have no
+    /// schema, so all their keys are shown generically in the Advanced section.
+    pub fn commit_file_selection(&mut self) {
+        self.input_mode = InputMode::Normal;
+        let Some(path) = self.file_candidates.get(self.selected_file).cloned() else {
+            return;
+        };
+        match Config::load_with(&path, self.config.is_strict()) {
+            Ok(mut config) => {
+                config.set_backup_retention(self.config.backup_retention());
+                config.set_format(self.config.format().clone());
+                config.set_read_only(self.config.is_read_only());
+                if path == self.settings_path {
+                    config.set_env_overrides(std::env::vars());
+                }
+                self.notes = Notes::load(&path).unwrap_or_default();
+                self.config = config;
+                self.refresh_git();
+                self.selected_setting = 0;
+                self.mcp_focus = McpFocus::Configs;
+                self.selected_mcp_permission = 0;
+                if !self.is_settings_file() {
+                    self.selected_section = Section::ALL
+                        .iter()
+                        .position(|s| *s == Section::Advanced)
+                        .unwrap_or(0);
+                }
+                self.status_message = Some(match load_notice(&self.config) {
+                    Some(notice) => format!("Opened {} — {notice}", path.display()),
+                    None => format!("Opened {}", path.display()),
+                });
+            }
+            Err(e) => self.status_message = Some(format!("Open failed: {e:#}")),
+        }
+        self.file_candidates.clear();
+    }
+
+    /// Shows a report overlay.
+    pub fn show_report(&mut self, title: impl Into<String>, lines: Vec<String>) {
+        self.report = Some(Report {
+            title: title.into(),
+            lines,
+        });
+        self.input_mode = InputMode::ViewingReport;
+    }
+
+    /// Has the installed amp CLI load the saved settings file and reports what
+    /// it complains about.
+    pub fn validate_with_amp(&mut self) {
+        if self.config.is_dirty() {
+            self.status_message = Some("Save before validating with amp.".into());
+            return;
+        }
+        let Some(binary) = amp::find_amp() else {
+            self.status_message = Some("amp is not installed or not on PATH.".into());
+            return;
+        };
+        match amp::check_settings(&binary, self.config.path()) {
+            Ok(Some(check)) => {
+                let mut lines = check.messages;
+                if lines.is_empty() {
+                    lines.push("No complaints.".to_string());
+                }
+                let title = if check.ok {
+                    " amp: settings OK "
+                } else {
+                    " amp: settings rejected "
+                };
+                self.show_report(title, lines);
+            }
+            Ok(None) => {
+                self.status_message = Some("This amp version can't check a settings file.".into());
+            }
+            Err(e) => self.status_message = Some(format!("amp check failed: {e:#}")),
+        }
+    }
+
+    /// Starts a connectivity test of the selected MCP server: it is spawned
+    /// (or its URL contacted) in the background and asked for its tools.
+    pub fn test_mcp_server(&mut self) {
+        if !self.current_section().is_split_panel()
+            || self.focus != Focus::Settings
+            || self.mcp_focus != McpFocus::Configs
+        {
+            self.status_message = Some("Select an MCP server config to test.".into());
+            return;
+        }
+        let names = self.mcp_server_names();
+        let Some(name) = names.get(self.selected_setting) else {
+            return;
+        };
+        if let Some(running) = &self.mcp_probe {
+            self.status_message = Some(format!("Still testing {running}…"));
+            return;
+        }
+        let server = self.config.get("amp.mcpServers")[name].clone();
+        let task_name = name.clone();
+        self.tasks.spawn(move || TaskResult::McpProbe {
+            server: task_name,
+            result: mcp_client::probe(&server, mcp_client::PROBE_TIMEOUT),
+        });
+        self.mcp_probe = Some(name.clone());
+        self.status_message = Some(format!("Testing {name}…"));
+    }
+
+    /// Checks the health of MCP servers in the background while the MCPs
+    /// section is open: each server once, and again after its config
+    /// changes.
+    pub fn check_mcp_health(&mut self) {
+        if !self.current_section().is_split_panel() {
+            return;
+        }
+        let servers = self.config.get("amp.mcpServers");
+        let servers = servers.as_object().cloned().unwrap_or_default();
+        self.mcp_health.retain(|name, _| servers.contains_key(name));
+        for (name, server) in servers {
+            if self
+                .mcp_health
+                .get(&name)
+                .is_some_and(|(checked, _)| *checked == server)
+            {
+                continue;
+            }
+            let (task_name, config) = (name.clone(), server.clone());
+            self.tasks.spawn(move || TaskResult::McpHealth {
+                health: mcp_client::check_health(&config, mcp_client::HEALTH_TIMEOUT),
+                server: task_name,
+                config,
+            });
+            self.mcp_health.insert(name, (server, None));
+        }
+    }
+
+    /// The health check result for a server; `None` until it's in.
+    pub fn mcp_server_health(&self, name: &str) -> Option<&Health> {
+        self.mcp_health.get(name)?.1.as_ref()
+    }
+
+    /// Describes the selected MCP server's health, for the status line.
+    pub fn selected_mcp_health(&self) -> Option<String> {
+        if self.focus != Focus::Settings
+            || !self.current_section().is_split_panel()
+            || self.mcp_focus != McpFocus::Configs
+        {
+            return None;
+        }
+        let name = self
+            .mcp_server_names()
+            .into_iter()
+            .nth(self.selected_setting)?;
+        let (_, health) = self.mcp_health.get(&name)?;
+        Some(match health {
+            Some(health) => format!("{} {name}: {}", health.symbol(), health.detail()),
+            None => format!("? {name}: checking…"),
+        })
+    }
+
+    /// Takes the result of a background task. It's shown right away unless
+    /// another overlay is open, in which case it waits so as not to replace it.
+    pub fn finish_task(&mut self, result: TaskResult) {
+        match result {
+            TaskResult::McpProbe { .. } => self.mcp_probe = None,
+            TaskResult::McpHealth {
+                server,
+                config,
+                health,
+            } => {
+                // A result for a config that has since changed is stale.
+                if let Some((checked, result)) = self.mcp_health.get_mut(&server) {
+                    if *checked == config {
+                        *result = Some(health);
+                    }
+                }
+                return;
+            }
+            // The catalog only changes what the browser lists, so it's
+            // taken in right away.
+            TaskResult::RegistryRefresh(result) => {
+                self.registry_refreshing = false;
+                self.status_message = Some(match result {
+                    Ok(entries) => {
+                        let count = entries.len();
+                        if self.input_mode == InputMode::BrowsingRegistry {
+                            self.registry = entries;
+                            self.selected_registry =
+                                self.selected_registry.min(count.saturating_sub(1));
+                        }
+                        format!("Refreshed the MCP catalog: {count} servers")
+                    }
+                    Err(e) => format!("Refreshing the MCP catalog failed: {e:#}"),
+                });
+                return;
+            }
+        }
+        self.finished_tasks.push_back(result);
+        self.show_finished_tasks();
+    }
+
+    /// Shows the next waiting task result if nothing else is open.
+    fn show_finished_tasks(&mut self) {
+        if self.is_editing() {
+            return;
+        }
+        let Some(result) = self.finished_tasks.pop_front() else {
+            return;
+        };
+        match result {
+            TaskResult::McpProbe {
+                server,
+                result: Ok(probe),
+            } => self.show_report(format!(" {server}: connected "), probe.report_lines()),
+            TaskResult::McpProbe {
+                server,
+                result: Err(e),
+            } => self.show_report(format!(" {server}: failed "), vec![format!("{e:#}")]),
+            TaskResult::McpHealth { .. } | TaskResult::RegistryRefresh(_) => {}
+        }
+    }
+
+    /// Periodic upkeep between key presses: shows task results that were
+    /// waiting for an overlay to close, starts MCP server health checks,
+    /// notices external edits to the file,
+    /// and clears a status message that has been up for `STATUS_TIMEOUT`.
+    pub fn tick(&mut self, now: Instant) {
+        self.show_finished_tasks();
+        self.check_mcp_health();
+        self.check_external_change();
+        if self.autosave_due.is_some_and(|due| now >= due) && !self.is_editing() {
+            self.flush_autosave();
+        }
+        let expired = self
+            .status_shown_at
+            .is_some_and(|at| now.duration_since(at) >= STATUS_TIMEOUT);
+        if expired && !self.is_editing() {
+            self.status_message = None;
+        }
+    }
+
+    /// Starts editing the note attached to the selected setting.
+    pub fn start_edit_note(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        self.edit_buffer
+            .set(self.notes.get(&key).unwrap_or_default().to_string());
+        self.input_mode = InputMode::EditingNote;
+    }
+
+    /// Commits the note for the selected setting and writes the sidecar file.
+    pub fn commit_note(&mut self) {
+        self.input_mode = InputMode::Normal;
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        self.notes.set(&key, &self.edit_buffer);
+        self.edit_buffer.clear();
+        match self.notes.save() {
+            Ok(()) => self.status_message = Some(format!("Saved note for {key}")),
+            Err(e) => self.status_message = Some(format!("Saving note failed: {e:#}")),
+        }
+    }
+
+    /// Shows what a save would change in the file on disk.
+    pub fn show_diff(&mut self) {
+        let (old, new) = match (self.config.read_disk(), self.config.render()) {
+            (Ok(old), Ok(new)) => (old, new),
+            (Err(e), _) | (_, Err(e)) => {
+                self.status_message = Some(format!("Diff failed: {e:#}"));
+                return;
+            }
+        };
+        let lines = diff::hunks(&diff::diff_lines(&old, &new), 3);
+        if lines.is_empty() {
+            self.status_message = Some("No unsaved changes.".into());
+            return;
+        }
+        self.diff = lines;
+        self.diff_scroll = 0;
+        self.diff_from_head = false;
+        self.input_mode = InputMode::ViewingDiff;
+    }
+
+    /// Checks the open file's git status again, after it was written or
+    /// another file opened.
+    pub fn refresh_git(&mut self) {
+        self.git_status = git::status(self.config.path());
+    }
+
+    /// Opens the diff of the file on disk against the last git commit.
+    pub fn show_git_diff(&mut self) {
+        if self.git_status.is_none() {
+            self.status_message = Some("The settings file isn't in a git repository.".into());
+            return;
+        }
+        let texts = git::head_contents(self.config.path())
+            .and_then(|head| Ok((head.unwrap_or_default(), self.config.read_disk()?)));
+        let (old, new) = match texts {
+            Ok(texts) => texts,
+            Err(e) => {
+                self.status_message = Some(format!("Diff failed: {e:#}"));
+                return;
+            }
+        };
+        let lines = diff::hunks(&diff::diff_lines(&old, &new), 3);
+        if lines.is_empty() {
+            self.status_message = Some("No changes since the last commit.".into());
+            return;
+        }
+        self.diff = lines;
+        self.diff_scroll = 0;
+        self.diff_from_head = true;
+        self.input_mode = InputMode::ViewingDiff;
+    }
+
+    /// Asks for a message to commit the settings file to git with. Only
+    /// what's on disk is committed, so unsaved changes must be saved first.
+    pub fn start_git_commit(&mut self) {
+        if self.config.is_read_only() {
+            self.status_message = Some("Read-only: changes are disabled".into());
+            return;
+        }
+        self.refresh_git();
+        let message = match self.git_status {
+            None => "The settings file isn't in a git repository.".to_string(),
+            Some(status) if !status.is_committable() => "Nothing to commit.".to_string(),
+            Some(_) if self.config.is_dirty() => format!(
+                "Save first ({}); only the file on disk is committed.",
+                self.keymap.label(Action::Save)
+            ),
+            Some(_) => {
+                let name = self
+                    .config
+                    .path()
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                self.edit_buffer.set(format!("Update {name}"));
+                self.input_mode = InputMode::EnteringCommitMessage;
+                return;
+            }
+        };
+        self.status_message = Some(message);
+    }
+
+    /// Commits the settings file with the message typed.
+    pub fn commit_git_message(&mut self) {
+        let message = self.edit_buffer.trim().to_string();
+        if message.is_empty() {
+            self.status_message = Some("Commit message cannot be empty.".into());
+            return;
+        }
+        self.cancel_edit();
+        self.status_message = Some(match git::commit(self.config.path(), &message) {
+            Ok(summary) => format!("Committed {summary}"),
+            Err(e) => format!("Commit failed: {e:#}"),
+        });
+        self.refresh_git();
+    }
+
+    /// Adds the current status message to the history if it's new. Called
+    /// once per frame, so every message that was ever on screen is kept.
+    pub fn log_status(&mut self) {
+        if self.status_message == self.logged_status {
+            return;
+        }
+        if let Some(text) = &self.status_message {
+            self.messages.push(text, SystemTime::now());
+        }
+        self.status_shown_at = self.status_message.as_ref().map(|_| Instant::now());
+        self.logged_status = self.status_message.clone();
+    }
+
+    /// Opens the history of status messages, newest first.
+    pub fn show_messages(&mut self) {
+        self.messages_scroll = 0;
+        self.input_mode = InputMode::ViewingMessages;
+    }
+
+    /// Scrolls the message history toward newer messages.
+    pub fn messages_scroll_up(&mut self) {
+        self.messages_scroll = self.messages_scroll.saturating_sub(1);
+    }
+
+    /// Scrolls the message history toward older messages.
+    pub fn messages_scroll_down(&mut self) {
+        if self.messages_scroll + 1 < self.messages.len() {
+            self.messages_scroll += 1;
+        }
+    }
+
+    /// Scrolls the diff overlay up one line.
+    pub fn diff_scroll_up(&mut self) {
+        self.diff_scroll = self.diff_scroll.saturating_sub(1);
+    }
+
+    /// Scrolls the diff overlay down one line.
+    pub fn diff_scroll_down(&mut self) {
+        if self.diff_scroll + 1 < self.diff.len() {
+            self.diff_scroll += 1;
+        }
+    }
+
+    /// Opens the backup picker for the open file.
+    pub fn start_restore_backup(&mut self) {
+        let backups = backup::list(self.config.path());
+        if backups.is_empty() {
+            self.status_message = Some("No backups yet.".into());
+            return;
+        }
+        self.backup_candidates = backups;
+        self.selected_backup = 0;
+        self.input_mode = InputMode::SelectingBackup;
+    }
+
+    /// Moves backup picker selection up.
+    pub fn backup_select_up(&mut self) {
+        if self.selected_backup > 0 {
+            self.selected_backup -= 1;
+        }
+    }
+
+    /// Moves backup picker selection down.
+    pub fn backup_select_down(&mut self) {
+        if self.selected_backup + 1 < self.backup_candidates.len() {
+            self.selected_backup += 1;
+        }
+    }
+
+    /// Loads the selected backup as unsaved changes.
+    pub fn commit_backup_restore(&mut self) {
+        let selected = self.backup_candidates.get(self.selected_backup).cloned();
+        self.cancel_edit();
+        let Some(path) = selected else {
+            return;
+        };
+        match self.config.restore_backup(&path) {
+            Ok(()) => {
+                self.selected_setting = 0;
+                self.selected_mcp_permission = 0;
+                self.status_message = Some(format!(
+                    "Restored backup from {} — Ctrl+S to keep it",
+                    backup::label(&path)
+                ));
+            }
+            Err(e) => self.status_message = Some(format!("Restore failed: {e:#}")),
+        }
+    }
+
+    /// Shows the selected setting's value in full, or the selected rule in
+    /// Permissions: for values too long for their column, and in read-only
+    /// mode, where nothing opens for editing. Secrets stay masked unless
+    /// revealed.
+    pub fn inspect_selected(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        let mut value = self.effective_value(&key);
+        let mut title = key.clone();
+        if self.current_section().is_single_key() {
+            if let Some(rule) = value.get(self.selected_setting).cloned() {
+                title = format!("{key}[{}]", self.selected_setting);
+                value = rule;
+            }
+        }
+        let shown = match value.as_str() {
+            Some(text) if self.is_sensitive(&key) => Value::String(self.mask(text).into_owned()),
+            _ => self.mask_nested_secrets(&value),
+        };
+        let pretty = serde_json::to_string_pretty(&shown).unwrap_or_default();
+        if self.config.is_read_only() {
+            title.push_str(" (read-only)");
+        }
+        self.show_report(
+            format!(" {title} "),
+            pretty.lines().map(str::to_string).collect(),
+        );
+    }
+
+    /// How many characters of `key`'s value are scrolled out of view.
+    pub fn value_scroll_of(&self, key: &str) -> usize {
+        match &self.value_scroll {
+            Some((scrolled, by)) if scrolled == key => *by,
+            _ => 0,
+        }
+    }
+
+    /// Scrolls the selected row's value a few characters sideways, for
+    /// reading values too long for their column.
+    pub fn scroll_value(&mut self, right: bool) {
+        let Some(key) = self
+            .selected_key()
+            .filter(|_| self.focus == Focus::Settings)
+        else {
+            return;
+        };
+        let by = self.value_scroll_of(&key);
+        let by = if right {
+            (by + VALUE_SCROLL_STEP).min(self.layout.value_overflow)
+        } else {
+            by.saturating_sub(VALUE_SCROLL_STEP)
+        };
+        self.value_scroll = Some((key, by));
+    }
+
+    /// Masks string values under secret-looking names anywhere inside a
+    /// value, such as an MCP server's `env` tokens.
+    fn mask_nested_secrets(&self, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(name, inner)| {
+                        let masked = match inner.as_str() {
+                            Some(text) if settings::looks_secret(name) => {
+                                Value::String(self.mask(text).into_owned())
+                            }
+                            _ => self.mask_nested_secrets(inner),
+                        };
+                        (name.clone(), masked)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.mask_nested_secrets(v)).collect())
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// Whether a key holds a credential that is masked until revealed.
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        match settings::get_setting_def(key) {
+            Some(def) => def.sensitive,
+            None => settings::looks_secret(key) && self.config.get(key).is_string(),
+        }
+    }
+
+    /// Returns how a sensitive value is shown: masked unless revealed.
+    pub fn mask<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.reveal_secrets || text.is_empty() {
+            text.into()
+        } else {
+            SECRET_MASK.into()
+        }
+    }
+
+    /// Shows or masks sensitive values.
+    pub fn toggle_reveal_secrets(&mut self) {
+        self.reveal_secrets = !self.reveal_secrets;
+        self.status_message = Some(if self.reveal_secrets {
+            "Showing sensitive values — press again to hide them".into()
+        } else {
+            "Sensitive values hidden".into()
+        });
+    }
+
+    /// Applies a change to the permission rule form.
+    pub fn edit_rule_form(&mut self, f: impl FnOnce(&mut RuleForm)) {
+        if let Some(form) = &mut self.rule_form {
+            f(form);
+        }
+    }
+
+    /// Writes the form back to its rule, or focuses the first invalid field.
+    pub fn commit_rule_form(&mut self) {
+        let Some(form) = &mut self.rule_form else {
+            return;
+        };
+        if let Some((field, error)) = form.first_error() {
+            form.field = field;
+            self.status_message = Some(format!("Can't save the rule: {error}"));
+            return;
+        }
+        let (index, rule) = (form.index, form.to_rule());
+        self.cancel_edit();
+        let mut arr = self
+            .config
+            .get("amp.permissions")
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let Some(slot) = arr.get_mut(index) else {
+            return;
+        };
+        if *slot != rule {
+            *slot = rule;
+            self.config.set("amp.permissions", Value::Array(arr));
+            self.status_message = Some(format!("Updated permission rule {index}"));
+        }
+    }
+
+    /// Leaves the form for `$EDITOR`, carrying over what's been typed.
+    pub fn rule_form_to_editor(&mut self) -> Option<EditorRequest> {
+        let form = self.rule_form.take()?;
+        self.cancel_edit();
+        Some(EditorRequest {
+            key: "amp.permissions".to_string(),
+            value: form.to_rule(),
+            array_index: Some(form.index),
+            object_key: None,
+        })
+    }
+
+    /// Opens the permission tester, which shows which rule would decide a
+    /// tool call as it is typed.
+    pub fn start_permission_test(&mut self) {
+        if self.current_section() != Section::Permissions {
+            self.status_message =
+                Some("Test permission rules from the Permissions section.".into());
+            return;
+        }
+        self.permission_test = Some(PermissionTest {
+            tool: "Bash".to_string(),
+            editing_args: true,
+            ..PermissionTest::default()
+        });
+        self.input_mode = InputMode::TestingPermissions;
+    }
+
+    /// Applies a change to the field being typed in the permission tester.
+    pub fn edit_permission_test(&mut self, f: impl FnOnce(&mut String)) {
+        if let Some(test) = &mut self.permission_test {
+            f(if test.editing_args {
+                &mut test.args
+            } else {
+                &mut test.tool
+            });
+        }
+    }
+
+    /// Switches the permission tester between the tool and argument fields.
+    pub fn switch_permission_test_field(&mut self) {
+        if let Some(test) = &mut self.permission_test {
+            test.editing_args = !test.editing_args;
+        }
+    }
+
+    /// Evaluates the typed call against `amp.permissions`, or explains why
+    /// the arguments can't be used.
+    pub fn permission_test_result(&self) -> Option<Result<Evaluation, String>> {
+        let test = self.permission_test.as_ref()?;
+        let tool = test.tool.trim();
+        let rules = self.config.get("amp.permissions");
+        let rules = rules.as_array().map(Vec::as_slice).unwrap_or_default();
+        Some(
+            permissions::parse_args(tool, &test.args)
+                .map(|args| permissions::evaluate(rules, tool, &args)),
+        )
+    }
+
+    /// Opens the env variable editor for the selected MCP server.
+    pub fn start_env_edit(&mut self) {
+        if !self.current_section().is_split_panel() || self.mcp_focus != McpFocus::Configs {
+            self.status_message = Some("Select an MCP server to edit its env.".into());
+            return;
+        }
+        let names = self.mcp_server_names();
+        let Some(name) = names.get(self.selected_setting) else {
+            return;
+        };
+        if self.config.get("amp.mcpServers")[name].get("url").is_some() {
+            self.status_message = Some(format!(
+                "{name} is a remote server; it has headers, not env."
+            ));
+            return;
+        }
+        self.env_server = Some(name.clone());
+        self.selected_env = 0;
+        self.input_mode = InputMode::EditingEnv;
+    }
+
+    /// Returns the env variables of the server being edited, sorted by name.
+    pub fn env_entries(&self) -> Vec<(String, String)> {
+        let Some(server) = &self.env_server else {
+            return Vec::new();
+        };
+        self.config.get("amp.mcpServers")[server]["env"]
+            .as_object()
+            .map(|env| {
+                env.iter()
+                    .map(|(k, v)| {
+                        (
+                            k.clone(),
+                            v.as_str().map_or_else(|| v.to_string(), str::to_string),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Rewrites the env map of the server being edited.
+    fn update_env(&mut self, f: impl FnOnce(&mut serde_json::Map<String, Value>)) {
+        let Some(server) = self.env_server.clone() else {
+            return;
+        };
+        let mut servers = self
+            .config
+            .get("amp.mcpServers")
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        let Some(Value::Object(config)) = servers.get_mut(&server) else {
+            return;
+        };
+        let mut env = config
+            .get("env")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+        f(&mut env);
+        if env.is_empty() {
+            config.shift_remove("env");
+        } else {
+            config.insert("env".to_string(), Value::Object(env));
+        }
+        self.config.set("amp.mcpServers", Value::Object(servers));
+    }
+
+    /// Moves env editor selection up.
+    pub fn env_select_up(&mut self) {
+        if self.selected_env > 0 {
+            self.selected_env -= 1;
+        }
+    }
+
+    /// Moves env editor selection down.
+    pub fn env_select_down(&mut self) {
+        if self.selected_env + 1 < self.env_entries().len() {
+            self.selected_env += 1;
+        }
+    }
+
+    /// Starts adding a variable.
+    pub fn start_add_env(&mut self) {
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::EnteringEnvKey;
+    }
+
+    /// Starts editing the selected variable's value.
+    pub fn start_edit_env(&mut self) {
+        let Some((key, value)) = self.env_entries().into_iter().nth(self.selected_env) else {
+            return;
+        };
+        self.pending_env_key = Some(key);
+        self.edit_buffer.set(value);
+        self.input_mode = InputMode::EnteringEnvValue;
+    }
+
+    /// Takes the new variable's name and moves on to its value.
+    pub fn commit_env_key(&mut self) {
+        let key = self.edit_buffer.trim().to_string();
+        if key.is_empty() || key.contains('=') {
+            self.status_message = Some("Enter a variable name without '='.".into());
+            return;
+        }
+        if self.env_entries().iter().any(|(k, _)| *k == key) {
+            self.status_message = Some(format!("{key} is already set; press Enter on it to edit."));
+            return;
+        }
+        self.pending_env_key = Some(key);
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::EnteringEnvValue;
+    }
+
+    /// Stores the typed value and returns to the variable list.
+    pub fn commit_env_value(&mut self) {
+        let Some(key) = self.pending_env_key.take() else {
+            return;
+        };
+        let value = self.edit_buffer.take();
+        self.update_env(|env| {
+            env.insert(key.clone(), Value::String(value));
+        });
+        if let Some(i) = self.env_entries().iter().position(|(k, _)| *k == key) {
+            self.selected_env = i;
+        }
+        self.input_mode = InputMode::EditingEnv;
+    }
+
+    /// Leaves a name or value prompt without changing anything.
+    pub fn cancel_env_input(&mut self) {
+        self.pending_env_key = None;
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::EditingEnv;
+    }
+
+    /// Removes the selected variable.
+    pub fn delete_env(&mut self) {
+        let Some((key, _)) = self.env_entries().into_iter().nth(self.selected_env) else {
+            return;
+        };
+        self.update_env(|env| {
+            env.shift_remove(&key);
+        });
+        let count = self.env_entries().len();
+        if count > 0 && self.selected_env >= count {
+            self.selected_env = count - 1;
+        }
+    }
+
+    /// Opens the form for an object setting with a known schema, at the
+    /// object `path` leads to.
+    fn start_object_form(&mut self, key: &'static str, schema: ObjectSchema, path: Vec<String>) {
+        self.object_form = Some(ObjectForm {
+            key,
+            schema,
+            top: path.len(),
+            path,
+            selected: 0,
+            parents: Vec::new(),
+            pending_key: None,
+        });
+        self.input_mode = InputMode::EditingObject;
+    }
+
+    /// The object the form shows, or `Null` if it isn't set.
+    fn object_value(&self) -> Value {
+        let Some(form) = &self.object_form else {
+            return Value::Null;
+        };
+        form.path
+            .iter()
+            .fold(self.config.get(form.key), |value, key| {
+                value.get(key).cloned().unwrap_or(Value::Null)
+            })
+    }
+
+    /// The rows of the object shown: a schema's fields in order, then any
+    /// keys it doesn't know; or a map's entries.
+    pub fn object_rows(&self) -> Vec<ObjectRow> {
+        let Some(schema) = self.object_form.as_ref().and_then(ObjectForm::schema_here) else {
+            return Vec::new();
+        };
+        let object = self.object_value().as_object().cloned().unwrap_or_default();
+        match schema {
+            ObjectSchema::Fields(fields) => fields
+                .iter()
+                .map(|field| ObjectRow {
+                    key: field.name.to_string(),
+                    value: object.get(field.name).cloned(),
+                    field: Some(field.clone()),
+                })
+                .chain(
+                    object
+                        .iter()
+                        .filter(|(k, _)| schema.field(k).is_none())
+                        .map(|(k, v)| ObjectRow {
+                            key: k.clone(),
+                            value: Some(v.clone()),
+                            field: None,
+                        }),
+                )
+                .collect(),
+            ObjectSchema::Map { value, .. } => object
+                .into_iter()
+                .map(|(key, v)| ObjectRow {
+                    key,
+                    value: Some(v),
+                    field: Some((**value).clone()),
+                })
+                .collect(),
+        }
+    }
+
+    fn selected_object_row(&self) -> Option<ObjectRow> {
+        let selected = self.object_form.as_ref()?.selected;
+        self.object_rows().into_iter().nth(selected)
+    }
+
+    /// Rewrites the object shown, creating it (and the objects above it)
+    /// if needed.
+    fn update_object(&mut self, f: impl FnOnce(&mut serde_json::Map<String, Value>)) {
+        let Some(form) = &self.object_form else {
+            return;
+        };
+        let key = form.key;
+        let mut root = self.config.get(key);
+        let mut object = &mut root;
+        for segment in &form.path {
+            if !object.is_object() {
+                *object = Value::Object(serde_json::Map::new());
+            }
+            object = object
+                .as_object_mut()
+                .expect("just made an object")
+                .entry(segment.clone())
+                .or_insert(Value::Null);
+        }
+        if !object.is_object() {
+            *object = Value::Object(serde_json::Map::new());
+        }
+        if let Value::Object(map) = object {
+            f(map);
+        }
+        self.config.set(key, root);
+    }
+
+    /// Moves object form selection up.
+    pub fn object_select_up(&mut self) {
+        if let Some(form) = &mut self.object_form {
+            form.selected = form.selected.saturating_sub(1);
+        }
+    }
+
+    /// Moves object form selection down.
+    pub fn object_select_down(&mut self) {
+        let count = self.object_rows().len();
+        if let Some(form) = &mut self.object_form {
+            if form.selected + 1 < count {
+                form.selected += 1;
+            }
+        }
+    }
+
+    /// Acts on the selected row: opens an object field with a schema as
+    /// its own panel, steps an enum through its options, flips a boolean,
+    /// or asks for anything else as text (JSON for arrays and objects).
+    pub fn change_object_value(&mut self, forward: bool) {
+        let Some(row) = self.selected_object_row() else {
+            return;
+        };
+        let current = row.value.clone();
+        let next = match &row.field {
+            Some(field) if field.object.is_some() => {
+                if forward {
+                    self.open_object_row(row.key, current);
+                }
+                return;
+            }
+            Some(field) if field.field_type == SettingType::StringEnum => {
+                let options = field.enum_options.unwrap_or_default();
+                let len = options.len();
+                if len == 0 {
+                    return;
+                }
+                let value = current.unwrap_or_else(|| field.default.clone());
+                let next = match options.iter().position(|o| value == *o) {
+                    Some(i) if forward => (i + 1) % len,
+                    Some(i) => (i + len - 1) % len,
+                    None => 0,
+                };
+                Value::String(options[next].to_string())
+            }
+            Some(field) if field.field_type == SettingType::Boolean => {
+                let value = current.unwrap_or_else(|| field.default.clone());
+                Value::Bool(!value.as_bool().unwrap_or(false))
+            }
+            field => {
+                let value = current
+                    .or_else(|| field.as_ref().map(|f| f.default.clone()))
+                    .unwrap_or(Value::Null);
+                self.edit_buffer.set(match value {
+                    Value::String(s) => s,
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                });
+                if let Some(form) = &mut self.object_form {
+                    form.pending_key = Some(row.key);
+                }
+                self.input_mode = InputMode::EnteringObjectValue;
+                return;
+            }
+        };
+        self.update_object(|obj| {
+            obj.insert(row.key, next);
+        });
+    }
+
+    /// Shows the object under `key` in place of the current one.
+    fn open_object_row(&mut self, key: String, value: Option<Value>) {
+        if value.as_ref().is_some_and(|v| !v.is_object()) {
+            self.status_message =
+                Some(format!("{key} isn't an object; press d to clear it first."));
+            return;
+        }
+        if let Some(form) = &mut self.object_form {
+            form.path.push(key);
+            form.parents.push(form.selected);
+            form.selected = 0;
+        }
+    }
+
+    /// Returns to the panel above, or closes the form at the top.
+    pub fn object_back(&mut self) {
+        let Some(form) = &mut self.object_form else {
+            return;
+        };
+        if form.path.len() <= form.top {
+            self.cancel_edit();
+            return;
+        }
+        form.path.pop();
+        form.selected = form.parents.pop().unwrap_or(0);
+    }
+
+    /// Starts adding an entry, in panels whose keys are chosen freely.
+    pub fn start_add_object_entry(&mut self) {
+        let is_map = self
+            .object_form
+            .as_ref()
+            .and_then(ObjectForm::schema_here)
+            .is_some_and(|s| matches!(s, ObjectSchema::Map { .. }));
+        if !is_map {
+            self.status_message = Some("These fields are fixed; Enter edits one.".into());
+            return;
+        }
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::EnteringObjectKey;
+    }
+
+    /// Adds an entry under the typed key with the schema's default value,
+    /// then asks for the value if it's typed rather than picked.
+    pub fn commit_object_key(&mut self) {
+        let Some(ObjectSchema::Map {
+            key_label, value, ..
+        }) = self
+            .object_form
+            .as_ref()
+            .and_then(ObjectForm::schema_here)
+            .cloned()
+        else {
+            return;
+        };
+        let key = self.edit_buffer.trim().to_string();
+        if key.is_empty() {
+            self.status_message = Some(format!("The {key_label} cannot be empty."));
+            return;
+        }
+        if self.object_rows().iter().any(|row| row.key == key) {
+            self.status_message = Some(format!(
+                "{key} is already set; press Enter on it to change it."
+            ));
+            return;
+        }
+        self.edit_buffer.clear();
+        self.update_object(|obj| {
+            obj.insert(key.clone(), value.default.clone());
+        });
+        self.select_object_row(&key);
+        self.input_mode = InputMode::EditingObject;
+        let picked = matches!(
+            value.field_type,
+            SettingType::StringEnum | SettingType::Boolean
+        );
+        if !picked {
+            self.change_object_value(true);
+        }
+    }
+
+    /// Stores the typed value and returns to the panel.
+    pub fn commit_object_value(&mut self) {
+        let Some(key) = self
+            .object_form
+            .as_ref()
+            .and_then(|f| f.pending_key.clone())
+        else {
+            return;
+        };
+        let field = self
+            .object_rows()
+            .into_iter()
+            .find(|row| row.key == key)
+            .and_then(|row| row.field);
+        let value = match parse_field_value(field.as_ref(), &self.edit_buffer) {
+            Ok(value) => value,
+            Err(e) => {
+                self.status_message = Some(e);
+                return;
+            }
+        };
+        self.update_object(|obj| {
+            obj.insert(key.clone(), value);
+        });
+        self.select_object_row(&key);
+        self.cancel_object_input();
+    }
+
+    fn select_object_row(&mut self, key: &str) {
+        let index = self.object_rows().iter().position(|row| row.key == key);
+        if let (Some(form), Some(index)) = (&mut self.object_form, index) {
+            form.selected = index;
+        }
+    }
+
+    /// Leaves a key or value prompt without changing anything.
+    pub fn cancel_object_input(&mut self) {
+        if let Some(form) = &mut self.object_form {
+            form.pending_key = None;
+        }
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::EditingObject;
+    }
+
+    /// Removes the selected entry, or unsets the selected field so Amp
+    /// uses its default.
+    pub fn delete_object_entry(&mut self) {
+        let Some(row) = self.selected_object_row() else {
+            return;
+        };
+        self.update_object(|obj| {
+            obj.shift_remove(&row.key);
+        });
+        let count = self.object_rows().len();
+        if let Some(form) = &mut self.object_form {
+            form.selected = form.selected.min(count.saturating_sub(1));
+        }
+    }
+
+    /// Opens the selected object or array in place as a tree, showing its
+    /// top level.
+    fn open_tree(&mut self) {
+        let section = self.current_section();
+        if section.is_single_key() || section.is_split_panel() {
+            self.status_message = Some("Only rows in a settings list open as a tree.".into());
+            return;
+        }
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        let tree = TreeView::new(&key);
+        if tree.rows(&self.config.get(&key)).is_empty() {
+            self.status_message = Some(format!("{key} has nothing to expand."));
+            return;
+        }
+        self.tree = Some(tree);
+        self.input_mode = InputMode::BrowsingTree;
+    }
+
+    /// The lines of the open tree, for its current value.
+    pub fn tree_rows(&self) -> Vec<TreeRow> {
+        match &self.tree {
+            Some(tree) => tree.rows(&self.config.get(&tree.key)),
+            None => Vec::new(),
+        }
+    }
+
+    fn selected_tree_row(&self) -> Option<TreeRow> {
+        let selected = self.tree.as_ref()?.selected;
+        self.tree_rows().into_iter().nth(selected)
+    }
+
+    /// Moves the tree cursor up.
+    pub fn tree_select_up(&mut self) {
+        if let Some(tree) = &mut self.tree {
+            tree.selected = tree.selected.saturating_sub(1);
+        }
+    }
+
+    /// Moves the tree cursor down.
+    pub fn tree_select_down(&mut self) {
+        let count = self.tree_rows().len();
+        if let Some(tree) = &mut self.tree {
+            if tree.selected + 1 < count {
+                tree.selected += 1;
+            }
+        }
+    }
+
+    /// Opens the selected branch, or steps into it if it's open already.
+    pub fn tree_expand(&mut self) {
+        let Some(row) = self.selected_tree_row() else {
+            return;
+        };
+        if !row.is_branch() {
+            return;
+        }
+        if row.expanded {
+            self.tree_select_down();
+        } else if let Some(tree) = &mut self.tree {
+            tree.expand(&row.path);
+        }
+    }
+
+    /// Folds the selected branch, or moves to the branch it's in.
+    pub fn tree_collapse(&mut self) {
+        let Some(row) = self.selected_tree_row() else {
+            return;
+        };
+        if row.expanded {
+            if let Some(tree) = &mut self.tree {
+                tree.collapse(&row.path);
+            }
+            return;
+        }
+        let parent = &row.path[..row.path.len() - 1];
+        let index = self.tree_rows().iter().position(|r| r.path == parent);
+        if let (Some(tree), Some(index)) = (&mut self.tree, index) {
+            tree.selected = index;
+        }
+    }
+
+    /// Acts on the selected row: folds or unfolds a branch, flips a
+    /// boolean, or asks for a new value (JSON, except for strings).
+    pub fn tree_activate(&mut self) {
+        let Some(row) = self.selected_tree_row() else {
+            return;
+        };
+        if row.is_branch() {
+            if let Some(tree) = &mut self.tree {
+                if row.expanded {
+                    tree.collapse(&row.path);
+                } else {
+                    tree.expand(&row.path);
+                }
+            }
+            return;
+        }
+        if self.config.is_read_only() {
+            self.status_message = Some("Read-only: changes are disabled".into());
+            return;
+        }
+        match row.value {
+            Value::Bool(b) => self.set_tree_value(&row.path, Value::Bool(!b)),
+            Value::String(s) => {
+                self.edit_buffer.set(s);
+                self.input_mode = InputMode::EditingTreeValue;
+            }
+            other => {
+                self.edit_buffer.set(other.to_string());
+                self.input_mode = InputMode::EditingTreeValue;
+            }
+        }
+    }
+
+    /// Stores the typed value in the selected leaf and returns to the tree.
+    /// Strings are taken as typed; other leaves must be JSON.
+    pub fn commit_tree_value(&mut self) {
+        let Some(row) = self.selected_tree_row() else {
+            return;
+        };
+        let value = if row.value.is_string() {
+            Value::String(self.edit_buffer.to_string())
+        } else {
+            match serde_json::from_str(&self.edit_buffer) {
+                Ok(value) => value,
+                Err(e) => {
+                    self.status_message = Some(format!("Invalid JSON: {e}"));
+                    return;
+                }
+            }
+        };
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::BrowsingTree;
+        self.set_tree_value(&row.path, value);
+    }
+
+    /// Leaves the leaf prompt without changing anything.
+    pub fn cancel_tree_input(&mut self) {
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::BrowsingTree;
+    }
+
+    fn set_tree_value(&mut self, path: &[Step], value: Value) {
+        let Some(key) = self.tree.as_ref().map(|t| t.key.clone()) else {
+            return;
+        };
+        let mut root = self.config.get(&key);
+        if !tree::set(&mut root, path, value.clone()) {
+            return;
+        }
+        // Check the result like `volt validate`, minding only new errors.
+        let mut trial = self.config.clone();
+        trial.set(&key, root.clone());
+        let before = lint::lint(&self.config);
+        let new_error = lint::lint(&trial).into_iter().find(|f| {
+            f.severity == lint::Severity::Error && f.path.starts_with(&key) && !before.contains(f)
+        });
+        if let Some(finding) = new_error {
+            self.status_message = Some(format!("Not saved: {}: {}", finding.path, finding.message));
+            return;
+        }
+        self.config.set(&key, root);
+        self.status_message = Some(format!("Set {} = {value}", tree::describe(&key, path)));
+    }
+
+    /// Closes the tree, leaving the setting's row selected.
+    pub fn close_tree(&mut self) {
+        self.tree = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Opens the profile picker.
+    pub fn start_profile_picker(&mut self) {
+        let Some(dir) = &self.profiles_dir else {
+            self.status_message = Some("Profiles need a home directory.".into());
+            return;
+        };
+        self.profile_candidates = profile::list(dir);
+        self.selected_profile = 0;
+        self.input_mode = InputMode::SelectingProfile;
+    }
+
+    /// Moves profile picker selection up.
+    pub fn profile_select_up(&mut self) {
+        if self.selected_profile > 0 {
+            self.selected_profile -= 1;
+        }
+    }
+
+    /// Moves profile picker selection down, as far as the "save new" row.
+    pub fn profile_select_down(&mut self) {
+        if self.selected_profile < self.profile_candidates.len() {
+            self.selected_profile += 1;
+        }
+    }
+
+    /// Loads the selected profile as unsaved changes, or asks for a name
+    /// when the "save new" row is selected.
+    pub fn commit_profile_selection(&mut self) {
+        let Some(name) = self.profile_candidates.get(self.selected_profile).cloned() else {
+            self.profile_candidates.clear();
+            self.edit_buffer.clear();
+            self.input_mode = InputMode::EnteringProfileName;
+            return;
+        };
+        self.cancel_edit();
+        let Some(dir) = &self.profiles_dir else {
+            return;
+        };
+        match profile::load(dir, &name) {
+            Ok(profile) => {
+                profile.apply(&mut self.config);
+                self.selected_setting = 0;
+                self.selected_mcp_permission = 0;
+                self.status_message = Some(format!(
+                    "Loaded profile {name} ({}) — Ctrl+S to keep it",
+                    profile.scope_label()
+                ));
+            }
+            Err(e) => self.status_message = Some(format!("Loading profile failed: {e:#}")),
+        }
+    }
+
+    /// Saves all current settings, including unsaved changes, as a profile.
+    pub fn commit_profile_name(&mut self) {
+        let name = self.edit_buffer.trim().to_string();
+        if name.is_empty() {
+            self.status_message = Some("Profile name cannot be empty.".into());
+            return;
+        }
+        self.cancel_edit();
+        let Some(dir) = &self.profiles_dir else {
+            return;
+        };
+        self.status_message = Some(match profile::save(dir, &name, &self.config, None) {
+            Ok(_) => format!("Saved profile {name}"),
+            Err(e) => format!("Saving profile failed: {e:#}"),
+        });
+    }
+
+    /// Asks where to write a redacted copy of the settings, suggesting a
+    /// file in the current directory.
+    pub fn start_export(&mut self) {
+        self.edit_buffer.set(EXPORT_FILE);
+        self.input_mode = InputMode::EnteringExportPath;
+    }
+
+    /// Writes the settings, secrets redacted, to the path typed.
+    pub fn commit_export_path(&mut self) {
+        let path = PathBuf::from(self.edit_buffer.trim());
+        if path.as_os_str().is_empty() {
+            self.status_message = Some("Enter a path to write to.".into());
+            return;
+        }
+        if path == self.config.path() {
+            self.status_message =
+                Some("That's the settings file itself; pick another path.".into());
+            return;
+        }
+        self.cancel_edit();
+        let text = serde_json::to_string_pretty(&self.config.export(true)).unwrap_or_default();
+        self.status_message = Some(match std::fs::write(&path, text + "\n") {
+            Ok(()) => format!("Wrote the settings to {}, secrets redacted", path.display()),
+            Err(e) => format!("Export failed: {e}"),
+        });
+    }
+
+    /// Opens the permission preset picker: the built-in presets and the
+    /// user's own.
+    pub fn start_preset_picker(&mut self) {
+        let (presets, errors) = presets::load_all(self.presets_dir.as_deref());
+        if let Some(error) = errors.first() {
+            self.status_message = Some(format!("Skipped a preset: {error}"));
+        }
+        self.preset_candidates = presets;
+        self.selected_preset = 0;
+        self.input_mode = InputMode::SelectingPreset;
+    }
+
+    /// Moves preset picker selection up.
+    pub fn preset_select_up(&mut self) {
+        if self.selected_preset > 0 {
+            self.selected_preset -= 1;
+        }
+    }
+
+    /// Moves preset picker selection down, as far as the "save new" row.
+    pub fn preset_select_down(&mut self) {
+        if self.selected_preset < self.preset_candidates.len() {
+            self.selected_preset += 1;
+        }
+    }
+
+    /// Adds the selected preset's rules to `amp.permissions`: after the
+    /// existing rules, leaving out ones already there, or in place of them.
+    /// On the "save new" row, asks for a name instead.
+    pub fn commit_preset_selection(&mut self, replace: bool) {
+        let Some(preset) = self.preset_candidates.get(self.selected_preset).cloned() else {
+            self.preset_candidates.clear();
+            self.edit_buffer.clear();
+            self.input_mode = InputMode::EnteringPresetName;
+            return;
+        };
+        self.cancel_edit();
+        let existing = self.config.get("amp.permissions");
+        let mut rules = existing.as_array().cloned().unwrap_or_default();
+        let message = if replace {
+            rules = preset.rules.clone();
+            format!(
+                "Replaced the permission rules with {} ({} rules)",
+                preset.name,
+                rules.len()
+            )
+        } else {
+            let before = rules.len();
+            for rule in &preset.rules {
+                if !rules.contains(rule) {
+                    rules.push(rule.clone());
+                }
+            }
+            let added = rules.len() - before;
+            match preset.rules.len() - added {
+                0 => format!("Added {added} rules from {}", preset.name),
+                present => format!(
+                    "Added {added} rules from {} ({present} already there)",
+                    preset.name
+                ),
+            }
+        };
+        self.config.set("amp.permissions", Value::Array(rules));
+        if self.current_section() == Section::Permissions {
+            self.selected_setting = 0;
+        }
+        self.status_message = Some(message);
+    }
+
+    /// Saves the current permission rules, including unsaved changes, as a
+    /// user preset.
+    pub fn commit_preset_name(&mut self) {
+        let name = self.edit_buffer.trim().to_string();
+        if name.is_empty() {
+            self.status_message = Some("Preset name cannot be empty.".into());
+            return;
+        }
+        self.cancel_edit();
+        let Some(dir) = &self.presets_dir else {
+            self.status_message = Some("Presets need a home directory.".into());
+            return;
+        };
+        let rules = self.config.get("amp.permissions");
+        let rules = rules.as_array().map(Vec::as_slice).unwrap_or_default();
+        self.status_message = Some(match presets::save(dir, &name, rules) {
+            Ok(_) => format!("Saved preset {name}"),
+            Err(e) => format!("Saving preset failed: {e:#}"),
+        });
+    }
+
+    /// Notices when the open file was changed by another program. Without
+    /// local changes the file is simply reloaded; otherwise the user is asked
+    /// whether to reload, merge, or keep their version. Only runs while no
+    /// other overlay is open.
+    pub fn check_external_change(&mut self) {
+        if self.input_mode != InputMode::Normal || !self.config.changed_on_disk() {
+            return;
+        }
+        if self.config.is_dirty() {
+            self.input_mode = InputMode::ResolvingExternalChange;
+        } else {
+            self.reload_from_disk();
+        }
+    }
+
+    /// The file to open in `$EDITOR`, once per request.
+    pub fn take_file_edit(&mut self) -> Option<PathBuf> {
+        std::mem::take(&mut self.file_edit_requested).then(|| self.config.path().to_path_buf())
+    }
+
+    /// Picks up the file after it was edited in `$EDITOR`: unsaved changes
+    /// are merged on top of it (local values win where both changed), and
+    /// the result is checked like `volt validate` would.
+    pub fn finish_file_edit(&mut self) {
+        let name = self
+            .config
+            .path()
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !self.config.changed_on_disk() {
+            self.status_message = Some(format!("{name} wasn't changed."));
+            return;
+        }
+        let dirty = self.config.is_dirty();
+        let result = if dirty {
+            self.config.merge_from_disk()
+        } else {
+            self.config.reload().map(|()| Vec::new())
+        };
+        let conflicts = match result {
+            Ok(conflicts) => conflicts.into_iter().map(|c| c.key).collect::<Vec<_>>(),
+            Err(e) => {
+                // Saving would overwrite the broken file; say so.
+                self.config.keep_local();
+                self.status_message = Some(format!(
+                    "{name} is invalid, keeping what was loaded (saving overwrites it; {} to fix it): {e:#}",
+                    self.keymap.label(Action::EditFile)
+                ));
+                return;
+            }
+        };
+        self.clamp_selection();
+        self.refresh_git();
+        let mut message = if !dirty {
+            format!("Reloaded {name}")
+        } else if conflicts.is_empty() {
+            format!("Merged {name} with unsaved changes")
+        } else {
+            format!(
+                "Merged {name}; kept unsaved values for {} over the file's",
+                conflicts.join(", ")
+            )
+        };
+        let findings = lint::lint(&self.config);
+        if let Some(first) = findings.first() {
+            message += &format!(
+                "; {} problem(s), first {}: {}",
+                findings.len(),
+                first.path,
+                first.message
+            );
+        }
+        self.status_message = Some(message);
+    }
+
+    /// Discards local changes and reloads the file from disk.
+    pub fn reload_from_disk(&mut self) {
+        self.input_mode = InputMode::Normal;
+        match self.config.reload() {
+            Ok(()) => {
+                self.clamp_selection();
+                self.refresh_git();
+                self.status_message = Some("Reloaded settings changed on disk".into());
+            }
+            Err(e) => {
+                // Leave the unreadable file alone until it changes again.
+                self.config.keep_local();
+                self.status_message = Some(format!("Reload failed: {e:#}"));
+            }
+        }
+    }
+
+    /// Reloads the file from disk and reapplies local changes on top. Keys
+    /// both sides changed open the merge view to pick a value for each.
+    pub fn merge_from_disk(&mut self) {
+        self.input_mode = InputMode::Normal;
+        match self.config.merge_from_disk() {
+            Ok(conflicts) if conflicts.is_empty() => {
+                self.clamp_selection();
+                self.status_message = Some("Merged changes from disk — Ctrl+S to save".into());
+            }
+            Ok(conflicts) => {
+                self.clamp_selection();
+                self.merge_conflicts = conflicts
+                    .into_iter()
+                    .map(|c| (c, MergeSide::Mine))
+                    .collect();
+                self.selected_conflict = 0;
+                self.input_mode = InputMode::ResolvingConflicts;
+            }
+            Err(e) => {
+                self.config.keep_local();
+                self.status_message = Some(format!("Merge failed: {e:#}"));
+            }
+        }
+    }
+
+    pub fn conflict_select_up(&mut self) {
+        self.selected_conflict = self.selected_conflict.saturating_sub(1);
+    }
+
+    pub fn conflict_select_down(&mut self) {
+        if self.selected_conflict + 1 < self.merge_conflicts.len() {
+            self.selected_conflict += 1;
+        }
+    }
+
+    /// Picks which value the selected conflicting key keeps.
+    pub fn pick_conflict_side(&mut self, side: MergeSide) {
+        if let Some((_, picked)) = self.merge_conflicts.get_mut(self.selected_conflict) {
+            *picked = side;
+        }
+    }
+
+    /// Applies the picks in the merge view. The merge already kept the
+    /// local values, so only keys where the file's value was picked change.
+    pub fn commit_merge(&mut self) {
+        let conflicts = std::mem::take(&mut self.merge_conflicts);
+        self.cancel_edit();
+        let (mut theirs, mut mine) = (Vec::new(), Vec::new());
+        for (conflict, side) in conflicts {
+            match side {
+                MergeSide::Theirs => {
+                    match conflict.theirs {
+                        Some(value) => self.config.set(&conflict.key, value),
+                        None => self.config.remove(&conflict.key),
+                    }
+                    theirs.push(conflict.key);
+                }
+                MergeSide::Mine => mine.push(conflict.key),
+            }
+        }
+        self.clamp_selection();
+        let mut message = "Merged changes from disk".to_string();
+        if !theirs.is_empty() {
+            message += &format!("; took the file's values for {}", theirs.join(", "));
+        }
+        if !mine.is_empty() {
+            message += &format!("; kept local values for {}", mine.join(", "));
+        }
+        self.status_message = Some(format!("{message} — Ctrl+S to save"));
+    }
+
+    /// Ignores the change on disk; the next save overwrites it.
+    pub fn keep_local_changes(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.config.keep_local();
+        self.status_message = Some("Kept local changes — saving will overwrite the file".into());
+    }
+
+    /// Pulls selections back in range after the settings were replaced.
+    fn clamp_selection(&mut self) {
+        let section = self.current_section();
+        let main_count = if section.is_split_panel() {
+            self.mcp_config_count()
+        } else if section.is_single_key() {
+            self.single_key_item_count()
+        } else {
+            self.current_settings().len()
+        };
+        self.selected_setting = self.selected_setting.min(main_count.saturating_sub(1));
+        self.selected_mcp_permission = self
+            .selected_mcp_permission
+            .min(self.mcp_permission_item_count().saturating_sub(1));
+    }
+
+    /// Opens the search overlay with every key listed.
+    pub fn start_search(&mut self) {
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::Searching;
+        self.update_search();
+    }
+
+    /// Every key in every section, in section order. Favorites only repeats
+    /// keys listed elsewhere, so it's left out.
+    fn all_keys(&self) -> Vec<SearchHit> {
+        Section::ALL
+            .iter()
+            .filter(|&&section| section != Section::Favorites)
+            .flat_map(|&section| {
+                self.settings_for(section)
+                    .into_iter()
+                    .map(move |entry| SearchHit {
+                        key: match entry {
+                            SettingEntry::Known(def) => def.key.to_string(),
+                            SettingEntry::Unknown(key) => key,
+                        },
+                        section,
+                    })
+            })
+            .collect()
+    }
+
+    /// Re-runs the search for the current query.
+    pub fn update_search(&mut self) {
+        let mut scored: Vec<(i64, SearchHit)> = self
+            .all_keys()
+            .into_iter()
+            .filter_map(|hit| fuzzy::score(&self.edit_buffer, &hit.key).map(|s| (s, hit)))
+            .collect();
+        // Stable sort keeps section order among equal scores.
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.search_results = scored.into_iter().map(|(_, hit)| hit).collect();
+        self.selected_search_result = 0;
+    }
+
+    /// Moves search selection up.
+    pub fn search_select_up(&mut self) {
+        if self.selected_search_result > 0 {
+            self.selected_search_result -= 1;
+        }
+    }
+
+    /// Moves search selection down.
+    pub fn search_select_down(&mut self) {
+        if self.selected_search_result + 1 < self.search_results.len() {
+            self.selected_search_result += 1;
+        }
+    }
+
+    /// Jumps to the selected search result, switching sections as needed.
+    pub fn commit_search(&mut self) {
+        let hit = self
+            .search_results
+            .get(self.selected_search_result)
+            .cloned();
+        self.cancel_edit();
+        if let Some(hit) = hit {
+            self.jump_to(&hit);
+        }
+    }
+
+    /// Selects a setting by key, as `volt --key` asks. Returns whether
+    /// any section lists it.
+    pub fn go_to_key(&mut self, key: &str) -> bool {
+        let Some(hit) = self.all_keys().into_iter().find(|hit| hit.key == key) else {
+            return false;
+        };
+        self.jump_to(&hit);
+        true
+    }
+
+    /// Opens a section with its first row selected, as `volt --section` asks.
+    pub fn go_to_section(&mut self, section: Section) {
+        let index = Section::ALL.iter().position(|s| *s == section).unwrap_or(0);
+        self.selected_section = index;
+        self.selected_setting = 0;
+        self.mcp_focus = McpFocus::Configs;
+        self.selected_mcp_permission = 0;
+        self.focus = Focus::Settings;
+    }
+
+    /// Shows a key, switching sections as needed.
+    fn jump_to(&mut self, hit: &SearchHit) {
+        self.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == hit.section)
+            .unwrap_or(0);
+        self.selected_setting = 0;
+        self.mcp_focus = McpFocus::Configs;
+        self.selected_mcp_permission = 0;
+        if hit.section.is_split_panel() {
+            if hit.key == "amp.mcpPermissions" {
+                self.mcp_focus = McpFocus::Permissions;
+            }
+        } else if !hit.section.is_single_key() {
+            let position = |app: &Self| {
+                app.shown_settings(hit.section)
+                    .iter()
+                    .position(|entry| entry_key(entry) == hit.key)
+            };
+            // A key the filter or its collapsed group hides is worth seeing
+            // once jumped to.
+            if let Some(group) = settings::group_for_key(&hit.key) {
+                self.collapsed_groups.remove(&group);
+            }
+            if position(self).is_none() {
+                self.filters.remove(&hit.section);
+            }
+            self.selected_setting = position(self).unwrap_or(0);
+        }
+        self.focus = Focus::Settings;
+    }
+
+    /// Runs a normal-mode action, as its key or the command palette asks,
+    /// returning an editor request if the action needs `$EDITOR`. Every
+    /// action is written to the action log, and recorded while a macro is.
+    pub fn dispatch(&mut self, action: Action) -> Option<EditorRequest> {
+        if let Some(log) = &mut self.action_log {
+            if let Err(e) = log.write(action, SystemTime::now()) {
+                self.status_message = Some(format!("Action log failed, no longer logging: {e}"));
+                self.action_log = None;
+            }
+        }
+        self.macros.record(action);
+        self.perform(action)
+    }
+
+    fn perform(&mut self, action: Action) -> Option<EditorRequest> {
+        let settings_focused = self.focus == Focus::Settings;
+        if self.config.is_read_only() {
+            if action.mutates() {
+                self.status_message = Some("Read-only: changes are disabled".into());
+                return None;
+            }
+            if settings_focused && matches!(action, Action::OpenEditor | Action::EditInline) {
+                self.inspect_selected();
+                return None;
+            }
+        }
+        // A collapsed group's row stands for the group, not its first setting.
+        if let Some(group) = self.selected_collapsed_group() {
+            match action {
+                Action::Activate | Action::ToggleGroup => {
+                    self.toggle_group();
+                    return None;
+                }
+                Action::OpenEditor
+                | Action::EditInline
+                | Action::AddItem
+                | Action::DeleteItem
+                | Action::MoveItemUp
+                | Action::MoveItemDown
+                | Action::ShowValue
+                | Action::ScrollLeft
+                | Action::ScrollRight
+                | Action::Duplicate
+                | Action::Copy
+                | Action::Paste
+                | Action::Reset
+                | Action::Increment
+                | Action::Decrement
+                | Action::FixType
+                | Action::EditNote
+                | Action::EditAtSource
+                | Action::ExpandTree => {
+                    self.status_message = Some(format!(
+                        "Expand {group} with {} first.",
+                        self.keymap.label(Action::ToggleGroup)
+                    ));
+                    return None;
+                }
+                _ => {}
+            }
+        }
+        match action {
+            Action::Quit => self.request_quit(),
+            Action::MoveUp => self.move_up(),
+            Action::MoveDown => self.move_down(),
+            Action::PageUp => self.page_up(),
+            Action::PageDown => self.page_down(),
+            Action::SelectFirst => self.select_first(),
+            Action::SelectLast => self.select_last(),
+            Action::ToggleFocus => self.toggle_focus(),
+            Action::Activate if settings_focused => return self.activate_setting(),
+            Action::Activate => self.toggle_focus(),
+            Action::OpenEditor if settings_focused => return self.force_editor(),
+            Action::EditInline if settings_focused => self.start_json_edit(),
+            Action::ImportMcp => self.start_mcp_import(),
+            Action::TestMcp => self.test_mcp_server(),
+            Action::RevealSecrets => self.toggle_reveal_secrets(),
+            Action::ShowValue if settings_focused => self.inspect_selected(),
+            Action::ScrollLeft => self.scroll_value(false),
+            Action::ScrollRight => self.scroll_value(true),
+            Action::Increment if settings_focused => self.step_number(true),
+            Action::Decrement if settings_focused => self.step_number(false),
+            Action::TestPermissions => self.start_permission_test(),
+            Action::FixType if settings_focused => self.fix_type(),
+            Action::MigrateAll => self.migrate_all(),
+            Action::RemoveShadowedRules => self.remove_shadowed_rules(),
+            Action::EditEnv => self.start_env_edit(),
+            Action::AddItem if settings_focused => self.add_array_item(),
+            Action::DeleteItem if settings_focused => self.delete_array_item(),
+            Action::MoveItemUp if settings_focused => self.move_array_item(true),
+            Action::MoveItemDown if settings_focused => self.move_array_item(false),
+            Action::SortRows if settings_focused => self.cycle_rule_sort(),
+            Action::ApplySort if settings_focused => self.apply_rule_sort(),
+            Action::Duplicate if settings_focused => self.duplicate_item(),
+            Action::Copy if settings_focused => self.start_copy(),
+            Action::Paste if settings_focused => self.paste(),
+            Action::Reset if settings_focused => self.reset_setting(),
+            Action::EditNote if settings_focused => self.start_edit_note(),
+            Action::ExpandTree if settings_focused => self.open_tree(),
+            Action::ToggleGroup if settings_focused => self.toggle_group(),
+            Action::ToggleFavorite if settings_focused => self.toggle_favorite(),
+            Action::OpenEditor
+            | Action::EditInline
+            | Action::AddItem
+            | Action::DeleteItem
+            | Action::MoveItemUp
+            | Action::MoveItemDown
+            | Action::SortRows
+            | Action::ApplySort
+            | Action::ShowValue
+            | Action::Duplicate
+            | Action::Copy
+            | Action::Paste
+            | Action::Reset
+            | Action::Increment
+            | Action::Decrement
+            | Action::FixType
+            | Action::EditNote
+            | Action::ExpandTree
+            | Action::ToggleGroup
+            | Action::ToggleFavorite => {}
+            Action::SwitchMcpPanel => self.switch_mcp_panel(),
+            Action::ToggleSidebar => self.toggle_sidebar(),
+            Action::NarrowSidebar => self.resize_sidebar(false),
+            Action::WidenSidebar => self.resize_sidebar(true),
+            Action::ToggleSplit => self.toggle_split(),
+            Action::CycleFilter => self.cycle_filter(),
+            Action::SwapPane => self.swap_split_pane(),
+            Action::ToggleHelp => self.show_help = !self.show_help,
+            Action::TogglePreview => self.show_preview = !self.show_preview,
+            Action::CommandPalette => self.start_palette(),
+            Action::RecordMacro => self.toggle_macro_recording(),
+            Action::ReplayMacro => return self.replay_macro(),
+            Action::EditFile => self.file_edit_requested = true,
+            Action::ToggleScope => self.toggle_scope(),
+            Action::EditAtSource => self.edit_at_source(),
+            Action::RestoreBackup => self.start_restore_backup(),
+            Action::Profiles => self.start_profile_picker(),
+            Action::PermissionPresets => self.start_preset_picker(),
+            Action::BrowseRegistry => self.start_registry(),
+            Action::AddServerRule => self.start_server_rule(),
+            Action::ShowTrash => self.start_trash(),
+            Action::ShowRecent => self.start_recent(),
+            Action::ExportRedacted => self.start_export(),
+            Action::GitDiff => self.show_git_diff(),
+            Action::GitCommit => self.start_git_commit(),
+            Action::RestoreLast => self.restore_last(),
+            Action::ResetSection => self.start_reset(ResetScope::Section(self.current_section())),
+            Action::ResetAll => self.start_reset(ResetScope::All),
+            Action::Search => self.start_search(),
+            Action::ShowMessages => self.show_messages(),
+            Action::CheckWithAmp => self.validate_with_amp(),
+            Action::SwitchFile => self.start_file_switch(),
+            Action::ShowDiff => self.show_diff(),
+            Action::Save => self.save(),
+        }
+        None
+    }
+
+    /// Starts recording a macro, or stops and keeps the one being recorded.
+    fn toggle_macro_recording(&mut self) {
+        let replay = self.keymap.label(Action::ReplayMacro);
+        self.status_message = Some(if self.macros.is_recording() {
+            match self.macros.stop() {
+                0 => "Stopped recording; the macro is empty.".to_string(),
+                n => format!("Recorded a macro of {n} action(s); press {replay} to replay it."),
+            }
+        } else {
+            self.macros.start();
+            format!(
+                "Recording a macro; press {} to stop.",
+                self.keymap.label(Action::RecordMacro)
+            )
+        });
+    }
+
+    /// Runs the recorded macro's actions in order. Replay stops early at an
+    /// action that opens a prompt, `$EDITOR`, or quits, as the keys that
+    /// followed it were typed into that.
+    fn replay_macro(&mut self) -> Option<EditorRequest> {
+        if self.macros.is_recording() {
+            self.status_message = Some(format!(
+                "Stop recording ({}) before replaying.",
+                self.keymap.label(Action::RecordMacro)
+            ));
+            return None;
+        }
+        let actions = self.macros.recorded().to_vec();
+        if actions.is_empty() {
+            self.status_message = Some(format!(
+                "No macro recorded; press {} to record one.",
+                self.keymap.label(Action::RecordMacro)
+            ));
+            return None;
+        }
+        for (i, &action) in actions.iter().enumerate() {
+            let request = self.dispatch(action);
+            if request.is_some() || self.is_editing() || self.should_quit {
+                if i + 1 < actions.len() {
+                    self.status_message = Some(format!(
+                        "Replay stopped after {} of {} actions at \"{}\".",
+                        i + 1,
+                        actions.len(),
+                        action.description()
+                    ));
+                }
+                return request;
+            }
+        }
+        self.status_message = Some(format!("Replayed {} action(s).", actions.len()));
+        None
+    }
+
+    /// Opens the command palette with every command listed.
+    pub fn start_palette(&mut self) {
+        self.edit_buffer.clear();
+        self.input_mode = InputMode::CommandPalette;
+        self.update_palette();
+    }
+
+    /// Re-matches the palette's commands against the current query.
+    pub fn update_palette(&mut self) {
+        let mut scored: Vec<(i64, PaletteCommand)> = PaletteCommand::all()
+            .into_iter()
+            .filter_map(|cmd| fuzzy::score(&self.edit_buffer, &cmd.label()).map(|s| (s, cmd)))
+            .collect();
+        if !self.edit_buffer.trim().is_empty() {
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        }
+        self.palette_results = scored.into_iter().map(|(_, cmd)| cmd).collect();
+        self.selected_palette_result = 0;
+    }
+
+    /// Moves palette selection up.
+    pub fn palette_select_up(&mut self) {
+        self.selected_palette_result = self.selected_palette_result.saturating_sub(1);
+    }
+
+    /// Moves palette selection down.
+    pub fn palette_select_down(&mut self) {
+        if self.selected_palette_result + 1 < self.palette_results.len() {
+            self.selected_palette_result += 1;
+        }
+    }
+
+    /// Closes the palette and carries out the selected command. Going to a
+    /// section happens here; an action is returned for the caller to run
+    /// as if its key had been pressed.
+    pub fn commit_palette(&mut self) -> Option<Action> {
+        let command = self
+            .palette_results
+            .get(self.selected_palette_result)
+            .copied();
+        self.cancel_edit();
+        match command? {
+            PaletteCommand::Run(action) => Some(action),
+            PaletteCommand::GoTo(section) => {
+                let index = Section::ALL.iter().position(|s| *s == section)?;
+                self.select_section(index);
+                self.focus = Focus::Settings;
+                None
+            }
+        }
+    }
+
+    /// The open files with unsaved changes: the one being edited, and the
+    /// other scope's when a project layer is loaded.
+    fn dirty_configs(&mut self) -> impl Iterator<Item = &mut Config> {
+        std::iter::once(&mut self.config)
+            .chain(self.other_scope_config.as_mut())
+            .filter(|c| c.is_dirty())
+    }
+
+    /// Quits, unless there are unsaved changes: then they're saved first
+    /// with `--autosave`, or the user is asked what to do with them.
+    pub fn request_quit(&mut self) {
+        if self.dirty_configs().next().is_none() {
+            self.should_quit = true;
+        } else if self.autosave {
+            self.save_and_quit();
+        } else {
+            self.input_mode = InputMode::ConfirmQuit;
+        }
+    }
+
+    /// Saves every file with unsaved changes and quits. A failed save keeps
+    /// volt open so nothing is lost.
+    pub fn save_and_quit(&mut self) {
+        self.input_mode = InputMode::Normal;
+        let failed = self.save_dirty();
+        if failed.is_empty() {
+            self.should_quit = true;
+        } else {
+            self.status_message = Some(format!("Save failed, not quitting: {}", failed.join("; ")));
+        }
+    }
+
+    /// Notices new changes to the open files and, with autosave on,
+    /// schedules a save `AUTOSAVE_DELAY` after the latest. Called once per
+    /// frame, like `log_status`.
+    pub fn note_changes(&mut self, now: Instant) {
+        let revision =
+            self.config.revision() + self.other_scope_config.as_ref().map_or(0, Config::revision);
+        if revision == self.seen_revision {
+            return;
+        }
+        self.seen_revision = revision;
+        let unsaved = self.dirty_configs().any(|c| !c.is_read_only());
+        self.autosave_due = (self.autosave && unsaved).then(|| now + AUTOSAVE_DELAY);
+    }
+
+    /// Whether changes are waiting to be autosaved.
+    pub fn autosave_pending(&self) -> bool {
+        self.autosave_due.is_some()
+    }
+
+    /// Writes a pending autosave now. Failures are reported and not retried
+    /// until the next change.
+    pub fn flush_autosave(&mut self) {
+        if self.autosave_due.take().is_none() {
+            return;
+        }
+        let failed = self.save_dirty();
+        if !failed.is_empty() {
+            self.status_message = Some(format!("Autosave failed: {}", failed.join("; ")));
+        }
+    }
+
+    /// Saves every file with unsaved changes, returning what went wrong
+    /// with each that couldn't be saved.
+    fn save_dirty(&mut self) -> Vec<String> {
+        let mut saved = Vec::new();
+        let failed = self
+            .dirty_configs()
+            .filter_map(|c| {
+                let keys = c.unsaved_keys();
+                match c.save() {
+                    Ok(()) => {
+                        saved.extend(keys);
+                        None
+                    }
+                    Err(e) => Some(format!("{}: {e:#}", c.path().display())),
+                }
+            })
+            .collect();
+        self.note_saved(saved, SystemTime::now());
+        self.refresh_git();
+        failed
+    }
+
+    /// Quits without saving.
+    pub fn discard_and_quit(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.should_quit = true;
+    }
+
+    /// Saves the configuration to disk.
+    pub fn save(&mut self) {
+        let keys = self.config.unsaved_keys();
+        match self.config.save() {
+            Ok(()) => {
+                self.note_saved(keys, SystemTime::now());
+                self.refresh_git();
+                self.status_message = Some(match self.git_status {
+                    Some(status) if status.is_committable() => format!(
+                        "Saved! {} to commit it to git.",
+                        self.keymap.label(Action::GitCommit)
+                    ),
+                    _ => "Saved!".to_string(),
+                });
+            }
+            Err(e) => self.status_message = Some(format!("Save failed: {e}")),
+        }
+    }
+}
+
+/// Summarizes what needs attention after loading a file: JSON5 syntax that
+/// saving will normalize, known settings stored with the wrong type, and
+/// deprecated ones.
+fn load_notice(config: &Config) -> Option<String> {
+    let mut notices = Vec::new();
+    if config.is_normalized() {
+        notices.push("Read JSON5 syntax; saving writes plain JSON".to_string());
+    }
+    let keys = config.type_mismatches();
+    match keys.as_slice() {
+        [] => {}
+        [key] => notices.push(format!("{key} has the wrong type (marked ⚠)")),
+        _ => notices.push(format!(
+            "{} settings have the wrong type (marked ⚠): {}",
+            keys.len(),
+            keys.join(", ")
+        )),
+    }
+    let deprecated = config.deprecated_keys();
+    match deprecated.as_slice() {
+        [] => {}
+        [key] => notices.push(format!("{key} is deprecated (marked ⚠)")),
+        _ => notices.push(format!(
+            "{} settings are deprecated (marked ⚠): {}",
+            deprecated.len(),
+            deprecated.join(", ")
+        )),
+    }
+    (!notices.is_empty()).then(|| notices.join("; "))
+}
+
+/// Names a JSON value's type with its article, e.g. "a string".
+fn json_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Compact JSON for a message, cut short if it's long.
+fn format_json_short(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > 40 {
+        format!("{}…", text.chars().take(39).collect::<String>())
+    } else {
+        text
+    }
+}
+
+/// Returns the scroll offset that keeps `selected` within a viewport of
+/// `visible` rows, moving as little as possible from `offset`.
+fn scroll_offset(offset: usize, selected: usize, count: usize, visible: u16) -> usize {
+    let visible = usize::from(visible).max(1);
+    let offset = offset.min(count.saturating_sub(visible));
+    if selected < offset {
+        selected
+    } else if selected >= offset + visible {
+        selected + 1 - visible
+    } else {
+        offset
+    }
+}
+
+/// An entry in the settings list — either a known setting or an unknown key.
+#[derive(Debug, Clone)]
+pub enum SettingEntry {
+    Known(Box<settings::SettingDef>),
+    Unknown(String),
+}
+
+/// Which of a section's keys are listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingsFilter {
+    #[default]
+    All,
+    /// Keys set in the file, whatever their value.
+    Modified,
+    /// Keys left out of the file, so their defaults apply.
+    Defaults,
+}
+
+impl SettingsFilter {
+    /// The filter `f` moves on to.
+    fn next(self) -> Self {
+        match self {
+            SettingsFilter::All => SettingsFilter::Modified,
+            SettingsFilter::Modified => SettingsFilter::Defaults,
+            SettingsFilter::Defaults => SettingsFilter::All,
+        }
+    }
+
+    fn keeps(self, state: KeyState) -> bool {
+        match self {
+            SettingsFilter::All => true,
+            SettingsFilter::Modified => state != KeyState::Unset,
+            SettingsFilter::Defaults => state == KeyState::Unset,
+        }
+    }
+
+    /// Names the filter in a panel title; `None` for no filter.
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            SettingsFilter::All => None,
+            SettingsFilter::Modified => Some("modified only"),
+            SettingsFilter::Defaults => Some("defaults only"),
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            SettingsFilter::All => "all settings",
+            SettingsFilter::Modified => "only settings set in the file",
+            SettingsFilter::Defaults => "only settings at their defaults",
+        }
+    }
+}
+
+/// Whether a key is set in the file, and if so whether that changes anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    /// Not in the file; the default applies.
+    Unset,
+    /// In the file with the same value as the default.
+    SetToDefault,
+    /// In the file with a value that differs from the default, or a key
+    /// with no default.
+    Changed,
+}
+
+/// How many expanded group headers sit above or at the setting at `index`,
+/// each taking a row of its own.
+pub fn expanded_headers_through(headers: &[GroupHeader], index: usize) -> usize {
+    headers
+        .iter()
+        .filter(|h| !h.collapsed && h.first <= index)
+        .count()
+}
+
+/// The key a settings entry stands for.
+fn entry_key(entry: &SettingEntry) -> &str {
+    match entry {
+        SettingEntry::Known(def) => def.key,
+        SettingEntry::Unknown(key) => key,
+    }
+}
+
+/// Reads a value typed into the object form: strings as typed, numbers
+/// parsed, and anything else as JSON checked against the field's type.
+fn parse_field_value(field: Option<&FieldDef>, text: &str) -> Result<Value, String> {
+    let value = match field.map(|f| f.field_type) {
+        Some(SettingType::String) => return Ok(Value::String(text.to_string())),
+        Some(SettingType::Number) => {
+            return settings::parse_number(text)
+                .map(Value::Number)
+                .ok_or_else(|| format!("Can't turn \"{}\" into a number", text.trim()));
+        }
+        _ => serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {e}"))?,
+    };
+    match field {
+        Some(field) if !field.accepts(&value) => Err(format!(
+            "{} must be {}",
+            field.name,
+            field.field_type.label()
+        )),
+        _ => Ok(value),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffKind;
+    use crate::events::AppEvent;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn test_app() -> App {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"{{
+    "amp.showCosts": true,
+    "amp.notifications.enabled": false,
+    "amp.experimental.labs": ["bombadil"]
+}}"#
+        )
+        .unwrap();
+        let config = Config::load(f.path()).unwrap();
+        App::new(config)
+    }
+
+    #[test]
+    fn test_quit_asks_about_unsaved_changes() {
+        let mut app = test_app();
+        app.request_quit();
+        assert!(app.should_quit);
+
+        let mut app = test_app();
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.request_quit();
+        assert_eq!(app.input_mode, InputMode::ConfirmQuit);
+        assert!(!app.should_quit);
+        app.cancel_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        app.discard_and_quit();
+        assert!(app.should_quit);
+        assert!(app.config.is_dirty());
+
+        // Changes in the other scope count too.
+        let mut app = test_app();
+        app.attach_project(test_app().config);
+        app.toggle_scope();
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.toggle_scope();
+        app.request_quit();
+        assert_eq!(app.input_mode, InputMode::ConfirmQuit);
+        app.save_and_quit();
+        assert!(app.should_quit);
+        assert!(!app.other_scope_config.as_ref().unwrap().is_dirty());
+    }
+
+    #[test]
+    fn test_autosave_on_quit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, "{}").unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.autosave = true;
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.config.set_read_only(true);
+        app.request_quit();
+        assert!(!app.should_quit);
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Save failed, not quitting"));
+
+        app.config.set_read_only(false);
+        app.request_quit();
+        assert!(app.should_quit);
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.get("amp.showCosts"), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_autosave_waits_for_changes_to_settle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, "{}").unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        let start = Instant::now();
+        app.note_changes(start);
+        assert!(!app.autosave_pending());
+
+        app.autosave = true;
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.note_changes(start);
+        assert!(app.autosave_pending());
+        // Another change pushes the save back.
+        app.config.set("amp.todos.enabled", Value::Bool(false));
+        app.note_changes(start + AUTOSAVE_DELAY / 2);
+        app.tick(start + AUTOSAVE_DELAY);
+        assert!(app.config.is_dirty());
+
+        // Nor does it save while an overlay is open.
+        app.start_search();
+        app.tick(start + AUTOSAVE_DELAY * 2);
+        assert!(app.config.is_dirty());
+        app.cancel_edit();
+        app.tick(start + AUTOSAVE_DELAY * 2);
+        assert!(!app.config.is_dirty());
+        assert!(!app.autosave_pending());
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.get("amp.todos.enabled"), Value::Bool(false));
+
+        // Saving doesn't count as a change.
+        app.note_changes(start + AUTOSAVE_DELAY * 3);
+        assert!(!app.autosave_pending());
+    }
+
+    #[test]
+    fn test_cycle_filter() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        let all = app.current_settings().len();
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| entry_key(e) == "amp.notifications.enabled")
+            .unwrap();
+        app.cycle_filter();
+        assert_eq!(app.filter(Section::General), SettingsFilter::Modified);
+        let keys: Vec<String> = app
+            .current_settings()
+            .iter()
+            .map(|e| entry_key(e).to_string())
+            .collect();
+        assert_eq!(keys, ["amp.showCosts", "amp.notifications.enabled"]);
+        assert_eq!(
+            app.selected_key().as_deref(),
+            Some("amp.notifications.enabled")
+        );
+        assert_eq!(
+            app.key_state(&app.current_settings()[0]),
+            KeyState::SetToDefault
+        );
+        assert_eq!(app.key_state(&app.current_settings()[1]), KeyState::Changed);
+
+        // Jumping to a hidden key turns the filter off.
+        app.start_search();
+        app.edit_buffer.set("amp.skills.path");
+        app.update_search();
+        app.commit_search();
+        assert_eq!(app.filter(Section::General), SettingsFilter::All);
+        assert_eq!(app.current_settings().len(), all);
+        assert_eq!(app.selected_key().as_deref(), Some("amp.skills.path"));
+
+        app.cycle_filter();
+        app.cycle_filter();
+        assert_eq!(app.filter(Section::General), SettingsFilter::Defaults);
+        assert_eq!(app.current_settings().len(), all - 2);
+        app.cycle_filter();
+        assert_eq!(app.filter(Section::General), SettingsFilter::All);
+
+        app.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == Section::Permissions)
+            .unwrap();
+        app.cycle_filter();
+        assert_eq!(app.filter(Section::Permissions), SettingsFilter::All);
+    }
+
+    #[test]
+    fn test_initial_state() {
+        let app = test_app();
+        assert_eq!(app.current_section(), Section::General);
+        assert_eq!(app.selected_setting, 0);
+        assert_eq!(app.focus, Focus::Sidebar);
+        assert!(!app.should_quit);
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
+
+    fn layered_app() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join("settings.json");
+        let project = dir.path().join(".amp").join("settings.json");
+        std::fs::create_dir_all(project.parent().unwrap()).unwrap();
+        std::fs::write(
+            &global,
+            r#"{"amp.showCosts": false, "amp.tools.stopTimeout": 60}"#,
+        )
+        .unwrap();
+        std::fs::write(&project, r#"{"amp.tools.stopTimeout": 120}"#).unwrap();
+
+        let mut app = App::new(Config::load(&global).unwrap());
+        app.attach_project(Config::load(&project).unwrap());
+        (dir, app)
+    }
+
+    #[test]
+    fn test_toggle_scope_without_project() {
+        let mut app = test_app();
+        app.toggle_scope();
+        assert_eq!(app.scope, Scope::Global);
+        assert!(app.status_message.unwrap().contains("No project settings"));
+    }
+
+    #[test]
+    fn test_key_scope_and_effective_value() {
+        let (_dir, mut app) = layered_app();
+        assert_eq!(app.key_scope("amp.tools.stopTimeout"), Some(Scope::Project));
+        assert_eq!(app.key_scope("amp.showCosts"), Some(Scope::Global));
+        assert_eq!(app.key_scope("amp.notifications.enabled"), None);
+        assert_eq!(app.effective_value("amp.tools.stopTimeout"), 60);
+
+        app.toggle_scope();
+        assert_eq!(app.scope, Scope::Project);
+        assert!(app.is_settings_file());
+        assert_eq!(app.key_scope("amp.tools.stopTimeout"), Some(Scope::Project));
+        assert_eq!(app.effective_value("amp.tools.stopTimeout"), 120);
+        assert_eq!(app.effective_value("amp.showCosts"), Value::Bool(false));
+        assert_eq!(
+            app.effective_value("amp.notifications.enabled"),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_provenance_and_edit_at_source() {
+        let (dir, mut app) = layered_app();
+        let project = dir.path().join(".amp").join("settings.json");
+        assert_eq!(
+            app.provenance("amp.tools.stopTimeout").unwrap(),
+            format!(
+                "the project file, {} (g l to edit it there)",
+                project.display()
+            )
+        );
+        assert_eq!(
+            app.provenance("amp.showCosts").unwrap(),
+            format!(
+                "the global file, {}",
+                dir.path().join("settings.json").display()
+            )
+        );
+        assert_eq!(
+            app.provenance("amp.notifications.enabled").unwrap(),
+            "the default; neither file sets it"
+        );
+
+        assert!(app.go_to_key("amp.tools.stopTimeout"));
+        app.edit_at_source();
+        assert_eq!(app.scope, Scope::Project);
+        assert_eq!(app.selected_key().as_deref(), Some("amp.tools.stopTimeout"));
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Editing amp.tools.stopTimeout in the project settings")
+        );
+        app.edit_at_source();
+        assert_eq!(app.scope, Scope::Project);
+
+        assert!(test_app().provenance("amp.showCosts").is_none());
+    }
+
+    #[test]
+    fn test_toggle_scope_keeps_each_layers_edits() {
+        let (_dir, mut app) = layered_app();
+        app.config.set("amp.showCosts", Value::Bool(true));
+        app.toggle_scope();
+        assert!(!app.config.is_dirty());
+        app.config.remove("amp.tools.stopTimeout");
+        app.toggle_scope();
+        assert_eq!(app.scope, Scope::Global);
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(true));
+        assert_eq!(app.key_scope("amp.tools.stopTimeout"), Some(Scope::Global));
+    }
+
+    #[test]
+    fn test_file_switch_refused_in_project_scope() {
+        let (_dir, mut app) = layered_app();
+        app.toggle_scope();
+        app.start_file_switch();
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
+
+    /// A layout like an 80x24 terminal: sidebar on the left, one pane on the right.
+    fn mouse_layout() -> ScreenLayout {
+        ScreenLayout {
+            sidebar: Rect::new(0, 0, 18, 23),
+            sidebar_stacked: false,
+            pane: Rect::new(18, 0, 62, 23),
+            other_pane: None,
+            list: ListArea {
+                area: Rect::new(18, 0, 62, 23),
+                first_row: 1,
+                visible_rows: 21,
+            },
+            mcp_permissions: None,
+            value_overflow: 0,
+        }
+    }
+
+    #[test]
+    fn test_value_scrolls_sideways_within_its_overflow() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        let key = app.selected_key().unwrap();
+        app.layout.value_overflow = 10;
+        app.scroll_value(true);
+        assert_eq!(app.value_scroll_of(&key), 8);
+        app.scroll_value(true);
+        assert_eq!(app.value_scroll_of(&key), 10);
+        app.scroll_value(false);
+        assert_eq!(app.value_scroll_of(&key), 2);
+
+        // Another row shows its value from the start.
+        app.move_down();
+        let other = app.selected_key().unwrap();
+        assert_eq!(app.value_scroll_of(&other), 0);
+        app.move_up();
+        assert_eq!(app.value_scroll_of(&key), 2);
+    }
+
+    #[test]
+    fn test_inspect_shows_the_selected_rule() {
+        let mut app = test_app_with_permissions();
+        app.focus = Focus::Settings;
+        app.selected_setting = 1;
+        app.inspect_selected();
+        let report = app.report.as_ref().unwrap();
+        assert_eq!(report.title, " amp.permissions[1] ");
+        assert!(report.lines.iter().any(|l| l.contains("\"Read\"")));
+    }
+
+    #[test]
+    fn test_click_sidebar_selects_section() {
+        let mut app = test_app();
+        app.layout = mouse_layout();
+        let t = Instant::now();
+        app.click(3, 4, t);
+        assert_eq!(app.current_section(), Section::Tools);
+        assert_eq!(app.focus, Focus::Sidebar);
+
+        // Clicking the border or below the last section does nothing.
+        app.click(3, 0, t + Duration::from_secs(1));
+        app.click(3, 15, t + Duration::from_secs(2));
+        assert_eq!(app.current_section(), Section::Tools);
+
+        // Double-clicking moves focus into the settings panel.
+        app.click(3, 2, t + Duration::from_secs(3));
+        app.click(3, 2, t + Duration::from_millis(3100));
+        assert_eq!(app.current_section(), Section::General);
+        assert_eq!(app.focus, Focus::Settings);
+    }
+
+    #[test]
+    fn test_click_row_and_double_click_activates() {
+        let mut app = test_app();
+        app.layout = mouse_layout();
+        let t = Instant::now();
+        // Row 1 is the first setting: amp.showCosts (currently true).
+        app.click(30, 1, t);
+        assert_eq!(app.focus, Focus::Settings);
+        assert_eq!(app.selected_setting, 0);
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(true));
+
+        app.click(30, 1, t + Duration::from_millis(200));
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+
+        // Slow second click is just another single click.
+        app.click(30, 1, t + Duration::from_secs(2));
+        app.click(30, 1, t + Duration::from_secs(4));
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_click_mcp_permissions_subpanel() {
+        let mut app = test_app();
+        app.config.set(
+            "amp.mcpPermissions",
+            serde_json::json!([{"matches": {"command": "x"}, "action": "allow"}, {"matches": {"command": "y"}, "action": "reject"}]),
+        );
+        app.selected_section = 4;
+        app.layout = ScreenLayout {
+            mcp_permissions: Some(ListArea {
+                area: Rect::new(18, 11, 62, 12),
+                first_row: 13,
+                visible_rows: 9,
+            }),
+            ..mouse_layout()
+        };
+        app.click(30, 14, Instant::now());
+        assert_eq!(app.mcp_focus, McpFocus::Permissions);
+        assert_eq!(app.selected_mcp_permission, 1);
+
+        app.click(30, 5, Instant::now() + Duration::from_secs(1));
+        assert_eq!(app.mcp_focus, McpFocus::Configs);
+    }
+
+    #[test]
+    fn test_scroll_moves_selection_under_cursor() {
+        let mut app = test_app();
+        app.layout = mouse_layout();
+        app.scroll(30, 5, true);
+        assert_eq!(app.focus, Focus::Settings);
+        assert_eq!(app.selected_setting, 1);
+        app.scroll(3, 5, true);
+        assert_eq!(app.focus, Focus::Sidebar);
+        assert_eq!(app.current_section(), Section::Permissions);
+        app.scroll(200, 200, true);
+        assert_eq!(app.current_section(), Section::Permissions);
+    }
+
+    #[test]
+    fn test_scroll_offset() {
+        // Already visible: unchanged.
+        assert_eq!(scroll_offset(0, 3, 20, 5), 0);
+        // Below the viewport: scroll just enough.
+        assert_eq!(scroll_offset(0, 7, 20, 5), 3);
+        // Above the viewport: scroll up to it.
+        assert_eq!(scroll_offset(10, 4, 20, 5), 4);
+        // List shrank: offset clamped.
+        assert_eq!(scroll_offset(10, 2, 3, 5), 0);
+    }
+
+    #[test]
+    fn test_paging_and_offsets() {
+        let mut app = test_app();
+        let rules: Vec<Value> = (0..30)
+            .map(|i| serde_json::json!({"tool": format!("t{i}"), "action": "allow"}))
+            .collect();
+        app.config.set("amp.permissions", Value::Array(rules));
+        app.selected_section = 2;
+        app.focus = Focus::Settings;
+        app.layout = mouse_layout();
+        app.layout.list.visible_rows = 10;
+
+        app.page_down();
+        assert_eq!(app.selected_setting, 10);
+        app.keep_selection_visible();
+        assert_eq!(app.setting_offset, 1);
+
+        app.select_last();
+        assert_eq!(app.selected_setting, 29);
+        app.keep_selection_visible();
+        assert_eq!(app.setting_offset, 20);
+
+        // Clicks account for the scroll offset.
+        app.click(30, 1, Instant::now());
+        assert_eq!(app.selected_setting, 20);
+
+        app.page_up();
+        assert_eq!(app.selected_setting, 10);
+        app.select_first();
+        assert_eq!(app.selected_setting, 0);
+        app.keep_selection_visible();
+        assert_eq!(app.setting_offset, 0);
+    }
+
+    #[test]
+    fn test_home_end_in_sidebar() {
+        let mut app = test_app();
+        app.select_last();
+        assert_eq!(app.current_section(), Section::Advanced);
+        app.select_first();
+        assert_eq!(app.current_section(), Section::Favorites);
+    }
+
+    #[test]
+    fn test_external_change_reloads_when_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"amp.showCosts": true}"#).unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+
+        app.check_external_change();
+        assert!(app.status_message.is_none());
+
+        std::fs::write(&path, r#"{"amp.showCosts": false}"#).unwrap();
+        app.check_external_change();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_external_change_prompts_when_dirty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"amp.showCosts": true}"#).unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.config
+            .set("amp.notifications.enabled", Value::Bool(false));
+
+        std::fs::write(&path, r#"{"amp.showCosts": false}"#).unwrap();
+        app.check_external_change();
+        assert_eq!(app.input_mode, InputMode::ResolvingExternalChange);
+
+        app.merge_from_disk();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+        assert_eq!(
+            app.config.get("amp.notifications.enabled"),
+            Value::Bool(false)
+        );
+
+        std::fs::write(&path, r#"{"amp.showCosts": true, "amp.x": 1}"#).unwrap();
+        app.check_external_change();
+        app.keep_local_changes();
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+        app.check_external_change();
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        std::fs::write(&path, "{}").unwrap();
+        app.check_external_change();
+        app.reload_from_disk();
+        assert!(!app.config.is_dirty());
+        assert_eq!(app.config.explicit_count(), 0);
+    }
+
+    #[test]
+    fn test_reset_section_and_all() {
+        let mut app = test_app();
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.config.set("amp.terminal.theme", "dark".into());
+        app.config.set("amp.tools.stopTimeout", Value::from(60));
+        app.config.set("my.custom", Value::from(1));
+
+        app.start_reset(ResetScope::Section(Section::General));
+        assert_eq!(app.input_mode, InputMode::ConfirmReset);
+        app.confirm_reset(false);
+        assert_eq!(app.config.get_raw("amp.showCosts"), None);
+        assert_eq!(app.config.get_raw("amp.terminal.theme"), None);
+        assert_eq!(app.config.get("amp.tools.stopTimeout"), Value::from(60));
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Reset 3 setting(s) in General to defaults (u restores them one at a time)")
+        );
+        app.start_reset(ResetScope::Section(Section::General));
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        app.start_reset(ResetScope::All);
+        app.confirm_reset(false);
+        assert_eq!(
+            app.config.unknown_keys(),
+            ["amp.experimental.labs", "my.custom"]
+        );
+        assert_eq!(app.config.explicit_count(), 2);
+        app.start_reset(ResetScope::All);
+        app.confirm_reset(true);
+        assert_eq!(app.config.explicit_count(), 0);
+
+        app.restore_last();
+        assert_eq!(app.config.get("my.custom"), Value::from(1));
+    }
+
+    #[test]
+    fn test_export_redacted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = test_app();
+        app.config
+            .set("amp.bitbucketToken", Value::String("secret".into()));
+        app.dispatch(Action::ExportRedacted);
+        assert_eq!(app.input_mode, InputMode::EnteringExportPath);
+        assert_eq!(app.edit_buffer, "settings.redacted.json");
+
+        let settings_path = app.config.path().display().to_string();
+        app.edit_buffer.set(&settings_path);
+        app.commit_export_path();
+        assert_eq!(app.input_mode, InputMode::EnteringExportPath);
+
+        let path = dir.path().join("shared.json");
+        app.edit_buffer.set(path.display().to_string());
+        app.commit_export_path();
+        let exported: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(exported["amp.bitbucketToken"], "<redacted>");
+        assert_eq!(exported["amp.showCosts"], true);
+    }
+
+    #[test]
+    fn test_git_commit() {
+        let Some(repo) = git::test_repo() else {
+            return;
+        };
+        let path = repo.path().join("settings.json");
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.refresh_git();
+        assert_eq!(app.git_status, None);
+
+        app.save();
+        assert_eq!(app.git_status, Some(git::Status::Untracked));
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Saved! g C to commit it to git.")
+        );
+        app.dispatch(Action::GitDiff);
+        assert_eq!(app.input_mode, InputMode::ViewingDiff);
+        assert!(app.diff_from_head);
+        app.cancel_edit();
+
+        app.config.set("amp.showCosts", Value::Bool(true));
+        app.dispatch(Action::GitCommit);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.status_message.take().unwrap().starts_with("Save first"));
+        app.save();
+
+        app.config.set_read_only(true);
+        app.dispatch(Action::GitCommit);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.status_message.take().as_deref(),
+            Some("Read-only: changes are disabled")
+        );
+        // Nor from the HEAD diff view's `c`.
+        app.start_git_commit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.git_status, Some(git::Status::Untracked));
+        app.config.set_read_only(false);
+
+        app.dispatch(Action::GitCommit);
+        assert_eq!(app.input_mode, InputMode::EnteringCommitMessage);
+        assert_eq!(app.edit_buffer, "Update settings.json");
+        app.commit_git_message();
+        assert!(app
+            .status_message
+            .take()
+            .unwrap()
+            .ends_with("Update settings.json"));
+        assert_eq!(app.git_status, Some(git::Status::Clean));
+        app.dispatch(Action::GitDiff);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("No changes since the last commit.")
+        );
+    }
+
+    #[test]
+    fn test_recent_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.start_recent();
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        app.modified
+            .insert("amp.tools.stopTimeout".into(), yesterday);
+        app.config.set("amp.showCosts", Value::Bool(false));
+        assert_eq!(
+            app.recent_changes(),
+            [
+                ("amp.showCosts".to_string(), None),
+                ("amp.tools.stopTimeout".to_string(), Some(yesterday)),
+            ]
+        );
+
+        app.save();
+        let changes = app.recent_changes();
+        assert_eq!(changes[0].0, "amp.showCosts");
+        assert!(changes[0].1.is_some_and(|at| at > yesterday));
+
+        // Times are kept to the second across launches.
+        let mut restored = App::new(Config::load(&path).unwrap());
+        restored.restore_history(&app.session());
+        assert_eq!(restored.recent_changes().len(), 2);
+        assert_eq!(
+            restored.modified["amp.tools.stopTimeout"],
+            UNIX_EPOCH
+                + Duration::from_secs(yesterday.duration_since(UNIX_EPOCH).unwrap().as_secs())
+        );
+
+        app.start_recent();
+        assert_eq!(app.input_mode, InputMode::ViewingRecent);
+        app.recent_select_down();
+        app.recent_select_down();
+        assert_eq!(app.selected_recent, 1);
+        app.commit_recent();
+        assert_eq!(app.current_section(), Section::Tools);
+        assert_eq!(app.selected_key().as_deref(), Some("amp.tools.stopTimeout"));
+    }
+
+    #[test]
+    fn test_favorites() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        app.dispatch(Action::ToggleFavorite);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Pinned amp.showCosts to Favorites")
+        );
+        app.go_to_section(Section::Permissions);
+        app.dispatch(Action::ToggleFavorite);
+        assert_eq!(app.favorites, ["amp.showCosts", "amp.permissions"]);
+
+        // Searching finds keys where they live, not in Favorites.
+        assert!(app.go_to_key("amp.showCosts"));
+        assert_eq!(app.current_section(), Section::General);
+
+        app.go_to_section(Section::Favorites);
+        let keys: Vec<_> = app
+            .current_settings()
+            .iter()
+            .map(|e| entry_key(e).to_string())
+            .collect();
+        assert_eq!(keys, app.favorites);
+        app.activate_setting();
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+        // amp.permissions has a panel of its own to edit it in.
+        app.go_to_section(Section::Favorites);
+        app.selected_setting = 1;
+        app.activate_setting();
+        assert_eq!(app.current_section(), Section::Permissions);
+
+        let mut restored = test_app();
+        restored.restore_session(&app.session());
+        assert_eq!(restored.favorites, app.favorites);
+
+        app.go_to_section(Section::Favorites);
+        app.selected_setting = 1;
+        app.dispatch(Action::ToggleFavorite);
+        assert_eq!(app.favorites, ["amp.showCosts"]);
+        assert_eq!(app.selected_setting, 0);
+    }
+
+    #[test]
+    fn test_general_groups_collapse() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        app.layout = mouse_layout();
+        let headers = app.group_headers(Section::General);
+        let names: Vec<_> = headers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Anthropic",
+                "Fuzzy",
+                "Git",
+                "Internal",
+                "Notifications",
+                "Skills",
+                "Tab",
+                "Terminal",
+                "Updates"
+            ]
+        );
+        // Keys without a group come first; clicking a header selects its
+        // first setting.
+        assert_eq!(headers[0].first, 3);
+        app.click(30, 4, Instant::now());
+        assert_eq!(
+            app.selected_key().as_deref(),
+            Some("amp.anthropic.thinking.enabled")
+        );
+
+        let count = app.current_settings().len();
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| entry_key(e) == "amp.git.commit.coauthor.enabled")
+            .unwrap();
+        app.dispatch(Action::ToggleGroup);
+        assert_eq!(app.current_settings().len(), count - 1);
+        assert_eq!(app.selected_collapsed_group().as_deref(), Some("Git"));
+        app.dispatch(Action::Reset);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Expand Git with z first.")
+        );
+        assert_eq!(app.session().collapsed_groups, ["Git"]);
+
+        let mut restored = test_app();
+        restored.restore_session(&app.session());
+        assert!(restored.collapsed_groups.contains("Git"));
+
+        app.dispatch(Action::Activate);
+        assert!(app.collapsed_groups.is_empty());
+        assert_eq!(
+            app.selected_key().as_deref(),
+            Some("amp.git.commit.ampThread.enabled")
+        );
+
+        // Jumping to a key in a collapsed group expands it.
+        app.dispatch(Action::ToggleGroup);
+        app.start_search();
+        app.edit_buffer.set("coauthor");
+        app.update_search();
+        app.commit_search();
+        assert!(app.collapsed_groups.is_empty());
+        assert_eq!(
+            app.selected_key().as_deref(),
+            Some("amp.git.commit.coauthor.enabled")
+        );
+    }
+
+    #[test]
+    fn test_trash_restores_removals() {
+        let mut app = test_app();
+        app.config
+            .set("amp.mcpServers", serde_json::json!({"a": {"command": "a"}}));
+        app.config.set("amp.showCosts", Value::Bool(false));
+
+        app.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == Section::Mcps)
+            .unwrap();
+        app.delete_array_item();
+        assert_eq!(app.config.get("amp.mcpServers"), serde_json::json!({}));
+        app.selected_section = 1;
+        app.go_to_key("amp.showCosts");
+        app.reset_setting();
+        assert_eq!(app.config.get_raw("amp.showCosts"), None);
+        // Resetting an unset key has nothing to keep.
+        app.reset_setting();
+        assert_eq!(app.trash.len(), 2);
+
+        app.restore_last();
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Restored amp.showCosts")
+        );
+
+        app.start_trash();
+        assert_eq!(app.input_mode, InputMode::ViewingTrash);
+        app.commit_trash_restore();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.config.get("amp.mcpServers"),
+            serde_json::json!({"a": {"command": "a"}})
+        );
+        assert!(app.trash.is_empty());
+        app.restore_last();
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Nothing was deleted this session.")
+        );
+    }
+
+    #[test]
+    fn test_merge_view_picks_per_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"amp.showCosts": true, "amp.tools.stopTimeout": 60, "amp.x": 1}"#,
+        )
+        .unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.config.set("amp.tools.stopTimeout", Value::from(90));
+        app.config.set("amp.x", Value::from(2));
+
+        std::fs::write(&path, r#"{"amp.tools.stopTimeout": 120, "amp.x": 3}"#).unwrap();
+        app.check_external_change();
+        app.merge_from_disk();
+        assert_eq!(app.input_mode, InputMode::ResolvingConflicts);
+        let keys: Vec<&str> = app
+            .merge_conflicts
+            .iter()
+            .map(|(c, _)| c.key.as_str())
+            .collect();
+        assert_eq!(keys, ["amp.showCosts", "amp.tools.stopTimeout", "amp.x"]);
+        assert_eq!(app.merge_conflicts[0].0.theirs, None);
+
+        app.pick_conflict_side(MergeSide::Theirs);
+        app.conflict_select_down();
+        app.conflict_select_down();
+        app.pick_conflict_side(MergeSide::Theirs);
+        app.conflict_select_down();
+        app.commit_merge();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.config.get_raw("amp.showCosts"), None);
+        assert_eq!(app.config.get("amp.tools.stopTimeout"), Value::from(90));
+        assert_eq!(app.config.get("amp.x"), Value::from(3));
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some(
+                "Merged changes from disk; took the file's values for amp.showCosts, amp.x; \
+                 kept local values for amp.tools.stopTimeout — Ctrl+S to save"
+            )
+        );
+        assert!(app.config.is_dirty());
+    }
+
+    #[test]
+    fn test_status_messages_are_logged_once() {
+        let mut app = test_app();
+        app.status_message = Some("Saved".into());
+        app.log_status();
+        app.log_status();
+        app.status_message = None;
+        app.log_status();
+        app.status_message = Some("Save failed: disk full".into());
+        app.log_status();
+        app.status_message = Some("Saved".into());
+        app.log_status();
+        let texts: Vec<&str> = app
+            .messages
+            .newest_first()
+            .map(|m| m.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["Saved", "Save failed: disk full", "Saved"]);
+
+        app.show_messages();
+        assert_eq!(app.input_mode, InputMode::ViewingMessages);
+        app.messages_scroll_down();
+        app.messages_scroll_down();
+        app.messages_scroll_down();
+        assert_eq!(app.messages_scroll, 2);
+        app.cancel_edit();
+        assert_eq!(app.messages_scroll, 0);
+    }
+
+    #[test]
+    fn test_show_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, "{\n  \"amp.showCosts\": false\n}\n").unwrap();
+
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.show_diff();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.status_message.as_deref(), Some("No unsaved changes."));
+
+        app.config.set("amp.showCosts", Value::Bool(true));
+        app.show_diff();
+        assert_eq!(app.input_mode, InputMode::ViewingDiff);
+        let changed: Vec<(DiffKind, &str)> = app
+            .diff
+            .iter()
+            .filter(|l| l.kind != DiffKind::Same)
+            .map(|l| (l.kind, l.text.as_str()))
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                (DiffKind::Removed, "  \"amp.showCosts\": false"),
+                (DiffKind::Added, "  \"amp.showCosts\": true")
+            ]
+        );
+
+        app.diff_scroll_down();
+        assert_eq!(app.diff_scroll, 1);
+        app.cancel_edit();
+        assert!(app.diff.is_empty());
+        assert_eq!(app.diff_scroll, 0);
+    }
+
+    #[test]
+    fn test_restore_backup_picker() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"amp.showCosts": false}"#).unwrap();
+
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.start_restore_backup();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.status_message.as_deref(), Some("No backups yet."));
+
+        app.config.set("amp.showCosts", Value::Bool(true));
+        app.save();
+        app.start_restore_backup();
+        assert_eq!(app.input_mode, InputMode::SelectingBackup);
+        assert_eq!(app.backup_candidates.len(), 1);
+
+        app.commit_backup_restore();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.config.is_dirty());
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+        assert!(app.backup_candidates.is_empty());
+    }
+
+    #[test]
+    fn test_search_jumps_to_setting() {
+        let mut app = test_app();
+        app.start_search();
+        assert_eq!(app.input_mode, InputMode::Searching);
+        assert!(app
+            .search_results
+            .iter()
+            .any(|h| h.key == "amp.experimental.labs" && h.section == Section::Advanced));
+
+        app.edit_buffer.set("stoptime");
+        app.update_search();
+        assert_eq!(app.search_results[0].key, "amp.tools.stopTimeout");
+
+        app.commit_search();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.current_section(), Section::Tools);
+        assert_eq!(app.focus, Focus::Settings);
+        assert_eq!(app.selected_key().as_deref(), Some("amp.tools.stopTimeout"));
+        assert!(app.search_results.is_empty());
+    }
+
+    #[test]
+    fn test_search_selects_mcp_subpanel() {
+        let mut app = test_app();
+        app.start_search();
+        app.edit_buffer.set("mcpPermissions");
+        app.update_search();
+        app.commit_search();
+        assert_eq!(app.current_section(), Section::Mcps);
+        assert_eq!(app.mcp_focus, McpFocus::Permissions);
+    }
+
+    #[test]
+    fn test_finish_file_edit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"amp.showCosts": true}"#).unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.dispatch(Action::EditFile);
+        assert_eq!(app.take_file_edit(), Some(path.clone()));
+        assert_eq!(app.take_file_edit(), None);
+
+        app.finish_file_edit();
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("settings.json wasn't changed.")
+        );
+
+        std::fs::write(&path, r#"{"amp.showCosts": false, "amp.x": 1}"#).unwrap();
+        app.finish_file_edit();
+        assert_eq!(app.config.get("amp.x"), 1);
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Reloaded settings.json; 1 problem(s), first amp.x:"));
+
+        // Unsaved changes are kept on top, winning over the file's.
+        app.config.set("amp.showCosts", Value::Bool(true));
+        std::fs::write(&path, r#"{"amp.showCosts": "no"}"#).unwrap();
+        app.finish_file_edit();
+        assert_eq!(app.config.get("amp.showCosts"), true);
+        assert_eq!(app.config.get_raw("amp.x"), None);
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Merged settings.json; kept unsaved values for amp.showCosts"));
+
+        std::fs::write(&path, "{ nope").unwrap();
+        app.finish_file_edit();
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("settings.json is invalid"));
+        assert!(!app.config.changed_on_disk());
+    }
+
+    #[test]
+    fn test_go_to_key_and_section() {
+        let mut app = test_app();
+        assert!(app.go_to_key("amp.tools.stopTimeout"));
+        assert_eq!(app.current_section(), Section::Tools);
+        assert_eq!(app.focus, Focus::Settings);
+        assert_eq!(app.selected_key().as_deref(), Some("amp.tools.stopTimeout"));
+
+        assert!(app.go_to_key("amp.mcpPermissions"));
+        assert_eq!(app.mcp_focus, McpFocus::Permissions);
+        assert!(!app.go_to_key("amp.nope"));
+        assert_eq!(app.current_section(), Section::Mcps);
+
+        app.go_to_section(Section::Experimental);
+        assert_eq!(app.current_section(), Section::Experimental);
+        assert_eq!(app.selected_setting, 0);
+    }
+
+    #[test]
+    fn test_search_without_matches() {
+        let mut app = test_app();
+        app.start_search();
+        app.edit_buffer.set("zzzz");
+        app.update_search();
+        assert!(app.search_results.is_empty());
+        app.search_select_down();
+        app.commit_search();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.current_section(), Section::General);
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let mut app = test_app();
+        app.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == Section::Tools)
+            .unwrap();
+        app.focus = Focus::Settings;
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| entry_key(e) == "amp.tools.stopTimeout")
+            .unwrap();
+        app.filters
+            .insert(Section::General, SettingsFilter::Defaults);
+        let session = app.session();
+        assert_eq!(session.key.as_deref(), Some("amp.tools.stopTimeout"));
+
+        let mut restored = test_app();
+        restored.restore_session(&session);
+        assert_eq!(restored.current_section(), Section::Tools);
+        assert_eq!(restored.selected_key(), app.selected_key());
+        assert_eq!(restored.focus, Focus::Settings);
+        assert_eq!(restored.filter(Section::General), SettingsFilter::Defaults);
+
+        // Whatever no longer exists is skipped.
+        let stale = Session {
+            section: Some("Nowhere".into()),
+            key: Some("amp.gone".into()),
+            row: 999,
+            ..Session::default()
+        };
+        let mut restored = test_app();
+        restored.restore_session(&stale);
+        assert_eq!(restored.current_section(), Section::General);
+        assert_eq!(
+            restored.selected_setting,
+            restored.current_settings().len() - 1
+        );
+    }
+
+    #[test]
+    fn test_record_and_replay_macro() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        assert!(app.dispatch(Action::ReplayMacro).is_none());
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("No macro"));
+
+        app.dispatch(Action::RecordMacro);
+        app.dispatch(Action::MoveDown);
+        app.dispatch(Action::MoveDown);
+        app.dispatch(Action::RecordMacro);
+        assert_eq!(app.selected_setting, 2);
+        assert_eq!(app.macros.recorded(), [Action::MoveDown, Action::MoveDown]);
+
+        app.dispatch(Action::ReplayMacro);
+        assert_eq!(app.selected_setting, 4);
+        assert_eq!(app.status_message.as_deref(), Some("Replayed 2 action(s)."));
+
+        // Keys typed into a prompt while recording weren't actions, so
+        // replay stops where the prompt opened.
+        app.dispatch(Action::RecordMacro);
+        app.dispatch(Action::Search);
+        app.cancel_edit();
+        app.dispatch(Action::MoveUp);
+        app.dispatch(Action::RecordMacro);
+        app.dispatch(Action::ReplayMacro);
+        assert_eq!(app.input_mode, InputMode::Searching);
+        assert_eq!(app.selected_setting, 3);
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Replay stopped after 1 of 2 actions"));
+    }
+
+    #[test]
+    fn test_command_palette() {
+        let mut app = test_app();
+        app.start_palette();
+        assert_eq!(app.input_mode, InputMode::CommandPalette);
+        assert!(!app
+            .palette_results
+            .contains(&PaletteCommand::Run(Action::CommandPalette)));
+
+        app.edit_buffer.set("review unsaved");
+        app.update_palette();
+        assert_eq!(
+            app.palette_results[0],
+            PaletteCommand::Run(Action::ShowDiff)
+        );
+        assert_eq!(app.commit_palette(), Some(Action::ShowDiff));
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        app.start_palette();
+        app.edit_buffer.set("go to tools");
+        app.update_palette();
+        assert_eq!(app.commit_palette(), None);
+        assert_eq!(app.current_section(), Section::Tools);
+        assert_eq!(app.focus, Focus::Settings);
+
+        app.start_palette();
+        app.edit_buffer.set("zzzz");
+        app.update_palette();
+        assert!(app.palette_results.is_empty());
+        assert_eq!(app.commit_palette(), None);
+    }
+
+    #[test]
+    fn test_offer_scaffold_only_when_missing() {
+        let f = NamedTempFile::new().unwrap();
+        let mut app = App::new(Config::load(f.path()).unwrap());
+        app.offer_scaffold();
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.offer_scaffold();
+        assert_eq!(app.input_mode, InputMode::ConfirmScaffold);
+
+        app.confirm_scaffold();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_decline_scaffold_leaves_file_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.offer_scaffold();
+        app.decline_scaffold();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_file_switch_opens_sibling_in_advanced() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        std::fs::write(&settings, r#"{"amp.showCosts": false}"#).unwrap();
+        std::fs::write(
+            dir.path().join("keybindings.json"),
+            r#"{"ctrl+k": "clear"}"#,
+        )
+        .unwrap();
+
+        let mut app = App::new(Config::load(&settings).unwrap());
+        app.start_file_switch();
+        assert_eq!(app.input_mode, InputMode::SelectingFile);
+        assert_eq!(app.file_candidates.len(), 2);
+        // The currently open file is preselected.
+        assert_eq!(app.file_candidates[app.selected_file], settings);
+
+        app.file_select_up();
+        app.commit_file_selection();
+        assert!(!app.is_settings_file());
+        assert_eq!(app.current_section(), Section::Advanced);
+        assert!(app
+            .current_settings()
+            .iter()
+            .any(|e| matches!(e, SettingEntry::Unknown(k) if k == "ctrl+k")));
+
+        // Switching back restores the settings file.
+        app.start_file_switch();
+        app.file_select_down();
+        app.commit_file_selection();
+        assert!(app.is_settings_file());
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_file_switch_refused_when_dirty() {
+        let mut app = test_app();
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.start_file_switch();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.status_message.is_some());
+    }
+
+    #[test]
+    fn test_validate_with_amp_requires_save() {
+        let mut app = test_app();
+        app.config.set("amp.showCosts", Value::Bool(false));
+        app.validate_with_amp();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.status_message.unwrap().contains("Save"));
+    }
+
+    #[test]
+    fn test_show_report_and_cancel() {
+        let mut app = test_app();
+        app.show_report(" Title ", vec!["line".to_string()]);
+        assert_eq!(app.input_mode, InputMode::ViewingReport);
+        assert_eq!(app.report.as_ref().unwrap().lines, vec!["line"]);
+        app.cancel_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.report.is_none());
+    }
+
+    #[test]
+    fn test_selected_key() {
+        let mut app = test_app();
+        assert_eq!(app.selected_key().as_deref(), Some("amp.showCosts"));
+        app.selected_section = 2; // Permissions
+        assert_eq!(app.selected_key().as_deref(), Some("amp.permissions"));
+        app.selected_section = 4; // MCPs
+        app.mcp_focus = McpFocus::Permissions;
+        assert_eq!(app.selected_key().as_deref(), Some("amp.mcpPermissions"));
+        app.selected_section = 6; // Advanced
+        assert_eq!(app.selected_key().as_deref(), Some("amp.experimental.labs"));
+    }
+
+    #[test]
+    fn test_edit_note_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.focus = Focus::Settings;
+
+        app.start_edit_note();
+        assert_eq!(app.input_mode, InputMode::EditingNote);
+        assert!(app.edit_buffer.is_empty());
+        app.edit_buffer.set("keep on, see OPS-42");
+        app.commit_note();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.notes.get("amp.showCosts"), Some("keep on, see OPS-42"));
+
+        // Notes survive a restart and prefill the editor.
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.start_edit_note();
+        assert_eq!(app.edit_buffer, "keep on, see OPS-42");
+    }
+
+    #[test]
+    fn test_split_view_toggle_and_swap() {
+        let mut app = test_app_with_permissions();
+        app.focus = Focus::Settings;
+        app.selected_setting = 2;
+
+        app.toggle_split();
+        let other = app.split.unwrap();
+        assert_eq!(Section::ALL[other.section], Section::Tools);
+        assert_eq!(app.split_side, SplitSide::Left);
+
+        app.swap_split_pane();
+        assert_eq!(app.current_section(), Section::Tools);
+        assert_eq!(app.selected_setting, 0);
+        assert_eq!(app.split_side, SplitSide::Right);
+
+        // Swapping back restores the first pane's selection.
+        app.swap_split_pane();
+        assert_eq!(app.current_section(), Section::Permissions);
+        assert_eq!(app.selected_setting, 2);
+
+        app.toggle_split();
+        assert!(app.split.is_none());
+        // Swapping without a split is a no-op.
+        app.swap_split_pane();
+        assert_eq!(app.current_section(), Section::Permissions);
+    }
+
+    #[test]
+    fn test_navigate_sections() {
+        let mut app = test_app();
+        assert_eq!(app.current_section(), Section::General);
+
+        app.move_down();
+        assert_eq!(app.current_section(), Section::Permissions);
+
+        app.move_down();
+        assert_eq!(app.current_section(), Section::Tools);
+
+        app.move_up();
+        assert_eq!(app.current_section(), Section::Permissions);
+    }
+
+    #[test]
+    fn test_toggle_focus() {
+        let mut app = test_app();
+        assert_eq!(app.focus, Focus::Sidebar);
+        app.toggle_focus();
+        assert_eq!(app.focus, Focus::Settings);
+        app.toggle_focus();
+        assert_eq!(app.focus, Focus::Sidebar);
+    }
+
+    #[test]
+    fn test_toggle_boolean() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        // amp.anthropic.thinking.enabled defaults to true; Enter cycles it
+        // unset → true → false → unset.
+        let key = "amp.anthropic.thinking.enabled";
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| entry_key(e) == key)
+            .unwrap();
+        assert!(app.uses_default(key));
+        app.activate_setting();
+        assert_eq!(app.config.get_raw(key), Some(&Value::Bool(true)));
+        assert!(!app.uses_default(key));
+        app.activate_setting();
+        assert_eq!(app.config.get_raw(key), Some(&Value::Bool(false)));
+        app.activate_setting();
+        assert_eq!(app.config.get_raw(key), None);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("amp.anthropic.thinking.enabled unset; using the default (✓)")
+        );
+    }
+
+    #[test]
+    fn test_enum_select() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        // Navigate to amp.terminal.theme (a StringEnum)
+        let entries = app.current_settings();
+        let theme_idx = entries
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.terminal.theme"))
+            .unwrap();
+        app.selected_setting = theme_idx;
+
+        // Default is empty string, so the picker starts on the first option
+        app.activate_setting();
+        assert_eq!(app.input_mode, InputMode::SelectingEnum);
+        assert_eq!(app.selected_option, 0);
+        app.enum_select_down();
+        app.commit_enum_selection();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.config.get("amp.terminal.theme"),
+            Value::String("dark".to_string())
+        );
+
+        // Reopening starts on the current value; Esc leaves it alone.
+        app.activate_setting();
+        assert_eq!(app.selected_option, 1);
+        app.enum_select_up();
+        app.enum_select_up();
+        assert_eq!(app.selected_option, 0);
+        app.cancel_edit();
+        assert_eq!(app.pending_enum, None);
+        assert_eq!(
+            app.config.get("amp.terminal.theme"),
+            Value::String("dark".to_string())
+        );
+    }
+
+    #[test]
+    fn test_enum_select_custom_prompts_for_value() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        let entries = app.current_settings();
+        let theme_idx = entries
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.terminal.theme"))
+            .unwrap();
+        app.selected_setting = theme_idx;
+
+        // Set theme to "nord" (the option just before "Custom")
+        app.config
+            .set("amp.terminal.theme", Value::String("nord".to_string()));
+
+        // Picking "Custom" enters editing mode
+        app.activate_setting();
+        app.enum_select_down();
+        app.enum_select_down();
+        app.commit_enum_selection();
+        assert_eq!(app.input_mode, InputMode::EditingValue);
+        assert_eq!(app.edit_buffer, "");
+
+        // Typing a custom name and committing should set it
+        app.edit_buffer.set("my-custom-theme");
+        app.commit_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.config.get("amp.terminal.theme"),
+            Value::String("my-custom-theme".to_string())
+        );
+
+        // A custom value reopens on "Custom", prefilled.
+        app.activate_setting();
+        let (_, options) = app.pending_enum.clone().unwrap();
+        assert_eq!(options[app.selected_option], "Custom");
+        app.commit_enum_selection();
+        assert_eq!(app.edit_buffer, "my-custom-theme");
+    }
+
+    #[test]
+    fn test_enum_select_includes_custom_themes() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        app.custom_themes = vec!["rose-pine".to_string()];
+        let entries = app.current_settings();
+        let theme_idx = entries
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.terminal.theme"))
+            .unwrap();
+        app.selected_setting = theme_idx;
+
+        let def = settings::get_setting_def("amp.terminal.theme").unwrap();
+        let options = app.enum_options(&def);
+        assert_eq!(options[options.len() - 2], "rose-pine");
+        assert_eq!(options[options.len() - 1], "Custom");
+
+        // The installed theme is offered after "nord", before "Custom".
+        app.config
+            .set("amp.terminal.theme", Value::String("nord".to_string()));
+        app.activate_setting();
+        app.enum_select_down();
+        app.commit_enum_selection();
+        assert_eq!(
+            app.config.get("amp.terminal.theme"),
+            Value::String("rose-pine".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reset_setting() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+
+        // notifications.enabled is set to false in our test data
+        let entries = app.current_settings();
+        let idx = entries
+            .iter()
+            .position(
+                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.notifications.enabled"),
+            )
+            .unwrap();
+        app.selected_setting = idx;
+
+        assert_eq!(
+            app.config.get("amp.notifications.enabled"),
+            Value::Bool(false)
+        );
+
+        app.reset_setting();
+        // Should fall back to default (true)
+        assert_eq!(
+            app.config.get("amp.notifications.enabled"),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_advanced_shows_unknown_keys() {
+        let mut app = test_app();
+        // Navigate to Advanced section
+        app.selected_section = 6; // Advanced is index 6
+        assert_eq!(app.current_section(), Section::Advanced);
+
         let entries = app.current_settings();
-        let theme_idx = entries
+        assert!(entries
             .iter()
-            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.terminal.theme"))
+            .any(|e| matches!(e, SettingEntry::Unknown(k) if k == "amp.experimental.labs")));
+    }
+
+    #[test]
+    fn test_move_bounds() {
+        let mut app = test_app();
+        // At top, moving up should stay
+        app.move_up();
+        assert_eq!(app.selected_section, 0);
+
+        // Move to bottom
+        for _ in 0..10 {
+            app.move_down();
+        }
+        assert_eq!(app.selected_section, Section::ALL.len() - 1);
+
+        // Further down should stay
+        app.move_down();
+        assert_eq!(app.selected_section, Section::ALL.len() - 1);
+    }
+
+    #[test]
+    fn test_section_change_resets_setting_index() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        app.selected_setting = 5;
+        app.focus = Focus::Sidebar;
+        app.move_down();
+        assert_eq!(app.selected_setting, 0);
+    }
+
+    #[test]
+    fn test_inline_edit_string() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        // Navigate to amp.bitbucketToken (a string)
+        let entries = app.current_settings();
+        let idx = entries
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.bitbucketToken"))
             .unwrap();
-        app.selected_setting = theme_idx;
+        app.selected_setting = idx;
+
+        app.activate_setting();
+        assert!(app.is_editing());
+        app.edit_buffer.set("my-token");
+        app.commit_edit();
+        assert!(!app.is_editing());
+        assert_eq!(
+            app.config.get("amp.bitbucketToken"),
+            Value::String("my-token".to_string())
+        );
+    }
+
+    #[test]
+    fn test_inline_edit_number() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        // Navigate to Tools section
+        app.selected_section = 3; // Tools
+        let entries = app.current_settings();
+        let idx = entries
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.tools.stopTimeout"))
+            .unwrap();
+        app.selected_setting = idx;
+
+        app.activate_setting();
+        assert!(app.is_editing());
+        app.edit_buffer.set("120");
+        app.commit_edit();
+        assert!(!app.is_editing());
+        assert_eq!(
+            app.config.get("amp.tools.stopTimeout"),
+            Value::Number(120.into())
+        );
+    }
+
+    #[test]
+    fn test_inline_edit_cancel() {
+        let mut app = test_app();
+        app.input_mode = InputMode::EditingValue;
+        app.edit_buffer.set("something");
+        app.cancel_edit();
+        assert!(!app.is_editing());
+        assert!(app.edit_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_object_returns_editor_request() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        // Objects with a schema open a form, unless the stored value isn't
+        // an object to begin with.
+        app.config
+            .set("amp.defaultVisibility", Value::String("private".into()));
+        let entries = app.current_settings();
+        let idx = entries
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.defaultVisibility"))
+            .unwrap();
+        app.selected_setting = idx;
+
+        let req = app.activate_setting();
+        assert!(req.is_some());
+        let req = req.unwrap();
+        assert_eq!(req.key, "amp.defaultVisibility");
+        assert!(req.array_index.is_none());
+    }
+
+    #[test]
+    fn test_array_string_add_item() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        let entries = app.current_settings();
+        let idx = entries
+            .iter()
+            .position(
+                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.fuzzy.alwaysIncludePaths"),
+            )
+            .unwrap();
+        app.selected_setting = idx;
+
+        app.add_array_item();
+        assert!(app.is_editing());
+        app.edit_buffer.set("*.rs");
+        app.commit_edit();
+        assert!(!app.is_editing());
+        assert_eq!(
+            app.config.get("amp.fuzzy.alwaysIncludePaths"),
+            Value::Array(vec![Value::String("*.rs".into())])
+        );
+    }
+
+    #[test]
+    fn test_array_string_delete_item() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        app.config.set(
+            "amp.fuzzy.alwaysIncludePaths",
+            Value::Array(vec![Value::String("a".into()), Value::String("b".into())]),
+        );
+        let entries = app.current_settings();
+        let idx = entries
+            .iter()
+            .position(
+                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.fuzzy.alwaysIncludePaths"),
+            )
+            .unwrap();
+        app.selected_setting = idx;
+
+        app.delete_array_item();
+        assert_eq!(
+            app.config.get("amp.fuzzy.alwaysIncludePaths"),
+            Value::Array(vec![Value::String("a".into())])
+        );
+    }
+
+    #[test]
+    fn test_delete_empty_array() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        let entries = app.current_settings();
+        let idx = entries
+            .iter()
+            .position(
+                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.fuzzy.alwaysIncludePaths"),
+            )
+            .unwrap();
+        app.selected_setting = idx;
+
+        app.delete_array_item();
+        assert!(app.status_message.is_some());
+        assert!(app.status_message.unwrap().contains("empty"));
+    }
+
+    #[test]
+    fn test_force_editor() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        // Any setting should produce an EditorRequest
+        let req = app.force_editor();
+        assert!(req.is_some());
+    }
+
+    #[test]
+    fn test_apply_editor_result() {
+        let mut app = test_app();
+        let req = EditorRequest {
+            key: "amp.defaultVisibility".to_string(),
+            value: Value::Object(serde_json::Map::new()),
+            array_index: None,
+            object_key: None,
+        };
+        let mut map = serde_json::Map::new();
+        map.insert("origin".into(), Value::String("private".into()));
+        app.apply_editor_result(&req, Value::Object(map));
+        let val = app.config.get("amp.defaultVisibility");
+        assert!(val.is_object());
+        assert_eq!(val["origin"], Value::String("private".into()));
+    }
+
+    #[test]
+    fn test_apply_editor_result_array_index() {
+        let mut app = test_app();
+        app.config.set(
+            "amp.permissions",
+            Value::Array(vec![Value::Object(serde_json::Map::new())]),
+        );
+        let req = EditorRequest {
+            key: "amp.permissions".to_string(),
+            value: Value::Object(serde_json::Map::new()),
+            array_index: Some(0),
+            object_key: None,
+        };
+        let mut edited = serde_json::Map::new();
+        edited.insert("tool".into(), Value::String("Bash".into()));
+        app.apply_editor_result(&req, Value::Object(edited));
+        let arr = app.config.get("amp.permissions");
+        assert_eq!(
+            arr.as_array().unwrap()[0]["tool"],
+            Value::String("Bash".into())
+        );
+    }
+
+    #[test]
+    fn test_unknown_key_array_shows_status() {
+        let mut app = test_app();
+        app.selected_section = 6; // Advanced
+        app.focus = Focus::Settings;
+        let entries = app.current_settings();
+        assert!(!entries.is_empty());
+        app.selected_setting = 0;
+        let req = app.activate_setting();
+        assert!(req.is_none());
+        assert!(app.status_message.is_some());
+    }
+
+    #[test]
+    fn test_unknown_key_object_returns_editor_request() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(f, r#"{{"amp.experimental.obj": {{"key": "val"}}}}"#).unwrap();
+        let config = Config::load(f.path()).unwrap();
+        let mut app = App::new(config);
+        app.selected_section = 6; // Advanced
+        app.focus = Focus::Settings;
+        app.selected_setting = 0;
+        let req = app.activate_setting();
+        assert!(req.is_some());
+        assert_eq!(req.unwrap().key, "amp.experimental.obj");
+    }
 
-        // Default is empty string, cycling should go to first option
-        app.activate_setting();
-        assert_eq!(
-            app.config.get("amp.terminal.theme"),
-            Value::String("terminal".to_string())
-        );
+    #[test]
+    fn test_unknown_key_bool_toggles() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(f, r#"{{"amp.experimental.flag": true}}"#).unwrap();
+        let config = Config::load(f.path()).unwrap();
+        let mut app = App::new(config);
+        app.selected_section = 6; // Advanced
+        app.focus = Focus::Settings;
+        app.selected_setting = 0;
+        let req = app.activate_setting();
+        assert!(req.is_none());
+        assert_eq!(app.config.get("amp.experimental.flag"), Value::Bool(false));
+    }
 
-        app.activate_setting();
-        assert_eq!(
-            app.config.get("amp.terminal.theme"),
-            Value::String("dark".to_string())
-        );
+    #[test]
+    fn test_unknown_key_string_opens_editor() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(f, r#"{{"amp.experimental.name": "test"}}"#).unwrap();
+        let config = Config::load(f.path()).unwrap();
+        let mut app = App::new(config);
+        app.selected_section = 6; // Advanced
+        app.focus = Focus::Settings;
+        app.selected_setting = 0;
+        let req = app.activate_setting();
+        assert!(req.is_none());
+        assert_eq!(app.input_mode, InputMode::EditingValue);
+        assert_eq!(app.edit_buffer, "test");
+    }
+
+    fn test_app_with_permissions() -> App {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"{{
+    "amp.permissions": [
+        {{"tool": "Bash", "decision": "allow"}},
+        {{"tool": "Read", "decision": "allow"}},
+        {{"tool": "edit_file", "decision": "ask"}}
+    ]
+}}"#
+        )
+        .unwrap();
+        let config = Config::load(f.path()).unwrap();
+        let mut app = App::new(config);
+        app.selected_section = 2; // Permissions
+        app
+    }
+
+    #[test]
+    fn test_permission_rule_unknown_tool() {
+        let mut app = test_app_with_permissions();
+        let mut servers = serde_json::Map::new();
+        servers.insert("github".into(), serde_json::json!({"command": "gh-mcp"}));
+        app.config.set("amp.mcpServers", Value::Object(servers));
+
+        assert!(!app.permission_rule_has_unknown_tool(&serde_json::json!({"tool": "Bash"})));
+        assert!(!app.permission_rule_has_unknown_tool(
+            &serde_json::json!({"tool": "mcp__github__list_issues"})
+        ));
+        assert!(app.permission_rule_has_unknown_tool(
+            &serde_json::json!({"tool": "mcp__gitlab__list_issues"})
+        ));
+        assert!(app.permission_rule_has_unknown_tool(&serde_json::json!({"tool": "Bsah"})));
+        // Rules without a tool field are not flagged.
+        assert!(!app.permission_rule_has_unknown_tool(&serde_json::json!({"action": "ask"})));
+    }
+
+    #[test]
+    fn test_single_key_item_count() {
+        let app = test_app_with_permissions();
+        assert_eq!(app.current_section(), Section::Permissions);
+        assert_eq!(app.current_item_count(), 3);
+    }
+
+    #[test]
+    fn test_single_key_navigate_items() {
+        let mut app = test_app_with_permissions();
+        app.focus = Focus::Settings;
+        assert_eq!(app.selected_setting, 0);
+        app.move_down();
+        assert_eq!(app.selected_setting, 1);
+        app.move_down();
+        assert_eq!(app.selected_setting, 2);
+        app.move_down();
+        assert_eq!(app.selected_setting, 2); // stays at last
+    }
+
+    #[test]
+    fn test_single_key_activate_opens_item() {
+        let mut app = test_app_with_permissions();
+        app.focus = Focus::Settings;
+        app.selected_setting = 1;
+        assert!(app.activate_setting().is_none());
+        assert_eq!(app.input_mode, InputMode::EditingRule);
+        let form = app.rule_form.as_ref().unwrap();
+        assert_eq!((form.index, form.tool.as_str()), (1, "Read"));
+    }
+
+    #[test]
+    fn test_single_key_delete_selected_item() {
+        let mut app = test_app_with_permissions();
+        app.focus = Focus::Settings;
+        app.selected_setting = 1; // "Read" item
+        app.delete_array_item();
+        assert_eq!(app.current_item_count(), 2);
+        // The remaining items should be Bash and edit_file
+        let arr = app.config.get("amp.permissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items[0]["tool"], Value::String("Bash".into()));
+        assert_eq!(items[1]["tool"], Value::String("edit_file".into()));
+    }
+
+    #[test]
+    fn test_single_key_delete_last_adjusts_selection() {
+        let mut app = test_app_with_permissions();
+        app.focus = Focus::Settings;
+        app.selected_setting = 2; // last item
+        app.delete_array_item();
+        assert_eq!(app.current_item_count(), 2);
+        assert_eq!(app.selected_setting, 1); // adjusted
     }
 
     #[test]
-    fn test_cycle_enum_custom_prompts_for_value() {
+    fn test_single_key_empty_item_count() {
         let mut app = test_app();
+        app.selected_section = 2; // Permissions
+        assert_eq!(app.current_item_count(), 0);
+    }
+
+    #[test]
+    fn test_single_key_reset_clears_array() {
+        let mut app = test_app_with_permissions();
         app.focus = Focus::Settings;
-        let entries = app.current_settings();
-        let theme_idx = entries
-            .iter()
-            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.terminal.theme"))
-            .unwrap();
-        app.selected_setting = theme_idx;
+        app.selected_setting = 1;
+        app.reset_setting();
+        assert_eq!(app.current_item_count(), 0);
+        assert_eq!(app.selected_setting, 0);
+    }
 
-        // Set theme to "nord" (the option just before "Custom")
-        app.config
-            .set("amp.terminal.theme", Value::String("nord".to_string()));
+    #[test]
+    fn test_start_add_custom_key() {
+        let mut app = test_app();
+        app.selected_section = 6; // Advanced
+        app.focus = Focus::Settings;
+        app.start_add_custom_key();
+        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
+        assert!(app.edit_buffer.is_empty());
+    }
 
-        // Cycling from "nord" should land on "Custom" and enter editing mode
-        app.activate_setting();
-        assert_eq!(app.input_mode, InputMode::EditingValue);
-        assert_eq!(app.edit_buffer, "");
+    #[test]
+    fn test_start_add_custom_key_not_advanced() {
+        let mut app = test_app();
+        app.selected_section = 1; // General
+        app.start_add_custom_key();
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
 
-        // Typing a custom name and committing should set it
-        app.edit_buffer = "my-custom-theme".to_string();
-        app.commit_edit();
+    #[test]
+    fn test_commit_key_name_empty() {
+        let mut app = test_app();
+        app.selected_section = 5;
+        app.input_mode = InputMode::EnteringKeyName;
+        app.edit_buffer.set("  ");
+        app.commit_key_name();
+        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
+        assert!(app.status_message.unwrap().contains("empty"));
+    }
+
+    #[test]
+    fn test_commit_key_name_duplicate() {
+        let mut app = test_app();
+        app.selected_section = 5;
+        app.input_mode = InputMode::EnteringKeyName;
+        app.edit_buffer.set("amp.showCosts");
+        app.commit_key_name();
+        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
+        assert!(app.status_message.unwrap().contains("already exists"));
+    }
+
+    #[test]
+    fn test_commit_key_name_success() {
+        let mut app = test_app();
+        app.selected_section = 5;
+        app.input_mode = InputMode::EnteringKeyName;
+        app.edit_buffer.set("my.custom.key");
+        app.commit_key_name();
+        assert_eq!(app.input_mode, InputMode::SelectingType);
+        assert_eq!(app.pending_custom_key.as_deref(), Some("my.custom.key"));
+        assert!(app.edit_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_commit_type_boolean() {
+        let mut app = test_app();
+        app.pending_custom_key = Some("my.bool.key".to_string());
+        app.selected_type = 0; // Boolean
+        let req = app.commit_type_selection();
+        assert!(req.is_none());
+        assert_eq!(app.config.get("my.bool.key"), Value::Bool(false));
         assert_eq!(app.input_mode, InputMode::Normal);
-        assert_eq!(
-            app.config.get("amp.terminal.theme"),
-            Value::String("my-custom-theme".to_string())
-        );
+        assert!(app.pending_custom_key.is_none());
     }
 
     #[test]
-    fn test_reset_setting() {
+    fn test_commit_type_string_enters_value_mode() {
         let mut app = test_app();
-        app.focus = Focus::Settings;
+        app.pending_custom_key = Some("my.str.key".to_string());
+        app.selected_type = 1; // String
+        let req = app.commit_type_selection();
+        assert!(req.is_none());
+        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
+        assert!(app.pending_custom_key.is_some());
+    }
 
-        // notifications.enabled is set to false in our test data
-        let entries = app.current_settings();
-        let idx = entries
-            .iter()
-            .position(
-                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.notifications.enabled"),
-            )
-            .unwrap();
-        app.selected_setting = idx;
+    #[test]
+    fn test_commit_type_number_enters_value_mode() {
+        let mut app = test_app();
+        app.pending_custom_key = Some("my.num.key".to_string());
+        app.selected_type = 2; // Number
+        let req = app.commit_type_selection();
+        assert!(req.is_none());
+        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
+    }
 
-        assert_eq!(
-            app.config.get("amp.notifications.enabled"),
-            Value::Bool(false)
-        );
+    #[test]
+    fn test_commit_type_array() {
+        let mut app = test_app();
+        app.pending_custom_key = Some("my.arr.key".to_string());
+        app.selected_type = 3; // Array
+        let req = app.commit_type_selection();
+        assert!(req.is_none());
+        assert_eq!(app.config.get("my.arr.key"), Value::Array(vec![]));
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
 
-        app.reset_setting();
-        // Should fall back to default (true)
+    #[test]
+    fn test_commit_type_object_returns_editor_request() {
+        let mut app = test_app();
+        app.pending_custom_key = Some("my.obj.key".to_string());
+        app.selected_type = 4; // Object
+        let req = app.commit_type_selection();
+        assert!(req.is_some());
+        let req = req.unwrap();
+        assert_eq!(req.key, "my.obj.key");
+        assert!(req.value.is_object());
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn test_commit_custom_value_string() {
+        let mut app = test_app();
+        app.pending_custom_key = Some("my.str.key".to_string());
+        app.selected_type = 1; // String
+        app.input_mode = InputMode::EnteringCustomValue;
+        app.edit_buffer.set("hello world");
+        app.commit_custom_value();
         assert_eq!(
-            app.config.get("amp.notifications.enabled"),
-            Value::Bool(true)
+            app.config.get("my.str.key"),
+            Value::String("hello world".into())
         );
+        assert_eq!(app.input_mode, InputMode::Normal);
     }
 
     #[test]
-    fn test_advanced_shows_unknown_keys() {
+    fn test_commit_custom_value_number() {
         let mut app = test_app();
-        // Navigate to Advanced section
-        app.selected_section = 4; // Advanced is index 4
-        assert_eq!(app.current_section(), Section::Advanced);
-
-        let entries = app.current_settings();
-        assert!(entries
-            .iter()
-            .any(|e| matches!(e, SettingEntry::Unknown(k) if k == "amp.experimental.modes")));
+        app.pending_custom_key = Some("my.num.key".to_string());
+        app.selected_type = 2; // Number
+        app.input_mode = InputMode::EnteringCustomValue;
+        app.edit_buffer.set("42");
+        app.commit_custom_value();
+        assert_eq!(app.config.get("my.num.key"), Value::Number(42.into()));
+        assert_eq!(app.input_mode, InputMode::Normal);
     }
 
     #[test]
-    fn test_move_bounds() {
+    fn test_commit_custom_value_invalid_number() {
         let mut app = test_app();
-        // At top, moving up should stay
-        app.move_up();
-        assert_eq!(app.selected_section, 0);
+        app.pending_custom_key = Some("my.num.key".to_string());
+        app.selected_type = 2; // Number
+        app.input_mode = InputMode::EnteringCustomValue;
+        app.edit_buffer.set("not a number");
+        app.commit_custom_value();
+        assert!(app.status_message.unwrap().contains("Invalid"));
+        assert!(app.pending_custom_key.is_some());
+        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
+    }
 
-        // Move to bottom
+    #[test]
+    fn test_type_select_navigation() {
+        let mut app = test_app();
+        app.selected_type = 0;
+        app.type_select_up();
+        assert_eq!(app.selected_type, 0); // stays at 0
+        app.type_select_down();
+        assert_eq!(app.selected_type, 1);
+        app.type_select_down();
+        assert_eq!(app.selected_type, 2);
+        // Go to last
         for _ in 0..10 {
-            app.move_down();
+            app.type_select_down();
         }
-        assert_eq!(app.selected_section, Section::ALL.len() - 1);
-
-        // Further down should stay
-        app.move_down();
-        assert_eq!(app.selected_section, Section::ALL.len() - 1);
+        assert_eq!(app.selected_type, CustomKeyType::ALL.len() - 1);
     }
 
     #[test]
-    fn test_section_change_resets_setting_index() {
+    fn test_cancel_edit_clears_custom_key_state() {
         let mut app = test_app();
-        app.focus = Focus::Settings;
-        app.selected_setting = 5;
-        app.focus = Focus::Sidebar;
-        app.move_down();
-        assert_eq!(app.selected_setting, 0);
+        app.input_mode = InputMode::SelectingType;
+        app.pending_custom_key = Some("my.key".to_string());
+        app.selected_type = 2;
+        app.cancel_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.pending_custom_key.is_none());
+        assert_eq!(app.selected_type, 0);
     }
 
     #[test]
-    fn test_inline_edit_string() {
-        let mut app = test_app();
+    fn test_add_custom_key_full_flow_string() {
+        // Use an app with a non-array unknown key so add_array_item starts the
+        // "add custom key" flow instead of trying to add to an existing array.
+        let mut f = NamedTempFile::new().unwrap();
+        write!(f, r#"{{"amp.experimental.flag": true}}"#).unwrap();
+        let config = Config::load(f.path()).unwrap();
+        let mut app = App::new(config);
+        app.selected_section = 6; // Advanced
         app.focus = Focus::Settings;
-        // Navigate to amp.bitbucketToken (a string)
-        let entries = app.current_settings();
-        let idx = entries
-            .iter()
-            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.bitbucketToken"))
-            .unwrap();
-        app.selected_setting = idx;
 
-        app.activate_setting();
-        assert!(app.is_editing());
-        app.edit_buffer = "my-token".to_string();
-        app.commit_edit();
-        assert!(!app.is_editing());
+        // Step 1: start
+        app.add_array_item();
+        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
+
+        // Step 2: enter key name
+        app.edit_buffer.set("my.custom.setting");
+        app.commit_key_name();
+        assert_eq!(app.input_mode, InputMode::SelectingType);
+
+        // Step 3: select string type
+        app.selected_type = 1; // String
+        app.commit_type_selection();
+        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
+
+        // Step 4: enter value
+        app.edit_buffer.set("my value");
+        app.commit_custom_value();
+        assert_eq!(app.input_mode, InputMode::Normal);
         assert_eq!(
-            app.config.get("amp.bitbucketToken"),
-            Value::String("my-token".to_string())
+            app.config.get("my.custom.setting"),
+            Value::String("my value".into())
         );
     }
 
     #[test]
-    fn test_inline_edit_number() {
+    fn test_permission_add_starts_tool_prompt() {
         let mut app = test_app();
+        app.selected_section = 2; // Permissions
         app.focus = Focus::Settings;
-        // Navigate to Tools section
-        app.selected_section = 2; // Tools
-        let entries = app.current_settings();
-        let idx = entries
-            .iter()
-            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.tools.stopTimeout"))
-            .unwrap();
-        app.selected_setting = idx;
-
-        app.activate_setting();
-        assert!(app.is_editing());
-        app.edit_buffer = "120".to_string();
-        app.commit_edit();
-        assert!(!app.is_editing());
-        assert_eq!(
-            app.config.get("amp.tools.stopTimeout"),
-            Value::Number(120.into())
-        );
+        app.add_array_item();
+        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
+        assert!(app.edit_buffer.is_empty());
     }
 
     #[test]
-    fn test_inline_edit_cancel() {
+    fn test_permission_tool_empty_rejected() {
         let mut app = test_app();
-        app.input_mode = InputMode::EditingValue;
-        app.edit_buffer = "something".to_string();
-        app.cancel_edit();
-        assert!(!app.is_editing());
-        assert!(app.edit_buffer.is_empty());
+        app.input_mode = InputMode::EnteringPermissionTool;
+        app.edit_buffer.set("  ");
+        app.commit_permission_tool();
+        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
+        assert!(app.status_message.unwrap().contains("empty"));
     }
 
     #[test]
-    fn test_object_returns_editor_request() {
+    fn test_permission_tool_moves_to_level_select() {
         let mut app = test_app();
-        app.focus = Focus::Settings;
-        let entries = app.current_settings();
-        let idx = entries
-            .iter()
-            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.defaultVisibility"))
-            .unwrap();
-        app.selected_setting = idx;
-
-        let req = app.activate_setting();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.defaultVisibility");
-        assert!(req.array_index.is_none());
+        app.input_mode = InputMode::EnteringPermissionTool;
+        app.edit_buffer.set("Bash");
+        app.commit_permission_tool();
+        assert_eq!(app.input_mode, InputMode::SelectingPermissionLevel);
+        assert_eq!(app.pending_permission_tool.as_deref(), Some("Bash"));
+        assert_eq!(app.selected_permission_level, 0);
     }
 
     #[test]
-    fn test_array_string_add_item() {
+    fn test_permission_level_navigation() {
         let mut app = test_app();
-        app.focus = Focus::Settings;
-        let entries = app.current_settings();
-        let idx = entries
-            .iter()
-            .position(
-                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.fuzzy.alwaysIncludePaths"),
-            )
-            .unwrap();
-        app.selected_setting = idx;
-
-        app.add_array_item();
-        assert!(app.is_editing());
-        app.edit_buffer = "*.rs".to_string();
-        app.commit_edit();
-        assert!(!app.is_editing());
-        assert_eq!(
-            app.config.get("amp.fuzzy.alwaysIncludePaths"),
-            Value::Array(vec![Value::String("*.rs".into())])
-        );
+        app.selected_permission_level = 0;
+        app.permission_level_up();
+        assert_eq!(app.selected_permission_level, 0);
+        app.permission_level_down();
+        assert_eq!(app.selected_permission_level, 1);
+        app.permission_level_down();
+        assert_eq!(app.selected_permission_level, 2);
+        app.permission_level_down();
+        assert_eq!(app.selected_permission_level, 3); // delegate
+        app.permission_level_down();
+        assert_eq!(app.selected_permission_level, 3); // stays at last
     }
 
     #[test]
-    fn test_array_string_delete_item() {
+    fn test_permission_commit_adds_rule() {
         let mut app = test_app();
-        app.focus = Focus::Settings;
-        app.config.set(
-            "amp.fuzzy.alwaysIncludePaths",
-            Value::Array(vec![Value::String("a".into()), Value::String("b".into())]),
-        );
-        let entries = app.current_settings();
-        let idx = entries
-            .iter()
-            .position(
-                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.fuzzy.alwaysIncludePaths"),
-            )
-            .unwrap();
-        app.selected_setting = idx;
+        app.pending_permission_tool = Some("Bash".to_string());
+        app.selected_permission_level = 1; // allow
+        app.commit_permission_level();
+        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
+        assert!(app.pending_permission_tool.is_none());
 
-        app.delete_array_item();
-        assert_eq!(
-            app.config.get("amp.fuzzy.alwaysIncludePaths"),
-            Value::Array(vec![Value::String("a".into())])
-        );
+        let arr = app.config.get("amp.permissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["tool"], Value::String("Bash".into()));
+        assert_eq!(items[0]["action"], Value::String("allow".into()));
     }
 
     #[test]
-    fn test_delete_empty_array() {
+    fn test_permission_full_flow() {
         let mut app = test_app();
+        app.selected_section = 2; // Permissions
         app.focus = Focus::Settings;
-        let entries = app.current_settings();
-        let idx = entries
-            .iter()
-            .position(
-                |e| matches!(e, SettingEntry::Known(d) if d.key == "amp.fuzzy.alwaysIncludePaths"),
-            )
-            .unwrap();
-        app.selected_setting = idx;
 
-        app.delete_array_item();
-        assert!(app.status_message.is_some());
-        assert!(app.status_message.unwrap().contains("empty"));
+        // Step 1: press 'a' to start
+        app.add_array_item();
+        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
+
+        // Step 2: enter tool name
+        app.edit_buffer.set("Read");
+        app.commit_permission_tool();
+        assert_eq!(app.input_mode, InputMode::SelectingPermissionLevel);
+
+        // Step 3: select "reject" (index 2)
+        app.permission_level_down();
+        app.permission_level_down();
+        assert_eq!(app.selected_permission_level, 2);
+        app.commit_permission_level();
+
+        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
+        let arr = app.config.get("amp.permissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["tool"], Value::String("Read".into()));
+        assert_eq!(items[0]["action"], Value::String("reject".into()));
     }
 
     #[test]
-    fn test_force_editor() {
+    fn test_cancel_permission_clears_state() {
         let mut app = test_app();
-        app.focus = Focus::Settings;
-        // Any setting should produce an EditorRequest
-        let req = app.force_editor();
-        assert!(req.is_some());
+        app.input_mode = InputMode::SelectingPermissionLevel;
+        app.pending_permission_tool = Some("Bash".to_string());
+        app.selected_permission_level = 1;
+        app.cancel_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.pending_permission_tool.is_none());
+        assert_eq!(app.selected_permission_level, 0);
     }
 
     #[test]
-    fn test_apply_editor_result() {
+    fn test_confirm_advanced_edit_returns_editor_request() {
         let mut app = test_app();
-        let req = EditorRequest {
-            key: "amp.defaultVisibility".to_string(),
-            value: Value::Object(serde_json::Map::new()),
-            array_index: None,
-            object_key: None,
-        };
-        let mut map = serde_json::Map::new();
-        map.insert("origin".into(), Value::String("private".into()));
-        app.apply_editor_result(&req, Value::Object(map));
-        let val = app.config.get("amp.defaultVisibility");
-        assert!(val.is_object());
-        assert_eq!(val["origin"], Value::String("private".into()));
+        // Add a permission rule first
+        app.pending_permission_tool = Some("Bash".to_string());
+        app.selected_permission_level = 0; // ask
+        app.commit_permission_level();
+        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
+
+        let req = app.confirm_advanced_edit();
+        assert!(req.is_some());
+        let req = req.unwrap();
+        assert_eq!(req.key, "amp.permissions");
+        assert_eq!(req.array_index, Some(0));
+        assert_eq!(req.value["tool"], Value::String("Bash".into()));
+        assert_eq!(req.value["action"], Value::String("ask".into()));
+        assert_eq!(app.input_mode, InputMode::Normal);
     }
 
     #[test]
-    fn test_apply_editor_result_array_index() {
+    fn test_decline_advanced_edit_returns_to_normal() {
         let mut app = test_app();
-        app.config.set(
-            "amp.permissions",
-            Value::Array(vec![Value::Object(serde_json::Map::new())]),
-        );
-        let req = EditorRequest {
-            key: "amp.permissions".to_string(),
-            value: Value::Object(serde_json::Map::new()),
-            array_index: Some(0),
-            object_key: None,
-        };
-        let mut edited = serde_json::Map::new();
-        edited.insert("tool".into(), Value::String("Bash".into()));
-        app.apply_editor_result(&req, Value::Object(edited));
-        let arr = app.config.get("amp.permissions");
-        assert_eq!(
-            arr.as_array().unwrap()[0]["tool"],
-            Value::String("Bash".into())
-        );
+        app.input_mode = InputMode::ConfirmAdvancedEdit;
+        app.decline_advanced_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
     }
 
     #[test]
-    fn test_unknown_key_array_shows_status() {
+    fn test_permission_full_flow_with_decline() {
         let mut app = test_app();
-        app.selected_section = 4; // Advanced
+        app.selected_section = 2; // Permissions
         app.focus = Focus::Settings;
-        let entries = app.current_settings();
-        assert!(!entries.is_empty());
-        app.selected_setting = 0;
-        let req = app.activate_setting();
-        assert!(req.is_none());
-        assert!(app.status_message.is_some());
+
+        app.add_array_item();
+        app.edit_buffer.set("Bash");
+        app.commit_permission_tool();
+        app.commit_permission_level(); // defaults to "ask"
+        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
+
+        app.decline_advanced_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        let arr = app.config.get("amp.permissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["tool"], Value::String("Bash".into()));
     }
 
     #[test]
-    fn test_unknown_key_object_returns_editor_request() {
-        let mut f = NamedTempFile::new().unwrap();
-        write!(f, r#"{{"amp.experimental.obj": {{"key": "val"}}}}"#).unwrap();
-        let config = Config::load(f.path()).unwrap();
-        let mut app = App::new(config);
-        app.selected_section = 4; // Advanced
-        app.focus = Focus::Settings;
-        app.selected_setting = 0;
-        let req = app.activate_setting();
-        assert!(req.is_some());
-        assert_eq!(req.unwrap().key, "amp.experimental.obj");
+    fn test_delegate_level_prompts_for_to() {
+        let mut app = test_app();
+        app.pending_permission_tool = Some("Bash".to_string());
+        app.selected_permission_level = 3; // Delegate
+        app.commit_permission_level();
+        assert_eq!(app.input_mode, InputMode::EnteringDelegateTo);
+        assert!(app.pending_permission_tool.is_some());
     }
 
     #[test]
-    fn test_unknown_key_bool_toggles() {
-        let mut f = NamedTempFile::new().unwrap();
-        write!(f, r#"{{"amp.experimental.flag": true}}"#).unwrap();
-        let config = Config::load(f.path()).unwrap();
-        let mut app = App::new(config);
-        app.selected_section = 4; // Advanced
-        app.focus = Focus::Settings;
-        app.selected_setting = 0;
-        let req = app.activate_setting();
-        assert!(req.is_none());
-        assert_eq!(app.config.get("amp.experimental.flag"), Value::Bool(false));
+    fn test_delegate_to_empty_rejected() {
+        let mut app = test_app();
+        app.input_mode = InputMode::EnteringDelegateTo;
+        app.pending_permission_tool = Some("Bash".to_string());
+        app.edit_buffer.set("  ");
+        app.commit_delegate_to();
+        assert_eq!(app.input_mode, InputMode::EnteringDelegateTo);
+        assert!(app.status_message.unwrap().contains("empty"));
     }
 
     #[test]
-    fn test_unknown_key_string_opens_editor() {
-        let mut f = NamedTempFile::new().unwrap();
-        write!(f, r#"{{"amp.experimental.name": "test"}}"#).unwrap();
-        let config = Config::load(f.path()).unwrap();
-        let mut app = App::new(config);
-        app.selected_section = 4; // Advanced
+    fn test_delegate_full_flow() {
+        let mut app = test_app();
+        app.selected_section = 2; // Permissions
         app.focus = Focus::Settings;
-        app.selected_setting = 0;
-        let req = app.activate_setting();
-        assert!(req.is_none());
-        assert_eq!(app.input_mode, InputMode::EditingValue);
-        assert_eq!(app.edit_buffer, "test");
+
+        app.add_array_item();
+        app.edit_buffer.set("*");
+        app.commit_permission_tool();
+
+        // Select delegate (index 3)
+        app.selected_permission_level = 3;
+        app.commit_permission_level();
+        assert_eq!(app.input_mode, InputMode::EnteringDelegateTo);
+
+        app.edit_buffer.set("my-permission-helper");
+        app.commit_delegate_to();
+        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
+
+        app.decline_advanced_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        let arr = app.config.get("amp.permissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["tool"], Value::String("*".into()));
+        assert_eq!(items[0]["action"], Value::String("delegate".into()));
+        assert_eq!(items[0]["to"], Value::String("my-permission-helper".into()));
     }
 
-    fn test_app_with_permissions() -> App {
+    fn test_app_with_mcp_permissions() -> App {
         let mut f = NamedTempFile::new().unwrap();
         write!(
             f,
             r#"{{
-    "amp.permissions": [
-        {{"tool": "Bash", "decision": "allow"}},
-        {{"tool": "Read", "decision": "allow"}},
-        {{"tool": "edit_file", "decision": "ask"}}
+    "amp.mcpServers": {{"test-server": {{"command": "npx"}}}},
+    "amp.mcpPermissions": [
+        {{"matches": {{"command": "npx"}}, "action": "allow"}},
+        {{"matches": {{"url": "https://evil.com"}}, "action": "reject"}}
     ]
 }}"#
         )
         .unwrap();
         let config = Config::load(f.path()).unwrap();
         let mut app = App::new(config);
-        app.selected_section = 1; // Permissions
+        app.selected_section = 4; // MCPs
         app
     }
 
     #[test]
-    fn test_single_key_item_count() {
-        let app = test_app_with_permissions();
-        assert_eq!(app.current_section(), Section::Permissions);
-        assert_eq!(app.current_item_count(), 3);
+    fn test_switch_mcp_panel_keeps_selections() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Sidebar;
+        app.selected_mcp_permission = 1;
+        app.switch_mcp_panel();
+        assert_eq!(app.focus, Focus::Settings);
+        assert_eq!(app.mcp_focus, McpFocus::Permissions);
+        assert_eq!(app.selected_mcp_permission, 1);
+        app.switch_mcp_panel();
+        assert_eq!(app.mcp_focus, McpFocus::Configs);
+
+        app.selected_section = 1;
+        app.switch_mcp_panel();
+        assert_eq!(app.mcp_focus, McpFocus::Configs);
     }
 
     #[test]
-    fn test_single_key_navigate_items() {
-        let mut app = test_app_with_permissions();
-        app.focus = Focus::Settings;
-        assert_eq!(app.selected_setting, 0);
-        app.move_down();
-        assert_eq!(app.selected_setting, 1);
-        app.move_down();
-        assert_eq!(app.selected_setting, 2);
-        app.move_down();
-        assert_eq!(app.selected_setting, 2); // stays at last
+    fn test_mcp_split_initial_focus() {
+        let app = test_app_with_mcp_permissions();
+        assert_eq!(app.current_section(), Section::Mcps);
+        assert_eq!(app.mcp_focus, McpFocus::Configs);
+        assert_eq!(app.selected_mcp_permission, 0);
     }
 
     #[test]
-    fn test_single_key_activate_opens_item() {
-        let mut app = test_app_with_permissions();
+    fn test_mcp_server_names() {
+        let app = test_app_with_mcp_permissions();
+        let names = app.mcp_server_names();
+        assert_eq!(names, vec!["test-server"]);
+    }
+
+    #[test]
+    fn test_mcp_config_count() {
+        let app = test_app_with_mcp_permissions();
+        assert_eq!(app.mcp_config_count(), 1);
+    }
+
+    #[test]
+    fn test_mcp_navigate_configs_to_permissions() {
+        let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.selected_setting = 1;
-        let req = app.activate_setting();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.permissions");
-        assert_eq!(req.array_index, Some(1));
-        assert_eq!(req.value["tool"], Value::String("Read".into()));
+        assert_eq!(app.mcp_focus, McpFocus::Configs);
+
+        // Move down past configs (only 1 entry) should go to permissions
+        app.move_down();
+        assert_eq!(app.mcp_focus, McpFocus::Permissions);
+        assert_eq!(app.selected_mcp_permission, 0);
     }
 
     #[test]
-    fn test_single_key_delete_selected_item() {
-        let mut app = test_app_with_permissions();
+    fn test_mcp_navigate_permissions_to_configs() {
+        let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.selected_setting = 1; // "Read" item
-        app.delete_array_item();
-        assert_eq!(app.current_item_count(), 2);
-        // The remaining items should be Bash and edit_file
-        let arr = app.config.get("amp.permissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items[0]["tool"], Value::String("Bash".into()));
-        assert_eq!(items[1]["tool"], Value::String("edit_file".into()));
+        app.mcp_focus = McpFocus::Permissions;
+        app.selected_mcp_permission = 0;
+
+        // Move up from top of permissions should go back to configs
+        app.move_up();
+        assert_eq!(app.mcp_focus, McpFocus::Configs);
     }
 
     #[test]
-    fn test_single_key_delete_last_adjusts_selection() {
-        let mut app = test_app_with_permissions();
+    fn test_mcp_navigate_within_permissions() {
+        let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.selected_setting = 2; // last item
-        app.delete_array_item();
-        assert_eq!(app.current_item_count(), 2);
-        assert_eq!(app.selected_setting, 1); // adjusted
+        app.mcp_focus = McpFocus::Permissions;
+        app.selected_mcp_permission = 0;
+
+        app.move_down();
+        assert_eq!(app.selected_mcp_permission, 1);
+        app.move_down();
+        assert_eq!(app.selected_mcp_permission, 1); // stays at last
+
+        app.move_up();
+        assert_eq!(app.selected_mcp_permission, 0);
     }
 
     #[test]
-    fn test_single_key_empty_item_count() {
-        let mut app = test_app();
-        app.selected_section = 1; // Permissions
-        assert_eq!(app.current_item_count(), 0);
+    fn test_mcp_permission_item_count() {
+        let app = test_app_with_mcp_permissions();
+        assert_eq!(app.mcp_permission_item_count(), 2);
     }
 
     #[test]
-    fn test_single_key_reset_clears_array() {
-        let mut app = test_app_with_permissions();
+    fn test_mcp_activate_config_opens_form() {
+        let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.selected_setting = 1;
-        app.reset_setting();
-        assert_eq!(app.current_item_count(), 0);
-        assert_eq!(app.selected_setting, 0);
+        app.mcp_focus = McpFocus::Configs;
+        app.selected_setting = 0;
+
+        assert!(app.activate_setting().is_none());
+        assert_eq!(app.input_mode, InputMode::EditingObject);
+        let rows = app.object_rows();
+        let keys: Vec<&str> = rows.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, ["command", "args", "env", "url", "headers"]);
+        assert_eq!(rows[0].value, Some(Value::String("npx".into())));
+        assert_eq!(rows[2].value, None);
+
+        // Drill into env and add a variable.
+        app.object_select_down();
+        app.object_select_down();
+        app.change_object_value(true);
+        assert_eq!(
+            app.object_form.as_ref().unwrap().path,
+            ["test-server", "env"]
+        );
+        app.start_add_object_entry();
+        app.edit_buffer.set("GITHUB_TOKEN");
+        app.commit_object_key();
+        assert_eq!(app.input_mode, InputMode::EnteringObjectValue);
+        app.edit_buffer.set("ghp_x");
+        app.commit_object_value();
+        assert_eq!(
+            app.config.get("amp.mcpServers")["test-server"],
+            serde_json::json!({"command": "npx", "env": {"GITHUB_TOKEN": "ghp_x"}})
+        );
+
+        // Esc goes back up a level, then closes at the server.
+        app.object_back();
+        assert_eq!(app.object_form.as_ref().unwrap().selected, 2);
+        app.object_back();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.force_editor().is_some());
     }
 
     #[test]
-    fn test_start_add_custom_key() {
-        let mut app = test_app();
-        app.selected_section = 4; // Advanced
+    fn test_mcp_activate_permission_opens_item() {
+        let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.start_add_custom_key();
-        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
-        assert!(app.edit_buffer.is_empty());
+        app.mcp_focus = McpFocus::Permissions;
+        app.selected_mcp_permission = 1;
+
+        let req = app.activate_setting();
+        assert!(req.is_some());
+        let req = req.unwrap();
+        assert_eq!(req.key, "amp.mcpPermissions");
+        assert_eq!(req.array_index, Some(1));
+        assert_eq!(req.value["action"], Value::String("reject".into()));
     }
 
     #[test]
-    fn test_start_add_custom_key_not_advanced() {
-        let mut app = test_app();
-        app.selected_section = 0; // General
-        app.start_add_custom_key();
-        assert_eq!(app.input_mode, InputMode::Normal);
+    fn test_mcp_permission_add_starts_match_field() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Permissions;
+        app.add_array_item();
+        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchField);
     }
 
     #[test]
-    fn test_commit_key_name_empty() {
-        let mut app = test_app();
-        app.selected_section = 4;
-        app.input_mode = InputMode::EnteringKeyName;
-        app.edit_buffer = "  ".to_string();
-        app.commit_key_name();
-        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
+    fn test_mcp_match_field_empty_rejected() {
+        let mut app = test_app_with_mcp_permissions();
+        app.input_mode = InputMode::EnteringMcpMatchField;
+        app.edit_buffer.set("  ");
+        app.commit_mcp_match_field();
+        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchField);
         assert!(app.status_message.unwrap().contains("empty"));
     }
 
     #[test]
-    fn test_commit_key_name_duplicate() {
-        let mut app = test_app();
-        app.selected_section = 4;
-        app.input_mode = InputMode::EnteringKeyName;
-        app.edit_buffer = "amp.showCosts".to_string();
-        app.commit_key_name();
-        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
-        assert!(app.status_message.unwrap().contains("already exists"));
+    fn test_mcp_match_field_moves_to_value() {
+        let mut app = test_app_with_mcp_permissions();
+        app.input_mode = InputMode::EnteringMcpMatchField;
+        app.edit_buffer.set("command");
+        app.commit_mcp_match_field();
+        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchValue);
+        assert_eq!(app.pending_mcp_match_field.as_deref(), Some("command"));
     }
 
     #[test]
-    fn test_commit_key_name_success() {
-        let mut app = test_app();
-        app.selected_section = 4;
-        app.input_mode = InputMode::EnteringKeyName;
-        app.edit_buffer = "my.custom.key".to_string();
-        app.commit_key_name();
-        assert_eq!(app.input_mode, InputMode::SelectingType);
-        assert_eq!(app.pending_custom_key.as_deref(), Some("my.custom.key"));
-        assert!(app.edit_buffer.is_empty());
+    fn test_mcp_match_value_empty_rejected() {
+        let mut app = test_app_with_mcp_permissions();
+        app.input_mode = InputMode::EnteringMcpMatchValue;
+        app.pending_mcp_match_field = Some("command".to_string());
+        app.edit_buffer.set("  ");
+        app.commit_mcp_match_value();
+        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchValue);
+        assert!(app.status_message.unwrap().contains("empty"));
     }
 
     #[test]
-    fn test_commit_type_boolean() {
-        let mut app = test_app();
-        app.pending_custom_key = Some("my.bool.key".to_string());
-        app.selected_type = 0; // Boolean
-        let req = app.commit_type_selection();
-        assert!(req.is_none());
-        assert_eq!(app.config.get("my.bool.key"), Value::Bool(false));
-        assert_eq!(app.input_mode, InputMode::Normal);
-        assert!(app.pending_custom_key.is_none());
+    fn test_mcp_match_value_moves_to_level_select() {
+        let mut app = test_app_with_mcp_permissions();
+        app.input_mode = InputMode::EnteringMcpMatchValue;
+        app.pending_mcp_match_field = Some("url".to_string());
+        app.edit_buffer.set("https://example.com");
+        app.commit_mcp_match_value();
+        assert_eq!(app.input_mode, InputMode::SelectingMcpPermissionLevel);
+        assert_eq!(
+            app.pending_mcp_match_value.as_deref(),
+            Some("https://example.com")
+        );
     }
 
     #[test]
-    fn test_commit_type_string_enters_value_mode() {
-        let mut app = test_app();
-        app.pending_custom_key = Some("my.str.key".to_string());
-        app.selected_type = 1; // String
-        let req = app.commit_type_selection();
-        assert!(req.is_none());
-        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
-        assert!(app.pending_custom_key.is_some());
+    fn test_mcp_permission_level_navigation() {
+        let mut app = test_app_with_mcp_permissions();
+        app.selected_mcp_permission_level = 0;
+        app.mcp_permission_level_up();
+        assert_eq!(app.selected_mcp_permission_level, 0); // stays at 0
+        app.mcp_permission_level_down();
+        assert_eq!(app.selected_mcp_permission_level, 1);
+        app.mcp_permission_level_down();
+        assert_eq!(app.selected_mcp_permission_level, 1); // stays at last (only 2 options)
     }
 
     #[test]
-    fn test_commit_type_number_enters_value_mode() {
+    fn test_mcp_permission_commit_adds_rule() {
         let mut app = test_app();
-        app.pending_custom_key = Some("my.num.key".to_string());
-        app.selected_type = 2; // Number
-        let req = app.commit_type_selection();
-        assert!(req.is_none());
-        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
+        app.pending_mcp_match_field = Some("command".to_string());
+        app.pending_mcp_match_value = Some("npx".to_string());
+        app.selected_mcp_permission_level = 0; // allow
+        app.commit_mcp_permission_level();
+        assert_eq!(app.input_mode, InputMode::ConfirmMcpEdit);
+
+        let arr = app.config.get("amp.mcpPermissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0]["matches"],
+            Value::Object({
+                let mut m = serde_json::Map::new();
+                m.insert("command".into(), Value::String("npx".into()));
+                m
+            })
+        );
+        assert_eq!(items[0]["action"], Value::String("allow".into()));
     }
 
     #[test]
-    fn test_commit_type_array() {
+    fn test_mcp_permission_full_flow() {
         let mut app = test_app();
-        app.pending_custom_key = Some("my.arr.key".to_string());
-        app.selected_type = 3; // Array
-        let req = app.commit_type_selection();
-        assert!(req.is_none());
-        assert_eq!(app.config.get("my.arr.key"), Value::Array(vec![]));
+        app.selected_section = 4; // MCPs
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Permissions;
+
+        // Step 1: start add
+        app.add_array_item();
+        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchField);
+
+        // Step 2: enter match field
+        app.edit_buffer.set("url");
+        app.commit_mcp_match_field();
+        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchValue);
+
+        // Step 3: enter match value
+        app.edit_buffer.set("https://evil.com/*");
+        app.commit_mcp_match_value();
+        assert_eq!(app.input_mode, InputMode::SelectingMcpPermissionLevel);
+
+        // Step 4: select reject (index 1)
+        app.mcp_permission_level_down();
+        assert_eq!(app.selected_mcp_permission_level, 1);
+        app.commit_mcp_permission_level();
+        assert_eq!(app.input_mode, InputMode::ConfirmMcpEdit);
+
+        // Step 5: decline editor
+        app.decline_mcp_edit();
         assert_eq!(app.input_mode, InputMode::Normal);
+
+        let arr = app.config.get("amp.mcpPermissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["action"], Value::String("reject".into()));
     }
 
     #[test]
-    fn test_commit_type_object_returns_editor_request() {
+    fn test_mcp_confirm_edit_returns_editor_request() {
         let mut app = test_app();
-        app.pending_custom_key = Some("my.obj.key".to_string());
-        app.selected_type = 4; // Object
-        let req = app.commit_type_selection();
+        app.pending_mcp_match_field = Some("command".to_string());
+        app.pending_mcp_match_value = Some("npx".to_string());
+        app.selected_mcp_permission_level = 0;
+        app.commit_mcp_permission_level();
+        assert_eq!(app.input_mode, InputMode::ConfirmMcpEdit);
+
+        let req = app.confirm_mcp_edit();
         assert!(req.is_some());
         let req = req.unwrap();
-        assert_eq!(req.key, "my.obj.key");
-        assert!(req.value.is_object());
+        assert_eq!(req.key, "amp.mcpPermissions");
+        assert_eq!(req.array_index, Some(0));
         assert_eq!(app.input_mode, InputMode::Normal);
     }
 
     #[test]
-    fn test_commit_custom_value_string() {
-        let mut app = test_app();
-        app.pending_custom_key = Some("my.str.key".to_string());
-        app.selected_type = 1; // String
-        app.input_mode = InputMode::EnteringCustomValue;
-        app.edit_buffer = "hello world".to_string();
-        app.commit_custom_value();
-        assert_eq!(
-            app.config.get("my.str.key"),
-            Value::String("hello world".into())
-        );
-        assert_eq!(app.input_mode, InputMode::Normal);
+    fn test_mcp_delete_permission_item() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Permissions;
+        app.selected_mcp_permission = 0;
+
+        app.delete_array_item();
+        assert_eq!(app.mcp_permission_item_count(), 1);
+        let arr = app.config.get("amp.mcpPermissions");
+        let items = arr.as_array().unwrap();
+        assert_eq!(items[0]["action"], Value::String("reject".into()));
     }
 
     #[test]
-    fn test_commit_custom_value_number() {
-        let mut app = test_app();
-        app.pending_custom_key = Some("my.num.key".to_string());
-        app.selected_type = 2; // Number
-        app.input_mode = InputMode::EnteringCustomValue;
-        app.edit_buffer = "42".to_string();
-        app.commit_custom_value();
-        assert_eq!(app.config.get("my.num.key"), Value::Number(42.into()));
-        assert_eq!(app.input_mode, InputMode::Normal);
+    fn test_mcp_delete_last_adjusts_selection() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Permissions;
+        app.selected_mcp_permission = 1; // last item
+
+        app.delete_array_item();
+        assert_eq!(app.mcp_permission_item_count(), 1);
+        assert_eq!(app.selected_mcp_permission, 0);
     }
 
     #[test]
-    fn test_commit_custom_value_invalid_number() {
-        let mut app = test_app();
-        app.pending_custom_key = Some("my.num.key".to_string());
-        app.selected_type = 2; // Number
-        app.input_mode = InputMode::EnteringCustomValue;
-        app.edit_buffer = "not a number".to_string();
-        app.commit_custom_value();
-        assert!(app.status_message.unwrap().contains("Invalid"));
-        assert!(app.pending_custom_key.is_some());
-        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
+    fn test_mcp_reset_permissions() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Permissions;
+
+        app.reset_setting();
+        assert_eq!(app.mcp_permission_item_count(), 0);
+        assert_eq!(app.selected_mcp_permission, 0);
     }
 
     #[test]
-    fn test_type_select_navigation() {
-        let mut app = test_app();
-        app.selected_type = 0;
-        app.type_select_up();
-        assert_eq!(app.selected_type, 0); // stays at 0
-        app.type_select_down();
-        assert_eq!(app.selected_type, 1);
-        app.type_select_down();
-        assert_eq!(app.selected_type, 2);
-        // Go to last
-        for _ in 0..10 {
-            app.type_select_down();
-        }
-        assert_eq!(app.selected_type, CustomKeyType::ALL.len() - 1);
+    fn test_mcp_reset_configs_deletes_server() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Configs;
+        app.selected_setting = 0;
+
+        app.reset_setting();
+        let val = app.config.get("amp.mcpServers");
+        assert!(val.as_object().unwrap().is_empty());
+        assert!(app.status_message.unwrap().contains("Removed server"));
     }
 
     #[test]
-    fn test_cancel_edit_clears_custom_key_state() {
-        let mut app = test_app();
-        app.input_mode = InputMode::SelectingType;
-        app.pending_custom_key = Some("my.key".to_string());
-        app.selected_type = 2;
-        app.cancel_edit();
-        assert_eq!(app.input_mode, InputMode::Normal);
-        assert!(app.pending_custom_key.is_none());
-        assert_eq!(app.selected_type, 0);
+    fn test_mcp_force_editor_configs() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Configs;
+        app.selected_setting = 0;
+
+        let req = app.force_editor();
+        assert!(req.is_some());
+        let req = req.unwrap();
+        assert_eq!(req.key, "amp.mcpServers");
+        assert_eq!(req.object_key.as_deref(), Some("test-server"));
+        assert!(req.array_index.is_none());
+        assert_eq!(req.value["command"], Value::String("npx".into()));
     }
 
     #[test]
-    fn test_add_custom_key_full_flow_string() {
-        // Use an app with a non-array unknown key so add_array_item starts the
-        // "add custom key" flow instead of trying to add to an existing array.
-        let mut f = NamedTempFile::new().unwrap();
-        write!(f, r#"{{"amp.experimental.flag": true}}"#).unwrap();
-        let config = Config::load(f.path()).unwrap();
-        let mut app = App::new(config);
-        app.selected_section = 4; // Advanced
+    fn test_mcp_force_editor_permissions() {
+        let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Permissions;
+        app.selected_mcp_permission = 1;
 
-        // Step 1: start
-        app.add_array_item();
-        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
-
-        // Step 2: enter key name
-        app.edit_buffer = "my.custom.setting".to_string();
-        app.commit_key_name();
-        assert_eq!(app.input_mode, InputMode::SelectingType);
+        let req = app.force_editor();
+        assert!(req.is_some());
+        let req = req.unwrap();
+        assert_eq!(req.key, "amp.mcpPermissions");
+        assert_eq!(req.array_index, Some(1));
+    }
 
-        // Step 3: select string type
-        app.selected_type = 1; // String
-        app.commit_type_selection();
-        assert_eq!(app.input_mode, InputMode::EnteringCustomValue);
+    #[test]
+    fn test_json_edit_applies_valid_json() {
+        let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
+        app.mcp_focus = McpFocus::Configs;
+        app.selected_setting = 0;
 
-        // Step 4: enter value
-        app.edit_buffer = "my value".to_string();
-        app.commit_custom_value();
+        app.start_json_edit();
+        assert_eq!(app.input_mode, InputMode::EditingJson);
+        // Put the cursor after the opening brace and add a field.
+        app.edit_json(|t| {
+            t.move_end();
+            t.newline();
+            for c in "\"url\": \"https://x\",".chars() {
+                t.insert_char(c);
+            }
+        });
+        assert!(app.json_edit.as_ref().unwrap().error.is_none());
+        app.commit_json_edit();
         assert_eq!(app.input_mode, InputMode::Normal);
-        assert_eq!(
-            app.config.get("my.custom.setting"),
-            Value::String("my value".into())
-        );
+        let servers = app.config.get("amp.mcpServers");
+        assert_eq!(servers["test-server"]["url"], "https://x");
+        assert_eq!(servers["test-server"]["command"], "npx");
     }
 
     #[test]
-    fn test_permission_add_starts_tool_prompt() {
-        let mut app = test_app();
-        app.selected_section = 1; // Permissions
+    fn test_json_edit_keeps_invalid_json_open() {
+        let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.add_array_item();
-        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
-        assert!(app.edit_buffer.is_empty());
+        app.mcp_focus = McpFocus::Permissions;
+        app.selected_mcp_permission = 0;
+        let before = app.config.get("amp.mcpPermissions");
+
+        app.start_json_edit();
+        app.edit_json(|t| {
+            t.move_down();
+            t.move_end();
+            t.backspace();
+        });
+        let error = app.json_edit.as_ref().unwrap().error.clone();
+        assert!(error.is_some());
+        app.commit_json_edit();
+        assert_eq!(app.input_mode, InputMode::EditingJson);
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Invalid JSON"));
+
+        app.cancel_edit();
+        assert!(app.json_edit.is_none());
+        assert_eq!(app.config.get("amp.mcpPermissions"), before);
     }
 
     #[test]
-    fn test_permission_tool_empty_rejected() {
+    fn test_invalid_editor_edit_can_be_reopened() {
         let mut app = test_app();
-        app.input_mode = InputMode::EnteringPermissionTool;
-        app.edit_buffer = "  ".to_string();
-        app.commit_permission_tool();
-        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
-        assert!(app.status_message.unwrap().contains("empty"));
+        app.focus = Focus::Settings;
+        let request = app.force_editor().unwrap();
+        let session = EditSession::new(&request.value).unwrap();
+
+        app.keep_invalid_edit(request.clone(), session, "EOF at line 1".into());
+        assert_eq!(app.input_mode, InputMode::ConfirmReopenEditor);
+        let reopened = app.confirm_reopen_editor().unwrap();
+        assert_eq!(reopened.key, request.key);
+        assert!(app.take_editor_draft().is_some());
+        assert!(app.take_editor_draft().is_none());
+
+        let session = EditSession::new(&request.value).unwrap();
+        app.keep_invalid_edit(request, session, "EOF".into());
+        app.decline_reopen_editor();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.editor_draft.is_none());
     }
 
     #[test]
-    fn test_permission_tool_moves_to_level_select() {
+    fn test_json_edit_refuses_scalars() {
         let mut app = test_app();
-        app.input_mode = InputMode::EnteringPermissionTool;
-        app.edit_buffer = "Bash".to_string();
-        app.commit_permission_tool();
-        assert_eq!(app.input_mode, InputMode::SelectingPermissionLevel);
-        assert_eq!(app.pending_permission_tool.as_deref(), Some("Bash"));
-        assert_eq!(app.selected_permission_level, 0);
+        app.focus = Focus::Settings;
+        app.start_json_edit();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.status_message.is_some());
     }
 
     #[test]
-    fn test_permission_level_navigation() {
-        let mut app = test_app();
-        app.selected_permission_level = 0;
-        app.permission_level_up();
-        assert_eq!(app.selected_permission_level, 0);
-        app.permission_level_down();
-        assert_eq!(app.selected_permission_level, 1);
-        app.permission_level_down();
-        assert_eq!(app.selected_permission_level, 2);
-        app.permission_level_down();
-        assert_eq!(app.selected_permission_level, 3); // delegate
-        app.permission_level_down();
-        assert_eq!(app.selected_permission_level, 3); // stays at last
+    fn test_mcp_import_checklist() {
+        let mut app = test_app_with_mcp_permissions();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claude_desktop_config.json");
+        std::fs::write(
+            &path,
+            r#"{"mcpServers": {"fresh": {"url": "https://f"}, "test-server": {"command": "uvx"}}}"#,
+        )
+        .unwrap();
+
+        app.start_mcp_import();
+        assert_eq!(app.input_mode, InputMode::SelectingImportSource);
+        app.load_import_candidates(Source::Claude, &path);
+        assert_eq!(app.input_mode, InputMode::SelectingImportServers);
+        let checked: Vec<(&str, bool, bool)> = app
+            .import_candidates
+            .iter()
+            .map(|c| (c.name.as_str(), c.checked, c.exists))
+            .collect();
+        assert_eq!(
+            checked,
+            vec![("fresh", true, false), ("test-server", false, true)]
+        );
+
+        app.import_select_down();
+        app.toggle_import_candidate();
+        app.commit_mcp_import();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.import_candidates.is_empty());
+        let servers = app.config.get("amp.mcpServers");
+        assert_eq!(servers["fresh"]["url"], "https://f");
+        assert_eq!(servers["test-server"]["command"], "uvx");
+        assert!(app.config.is_dirty());
     }
 
     #[test]
-    fn test_permission_commit_adds_rule() {
+    fn test_move_permission_rule() {
         let mut app = test_app();
-        app.pending_permission_tool = Some("Bash".to_string());
-        app.selected_permission_level = 1; // allow
-        app.commit_permission_level();
-        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
-        assert!(app.pending_permission_tool.is_none());
-
-        let arr = app.config.get("amp.permissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items.len(), 1);
-        assert_eq!(items[0]["tool"], Value::String("Bash".into()));
-        assert_eq!(items[0]["action"], Value::String("allow".into()));
+        app.selected_section = 2;
+        app.focus = Focus::Settings;
+        assert!(app.current_section().is_single_key());
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([{"tool": "a"}, {"tool": "b"}, {"tool": "c"}]),
+        );
+        app.selected_setting = 0;
+        app.move_array_item(true);
+        assert_eq!(app.selected_setting, 0);
+        app.move_array_item(false);
+        app.move_array_item(false);
+        assert_eq!(app.selected_setting, 2);
+        app.move_array_item(false);
+        assert_eq!(
+            app.config.get("amp.permissions"),
+            serde_json::json!([{"tool": "b"}, {"tool": "c"}, {"tool": "a"}])
+        );
+        assert_eq!(app.selected_setting, 2);
     }
 
     #[test]
-    fn test_permission_full_flow() {
+    fn test_sorting_rules_only_changes_the_view_until_applied() {
         let mut app = test_app();
-        app.selected_section = 1; // Permissions
+        app.selected_section = 2;
         app.focus = Focus::Settings;
+        let rules = serde_json::json!([
+            {"tool": "edit_file", "action": "ask"},
+            {"tool": "Bash", "action": "allow"},
+            {"action": "reject"},
+            {"tool": "bash", "action": "reject"}
+        ]);
+        app.config.set("amp.permissions", rules.clone());
+        app.config.save().unwrap();
+
+        app.cycle_rule_sort();
+        assert_eq!(app.rule_sort, Some("tool"));
+        assert_eq!(app.rule_rows(), vec![1, 3, 0, 2]);
+        assert_eq!(app.config.get("amp.permissions"), rules);
+
+        // Navigation follows the table, and the selection stays an index
+        // into the file's array.
+        app.selected_setting = 0;
+        app.move_down();
+        assert_eq!(app.selected_setting, 2);
+        app.move_up();
+        assert_eq!(app.selected_setting, 0);
+        app.select_last();
+        assert_eq!(app.selected_setting, 2);
+        app.select_first();
+        assert_eq!(app.selected_setting, 1);
+        app.move_array_item(false);
+        assert_eq!(app.config.get("amp.permissions"), rules);
 
-        // Step 1: press 'a' to start
-        app.add_array_item();
-        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
-
-        // Step 2: enter tool name
-        app.edit_buffer = "Read".to_string();
-        app.commit_permission_tool();
-        assert_eq!(app.input_mode, InputMode::SelectingPermissionLevel);
+        app.cycle_rule_sort();
+        assert_eq!(app.rule_sort, Some("action"));
+        assert_eq!(app.rule_rows(), vec![1, 0, 2, 3]);
 
-        // Step 3: select "reject" (index 2)
-        app.permission_level_down();
-        app.permission_level_down();
-        assert_eq!(app.selected_permission_level, 2);
-        app.commit_permission_level();
+        app.selected_setting = 0;
+        app.apply_rule_sort();
+        assert_eq!(app.rule_sort, None);
+        assert_eq!(app.selected_setting, 1);
+        assert_eq!(
+            app.config.get("amp.permissions"),
+            serde_json::json!([
+                {"tool": "Bash", "action": "allow"},
+                {"tool": "edit_file", "action": "ask"},
+                {"action": "reject"},
+                {"tool": "bash", "action": "reject"}
+            ])
+        );
+        assert!(app.config.is_dirty());
 
-        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
-        let arr = app.config.get("amp.permissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items.len(), 1);
-        assert_eq!(items[0]["tool"], Value::String("Read".into()));
-        assert_eq!(items[0]["action"], Value::String("reject".into()));
+        app.apply_rule_sort();
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Sort the permission rules"));
     }
 
     #[test]
-    fn test_cancel_permission_clears_state() {
-        let mut app = test_app();
-        app.input_mode = InputMode::SelectingPermissionLevel;
-        app.pending_permission_tool = Some("Bash".to_string());
-        app.selected_permission_level = 1;
-        app.cancel_edit();
-        assert_eq!(app.input_mode, InputMode::Normal);
-        assert!(app.pending_permission_tool.is_none());
-        assert_eq!(app.selected_permission_level, 0);
+    fn test_step_number() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"amp.tools.stopTimeout": 5}"#).unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.focus = Focus::Settings;
+        app.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == Section::Tools)
+            .unwrap();
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.tools.stopTimeout"))
+            .unwrap();
+
+        app.step_number(true);
+        assert_eq!(app.config.get("amp.tools.stopTimeout"), 15);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("amp.tools.stopTimeout = 15 seconds")
+        );
+        app.step_number(false);
+        app.step_number(false);
+        assert_eq!(app.config.get("amp.tools.stopTimeout"), 1);
+        app.step_number(false);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("amp.tools.stopTimeout is already at its minimum (1)")
+        );
     }
 
     #[test]
-    fn test_confirm_advanced_edit_returns_editor_request() {
-        let mut app = test_app();
-        // Add a permission rule first
-        app.pending_permission_tool = Some("Bash".to_string());
-        app.selected_permission_level = 0; // ask
-        app.commit_permission_level();
-        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
+    fn test_wrong_type_is_flagged_and_fixed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"amp.showCosts": "true", "amp.tools.stopTimeout": "abc"}"#,
+        )
+        .unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("2 settings have the wrong type"));
 
-        let req = app.confirm_advanced_edit();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.permissions");
-        assert_eq!(req.array_index, Some(0));
-        assert_eq!(req.value["tool"], Value::String("Bash".into()));
-        assert_eq!(req.value["action"], Value::String("ask".into()));
-        assert_eq!(app.input_mode, InputMode::Normal);
+        app.focus = Focus::Settings;
+        app.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == Section::General)
+            .unwrap();
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.showCosts"))
+            .unwrap();
+        assert!(app.has_wrong_type("amp.showCosts"));
+        assert_eq!(
+            app.selected_type_issue().as_deref(),
+            Some("amp.showCosts is stored as a string, not a boolean; press F to coerce to boolean true")
+        );
+        app.fix_type();
+        assert_eq!(app.config.get("amp.showCosts"), Value::Bool(true));
+        assert!(app.selected_type_issue().is_none());
+        app.fix_type();
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .contains("already has the right type"));
+
+        app.config
+            .set("amp.showCosts", Value::String("maybe".into()));
+        app.fix_type();
+        assert_eq!(
+            app.config.get("amp.showCosts"),
+            Value::String("maybe".into())
+        );
+        assert!(app
+            .selected_type_issue()
+            .unwrap()
+            .ends_with("edit it or reset it"));
     }
 
     #[test]
-    fn test_decline_advanced_edit_returns_to_normal() {
-        let mut app = test_app();
-        app.input_mode = InputMode::ConfirmAdvancedEdit;
-        app.decline_advanced_edit();
-        assert_eq!(app.input_mode, InputMode::Normal);
+    fn test_deprecated_keys_are_flagged_and_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"amp.hideCosts": true}"#).unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("amp.hideCosts is deprecated (marked ⚠)")
+        );
+
+        app.focus = Focus::Settings;
+        app.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == Section::Advanced)
+            .unwrap();
+        assert_eq!(
+            app.selected_deprecation().as_deref(),
+            Some("amp.hideCosts is deprecated, replaced by amp.showCosts (inverted); press M to migrate all")
+        );
+        app.migrate_all();
+        assert_eq!(app.input_mode, InputMode::ViewingReport);
+        assert_eq!(
+            app.config.get_raw("amp.showCosts"),
+            Some(&Value::Bool(false))
+        );
+        assert!(app.config.get_raw("amp.hideCosts").is_none());
+        assert!(app.selected_deprecation().is_none());
+
+        app.cancel_edit();
+        app.migrate_all();
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("No deprecated settings to migrate.")
+        );
     }
 
     #[test]
-    fn test_permission_full_flow_with_decline() {
+    fn test_pattern_validation() {
         let mut app = test_app();
-        app.selected_section = 1; // Permissions
+        app.selected_section = 2;
         app.focus = Focus::Settings;
-
         app.add_array_item();
-        app.edit_buffer = "Bash".to_string();
+        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
+        app.edit_buffer.set("mcp__[gh");
+        assert_eq!(app.pattern_input_error().as_deref(), Some("unclosed ["));
         app.commit_permission_tool();
-        app.commit_permission_level(); // defaults to "ask"
-        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
-
-        app.decline_advanced_edit();
-        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.input_mode, InputMode::EnteringPermissionTool);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Invalid tool pattern: unclosed [")
+        );
+        app.edit_buffer.set("mcp__[gh]*");
+        app.commit_permission_tool();
+        assert_eq!(app.input_mode, InputMode::SelectingPermissionLevel);
+        app.cancel_edit();
 
-        let arr = app.config.get("amp.permissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items.len(), 1);
-        assert_eq!(items[0]["tool"], Value::String("Bash".into()));
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([
+                {"tool": "Bash", "action": "allow"},
+                {"tool": "Bash", "matches": {"cmd": "/(git/"}, "action": "ask"}
+            ]),
+        );
+        assert_eq!(app.selected_pattern_error(), None);
+        app.selected_setting = 1;
+        assert_eq!(
+            app.selected_pattern_error().as_deref(),
+            Some("cmd \"/(git/\": unclosed (")
+        );
     }
 
     #[test]
-    fn test_delegate_level_prompts_for_to() {
+    fn test_remove_shadowed_rules() {
         let mut app = test_app();
-        app.pending_permission_tool = Some("Bash".to_string());
-        app.selected_permission_level = 3; // Delegate
-        app.commit_permission_level();
-        assert_eq!(app.input_mode, InputMode::EnteringDelegateTo);
-        assert!(app.pending_permission_tool.is_some());
-    }
+        app.selected_section = 2;
+        app.focus = Focus::Settings;
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([
+                {"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"},
+                {"tool": "*", "action": "ask"},
+                {"tool": "Read", "action": "allow"},
+                {"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"}
+            ]),
+        );
+        assert_eq!(app.selected_rule_shadowing(), None);
+        app.selected_setting = 3;
+        assert_eq!(
+            app.selected_rule_shadowing().as_deref(),
+            Some("Rule 3 duplicates rule 0; press R to remove shadowed rules")
+        );
 
-    #[test]
-    fn test_delegate_to_empty_rejected() {
-        let mut app = test_app();
-        app.input_mode = InputMode::EnteringDelegateTo;
-        app.pending_permission_tool = Some("Bash".to_string());
-        app.edit_buffer = "  ".to_string();
-        app.commit_delegate_to();
-        assert_eq!(app.input_mode, InputMode::EnteringDelegateTo);
-        assert!(app.status_message.unwrap().contains("empty"));
+        app.dispatch(Action::RemoveShadowedRules);
+        assert_eq!(
+            app.config.get("amp.permissions"),
+            serde_json::json!([
+                {"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"},
+                {"tool": "*", "action": "ask"}
+            ])
+        );
+        assert_eq!(app.selected_setting, 1);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Removed 2 shadowed or duplicate permission rules.")
+        );
+        app.dispatch(Action::RemoveShadowedRules);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("No shadowed or duplicate permission rules.")
+        );
     }
 
     #[test]
-    fn test_delegate_full_flow() {
+    fn test_rule_form() {
         let mut app = test_app();
-        app.selected_section = 1; // Permissions
+        app.selected_section = 2;
         app.focus = Focus::Settings;
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([
+                {"tool": "Bash", "action": "allow"},
+                "not an object"
+            ]),
+        );
+        assert!(app.activate_setting().is_none());
+        assert_eq!(app.input_mode, InputMode::EditingRule);
+
+        app.edit_rule_form(|f| {
+            f.add_match();
+            f.text_mut().unwrap().push_str("cmd");
+        });
+        app.commit_rule_form();
+        assert_eq!(app.input_mode, InputMode::EditingRule);
+        assert_eq!(
+            app.rule_form.as_ref().unwrap().field,
+            crate::rule_form::Field::MatchPattern(0)
+        );
 
-        app.add_array_item();
-        app.edit_buffer = "*".to_string();
-        app.commit_permission_tool();
-
-        // Select delegate (index 3)
-        app.selected_permission_level = 3;
-        app.commit_permission_level();
-        assert_eq!(app.input_mode, InputMode::EnteringDelegateTo);
-
-        app.edit_buffer = "my-permission-helper".to_string();
-        app.commit_delegate_to();
-        assert_eq!(app.input_mode, InputMode::ConfirmAdvancedEdit);
-
-        app.decline_advanced_edit();
+        app.edit_rule_form(|f| f.text_mut().unwrap().push_str("git *"));
+        app.commit_rule_form();
         assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.config.get("amp.permissions")[0],
+            serde_json::json!({"tool": "Bash", "action": "allow", "matches": {"cmd": "git *"}})
+        );
 
-        let arr = app.config.get("amp.permissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items.len(), 1);
-        assert_eq!(items[0]["tool"], Value::String("*".into()));
-        assert_eq!(items[0]["action"], Value::String("delegate".into()));
-        assert_eq!(items[0]["to"], Value::String("my-permission-helper".into()));
-    }
+        app.activate_setting();
+        app.edit_rule_form(|f| f.cycle_action(false));
+        let req = app.rule_form_to_editor().unwrap();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(req.array_index, Some(0));
+        assert_eq!(req.value["action"], "ask");
 
-    fn test_app_with_mcp_permissions() -> App {
-        let mut f = NamedTempFile::new().unwrap();
-        write!(
-            f,
-            r#"{{
-    "amp.mcpServers": {{"test-server": {{"command": "npx"}}}},
-    "amp.mcpPermissions": [
-        {{"matches": {{"command": "npx"}}, "action": "allow"}},
-        {{"matches": {{"url": "https://evil.com"}}, "action": "reject"}}
-    ]
-}}"#
-        )
-        .unwrap();
-        let config = Config::load(f.path()).unwrap();
-        let mut app = App::new(config);
-        app.selected_section = 3; // MCPs
-        app
+        app.selected_setting = 1;
+        assert!(app.activate_setting().is_some());
     }
 
     #[test]
-    fn test_mcp_split_initial_focus() {
-        let app = test_app_with_mcp_permissions();
-        assert_eq!(app.current_section(), Section::Mcps);
-        assert_eq!(app.mcp_focus, McpFocus::Configs);
-        assert_eq!(app.selected_mcp_permission, 0);
-    }
+    fn test_permission_tester() {
+        let mut app = test_app();
+        app.start_permission_test();
+        assert_eq!(app.input_mode, InputMode::Normal);
 
-    #[test]
-    fn test_mcp_server_names() {
-        let app = test_app_with_mcp_permissions();
-        let names = app.mcp_server_names();
-        assert_eq!(names, vec!["test-server"]);
-    }
+        app.selected_section = 2;
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([
+                {"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"},
+                {"tool": "*", "action": "ask"}
+            ]),
+        );
+        app.start_permission_test();
+        assert_eq!(app.input_mode, InputMode::TestingPermissions);
+        for c in "git log".chars() {
+            app.edit_permission_test(|s| s.push(c));
+        }
+        let eval = app.permission_test_result().unwrap().unwrap();
+        assert_eq!(eval.matched, Some(0));
+
+        app.switch_permission_test_field();
+        app.edit_permission_test(String::clear);
+        app.edit_permission_test(|s| s.push_str("Read"));
+        assert!(app.permission_test_result().unwrap().is_err());
+        app.switch_permission_test_field();
+        app.edit_permission_test(|s| *s = r#"{"path": "x"}"#.to_string());
+        let eval = app.permission_test_result().unwrap().unwrap();
+        assert_eq!(eval.decision.as_deref(), Some("ask"));
 
-    #[test]
-    fn test_mcp_config_count() {
-        let app = test_app_with_mcp_permissions();
-        assert_eq!(app.mcp_config_count(), 1);
+        app.cancel_edit();
+        assert!(app.permission_test.is_none());
     }
 
     #[test]
-    fn test_mcp_navigate_configs_to_permissions() {
+    fn test_env_editor() {
         let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        assert_eq!(app.mcp_focus, McpFocus::Configs);
+        app.start_env_edit();
+        assert_eq!(app.input_mode, InputMode::EditingEnv);
+        assert!(app.env_entries().is_empty());
+
+        app.start_add_env();
+        app.edit_buffer.set("GITHUB_TOKEN");
+        app.commit_env_key();
+        assert_eq!(app.input_mode, InputMode::EnteringEnvValue);
+        app.edit_buffer.set("ghp_123");
+        app.commit_env_value();
+        app.start_add_env();
+        app.edit_buffer.set("DEBUG");
+        app.commit_env_key();
+        app.edit_buffer.set("1");
+        app.commit_env_value();
+        // Variables stay in the order they were added.
+        assert_eq!(app.selected_env, 1);
+        assert_eq!(
+            app.config.get("amp.mcpServers")["test-server"]["env"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            ["GITHUB_TOKEN", "DEBUG"]
+        );
 
-        // Move down past configs (only 1 entry) should go to permissions
-        app.move_down();
-        assert_eq!(app.mcp_focus, McpFocus::Permissions);
-        assert_eq!(app.selected_mcp_permission, 0);
+        app.start_add_env();
+        app.edit_buffer.set("DEBUG");
+        app.commit_env_key();
+        assert_eq!(app.input_mode, InputMode::EnteringEnvKey);
+        app.cancel_env_input();
+
+        app.start_edit_env();
+        assert_eq!(app.edit_buffer, "1");
+        app.edit_buffer.set("2");
+        app.commit_env_value();
+        assert_eq!(app.env_entries()[1], ("DEBUG".to_string(), "2".to_string()));
+
+        app.delete_env();
+        app.delete_env();
+        assert!(app.config.get("amp.mcpServers")["test-server"]
+            .get("env")
+            .is_none());
+        app.cancel_edit();
+        assert!(app.env_server.is_none());
     }
 
     #[test]
-    fn test_mcp_navigate_permissions_to_configs() {
-        let mut app = test_app_with_mcp_permissions();
-        app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-        app.selected_mcp_permission = 0;
+    fn test_sensitive_values_are_masked_until_revealed() {
+        let mut app = test_app();
+        assert!(app.is_sensitive("amp.bitbucketToken"));
+        assert!(!app.is_sensitive("amp.showCosts"));
+        app.config.set("my.apiToken", Value::String("t".into()));
+        assert!(app.is_sensitive("my.apiToken"));
 
-        // Move up from top of permissions should go back to configs
-        app.move_up();
-        assert_eq!(app.mcp_focus, McpFocus::Configs);
+        assert_eq!(app.mask("hunter2"), SECRET_MASK);
+        assert_eq!(app.mask(""), "");
+        app.toggle_reveal_secrets();
+        assert_eq!(app.mask("hunter2"), "hunter2");
     }
 
     #[test]
-    fn test_mcp_navigate_within_permissions() {
-        let mut app = test_app_with_mcp_permissions();
-        app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-        app.selected_mcp_permission = 0;
+    fn test_profile_picker_saves_and_loads() {
+        let mut app = test_app();
+        let dir = tempfile::tempdir().unwrap();
+        app.profiles_dir = Some(dir.path().to_path_buf());
+        app.config
+            .set("amp.terminal.theme", Value::String("nord".into()));
 
-        app.move_down();
-        assert_eq!(app.selected_mcp_permission, 1);
-        app.move_down();
-        assert_eq!(app.selected_mcp_permission, 1); // stays at last
+        app.start_profile_picker();
+        assert_eq!(app.input_mode, InputMode::SelectingProfile);
+        assert!(app.profile_candidates.is_empty());
+        app.commit_profile_selection();
+        assert_eq!(app.input_mode, InputMode::EnteringProfileName);
+        app.edit_buffer.set("work");
+        app.commit_profile_name();
+        assert_eq!(app.status_message.as_deref(), Some("Saved profile work"));
 
-        app.move_up();
-        assert_eq!(app.selected_mcp_permission, 0);
+        app.config
+            .set("amp.terminal.theme", Value::String("light".into()));
+        app.start_profile_picker();
+        assert_eq!(app.profile_candidates, vec!["work"]);
+        app.profile_select_down();
+        app.profile_select_down();
+        assert_eq!(app.selected_profile, 1);
+        app.profile_select_up();
+        app.commit_profile_selection();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.config.get("amp.terminal.theme"), "nord");
     }
 
     #[test]
-    fn test_mcp_permission_item_count() {
-        let app = test_app_with_mcp_permissions();
-        assert_eq!(app.mcp_permission_item_count(), 2);
+    fn test_preset_picker() {
+        let mut app = test_app();
+        let dir = tempfile::tempdir().unwrap();
+        app.presets_dir = Some(dir.path().to_path_buf());
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([{"tool": "web_search", "action": "reject"}]),
+        );
+        let pick = |app: &mut App, name: &str, replace: bool| {
+            app.dispatch(Action::PermissionPresets);
+            assert_eq!(app.input_mode, InputMode::SelectingPreset);
+            app.selected_preset = app
+                .preset_candidates
+                .iter()
+                .position(|p| p.name == name)
+                .unwrap();
+            app.commit_preset_selection(replace);
+        };
+
+        pick(&mut app, "no-network", false);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Added 2 rules from no-network (1 already there)")
+        );
+        assert_eq!(
+            app.config.get("amp.permissions").as_array().unwrap().len(),
+            3
+        );
+
+        pick(&mut app, "git-only", true);
+        assert_eq!(
+            app.config.get("amp.permissions").as_array().unwrap().len(),
+            3
+        );
+        assert_eq!(
+            app.config.get("amp.permissions")[2],
+            serde_json::json!({"tool": "Bash", "action": "reject"})
+        );
+
+        // The row after the presets saves the rules as a new one.
+        app.dispatch(Action::PermissionPresets);
+        app.selected_preset = app.preset_candidates.len();
+        app.preset_select_down();
+        app.commit_preset_selection(false);
+        assert_eq!(app.input_mode, InputMode::EnteringPresetName);
+        app.edit_buffer.set("mine");
+        app.commit_preset_name();
+        assert_eq!(app.status_message.as_deref(), Some("Saved preset mine"));
+        app.config.set("amp.permissions", serde_json::json!([]));
+        pick(&mut app, "mine", false);
+        assert_eq!(
+            app.config.get("amp.permissions").as_array().unwrap().len(),
+            3
+        );
     }
 
     #[test]
-    fn test_mcp_activate_config_opens_editor() {
-        let mut app = test_app_with_mcp_permissions();
-        app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Configs;
-        app.selected_setting = 0;
+    fn test_registry_fills_server_form() {
+        let mut app = test_app();
+        let dir = tempfile::tempdir().unwrap();
+        app.registry_cache = Some(dir.path().join("registry.json"));
+        app.config.set(
+            "amp.mcpServers",
+            serde_json::json!({"github": {"command": "gh-mcp"}}),
+        );
+        app.dispatch(Action::BrowseRegistry);
+        assert_eq!(app.input_mode, InputMode::BrowsingRegistry);
+        app.selected_registry = app
+            .registry
+            .iter()
+            .position(|e| e.name == "github")
+            .unwrap();
+        app.commit_registry_selection();
 
-        let req = app.activate_setting();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.mcpServers");
-        assert_eq!(req.object_key.as_deref(), Some("test-server"));
-        assert!(req.array_index.is_none());
-        assert_eq!(req.value["command"], Value::String("npx".into()));
+        assert_eq!(app.input_mode, InputMode::EditingServer);
+        assert_eq!(app.current_section(), Section::Mcps);
+        let form = app.server_form.as_ref().unwrap();
+        assert_eq!(form.name, "github-2");
+        assert_eq!(form.field, server_form::Field::EnvValue(0));
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Fill in github-2's 1 env values, then Enter to add it")
+        );
+        app.edit_server_form(|f| f.text_mut().unwrap().push_str("ghp_x"));
+        app.commit_server_form();
+        assert_eq!(
+            app.config.get("amp.mcpServers")["github-2"]["env"],
+            serde_json::json!({"GITHUB_PERSONAL_ACCESS_TOKEN": "ghp_x"})
+        );
     }
 
     #[test]
-    fn test_mcp_activate_permission_opens_item() {
-        let mut app = test_app_with_mcp_permissions();
+    fn test_copy_text_formats() {
+        let mut app = test_app();
         app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-        app.selected_mcp_permission = 1;
+        let key = app.selected_key().unwrap();
+        app.config.set(&key, Value::Bool(true));
+        assert_eq!(app.copy_text(CopyFormat::Key).unwrap(), key);
+        assert_eq!(app.copy_text(CopyFormat::Value).unwrap(), "true");
+        assert_eq!(
+            app.copy_text(CopyFormat::Snippet).unwrap(),
+            format!("\"{key}\": true")
+        );
 
-        let req = app.activate_setting();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.mcpPermissions");
-        assert_eq!(req.array_index, Some(1));
-        assert_eq!(req.value["action"], Value::String("reject".into()));
+        app.config
+            .set("amp.bitbucketToken", Value::String("secret".into()));
+        let section: Value =
+            serde_json::from_str(&app.copy_text(CopyFormat::Section).unwrap()).unwrap();
+        assert_eq!(
+            section,
+            serde_json::json!({
+                "amp.bitbucketToken": "<redacted>",
+                "amp.notifications.enabled": false,
+                "amp.showCosts": true
+            })
+        );
+        let all: Value = serde_json::from_str(&app.copy_text(CopyFormat::All).unwrap()).unwrap();
+        assert_eq!(
+            all["amp.experimental.labs"],
+            serde_json::json!(["bombadil"])
+        );
+        assert_eq!(all["amp.bitbucketToken"], "<redacted>");
     }
 
     #[test]
-    fn test_mcp_permission_add_starts_match_field() {
-        let mut app = test_app_with_mcp_permissions();
+    fn test_paste_text_validates() {
+        let mut app = test_app();
+        app.selected_section = 2;
         app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-        app.add_array_item();
-        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchField);
-    }
+        app.paste_text("[{\"tool\": \"Bash\", \"action\": \"allow\"}]");
+        assert_eq!(app.config.get("amp.permissions")[0]["tool"], "Bash");
 
-    #[test]
-    fn test_mcp_match_field_empty_rejected() {
-        let mut app = test_app_with_mcp_permissions();
-        app.input_mode = InputMode::EnteringMcpMatchField;
-        app.edit_buffer = "  ".to_string();
-        app.commit_mcp_match_field();
-        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchField);
-        assert!(app.status_message.unwrap().contains("empty"));
-    }
+        app.paste_text("\"amp.permissions\": [],");
+        assert_eq!(app.config.get("amp.permissions"), serde_json::json!([]));
 
-    #[test]
-    fn test_mcp_match_field_moves_to_value() {
-        let mut app = test_app_with_mcp_permissions();
-        app.input_mode = InputMode::EnteringMcpMatchField;
-        app.edit_buffer = "command".to_string();
-        app.commit_mcp_match_field();
-        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchValue);
-        assert_eq!(app.pending_mcp_match_field.as_deref(), Some("command"));
+        app.paste_text("not json");
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Paste rejected"));
+        assert_eq!(app.config.get("amp.permissions"), serde_json::json!([]));
     }
 
     #[test]
-    fn test_mcp_match_value_empty_rejected() {
-        let mut app = test_app_with_mcp_permissions();
-        app.input_mode = InputMode::EnteringMcpMatchValue;
-        app.pending_mcp_match_field = Some("command".to_string());
-        app.edit_buffer = "  ".to_string();
-        app.commit_mcp_match_value();
-        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchValue);
-        assert!(app.status_message.unwrap().contains("empty"));
+    fn test_object_form_edits_default_visibility() {
+        let mut app = test_app();
+        app.focus = Focus::Settings;
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.defaultVisibility"))
+            .unwrap();
+        app.activate_setting();
+        assert_eq!(app.input_mode, InputMode::EditingObject);
+
+        app.start_add_object_entry();
+        app.edit_buffer.set("github.com/acme/site");
+        app.commit_object_key();
+        assert_eq!(app.input_mode, InputMode::EditingObject);
+        app.change_object_value(true);
+        app.start_add_object_entry();
+        app.edit_buffer.set("github.com/acme/site");
+        app.commit_object_key();
+        assert_eq!(app.input_mode, InputMode::EnteringObjectKey);
+        app.cancel_object_input();
+        assert_eq!(
+            app.config.get("amp.defaultVisibility"),
+            serde_json::json!({"github.com/acme/site": "team"})
+        );
+        app.change_object_value(false);
+        app.change_object_value(false);
+        assert_eq!(
+            app.config.get("amp.defaultVisibility")["github.com/acme/site"],
+            "public"
+        );
+        app.delete_object_entry();
+        assert_eq!(
+            app.config.get("amp.defaultVisibility"),
+            serde_json::json!({})
+        );
     }
 
     #[test]
-    fn test_mcp_match_value_moves_to_level_select() {
-        let mut app = test_app_with_mcp_permissions();
-        app.input_mode = InputMode::EnteringMcpMatchValue;
-        app.pending_mcp_match_field = Some("url".to_string());
-        app.edit_buffer = "https://example.com".to_string();
-        app.commit_mcp_match_value();
-        assert_eq!(app.input_mode, InputMode::SelectingMcpPermissionLevel);
+    fn test_nested_custom_keys() {
+        let mut app = test_app();
+        app.config.set("my.tool", serde_json::json!({"level": 1}));
+        let add = |app: &mut App, key: &str, ty: CustomKeyType, value: &str| {
+            app.start_add_custom_key();
+            app.edit_buffer.set(key);
+            app.commit_key_name();
+            app.selected_type = CustomKeyType::ALL.iter().position(|t| *t == ty).unwrap();
+            app.commit_type_selection();
+            if app.input_mode == InputMode::EnteringCustomValue {
+                app.edit_buffer.set(value);
+                app.commit_custom_value();
+            }
+        };
+        app.selected_section = Section::ALL
+            .iter()
+            .position(|s| *s == Section::Advanced)
+            .unwrap();
+
+        // Off by default: the dotted key is added as written.
+        add(&mut app, "my.tool.name", CustomKeyType::String, "x");
+        assert_eq!(app.config.get_raw("my.tool.name"), Some(&Value::from("x")));
+
+        app.nested_keys = true;
+        add(&mut app, "my.tool.opts.depth", CustomKeyType::Number, "3");
         assert_eq!(
-            app.pending_mcp_match_value.as_deref(),
-            Some("https://example.com")
+            app.config.get("my.tool"),
+            serde_json::json!({"level": 1, "opts": {"depth": 3}})
+        );
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Added 'opts.depth' in my.tool")
+        );
+        add(
+            &mut app,
+            "amp.defaultVisibility.github.com/acme",
+            CustomKeyType::String,
+            "team",
+        );
+        assert_eq!(
+            app.config.get("amp.defaultVisibility"),
+            serde_json::json!({"github.com/acme": "team"})
         );
-    }
 
-    #[test]
-    fn test_mcp_permission_level_navigation() {
-        let mut app = test_app_with_mcp_permissions();
-        app.selected_mcp_permission_level = 0;
-        app.mcp_permission_level_up();
-        assert_eq!(app.selected_mcp_permission_level, 0); // stays at 0
-        app.mcp_permission_level_down();
-        assert_eq!(app.selected_mcp_permission_level, 1);
-        app.mcp_permission_level_down();
-        assert_eq!(app.selected_mcp_permission_level, 1); // stays at last (only 2 options)
+        app.start_add_custom_key();
+        app.edit_buffer.set("my.tool.opts.depth");
+        app.commit_key_name();
+        assert_eq!(app.input_mode, InputMode::EnteringKeyName);
+        app.cancel_edit();
+
+        add(&mut app, "my.tool.level.x", CustomKeyType::Boolean, "");
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("my.tool.level isn't an object, so 'my.tool.level.x' can't go inside it.")
+        );
+        // A prefix that's nothing in particular leaves the key as written.
+        add(&mut app, "new.thing", CustomKeyType::Array, "");
+        assert_eq!(
+            app.config.get_raw("new.thing"),
+            Some(&serde_json::json!([]))
+        );
     }
 
     #[test]
-    fn test_mcp_permission_commit_adds_rule() {
+    fn test_tree_view() {
         let mut app = test_app();
-        app.pending_mcp_match_field = Some("command".to_string());
-        app.pending_mcp_match_value = Some("npx".to_string());
-        app.selected_mcp_permission_level = 0; // allow
-        app.commit_mcp_permission_level();
-        assert_eq!(app.input_mode, InputMode::ConfirmMcpEdit);
+        app.focus = Focus::Settings;
+        app.config.set(
+            "my.obj",
+            serde_json::json!({"a": {"b": [1, true]}, "s": "x"}),
+        );
+        app.config.set(
+            "amp.defaultVisibility",
+            serde_json::json!({"github.com/acme": "team"}),
+        );
+        let labels =
+            |app: &App| -> Vec<String> { app.tree_rows().into_iter().map(|r| r.label).collect() };
 
-        let arr = app.config.get("amp.mcpPermissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items.len(), 1);
+        assert!(app.go_to_key("amp.showCosts"));
+        app.dispatch(Action::ExpandTree);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.tree.is_none());
+
+        assert!(app.go_to_key("my.obj"));
+        app.dispatch(Action::ExpandTree);
+        assert_eq!(app.input_mode, InputMode::BrowsingTree);
+        assert_eq!(labels(&app), ["a", "s"]);
+        app.tree_expand();
+        app.tree_select_down();
+        app.tree_expand();
+        app.tree_expand();
+        assert_eq!(labels(&app), ["a", "b", "[0]", "[1]", "s"]);
+        assert_eq!(app.tree.as_ref().unwrap().selected, 2);
+
+        app.tree_select_down();
+        app.tree_activate();
+        assert_eq!(app.config.get("my.obj")["a"]["b"][1], false);
+        app.tree_select_up();
+        app.tree_activate();
+        assert_eq!(app.input_mode, InputMode::EditingTreeValue);
+        assert_eq!(app.edit_buffer.as_str(), "1");
+        app.edit_buffer.set("nope");
+        app.commit_tree_value();
+        assert_eq!(app.input_mode, InputMode::EditingTreeValue);
+        app.edit_buffer.set("5");
+        app.commit_tree_value();
+        assert_eq!(app.input_mode, InputMode::BrowsingTree);
+        assert_eq!(app.config.get("my.obj")["a"]["b"][0], 5);
+        assert_eq!(app.status_message.as_deref(), Some("Set my.obj.a.b[0] = 5"));
+
+        // Left on a leaf goes to its branch, then folds it.
+        app.tree_collapse();
+        app.tree_collapse();
+        assert_eq!(labels(&app), ["a", "b", "s"]);
+        app.tree_select_down();
+        app.tree_activate();
+        app.edit_buffer.set("two words");
+        app.commit_tree_value();
+        assert_eq!(app.config.get("my.obj")["s"], "two words");
+        app.close_tree();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.selected_key().as_deref(), Some("my.obj"));
+
+        // Known settings are still checked against their schema.
+        assert!(app.go_to_key("amp.defaultVisibility"));
+        app.dispatch(Action::ExpandTree);
+        app.tree_activate();
+        app.edit_buffer.set("everyone");
+        app.commit_tree_value();
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Not saved"));
         assert_eq!(
-            items[0]["matches"],
-            Value::Object({
-                let mut m = serde_json::Map::new();
-                m.insert("command".into(), Value::String("npx".into()));
-                m
-            })
+            app.config.get("amp.defaultVisibility")["github.com/acme"],
+            "team"
         );
-        assert_eq!(items[0]["action"], Value::String("allow".into()));
     }
 
     #[test]
-    fn test_mcp_permission_full_flow() {
+    fn test_disabled_tools_checklist() {
         let mut app = test_app();
-        app.selected_section = 3; // MCPs
+        app.selected_section = 3;
         app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-
-        // Step 1: start add
-        app.add_array_item();
-        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchField);
+        app.config.set(
+            "amp.tools.disable",
+            serde_json::json!(["mcp__github__create_issue", "Read"]),
+        );
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| matches!(e, SettingEntry::Known(d) if d.key == "amp.tools.disable"))
+            .unwrap();
+        app.activate_setting();
+        assert_eq!(app.input_mode, InputMode::SelectingDisabledTools);
+        let checked: Vec<&str> = app
+            .tool_choices
+            .iter()
+            .filter(|c| c.checked)
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(checked, ["Read", "mcp__github__create_issue"]);
+        assert!(app.tool_choices.last().unwrap().custom);
+
+        // Check Bash, uncheck the MCP tool, and add a custom one.
+        app.toggle_tool_choice();
+        app.selected_tool_choice = app.tool_choices.len() - 1;
+        app.toggle_tool_choice();
+        app.start_add_custom_tool();
+        app.edit_buffer.set("mcp__linear__search");
+        app.commit_custom_tool();
+        assert_eq!(app.input_mode, InputMode::SelectingDisabledTools);
+        app.commit_tool_picker();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.config.get("amp.tools.disable"),
+            serde_json::json!(["Read", "Bash", "mcp__linear__search"])
+        );
+    }
 
-        // Step 2: enter match field
-        app.edit_buffer = "url".to_string();
-        app.commit_mcp_match_field();
-        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchValue);
+    #[test]
+    fn test_prompt_completion_and_history() {
+        let mut app = test_app();
+        app.selected_section = 2;
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([{"tool": "mcp__github__create_issue", "action": "allow"}]),
+        );
+        app.input_mode = InputMode::EnteringPermissionTool;
+        app.edit_buffer.set("ba");
+        app.complete_prompt(true);
+        assert_eq!(app.edit_buffer, "Bash");
+        assert_eq!(app.completion_position(), Some((1, 1)));
+        app.edit_buffer.set("mcp");
+        app.complete_prompt(true);
+        assert_eq!(app.edit_buffer, "mcp__github__create_issue");
+        app.commit_permission_tool();
 
-        // Step 3: enter match value
-        app.edit_buffer = "https://evil.com/*".to_string();
-        app.commit_mcp_match_value();
-        assert_eq!(app.input_mode, InputMode::SelectingMcpPermissionLevel);
+        app.cancel_edit();
+        app.input_mode = InputMode::EnteringPermissionTool;
+        app.recall_prompt(true);
+        assert_eq!(app.edit_buffer, "mcp__github__create_issue");
+        app.recall_prompt(false);
+        assert_eq!(app.edit_buffer, "");
 
-        // Step 4: select reject (index 1)
-        app.mcp_permission_level_down();
-        assert_eq!(app.selected_mcp_permission_level, 1);
-        app.commit_mcp_permission_level();
-        assert_eq!(app.input_mode, InputMode::ConfirmMcpEdit);
+        app.input_mode = InputMode::EnteringMcpServerName;
+        assert_eq!(app.prompt_candidates(), vec!["github".to_string()]);
+        app.edit_buffer.set("x");
+        app.complete_prompt(true);
+        assert_eq!(app.edit_buffer, "x");
+        assert_eq!(app.status_message.as_deref(), Some("No completions."));
+    }
 
-        // Step 5: decline editor
-        app.decline_mcp_edit();
+    #[test]
+    fn test_terminal_paste_goes_to_the_field_being_typed() {
+        let mut app = test_app();
+        app.insert_pasted("amp.showCosts");
         assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(!app.config.is_dirty());
 
-        let arr = app.config.get("amp.mcpPermissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items.len(), 1);
-        assert_eq!(items[0]["action"], Value::String("reject".into()));
+        app.start_search();
+        app.insert_pasted("show\ncosts\n");
+        assert_eq!(app.edit_buffer, "show costs");
+        assert!(!app.search_results.is_empty());
+
+        app.cancel_edit();
+        app.selected_section = 2;
+        app.start_permission_test();
+        app.insert_pasted("git status\n");
+        assert_eq!(app.permission_test.as_ref().unwrap().args, "git status");
     }
 
     #[test]
-    fn test_mcp_confirm_edit_returns_editor_request() {
+    fn test_duplicate_permission_rule() {
         let mut app = test_app();
-        app.pending_mcp_match_field = Some("command".to_string());
-        app.pending_mcp_match_value = Some("npx".to_string());
-        app.selected_mcp_permission_level = 0;
-        app.commit_mcp_permission_level();
-        assert_eq!(app.input_mode, InputMode::ConfirmMcpEdit);
-
-        let req = app.confirm_mcp_edit();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.mcpPermissions");
-        assert_eq!(req.array_index, Some(0));
-        assert_eq!(app.input_mode, InputMode::Normal);
+        app.selected_section = 2;
+        app.focus = Focus::Settings;
+        app.config.set(
+            "amp.permissions",
+            serde_json::json!([{"tool": "a"}, {"tool": "b"}]),
+        );
+        app.selected_setting = 0;
+        app.duplicate_item();
+        assert_eq!(
+            app.config.get("amp.permissions"),
+            serde_json::json!([{"tool": "a"}, {"tool": "a"}, {"tool": "b"}])
+        );
+        assert_eq!(app.selected_setting, 1);
     }
 
     #[test]
-    fn test_mcp_delete_permission_item() {
+    fn test_duplicate_mcp_server_prompts_for_name() {
         let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-        app.selected_mcp_permission = 0;
+        app.duplicate_item();
+        assert_eq!(app.input_mode, InputMode::EnteringMcpServerName);
+        assert_eq!(app.edit_buffer, "test-server-copy");
 
-        app.delete_array_item();
-        assert_eq!(app.mcp_permission_item_count(), 1);
-        let arr = app.config.get("amp.mcpPermissions");
-        let items = arr.as_array().unwrap();
-        assert_eq!(items[0]["action"], Value::String("reject".into()));
+        let req = app.commit_mcp_server_name().unwrap();
+        assert_eq!(req.object_key.as_deref(), Some("test-server-copy"));
+        assert_eq!(req.value, serde_json::json!({"command": "npx"}));
+        assert!(app.pending_mcp_server_copy.is_none());
     }
 
     #[test]
-    fn test_mcp_delete_last_adjusts_selection() {
+    fn test_move_mcp_permission_rule() {
         let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
         app.mcp_focus = McpFocus::Permissions;
-        app.selected_mcp_permission = 1; // last item
-
-        app.delete_array_item();
-        assert_eq!(app.mcp_permission_item_count(), 1);
+        let before = app.config.get("amp.mcpPermissions");
+        let items = before.as_array().unwrap().clone();
+        assert!(items.len() >= 2);
+        app.selected_mcp_permission = 1;
+        app.move_array_item(true);
         assert_eq!(app.selected_mcp_permission, 0);
+        let after = app.config.get("amp.mcpPermissions");
+        assert_eq!(after[0], items[1]);
+        assert_eq!(after[1], items[0]);
     }
 
     #[test]
-    fn test_mcp_reset_permissions() {
+    fn test_mcp_server_test_reports_failure() {
         let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-
-        app.reset_setting();
-        assert_eq!(app.mcp_permission_item_count(), 0);
-        assert_eq!(app.selected_mcp_permission, 0);
+        app.config.set(
+            "amp.mcpServers",
+            serde_json::json!({"broken": {"command": "/definitely/not/a/server"}}),
+        );
+        let (tx, rx) = mpsc::channel();
+        app.tasks = Tasks::new(tx);
+        app.test_mcp_server();
+        assert_eq!(app.mcp_probe.as_deref(), Some("broken"));
+        let Ok(AppEvent::Task(result)) = rx.recv_timeout(Duration::from_secs(5)) else {
+            panic!("the test didn't finish");
+        };
+        // An open overlay holds the result back until it closes.
+        app.show_messages();
+        app.finish_task(result);
+        assert!(app.mcp_probe.is_none());
+        assert_eq!(app.input_mode, InputMode::ViewingMessages);
+        app.cancel_edit();
+        app.tick(Instant::now());
+        assert_eq!(app.input_mode, InputMode::ViewingReport);
+        assert_eq!(app.report.as_ref().unwrap().title, " broken: failed ");
     }
 
     #[test]
-    fn test_mcp_reset_configs_deletes_server() {
+    fn test_server_rule_drill_down() {
         let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Configs;
-        app.selected_setting = 0;
+        app.config.set(
+            "amp.mcpServers",
+            serde_json::json!({
+                "gh": {"command": "npx", "args": ["-y", "server-github"]},
+                "remote": {"url": "https://mcp.example.com/sse"}
+            }),
+        );
+        app.config.set(
+            "amp.mcpPermissions",
+            serde_json::json!([
+                {"matches": {"url": "https://mcp.example.com/*"}, "action": "reject"},
+                {"matches": {"command": "npx"}, "action": "allow"}
+            ]),
+        );
+        assert_eq!(
+            app.selected_server_rules(),
+            Some(("gh".to_string(), vec![1]))
+        );
 
-        app.reset_setting();
-        let val = app.config.get("amp.mcpServers");
-        assert!(val.as_object().unwrap().is_empty());
-        assert!(app.status_message.unwrap().contains("Removed server"));
+        app.selected_setting = 1;
+        app.dispatch(Action::AddServerRule);
+        assert_eq!(app.input_mode, InputMode::EnteringMcpMatchValue);
+        assert_eq!(app.edit_buffer.as_str(), "https://mcp.example.com/sse");
+        app.commit_mcp_match_value();
+        app.commit_mcp_permission_level();
+        assert_eq!(
+            app.config.get("amp.mcpPermissions")[2]["matches"],
+            serde_json::json!({"url": "https://mcp.example.com/sse"})
+        );
+        app.cancel_edit();
+        assert_eq!(
+            app.selected_server_rules(),
+            Some(("remote".to_string(), vec![0, 2]))
+        );
+
+        app.mcp_focus = McpFocus::Permissions;
+        app.dispatch(Action::AddServerRule);
+        assert_eq!(app.input_mode, InputMode::Normal);
     }
 
     #[test]
-    fn test_mcp_force_editor_configs() {
+    fn test_mcp_health_checks() {
         let mut app = test_app_with_mcp_permissions();
         app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Configs;
-        app.selected_setting = 0;
+        app.config.set(
+            "amp.mcpServers",
+            serde_json::json!({"broken": {"command": "/definitely/not/a/server"}}),
+        );
+        let (tx, rx) = mpsc::channel();
+        app.tasks = Tasks::new(tx);
+        app.tick(Instant::now());
+        assert_eq!(
+            app.selected_mcp_health().as_deref(),
+            Some("? broken: checking…")
+        );
+        // Checked once per config.
+        app.check_mcp_health();
+        let Ok(AppEvent::Task(result)) = rx.recv_timeout(Duration::from_secs(5)) else {
+            panic!("the check didn't finish");
+        };
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        app.finish_task(result);
+        assert_eq!(
+            app.selected_mcp_health().as_deref(),
+            Some("✗ broken: `/definitely/not/a/server` is not on PATH")
+        );
 
-        let req = app.force_editor();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.mcpServers");
-        assert_eq!(req.object_key.as_deref(), Some("test-server"));
-        assert!(req.array_index.is_none());
-        assert_eq!(req.value["command"], Value::String("npx".into()));
+        // Changing the config checks again; the old result is stale.
+        app.config.set(
+            "amp.mcpServers",
+            serde_json::json!({"broken": {"url": "not a url"}}),
+        );
+        app.check_mcp_health();
+        assert!(app.mcp_server_health("broken").is_none());
+        app.finish_task(TaskResult::McpHealth {
+            server: "broken".into(),
+            config: serde_json::json!({"command": "/definitely/not/a/server"}),
+            health: Health::Up("stale".into()),
+        });
+        assert!(app.mcp_server_health("broken").is_none());
     }
 
     #[test]
-    fn test_mcp_force_editor_permissions() {
-        let mut app = test_app_with_mcp_permissions();
-        app.focus = Focus::Settings;
-        app.mcp_focus = McpFocus::Permissions;
-        app.selected_mcp_permission = 1;
+    fn test_status_message_expires() {
+        let mut app = test_app();
+        app.status_message = Some("Saved".into());
+        app.log_status();
+        let shown = Instant::now();
+        app.tick(shown);
+        assert!(app.status_message.is_some());
+        app.tick(shown + STATUS_TIMEOUT);
+        assert!(app.status_message.is_none());
+    }
 
-        let req = app.force_editor();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.mcpPermissions");
-        assert_eq!(req.array_index, Some(1));
+    #[test]
+    fn test_read_only_inspects_instead_of_editing() {
+        let mut app = test_app();
+        app.config.set_read_only(true);
+        let key = app.selected_key().unwrap();
+        let before = app.effective_value(&key);
+        assert!(app.activate_setting().is_none());
+        assert_eq!(app.effective_value(&key), before);
+        assert!(!app.config.is_dirty());
+        assert_eq!(app.input_mode, InputMode::ViewingReport);
+        assert_eq!(
+            app.report.as_ref().unwrap().title,
+            format!(" {key} (read-only) ")
+        );
+
+        let server = serde_json::json!({"command": "npx", "env": {"GITHUB_TOKEN": "ghp_x"}});
+        assert_eq!(
+            app.mask_nested_secrets(&server),
+            serde_json::json!({"command": "npx", "env": {"GITHUB_TOKEN": SECRET_MASK}})
+        );
+    }
+
+    #[test]
+    fn test_mcp_server_test_needs_a_server_selected() {
+        let mut app = test_app();
+        app.test_mcp_server();
+        assert!(app.mcp_probe.is_none());
+        assert!(app.status_message.is_some());
+    }
+
+    #[test]
+    fn test_mcp_import_only_from_mcps_section() {
+        let mut app = test_app();
+        app.start_mcp_import();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.status_message.is_some());
     }
 
     #[test]
@@ -2711,7 +10886,7 @@ mod tests {
     fn test_mcp_server_name_empty_rejected() {
         let mut app = test_app_with_mcp_permissions();
         app.input_mode = InputMode::EnteringMcpServerName;
-        app.edit_buffer = "  ".to_string();
+        app.edit_buffer.set("  ");
         let req = app.commit_mcp_server_name();
         assert!(req.is_none());
         assert_eq!(app.input_mode, InputMode::EnteringMcpServerName);
@@ -2722,7 +10897,7 @@ mod tests {
     fn test_mcp_server_name_duplicate_rejected() {
         let mut app = test_app_with_mcp_permissions();
         app.input_mode = InputMode::EnteringMcpServerName;
-        app.edit_buffer = "test-server".to_string();
+        app.edit_buffer.set("test-server");
         let req = app.commit_mcp_server_name();
         assert!(req.is_none());
         assert_eq!(app.input_mode, InputMode::EnteringMcpServerName);
@@ -2730,17 +10905,52 @@ mod tests {
     }
 
     #[test]
-    fn test_mcp_server_name_success_returns_editor_request() {
+    fn test_mcp_server_name_success_opens_form() {
         let mut app = test_app_with_mcp_permissions();
+        app.focus = Focus::Settings;
         app.input_mode = InputMode::EnteringMcpServerName;
-        app.edit_buffer = "new-server".to_string();
-        let req = app.commit_mcp_server_name();
-        assert!(req.is_some());
-        let req = req.unwrap();
-        assert_eq!(req.key, "amp.mcpServers");
-        assert_eq!(req.object_key.as_deref(), Some("new-server"));
-        assert!(req.value.is_object());
+        app.edit_buffer.set("new-server");
+        assert!(app.commit_mcp_server_name().is_none());
+        assert_eq!(app.input_mode, InputMode::EditingServer);
+
+        app.commit_server_form();
+        assert_eq!(app.input_mode, InputMode::EditingServer);
+        assert_eq!(
+            app.server_form.as_ref().unwrap().field,
+            crate::server_form::Field::Command
+        );
+
+        app.edit_server_form(|f| {
+            f.command = "uvx".into();
+            f.add_arg();
+            f.text_mut().unwrap().push_str("mcp-server-git");
+        });
+        app.commit_server_form();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.config.get("amp.mcpServers")["new-server"],
+            serde_json::json!({"command": "uvx", "args": ["mcp-server-git"]})
+        );
+        assert_eq!(app.mcp_server_names()[app.selected_setting], "new-server");
+    }
+
+    #[test]
+    fn test_server_form_to_editor() {
+        let mut app = test_app_with_mcp_permissions();
+        app.input_mode = InputMode::EnteringMcpServerName;
+        app.edit_buffer.set("remote");
+        app.commit_mcp_server_name();
+        app.edit_server_form(|f| {
+            f.toggle_transport();
+            f.url = "https://example.com/mcp".into();
+        });
+        let req = app.server_form_to_editor().unwrap();
         assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(req.object_key.as_deref(), Some("remote"));
+        assert_eq!(
+            req.value,
+            serde_json::json!({"url": "https://example.com/mcp"})
+        );
     }
 
     #[test]
@@ -2758,7 +10968,7 @@ mod tests {
     #[test]
     fn test_mcp_delete_config_empty() {
         let mut app = test_app();
-        app.selected_section = 3; // MCPs
+        app.selected_section = 4; // MCPs
         app.focus = Focus::Settings;
         app.mcp_focus = McpFocus::Configs;
 
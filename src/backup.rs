@@ -0,0 +1,291 @@
+//! Timestamped backups of the settings file, taken before each save.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+use crate::config;
+
+/// Number of backups kept per settings file unless configured otherwise.
+pub const DEFAULT_RETENTION: usize = 10;
+
+/// Returns the directory backups of a settings file are written to, e.g.
+/// `~/.config/amp/backups`. A project's `.amp/settings.json` is backed up
+/// under volt's cache directory instead, e.g.
+/// `~/.cache/volt/backups/myapp-9f1c2e3a4b5d6e7f`, so the copies (secrets
+/// and all) never land in the project's repository.
+pub fn backup_dir(settings: &Path) -> PathBuf {
+    backup_dir_in(settings, dirs::cache_dir().as_deref())
+}
+
+/// [`backup_dir`] with the platform cache directory passed in.
+fn backup_dir_in(settings: &Path, cache_dir: Option<&Path>) -> PathBuf {
+    let dir = settings.parent().unwrap_or_else(|| Path::new("."));
+    match dir.parent() {
+        Some(project) if dir.file_name().is_some_and(|n| n == ".amp") => {
+            let project = fs::canonicalize(project).unwrap_or_else(|_| project.to_path_buf());
+            let name = project
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "project".to_string());
+            let id = config::hash(&project.to_string_lossy());
+            let cache = cache_dir.map_or_else(std::env::temp_dir, Path::to_path_buf);
+            cache
+                .join("volt")
+                .join("backups")
+                .join(format!("{name}-{id}"))
+        }
+        _ => dir.join("backups"),
+    }
+}
+
+/// Copies the current settings file into the backup directory, then prunes the
+/// oldest backups beyond `retention`. A second backup within the same second
+/// gets a `-1`, `-2`, ... suffix rather than replacing the first. Does nothing
+/// when `retention` is zero or the file is missing or empty.
+pub fn create(settings: &Path, retention: usize) -> Result<Option<PathBuf>> {
+    create_in(settings, retention, &backup_dir(settings))
+}
+
+/// [`create`] with the backup directory passed in.
+fn create_in(settings: &Path, retention: usize, dir: &Path) -> Result<Option<PathBuf>> {
+    if retention == 0 {
+        return Ok(None);
+    }
+    let Ok(meta) = fs::metadata(settings) else {
+        return Ok(None);
+    };
+    if meta.len() == 0 {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(dir).with_context(|| format!("creating directory {}", dir.display()))?;
+    let stamp = timestamp(SystemTime::now());
+    let mut target = dir.join(format!("{}.{stamp}", file_name(settings)));
+    for n in 1.. {
+        if !target.exists() {
+            break;
+        }
+        target = dir.join(format!("{}.{stamp}-{n}", file_name(settings)));
+    }
+    fs::copy(settings, &target).with_context(|| format!("writing {}", target.display()))?;
+
+    for old in list_in(settings, dir).into_iter().skip(retention) {
+        fs::remove_file(&old).with_context(|| format!("removing {}", old.display()))?;
+    }
+    Ok(Some(target))
+}
+
+/// Returns the backups of a settings file, newest first.
+pub fn list(settings: &Path) -> Vec<PathBuf> {
+    list_in(settings, &backup_dir(settings))
+}
+
+/// [`list`] with the backup directory passed in.
+fn list_in(settings: &Path, dir: &Path) -> Vec<PathBuf> {
+    let prefix = format!("{}.", file_name(settings));
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix(&prefix))
+                .is_some_and(is_timestamp)
+        })
+        .collect();
+    // Timestamps sort lexically, then by suffix, so the newest backup sorts last.
+    backups.sort_by_key(|p| {
+        let stamp = label(p);
+        let (base, n) = stamp.split_at(19);
+        (
+            base.to_string(),
+            n.trim_start_matches('-').parse::<u32>().unwrap_or(0),
+        )
+    });
+    backups.reverse();
+    backups
+}
+
+/// Returns the timestamp part of a backup's file name.
+pub fn label(backup: &Path) -> String {
+    let name = backup
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    name.rsplit_once('.')
+        .map(|(_, stamp)| stamp.to_string())
+        .unwrap_or(name)
+}
+
+fn file_name(settings: &Path) -> String {
+    settings
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "settings.json".to_string())
+}
+
+/// Formats a time as a file-name-safe UTC timestamp: `2024-05-01T10-00-00`.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}-{:02}-{:02}",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+/// See Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Returns whether a string looks like a timestamp produced by [`timestamp`],
+/// optionally with the `-N` suffix [`create`] adds to keep names unique.
+fn is_timestamp(s: &str) -> bool {
+    let (stamp, suffix) = s.split_at(s.len().min(19));
+    stamp.len() == 19
+        && stamp.chars().enumerate().all(|(i, c)| match i {
+            4 | 7 | 13 | 16 => c == '-',
+            10 => c == 'T',
+            _ => c.is_ascii_digit(),
+        })
+        && (suffix.is_empty()
+            || suffix
+                .strip_prefix('-')
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00-00-00");
+        let t = UNIX_EPOCH + Duration::from_secs(1_714_557_600);
+        assert_eq!(timestamp(t), "2024-05-01T10-00-00");
+        let leap = UNIX_EPOCH + Duration::from_secs(951_825_599);
+        assert_eq!(timestamp(leap), "2000-02-29T11-59-59");
+    }
+
+    #[test]
+    fn test_is_timestamp() {
+        assert!(is_timestamp("2024-05-01T10-00-00"));
+        assert!(is_timestamp("2024-05-01T10-00-00-2"));
+        assert!(!is_timestamp("2024-05-01T10-00-00-"));
+        assert!(!is_timestamp("2024-05-01T10-00-00x"));
+        assert!(!is_timestamp("2024-05-01"));
+        assert!(!is_timestamp("notes"));
+    }
+
+    #[test]
+    fn test_create_and_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        fs::write(&settings, "{}").unwrap();
+
+        let backups = backup_dir(&settings);
+        fs::create_dir_all(&backups).unwrap();
+        for stamp in ["2024-01-01T00-00-00", "2024-01-02T00-00-00"] {
+            fs::write(backups.join(format!("settings.json.{stamp}")), "{}").unwrap();
+        }
+        // Unrelated files are left alone.
+        fs::write(backups.join("other.json.2024-01-01T00-00-00"), "{}").unwrap();
+
+        let created = create(&settings, 2).unwrap().unwrap();
+        let remaining = list(&settings);
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining[0], created);
+        assert_eq!(label(&remaining[1]), "2024-01-02T00-00-00");
+        assert!(backups.join("other.json.2024-01-01T00-00-00").exists());
+    }
+
+    #[test]
+    fn test_create_twice_in_one_second() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        fs::write(&settings, "original").unwrap();
+        let first = create(&settings, 10).unwrap().unwrap();
+        fs::write(&settings, "intermediate").unwrap();
+        let second = create(&settings, 10).unwrap().unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "original");
+        assert_eq!(list(&settings), vec![second.clone(), first]);
+        assert_eq!(fs::read_to_string(&second).unwrap(), "intermediate");
+
+        // With a retention of one, the newest survives.
+        let third = create(&settings, 1).unwrap().unwrap();
+        assert_eq!(list(&settings), vec![third]);
+    }
+
+    #[test]
+    fn test_project_backups_stay_out_of_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join(".amp").join("settings.json");
+        fs::create_dir_all(settings.parent().unwrap()).unwrap();
+        fs::write(&settings, "{}").unwrap();
+
+        let cache = tempfile::tempdir().unwrap();
+        let backups = backup_dir_in(&settings, Some(cache.path()));
+        let project = fs::canonicalize(dir.path()).unwrap();
+        let name = project.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            backups,
+            cache.path().join("volt").join("backups").join(format!(
+                "{name}-{}",
+                config::hash(&project.to_string_lossy())
+            ))
+        );
+        let created = create_in(&settings, 1, &backups).unwrap().unwrap();
+        assert!(created.starts_with(&backups));
+        assert_eq!(list_in(&settings, &backups), vec![created]);
+        assert!(!dir.path().join(".amp").join("backups").exists());
+
+        // Without a cache directory, they go to the temp dir, still not the project.
+        assert!(backup_dir_in(&settings, None).starts_with(std::env::temp_dir()));
+
+        let global = dir.path().join("amp").join("settings.json");
+        assert_eq!(
+            backup_dir_in(&global, Some(cache.path())),
+            dir.path().join("amp").join("backups")
+        );
+    }
+
+    #[test]
+    fn test_create_skips_missing_empty_or_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        assert!(create(&settings, 5).unwrap().is_none());
+
+        fs::write(&settings, "").unwrap();
+        assert!(create(&settings, 5).unwrap().is_none());
+
+        fs::write(&settings, "{}").unwrap();
+        assert!(create(&settings, 0).unwrap().is_none());
+        assert!(!backup_dir(&settings).exists());
+    }
+}
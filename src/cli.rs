@@ -0,0 +1,1306 @@
+//! Non-interactive subcommands for reading and writing settings from scripts.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::config::{Config, PathSource};
+use crate::diff::{self, DiffKind};
+use crate::doctor::{self, Status};
+use crate::editor::{EditOutcome, EditSession};
+use crate::lint::{self, Severity};
+use crate::mcp_import::{self, Source};
+use crate::profile;
+use crate::script;
+use crate::settings::{self, Section, SettingType};
+use crate::sync::{self, Remote};
+
+/// Prints the settings file's path, where it came from, and whether it
+/// exists yet.
+pub fn which(path: &Path, source: PathSource, json: bool, out: &mut impl Write) -> Result<()> {
+    let exists = path.is_file();
+    if json {
+        let report = serde_json::json!({
+            "path": path,
+            "source": source.label(),
+            "exists": exists,
+        });
+        writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    } else {
+        let missing = if exists { "" } else { ", doesn't exist yet" };
+        writeln!(out, "{} (from {}{missing})", path.display(), source.label())?;
+    }
+    Ok(())
+}
+
+/// Prints the effective value of a key.
+pub fn get(config: &Config, key: &str, json: bool, out: &mut impl Write) -> Result<()> {
+    if config.get_raw(key).is_none() && settings::get_setting_def(key).is_none() {
+        bail!("unknown key '{key}'");
+    }
+    let value = config.get(key);
+    if json {
+        writeln!(out, "{}", serde_json::to_string(&value)?)?;
+    } else {
+        writeln!(out, "{}", format_plain(&value))?;
+    }
+    Ok(())
+}
+
+/// Parses, validates, and stores a value for a key, then saves.
+pub fn set(
+    config: &mut Config,
+    key: &str,
+    raw: &str,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let value = parse_value(key, raw)?;
+    Config::validate_value(key, &value)?;
+    config.set(key, value.clone());
+    config.save()?;
+    report_change(key, Some(&value), json, out)
+}
+
+/// Prints a key's effective value as pretty JSON, then replaces it with the
+/// JSON value `read` returns (the rest of stdin), validated, and saves.
+/// Blank input leaves the value alone.
+pub fn edit_piped(
+    config: &mut Config,
+    key: &str,
+    read: impl FnOnce() -> Result<String>,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let current = editable_value(config, key)?;
+    writeln!(out, "{}", serde_json::to_string_pretty(&current)?)?;
+    out.flush()?;
+    let text = read()?;
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    let value = serde_json::from_str(&text)
+        .with_context(|| format!("expected a JSON value for '{key}' on stdin"))?;
+    replace_value(config, key, &current, value, json, out)
+}
+
+/// Opens a key's effective value in the user's editor, then validates what
+/// was left there and saves it. `editor` is the `editor` preference.
+pub fn edit_in_editor(
+    config: &mut Config,
+    key: &str,
+    editor: Option<&str>,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let current = editable_value(config, key)?;
+    match EditSession::new(&current)?.edit(editor)? {
+        EditOutcome::Edited(value) => replace_value(config, key, &current, value, json, out),
+        EditOutcome::Invalid(e) => bail!("the edited value isn't valid JSON: {e}"),
+    }
+}
+
+/// The effective value of a key `volt edit` can work on: one that's set or
+/// known.
+fn editable_value(config: &Config, key: &str) -> Result<Value> {
+    if config.is_read_only() {
+        bail!("{} is open read-only", config.path().display());
+    }
+    if config.get_raw(key).is_none() && settings::get_setting_def(key).is_none() {
+        bail!("unknown key '{key}'");
+    }
+    Ok(config.get(key))
+}
+
+/// Validates and stores an edited value, saving only if it changed.
+fn replace_value(
+    config: &mut Config,
+    key: &str,
+    current: &Value,
+    value: Value,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    Config::validate_value(key, &value)?;
+    if value == *current && config.get_raw(key).is_some() {
+        if json {
+            return report_change(key, Some(&value), json, out);
+        }
+        writeln!(out, "{key} unchanged")?;
+        return Ok(());
+    }
+    config.set(key, value.clone());
+    config.save()?;
+    report_change(key, Some(&value), json, out)
+}
+
+/// Removes a key so it falls back to its default, then saves.
+pub fn unset(config: &mut Config, key: &str, json: bool, out: &mut impl Write) -> Result<()> {
+    if config.get_raw(key).is_none() {
+        bail!("'{key}' is not set");
+    }
+    config.remove(key);
+    config.save()?;
+    report_change(key, None, json, out)
+}
+
+/// Lists keys and their effective values, optionally limited to one section.
+pub fn list(
+    config: &Config,
+    section: Option<&str>,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let section = match section {
+        Some(label) => {
+            Some(Section::from_label(label).with_context(|| format!("unknown section '{label}'"))?)
+        }
+        None => None,
+    };
+
+    let mut keys: Vec<String> = Vec::new();
+    for s in Section::ALL {
+        if section.is_some_and(|wanted| wanted != *s) {
+            continue;
+        }
+        if *s == Section::Advanced {
+            keys.extend(config.unknown_keys());
+        } else {
+            keys.extend(
+                settings::settings_for_section(*s)
+                    .iter()
+                    .map(|d| d.key.to_string()),
+            );
+        }
+    }
+
+    if json {
+        let map: Map<String, Value> = keys.iter().map(|k| (k.clone(), config.get(k))).collect();
+        writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&Value::Object(map))?
+        )?;
+    } else {
+        for key in keys {
+            let suffix = if config.get_raw(&key).is_some() {
+                ""
+            } else {
+                " (default)"
+            };
+            writeln!(out, "{key} = {}{suffix}", format_plain(&config.get(&key)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints a lint report for the whole file. Returns false when any errors
+/// were found, so the caller can exit nonzero.
+pub fn validate(config: &Config, json: bool, out: &mut impl Write) -> Result<bool> {
+    let findings = lint::lint(config);
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+
+    if json {
+        let report = serde_json::json!({
+            "file": config.path().display().to_string(),
+            "ok": errors == 0,
+            "errors": errors,
+            "warnings": warnings,
+            "findings": findings
+                .iter()
+                .map(|f| serde_json::json!({
+                    "severity": f.severity.label(),
+                    "path": f.path,
+                    "message": f.message,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+    } else {
+        for f in &findings {
+            writeln!(out, "{:<7} {}: {}", f.severity.label(), f.path, f.message)?;
+        }
+        writeln!(
+            out,
+            "{}: {errors} error(s), {warnings} warning(s)",
+            config.path().display()
+        )?;
+    }
+    Ok(errors == 0)
+}
+
+/// Prints a pass/warn/fail line for each environment check. Returns false
+/// when any check failed, so the caller can exit nonzero.
+pub fn doctor(path: &Path, strict: bool, json: bool, out: &mut impl Write) -> Result<bool> {
+    let checks = doctor::diagnose(path, strict);
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let (warnings, failures) = (count(Status::Warn), count(Status::Fail));
+
+    if json {
+        let report = serde_json::json!({
+            "file": path.display().to_string(),
+            "ok": failures == 0,
+            "checks": checks
+                .iter()
+                .map(|c| serde_json::json!({
+                    "status": c.status.label(),
+                    "name": c.name,
+                    "detail": c.detail,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+    } else {
+        for c in &checks {
+            writeln!(out, "{}  {}: {}", c.status.label(), c.name, c.detail)?;
+        }
+        writeln!(
+            out,
+            "{} check(s): {failures} failed, {warnings} warning(s)",
+            checks.len()
+        )?;
+    }
+    Ok(!doctor::failed(&checks))
+}
+
+/// Rewrites deprecated and renamed settings to their replacements, then
+/// saves (which keeps a backup of the old file). With `dry_run`, only
+/// reports what would change.
+pub fn migrate(config: &mut Config, dry_run: bool, json: bool, out: &mut impl Write) -> Result<()> {
+    let steps = config.migrate();
+    if !dry_run && steps.iter().any(|s| s.applied) {
+        config.save()?;
+    }
+    if json {
+        let report: Vec<Value> = steps
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "key": s.old,
+                    "replacement": s.new,
+                    "applied": s.applied,
+                    "note": s.note,
+                })
+            })
+            .collect();
+        writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    } else {
+        for step in &steps {
+            writeln!(out, "{}: {}", step.old, step.note)?;
+        }
+        if steps.is_empty() {
+            writeln!(out, "no deprecated settings")?;
+        } else if dry_run {
+            writeln!(out, "(dry run; nothing was written)")?;
+        }
+    }
+    Ok(())
+}
+
+/// Imports MCP servers from another tool's config file into `amp.mcpServers`,
+/// then saves. Servers that already exist are skipped unless `overwrite`.
+pub fn import_mcp(
+    config: &mut Config,
+    source: Source,
+    path: &Path,
+    only: &[String],
+    overwrite: bool,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let mut servers = mcp_import::read(source, path)?;
+    for name in only {
+        if !servers.iter().any(|(n, _)| n == name) {
+            bail!("no server named '{name}' in {}", path.display());
+        }
+    }
+    if !only.is_empty() {
+        servers.retain(|(name, _)| only.contains(name));
+    }
+
+    let existing = config.get("amp.mcpServers");
+    let (skipped, imported): (Vec<_>, Vec<_>) = servers
+        .into_iter()
+        .partition(|(name, _)| !overwrite && existing.get(name).is_some());
+    if !imported.is_empty() {
+        mcp_import::merge(config, &imported);
+        config.save()?;
+    }
+
+    let imported: Vec<String> = imported.into_iter().map(|(name, _)| name).collect();
+    let skipped: Vec<String> = skipped.into_iter().map(|(name, _)| name).collect();
+    if json {
+        let report = serde_json::json!({"imported": imported, "skipped": skipped});
+        writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    } else {
+        for name in &imported {
+            writeln!(out, "imported {name}")?;
+        }
+        for name in &skipped {
+            writeln!(
+                out,
+                "skipped {name} (already configured; use --overwrite to replace)"
+            )?;
+        }
+        if imported.is_empty() && skipped.is_empty() {
+            writeln!(out, "no servers to import")?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies a script of operations (see [`script`]) and saves once they all
+/// succeed; if any fails, nothing is written.
+pub fn script(config: &mut Config, text: &str, json: bool, out: &mut impl Write) -> Result<()> {
+    let ops = script::parse(text)?;
+    let summary = script::apply(config, &ops)?;
+    if !ops.is_empty() {
+        config.save()?;
+    }
+    if json {
+        let report = serde_json::json!({"applied": summary});
+        writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    } else {
+        for line in &summary {
+            writeln!(out, "{line}")?;
+        }
+        writeln!(out, "applied {} operation(s)", summary.len())?;
+    }
+    Ok(())
+}
+
+/// Saves the settings as a named profile, limited to some sections when any
+/// are given.
+pub fn profile_save(
+    config: &Config,
+    dir: &Path,
+    name: &str,
+    sections: &[String],
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let sections = sections
+        .iter()
+        .map(|label| {
+            Section::from_label(label).with_context(|| format!("unknown section '{label}'"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let sections = (!sections.is_empty()).then_some(sections.as_slice());
+    let path = profile::save(dir, name, config, sections)?;
+    if json {
+        let report = serde_json::json!({"profile": name, "path": path.display().to_string()});
+        writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    } else {
+        writeln!(out, "saved profile {name} to {}", path.display())?;
+    }
+    Ok(())
+}
+
+/// Replaces the settings a profile covers with the profile's, then saves.
+pub fn profile_load(
+    config: &mut Config,
+    dir: &Path,
+    name: &str,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let profile = profile::load(dir, name)?;
+    profile.apply(config);
+    config.save()?;
+    if json {
+        let report = serde_json::json!({"profile": name, "scope": profile.scope_label()});
+        writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    } else {
+        writeln!(out, "loaded profile {name} ({})", profile.scope_label())?;
+    }
+    Ok(())
+}
+
+/// Lists the saved profiles.
+pub fn profile_list(dir: &Path, json: bool, out: &mut impl Write) -> Result<()> {
+    let names = profile::list(dir);
+    if json {
+        writeln!(out, "{}", serde_json::to_string(&names)?)?;
+    } else {
+        for name in names {
+            writeln!(out, "{name}")?;
+        }
+    }
+    Ok(())
+}
+
+/// What `volt diff` compares the settings file with.
+pub enum Against {
+    /// The built-in default of each key.
+    Defaults,
+    /// Another settings file.
+    File(Box<Config>),
+}
+
+/// How `volt diff` lays out its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiffFormat {
+    /// One row per key with both values side by side.
+    Table,
+    /// A unified diff of each key's pretty-printed value.
+    Unified,
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Prints the keys whose values differ between the settings file and the
+/// defaults or another file. A key missing on one side counts as a
+/// difference, except that unknown keys have no default to compare with.
+pub fn diff(
+    config: &Config,
+    against: &Against,
+    format: DiffFormat,
+    color: bool,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let (label, mut keys): (String, Vec<String>) = match against {
+        Against::Defaults => ("defaults".to_string(), Vec::new()),
+        Against::File(other) => (
+            other.path().display().to_string(),
+            other.entries().map(|(k, _)| k.clone()).collect(),
+        ),
+    };
+    keys.extend(config.entries().map(|(k, _)| k.clone()));
+    keys.sort();
+    keys.dedup();
+
+    let other_value = |key: &str| -> Option<Value> {
+        match against {
+            Against::Defaults => settings::get_setting_def(key).map(|d| d.default),
+            Against::File(other) => other.get_raw(key).cloned(),
+        }
+    };
+    let changes: Vec<(String, Option<Value>, Option<Value>)> = keys
+        .into_iter()
+        .filter_map(|key| {
+            let ours = config.get_raw(&key).cloned();
+            let theirs = other_value(&key);
+            (ours != theirs).then_some((key, ours, theirs))
+        })
+        .collect();
+
+    let paint = |code: &str, text: &str| {
+        if color {
+            format!("{code}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    };
+
+    if json {
+        let report: Vec<Value> = changes
+            .iter()
+            .map(|(key, ours, theirs)| {
+                serde_json::json!({"key": key, "value": ours, "against": theirs})
+            })
+            .collect();
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+    if changes.is_empty() {
+        writeln!(out, "no differences from {label}")?;
+        return Ok(());
+    }
+
+    match format {
+        DiffFormat::Table => {
+            let cell = |v: &Option<Value>| v.as_ref().map_or("(unset)".to_string(), format_plain);
+            let rows: Vec<[String; 3]> = changes
+                .iter()
+                .map(|(key, ours, theirs)| [key.clone(), cell(ours), cell(theirs)])
+                .collect();
+            let header = [
+                "KEY".to_string(),
+                "THIS FILE".to_string(),
+                label.to_uppercase(),
+            ];
+            let key_width = rows
+                .iter()
+                .chain([&header])
+                .map(|r| r[0].chars().count())
+                .max();
+            let ours_width = rows
+                .iter()
+                .chain([&header])
+                .map(|r| r[1].chars().count())
+                .max();
+            let (key_width, ours_width) = (key_width.unwrap_or(0), ours_width.unwrap_or(0));
+            writeln!(
+                out,
+                "{}",
+                paint(
+                    BOLD,
+                    &format!(
+                        "{:<key_width$}  {:<ours_width$}  {}",
+                        header[0], header[1], header[2]
+                    )
+                )
+            )?;
+            for [key, ours, theirs] in &rows {
+                writeln!(
+                    out,
+                    "{key:<key_width$}  {}  {}",
+                    paint(GREEN, &format!("{ours:<ours_width$}")),
+                    paint(RED, theirs)
+                )?;
+            }
+        }
+        DiffFormat::Unified => {
+            writeln!(out, "{}", paint(BOLD, &format!("--- {label}")))?;
+            writeln!(
+                out,
+                "{}",
+                paint(BOLD, &format!("+++ {}", config.path().display()))
+            )?;
+            let pretty = |v: &Option<Value>| -> Result<String> {
+                Ok(match v {
+                    Some(v) => serde_json::to_string_pretty(v)?,
+                    None => String::new(),
+                })
+            };
+            for (key, ours, theirs) in &changes {
+                writeln!(out, "{}", paint(CYAN, &format!("@@ {key} @@")))?;
+                for line in diff::diff_lines(&pretty(theirs)?, &pretty(ours)?) {
+                    let text = match line.kind {
+                        DiffKind::Same => format!(" {}", line.text),
+                        DiffKind::Added => paint(GREEN, &format!("+{}", line.text)),
+                        DiffKind::Removed => paint(RED, &format!("-{}", line.text)),
+                        DiffKind::Gap => continue,
+                    };
+                    writeln!(out, "{text}")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the file's settings as pretty JSON, with secrets redacted if
+/// `redact`, for sharing in a support request.
+pub fn export(config: &Config, redact: bool, out: &mut impl Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        serde_json::to_string_pretty(&config.export(redact))?
+    )?;
+    Ok(())
+}
+
+/// Pushes the settings file to `remote`, or pulls it from there, keeping
+/// what was synced in the state file at `state`.
+pub fn sync(
+    config: &mut Config,
+    remote: &Remote,
+    state: &Path,
+    push: bool,
+    force: bool,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let mut state = sync::State::load(state)?;
+    let outcome = if push {
+        sync::push(config, remote, &mut state, force)?
+    } else {
+        sync::pull(config, remote, &mut state, force)?
+    };
+    let file = config.path().display();
+    if json {
+        let report = serde_json::json!({"remote": remote.to_string(), "result": outcome.label()});
+        writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    } else {
+        match outcome {
+            sync::Outcome::Pushed => writeln!(out, "pushed {file} to {remote}")?,
+            sync::Outcome::Pulled => writeln!(out, "pulled {file} from {remote}")?,
+            sync::Outcome::UpToDate => writeln!(out, "{file} and {remote} are up to date")?,
+        }
+    }
+    Ok(())
+}
+
+/// Prints every setting volt knows (built in, or from `--schema`) with its
+/// section, type, default, options, and description, as TOML, or JSON with
+/// `json`.
+pub fn schema(json: bool, out: &mut impl Write) -> Result<()> {
+    let settings: Vec<Value> = settings::known_settings()
+        .iter()
+        .map(describe_setting)
+        .collect();
+    if json {
+        let dump = serde_json::json!({ "settings": settings });
+        writeln!(out, "{}", serde_json::to_string_pretty(&dump)?)?;
+    } else {
+        let dump = serde_json::json!({ "settings": settings.iter().map(without_nulls).collect::<Vec<_>>() });
+        write!(
+            out,
+            "{}",
+            toml::to_string(&dump).context("writing the schema as TOML")?
+        )?;
+    }
+    Ok(())
+}
+
+/// One setting's entry in `volt schema`. Types are JSON Schema's, with
+/// `items` for arrays and `enum` for the known options.
+fn describe_setting(def: &settings::SettingDef) -> Value {
+    let (kind, items) = match def.setting_type {
+        SettingType::Boolean => ("boolean", None),
+        SettingType::String | SettingType::StringEnum => ("string", None),
+        SettingType::Number => ("number", None),
+        SettingType::ArrayString => ("array", Some("string")),
+        SettingType::ArrayObject => ("array", Some("object")),
+        SettingType::Object => ("object", None),
+    };
+    let mut entry = Map::new();
+    entry.insert("key".into(), def.key.into());
+    let section = settings::section_for_key(def.key).unwrap_or(Section::General);
+    entry.insert(
+        "section".into(),
+        section.label().to_ascii_lowercase().into(),
+    );
+    entry.insert("type".into(), kind.into());
+    if let Some(items) = items {
+        entry.insert("items".into(), items.into());
+    }
+    entry.insert("default".into(), def.default.clone());
+    if let Some(options) = def.enum_options {
+        entry.insert("enum".into(), options.to_vec().into());
+        entry.insert("allowsCustom".into(), def.allows_custom.into());
+    }
+    if let Some(number) = def.number {
+        entry.insert("minimum".into(), number.min.into());
+        entry.insert("maximum".into(), number.max.into());
+        entry.insert("step".into(), number.step.into());
+        entry.insert("unit".into(), number.unit.into());
+    }
+    entry.insert("sensitive".into(), def.sensitive.into());
+    entry.insert("description".into(), def.description.into());
+    Value::Object(entry)
+}
+
+/// Drops null fields, which TOML can't represent.
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Interprets a command-line value according to the key's known type.
+///
+/// Booleans and numbers are parsed from their usual spellings, strings are taken
+/// verbatim, and arrays/objects must be JSON. Unknown keys accept any JSON value
+/// and fall back to a plain string.
+pub fn parse_value(key: &str, raw: &str) -> Result<Value> {
+    let Some(def) = settings::get_setting_def(key) else {
+        return Ok(serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())));
+    };
+    let value = match def.setting_type {
+        SettingType::Boolean => match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => bail!("expected true or false for '{key}'"),
+        },
+        SettingType::Number => settings::parse_number(raw)
+            .map(Value::Number)
+            .with_context(|| format!("expected a number for '{key}'"))?,
+        SettingType::String | SettingType::StringEnum => Value::String(raw.to_string()),
+        SettingType::ArrayString | SettingType::ArrayObject | SettingType::Object => {
+            serde_json::from_str(raw).with_context(|| format!("expected JSON for '{key}'"))?
+        }
+    };
+    Ok(value)
+}
+
+fn report_change(key: &str, value: Option<&Value>, json: bool, out: &mut impl Write) -> Result<()> {
+    if json {
+        let mut map = Map::new();
+        map.insert("key".to_string(), Value::String(key.to_string()));
+        map.insert("value".to_string(), value.cloned().unwrap_or(Value::Null));
+        writeln!(out, "{}", serde_json::to_string(&Value::Object(map))?)?;
+    } else {
+        match value {
+            Some(v) => writeln!(out, "{key} = {}", format_plain(v))?,
+            None => writeln!(out, "{key} unset")?,
+        }
+    }
+    Ok(())
+}
+
+/// Treats stdout closing early, as in `volt list | head`, as success: the
+/// reader has everything it wanted.
+pub fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+    match result {
+        Err(e)
+            if e.chain().any(|cause| {
+                cause
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|io| io.kind() == std::io::ErrorKind::BrokenPipe)
+            }) =>
+        {
+            Ok(())
+        }
+        other => other,
+    }
+}
+
+/// Formats a value for plain output: strings bare, everything else as compact JSON.
+fn format_plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_config(contents: &str) -> (tempfile::TempDir, Config) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, contents).unwrap();
+        let config = Config::load(&path).unwrap();
+        (dir, config)
+    }
+
+    fn run(f: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> String {
+        let mut out = Vec::new();
+        f(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_get_plain_and_json() {
+        let (_dir, config) = temp_config(r#"{"amp.terminal.theme": "nord"}"#);
+        assert_eq!(
+            run(|o| get(&config, "amp.terminal.theme", false, o)),
+            "nord\n"
+        );
+        assert_eq!(
+            run(|o| get(&config, "amp.terminal.theme", true, o)),
+            "\"nord\"\n"
+        );
+        // Unset known keys report their default.
+        assert_eq!(
+            run(|o| get(&config, "amp.tools.stopTimeout", false, o)),
+            "300\n"
+        );
+    }
+
+    #[test]
+    fn test_get_unknown_key_fails() {
+        let (_dir, config) = temp_config("{}");
+        assert!(get(&config, "amp.nope", false, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_set_validates_and_saves() {
+        let (dir, mut config) = temp_config("{}");
+        let out = run(|o| set(&mut config, "amp.showCosts", "false", false, o));
+        assert_eq!(out, "amp.showCosts = false\n");
+
+        let reloaded = Config::load(&dir.path().join("settings.json")).unwrap();
+        assert_eq!(reloaded.get("amp.showCosts"), Value::Bool(false));
+
+        assert!(set(&mut config, "amp.showCosts", "yes", false, &mut Vec::new()).is_err());
+        assert!(set(
+            &mut config,
+            "amp.updates.mode",
+            "sometimes",
+            false,
+            &mut Vec::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_set_json_output() {
+        let (_dir, mut config) = temp_config("{}");
+        let out = run(|o| set(&mut config, "amp.tools.disable", r#"["Bash"]"#, true, o));
+        assert_eq!(
+            out,
+            "{\"key\":\"amp.tools.disable\",\"value\":[\"Bash\"]}\n"
+        );
+    }
+
+    #[test]
+    fn test_edit_piped() {
+        let (dir, mut config) = temp_config(r#"{"amp.tools.disable": ["Bash"]}"#);
+        let input = || Ok(r#"["Bash", "Read"]"#.to_string());
+        let out = run(|o| edit_piped(&mut config, "amp.tools.disable", input, false, o));
+        assert_eq!(
+            out,
+            "[\n  \"Bash\"\n]\namp.tools.disable = [\"Bash\",\"Read\"]\n"
+        );
+        let reloaded = Config::load(&dir.path().join("settings.json")).unwrap();
+        assert_eq!(
+            reloaded.get("amp.tools.disable"),
+            serde_json::json!(["Bash", "Read"])
+        );
+
+        // Blank input and the same value leave the file alone.
+        let out = run(|o| edit_piped(&mut config, "amp.showCosts", || Ok("\n".into()), false, o));
+        assert_eq!(out, "true\n");
+        assert!(config.get_raw("amp.showCosts").is_none());
+        let same = || Ok(r#"["Bash", "Read"]"#.to_string());
+        let out = run(|o| edit_piped(&mut config, "amp.tools.disable", same, false, o));
+        assert!(out.ends_with("amp.tools.disable unchanged\n"));
+
+        let bad = || Ok("\"yes\"".to_string());
+        let err = edit_piped(&mut config, "amp.showCosts", bad, false, &mut Vec::new());
+        assert!(err.is_err());
+        let bad = || Ok("[1,".to_string());
+        let err = edit_piped(
+            &mut config,
+            "amp.tools.disable",
+            bad,
+            false,
+            &mut Vec::new(),
+        );
+        assert!(format!("{:#}", err.unwrap_err()).starts_with("expected a JSON value"));
+        assert!(edit_piped(
+            &mut config,
+            "amp.nope",
+            || unreachable!(),
+            false,
+            &mut Vec::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let (_dir, config) = temp_config(
+            r#"{
+                "amp.bitbucketToken": "abc",
+                "amp.mcpServers": {"gh": {"command": "npx", "env": {"GITHUB_TOKEN": "ghp", "DEBUG": "1"}}},
+                "amp.showCosts": false
+            }"#,
+        );
+        let out = run(|o| export(&config, true, o));
+        let exported: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            exported,
+            serde_json::json!({
+                "amp.bitbucketToken": "<redacted>",
+                "amp.mcpServers": {"gh": {"command": "npx", "env": {"GITHUB_TOKEN": "<redacted>", "DEBUG": "1"}}},
+                "amp.showCosts": false
+            })
+        );
+        let out = run(|o| export(&config, false, o));
+        assert!(out.contains("\"ghp\""));
+    }
+
+    #[test]
+    fn test_sync() {
+        let (dir, mut config) = temp_config(r#"{"amp.showCosts": false}"#);
+        let remote = Remote::Path(dir.path().join("remote.json"));
+        let state = dir.path().join("sync.json");
+        let out = run(|o| sync(&mut config, &remote, &state, true, false, false, o));
+        assert!(out.starts_with("pushed "), "{out}");
+        let out = run(|o| sync(&mut config, &remote, &state, false, false, true, o));
+        assert!(out.contains(r#""result":"up to date""#), "{out}");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("remote.json")).unwrap(),
+            r#"{"amp.showCosts": false}"#
+        );
+    }
+
+    #[test]
+    fn test_schema() {
+        let out = run(|o| schema(true, o));
+        let dump: Value = serde_json::from_str(&out).unwrap();
+        let settings = dump["settings"].as_array().unwrap();
+        assert_eq!(settings.len(), settings::known_settings().len());
+        let find = |key: &str| settings.iter().find(|s| s["key"] == key).unwrap();
+        assert_eq!(
+            find("amp.updates.mode")["enum"],
+            serde_json::json!(["auto", "warn", "disabled"])
+        );
+        let stop = find("amp.tools.stopTimeout");
+        assert_eq!(stop["section"], "tools");
+        assert_eq!(stop["type"], "number");
+        assert_eq!(stop["default"], 300);
+        assert_eq!(stop["unit"], "seconds");
+        assert_eq!(stop["maximum"], Value::Null);
+        assert_eq!(find("amp.tools.disable")["items"], "string");
+
+        let out = run(|o| schema(false, o));
+        let dump: toml::Table = out.parse().unwrap();
+        let settings = dump["settings"].as_array().unwrap();
+        assert_eq!(settings.len(), settings::known_settings().len());
+        assert_eq!(
+            settings[0]["key"].as_str(),
+            Some(settings::known_settings()[0].key)
+        );
+    }
+
+    #[test]
+    fn test_doctor_report() {
+        let (dir, config) = temp_config(
+            r#"{"amp.mcpServers": {"gone": {"command": "volt-doctor-no-such-command"}}}"#,
+        );
+        let mut out = Vec::new();
+        assert!(!doctor(config.path(), false, false, &mut out).unwrap());
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with(&format!(
+            "pass  settings file: {} parses\n",
+            config.path().display()
+        )));
+        assert!(text.contains("fail  mcp gone: `volt-doctor-no-such-command` is not on PATH\n"));
+
+        let mut out = Vec::new();
+        fs::write(dir.path().join("settings.json"), "{}").unwrap();
+        assert!(doctor(config.path(), false, true, &mut out).unwrap());
+        let report: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(report["ok"], true);
+        assert_eq!(report["checks"][0]["status"], "pass");
+    }
+
+    #[test]
+    fn test_migrate() {
+        let (dir, mut config) =
+            temp_config(r#"{"amp.hideCosts": false, "amp.todos.enabled": true}"#);
+        let out = run(|o| migrate(&mut config, true, false, o));
+        assert_eq!(
+            out,
+            "amp.hideCosts: replaced by amp.showCosts (inverted)\n\
+             amp.todos.enabled: removed, no longer used\n\
+             (dry run; nothing was written)\n"
+        );
+        let path = dir.path().join("settings.json");
+        assert_eq!(Config::load(&path).unwrap().deprecated_keys().len(), 2);
+
+        let (dir, mut config) = temp_config(r#"{"amp.hideCosts": false}"#);
+        let out = run(|o| migrate(&mut config, false, true, o));
+        assert_eq!(
+            out,
+            r#"[{"key":"amp.hideCosts","replacement":"amp.showCosts","applied":true,"note":"replaced by amp.showCosts (inverted)"}]"#
+                .to_string()
+                + "\n"
+        );
+        let reloaded = Config::load(&dir.path().join("settings.json")).unwrap();
+        assert_eq!(reloaded.get_raw("amp.showCosts"), Some(&Value::Bool(true)));
+        assert!(reloaded.get_raw("amp.hideCosts").is_none());
+
+        assert_eq!(
+            run(|o| migrate(&mut config, false, false, o)),
+            "no deprecated settings\n"
+        );
+    }
+
+    #[test]
+    fn test_unset() {
+        let (_dir, mut config) = temp_config(r#"{"amp.showCosts": false}"#);
+        assert_eq!(
+            run(|o| unset(&mut config, "amp.showCosts", false, o)),
+            "amp.showCosts unset\n"
+        );
+        assert!(config.get_raw("amp.showCosts").is_none());
+        assert!(unset(&mut config, "amp.showCosts", false, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_list_section() {
+        let (_dir, config) = temp_config(r#"{"amp.tools.stopTimeout": 60}"#);
+        let out = run(|o| list(&config, Some("tools"), false, o));
+        assert_eq!(
+            out,
+            "amp.tools.disable = [] (default)\namp.tools.stopTimeout = 60\n"
+        );
+        assert!(list(&config, Some("nope"), false, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_ignore_broken_pipe() {
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let (_dir, config) = temp_config("{}");
+        let result = list(&config, None, false, &mut Closed);
+        assert!(result.is_err());
+        assert!(ignore_broken_pipe(result).is_ok());
+        assert!(ignore_broken_pipe(list(&config, Some("nope"), false, &mut Closed)).is_err());
+    }
+
+    #[test]
+    fn test_list_json_includes_unknown_keys() {
+        let (_dir, config) = temp_config(r#"{"amp.experimental.labs": ["x"]}"#);
+        let out = run(|o| list(&config, None, true, o));
+        let parsed: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed["amp.experimental.labs"], serde_json::json!(["x"]));
+        assert_eq!(parsed["amp.showCosts"], Value::Bool(true));
+    }
+
+    #[test]
+    fn test_validate_reports_and_fails_on_errors() {
+        let (_dir, config) = temp_config(r#"{"amp.showCosts": "yes", "amp.mystery": 1}"#);
+        let mut out = Vec::new();
+        assert!(!validate(&config, false, &mut out).unwrap());
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("error   amp.showCosts: expected boolean"));
+        assert!(out.contains("warning amp.mystery: not a known Amp setting"));
+        assert!(out.ends_with("1 error(s), 1 warning(s)\n"));
+
+        let mut out = Vec::new();
+        validate(&config, true, &mut out).unwrap();
+        let report: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(report["ok"], Value::Bool(false));
+        assert_eq!(report["findings"][0]["path"], "amp.showCosts");
+        assert_eq!(report["findings"][1]["severity"], "warning");
+    }
+
+    #[test]
+    fn test_validate_passes_with_only_warnings() {
+        let (_dir, config) = temp_config(r#"{"amp.mystery": 1}"#);
+        assert!(validate(&config, true, &mut Vec::new()).unwrap());
+    }
+
+    #[test]
+    fn test_script_saves_only_when_every_op_succeeds() {
+        let (dir, mut config) = temp_config(r#"{"amp.showCosts": true}"#);
+        let path = dir.path().join("settings.json");
+        let failing = r#"[{"op": "set", "key": "amp.showCosts", "value": false},
+                          {"op": "add-array-item", "key": "amp.showCosts", "item": 1}]"#;
+        let mut out = Vec::new();
+        assert!(script(&mut config, failing, false, &mut out).is_err());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"amp.showCosts": true}"#
+        );
+
+        let (_dir, mut config) = temp_config(r#"{"amp.showCosts": true}"#);
+        let ops = r#"[{"op": "set", "key": "amp.showCosts", "value": false}]"#;
+        let mut out = Vec::new();
+        script(&mut config, ops, true, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"applied\":[\"set amp.showCosts = false\"]}\n"
+        );
+        let saved = Config::load(config.path()).unwrap();
+        assert_eq!(saved.get("amp.showCosts"), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_import_mcp_skips_existing_unless_overwrite() {
+        let (dir, mut config) = temp_config(r#"{"amp.mcpServers": {"a": {"command": "old"}}}"#);
+        config.set_backup_retention(0);
+        let cursor = dir.path().join("mcp.json");
+        fs::write(
+            &cursor,
+            r#"{"mcpServers": {"a": {"command": "new"}, "b": {"command": "b"}}}"#,
+        )
+        .unwrap();
+
+        let out = run(|o| import_mcp(&mut config, Source::Cursor, &cursor, &[], false, false, o));
+        assert_eq!(
+            out,
+            "imported b\nskipped a (already configured; use --overwrite to replace)\n"
+        );
+        let reloaded = Config::load(config.path()).unwrap();
+        assert_eq!(reloaded.get("amp.mcpServers")["a"]["command"], "old");
+        assert_eq!(reloaded.get("amp.mcpServers")["b"]["command"], "b");
+
+        let only = ["a".to_string()];
+        let out = run(|o| import_mcp(&mut config, Source::Cursor, &cursor, &only, true, true, o));
+        assert_eq!(out, "{\"imported\":[\"a\"],\"skipped\":[]}\n");
+        assert_eq!(config.get("amp.mcpServers")["a"]["command"], "new");
+
+        let missing = ["zzz".to_string()];
+        assert!(import_mcp(
+            &mut config,
+            Source::Cursor,
+            &cursor,
+            &missing,
+            false,
+            false,
+            &mut Vec::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(
+            parse_value("amp.tools.stopTimeout", "1.5").unwrap(),
+            serde_json::json!(1.5)
+        );
+        assert!(parse_value("amp.tools.stopTimeout", "soon").is_err());
+        assert_eq!(
+            parse_value("amp.skills.path", "~/skills").unwrap(),
+            Value::String("~/skills".into())
+        );
+        assert_eq!(
+            parse_value("amp.custom", "42").unwrap(),
+            serde_json::json!(42)
+        );
+        assert_eq!(
+            parse_value("amp.custom", "hello").unwrap(),
+            Value::String("hello".into())
+        );
+    }
+
+    #[test]
+    fn test_diff_against_defaults() {
+        let (_dir, config) = temp_config(
+            r#"{"amp.terminal.theme": "nord", "amp.git.commit.coauthor.enabled": true, "custom.key": 1}"#,
+        );
+        let default_theme = settings::get_setting_def("amp.terminal.theme")
+            .unwrap()
+            .default;
+        let output = run(|o| {
+            diff(
+                &config,
+                &Against::Defaults,
+                DiffFormat::Table,
+                false,
+                false,
+                o,
+            )
+        });
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("KEY"), "{output}");
+        assert!(lines[0].ends_with("DEFAULTS"));
+        assert!(output.contains("custom.key"));
+        assert!(
+            output.contains(&format!("nord  {}", format_plain(&default_theme))),
+            "{output}"
+        );
+
+        let json = run(|o| {
+            diff(
+                &config,
+                &Against::Defaults,
+                DiffFormat::Table,
+                false,
+                true,
+                o,
+            )
+        });
+        let report: Value = serde_json::from_str(&json).unwrap();
+        let custom = report
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["key"] == "custom.key")
+            .unwrap();
+        assert_eq!(custom["against"], Value::Null);
+    }
+
+    #[test]
+    fn test_diff_against_file_unified() {
+        let (_dir, config) = temp_config(r#"{"a": {"x": 1, "y": 2}, "same": true}"#);
+        let (_other_dir, other) =
+            temp_config(r#"{"a": {"x": 1, "y": 3}, "same": true, "gone": 1}"#);
+        let output = run(|o| {
+            diff(
+                &config,
+                &Against::File(Box::new(other)),
+                DiffFormat::Unified,
+                false,
+                false,
+                o,
+            )
+        });
+        let lines: Vec<&str> = output.lines().skip(2).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "@@ a @@",
+                " {",
+                "   \"x\": 1,",
+                "-  \"y\": 3",
+                "+  \"y\": 2",
+                " }",
+                "@@ gone @@",
+                "-1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_without_differences() {
+        let (_dir, config) = temp_config("{}");
+        assert_eq!(
+            run(|o| diff(
+                &config,
+                &Against::Defaults,
+                DiffFormat::Unified,
+                true,
+                false,
+                o
+            )),
+            "no differences from defaults\n"
+        );
+    }
+
+    #[test]
+    fn test_profile_save_list_load() {
+        let (dir, mut config) = temp_config(r#"{"amp.terminal.theme": "nord", "custom.key": 1}"#);
+        config.set_backup_retention(0);
+        let profiles = dir.path().join("profiles");
+        assert_eq!(
+            run(|o| profile_save(
+                &config,
+                &profiles,
+                "gen",
+                &["general".to_string()],
+                false,
+                o
+            )),
+            format!(
+                "saved profile gen to {}\n",
+                profiles.join("gen.json").display()
+            )
+        );
+        assert!(profile_save(
+            &config,
+            &profiles,
+            "x",
+            &["nope".to_string()],
+            false,
+            &mut Vec::new()
+        )
+        .is_err());
+        assert_eq!(run(|o| profile_list(&profiles, true, o)), "[\"gen\"]\n");
+
+        config.set("amp.terminal.theme", Value::String("light".to_string()));
+        config.save().unwrap();
+        assert_eq!(
+            run(|o| profile_load(&mut config, &profiles, "gen", false, o)),
+            "loaded profile gen (General)\n"
+        );
+        let reloaded = Config::load(config.path()).unwrap();
+        assert_eq!(reloaded.get("amp.terminal.theme"), "nord");
+        assert_eq!(reloaded.get("custom.key"), 1);
+    }
+}
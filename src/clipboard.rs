@@ -0,0 +1,133 @@
+//! System clipboard access through the platform's clipboard tools, falling
+//! back to the OSC 52 escape sequence so copying also works over SSH.
+
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use crate::amp;
+
+/// Commands that copy stdin to the clipboard, in order of preference.
+const COPY_COMMANDS: &[&[&str]] = &[
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["clip.exe"],
+];
+
+/// Commands that print the clipboard to stdout, in order of preference.
+const PASTE_COMMANDS: &[&[&str]] = &[
+    &["pbpaste"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+    &["powershell.exe", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+/// Copies text to the clipboard and returns how it was sent. Over SSH the
+/// local tools would fill the remote machine's clipboard, so OSC 52 is used
+/// to reach the user's terminal instead.
+pub fn copy(text: &str) -> Result<&'static str> {
+    if !over_ssh() {
+        if let Some(args) = find_command(COPY_COMMANDS) {
+            let mut child = Command::new(args[0])
+                .args(&args[1..])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .with_context(|| format!("running {}", args[0]))?;
+            child
+                .stdin
+                .take()
+                .context("no stdin")?
+                .write_all(text.as_bytes())
+                .with_context(|| format!("writing to {}", args[0]))?;
+            let status = child.wait()?;
+            if !status.success() {
+                bail!("{} exited with {status}", args[0]);
+            }
+            return Ok(args[0]);
+        }
+    }
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52(text).as_bytes())?;
+    stdout.flush()?;
+    Ok("OSC 52")
+}
+
+/// Reads text from the clipboard. Terminals rarely allow reading it through
+/// OSC 52, so this needs one of the platform tools.
+pub fn paste() -> Result<String> {
+    let Some(args) = find_command(PASTE_COMMANDS) else {
+        bail!("no clipboard tool found (install wl-clipboard, xclip, or xsel)");
+    };
+    let output = Command::new(args[0])
+        .args(&args[1..])
+        .output()
+        .with_context(|| format!("running {}", args[0]))?;
+    if !output.status.success() {
+        bail!("{} exited with {}", args[0], output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn over_ssh() -> bool {
+    env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some()
+}
+
+/// Picks the first command whose program is installed. `wl-copy` and
+/// `wl-paste` only work inside a Wayland session.
+fn find_command(commands: &'static [&'static [&'static str]]) -> Option<&'static [&'static str]> {
+    let wayland = env::var_os("WAYLAND_DISPLAY").is_some();
+    commands.iter().copied().find(|args| {
+        (wayland || !args[0].starts_with("wl-")) && amp::find_on_path(args[0]).is_some()
+    })
+}
+
+/// Builds the OSC 52 sequence asking the terminal to set its clipboard.
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64("é".as_bytes()), "w6k=");
+    }
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+    }
+}
@@ -2,55 +2,413 @@
 
 use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use anyhow::{Context, Result};
-use json_comments::StripComments;
 use serde_json::{Map, Value};
 
+use crate::backup;
+use crate::jsonc;
 use crate::settings::{self, SettingType};
 
+/// Where the settings file's path came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSource {
+    /// The `--config` flag.
+    Flag,
+    /// The `AMP_SETTINGS_PATH` environment variable.
+    EnvVar,
+    /// `$XDG_CONFIG_HOME/amp`.
+    XdgConfigHome,
+    /// `~/.config/amp`.
+    Home,
+    /// The platform's config directory, such as `%APPDATA%\amp`.
+    PlatformConfigDir,
+}
+
+impl PathSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            PathSource::Flag => "--config",
+            PathSource::EnvVar => "AMP_SETTINGS_PATH",
+            PathSource::XdgConfigHome => "XDG_CONFIG_HOME",
+            PathSource::Home => "~/.config",
+            PathSource::PlatformConfigDir if cfg!(windows) => "%APPDATA%",
+            PathSource::PlatformConfigDir if cfg!(target_os = "macos") => {
+                "~/Library/Application Support"
+            }
+            PathSource::PlatformConfigDir => "platform config directory",
+        }
+    }
+}
+
+/// Whether the platform's config directory is searched before `~/.config`,
+/// and so is where a new file goes. Windows programs keep their settings
+/// in `%APPDATA%`; elsewhere Amp uses `~/.config`.
+const PLATFORM_DIR_FIRST: bool = cfg!(windows);
+
+/// File names Amp's settings are looked for under, in order.
+const SETTINGS_FILE_NAMES: &[&str] = &["settings.json", "settings.jsonc"];
+
+/// [`Config::locate`] with the environment and home directories passed in.
+fn locate_in(
+    env: impl Fn(&str) -> Option<std::ffi::OsString>,
+    home: Option<&Path>,
+    config_dir: Option<&Path>,
+    platform_first: bool,
+) -> Result<(PathBuf, PathSource)> {
+    if let Some(path) = env("AMP_SETTINGS_PATH").filter(|p| !p.is_empty()) {
+        return Ok((PathBuf::from(path), PathSource::EnvVar));
+    }
+    let mut dirs = Vec::new();
+    // Like other XDG tools, ignore a relative XDG_CONFIG_HOME.
+    if let Some(xdg) = env("XDG_CONFIG_HOME").map(PathBuf::from) {
+        if xdg.is_absolute() {
+            dirs.push((xdg.join("amp"), PathSource::XdgConfigHome));
+        }
+    }
+    let home = home.map(|home| (home.join(".config").join("amp"), PathSource::Home));
+    let platform = config_dir.map(|dir| (dir.join("amp"), PathSource::PlatformConfigDir));
+    if platform_first {
+        dirs.extend(platform.into_iter().chain(home));
+    } else {
+        dirs.extend(home.into_iter().chain(platform));
+    }
+    dirs.dedup_by(|a, b| a.0 == b.0);
+
+    let found = dirs.iter().find_map(|(dir, source)| {
+        SETTINGS_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+            .map(|path| (path, *source))
+    });
+    match (found, dirs.first()) {
+        (Some(found), _) => Ok(found),
+        (None, Some((dir, source))) => Ok((dir.join(SETTINGS_FILE_NAMES[0]), *source)),
+        (None, None) => anyhow::bail!("could not determine home directory"),
+    }
+}
+
+/// Resolves a leading `~/` the way a shell would.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// A short content hash (64-bit FNV-1a), stable across volt versions.
+pub fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
 /// Represents the loaded configuration state.
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Path to the settings.json file.
     path: PathBuf,
     /// All setting values (known + unknown), keyed by setting name.
-    values: BTreeMap<String, Value>,
+    values: Map<String, Value>,
+    /// The values as of the last load/save, used to tell local edits apart
+    /// from changes made to the file by someone else.
+    baseline: Map<String, Value>,
+    /// The file's text as of the last load/save, used to preserve comments,
+    /// formatting, and key order when saving.
+    source: Option<String>,
     /// Whether values have been modified since last save/load.
     dirty: bool,
+    /// Counts modifications, so a watcher can tell a new one happened.
+    revision: u64,
+    /// Size and modification time of the file as of the last load/save.
+    disk_info: Option<DiskInfo>,
+    /// How many backups to keep when saving; zero disables backups.
+    backup_retention: usize,
+    /// Whether JSON5 syntax and trailing commas are rejected rather than
+    /// accepted.
+    strict: bool,
+    /// Whether the file used JSON5 syntax, which the next save rewrites as
+    /// plain JSON.
+    normalized: bool,
+    /// Whether saving is refused, for browsing a file without changing it.
+    read_only: bool,
+    /// Values set by `AMP_*` environment variables, which win over the
+    /// file's. They're shown but never edited or saved.
+    env: BTreeMap<String, EnvOverride>,
+    /// How saving lays out the file.
+    format: Format,
+}
+
+/// A setting overridden by an environment variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvOverride {
+    /// The variable, e.g. `AMP_SHOW_COSTS`.
+    pub var: String,
+    pub value: Value,
+}
+
+/// How saving lays out a file, from `[format]` in volt's preferences. By
+/// default a file keeps its own indentation, key order, and final newline,
+/// and new files get two-space indentation and a final newline.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Format {
+    /// The indentation of each level, e.g. four spaces or a tab, applied to
+    /// the whole file.
+    pub indent: Option<String>,
+    /// Whether top-level keys are put in order by name.
+    pub sort_keys: bool,
+    /// Whether the file ends with a newline.
+    pub trailing_newline: Option<bool>,
+}
+
+impl Format {
+    /// Applies the key order and final newline to a rendered file.
+    fn finish(&self, text: String) -> Result<String> {
+        let text = if self.sort_keys {
+            jsonc::sort_members(&text)?
+        } else {
+            text
+        };
+        Ok(match self.trailing_newline {
+            Some(true) if !text.ends_with('\n') => text + "\n",
+            Some(false) => text.trim_end_matches(['\n', '\r']).to_string(),
+            _ => text,
+        })
+    }
+}
+
+/// File metadata captured after loading or saving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskInfo {
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// A key both this session and another program changed, found by
+/// [`Config::merge_from_disk`]. `None` means the key isn't set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub key: String,
+    /// The value as of the last load/save.
+    pub base: Option<Value>,
+    /// The value now on disk.
+    pub theirs: Option<Value>,
+    /// The unsaved local value, which the merge keeps.
+    pub mine: Option<Value>,
+}
+
+/// One deprecated key handled by [`Config::migrate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    pub old: String,
+    /// The key the value moved to, if it moved.
+    pub new: Option<String>,
+    /// What happened, e.g. "renamed to amp.tools.disable".
+    pub note: String,
+    /// Whether the old key was rewritten; skipped keys are left as they are.
+    pub applied: bool,
 }
 
 impl Config {
     /// Loads settings from the given path, or creates an empty config if the file
-    /// doesn't exist.
+    /// doesn't exist. JSON5 syntax is accepted.
     pub fn load(path: &Path) -> Result<Self> {
-        let values = if path.exists() {
+        Self::load_with(path, false)
+    }
+
+    /// Loads settings like [`Config::load`], rejecting JSON5 syntax and
+    /// trailing commas when `strict`.
+    pub fn load_with(path: &Path, strict: bool) -> Result<Self> {
+        let (values, source, normalized) = if path.exists() {
             let contents =
                 fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
             if contents.trim().is_empty() {
-                BTreeMap::new()
+                (Map::new(), None, false)
             } else {
-                let stripped = StripComments::new(contents.as_bytes());
-                let parsed: Map<String, Value> = serde_json::from_reader(stripped)
+                let (parsed, source) = parse_source(&contents, strict)
                     .with_context(|| format!("parsing {}", path.display()))?;
-                parsed.into_iter().collect()
+                let normalized = source != contents;
+                (parsed, Some(source), normalized)
             }
         } else {
-            BTreeMap::new()
+            (Map::new(), None, false)
         };
 
         Ok(Self {
             path: path.to_path_buf(),
+            baseline: values.clone(),
             values,
+            source,
             dirty: false,
+            revision: 0,
+            disk_info: read_disk_info(path),
+            backup_retention: backup::DEFAULT_RETENTION,
+            strict,
+            normalized,
+            read_only: false,
+            env: BTreeMap::new(),
+            format: Format::default(),
         })
     }
 
-    /// Returns the resolved default settings file path for the current OS.
-    pub fn default_path() -> Result<PathBuf> {
-        let home = dirs::home_dir().context("could not determine home directory")?;
-        Ok(home.join(".config").join("amp").join("settings.json"))
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    /// Sets how saving lays out the file.
+    pub fn set_format(&mut self, format: Format) {
+        self.format = format;
+    }
+
+    /// Makes every save fail, so the file can be inspected safely.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Records the known settings that environment variables override, from
+    /// `vars` (the process environment outside tests). Values that don't fit
+    /// the setting's type are ignored, as they'd be invalid in the file too.
+    pub fn set_env_overrides(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+        let vars: BTreeMap<String, String> = vars.into_iter().collect();
+        self.env = settings::known_settings()
+            .into_iter()
+            .filter_map(|def| {
+                let var = settings::env_var(def.key);
+                let raw = vars.get(&var)?;
+                let value = match def.setting_type {
+                    SettingType::String | SettingType::StringEnum => Value::String(raw.clone()),
+                    SettingType::Boolean => match raw.trim() {
+                        "true" | "1" => Value::Bool(true),
+                        "false" | "0" => Value::Bool(false),
+                        _ => return None,
+                    },
+                    SettingType::Number => Value::Number(settings::parse_number(raw)?),
+                    _ => serde_json::from_str(raw).ok()?,
+                };
+                Self::validate_value(def.key, &value).ok()?;
+                Some((def.key.to_string(), EnvOverride { var, value }))
+            })
+            .collect();
+    }
+
+    /// The environment variable overriding a key, if one does.
+    pub fn env_override(&self, key: &str) -> Option<&EnvOverride> {
+        self.env.get(key)
+    }
+
+    /// Returns whether saving is refused.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns whether JSON5 syntax is rejected when loading.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Returns whether the file was written in JSON5 and the next save will
+    /// rewrite it as plain JSON.
+    pub fn is_normalized(&self) -> bool {
+        self.normalized
+    }
+
+    /// Returns whether the settings file exists on disk.
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Writes a starter settings file listing every known setting at its default,
+    /// commented out, then reloads from it.
+    pub fn write_scaffold(&mut self) -> Result<()> {
+        anyhow::ensure!(!self.read_only, "{} is open read-only", self.path.display());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+        fs::write(&self.path, scaffold_contents())
+            .with_context(|| format!("writing {}", self.path.display()))?;
+        let (retention, read_only) = (self.backup_retention, self.read_only);
+        let env = std::mem::take(&mut self.env);
+        let format = std::mem::take(&mut self.format);
+        *self = Self::load_with(&self.path, self.strict)?;
+        self.backup_retention = retention;
+        self.read_only = read_only;
+        self.env = env;
+        self.format = format;
+        Ok(())
+    }
+
+    /// Finds the settings file when `--config` isn't given, and says how.
+    ///
+    /// `AMP_SETTINGS_PATH` names the file outright. Otherwise the `amp`
+    /// directory is looked for under `$XDG_CONFIG_HOME`, then `~/.config`
+    /// and the platform's config directory (`~/Library/Application Support`,
+    /// `%APPDATA%`), the latter first on Windows; the first holding a
+    /// `settings.json` or `settings.jsonc` wins, and with none, the first of
+    /// them is where a new file goes.
+    pub fn locate() -> Result<(PathBuf, PathSource)> {
+        locate_in(
+            |name| std::env::var_os(name),
+            dirs::home_dir().as_deref(),
+            dirs::config_dir().as_deref(),
+            PLATFORM_DIR_FIRST,
+        )
+    }
+
+    /// Returns the path of the settings file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the JSON config files living next to this one (and one directory
+    /// level below it), including this file itself, sorted by path.
+    pub fn sibling_files(&self) -> Vec<PathBuf> {
+        let Some(dir) = self.path.parent() else {
+            return vec![self.path.clone()];
+        };
+        let mut files = vec![self.path.clone()];
+        collect_json_files(dir, 1, &mut files);
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Returns the names of user-installed themes found in the `themes` directory
+    /// next to the settings file. Both theme directories and single theme files
+    /// are recognized; built-in theme names are skipped.
+    pub fn custom_themes(&self) -> Vec<String> {
+        let Some(dir) = self.path.parent().map(|p| p.join("themes")) else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Vec::new();
+        };
+
+        let builtin = settings::get_setting_def("amp.terminal.theme")
+            .and_then(|def| def.enum_options)
+            .unwrap_or_default();
+
+        let mut themes: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                let name = if path.is_dir() {
+                    path.file_name()?.to_str()?.to_string()
+                } else {
+                    path.file_stem()?.to_str()?.to_string()
+                };
+                Some(name)
+            })
+            .filter(|name| !name.starts_with('.') && !builtin.contains(&name.as_str()))
+            .collect();
+        themes.sort();
+        themes.dedup();
+        themes
     }
 
     /// Gets the current value for a key, falling back to the known default.
@@ -72,13 +430,13 @@ impl Config {
     /// Sets a value for a key.
     pub fn set(&mut self, key: &str, value: Value) {
         self.values.insert(key.to_string(), value);
-        self.dirty = true;
+        self.touch();
     }
 
     /// Removes a key (resets to default).
     pub fn remove(&mut self, key: &str) {
-        if self.values.remove(key).is_some() {
-            self.dirty = true;
+        if self.values.shift_remove(key).is_some() {
+            self.touch();
         }
     }
 
@@ -87,28 +445,224 @@ impl Config {
         self.dirty
     }
 
-    /// Saves the config to disk as formatted JSON.
+    /// Returns a number that changes whenever the values are modified.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn touch(&mut self) {
+        self.dirty = true;
+        self.revision += 1;
+    }
+
+    /// Returns the number of keys explicitly set in the file.
+    pub fn explicit_count(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns every key explicitly set in the file with its value.
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values.iter()
+    }
+
+    /// The file's settings as one JSON object, with secrets replaced by
+    /// `"<redacted>"` if `redact` (see [`settings::redact`]).
+    pub fn export(&self, redact: bool) -> Value {
+        Value::Object(
+            self.values
+                .iter()
+                .map(|(key, value)| {
+                    let value = if redact {
+                        settings::redact(key, value)
+                    } else {
+                        value.clone()
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns how many backups are kept when saving.
+    pub fn backup_retention(&self) -> usize {
+        self.backup_retention
+    }
+
+    /// Sets how many backups to keep when saving; zero disables backups.
+    pub fn set_backup_retention(&mut self, retention: usize) {
+        self.backup_retention = retention;
+    }
+
+    /// Replaces the in-memory settings with the contents of a backup. The file
+    /// itself is untouched until the next save.
+    pub fn restore_backup(&mut self, backup: &Path) -> Result<()> {
+        let contents =
+            fs::read_to_string(backup).with_context(|| format!("reading {}", backup.display()))?;
+        let (parsed, source) = parse_source(&contents, self.strict)
+            .with_context(|| format!("parsing {}", backup.display()))?;
+        self.values = parsed;
+        self.source = Some(source);
+        self.touch();
+        Ok(())
+    }
+
+    /// Replaces the file with `contents` as given, comments and all, after
+    /// checking it parses and backing up the previous file, then reloads.
+    pub fn overwrite(&mut self, contents: &str) -> Result<()> {
+        anyhow::ensure!(!self.read_only, "{} is open read-only", self.path.display());
+        parse_source(contents, self.strict).context("the new contents are not valid settings")?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+        backup::create(&self.path, self.backup_retention).context("backing up settings")?;
+        write_atomic(&self.path, contents)?;
+        self.reload()
+    }
+
+    /// Returns the file's size and modification time as of the last load/save.
+    pub fn disk_info(&self) -> Option<DiskInfo> {
+        self.disk_info
+    }
+
+    /// Saves the config to disk, first backing up the previous file. If the file
+    /// already existed, only the changed members are rewritten so comments and
+    /// formatting are preserved; otherwise the settings are written as formatted JSON.
     pub fn save(&mut self) -> Result<()> {
-        let map: Map<String, Value> = self
-            .values
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-        let json =
-            serde_json::to_string_pretty(&Value::Object(map)).context("serializing settings")?;
+        anyhow::ensure!(!self.read_only, "{} is open read-only", self.path.display());
+        let json = self.render()?;
 
         if let Some(parent) = self.path.parent() {
             fs::create_dir_all(parent)
                 .with_context(|| format!("creating directory {}", parent.display()))?;
         }
 
-        fs::write(&self.path, json + "\n")
-            .with_context(|| format!("writing {}", self.path.display()))?;
+        backup::create(&self.path, self.backup_retention).context("backing up settings")?;
+        write_atomic(&self.path, &json)?;
 
+        self.source = Some(json);
+        self.baseline = self.values.clone();
         self.dirty = false;
+        self.normalized = false;
+        self.disk_info = read_disk_info(&self.path);
         Ok(())
     }
 
+    /// Returns whether the file on disk was modified, created, or deleted
+    /// since it was last loaded or saved.
+    pub fn changed_on_disk(&self) -> bool {
+        read_disk_info(&self.path) != self.disk_info
+    }
+
+    /// Discards in-memory changes and reloads the file from disk.
+    pub fn reload(&mut self) -> Result<()> {
+        let (retention, read_only) = (self.backup_retention, self.read_only);
+        let revision = self.revision + 1;
+        let env = std::mem::take(&mut self.env);
+        let format = std::mem::take(&mut self.format);
+        *self = Self::load_with(&self.path, self.strict)?;
+        self.backup_retention = retention;
+        self.read_only = read_only;
+        self.env = env;
+        self.format = format;
+        self.revision = revision;
+        Ok(())
+    }
+
+    /// The keys whose values differ from the file as last loaded or saved.
+    pub fn unsaved_keys(&self) -> Vec<String> {
+        let keys: std::collections::BTreeSet<&String> =
+            self.values.keys().chain(self.baseline.keys()).collect();
+        keys.into_iter()
+            .filter(|key| self.values.get(*key) != self.baseline.get(*key))
+            .cloned()
+            .collect()
+    }
+
+    /// Reloads the file from disk and reapplies the keys changed locally since
+    /// the last load/save on top. Returns the keys that were also changed on
+    /// disk, where the local value won.
+    pub fn merge_from_disk(&mut self) -> Result<Vec<Conflict>> {
+        let mut merged = Self::load_with(&self.path, self.strict)?;
+        merged.backup_retention = self.backup_retention;
+        merged.read_only = self.read_only;
+        merged.env = self.env.clone();
+        merged.format = std::mem::take(&mut self.format);
+
+        let mut conflicts = Vec::new();
+        let keys: std::collections::BTreeSet<&String> =
+            self.values.keys().chain(self.baseline.keys()).collect();
+        for key in keys {
+            let local = self.values.get(key);
+            let base = self.baseline.get(key);
+            if local == base {
+                continue;
+            }
+            let disk = merged.values.get(key);
+            if disk != base && disk != local {
+                conflicts.push(Conflict {
+                    key: key.clone(),
+                    base: base.cloned(),
+                    theirs: disk.cloned(),
+                    mine: local.cloned(),
+                });
+            }
+            match local {
+                Some(value) => merged.set(key, value.clone()),
+                None => merged.remove(key),
+            }
+        }
+
+        merged.revision += self.revision + 1;
+        *self = merged;
+        Ok(conflicts)
+    }
+
+    /// Accepts the file's current state on disk without reloading it, so the
+    /// next save overwrites the external changes with the in-memory values.
+    pub fn keep_local(&mut self) {
+        self.disk_info = read_disk_info(&self.path);
+        self.touch();
+    }
+
+    /// Returns the text a save would write.
+    pub fn render(&self) -> Result<String> {
+        let text = match &self.source {
+            Some(source) => {
+                let source = match &self.format.indent {
+                    Some(indent) => jsonc::reindent(source, indent),
+                    None => source.clone(),
+                };
+                jsonc::patch(&source, self.values.iter())
+                    .with_context(|| format!("updating {}", self.path.display()))?
+            }
+            None => {
+                let map: Map<String, Value> = self
+                    .values
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                let text = serde_json::to_string_pretty(&Value::Object(map))
+                    .context("serializing settings")?
+                    + "\n";
+                match &self.format.indent {
+                    Some(indent) => jsonc::reindent(&text, indent),
+                    None => text,
+                }
+            }
+        };
+        self.format.finish(text)
+    }
+
+    /// Returns the file's current contents on disk, or an empty string if it
+    /// doesn't exist yet.
+    pub fn read_disk(&self) -> Result<String> {
+        if !self.path.exists() {
+            return Ok(String::new());
+        }
+        fs::read_to_string(&self.path).with_context(|| format!("reading {}", self.path.display()))
+    }
+
     /// Returns all keys that are not known settings (for the Advanced section).
     pub fn unknown_keys(&self) -> Vec<String> {
         self.values
@@ -118,47 +672,81 @@ impl Config {
             .collect()
     }
 
+    /// Returns the keys that are set but deprecated or renamed.
+    pub fn deprecated_keys(&self) -> Vec<String> {
+        self.values
+            .keys()
+            .filter(|k| settings::migration_for(k).is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// Rewrites every deprecated key to its replacement. A key whose
+    /// replacement is already set is dropped in favor of it, and a value that
+    /// can't be carried over (say, a non-boolean to invert) is left in place.
+    pub fn migrate(&mut self) -> Vec<MigrationStep> {
+        let mut steps = Vec::new();
+        for old in self.deprecated_keys() {
+            let Some(migration) = settings::migration_for(&old) else {
+                continue;
+            };
+            let mut step = MigrationStep {
+                old: old.clone(),
+                new: None,
+                note: String::new(),
+                applied: true,
+            };
+            match migration.new {
+                None => step.note = format!("removed, {}", migration.describe()),
+                Some(new) if self.values.contains_key(new) => {
+                    step.note = format!("removed, {new} is already set");
+                }
+                Some(new) => {
+                    let value = migration.apply(&self.values[&old]).and_then(|v| {
+                        Self::validate_value(new, &v)
+                            .map(|_| v)
+                            .map_err(|e| e.to_string())
+                    });
+                    match value {
+                        Ok(value) => {
+                            self.set(new, value);
+                            step.new = Some(new.to_string());
+                            step.note = migration.describe();
+                        }
+                        Err(e) => {
+                            step.note = format!("kept: {e}");
+                            step.applied = false;
+                        }
+                    }
+                }
+            }
+            if step.applied {
+                self.remove(&old);
+            }
+            steps.push(step);
+        }
+        steps
+    }
+
     /// Validates that a value matches the expected type for a known setting.
     pub fn validate_value(key: &str, value: &Value) -> Result<()> {
         let Some(def) = settings::get_setting_def(key) else {
             return Ok(());
         };
 
-        let type_ok = match def.setting_type {
-            SettingType::Boolean => value.is_boolean(),
-            SettingType::String | SettingType::StringEnum => value.is_string(),
-            SettingType::Number => value.is_number(),
-            SettingType::ArrayString => {
-                value.is_array()
-                    && value
-                        .as_array()
-                        .map(|a| a.iter().all(|v| v.is_string()))
-                        .unwrap_or(false)
-            }
-            SettingType::ArrayObject => {
-                value.is_array()
-                    && value
-                        .as_array()
-                        .map(|a| a.iter().all(|v| v.is_object()))
-                        .unwrap_or(false)
-            }
-            SettingType::Object => value.is_object(),
-        };
-
         anyhow::ensure!(
-            type_ok,
+            type_matches(def.setting_type, value),
             "expected {} for key '{}'",
-            match def.setting_type {
-                SettingType::Boolean => "boolean",
-                SettingType::String | SettingType::StringEnum => "string",
-                SettingType::Number => "number",
-                SettingType::ArrayString => "array of strings",
-                SettingType::ArrayObject => "array of objects",
-                SettingType::Object => "object",
-            },
+            type_name(def.setting_type),
             key
         );
 
+        if let (SettingType::Number, Some(n)) = (def.setting_type, value.as_f64()) {
+            if let Err(e) = def.number_spec().check(n) {
+                anyhow::bail!("'{key}' {e}");
+            }
+        }
+
         if def.setting_type == SettingType::StringEnum && !def.allows_custom {
             if let (Some(options), Some(s)) = (def.enum_options, value.as_str()) {
                 if !options.contains(&s) {
@@ -174,11 +762,269 @@ impl Config {
 
         Ok(())
     }
+
+    /// Returns the known settings whose stored value has the wrong type,
+    /// e.g. `"amp.showCosts": "true"`.
+    pub fn type_mismatches(&self) -> Vec<String> {
+        self.entries()
+            .filter(|(key, value)| {
+                settings::get_setting_def(key)
+                    .is_some_and(|def| !type_matches(def.setting_type, value))
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Converts a value of the wrong type to what the setting expects when
+    /// the intent is unambiguous: `"true"` to `true`, `"30"` to `30`, `5` to
+    /// `"5"`, a lone string to a one-item array, or JSON text to the object
+    /// or array it spells. Returns `None` when there's no sensible reading.
+    pub fn coerce_value(key: &str, value: &Value) -> Option<Value> {
+        let def = settings::get_setting_def(key)?;
+        let text = value.as_str().map(str::trim);
+        let coerced = match (def.setting_type, value) {
+            (SettingType::Boolean, Value::Number(n)) => match n.as_f64()? {
+                0.0 => Value::Bool(false),
+                1.0 => Value::Bool(true),
+                _ => return None,
+            },
+            (SettingType::Boolean, _) => match text?.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Value::Bool(true),
+                "false" | "no" | "off" | "0" => Value::Bool(false),
+                _ => return None,
+            },
+            (SettingType::Number, _) => Value::Number(settings::parse_number(text?)?),
+            (SettingType::String | SettingType::StringEnum, Value::Bool(_) | Value::Number(_)) => {
+                Value::String(value.to_string())
+            }
+            (SettingType::ArrayString, Value::String(s)) => {
+                match serde_json::from_str::<Value>(s) {
+                    Ok(parsed @ Value::Array(_)) => parsed,
+                    _ => Value::Array(vec![value.clone()]),
+                }
+            }
+            (SettingType::ArrayString, Value::Array(items)) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| match item {
+                        Value::String(_) => Some(item.clone()),
+                        Value::Bool(_) | Value::Number(_) => Some(Value::String(item.to_string())),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+            (SettingType::Object | SettingType::ArrayObject, Value::String(s)) => {
+                serde_json::from_str(s).ok()?
+            }
+            _ => return None,
+        };
+        Self::validate_value(key, &coerced).ok()?;
+        Some(coerced)
+    }
+}
+
+/// Parses a settings file, returning its values and the text to patch when
+/// saving. Unless `strict`, JSON5 syntax is accepted by rewriting it as
+/// JSONC, and that rewrite is what gets saved.
+fn parse_source(contents: &str, strict: bool) -> Result<(Map<String, Value>, String)> {
+    if strict {
+        return match jsonc::parse_strict(contents) {
+            Ok(values) => Ok((values, contents.to_string())),
+            Err(e) if parse_source(contents, false).is_ok() => {
+                Err(e.context("JSON5 syntax and trailing commas are rejected with --strict"))
+            }
+            Err(e) => Err(e),
+        };
+    }
+    match jsonc::parse(contents) {
+        Ok(values) => Ok((values, contents.to_string())),
+        Err(e) => {
+            let converted = jsonc::from_json5(contents)?;
+            let values = jsonc::parse(&converted).map_err(|_| e)?;
+            Ok((values, converted))
+        }
+    }
+}
+
+/// Returns whether a value has the JSON shape a setting type expects.
+pub fn type_matches(setting_type: SettingType, value: &Value) -> bool {
+    match setting_type {
+        SettingType::Boolean => value.is_boolean(),
+        SettingType::String | SettingType::StringEnum => value.is_string(),
+        SettingType::Number => value.is_number(),
+        SettingType::ArrayString => value
+            .as_array()
+            .is_some_and(|a| a.iter().all(Value::is_string)),
+        SettingType::ArrayObject => value
+            .as_array()
+            .is_some_and(|a| a.iter().all(Value::is_object)),
+        SettingType::Object => value.is_object(),
+    }
+}
+
+/// Describes a setting type in messages, e.g. "array of strings".
+pub fn type_name(setting_type: SettingType) -> &'static str {
+    match setting_type {
+        SettingType::Boolean => "boolean",
+        SettingType::String | SettingType::StringEnum => "string",
+        SettingType::Number => "number",
+        SettingType::ArrayString => "array of strings",
+        SettingType::ArrayObject => "array of objects",
+        SettingType::Object => "object",
+    }
+}
+
+/// Replaces a file's contents without ever leaving it half-written: the data is
+/// written to a temp file in the same directory, synced, and renamed over the
+/// target. The original file's permissions (and on Unix, ownership) are kept,
+/// and a symlinked file (e.g. into a dotfiles repository) stays a symlink: its
+/// target is what gets replaced.
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let resolved = fs::canonicalize(path).ok();
+    let path = resolved.as_deref().unwrap_or(path);
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("creating temp file in {}", dir.display()))?;
+    tmp.write_all(contents.as_bytes())
+        .with_context(|| format!("writing {}", tmp.path().display()))?;
+    tmp.as_file()
+        .sync_all()
+        .with_context(|| format!("syncing {}", tmp.path().display()))?;
+
+    if let Ok(meta) = fs::metadata(path) {
+        fs::set_permissions(tmp.path(), meta.permissions())
+            .with_context(|| format!("copying permissions of {}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            // Only root can give a file away; for everyone else this is a no-op
+            // or fails harmlessly when the owner is already us.
+            let _ = std::os::unix::fs::chown(tmp.path(), Some(meta.uid()), Some(meta.gid()));
+        }
+    }
+
+    tmp.persist(path)
+        .with_context(|| format!("replacing {}", path.display()))?;
+
+    #[cfg(unix)]
+    if let Ok(dir) = fs::File::open(dir) {
+        // Make the rename itself durable.
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Reads the size and modification time of a file, if it exists.
+fn read_disk_info(path: &Path) -> Option<DiskInfo> {
+    let meta = fs::metadata(path).ok()?;
+    Some(DiskInfo {
+        size: meta.len(),
+        modified: meta.modified().ok()?,
+    })
+}
+
+/// Collects `.json`/`.jsonc` files in `dir`, descending `depth` more levels into
+/// subdirectories. Hidden entries are skipped.
+fn collect_json_files(dir: &Path, depth: usize, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            if depth > 0 {
+                collect_json_files(&path, depth - 1, out);
+            }
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("json") | Some("jsonc")
+        ) {
+            out.push(path);
+        }
+    }
+}
+
+/// Builds the contents of a starter settings file: every known setting at its
+/// default value, commented out so that nothing is explicitly set yet.
+pub fn scaffold_contents() -> String {
+    let mut out = String::new();
+    out.push_str("// Amp settings, scaffolded by volt.\n");
+    out.push_str("// Every known setting is listed below at its default value.\n");
+    out.push_str("// Uncomment a line and change its value to override the default.\n");
+    out.push_str("{\n");
+    for def in settings::known_settings() {
+        let value = serde_json::to_string(&def.default).unwrap_or_else(|_| "null".to_string());
+        out.push_str(&format!("  // \"{}\": {},\n", def.key, value));
+    }
+    out.push_str("}\n");
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_env_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, r#"{"amp.showCosts": false}"#).unwrap();
+        let mut config = Config::load(&path).unwrap();
+        let vars = [
+            ("AMP_SHOW_COSTS", "1"),
+            ("AMP_TOOLS_STOP_TIMEOUT", "30"),
+            ("AMP_TOOLS_DISABLE", r#"["Bash"]"#),
+            ("AMP_UPDATES_MODE", "sometimes"),
+            ("AMP_NOTIFICATIONS_ENABLED", "yes"),
+            ("HOME", "/root"),
+        ];
+        config.set_env_overrides(vars.map(|(k, v)| (k.to_string(), v.to_string())));
+        let overridden = |config: &Config, key| config.env_override(key).map(|o| o.value.clone());
+        assert_eq!(
+            overridden(&config, "amp.showCosts"),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(
+            config.env_override("amp.showCosts").unwrap().var,
+            "AMP_SHOW_COSTS"
+        );
+        assert_eq!(
+            overridden(&config, "amp.tools.stopTimeout"),
+            Some(30.into())
+        );
+        assert_eq!(
+            overridden(&config, "amp.tools.disable"),
+            Some(serde_json::json!(["Bash"]))
+        );
+        // Values the file couldn't hold either are ignored.
+        assert_eq!(overridden(&config, "amp.updates.mode"), None);
+        assert_eq!(overridden(&config, "amp.notifications.enabled"), None);
+
+        // The file keeps its own value, and the override survives a reload.
+        assert_eq!(config.get("amp.showCosts"), Value::Bool(false));
+        config.set("amp.tools.stopTimeout", 60.into());
+        config.save().unwrap();
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            saved,
+            serde_json::json!({"amp.showCosts": false, "amp.tools.stopTimeout": 60})
+        );
+        config.reload().unwrap();
+        assert_eq!(
+            overridden(&config, "amp.showCosts"),
+            Some(Value::Bool(true))
+        );
+    }
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -187,7 +1033,7 @@ mod tests {
     "amp.showCosts": true,
     "amp.notifications.enabled": false,
     "amp.tools.stopTimeout": 600,
-    "amp.experimental.modes": ["bombadil"]
+    "amp.experimental.labs": ["bombadil"]
 }"#
     }
 
@@ -236,6 +1082,55 @@ mod tests {
         assert_eq!(config.get("amp.notifications.enabled"), Value::Bool(false));
     }
 
+    #[test]
+    fn test_load_trailing_commas() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"{{
+    "amp.tools.disable": ["Bash", "Read",],
+    "amp.notifications.enabled": false,
+}}"#
+        )
+        .unwrap();
+
+        let config = Config::load(f.path()).unwrap();
+        assert_eq!(config.get("amp.notifications.enabled"), Value::Bool(false));
+        assert_eq!(config.get("amp.tools.disable").as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_write_scaffold() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("amp").join("settings.json");
+        let mut config = Config::load(&path).unwrap();
+        assert!(!config.exists());
+
+        config.write_scaffold().unwrap();
+        assert!(config.exists());
+        // Everything is commented out, so nothing is explicitly set.
+        assert!(config.get_raw("amp.showCosts").is_none());
+        assert!(!config.is_dirty());
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("// \"amp.showCosts\": true,"));
+        assert!(contents.contains("// \"amp.tools.stopTimeout\": 300,"));
+    }
+
+    #[test]
+    fn test_scaffold_uncommented_line_parses() {
+        let contents = scaffold_contents().replacen(
+            "// \"amp.showCosts\": true",
+            "\"amp.showCosts\": false",
+            1,
+        );
+        let mut f = NamedTempFile::new().unwrap();
+        write!(f, "{contents}").unwrap();
+
+        let config = Config::load(f.path()).unwrap();
+        assert_eq!(config.get("amp.showCosts"), Value::Bool(false));
+    }
+
     #[test]
     fn test_load_invalid_json() {
         let mut f = NamedTempFile::new().unwrap();
@@ -275,10 +1170,16 @@ mod tests {
 
         let config = Config::load(f.path()).unwrap();
         let unknown = config.unknown_keys();
-        assert!(unknown.contains(&"amp.experimental.modes".to_string()));
+        assert!(unknown.contains(&"amp.experimental.labs".to_string()));
         assert!(!unknown.contains(&"amp.showCosts".to_string()));
     }
 
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(""), "cbf29ce484222325");
+        assert_ne!(hash("{}"), hash("{ }"));
+    }
+
     #[test]
     fn test_save_roundtrip() {
         let tmpfile = NamedTempFile::new().unwrap();
@@ -300,6 +1201,327 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_disk_info_refreshed_on_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut config = Config::load(&path).unwrap();
+        assert!(config.disk_info().is_none());
+        assert_eq!(config.explicit_count(), 0);
+
+        config.set("amp.showCosts", Value::Bool(false));
+        config.save().unwrap();
+        let info = config.disk_info().unwrap();
+        assert_eq!(info.size, fs::metadata(&path).unwrap().len());
+        assert_eq!(config.explicit_count(), 1);
+    }
+
+    #[test]
+    fn test_save_backs_up_previous_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, r#"{"amp.showCosts": true}"#).unwrap();
+
+        let mut config = Config::load(&path).unwrap();
+        config.set("amp.showCosts", Value::Bool(false));
+        config.save().unwrap();
+
+        let backups = backup::list(&path);
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            fs::read_to_string(&backups[0]).unwrap(),
+            r#"{"amp.showCosts": true}"#
+        );
+
+        config.set_backup_retention(0);
+        config.set("amp.showCosts", Value::Bool(true));
+        config.save().unwrap();
+        assert_eq!(backup::list(&path).len(), 1);
+    }
+
+    #[test]
+    fn test_save_leaves_no_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut config = Config::load(&path).unwrap();
+        config.set_backup_retention(0);
+        config.set("amp.showCosts", Value::Bool(false));
+        config.save().unwrap();
+        config.set("amp.showCosts", Value::Bool(true));
+        config.save().unwrap();
+
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec!["settings.json"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let mut config = Config::load(&path).unwrap();
+        config.set_backup_retention(0);
+        config.set("amp.showCosts", Value::Bool(false));
+        config.save().unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_through_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("dotfiles");
+        fs::create_dir(&repo).unwrap();
+        let target = repo.join("settings.json");
+        fs::write(&target, "{}").unwrap();
+        let link = dir.path().join("settings.json");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let mut config = Config::load(&link).unwrap();
+        config.set_backup_retention(0);
+        config.set("amp.showCosts", Value::Bool(false));
+        config.save().unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        let saved = fs::read_to_string(&target).unwrap();
+        assert!(saved.contains("\"amp.showCosts\": false"), "{saved}");
+        let names: Vec<_> = fs::read_dir(&repo)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec!["settings.json"]);
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let backup = dir.path().join("old.json");
+        fs::write(&path, r#"{"amp.showCosts": false}"#).unwrap();
+        fs::write(&backup, "// old\n{\"amp.tools.stopTimeout\": 60}").unwrap();
+
+        let mut config = Config::load(&path).unwrap();
+        config.restore_backup(&backup).unwrap();
+        assert!(config.is_dirty());
+        assert!(config.get_raw("amp.showCosts").is_none());
+        assert_eq!(
+            config.get("amp.tools.stopTimeout"),
+            Value::Number(60.into())
+        );
+
+        config.save().unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("// old"));
+    }
+
+    #[test]
+    fn test_save_preserves_comments_and_order() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"{{
+    // Turned off for screen recordings.
+    "amp.showCosts": false,
+    "amp.anthropic.thinking.enabled": true, // keep
+    "amp.tools.stopTimeout": 600,
+}}
+"#
+        )
+        .unwrap();
+
+        let mut config = Config::load(f.path()).unwrap();
+        // Don't leave backups behind in the shared temp dir.
+        config.set_backup_retention(0);
+        config.set("amp.tools.stopTimeout", Value::Number(900.into()));
+        config.remove("amp.anthropic.thinking.enabled");
+        config.set("amp.notifications.enabled", Value::Bool(false));
+        config.save().unwrap();
+
+        let contents = fs::read_to_string(f.path()).unwrap();
+        assert_eq!(
+            contents,
+            r#"{
+    // Turned off for screen recordings.
+    "amp.showCosts": false,
+    "amp.tools.stopTimeout": 900,
+    "amp.notifications.enabled": false,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_coerce_value() {
+        use serde_json::json;
+        let coerce = |key: &str, value: serde_json::Value| Config::coerce_value(key, &value);
+        assert_eq!(coerce("amp.showCosts", json!(" TRUE ")), Some(json!(true)));
+        assert_eq!(coerce("amp.showCosts", json!(0)), Some(json!(false)));
+        assert_eq!(coerce("amp.showCosts", json!("maybe")), None);
+        assert_eq!(
+            coerce("amp.tools.stopTimeout", json!("30")),
+            Some(json!(30))
+        );
+        assert_eq!(
+            coerce("amp.tools.stopTimeout", json!("1.5")),
+            Some(json!(1.5))
+        );
+        assert_eq!(coerce("amp.tools.stopTimeout", json!("soon")), None);
+        assert_eq!(
+            coerce("amp.tools.disable", json!("Bash")),
+            Some(json!(["Bash"]))
+        );
+        assert_eq!(
+            coerce("amp.tools.disable", json!("[\"a\", \"b\"]")),
+            Some(json!(["a", "b"]))
+        );
+        assert_eq!(
+            coerce("amp.tools.disable", json!([1, "x"])),
+            Some(json!(["1", "x"]))
+        );
+        assert_eq!(
+            coerce("amp.mcpServers", json!("{\"a\": {}}")),
+            Some(json!({"a": {}}))
+        );
+        assert_eq!(coerce("amp.mystery", json!("true")), None);
+    }
+
+    #[test]
+    fn test_type_mismatches() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"{{"amp.showCosts": "true", "amp.tools.stopTimeout": 5}}"#
+        )
+        .unwrap();
+        let config = Config::load(f.path()).unwrap();
+        assert_eq!(config.type_mismatches(), vec!["amp.showCosts"]);
+    }
+
+    #[test]
+    fn test_load_json5_and_normalize_on_save() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            "{{\n  // costs\n  amp.showCosts: false,\n  'amp.terminal.theme': 'nord',\n}}\n"
+        )
+        .unwrap();
+        assert!(Config::load_with(f.path(), true)
+            .unwrap_err()
+            .chain()
+            .any(|e| e.to_string().contains("--strict")));
+
+        let mut config = Config::load(f.path()).unwrap();
+        assert!(config.is_normalized());
+        assert!(!config.is_dirty());
+        assert_eq!(config.get("amp.showCosts"), Value::Bool(false));
+        config.save().unwrap();
+        assert!(!config.is_normalized());
+        let saved = fs::read_to_string(f.path()).unwrap();
+        assert_eq!(
+            saved,
+            "{\n  // costs\n  \"amp.showCosts\": false,\n  \"amp.terminal.theme\": \"nord\"\n}\n"
+        );
+        assert!(Config::load_with(f.path(), true).is_ok());
+    }
+
+    #[test]
+    fn test_plain_jsonc_is_not_normalized() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(f, "{{\"amp.showCosts\": false,}}").unwrap();
+        assert!(!Config::load(f.path()).unwrap().is_normalized());
+        assert!(Config::load_with(f.path(), true).is_err());
+    }
+
+    #[test]
+    fn test_read_only_refuses_to_save() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(f, r#"{{"amp.showCosts": true}}"#).unwrap();
+        let mut config = Config::load(f.path()).unwrap();
+        config.set_read_only(true);
+        config.set("amp.showCosts", Value::Bool(false));
+        assert!(config
+            .save()
+            .unwrap_err()
+            .to_string()
+            .ends_with("is open read-only"));
+        config.reload().unwrap();
+        assert!(config.is_read_only());
+        assert_eq!(
+            fs::read_to_string(f.path()).unwrap(),
+            r#"{"amp.showCosts": true}"#
+        );
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"{{
+    "amp.hideCosts": true,
+    "amp.thinking.enabled": true,
+    "amp.tools.disabled": ["Bash"],
+    "amp.tools.disable": ["Read"],
+    "amp.todos.enabled": false,
+    "amp.notifications.system.enabled": 1
+}}"#
+        )
+        .unwrap();
+        let mut config = Config::load(f.path()).unwrap();
+        assert_eq!(config.deprecated_keys().len(), 5);
+
+        let steps = config.migrate();
+        let notes: Vec<(&str, &str, bool)> = steps
+            .iter()
+            .map(|s| (s.old.as_str(), s.note.as_str(), s.applied))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                (
+                    "amp.hideCosts",
+                    "replaced by amp.showCosts (inverted)",
+                    true
+                ),
+                (
+                    "amp.thinking.enabled",
+                    "renamed to amp.anthropic.thinking.enabled",
+                    true
+                ),
+                (
+                    "amp.tools.disabled",
+                    "removed, amp.tools.disable is already set",
+                    true
+                ),
+                ("amp.todos.enabled", "removed, no longer used", true),
+                (
+                    "amp.notifications.system.enabled",
+                    "kept: expected boolean for key 'amp.notifications.enabled'",
+                    false
+                ),
+            ]
+        );
+        assert_eq!(config.get_raw("amp.showCosts"), Some(&Value::Bool(false)));
+        assert_eq!(
+            config.get_raw("amp.anthropic.thinking.enabled"),
+            Some(&Value::Bool(true))
+        );
+        assert_eq!(
+            config.deprecated_keys(),
+            vec!["amp.notifications.system.enabled"]
+        );
+        assert!(config.is_dirty());
+    }
+
     #[test]
     fn test_validate_boolean() {
         assert!(Config::validate_value("amp.showCosts", &Value::Bool(true)).is_ok());
@@ -312,6 +1534,12 @@ mod tests {
             Config::validate_value("amp.tools.stopTimeout", &Value::Number(100.into())).is_ok()
         );
         assert!(Config::validate_value("amp.tools.stopTimeout", &Value::Bool(true)).is_err());
+        let err =
+            Config::validate_value("amp.tools.stopTimeout", &Value::Number(0.into())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'amp.tools.stopTimeout' must be at least 1"
+        );
     }
 
     #[test]
@@ -337,9 +1565,150 @@ mod tests {
     }
 
     #[test]
-    fn test_default_path() {
-        let path = Config::default_path().unwrap();
-        assert!(path.ends_with(".config/amp/settings.json"));
+    fn test_locate() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home");
+        let platform = root.path().join("Library");
+        let xdg = root.path().join("xdg");
+        let no_env = |_: &str| None;
+        let locate = |env: &dyn Fn(&str) -> Option<std::ffi::OsString>| {
+            locate_in(env, Some(&home), Some(&platform), false).unwrap()
+        };
+
+        // Nothing exists yet: a new file goes under ~/.config.
+        assert_eq!(
+            locate(&no_env),
+            (home.join(".config/amp/settings.json"), PathSource::Home)
+        );
+
+        // An existing file in the platform directory is found, JSONC too.
+        fs::create_dir_all(platform.join("amp")).unwrap();
+        fs::write(platform.join("amp/settings.jsonc"), "{}").unwrap();
+        assert_eq!(
+            locate(&no_env),
+            (
+                platform.join("amp/settings.jsonc"),
+                PathSource::PlatformConfigDir
+            )
+        );
+
+        let with_xdg = |name: &str| (name == "XDG_CONFIG_HOME").then(|| xdg.clone().into());
+        assert_eq!(locate(&with_xdg).1, PathSource::PlatformConfigDir);
+        fs::create_dir_all(xdg.join("amp")).unwrap();
+        fs::write(xdg.join("amp/settings.json"), "{}").unwrap();
+        assert_eq!(
+            locate(&with_xdg),
+            (xdg.join("amp/settings.json"), PathSource::XdgConfigHome)
+        );
+        let relative_xdg = |name: &str| (name == "XDG_CONFIG_HOME").then(|| "xdg".into());
+        assert_eq!(locate(&relative_xdg).1, PathSource::PlatformConfigDir);
+
+        let explicit = |name: &str| {
+            (name == "AMP_SETTINGS_PATH" || name == "XDG_CONFIG_HOME")
+                .then(|| "/etc/amp.json".into())
+        };
+        assert_eq!(
+            locate(&explicit),
+            (PathBuf::from("/etc/amp.json"), PathSource::EnvVar)
+        );
+
+        assert!(locate_in(no_env, None, None, false).is_err());
+    }
+
+    #[test]
+    fn test_locate_platform_dir_first() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home");
+        let appdata = root.path().join("AppData").join("Roaming");
+        let locate = || locate_in(|_| None, Some(&home), Some(&appdata), true).unwrap();
+        assert_eq!(
+            locate(),
+            (
+                appdata.join("amp").join("settings.json"),
+                PathSource::PlatformConfigDir
+            )
+        );
+        // A file already under ~/.config is still found.
+        fs::create_dir_all(home.join(".config").join("amp")).unwrap();
+        fs::write(home.join(".config").join("amp").join("settings.json"), "{}").unwrap();
+        assert_eq!(locate().1, PathSource::Home);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_defaults_to_appdata() {
+        const { assert!(PLATFORM_DIR_FIRST) };
+        assert_eq!(PathSource::PlatformConfigDir.label(), "%APPDATA%");
+        let appdata = dirs::config_dir().unwrap();
+        assert!(appdata.ends_with("AppData\\Roaming"));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_macos_defaults_to_dot_config() {
+        const { assert!(!PLATFORM_DIR_FIRST) };
+        assert_eq!(
+            PathSource::PlatformConfigDir.label(),
+            "~/Library/Application Support"
+        );
+        let support = dirs::config_dir().unwrap();
+        assert!(support.ends_with("Library/Application Support"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_defaults_to_dot_config() {
+        const { assert!(!PLATFORM_DIR_FIRST) };
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path();
+        // dirs::config_dir() is ~/.config itself unless XDG_CONFIG_HOME moves it.
+        let config_dir = home.join(".config");
+        let (path, source) = locate_in(|_| None, Some(home), Some(&config_dir), false).unwrap();
+        assert_eq!(path, home.join(".config/amp/settings.json"));
+        assert_eq!(source, PathSource::Home);
+    }
+
+    #[test]
+    fn test_custom_themes() {
+        let dir = tempfile::tempdir().unwrap();
+        let themes = dir.path().join("themes");
+        fs::create_dir_all(themes.join("rose-pine")).unwrap();
+        fs::write(themes.join("everforest.json"), "{}").unwrap();
+        fs::write(themes.join("nord.json"), "{}").unwrap();
+        fs::write(themes.join(".DS_Store"), "").unwrap();
+
+        let config = Config::load(&dir.path().join("settings.json")).unwrap();
+        assert_eq!(config.custom_themes(), vec!["everforest", "rose-pine"]);
+    }
+
+    #[test]
+    fn test_custom_themes_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load(&dir.path().join("settings.json")).unwrap();
+        assert!(config.custom_themes().is_empty());
+    }
+
+    #[test]
+    fn test_sibling_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("keybindings.json"), "{}").unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+        fs::create_dir_all(dir.path().join("agents")).unwrap();
+        fs::write(dir.path().join("agents").join("review.jsonc"), "{}").unwrap();
+        fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+        fs::write(dir.path().join("a").join("b").join("deep.json"), "{}").unwrap();
+
+        let settings = dir.path().join("settings.json");
+        let config = Config::load(&settings).unwrap();
+        let files = config.sibling_files();
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("agents").join("review.jsonc"),
+                dir.path().join("keybindings.json"),
+                settings,
+            ]
+        );
     }
 
     #[test]
@@ -351,16 +1720,204 @@ mod tests {
         let mut config = Config::load(&path).unwrap();
         config.set("amp.showCosts", Value::Bool(false));
         config.set(
-            "amp.experimental.modes",
+            "amp.experimental.labs",
             Value::Array(vec![Value::String("test".into())]),
         );
         config.save().unwrap();
 
         let reloaded = Config::load(&path).unwrap();
         assert_eq!(
-            reloaded.get("amp.experimental.modes"),
+            reloaded.get("amp.experimental.labs"),
             Value::Array(vec![Value::String("test".into())])
         );
         assert_eq!(reloaded.get("amp.showCosts"), Value::Bool(false));
     }
+
+    #[test]
+    fn test_save_keeps_key_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(
+            &path,
+            r#"{
+  "amp.showCosts": true,
+  "amp.mcpServers": {
+    "zeta": {"command": "z", "args": []},
+    "alpha": {"url": "https://a"}
+  }
+}
+"#,
+        )
+        .unwrap();
+        let mut config = Config::load(&path).unwrap();
+        let mut servers = config.get("amp.mcpServers");
+        servers["zeta"]["args"] = serde_json::json!(["-y"]);
+        config.set("amp.mcpServers", servers);
+        config.set("amp.bitbucketToken", Value::String("t".into()));
+        config.set("amp.anthropic.thinking.enabled", Value::Bool(false));
+        config.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            r#"{
+  "amp.showCosts": true,
+  "amp.mcpServers": {
+    "zeta": {
+      "command": "z",
+      "args": [
+        "-y"
+      ]
+    },
+    "alpha": {
+      "url": "https://a"
+    }
+  },
+  "amp.bitbucketToken": "t",
+  "amp.anthropic.thinking.enabled": false
+}
+"#
+        );
+        let reloaded = Config::load(&path).unwrap();
+        let keys: Vec<&String> = reloaded.entries().map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            [
+                "amp.showCosts",
+                "amp.mcpServers",
+                "amp.bitbucketToken",
+                "amp.anthropic.thinking.enabled"
+            ]
+        );
+
+        // A new file lists keys in the order they were set.
+        let mut fresh = Config::load(&dir.path().join("new.json")).unwrap();
+        fresh.set("amp.showCosts", Value::Bool(false));
+        fresh.set("amp.bitbucketToken", Value::String("t".into()));
+        assert_eq!(
+            fresh.render().unwrap(),
+            "{\n  \"amp.showCosts\": false,\n  \"amp.bitbucketToken\": \"t\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_save_with_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(
+            &path,
+            "{\n    // costs\n    \"amp.showCosts\": true,\n    \"amp.bitbucketToken\": \"t\"\n}\n",
+        )
+        .unwrap();
+
+        // By default the file's four-space indent is kept.
+        let mut config = Config::load(&path).unwrap();
+        config.set("amp.tools.disable", serde_json::json!(["Bash"]));
+        assert_eq!(
+            config.render().unwrap(),
+            "{\n    // costs\n    \"amp.showCosts\": true,\n    \"amp.bitbucketToken\": \"t\",\n    \"amp.tools.disable\": [\n        \"Bash\"\n    ]\n}\n"
+        );
+
+        config.set_format(Format {
+            indent: Some("\t".into()),
+            sort_keys: true,
+            trailing_newline: Some(false),
+        });
+        config.save().unwrap();
+        config.reload().unwrap();
+        assert_eq!(config.format().indent.as_deref(), Some("\t"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\n\t\"amp.bitbucketToken\": \"t\",\n\t// costs\n\t\"amp.showCosts\": true,\n\t\"amp.tools.disable\": [\n\t\t\"Bash\"\n\t]\n}"
+        );
+
+        let mut fresh = Config::load(&dir.path().join("new.json")).unwrap();
+        fresh.set_format(Format {
+            indent: Some("    ".into()),
+            ..Format::default()
+        });
+        fresh.set("amp.showCosts", Value::Bool(false));
+        assert_eq!(
+            fresh.render().unwrap(),
+            "{\n    \"amp.showCosts\": false\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_from_disk_keeps_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, "{\n  \"amp.showCosts\": true\n}\n").unwrap();
+        let mut config = Config::load(&path).unwrap();
+        config.set_format(Format {
+            indent: Some("\t".into()),
+            sort_keys: true,
+            trailing_newline: Some(false),
+        });
+        config.set("amp.bitbucketToken", Value::String("t".into()));
+
+        fs::write(&path, "{\n  \"amp.showCosts\": false\n}\n").unwrap();
+        config.merge_from_disk().unwrap();
+        assert_eq!(
+            config.render().unwrap(),
+            "{\n\t\"amp.bitbucketToken\": \"t\",\n\t\"amp.showCosts\": false\n}"
+        );
+    }
+
+    #[test]
+    fn test_changed_on_disk_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, r#"{"amp.showCosts": true}"#).unwrap();
+        let mut config = Config::load(&path).unwrap();
+        assert!(!config.changed_on_disk());
+
+        fs::write(&path, r#"{"amp.showCosts": false, "amp.x": 1}"#).unwrap();
+        assert!(config.changed_on_disk());
+
+        config.set("amp.showCosts", Value::Bool(true));
+        config.reload().unwrap();
+        assert!(!config.changed_on_disk());
+        assert!(!config.is_dirty());
+        assert_eq!(config.get("amp.showCosts"), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_merge_from_disk_keeps_local_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, r#"{"amp.a": 1, "amp.b": 1, "amp.c": 1}"#).unwrap();
+        let mut config = Config::load(&path).unwrap();
+        config.set("amp.a", Value::from(2));
+        config.set("amp.b", Value::from(2));
+        config.remove("amp.c");
+
+        fs::write(&path, r#"{"amp.a": 3, "amp.b": 1, "amp.c": 1, "amp.d": 3}"#).unwrap();
+        let conflicts = config.merge_from_disk().unwrap();
+        assert_eq!(
+            conflicts,
+            vec![Conflict {
+                key: "amp.a".to_string(),
+                base: Some(Value::from(1)),
+                theirs: Some(Value::from(3)),
+                mine: Some(Value::from(2)),
+            }]
+        );
+        assert!(config.is_dirty());
+        assert!(!config.changed_on_disk());
+        assert_eq!(config.get("amp.a"), Value::from(2));
+        assert_eq!(config.get("amp.b"), Value::from(2));
+        assert_eq!(config.get_raw("amp.c"), None);
+        assert_eq!(config.get("amp.d"), Value::from(3));
+    }
+
+    #[test]
+    fn test_keep_local_stops_reporting_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut config = Config::load(&path).unwrap();
+        fs::write(&path, "{}").unwrap();
+        assert!(config.changed_on_disk());
+        config.keep_local();
+        assert!(!config.changed_on_disk());
+        assert!(config.is_dirty());
+    }
 }
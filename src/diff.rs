@@ -0,0 +1,151 @@
+//! Line-based diffs between the file on disk and what a save would write.
+
+/// How a line differs between the old and new text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Same,
+    Added,
+    Removed,
+    /// Stands in for unchanged lines left out between hunks.
+    Gap,
+}
+
+/// One line of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+impl DiffLine {
+    fn new(kind: DiffKind, text: &str) -> Self {
+        Self {
+            kind,
+            text: text.to_string(),
+        }
+    }
+}
+
+/// Diffs two texts line by line using a longest-common-subsequence table.
+/// Settings files are small, so the quadratic table is fine.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] is the LCS length of old[i..] and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            out.push(DiffLine::new(DiffKind::Same, old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            out.push(DiffLine::new(DiffKind::Removed, old[i]));
+            i += 1;
+        } else {
+            out.push(DiffLine::new(DiffKind::Added, new[j]));
+            j += 1;
+        }
+    }
+    out.extend(old[i..].iter().map(|l| DiffLine::new(DiffKind::Removed, l)));
+    out.extend(new[j..].iter().map(|l| DiffLine::new(DiffKind::Added, l)));
+    out
+}
+
+/// Keeps only changed lines and `context` unchanged lines around them,
+/// replacing each run of omitted lines with a single gap marker.
+pub fn hunks(lines: &[DiffLine], context: usize) -> Vec<DiffLine> {
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.kind != DiffKind::Same)
+        .map(|(i, _)| i)
+        .collect();
+    let near_change = |i: usize| {
+        changed
+            .iter()
+            .any(|&c| i + context >= c && i <= c + context)
+    };
+
+    let mut out = Vec::new();
+    let mut skipped = false;
+    for (i, line) in lines.iter().enumerate() {
+        if near_change(i) {
+            if skipped {
+                out.push(DiffLine::new(DiffKind::Gap, "…"));
+                skipped = false;
+            }
+            out.push(line.clone());
+        } else {
+            skipped = true;
+        }
+    }
+    if skipped && !out.is_empty() {
+        out.push(DiffLine::new(DiffKind::Gap, "…"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(lines: &[DiffLine]) -> Vec<DiffKind> {
+        lines.iter().map(|l| l.kind).collect()
+    }
+
+    #[test]
+    fn test_identical() {
+        let lines = diff_lines("a\nb\n", "a\nb\n");
+        assert!(lines.iter().all(|l| l.kind == DiffKind::Same));
+        assert!(hunks(&lines, 2).is_empty());
+    }
+
+    #[test]
+    fn test_change_in_middle() {
+        let lines = diff_lines(
+            "{\n  \"a\": 1,\n  \"b\": 2\n}",
+            "{\n  \"a\": 5,\n  \"b\": 2\n}",
+        );
+        assert_eq!(
+            kinds(&lines),
+            vec![
+                DiffKind::Same,
+                DiffKind::Removed,
+                DiffKind::Added,
+                DiffKind::Same,
+                DiffKind::Same
+            ]
+        );
+        assert_eq!(lines[1].text, "  \"a\": 1,");
+        assert_eq!(lines[2].text, "  \"a\": 5,");
+    }
+
+    #[test]
+    fn test_from_empty() {
+        let lines = diff_lines("", "{\n}");
+        assert_eq!(kinds(&lines), vec![DiffKind::Added, DiffKind::Added]);
+    }
+
+    #[test]
+    fn test_hunks_insert_gaps() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9";
+        let new = "1\nX\n3\n4\n5\n6\n7\n8\nY";
+        let out = hunks(&diff_lines(old, new), 1);
+        let texts: Vec<&str> = out.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["1", "2", "X", "3", "…", "8", "9", "Y"]);
+        assert_eq!(out[4].kind, DiffKind::Gap);
+    }
+}
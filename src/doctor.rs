@@ -0,0 +1,333 @@
+//! Environment checks for `volt doctor`: whether the settings file parses
+//! and validates, and whether the programs and paths it refers to exist.
+
+use std::env;
+use std::path::Path;
+
+use crate::amp;
+use crate::config::{self, Config};
+use crate::editor;
+use crate::lint::{self, Severity};
+use crate::prefs::Prefs;
+
+/// The outcome of one check. Only failures make the doctor exit nonzero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+}
+
+/// One line of the report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub status: Status,
+    /// What was checked, such as `mcp github` or `$EDITOR`.
+    pub name: String,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(status: Status, name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            name: name.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every check against the settings file at `path`. A file that
+/// doesn't parse fails the first check and skips the ones that need it.
+pub fn diagnose(path: &Path, strict: bool) -> Vec<Check> {
+    let mut checks = Vec::new();
+    match Config::load_with(path, strict) {
+        Ok(config) => {
+            checks.push(if config.exists() {
+                Check::new(
+                    Status::Pass,
+                    "settings file",
+                    format!("{} parses", path.display()),
+                )
+            } else {
+                Check::new(
+                    Status::Warn,
+                    "settings file",
+                    format!("{} doesn't exist; Amp uses its defaults", path.display()),
+                )
+            });
+            checks.extend(check_values(&config));
+            checks.extend(check_mcp_commands(&config));
+            checks.extend(check_skills_path(&config));
+            if config.exists() {
+                checks.push(check_with_amp(amp::find_amp().as_deref(), path));
+            }
+        }
+        Err(e) => checks.push(Check::new(Status::Fail, "settings file", format!("{e:#}"))),
+    }
+    // A preferences file that doesn't load is reported by the TUI, not here.
+    let configured = Prefs::default_path()
+        .and_then(|path| Prefs::load(&path))
+        .ok()
+        .and_then(|prefs| prefs.editor);
+    let (editor, source) = editor::choose(configured.as_deref(), |name| env::var(name).ok());
+    checks.push(check_editor(&editor, source));
+    checks
+}
+
+/// Turns `volt validate`'s findings into checks, or one passing check when
+/// there are none.
+fn check_values(config: &Config) -> Vec<Check> {
+    let findings = lint::lint(config);
+    if findings.is_empty() {
+        return vec![Check::new(
+            Status::Pass,
+            "settings values",
+            format!("{} setting(s) are valid", config.explicit_count()),
+        )];
+    }
+    findings
+        .into_iter()
+        .map(|f| {
+            let status = match f.severity {
+                Severity::Error => Status::Fail,
+                Severity::Warning => Status::Warn,
+            };
+            Check::new(status, f.path, f.message)
+        })
+        .collect()
+}
+
+/// Checks that every stdio MCP server's command can be found. Remote
+/// servers aren't contacted; `t` in the TUI tests those.
+fn check_mcp_commands(config: &Config) -> Vec<Check> {
+    let Some(servers) = config.get("amp.mcpServers").as_object().cloned() else {
+        return Vec::new();
+    };
+    servers
+        .iter()
+        .filter_map(|(name, server)| {
+            let command = server.get("command")?.as_str()?;
+            let name = format!("mcp {name}");
+            Some(match amp::find_on_path(command) {
+                Some(found) => Check::new(Status::Pass, name, found.display().to_string()),
+                None => Check::new(Status::Fail, name, format!("`{command}` is not on PATH")),
+            })
+        })
+        .collect()
+}
+
+/// Checks that `amp.skills.path`, when set, is a directory.
+fn check_skills_path(config: &Config) -> Option<Check> {
+    let path = config.get("amp.skills.path");
+    let path = path.as_str().filter(|p| !p.is_empty())?;
+    let resolved = config::expand_home(path);
+    Some(if resolved.is_dir() {
+        Check::new(
+            Status::Pass,
+            "amp.skills.path",
+            resolved.display().to_string(),
+        )
+    } else if resolved.exists() {
+        Check::new(
+            Status::Fail,
+            "amp.skills.path",
+            format!("{path} is not a directory"),
+        )
+    } else {
+        Check::new(
+            Status::Fail,
+            "amp.skills.path",
+            format!("{path} doesn't exist"),
+        )
+    })
+}
+
+/// Has amp itself load the file, as `Action::CheckWithAmp` does in the TUI.
+/// Amp missing, or too old to check a file, is a warning; amp rejecting it
+/// is a failure.
+fn check_with_amp(binary: Option<&Path>, path: &Path) -> Check {
+    let Some(binary) = binary else {
+        return Check::new(Status::Warn, "amp", "amp is not installed or not on PATH");
+    };
+    match amp::check_settings(binary, path) {
+        Ok(Some(check)) if !check.ok => {
+            let complaint = if check.messages.is_empty() {
+                "amp rejects the file".to_string()
+            } else {
+                check.messages.join("; ")
+            };
+            Check::new(Status::Fail, "amp", complaint)
+        }
+        Ok(Some(check)) if !check.messages.is_empty() => {
+            Check::new(Status::Warn, "amp", check.messages.join("; "))
+        }
+        Ok(Some(_)) => Check::new(Status::Pass, "amp", "amp loads the file"),
+        Ok(None) => Check::new(
+            Status::Warn,
+            "amp",
+            format!("{} can't check a settings file", binary.display()),
+        ),
+        Err(e) => Check::new(Status::Warn, "amp", format!("{e:#}")),
+    }
+}
+
+/// Checks that the editor volt opens values in, chosen from `source`, can
+/// be found. Falling back to the OS default is a warning.
+fn check_editor(editor: &str, source: &str) -> Check {
+    let program = match shell_words::split(editor) {
+        Ok(words) if !words.is_empty() => words[0].clone(),
+        _ => {
+            return Check::new(
+                Status::Fail,
+                "$EDITOR",
+                format!("`{editor}` (from {source}) isn't a valid command line"),
+            )
+        }
+    };
+    match amp::find_on_path(&program) {
+        Some(_) if source == "the default" => Check::new(
+            Status::Warn,
+            "$EDITOR",
+            format!("$VISUAL and $EDITOR aren't set; values open in {editor}"),
+        ),
+        Some(found) => Check::new(
+            Status::Pass,
+            "$EDITOR",
+            format!("{} (from {source})", found.display()),
+        ),
+        None => Check::new(
+            Status::Fail,
+            "$EDITOR",
+            format!("`{program}` (from {source}) is not on PATH"),
+        ),
+    }
+}
+
+/// Whether any check failed.
+pub fn failed(checks: &[Check]) -> bool {
+    checks.iter().any(|c| c.status == Status::Fail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn config_with(contents: &Value) -> (tempfile::TempDir, Config) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, contents.to_string()).unwrap();
+        let config = Config::load(&path).unwrap();
+        (dir, config)
+    }
+
+    #[test]
+    fn test_unparsable_file_fails_and_skips_config_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, "{ nope").unwrap();
+        let checks = diagnose(&path, false);
+        assert_eq!(checks[0].status, Status::Fail);
+        assert_eq!(checks[0].name, "settings file");
+        assert_eq!(checks.len(), 2);
+        assert_eq!(checks[1].name, "$EDITOR");
+        assert!(failed(&checks));
+    }
+
+    #[test]
+    fn test_missing_file_warns() {
+        let dir = tempfile::tempdir().unwrap();
+        let checks = diagnose(&dir.path().join("settings.json"), false);
+        assert_eq!(checks[0].status, Status::Warn);
+        assert_eq!(checks[1].status, Status::Pass);
+    }
+
+    #[test]
+    fn test_values_follow_lint_severity() {
+        let (_dir, config) = config_with(&json!({"amp.showCosts": "yes", "amp.bogus": 1}));
+        let checks = check_values(&config);
+        assert!(checks
+            .iter()
+            .any(|c| c.name == "amp.showCosts" && c.status == Status::Fail));
+        assert!(checks
+            .iter()
+            .any(|c| c.name == "amp.bogus" && c.status == Status::Warn));
+    }
+
+    #[test]
+    fn test_mcp_commands_and_skills_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_cfg, config) = config_with(&json!({
+            "amp.mcpServers": {
+                "shell": {"command": "sh"},
+                "gone": {"command": "volt-doctor-no-such-command"},
+                "remote": {"url": "https://example.com/mcp"}
+            },
+            "amp.skills.path": dir.path().join("missing").display().to_string()
+        }));
+        let mcp = check_mcp_commands(&config);
+        assert_eq!(mcp.len(), 2);
+        let status = |name: &str| mcp.iter().find(|c| c.name == name).unwrap().status;
+        assert_eq!(status("mcp shell"), Status::Pass);
+        assert_eq!(status("mcp gone"), Status::Fail);
+
+        let skills = check_skills_path(&config).unwrap();
+        assert_eq!(skills.status, Status::Fail);
+        assert!(skills.detail.ends_with("doesn't exist"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_with_amp() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        assert_eq!(check_with_amp(None, &settings).status, Status::Warn);
+
+        let old = amp::fake_amp(dir.path(), "echo 'usage: amp'");
+        assert_eq!(check_with_amp(Some(&old), &settings).status, Status::Warn);
+
+        let clean = amp::fake_amp(
+            dir.path(),
+            r#"[ "$1" = "--help" ] && echo "  --settings-file <path>"; exit 0"#,
+        );
+        assert_eq!(check_with_amp(Some(&clean), &settings).status, Status::Pass);
+
+        let rejects = amp::fake_amp(
+            dir.path(),
+            r#"[ "$1" = "--help" ] && { echo "--settings-file"; exit 0; }
+echo "error: amp.permissions[0] is invalid" >&2
+exit 1"#,
+        );
+        let check = check_with_amp(Some(&rejects), &settings);
+        assert_eq!(check.status, Status::Fail);
+        assert_eq!(check.detail, "error: amp.permissions[0] is invalid");
+    }
+
+    #[test]
+    fn test_editor() {
+        assert_eq!(check_editor("sh", "the default").status, Status::Warn);
+        let pass = check_editor("sh -c 'true' {file}", "$VISUAL");
+        assert_eq!(pass.status, Status::Pass);
+        assert!(
+            pass.detail.ends_with("sh (from $VISUAL)"),
+            "{}",
+            pass.detail
+        );
+        assert_eq!(
+            check_editor("volt-doctor-no-such-editor --wait", "$EDITOR").status,
+            Status::Fail
+        );
+        assert_eq!(check_editor("sh 'x", "$EDITOR").status, Status::Fail);
+    }
+}
@@ -1,41 +1,139 @@
 //! External editor support for editing JSON values via `$EDITOR`.
+//!
+//! The editor is volt's `editor` preference if set, then `$VISUAL`, then
+//! `$EDITOR`, then a default for the OS. Each is a command line split the
+//! way a shell would, so `code --wait` works; a `{file}` argument is
+//! replaced by the file's path, which is appended otherwise.
 
 use std::env;
+use std::ffi::OsString;
 use std::fs;
+use std::path::Path;
 use std::process::Command;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde_json::Value;
+use tempfile::NamedTempFile;
 
-/// Opens a JSON value in the user's `$EDITOR`, waits for save & quit,
-/// then reads back and parses the result.
-pub fn edit_value_in_editor(value: &Value) -> Result<Value> {
-    let editor = env::var("EDITOR")
-        .or_else(|_| env::var("VISUAL"))
-        .unwrap_or_else(|_| "vi".to_string());
+use crate::amp;
 
-    let json = serde_json::to_string_pretty(value).context("serializing value for editor")?;
+/// A value being edited in a temp file. The file lives as long as the
+/// session, so an edit that doesn't parse can be reopened instead of lost.
+#[derive(Debug)]
+pub struct EditSession {
+    file: NamedTempFile,
+}
+
+/// What the user left in the temp file.
+#[derive(Debug)]
+pub enum EditOutcome {
+    Edited(Value),
+    /// The file isn't valid JSON; holds the parse error.
+    Invalid(String),
+}
 
-    let tmp = tempfile::Builder::new()
-        .suffix(".json")
-        .tempfile()
-        .context("creating temp file")?;
+impl EditSession {
+    /// Writes a JSON value to a new temp file.
+    pub fn new(value: &Value) -> Result<Self> {
+        let json = serde_json::to_string_pretty(value).context("serializing value for editor")?;
+        let file = tempfile::Builder::new()
+            .suffix(".json")
+            .tempfile()
+            .context("creating temp file")?;
+        fs::write(file.path(), &json).context("writing temp file")?;
+        Ok(Self { file })
+    }
+
+    /// Opens the temp file in the user's editor (see [`choose`]), waits for
+    /// save & quit, then reads back and parses the result.
+    pub fn edit(&self, configured: Option<&str>) -> Result<EditOutcome> {
+        open(self.file.path(), configured)?;
+        let edited = fs::read_to_string(self.file.path()).context("reading edited file")?;
+        Ok(match serde_json::from_str(&edited) {
+            Ok(value) => EditOutcome::Edited(value),
+            Err(e) => EditOutcome::Invalid(e.to_string()),
+        })
+    }
+}
 
-    fs::write(tmp.path(), &json).context("writing temp file")?;
+/// Opens a file in the user's editor and waits for it to exit.
+/// `configured` is the `editor` preference.
+pub fn open(path: &Path, configured: Option<&str>) -> Result<()> {
+    let (editor, _) = choose(configured, |name| env::var(name).ok());
+    let mut words = command_line(&editor, path)?.into_iter();
+    let program = words.next().context("the editor command is empty")?;
 
-    let status = Command::new(&editor)
-        .arg(tmp.path())
+    let status = Command::new(&program)
+        .args(words)
         .status()
         .with_context(|| format!("launching editor '{editor}'"))?;
 
     if !status.success() {
-        anyhow::bail!("editor exited with {status}");
+        bail!("editor exited with {status}");
     }
+    Ok(())
+}
+
+/// The editor command line to use, and where it came from: the `editor`
+/// preference, `$VISUAL`, `$EDITOR`, or the OS default. Blank values are
+/// skipped. `var` looks up an environment variable.
+pub fn choose(
+    configured: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+) -> (String, &'static str) {
+    let set = |value: Option<String>| value.filter(|v| !v.trim().is_empty());
+    if let Some(editor) = set(configured.map(str::to_string)) {
+        return (editor, "the editor preference");
+    }
+    for name in ["VISUAL", "EDITOR"] {
+        if let Some(editor) = set(var(name)) {
+            let source = if name == "VISUAL" {
+                "$VISUAL"
+            } else {
+                "$EDITOR"
+            };
+            return (editor, source);
+        }
+    }
+    (default_editor().to_string(), "the default")
+}
 
-    let edited = fs::read_to_string(tmp.path()).context("reading edited file")?;
-    let parsed: Value = serde_json::from_str(&edited).context("parsing edited JSON")?;
+/// Notepad on Windows; elsewhere the first of `editor` (Debian's
+/// alternatives link), `nano`, and `vi` that's installed.
+fn default_editor() -> &'static str {
+    if cfg!(windows) {
+        return "notepad";
+    }
+    ["editor", "nano", "vi"]
+        .into_iter()
+        .find(|e| amp::find_on_path(e).is_some())
+        .unwrap_or("vi")
+}
 
-    Ok(parsed)
+/// Splits an editor command line into the program and its arguments, with
+/// `path` in place of each `{file}`, or after the rest if there's none.
+pub fn command_line(editor: &str, path: &Path) -> Result<Vec<OsString>> {
+    let words =
+        shell_words::split(editor).with_context(|| format!("parsing editor command '{editor}'"))?;
+    if words.is_empty() {
+        bail!("the editor command is empty");
+    }
+    let mut substituted = false;
+    let mut line: Vec<OsString> = words
+        .into_iter()
+        .map(|word| {
+            if word.contains("{file}") {
+                substituted = true;
+                word.replace("{file}", &path.to_string_lossy()).into()
+            } else {
+                word.into()
+            }
+        })
+        .collect();
+    if !substituted {
+        line.push(path.into());
+    }
+    Ok(line)
 }
 
 #[cfg(test)]
@@ -44,18 +142,72 @@ mod tests {
 
     #[test]
     fn test_edit_value_with_true_editor() {
-        // Use `true` as editor (no-op, exits 0, file unchanged)
-        env::set_var("EDITOR", "true");
+        // `true` exits 0 without touching the file.
         let original = Value::Object(serde_json::Map::new());
-        let result = edit_value_in_editor(&original).unwrap();
-        assert_eq!(result, original);
+        let session = EditSession::new(&original).unwrap();
+        match session.edit(Some("true")).unwrap() {
+            EditOutcome::Edited(value) => assert_eq!(value, original),
+            EditOutcome::Invalid(e) => panic!("unexpected parse error: {e}"),
+        }
     }
 
     #[test]
     fn test_edit_value_with_failing_editor() {
-        env::set_var("EDITOR", "false");
         let original = Value::Object(serde_json::Map::new());
-        let result = edit_value_in_editor(&original);
-        assert!(result.is_err());
+        let session = EditSession::new(&original).unwrap();
+        assert!(session.edit(Some("false")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_edit_keeps_the_file() {
+        let session = EditSession::new(&Value::Null).unwrap();
+        fs::write(session.file.path(), "{\"a\": ").unwrap();
+
+        match session.edit(Some("true")).unwrap() {
+            EditOutcome::Invalid(e) => assert!(e.contains("line 1"), "{e}"),
+            EditOutcome::Edited(v) => panic!("parsed {v}"),
+        }
+        // The broken text is still there to be fixed on the next run.
+        assert_eq!(fs::read_to_string(session.file.path()).unwrap(), "{\"a\": ");
+    }
+
+    #[test]
+    fn test_choose() {
+        let env = |visual: Option<&str>, editor: Option<&str>| {
+            let (visual, editor) = (visual.map(String::from), editor.map(String::from));
+            move |name: &str| match name {
+                "VISUAL" => visual.clone(),
+                "EDITOR" => editor.clone(),
+                _ => None,
+            }
+        };
+        assert_eq!(
+            choose(Some("hx"), env(Some("code -w"), Some("vim"))),
+            ("hx".to_string(), "the editor preference")
+        );
+        assert_eq!(
+            choose(None, env(Some("code -w"), Some("vim"))),
+            ("code -w".to_string(), "$VISUAL")
+        );
+        assert_eq!(
+            choose(Some(" "), env(Some(""), Some("vim"))),
+            ("vim".to_string(), "$EDITOR")
+        );
+        assert_eq!(choose(None, env(None, None)).1, "the default");
+    }
+
+    #[test]
+    fn test_command_line() {
+        let path = Path::new("/tmp/a b.json");
+        assert_eq!(
+            command_line("code --wait", path).unwrap(),
+            ["code", "--wait", "/tmp/a b.json"].map(OsString::from)
+        );
+        assert_eq!(
+            command_line("'my editor' --file={file} -n", path).unwrap(),
+            ["my editor", "--file=/tmp/a b.json", "-n"].map(OsString::from)
+        );
+        assert!(command_line("vim 'unclosed", path).is_err());
+        assert!(command_line("", path).is_err());
     }
 }
@@ -0,0 +1,186 @@
+//! The TUI's event sources: terminal input, a periodic tick, and results of
+//! background tasks all arrive on one channel, so the main loop sleeps until
+//! any of them has something for it.
+
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event};
+use serde_json::Value;
+
+use crate::mcp_client::{Health, Probe};
+use crate::registry;
+
+/// How long the input thread waits for a key before checking whether it
+/// has been paused.
+const INPUT_POLL: Duration = Duration::from_millis(50);
+
+/// Something for the main loop to handle.
+#[derive(Debug)]
+pub enum AppEvent {
+    /// A key press, mouse action, or resize from the terminal.
+    Input(io::Result<Event>),
+    /// Sent at a fixed interval; drives file watching and status expiry.
+    Tick,
+    /// A background task finished.
+    Task(TaskResult),
+}
+
+/// The outcome of a background task.
+#[derive(Debug)]
+pub enum TaskResult {
+    /// A connectivity test of the named MCP server.
+    McpProbe {
+        server: String,
+        result: Result<Probe>,
+    },
+    /// A health check of the named MCP server, as configured in `config`.
+    McpHealth {
+        server: String,
+        config: Value,
+        health: Health,
+    },
+    /// A download of the MCP server catalog.
+    RegistryRefresh(Result<Vec<registry::Entry>>),
+}
+
+/// Runs work off the UI thread and delivers its result as an
+/// [`AppEvent::Task`].
+#[derive(Debug, Clone)]
+pub struct Tasks {
+    tx: Sender<AppEvent>,
+}
+
+impl Tasks {
+    pub fn new(tx: Sender<AppEvent>) -> Self {
+        Self { tx }
+    }
+
+    /// Runs `work` on a new thread. The result is dropped if the event loop
+    /// has already exited.
+    pub fn spawn(&self, work: impl FnOnce() -> TaskResult + Send + 'static) {
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(AppEvent::Task(work()));
+        });
+    }
+}
+
+/// Whether the input thread may read the terminal, and whether it is
+/// reading right now.
+#[derive(Debug, Default)]
+struct InputState {
+    paused: bool,
+    reading: bool,
+}
+
+/// The channel every event arrives on, plus control of the input thread.
+pub struct Events {
+    tx: Sender<AppEvent>,
+    rx: Receiver<AppEvent>,
+    input: Arc<(Mutex<InputState>, Condvar)>,
+}
+
+impl Events {
+    /// Starts the input and tick threads.
+    pub fn start(tick: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let input = Arc::new((Mutex::new(InputState::default()), Condvar::new()));
+
+        let input_tx = tx.clone();
+        let gate = Arc::clone(&input);
+        thread::spawn(move || read_input(&input_tx, &gate));
+
+        let tick_tx = tx.clone();
+        thread::spawn(move || loop {
+            thread::sleep(tick);
+            if tick_tx.send(AppEvent::Tick).is_err() {
+                return;
+            }
+        });
+
+        Self { tx, rx, input }
+    }
+
+    /// Waits for the next event.
+    pub fn next(&self) -> Result<AppEvent> {
+        self.rx.recv().context("event sources stopped")
+    }
+
+    /// A handle for starting background tasks that report back here.
+    pub fn tasks(&self) -> Tasks {
+        Tasks::new(self.tx.clone())
+    }
+
+    /// Stops reading the terminal, waiting for a read in progress to
+    /// finish, so a program run in the foreground (`$EDITOR`) gets every key.
+    pub fn pause_input(&self) {
+        let (state, changed) = &*self.input;
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.paused = true;
+        while state.reading {
+            state = changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Resumes reading the terminal after [`Events::pause_input`].
+    pub fn resume_input(&self) {
+        let (state, changed) = &*self.input;
+        state.lock().unwrap_or_else(|e| e.into_inner()).paused = false;
+        changed.notify_all();
+    }
+}
+
+/// Forwards terminal events until the receiver goes away or reading fails.
+fn read_input(tx: &Sender<AppEvent>, input: &(Mutex<InputState>, Condvar)) {
+    let (state, changed) = input;
+    loop {
+        {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            state.reading = false;
+            changed.notify_all();
+            while state.paused {
+                state = changed.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+            state.reading = true;
+        }
+        let event = match event::poll(INPUT_POLL) {
+            Ok(false) => continue,
+            Ok(true) => event::read(),
+            Err(e) => Err(e),
+        };
+        let failed = event.is_err();
+        if tx.send(AppEvent::Input(event)).is_err() || failed {
+            // Don't leave `pause_input` waiting on a read that won't end.
+            state.lock().unwrap_or_else(|e| e.into_inner()).reading = false;
+            changed.notify_all();
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_report_back() {
+        let (tx, rx) = mpsc::channel();
+        let tasks = Tasks::new(tx);
+        tasks.spawn(|| TaskResult::McpProbe {
+            server: "gh".into(),
+            result: Err(anyhow::anyhow!("nope")),
+        });
+        match rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(AppEvent::Task(TaskResult::McpProbe { server, result })) => {
+                assert_eq!(server, "gh");
+                assert!(result.is_err());
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Fuzzy subsequence matching used by the settings search.
+
+/// Scores how well `query` matches `candidate`, or returns `None` when the
+/// query's characters don't all appear in order. Matching is case-insensitive;
+/// consecutive runs and matches at word boundaries (after `.`, `_`, `-` or a
+/// lowercase-to-uppercase change) score higher, and shorter candidates win ties.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0i64;
+    let mut pos = 0;
+    let mut prev_match: Option<usize> = None;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        let idx = (pos..chars.len()).find(|&i| chars[i].to_ascii_lowercase() == q)?;
+        score += 1;
+        if prev_match.is_some_and(|p| p + 1 == idx) {
+            score += 5;
+        }
+        if is_boundary(&chars, idx) {
+            score += 3;
+        }
+        prev_match = Some(idx);
+        pos = idx + 1;
+    }
+
+    Some(score * 100 - chars.len() as i64)
+}
+
+/// Returns whether `idx` starts a word within the candidate.
+fn is_boundary(chars: &[char], idx: usize) -> bool {
+    if idx == 0 {
+        return true;
+    }
+    let prev = chars[idx - 1];
+    matches!(prev, '.' | '_' | '-' | ' ') || (prev.is_lowercase() && chars[idx].is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(score("stp", "amp.tools.stopTimeout").is_some());
+        assert!(score("xyz", "amp.tools.stopTimeout").is_none());
+        assert!(score("tset", "amp.showCosts").is_none());
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert!(score("SHOWCOSTS", "amp.showCosts").is_some());
+    }
+
+    #[test]
+    fn test_empty_query_matches_everything() {
+        assert!(score("", "amp.showCosts").is_some());
+        assert!(score("  ", "amp.showCosts").is_some());
+    }
+
+    #[test]
+    fn test_consecutive_and_boundary_rank_higher() {
+        let tight = score("theme", "amp.terminal.theme").unwrap();
+        let scattered = score("theme", "amp.tools.highEffortModes").unwrap_or(i64::MIN);
+        assert!(tight > scattered);
+
+        let boundary = score("cost", "amp.showCosts").unwrap();
+        let inner = score("cost", "amp.accostume").unwrap();
+        assert!(boundary > inner);
+    }
+}
@@ -0,0 +1,174 @@
+//! Integration with git for a settings file kept in a repository, such as a
+//! dotfiles checkout: whether it changed since the last commit, how, and
+//! committing it. Runs the installed `git` CLI.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use anyhow::{bail, Context, Result};
+
+/// Where a settings file stands against the repository's last commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Committed as it is on disk.
+    Clean,
+    /// Changed since the last commit, staged or not.
+    Modified,
+    /// In the repository's tree but never added.
+    Untracked,
+}
+
+impl Status {
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Clean => "clean",
+            Status::Modified => "modified",
+            Status::Untracked => "untracked",
+        }
+    }
+
+    /// Whether there's something to commit.
+    pub fn is_committable(self) -> bool {
+        self != Status::Clean
+    }
+}
+
+/// The directory to run git in and the file's name there. Symlinks are
+/// followed, so a settings file linked from a dotfiles repository is found
+/// in it.
+fn locate(path: &Path) -> Option<(PathBuf, String)> {
+    let path = fs::canonicalize(path).ok()?;
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    Some((path.parent()?.to_path_buf(), name))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("running git")
+}
+
+/// The last line git printed to stderr, for an error message.
+fn complaint(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr
+        .lines()
+        .map(str::trim)
+        .rfind(|l| !l.is_empty())
+        .unwrap_or("git failed")
+        .to_string()
+}
+
+/// The file's git status, or `None` when it isn't in a repository, is
+/// ignored there, or git isn't installed.
+pub fn status(path: &Path) -> Option<Status> {
+    let (dir, name) = locate(path)?;
+    let output = git(&dir, &["status", "--porcelain", "--ignored", "--", &name]).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.lines().next() {
+        None => Some(Status::Clean),
+        Some(line) if line.starts_with("!!") => None,
+        Some(line) if line.starts_with("??") => Some(Status::Untracked),
+        Some(_) => Some(Status::Modified),
+    }
+}
+
+/// The file as of the last commit; `None` if it isn't in it.
+pub fn head_contents(path: &Path) -> Result<Option<String>> {
+    let Some((dir, name)) = locate(path) else {
+        bail!("{} can't be found", path.display());
+    };
+    let output = git(&dir, &["show", &format!("HEAD:./{name}")])?;
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Commits the file as it is on disk, and nothing else that's staged,
+/// returning git's summary line (e.g. `[main 1a2b3c4] Update settings`).
+pub fn commit(path: &Path, message: &str) -> Result<String> {
+    let Some((dir, name)) = locate(path) else {
+        bail!("{} can't be found", path.display());
+    };
+    let added = git(&dir, &["add", "--", &name])?;
+    if !added.status.success() {
+        bail!("git add failed: {}", complaint(&added));
+    }
+    let committed = git(&dir, &["commit", "-m", message, "--", &name])?;
+    if !committed.status.success() {
+        bail!("git commit failed: {}", complaint(&committed));
+    }
+    let stdout = String::from_utf8_lossy(&committed.stdout);
+    Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// A repository in a temp dir with an identity to commit as, or `None`
+/// when git isn't installed.
+#[cfg(test)]
+pub fn test_repo() -> Option<tempfile::TempDir> {
+    crate::amp::find_on_path("git")?;
+    let dir = tempfile::tempdir().unwrap();
+    for args in [
+        &["init", "-q"][..],
+        &["config", "user.name", "Volt Test"],
+        &["config", "user.email", "volt@example.com"],
+        &["config", "commit.gpgsign", "false"],
+    ] {
+        assert!(git(dir.path(), args).unwrap().status.success());
+    }
+    Some(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_and_commit() {
+        let outside = tempfile::tempdir().unwrap();
+        let loose = outside.path().join("settings.json");
+        fs::write(&loose, "{}").unwrap();
+        // A temp dir may itself sit inside a repository; only check when not.
+        if git(outside.path(), &["rev-parse"]).is_ok_and(|o| !o.status.success()) {
+            assert_eq!(status(&loose), None);
+        }
+
+        let Some(repo) = test_repo() else {
+            return;
+        };
+        let path = repo.path().join("settings.json");
+        fs::write(&path, "{\"amp.showCosts\": true}\n").unwrap();
+        assert_eq!(status(&path), Some(Status::Untracked));
+        assert_eq!(head_contents(&path).unwrap(), None);
+
+        let summary = commit(&path, "Add settings").unwrap();
+        assert!(summary.ends_with("Add settings"), "{summary}");
+        assert_eq!(status(&path), Some(Status::Clean));
+        assert_eq!(
+            head_contents(&path).unwrap().as_deref(),
+            Some("{\"amp.showCosts\": true}\n")
+        );
+
+        fs::write(&path, "{}\n").unwrap();
+        assert_eq!(status(&path), Some(Status::Modified));
+        // Other staged files stay out of the commit.
+        fs::write(repo.path().join("other"), "x").unwrap();
+        git(repo.path(), &["add", "other"]).unwrap();
+        commit(&path, "Clear settings").unwrap();
+        assert_eq!(status(&path), Some(Status::Clean));
+        assert_eq!(status(&repo.path().join("other")), Some(Status::Modified));
+
+        fs::write(repo.path().join(".gitignore"), "settings.json\n").unwrap();
+        git(repo.path(), &["rm", "-q", "--cached", "settings.json"]).unwrap();
+        git(repo.path(), &["commit", "-qm", "Ignore"]).unwrap();
+        assert_eq!(status(&path), None);
+    }
+}
@@ -0,0 +1,908 @@
+//! Minimal JSONC support: parsing files with comments and trailing commas, and
+//! patching the top-level object of such a file in place so that comments,
+//! formatting, and key order survive a save. JSON5 files are read by first
+//! rewriting their extra syntax as JSONC.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// A top-level `"key": value` member, as byte spans into the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Member {
+    key: String,
+    /// Start of the key's opening quote.
+    start: usize,
+    value_start: usize,
+    value_end: usize,
+    /// Position of the comma following the value, if any.
+    comma: Option<usize>,
+}
+
+/// The layout of a top-level JSON object.
+#[derive(Debug)]
+struct Document {
+    members: Vec<Member>,
+    /// Position of the closing `}`.
+    close: usize,
+}
+
+/// Parses JSONC text (comments and trailing commas allowed) into an object.
+pub fn parse(text: &str) -> Result<Map<String, Value>> {
+    let stripped = strip(text);
+    serde_json::from_str(&stripped).context("invalid JSON")
+}
+
+/// Parses JSON with comments but nothing else beyond standard JSON: no
+/// trailing commas or JSON5 syntax.
+pub fn parse_strict(text: &str) -> Result<Map<String, Value>> {
+    serde_json::from_str(&strip_comments(text)).context("invalid JSON")
+}
+
+/// Rewrites the JSON5 syntax JSONC lacks as its JSONC equivalent: single-quoted
+/// strings, unquoted (even dotted) keys, hexadecimal numbers and ones with a leading `+` or
+/// a bare decimal point, escaped line breaks in strings, and trailing commas.
+/// Comments and layout are kept. `Infinity` and `NaN` have no JSON form and
+/// are an error.
+pub fn from_json5(text: &str) -> Result<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            quote @ ('"' | '\'') => {
+                let end = skip_quoted(&chars, i, quote);
+                if end > chars.len() {
+                    bail!("unterminated string");
+                }
+                out.push_str(&requote(&chars[i + 1..end - 1]));
+                i = end;
+            }
+            '/' if matches!(chars.get(i + 1), Some('/' | '*')) => {
+                let end = skip_comment(&chars, i);
+                out.extend(&chars[i..end]);
+                i = end;
+            }
+            ',' => {
+                if !matches!(next_significant(&chars, i + 1), Some('}' | ']')) {
+                    out.push(',');
+                }
+                i += 1;
+            }
+            c if c.is_ascii_digit() || matches!(c, '+' | '-' | '.') => {
+                let mut end = i + 1;
+                while end < chars.len()
+                    && (chars[end].is_ascii_alphanumeric()
+                        || chars[end] == '.'
+                        || (matches!(chars[end], '+' | '-') && matches!(chars[end - 1], 'e' | 'E')))
+                {
+                    end += 1;
+                }
+                let token: String = chars[i..end].iter().collect();
+                out.push_str(&json5_number(&token)?);
+                i = end;
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut end = i + 1;
+                // Dots and dashes go beyond JSON5, but unquoted Amp keys
+                // such as `amp.showCosts` need them.
+                while end < chars.len()
+                    && (chars[end].is_alphanumeric() || matches!(chars[end], '_' | '$' | '.' | '-'))
+                {
+                    end += 1;
+                }
+                let word: String = chars[i..end].iter().collect();
+                if next_significant(&chars, end) == Some(':') {
+                    out.push_str(&serde_json::to_string(&word)?);
+                } else {
+                    match word.as_str() {
+                        "true" | "false" | "null" => out.push_str(&word),
+                        "Infinity" | "NaN" => bail!("{word} has no JSON equivalent"),
+                        _ => bail!("unexpected '{word}'"),
+                    }
+                }
+                i = end;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Returns the index after the string opened by `quote` at `i`, or past the
+/// end if it's unterminated.
+fn skip_quoted(chars: &[char], i: usize, quote: char) -> usize {
+    let mut j = i + 1;
+    while j < chars.len() {
+        match chars[j] {
+            '\\' => j += 2,
+            c if c == quote => return j + 1,
+            _ => j += 1,
+        }
+    }
+    chars.len() + 1
+}
+
+/// Writes a string's contents as a double-quoted JSON string, unescaping
+/// `\'` and dropping escaped line breaks.
+fn requote(inner: &[char]) -> String {
+    let mut out = String::from('"');
+    let mut i = 0;
+    while i < inner.len() {
+        match (inner[i], inner.get(i + 1)) {
+            ('\\', Some('\'')) => out.push('\''),
+            ('\\', Some('\n')) => {}
+            ('\\', Some('\r')) => {
+                if inner.get(i + 2) == Some(&'\n') {
+                    i += 1;
+                }
+            }
+            ('\\', Some(&c)) => {
+                out.push('\\');
+                out.push(c);
+            }
+            ('"', _) => {
+                out.push_str("\\\"");
+                i += 1;
+                continue;
+            }
+            (c, _) => {
+                out.push(c);
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    out.push('"');
+    out
+}
+
+/// Returns the index after the comment starting at `i`.
+fn skip_comment(chars: &[char], i: usize) -> usize {
+    if chars.get(i + 1) == Some(&'/') {
+        chars[i..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(chars.len(), |p| i + p)
+    } else {
+        (i + 2..chars.len().saturating_sub(1))
+            .find(|&j| chars[j] == '*' && chars[j + 1] == '/')
+            .map_or(chars.len(), |j| j + 2)
+    }
+}
+
+/// Returns the next character from `i` that isn't whitespace or a comment.
+fn next_significant(chars: &[char], mut i: usize) -> Option<char> {
+    loop {
+        match chars.get(i)? {
+            c if c.is_whitespace() => i += 1,
+            '/' if matches!(chars.get(i + 1), Some('/' | '*')) => i = skip_comment(chars, i),
+            &c => return Some(c),
+        }
+    }
+}
+
+/// Converts a JSON5 number to JSON.
+fn json5_number(token: &str) -> Result<String> {
+    let (sign, digits) = match token.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", token.strip_prefix('+').unwrap_or(token)),
+    };
+    if matches!(digits, "Infinity" | "NaN") {
+        bail!("{token} has no JSON equivalent");
+    }
+    let number = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16)
+            .with_context(|| format!("invalid number '{token}'"))?
+            .to_string(),
+        None => {
+            let mut number = digits.to_string();
+            if number.starts_with('.') {
+                number.insert(0, '0');
+            }
+            if let Some(dot) = number.find('.') {
+                if !number[dot + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+                    number.insert(dot + 1, '0');
+                }
+            }
+            number
+        }
+    };
+    let number = format!("{sign}{number}");
+    match serde_json::from_str::<Value>(&number) {
+        Ok(Value::Number(_)) => Ok(number),
+        _ => bail!("invalid number '{token}'"),
+    }
+}
+
+/// Replaces comments and trailing commas with spaces. Newlines are kept and the
+/// output has the same byte length as the input, so offsets stay valid.
+pub fn strip(text: &str) -> String {
+    let mut out = strip_comments(text).into_bytes();
+    let mut i = 0;
+    while i < out.len() {
+        match out[i] {
+            b'"' => i = skip_string(&out, i),
+            b',' => {
+                let next = out[i + 1..].iter().find(|b| !b.is_ascii_whitespace());
+                if matches!(next, Some(b'}') | Some(b']')) {
+                    out[i] = b' ';
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Replaces comments with spaces, keeping newlines and byte offsets.
+fn strip_comments(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => i = skip_string(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    out[i] = b' ';
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = find_subslice(bytes, i + 2, b"*/").map_or(bytes.len(), |p| p + 2);
+                for b in &mut out[i..end] {
+                    if *b != b'\n' {
+                        *b = b' ';
+                    }
+                }
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    // Multi-byte sequences inside comments were blanked byte-for-byte, and
+    // nothing outside comments was touched, so this is still valid UTF-8.
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Rewrites `original` so that its top-level object holds exactly `values`.
+///
+/// Members whose value is unchanged are left untouched (including any comments
+/// around them), changed values are replaced in place, removed keys are cut
+/// out along with their line, and new keys are appended at the end in the
+/// order given. Trailing-comma style is kept.
+pub fn patch<'a, I>(original: &str, values: I) -> Result<String>
+where
+    I: IntoIterator<Item = (&'a String, &'a Value)>,
+{
+    let stripped = strip(original);
+    let doc = scan(&strip_comments(original))?;
+    let wanted: Vec<(&String, &Value)> = values.into_iter().collect();
+    let indent = detect_indent(original, &doc);
+    let trailing_style = doc.members.last().is_some_and(|m| m.comma.is_some());
+
+    // (position, bytes to remove, replacement). Edits never overlap; at equal
+    // positions, earlier edits end up earlier in the output.
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut kept: Vec<&Member> = Vec::new();
+
+    for member in &doc.members {
+        match wanted.iter().find(|(k, _)| **k == member.key) {
+            Some((_, new)) => {
+                kept.push(member);
+                let old: Option<Value> =
+                    serde_json::from_str(&stripped[member.value_start..member.value_end]).ok();
+                if old.as_ref() != Some(*new) {
+                    let base = line_indent(original, member.start);
+                    edits.push((
+                        member.value_start,
+                        member.value_end - member.value_start,
+                        to_json(new, &indent, &base),
+                    ));
+                }
+            }
+            None => {
+                let (from, to) = removal_range(original, &stripped, member);
+                edits.push((from, to - from, String::new()));
+            }
+        }
+    }
+
+    let rendered: Vec<String> = wanted
+        .iter()
+        .filter(|(k, _)| !doc.members.iter().any(|m| &m.key == *k))
+        .map(|(k, v)| {
+            format!(
+                "{indent}{}: {}",
+                serde_json::to_string(k).unwrap_or_default(),
+                to_json(v, &indent, &indent)
+            )
+        })
+        .collect();
+
+    match kept.last() {
+        Some(last) if !rendered.is_empty() => {
+            let anchor = match last.comma {
+                Some(c) => c + 1,
+                None => {
+                    edits.push((last.value_end, 0, ",".to_string()));
+                    last.value_end
+                }
+            };
+            let joined = if trailing_style {
+                rendered
+                    .iter()
+                    .map(|r| format!("{r},"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                rendered.join(",\n")
+            };
+            match stripped[anchor..doc.close].find('\n') {
+                Some(p) => edits.push((anchor + p, 0, format!("\n{joined}"))),
+                None => edits.push((anchor, 0, format!("\n{joined}\n"))),
+            }
+        }
+        Some(last) => {
+            // The original last member was removed: drop the separator the new
+            // last member no longer needs.
+            if let (Some(c), false) = (last.comma, trailing_style) {
+                edits.push((c, 1, String::new()));
+            }
+        }
+        None if !rendered.is_empty() => {
+            let text = rendered.join(",\n");
+            let line_start = original[..doc.close].rfind('\n').map_or(0, |p| p + 1);
+            if line_start > 0 && original[line_start..doc.close].trim().is_empty() {
+                edits.push((line_start, 0, format!("{text}\n")));
+            } else {
+                edits.push((doc.close, 0, format!("\n{text}\n")));
+            }
+        }
+        None => {}
+    }
+
+    let mut order: Vec<usize> = (0..edits.len()).collect();
+    order.sort_by(|a, b| edits[*b].0.cmp(&edits[*a].0).then(b.cmp(a)));
+    let mut out = original.to_string();
+    for i in order {
+        let (at, len, text) = &edits[i];
+        out.replace_range(*at..*at + *len, text);
+    }
+    Ok(out)
+}
+
+/// Reindents every line with `indent` per nesting level. Blank lines lose
+/// their whitespace, and lines inside block comments are left as they are.
+pub fn reindent(text: &str, indent: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    let mut in_block = false;
+    for line in text.split_inclusive('\n') {
+        let body = line.trim_start_matches([' ', '\t']);
+        if in_block {
+            out.push_str(line);
+        } else if body.trim().is_empty() {
+            out.push_str(&body[body.trim_end_matches(['\n', '\r']).len()..]);
+        } else {
+            let closing = usize::from(body.starts_with(['}', ']']));
+            out.push_str(&indent.repeat(depth.saturating_sub(closing)));
+            out.push_str(body);
+        }
+
+        // Strings can't span lines, so only block comments carry over.
+        let mut chars = line.chars().peekable();
+        let mut in_string = false;
+        while let Some(c) = chars.next() {
+            if in_block {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    in_block = false;
+                }
+            } else if in_string {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => in_string = false,
+                    _ => {}
+                }
+            } else {
+                match (c, chars.peek()) {
+                    ('"', _) => in_string = true,
+                    ('/', Some('/')) => break,
+                    ('/', Some('*')) => {
+                        chars.next();
+                        in_block = true;
+                    }
+                    ('{' | '[', _) => depth += 1,
+                    ('}' | ']', _) => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Puts the top-level members in order by key. Each member moves with the
+/// comment lines above it and any comment after it on its line; commas are
+/// fixed up, keeping the trailing-comma style. Files that put more than one
+/// member on a line are returned as they are.
+pub fn sort_members(original: &str) -> Result<String> {
+    let stripped = strip_comments(original);
+    let doc = scan(&stripped)?;
+    if doc.members.windows(2).all(|w| w[0].key <= w[1].key) {
+        return Ok(original.to_string());
+    }
+    let trailing_style = doc.members.last().is_some_and(|m| m.comma.is_some());
+    let line_end = |pos: usize| stripped[pos..].find('\n').map(|p| pos + p + 1);
+    let Some(first) = doc.members.first() else {
+        return Ok(original.to_string());
+    };
+    let Some(mut start) = line_end(stripped[..first.start].rfind('{').unwrap_or(0)) else {
+        return Ok(original.to_string());
+    };
+    let body_start = start;
+
+    let mut blocks = Vec::new();
+    for member in &doc.members {
+        // Only the first member can start on a line that came before its
+        // block, by sharing the `{` line.
+        let after = member.comma.map_or(member.value_end, |c| c + 1);
+        let end = line_end(after).filter(|&end| stripped[after..end].trim().is_empty());
+        let Some(end) = end.filter(|_| start <= member.start) else {
+            return Ok(original.to_string());
+        };
+        let mut text = original[start..end].to_string();
+        if let Some(comma) = member.comma {
+            text.remove(comma - start);
+        }
+        blocks.push((member.key.as_str(), text, member.value_end - start));
+        start = end;
+    }
+
+    blocks.sort_by_key(|(key, ..)| *key);
+    let count = blocks.len();
+    let mut out = original[..body_start].to_string();
+    for (i, (_, mut text, value_end)) in blocks.into_iter().enumerate() {
+        if i + 1 < count || trailing_style {
+            text.insert(value_end, ',');
+        }
+        out.push_str(&text);
+    }
+    out.push_str(&original[start..]);
+    Ok(out)
+}
+
+/// Serializes a value pretty-printed with `indent` per level, continuation
+/// lines offset by `base`.
+fn to_json(value: &Value, indent: &str, base: &str) -> String {
+    let mut buf = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    if value.serialize(&mut ser).is_err() {
+        return "null".to_string();
+    }
+    let text = String::from_utf8(buf).unwrap_or_default();
+    text.replace('\n', &format!("\n{base}"))
+}
+
+/// Returns the byte range to delete when removing a member: the member and its
+/// comma, and the whole line (including a trailing comment and the comment
+/// lines directly above it, unless they open the object) when the member sits
+/// on its own line. Inline
+/// members take adjacent spaces with them.
+fn removal_range(original: &str, stripped: &str, member: &Member) -> (usize, usize) {
+    let mut line_start = original[..member.start].rfind('\n').map_or(0, |p| p + 1);
+    let end = member.comma.map_or(member.value_end, |c| c + 1);
+    let rest = &stripped[end..];
+    let eol = rest.find('\n').map_or(rest.len(), |p| p + 1);
+    let own_line = original[line_start..member.start].trim().is_empty();
+
+    if own_line && rest[..eol].trim().is_empty() {
+        // Comment lines are blank once comments are stripped; a truly blank
+        // line ends the member's comment block, and one right after the `{`
+        // is the file's header rather than the member's.
+        let own_start = line_start;
+        while line_start > 0 {
+            let prev = original[..line_start - 1].rfind('\n').map_or(0, |p| p + 1);
+            let is_comment = stripped[prev..line_start].trim().is_empty()
+                && !original[prev..line_start].trim().is_empty();
+            if !is_comment {
+                break;
+            }
+            line_start = prev;
+        }
+        if stripped[..line_start].trim_end().ends_with('{') {
+            line_start = own_start;
+        }
+        return (line_start, end + eol);
+    }
+
+    let bytes = stripped.as_bytes();
+    if member.comma.is_some() {
+        let mut to = end;
+        while to < bytes.len() && bytes[to] == b' ' {
+            to += 1;
+        }
+        (member.start, to)
+    } else {
+        let mut from = member.start;
+        while from > 0 && bytes[from - 1].is_ascii_whitespace() {
+            from -= 1;
+        }
+        (from, end)
+    }
+}
+
+/// Returns the whitespace at the start of the line containing `pos`.
+fn line_indent(text: &str, pos: usize) -> String {
+    let line_start = text[..pos].rfind('\n').map_or(0, |p| p + 1);
+    text[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect()
+}
+
+/// Detects the per-level indentation from the first member, defaulting to two spaces.
+fn detect_indent(original: &str, doc: &Document) -> String {
+    doc.members
+        .first()
+        .map(|m| line_indent(original, m.start))
+        .filter(|i| !i.is_empty())
+        .unwrap_or_else(|| "  ".to_string())
+}
+
+/// Scans the top-level object of comment-free JSON text. Trailing commas are
+/// allowed and recorded on the last member.
+fn scan(text: &str) -> Result<Document> {
+    let bytes = text.as_bytes();
+    let mut i = skip_ws(bytes, 0);
+    if bytes.get(i) != Some(&b'{') {
+        bail!("expected a top-level JSON object");
+    }
+    i += 1;
+    let mut members = Vec::new();
+
+    loop {
+        i = skip_ws(bytes, i);
+        match bytes.get(i) {
+            Some(b'}') => break,
+            Some(b'"') => {}
+            _ => bail!("expected a key at byte {i}"),
+        }
+        let start = i;
+        let key_end = skip_string(bytes, i);
+        let key: String =
+            serde_json::from_str(&text[start..key_end]).context("invalid key string")?;
+        i = skip_ws(bytes, key_end);
+        if bytes.get(i) != Some(&b':') {
+            bail!("expected ':' after key '{key}'");
+        }
+        let value_start = skip_ws(bytes, i + 1);
+        let value_end = skip_value(bytes, value_start)?;
+        i = skip_ws(bytes, value_end);
+        let comma = if bytes.get(i) == Some(&b',') {
+            i += 1;
+            Some(i - 1)
+        } else {
+            None
+        };
+        members.push(Member {
+            key,
+            start,
+            value_start,
+            value_end,
+            comma,
+        });
+        if comma.is_none() {
+            i = skip_ws(bytes, i);
+            if bytes.get(i) != Some(&b'}') {
+                bail!("expected ',' or '}}' at byte {i}");
+            }
+            break;
+        }
+    }
+
+    Ok(Document { members, close: i })
+}
+
+/// Skips a JSON value starting at `i`, returning the position after it.
+fn skip_value(bytes: &[u8], i: usize) -> Result<usize> {
+    match bytes.get(i) {
+        Some(b'"') => Ok(skip_string(bytes, i)),
+        Some(b'{') | Some(b'[') => {
+            let mut depth = 0usize;
+            let mut j = i;
+            while j < bytes.len() {
+                match bytes[j] {
+                    b'"' => {
+                        j = skip_string(bytes, j);
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                j += 1;
+            }
+            bail!("unterminated value at byte {i}")
+        }
+        Some(_) => {
+            let mut j = i;
+            while j < bytes.len() && !matches!(bytes[j], b',' | b'}' | b']') {
+                if bytes[j].is_ascii_whitespace() {
+                    break;
+                }
+                j += 1;
+            }
+            Ok(j)
+        }
+        None => bail!("unexpected end of input"),
+    }
+}
+
+/// Skips a string literal starting at the opening quote at `i`.
+fn skip_string(bytes: &[u8], i: usize) -> usize {
+    let mut j = i + 1;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'\\' => j += 2,
+            b'"' => return j + 1,
+            _ => j += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn skip_ws(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+fn find_subslice(haystack: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patched(original: &str, values: Value) -> String {
+        let map = values.as_object().unwrap().clone();
+        let out = patch(original, map.iter()).unwrap();
+        // Whatever we produce must still parse to exactly the requested values.
+        assert_eq!(
+            Value::Object(parse(&out).unwrap()),
+            values,
+            "output:\n{out}"
+        );
+        out
+    }
+
+    #[test]
+    fn test_parse_comments_and_trailing_commas() {
+        let map = parse(
+            r#"{
+  // line comment with "quotes"
+  "a": "http://example.com", /* block */
+  "b": [1, 2,],
+}"#,
+        )
+        .unwrap();
+        assert_eq!(map["a"], json!("http://example.com"));
+        assert_eq!(map["b"], json!([1, 2]));
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        assert!(parse_strict("// note\n{\"a\": 1}").is_ok());
+        assert!(parse_strict("{\"a\": 1,}").is_err());
+    }
+
+    #[test]
+    fn test_from_json5() {
+        let text = r#"{
+  // unquoted keys and single quotes
+  amp.x: 1,
+  showCosts: 'it\'s "fine"',
+  $n: [+1, .5, 5., 0x1F, -2e+3,],
+  'quoted': 'a\
+b', /* trailing */
+}"#;
+        let converted = from_json5(text).unwrap();
+        assert!(converted.contains("// unquoted keys"));
+        assert_eq!(
+            Value::Object(parse(&converted).unwrap()),
+            json!({
+                "amp.x": 1,
+                "showCosts": "it's \"fine\"",
+                "$n": [1, 0.5, 5.0, 31, -2e3],
+                "quoted": "ab"
+            })
+        );
+        assert!(!converted.contains(",]") && !converted.trim_end().ends_with(",\n}"));
+    }
+
+    #[test]
+    fn test_from_json5_rejects_what_json_lacks() {
+        assert_eq!(
+            from_json5("{a: Infinity}").unwrap_err().to_string(),
+            "Infinity has no JSON equivalent"
+        );
+        assert_eq!(
+            from_json5("{a: -NaN}").unwrap_err().to_string(),
+            "-NaN has no JSON equivalent"
+        );
+        assert!(from_json5("{a: 'open}").is_err());
+        assert!(from_json5("{a: undefined}").is_err());
+    }
+
+    #[test]
+    fn test_strip_preserves_length() {
+        let text = "{ /* é */ \"a\": 1, // ü\n}";
+        assert_eq!(strip(text).len(), text.len());
+    }
+
+    #[test]
+    fn test_patch_unchanged_is_identity() {
+        let original = "{\n  // keep me\n  \"a\": true,\n  \"b\": 1\n}\n";
+        assert_eq!(patched(original, json!({"a": true, "b": 1})), original);
+    }
+
+    #[test]
+    fn test_patch_changes_value_in_place() {
+        let original = "{\n  // costs\n  \"a\": true, // trailing note\n  \"b\": 1\n}\n";
+        let out = patched(original, json!({"a": false, "b": 1}));
+        assert_eq!(
+            out,
+            "{\n  // costs\n  \"a\": false, // trailing note\n  \"b\": 1\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_patch_nested_value_uses_file_indent() {
+        let original = "{\n    \"a\": 1\n}\n";
+        let out = patched(original, json!({"a": {"x": [1]}}));
+        assert_eq!(
+            out,
+            "{\n    \"a\": {\n        \"x\": [\n            1\n        ]\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_reindent() {
+        let original = "{\n  \"a\": {\n    \"b\": [1, 2], // [\n\n    \"c\": \"}\"\n  },\n  /* {\n   keep */\n  \"d\": 1\n}\n";
+        assert_eq!(
+            reindent(original, "\t"),
+            "{\n\t\"a\": {\n\t\t\"b\": [1, 2], // [\n\n\t\t\"c\": \"}\"\n\t},\n\t/* {\n   keep */\n\t\"d\": 1\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_members() {
+        let original = "{\n  // costs\n  \"b\": {\n    \"x\": 1\n  }, // note\n  \"a\": 1\n}\n";
+        assert_eq!(
+            sort_members(original).unwrap(),
+            "{\n  \"a\": 1,\n  // costs\n  \"b\": {\n    \"x\": 1\n  } // note\n}\n"
+        );
+        let trailing = "{\n  \"b\": 1,\n  \"a\": 2,\n}";
+        assert_eq!(
+            sort_members(trailing).unwrap(),
+            "{\n  \"a\": 2,\n  \"b\": 1,\n}"
+        );
+        let inline = "{\"b\": 1, \"a\": 2}";
+        assert_eq!(sort_members(inline).unwrap(), inline);
+        let opening = "{\"b\": 1,\n  \"a\": 2\n}";
+        assert_eq!(sort_members(opening).unwrap(), opening);
+    }
+
+    #[test]
+    fn test_patch_appends_new_keys() {
+        let original = "{\n  \"a\": 1 // one\n}\n";
+        let out = patched(original, json!({"a": 1, "b": 2}));
+        assert_eq!(out, "{\n  \"a\": 1, // one\n  \"b\": 2\n}\n");
+    }
+
+    #[test]
+    fn test_patch_appends_with_trailing_comma_style() {
+        let original = "{\n  \"a\": 1,\n}\n";
+        let out = patched(original, json!({"a": 1, "b": 2}));
+        assert_eq!(out, "{\n  \"a\": 1,\n  \"b\": 2,\n}\n");
+    }
+
+    #[test]
+    fn test_patch_into_commented_scaffold() {
+        let original = "{\n  // \"a\": 1,\n}\n";
+        let out = patched(original, json!({"b": 2}));
+        assert_eq!(out, "{\n  // \"a\": 1,\n  \"b\": 2\n}\n");
+    }
+
+    #[test]
+    fn test_patch_removes_middle_member() {
+        let original = "{\n  \"a\": 1,\n  \"b\": 2,\n  \"c\": 3\n}\n";
+        let out = patched(original, json!({"a": 1, "c": 3}));
+        assert_eq!(out, "{\n  \"a\": 1,\n  \"c\": 3\n}\n");
+    }
+
+    #[test]
+    fn test_patch_removes_last_member() {
+        let original = "{\n  \"a\": 1,\n  \"b\": 2\n}\n";
+        let out = patched(original, json!({"a": 1}));
+        assert_eq!(out, "{\n  \"a\": 1\n}\n");
+    }
+
+    #[test]
+    fn test_patch_removes_only_member() {
+        let original = "{\n  \"a\": 1\n}\n";
+        let out = patched(original, json!({}));
+        assert_eq!(out, "{\n}\n");
+    }
+
+    #[test]
+    fn test_patch_removes_member_with_its_trailing_comment() {
+        let original = "{\n  \"a\": 1, // about a\n  \"b\": 2\n}\n";
+        let out = patched(original, json!({"b": 2}));
+        assert_eq!(out, "{\n  \"b\": 2\n}\n");
+    }
+
+    #[test]
+    fn test_patch_removes_member_with_the_comments_above_it() {
+        let original = "{\n  \"a\": 1,\n\n  // about b\n  /* more\n     about b */\n  \"b\": 2,\n  // about c\n  \"c\": 3\n}\n";
+        let out = patched(original, json!({"a": 1, "c": 3}));
+        assert_eq!(out, "{\n  \"a\": 1,\n\n  // about c\n  \"c\": 3\n}\n");
+        // A blank line detaches a comment from the member below it.
+        let original = "{\n  // header\n\n  \"a\": 1,\n  \"b\": 2\n}\n";
+        let out = patched(original, json!({"b": 2}));
+        assert_eq!(out, "{\n  // header\n\n  \"b\": 2\n}\n");
+    }
+
+    #[test]
+    fn test_patch_removes_last_member_trailing_comma_style() {
+        let original = "{\n  \"a\": 1,\n  \"b\": [1, 2,],\n}\n";
+        let out = patched(original, json!({"b": [1, 2]}));
+        assert_eq!(out, "{\n  \"b\": [1, 2,],\n}\n");
+    }
+
+    #[test]
+    fn test_patch_replace_and_remove_all_then_add() {
+        let original = "{\n  // header\n  \"a\": 1,\n  \"b\": 2\n}\n";
+        let out = patched(original, json!({"c": "x"}));
+        assert_eq!(out, "{\n  // header\n  \"c\": \"x\"\n}\n");
+    }
+
+    #[test]
+    fn test_patch_single_line_object() {
+        let out = patched(r#"{"a": 1, "b": 2}"#, json!({"b": 3}));
+        assert_eq!(out, r#"{"b": 3}"#);
+    }
+
+    #[test]
+    fn test_patch_rejects_non_object() {
+        let map = Map::new();
+        assert!(patch("[1, 2]", map.iter()).is_err());
+    }
+}
@@ -0,0 +1,802 @@
+//! Translates key presses in normal mode into app actions, with bindings that
+//! can be remapped from volt's config file.
+
+use anyhow::{bail, Context, Result};
+use crossterm::event::{KeyCode, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+/// Something the user can do from normal mode. Serialized under its
+/// [`Action::name`], as macros and the action log store it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    MoveUp,
+    MoveDown,
+    PageUp,
+    PageDown,
+    SelectFirst,
+    SelectLast,
+    ToggleFocus,
+    SwitchMcpPanel,
+    Activate,
+    OpenEditor,
+    EditInline,
+    ShowValue,
+    ScrollLeft,
+    ScrollRight,
+    Increment,
+    Decrement,
+    ImportMcp,
+    TestMcp,
+    EditEnv,
+    AddItem,
+    DeleteItem,
+    MoveItemUp,
+    MoveItemDown,
+    SortRows,
+    ApplySort,
+    Duplicate,
+    TestPermissions,
+    FixType,
+    MigrateAll,
+    Copy,
+    Paste,
+    Reset,
+    EditNote,
+    RevealSecrets,
+    ToggleSidebar,
+    NarrowSidebar,
+    WidenSidebar,
+    ToggleSplit,
+    CycleFilter,
+    SwapPane,
+    ToggleHelp,
+    TogglePreview,
+    ExpandTree,
+    ToggleGroup,
+    ToggleFavorite,
+    RemoveShadowedRules,
+    ToggleScope,
+    EditAtSource,
+    RestoreBackup,
+    Profiles,
+    PermissionPresets,
+    BrowseRegistry,
+    AddServerRule,
+    ShowTrash,
+    ShowRecent,
+    ExportRedacted,
+    GitDiff,
+    GitCommit,
+    RestoreLast,
+    ResetSection,
+    ResetAll,
+    Search,
+    ShowMessages,
+    CheckWithAmp,
+    SwitchFile,
+    ShowDiff,
+    Save,
+    CommandPalette,
+    RecordMacro,
+    ReplayMacro,
+    EditFile,
+}
+
+impl Action {
+    pub const ALL: &[Action] = &[
+        Action::Quit,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::PageUp,
+        Action::PageDown,
+        Action::SelectFirst,
+        Action::SelectLast,
+        Action::ToggleFocus,
+        Action::SwitchMcpPanel,
+        Action::Activate,
+        Action::OpenEditor,
+        Action::EditInline,
+        Action::ShowValue,
+        Action::ScrollLeft,
+        Action::ScrollRight,
+        Action::Increment,
+        Action::Decrement,
+        Action::ImportMcp,
+        Action::TestMcp,
+        Action::EditEnv,
+        Action::AddItem,
+        Action::DeleteItem,
+        Action::MoveItemUp,
+        Action::MoveItemDown,
+        Action::SortRows,
+        Action::ApplySort,
+        Action::Duplicate,
+        Action::TestPermissions,
+        Action::FixType,
+        Action::MigrateAll,
+        Action::Copy,
+        Action::Paste,
+        Action::Reset,
+        Action::EditNote,
+        Action::RevealSecrets,
+        Action::ToggleSidebar,
+        Action::NarrowSidebar,
+        Action::WidenSidebar,
+        Action::ToggleSplit,
+        Action::CycleFilter,
+        Action::SwapPane,
+        Action::ToggleHelp,
+        Action::TogglePreview,
+        Action::ExpandTree,
+        Action::ToggleGroup,
+        Action::ToggleFavorite,
+        Action::RemoveShadowedRules,
+        Action::ToggleScope,
+        Action::EditAtSource,
+        Action::RestoreBackup,
+        Action::Profiles,
+        Action::PermissionPresets,
+        Action::BrowseRegistry,
+        Action::AddServerRule,
+        Action::ShowTrash,
+        Action::ShowRecent,
+        Action::ExportRedacted,
+        Action::GitDiff,
+        Action::GitCommit,
+        Action::RestoreLast,
+        Action::ResetSection,
+        Action::ResetAll,
+        Action::Search,
+        Action::ShowMessages,
+        Action::CheckWithAmp,
+        Action::SwitchFile,
+        Action::ShowDiff,
+        Action::Save,
+        Action::CommandPalette,
+        Action::RecordMacro,
+        Action::ReplayMacro,
+        Action::EditFile,
+    ];
+
+    /// The name used for the action in the `[keys]` table of the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::SelectFirst => "select_first",
+            Action::SelectLast => "select_last",
+            Action::ToggleFocus => "toggle_focus",
+            Action::SwitchMcpPanel => "switch_mcp_panel",
+            Action::Activate => "activate",
+            Action::OpenEditor => "open_editor",
+            Action::EditInline => "edit_inline",
+            Action::ShowValue => "show_value",
+            Action::ScrollLeft => "scroll_left",
+            Action::ScrollRight => "scroll_right",
+            Action::Increment => "increment",
+            Action::Decrement => "decrement",
+            Action::ImportMcp => "import_mcp",
+            Action::TestMcp => "test_mcp",
+            Action::EditEnv => "edit_env",
+            Action::AddItem => "add_item",
+            Action::DeleteItem => "delete_item",
+            Action::MoveItemUp => "move_item_up",
+            Action::MoveItemDown => "move_item_down",
+            Action::SortRows => "sort_rows",
+            Action::ApplySort => "apply_sort",
+            Action::Duplicate => "duplicate",
+            Action::TestPermissions => "test_permissions",
+            Action::FixType => "fix_type",
+            Action::MigrateAll => "migrate_all",
+            Action::Copy => "copy",
+            Action::Paste => "paste",
+            Action::Reset => "reset",
+            Action::EditNote => "edit_note",
+            Action::RevealSecrets => "reveal_secrets",
+            Action::ToggleSidebar => "toggle_sidebar",
+            Action::NarrowSidebar => "narrow_sidebar",
+            Action::WidenSidebar => "widen_sidebar",
+            Action::ToggleSplit => "toggle_split",
+            Action::CycleFilter => "cycle_filter",
+            Action::SwapPane => "swap_pane",
+            Action::ToggleHelp => "toggle_help",
+            Action::TogglePreview => "toggle_preview",
+            Action::ExpandTree => "expand_tree",
+            Action::ToggleGroup => "toggle_group",
+            Action::ToggleFavorite => "toggle_favorite",
+            Action::RemoveShadowedRules => "remove_shadowed_rules",
+            Action::ToggleScope => "toggle_scope",
+            Action::EditAtSource => "edit_at_source",
+            Action::RestoreBackup => "restore_backup",
+            Action::Profiles => "profiles",
+            Action::PermissionPresets => "permission_presets",
+            Action::BrowseRegistry => "browse_registry",
+            Action::AddServerRule => "add_server_rule",
+            Action::ShowTrash => "show_trash",
+            Action::ShowRecent => "show_recent",
+            Action::ExportRedacted => "export_redacted",
+            Action::GitDiff => "git_diff",
+            Action::GitCommit => "git_commit",
+            Action::RestoreLast => "restore_last",
+            Action::ResetSection => "reset_section",
+            Action::ResetAll => "reset_all",
+            Action::Search => "search",
+            Action::ShowMessages => "show_messages",
+            Action::CheckWithAmp => "check_with_amp",
+            Action::SwitchFile => "switch_file",
+            Action::ShowDiff => "show_diff",
+            Action::Save => "save",
+            Action::CommandPalette => "command_palette",
+            Action::RecordMacro => "record_macro",
+            Action::ReplayMacro => "replay_macro",
+            Action::EditFile => "edit_file",
+        }
+    }
+
+    /// What the action does, as listed in the command palette.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::SelectFirst => "Select the first row",
+            Action::SelectLast => "Select the last row",
+            Action::ToggleFocus => "Switch focus between sidebar and settings",
+            Action::SwitchMcpPanel => "Switch between MCP configs and permissions",
+            Action::Activate => "Toggle or edit the selected setting",
+            Action::OpenEditor => "Open the selected value in $EDITOR",
+            Action::EditInline => "Edit the selected value as JSON",
+            Action::ShowValue => "Show the selected value in full",
+            Action::ScrollLeft => "Scroll the selected value left",
+            Action::ScrollRight => "Scroll the selected value right",
+            Action::Increment => "Increase the selected number",
+            Action::Decrement => "Decrease the selected number",
+            Action::ImportMcp => "Import MCP servers from other apps",
+            Action::TestMcp => "Test the selected MCP server",
+            Action::EditEnv => "Edit the selected MCP server's env",
+            Action::AddItem => "Add an item, permission rule, MCP server, or custom key",
+            Action::DeleteItem => "Delete the selected item",
+            Action::MoveItemUp => "Move the selected rule up",
+            Action::MoveItemDown => "Move the selected rule down",
+            Action::SortRows => "Sort the permission rules",
+            Action::ApplySort => "Save the shown rule order",
+            Action::Duplicate => "Duplicate the selected rule or MCP server",
+            Action::TestPermissions => "Test a tool call against the permission rules",
+            Action::FixType => "Fix the selected value's type",
+            Action::MigrateAll => "Migrate deprecated settings",
+            Action::Copy => "Copy the selected setting, its section, or all settings",
+            Action::Paste => "Paste into the selected setting",
+            Action::Reset => "Reset the selected setting to its default",
+            Action::EditNote => "Edit the selected setting's note",
+            Action::RevealSecrets => "Reveal or hide sensitive values",
+            Action::ToggleSidebar => "Collapse or expand the sidebar",
+            Action::NarrowSidebar => "Narrow the sidebar",
+            Action::WidenSidebar => "Widen the sidebar",
+            Action::ToggleSplit => "Toggle the split view",
+            Action::CycleFilter => "Cycle the settings filter",
+            Action::SwapPane => "Swap the active split pane",
+            Action::ToggleHelp => "Toggle the help pane",
+            Action::TogglePreview => "Toggle the JSON preview of the selected value",
+            Action::ExpandTree => "Expand the selected object or array into a tree",
+            Action::ToggleGroup => "Collapse or expand the selected setting's group",
+            Action::ToggleFavorite => "Pin or unpin the selected setting in Favorites",
+            Action::RemoveShadowedRules => "Remove permission rules that can never apply",
+            Action::ToggleScope => "Switch between global and project scope",
+            Action::EditAtSource => "Switch to the layer the selected value comes from",
+            Action::RestoreBackup => "Restore a backup",
+            Action::Profiles => "Load or save a profile",
+            Action::PermissionPresets => "Add permission rules from a preset",
+            Action::BrowseRegistry => "Add an MCP server from the catalog",
+            Action::AddServerRule => "Add an MCP permission rule for the selected server",
+            Action::ShowTrash => "Restore something deleted this session",
+            Action::ShowRecent => "Show recently changed settings",
+            Action::ExportRedacted => "Write the settings with secrets redacted, for sharing",
+            Action::GitDiff => "Show changes to the settings file since the last git commit",
+            Action::GitCommit => "Commit the saved settings file to git",
+            Action::RestoreLast => "Restore the last deleted key, server, or item",
+            Action::ResetSection => "Reset every setting in this section to its default",
+            Action::ResetAll => "Reset all settings",
+            Action::Search => "Search settings",
+            Action::ShowMessages => "Show message history",
+            Action::CheckWithAmp => "Check the saved file with amp",
+            Action::SwitchFile => "Open another config file",
+            Action::ShowDiff => "Review unsaved changes",
+            Action::Save => "Save",
+            Action::CommandPalette => "Open the command palette",
+            Action::RecordMacro => "Start or stop recording a macro",
+            Action::ReplayMacro => "Replay the recorded macro",
+            Action::EditFile => "Open the whole settings file in $EDITOR",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|a| a.name() == name)
+    }
+
+    /// Whether the action changes the settings (or their notes), and so is
+    /// disabled in read-only mode. Activating and editing instead show the
+    /// selected value there.
+    pub fn mutates(self) -> bool {
+        matches!(
+            self,
+            Action::ImportMcp
+                | Action::EditEnv
+                | Action::AddItem
+                | Action::DeleteItem
+                | Action::MoveItemUp
+                | Action::MoveItemDown
+                | Action::ApplySort
+                | Action::Duplicate
+                | Action::FixType
+                | Action::MigrateAll
+                | Action::RemoveShadowedRules
+                | Action::Paste
+                | Action::Reset
+                | Action::Increment
+                | Action::Decrement
+                | Action::EditNote
+                | Action::RestoreBackup
+                | Action::Profiles
+                | Action::PermissionPresets
+                | Action::BrowseRegistry
+                | Action::AddServerRule
+                | Action::ShowTrash
+                | Action::RestoreLast
+                | Action::ResetSection
+                | Action::ResetAll
+                | Action::EditFile
+                | Action::GitCommit
+                | Action::Save
+        )
+    }
+
+    /// The keys bound to the action when the config file doesn't say otherwise.
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "ctrl+c"],
+            Action::MoveUp => &["up", "k"],
+            Action::MoveDown => &["down", "j"],
+            Action::PageUp => &["pageup"],
+            Action::PageDown => &["pagedown"],
+            Action::SelectFirst => &["home"],
+            Action::SelectLast => &["end"],
+            Action::ToggleFocus => &["tab", "backtab"],
+            Action::SwitchMcpPanel => &["ctrl+up", "ctrl+down"],
+            Action::Activate => &["enter"],
+            Action::OpenEditor => &["e"],
+            Action::EditInline => &["i"],
+            Action::ShowValue => &["space"],
+            Action::ScrollLeft => &["left"],
+            Action::ScrollRight => &["right"],
+            Action::Increment => &["+", "shift+right"],
+            Action::Decrement => &["-", "shift+left"],
+            Action::ImportMcp => &["I"],
+            Action::TestMcp => &["t"],
+            Action::EditEnv => &["$"],
+            Action::AddItem => &["a"],
+            Action::DeleteItem => &["d"],
+            Action::MoveItemUp => &["shift+up", "K"],
+            Action::MoveItemDown => &["shift+down", "J"],
+            Action::SortRows => &["s"],
+            Action::ApplySort => &["O"],
+            Action::Duplicate => &["c"],
+            Action::TestPermissions => &["T"],
+            Action::FixType => &["F"],
+            Action::MigrateAll => &["M"],
+            Action::Copy => &["Y"],
+            Action::Paste => &["P"],
+            Action::Reset => &["r"],
+            Action::EditNote => &["n"],
+            Action::RevealSecrets => &["m"],
+            Action::ToggleSidebar => &["ctrl+b"],
+            Action::NarrowSidebar => &["ctrl+left"],
+            Action::WidenSidebar => &["ctrl+right"],
+            Action::ToggleSplit => &["v"],
+            Action::CycleFilter => &["f"],
+            Action::SwapPane => &["w"],
+            Action::ToggleHelp => &["?"],
+            Action::TogglePreview => &["o"],
+            Action::ExpandTree => &["x"],
+            Action::ToggleGroup => &["z"],
+            Action::ToggleFavorite => &["*"],
+            Action::RemoveShadowedRules => &["R"],
+            Action::ToggleScope => &["S"],
+            Action::EditAtSource => &["g l"],
+            Action::RestoreBackup => &["B"],
+            Action::Profiles => &["p"],
+            Action::PermissionPresets => &["g p"],
+            Action::BrowseRegistry => &["g r"],
+            Action::AddServerRule => &["A"],
+            Action::ShowTrash => &["g t"],
+            Action::ShowRecent => &["g c"],
+            Action::ExportRedacted => &["g x"],
+            Action::GitDiff => &["g h"],
+            Action::GitCommit => &["g C"],
+            Action::RestoreLast => &["u"],
+            Action::ResetSection => &["g R"],
+            Action::ResetAll => &["g D"],
+            Action::Search => &["/"],
+            Action::ShowMessages => &["g m"],
+            Action::CheckWithAmp => &["V"],
+            Action::SwitchFile => &["ctrl+o"],
+            Action::ShowDiff => &["ctrl+d"],
+            Action::Save => &["ctrl+s"],
+            Action::CommandPalette => &[":", "ctrl+p"],
+            Action::RecordMacro => &["ctrl+r"],
+            Action::ReplayMacro => &["@"],
+            Action::EditFile => &["E"],
+        }
+    }
+}
+
+/// A key together with the modifiers that must be held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Builds a binding from a key event. Shift is folded into the character
+    /// for printable keys so `S` matches however the terminal reports it, and
+    /// letters held with Ctrl or Alt are compared case-insensitively, as
+    /// terminals can't tell `Ctrl+s` from `Ctrl+S`.
+    pub fn from_event(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) => {
+                let shift = modifiers.contains(KeyModifiers::SHIFT);
+                let modifiers = modifiers - KeyModifiers::SHIFT;
+                let c = if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+                    c.to_ascii_lowercase()
+                } else if shift {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                };
+                Self {
+                    code: KeyCode::Char(c),
+                    modifiers,
+                }
+            }
+            KeyCode::BackTab => Self {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => Self {
+                code: KeyCode::BackTab,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    /// Parses a key description such as `q`, `S`, `ctrl+s`, `alt+enter`, or `f2`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = spec.split('+').collect();
+        // A trailing "+" is the plus key itself, e.g. "ctrl++".
+        if spec.ends_with("++") || spec == "+" {
+            parts.retain(|p| !p.is_empty());
+            parts.push("+");
+        }
+        let Some((key, mods)) = parts.split_last() else {
+            bail!("empty key");
+        };
+        for m in mods {
+            modifiers |= match m.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier '{m}' in '{spec}'"),
+            };
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            lower => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n @ 1..=12) => KeyCode::F(n),
+                        _ => bail!("unknown key '{key}' in '{spec}'"),
+                    },
+                }
+            }
+        };
+        Ok(Self::from_event(code, modifiers))
+    }
+
+    /// Formats the binding the way the status bar and help show it.
+    pub fn label(self) -> String {
+        let key = match self.code {
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::BackTab => "Shift+Tab".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::Insert => "Ins".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => {
+                c.to_ascii_uppercase().to_string()
+            }
+            KeyCode::Char(c) => c.to_string(),
+            other => format!("{other:?}"),
+        };
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("Ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("Alt+");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("Shift+");
+        }
+        label + &key
+    }
+}
+
+/// Keys pressed one after another, such as `g m`. Most bindings are a
+/// single key.
+pub type KeySequence = Vec<KeyBinding>;
+
+/// Parses a space-separated key sequence such as `g m` or `ctrl+s`.
+fn parse_sequence(spec: &str) -> Result<KeySequence> {
+    let keys = spec
+        .split_whitespace()
+        .map(KeyBinding::parse)
+        .collect::<Result<KeySequence>>()?;
+    if keys.is_empty() {
+        bail!("empty key");
+    }
+    Ok(keys)
+}
+
+/// What the keys pressed so far amount to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    /// They complete a binding.
+    Action(Action),
+    /// They start a longer binding; wait for the next key.
+    Prefix,
+    /// They don't lead anywhere.
+    Unbound,
+}
+
+/// The key bindings in effect for normal mode.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .flat_map(|&action| {
+                action.default_keys().iter().map(move |spec| {
+                    let keys = parse_sequence(spec).expect("default key bindings parse");
+                    (keys, action)
+                })
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Returns the action bound to a single key press, if any.
+    #[cfg(test)]
+    pub fn lookup(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        match self.lookup_sequence(&[KeyBinding::from_event(code, modifiers)]) {
+            Lookup::Action(action) => Some(action),
+            Lookup::Prefix | Lookup::Unbound => None,
+        }
+    }
+
+    /// Looks up the keys pressed so far in a possibly multi-key binding.
+    pub fn lookup_sequence(&self, pressed: &[KeyBinding]) -> Lookup {
+        let mut lookup = Lookup::Unbound;
+        for (keys, action) in &self.bindings {
+            if keys.as_slice() == pressed {
+                return Lookup::Action(*action);
+            }
+            if keys.starts_with(pressed) {
+                lookup = Lookup::Prefix;
+            }
+        }
+        lookup
+    }
+
+    /// Returns the key sequences bound to an action, in the order they were
+    /// given.
+    pub fn keys_for(&self, action: Action) -> Vec<KeySequence> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(keys, _)| keys.clone())
+            .collect()
+    }
+
+    /// Returns the label of an action's first key sequence, or `-` if it is
+    /// unbound.
+    pub fn label(&self, action: Action) -> String {
+        self.keys_for(action)
+            .first()
+            .map(|keys| sequence_label(keys))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Replaces the keys of one action. A key taken from another action is
+    /// moved, so each key triggers exactly one action; an empty list unbinds
+    /// it. Sequences that one of the new keys would shadow, or be shadowed
+    /// by (`g` and `g m`), are taken too.
+    pub fn bind(&mut self, action: Action, specs: &[String]) -> Result<()> {
+        let keys = specs
+            .iter()
+            .map(|spec| parse_sequence(spec))
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("binding {}", action.name()))?;
+        self.bindings.retain(|(existing, a)| {
+            *a != action
+                && !keys
+                    .iter()
+                    .any(|k| k.starts_with(existing) || existing.starts_with(k))
+        });
+        self.bindings.extend(keys.into_iter().map(|k| (k, action)));
+        Ok(())
+    }
+}
+
+/// Formats a key sequence the way the status bar and help show it.
+pub fn sequence_label(keys: &[KeyBinding]) -> String {
+    keys.iter().map(|k| k.label()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_cover_every_action() {
+        let keymap = Keymap::default();
+        for &action in Action::ALL {
+            assert!(!keymap.keys_for(action).is_empty(), "{action:?}");
+            assert_eq!(Action::from_name(action.name()), Some(action));
+            let json = serde_json::to_value(action).unwrap();
+            assert_eq!(json, action.name(), "{action:?}");
+            assert_eq!(serde_json::from_value::<Action>(json).unwrap(), action);
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('s'), KeyModifiers::CONTROL),
+            Some(Action::Save)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('s'), KeyModifiers::NONE),
+            Some(Action::SortRows)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('S'), KeyModifiers::SHIFT),
+            Some(Action::ToggleScope)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::BackTab, KeyModifiers::SHIFT),
+            Some(Action::ToggleFocus)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('d'), KeyModifiers::CONTROL),
+            Some(Action::ShowDiff)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('d'), KeyModifiers::NONE),
+            Some(Action::DeleteItem)
+        );
+    }
+
+    #[test]
+    fn test_parse_and_label() {
+        let b = KeyBinding::parse("Ctrl+S").unwrap();
+        assert_eq!(b.code, KeyCode::Char('s'));
+        assert_eq!(b.label(), "Ctrl+S");
+        assert_eq!(KeyBinding::parse("f2").unwrap().code, KeyCode::F(2));
+        assert_eq!(
+            KeyBinding::parse("shift+b").unwrap(),
+            KeyBinding::parse("B").unwrap()
+        );
+        assert_eq!(
+            KeyBinding::parse("shift+tab").unwrap().code,
+            KeyCode::BackTab
+        );
+        assert_eq!(KeyBinding::parse("alt+enter").unwrap().label(), "Alt+Enter");
+        assert_eq!(KeyBinding::parse("up").unwrap().label(), "↑");
+        assert_eq!(
+            KeyBinding::parse("ctrl++").unwrap(),
+            KeyBinding {
+                code: KeyCode::Char('+'),
+                modifiers: KeyModifiers::CONTROL
+            }
+        );
+        assert!(KeyBinding::parse("hyper+x").is_err());
+        assert!(KeyBinding::parse("f13").is_err());
+        assert!(KeyBinding::parse("").is_err());
+    }
+
+    #[test]
+    fn test_bind_replaces_and_steals() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Action::MoveUp, &["up".to_string()]).unwrap();
+        assert_eq!(keymap.lookup(KeyCode::Char('k'), KeyModifiers::NONE), None);
+
+        keymap.bind(Action::Save, &["w".to_string()]).unwrap();
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('w'), KeyModifiers::NONE),
+            Some(Action::Save)
+        );
+        assert!(keymap.keys_for(Action::SwapPane).is_empty());
+        assert_eq!(keymap.label(Action::SwapPane), "-");
+
+        assert!(keymap.bind(Action::Quit, &["nope".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_key_sequences() {
+        let mut keymap = Keymap::default();
+        let g = KeyBinding::parse("g").unwrap();
+        let m = KeyBinding::parse("m").unwrap();
+        assert_eq!(keymap.lookup_sequence(&[g]), Lookup::Prefix);
+        assert_eq!(
+            keymap.lookup_sequence(&[g, m]),
+            Lookup::Action(Action::ShowMessages)
+        );
+        assert_eq!(keymap.lookup_sequence(&[g, g]), Lookup::Unbound);
+        assert_eq!(keymap.label(Action::ShowMessages), "g m");
+
+        // Binding `g` on its own takes the sequences it would shadow.
+        keymap
+            .bind(Action::SelectFirst, &["g".to_string()])
+            .unwrap();
+        assert_eq!(
+            keymap.lookup_sequence(&[g]),
+            Lookup::Action(Action::SelectFirst)
+        );
+        assert!(keymap.keys_for(Action::ShowMessages).is_empty());
+    }
+}
@@ -0,0 +1,361 @@
+//! Whole-file checks for a settings file, reported by `volt validate`.
+
+use serde_json::Value;
+
+use crate::app::{McpPermissionLevel, PermissionLevel};
+use crate::config::Config;
+use crate::matcher;
+use crate::settings::{self, ObjectSchema, SettingType};
+use crate::tools;
+
+/// How serious a finding is. Only errors make validation fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// One problem found in the settings file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// The setting, or a path into it such as `amp.permissions[2]`.
+    pub path: String,
+    pub message: String,
+}
+
+impl Finding {
+    fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks every key in the file: types of known settings, unknown keys, and
+/// the structure of permission rules and MCP server entries.
+pub fn lint(config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let servers: Vec<String> = config
+        .get("amp.mcpServers")
+        .as_object()
+        .map(|obj| obj.keys().cloned().collect())
+        .unwrap_or_default();
+
+    for (key, value) in config.entries() {
+        if let Some(migration) = settings::migration_for(key) {
+            let message = format!("deprecated, {}; run `volt migrate`", migration.describe());
+            findings.push(Finding::warning(key, message));
+            continue;
+        }
+        let Some(def) = settings::get_setting_def(key) else {
+            findings.push(Finding::warning(key, "not a known Amp setting"));
+            continue;
+        };
+        if let Err(e) = Config::validate_value(key, value) {
+            findings.push(Finding::error(key, e.to_string()));
+        }
+        match key.as_str() {
+            "amp.permissions" => lint_permissions(value, &servers, &mut findings),
+            "amp.mcpServers" => lint_mcp_servers(value, &mut findings),
+            "amp.mcpPermissions" => lint_mcp_permissions(value, &mut findings),
+            _ => {
+                if let Some(schema) = &def.object_schema {
+                    lint_object(key, value, schema, &mut findings);
+                }
+            }
+        }
+    }
+
+    findings.sort_by_key(|f| f.severity);
+    findings
+}
+
+fn lint_permissions(value: &Value, servers: &[String], findings: &mut Vec<Finding>) {
+    let Some(rules) = value.as_array() else {
+        return;
+    };
+    for (i, rule) in rules.iter().enumerate() {
+        let path = format!("amp.permissions[{i}]");
+        if let Some(e) = matcher::rule_error(rule) {
+            findings.push(Finding::error(&path, format!("invalid pattern in {e}")));
+        }
+        let Some(rule) = rule.as_object() else {
+            continue;
+        };
+        match rule.get("tool") {
+            Some(Value::String(tool)) => {
+                if !tools::is_known_tool(tool, servers) {
+                    findings.push(Finding::warning(
+                        &path,
+                        format!("tool \"{tool}\" is not a built-in or configured MCP tool"),
+                    ));
+                }
+            }
+            Some(_) => findings.push(Finding::error(&path, "\"tool\" must be a string")),
+            None => findings.push(Finding::error(&path, "missing \"tool\"")),
+        }
+        let actions: Vec<&str> = PermissionLevel::ALL.iter().map(|l| l.label()).collect();
+        check_action(&path, rule.get("action"), &actions, findings);
+        if rule.get("action").and_then(Value::as_str) == Some("delegate")
+            && !rule.get("to").is_some_and(Value::is_string)
+        {
+            findings.push(Finding::error(
+                &path,
+                "delegate rules need a \"to\" program name",
+            ));
+        }
+        if rule.get("matches").is_some_and(|m| !m.is_object()) {
+            findings.push(Finding::error(&path, "\"matches\" must be an object"));
+        }
+    }
+}
+
+fn lint_mcp_servers(value: &Value, findings: &mut Vec<Finding>) {
+    let Some(servers) = value.as_object() else {
+        return;
+    };
+    for (name, server) in servers {
+        let path = format!("amp.mcpServers.{name}");
+        let Some(server) = server.as_object() else {
+            findings.push(Finding::error(&path, "server config must be an object"));
+            continue;
+        };
+        match (server.get("command"), server.get("url")) {
+            (None, None) => {
+                findings.push(Finding::error(&path, "needs a \"command\" or a \"url\""));
+            }
+            (Some(c), _) if !c.is_string() => {
+                findings.push(Finding::error(&path, "\"command\" must be a string"));
+            }
+            (_, Some(u)) if !u.is_string() => {
+                findings.push(Finding::error(&path, "\"url\" must be a string"));
+            }
+            _ => {}
+        }
+        if let Some(args) = server.get("args") {
+            let ok = args
+                .as_array()
+                .is_some_and(|a| a.iter().all(Value::is_string));
+            if !ok {
+                findings.push(Finding::error(
+                    &path,
+                    "\"args\" must be an array of strings",
+                ));
+            }
+        }
+        for field in ["env", "headers"] {
+            if let Some(map) = server.get(field) {
+                let ok = map
+                    .as_object()
+                    .is_some_and(|m| m.values().all(Value::is_string));
+                if !ok {
+                    findings.push(Finding::error(
+                        &path,
+                        format!("\"{field}\" must map names to strings"),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn lint_mcp_permissions(value: &Value, findings: &mut Vec<Finding>) {
+    let Some(rules) = value.as_array() else {
+        return;
+    };
+    let actions: Vec<&str> = McpPermissionLevel::ALL.iter().map(|l| l.label()).collect();
+    for (i, rule) in rules.iter().enumerate() {
+        let path = format!("amp.mcpPermissions[{i}]");
+        if let Some(e) = matcher::rule_error(rule) {
+            findings.push(Finding::error(&path, format!("invalid pattern in {e}")));
+        }
+        let Some(rule) = rule.as_object() else {
+            continue;
+        };
+        if !rule.get("matches").is_some_and(Value::is_object) {
+            findings.push(Finding::error(&path, "needs a \"matches\" object"));
+        }
+        check_action(&path, rule.get("action"), &actions, findings);
+    }
+}
+
+/// Checks the fields of an object setting against its schema, descending
+/// into fields that have schemas of their own. Keys the schema doesn't
+/// declare are left alone.
+fn lint_object(path: &str, value: &Value, schema: &ObjectSchema, findings: &mut Vec<Finding>) {
+    let Some(entries) = value.as_object() else {
+        return;
+    };
+    for (name, entry) in entries {
+        let Some(field) = schema.field(name) else {
+            continue;
+        };
+        let path = format!("{path}.{name}");
+        if field.accepts(entry) {
+            if let Some(nested) = &field.object {
+                lint_object(&path, entry, nested, findings);
+            }
+            continue;
+        }
+        let message = match (field.field_type, entry) {
+            (SettingType::StringEnum, Value::String(s)) => format!(
+                "unknown value \"{s}\", expected one of: {}",
+                field.enum_options.unwrap_or_default().join(", ")
+            ),
+            (SettingType::StringEnum | SettingType::String, _) => "must be a string".into(),
+            (SettingType::Boolean, _) => "must be true or false".into(),
+            (SettingType::Number, _) => "must be a number".into(),
+            (other, _) => format!("must be {}", other.label()),
+        };
+        findings.push(Finding::error(path, message));
+    }
+}
+
+fn check_action(path: &str, action: Option<&Value>, allowed: &[&str], findings: &mut Vec<Finding>) {
+    match action {
+        Some(Value::String(a)) if allowed.contains(&a.as_str()) => {}
+        Some(Value::String(a)) => findings.push(Finding::error(
+            path,
+            format!(
+                "unknown action \"{a}\", expected one of: {}",
+                allowed.join(", ")
+            ),
+        )),
+        Some(_) => findings.push(Finding::error(path, "\"action\" must be a string")),
+        None => findings.push(Finding::error(path, "missing \"action\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn lint_str(json: &str) -> Vec<Finding> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, json).unwrap();
+        lint(&Config::load(&path).unwrap())
+    }
+
+    fn paths(findings: &[Finding], severity: Severity) -> Vec<&str> {
+        findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .map(|f| f.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_file() {
+        let findings = lint_str(
+            r#"{
+                "amp.showCosts": false,
+                "amp.permissions": [{"tool": "Bash", "action": "allow"}],
+                "amp.mcpServers": {"gh": {"command": "gh-mcp", "args": ["--x"]}},
+                "amp.mcpPermissions": [{"matches": {"command": "gh-mcp"}, "action": "allow"}]
+            }"#,
+        );
+        assert!(findings.is_empty(), "{findings:?}");
+    }
+
+    #[test]
+    fn test_wrong_types_and_unknown_keys() {
+        let findings = lint_str(r#"{"amp.showCosts": "yes", "amp.mystery": 1}"#);
+        assert_eq!(paths(&findings, Severity::Error), vec!["amp.showCosts"]);
+        assert_eq!(paths(&findings, Severity::Warning), vec!["amp.mystery"]);
+    }
+
+    #[test]
+    fn test_object_entries_follow_schema() {
+        let findings = lint_str(
+            r#"{"amp.defaultVisibility": {"github.com/a": "team", "github.com/b": "secret", "c": 1}}"#,
+        );
+        assert_eq!(
+            paths(&findings, Severity::Error),
+            vec![
+                "amp.defaultVisibility.github.com/b",
+                "amp.defaultVisibility.c"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deprecated_keys() {
+        let findings = lint_str(r#"{"amp.hideCosts": true}"#);
+        assert_eq!(
+            findings[0].message,
+            "deprecated, replaced by amp.showCosts (inverted); run `volt migrate`"
+        );
+    }
+
+    #[test]
+    fn test_permission_rules() {
+        let findings = lint_str(
+            r#"{"amp.permissions": [
+                {"action": "allow"},
+                {"tool": "Bash", "action": "maybe"},
+                {"tool": "Bash", "action": "delegate"},
+                {"tool": "Nope", "action": "ask"},
+                {"tool": "Bash", "matches": {"cmd": "/(/"}, "action": "ask"}
+            ]}"#,
+        );
+        assert_eq!(
+            paths(&findings, Severity::Error),
+            vec![
+                "amp.permissions[0]",
+                "amp.permissions[1]",
+                "amp.permissions[2]",
+                "amp.permissions[4]"
+            ]
+        );
+        assert_eq!(
+            paths(&findings, Severity::Warning),
+            vec!["amp.permissions[3]"]
+        );
+    }
+
+    #[test]
+    fn test_mcp_entries() {
+        let findings = lint_str(
+            r#"{
+                "amp.mcpServers": {
+                    "a": {},
+                    "b": {"command": "x", "args": "--flag"},
+                    "c": {"url": "https://x", "headers": {"n": 1}}
+                },
+                "amp.mcpPermissions": [{"action": "allow"}, {"matches": {}, "action": "ask"}]
+            }"#,
+        );
+        assert_eq!(
+            paths(&findings, Severity::Error),
+            vec![
+                "amp.mcpServers.a",
+                "amp.mcpServers.b",
+                "amp.mcpServers.c",
+                "amp.mcpPermissions[0]",
+                "amp.mcpPermissions[1]"
+            ]
+        );
+    }
+}
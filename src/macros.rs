@@ -0,0 +1,132 @@
+//! Recording normal-mode actions to replay them as a macro, and logging
+//! every action dispatched to a file that can be attached to bug reports.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use crate::keymap::Action;
+
+/// The macro being recorded, if any, and the last one finished.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    recording: Option<Vec<Action>>,
+    recorded: Vec<Action>,
+}
+
+impl Recorder {
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts a new recording; the previous macro is kept until it ends.
+    pub fn start(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    /// Ends the recording, making it the macro to replay, and returns how
+    /// many actions it holds.
+    pub fn stop(&mut self) -> usize {
+        if let Some(actions) = self.recording.take() {
+            self.recorded = actions;
+        }
+        self.recorded.len()
+    }
+
+    /// Adds an action to the recording in progress. Starting, stopping, and
+    /// replaying macros aren't part of one, nor is opening the command
+    /// palette: the command picked there is recorded instead.
+    pub fn record(&mut self, action: Action) {
+        if matches!(
+            action,
+            Action::RecordMacro | Action::ReplayMacro | Action::CommandPalette
+        ) {
+            return;
+        }
+        if let Some(actions) = &mut self.recording {
+            actions.push(action);
+        }
+    }
+
+    /// The macro to replay, oldest action first.
+    pub fn recorded(&self) -> &[Action] {
+        &self.recorded
+    }
+}
+
+/// A file with one JSON line per dispatched action:
+/// `{"at": <unix seconds>, "action": "move_down"}`.
+#[derive(Debug)]
+pub struct ActionLog {
+    file: File,
+}
+
+impl ActionLog {
+    /// Opens the log for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening action log {}", path.display()))?;
+        Ok(Self { file })
+    }
+
+    pub fn write(&mut self, action: Action, at: SystemTime) -> io::Result<()> {
+        let secs = at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        writeln!(self.file, "{}", json!({"at": secs, "action": action}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder() {
+        let mut recorder = Recorder::default();
+        recorder.record(Action::MoveDown);
+        assert!(recorder.recorded().is_empty());
+
+        recorder.start();
+        assert!(recorder.is_recording());
+        recorder.record(Action::MoveDown);
+        recorder.record(Action::ReplayMacro);
+        recorder.record(Action::CommandPalette);
+        recorder.record(Action::Reset);
+        assert_eq!(recorder.stop(), 2);
+        assert_eq!(recorder.recorded(), [Action::MoveDown, Action::Reset]);
+
+        // A new recording replaces the macro only once it ends.
+        recorder.start();
+        recorder.record(Action::Save);
+        assert_eq!(recorder.recorded().len(), 2);
+        recorder.stop();
+        assert_eq!(recorder.recorded(), [Action::Save]);
+    }
+
+    #[test]
+    fn test_action_log_appends_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("actions.jsonl");
+        let mut log = ActionLog::open(&path).unwrap();
+        log.write(Action::MoveDown, UNIX_EPOCH).unwrap();
+        drop(log);
+        let mut log = ActionLog::open(&path).unwrap();
+        log.write(Action::Save, UNIX_EPOCH).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"at":0,"action":"move_down"}"#,
+                r#"{"at":0,"action":"save"}"#
+            ]
+        );
+    }
+}
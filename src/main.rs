@@ -1,129 +1,716 @@
 //! Volt — TUI Settings Editor for Amp.
 
+mod amp;
 mod app;
+mod backup;
+mod cli;
+mod clipboard;
 mod config;
+mod diff;
+mod doctor;
 mod editor;
+mod events;
+mod fuzzy;
+mod git;
+mod jsonc;
+mod keymap;
+mod lint;
+mod macros;
+mod matcher;
+mod mcp_client;
+mod mcp_import;
+mod messages;
+mod notes;
+mod permissions;
+mod prefs;
+mod presets;
+mod profile;
+mod prompt;
+mod registry;
+mod rule_form;
+mod schema;
+mod script;
+mod server_form;
+mod session;
 mod settings;
+mod sync;
+mod textarea;
+mod theme;
+mod tools;
+mod trash;
+mod tree;
 mod ui;
 
-use std::io;
+use std::io::{self, IsTerminal, Write};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use clap::Parser;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event,
+    KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use crossterm::ExecutableCommand;
+use ratatui::layout::Rect;
 use ratatui::prelude::CrosstermBackend;
 use ratatui::Terminal;
 
-use app::{App, EditorRequest, Focus, InputMode};
-use config::Config;
+use app::{App, EditorRequest, InputMode, MergeSide};
+use config::{Config, PathSource};
+use editor::{EditOutcome, EditSession};
+use events::{AppEvent, Events};
+use keymap::{Action, KeyBinding, Lookup};
+use macros::ActionLog;
+use mcp_import::Source;
+use prefs::Prefs;
+use rule_form::{Field, RuleForm};
+use server_form::ServerForm;
+use session::Session;
+use settings::Section;
+use textarea::{TextArea, TextInput};
+use theme::Theme;
 
 /// Volt — TUI Settings Editor for Amp
 #[derive(Parser, Debug)]
 #[command(name = "volt", version, about)]
 struct Cli {
     /// Path to the settings.json file (overrides default)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     config: Option<PathBuf>,
+
+    /// Print the settings file volt would open, and why, then exit
+    #[arg(long)]
+    which: bool,
+
+    /// Show key count, file size, and last-saved time in the footer
+    #[arg(long)]
+    stats: bool,
+
+    /// Number of backups to keep when saving (0 disables backups)
+    #[arg(long, default_value_t = backup::DEFAULT_RETENTION)]
+    backups: usize,
+
+    /// JSON Schema file or URL describing Amp's settings, merged with the built-in list
+    #[arg(long)]
+    schema: Option<String>,
+
+    /// Color theme: dark, light, solarized, or high-contrast (overrides the volt config)
+    #[arg(long)]
+    theme: Option<String>,
+
+    /// Print subcommand output as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Browse without changing anything: edits are disabled and saving fails
+    #[arg(long, global = true)]
+    read_only: bool,
+
+    /// Save changes a few seconds after they're made, and on quit instead of asking
+    #[arg(long)]
+    autosave: bool,
+
+    /// Start in General instead of where the last session left off; pinned
+    /// Favorites are kept
+    #[arg(long)]
+    fresh: bool,
+
+    /// Start with this setting selected, e.g. amp.mcpServers
+    #[arg(long, value_name = "KEY", conflicts_with = "section")]
+    key: Option<String>,
+
+    /// Start in this section (general, permissions, tools, mcps, experimental, advanced)
+    #[arg(long)]
+    section: Option<String>,
+
+    /// Open the setting given with --key in $EDITOR right away
+    #[arg(long, requires = "key")]
+    edit: bool,
+
+    /// Append every action taken to this file as JSON lines, e.g. to attach to a bug report
+    #[arg(long, value_name = "PATH")]
+    action_log: Option<PathBuf>,
+
+    /// Reject JSON5 syntax and trailing commas instead of accepting them and saving plain JSON
+    #[arg(long, global = true)]
+    strict: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Non-interactive operations; without one, the TUI starts.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the effective value of a setting
+    Get { key: String },
+    /// Set a setting and save
+    Set {
+        key: String,
+        /// The new value; negative numbers such as -5 need no `--`
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+    /// Print a setting's value as JSON, then replace it with JSON from stdin (or $EDITOR on a terminal) and save
+    Edit { key: String },
+    /// Remove a setting so it falls back to its default, then save
+    Unset { key: String },
+    /// List settings and their effective values
+    List {
+        /// Only list one section (general, permissions, tools, mcps, experimental, advanced)
+        section: Option<String>,
+    },
+    /// Print the settings file as JSON
+    Export {
+        /// Replace sensitive values (tokens, secret-looking env variables) with "<redacted>", for sharing
+        #[arg(long)]
+        redact: bool,
+    },
+    /// Print every known setting's key, section, type, default, options, and description as TOML (or JSON with --json)
+    Schema,
+    /// Check the whole file and report problems; exits nonzero on errors
+    Validate,
+    /// Check the settings file, MCP server commands, skill paths, and $EDITOR; exits nonzero on failures
+    Doctor,
+    /// Rewrite deprecated and renamed settings to their replacements, keeping a backup
+    Migrate {
+        /// Only report what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show which keys differ from the defaults or from another settings file
+    Diff {
+        /// `defaults` or the path of another settings file
+        #[arg(long, default_value = "defaults")]
+        against: String,
+        /// Output layout
+        #[arg(long, value_enum, default_value = "table")]
+        format: cli::DiffFormat,
+        /// When to color the output: auto, always, or never
+        #[arg(long, default_value = "auto")]
+        color: String,
+    },
+    /// Save, load, or list settings profiles (stored in ~/.config/volt/profiles)
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+    /// Apply a JSON list of operations (set, unset, add-array-item, add-mcp-server), saving only if all succeed
+    Script {
+        /// File holding the operations, or `-` for stdin
+        file: PathBuf,
+    },
+    /// Import MCP servers from Claude Desktop, Cursor, or VS Code
+    ImportMcp {
+        /// Tool to import from: claude, cursor, or vscode
+        #[arg(long)]
+        from: String,
+        /// Config file to read (defaults to the tool's usual location)
+        path: Option<PathBuf>,
+        /// Only import these servers (repeatable)
+        #[arg(long = "server")]
+        servers: Vec<String>,
+        /// Replace servers that are already configured
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// Share the settings file across machines through a gist, an HTTPS URL, or a path
+    Sync {
+        #[command(subcommand)]
+        action: SyncCommand,
+    },
+}
+
+/// Directions to sync the settings file in.
+#[derive(Subcommand, Debug)]
+enum SyncCommand {
+    /// Copy the settings file to the remote
+    Push {
+        /// gist:<id>, an https:// URL, or a path (defaults to sync_remote in ~/.config/volt/config.toml)
+        #[arg(long)]
+        remote: Option<String>,
+        /// Overwrite the remote even if it changed since it was last synced from here
+        #[arg(long)]
+        force: bool,
+    },
+    /// Replace the settings file with the remote's copy, keeping a backup
+    Pull {
+        /// gist:<id>, an https:// URL, or a path (defaults to sync_remote in ~/.config/volt/config.toml)
+        #[arg(long)]
+        remote: Option<String>,
+        /// Overwrite the settings file even if it changed since it was last synced
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Operations on settings profiles.
+#[derive(Subcommand, Debug)]
+enum ProfileCommand {
+    /// Snapshot the current settings as a profile
+    Save {
+        name: String,
+        /// Only include these sections (repeatable); loading then leaves other sections alone
+        #[arg(long = "section")]
+        sections: Vec<String>,
+    },
+    /// Replace the settings a profile covers with its values, then save
+    Load { name: String },
+    /// List saved profiles
+    List,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let config_path = match cli.config {
-        Some(p) => p,
-        None => Config::default_path()?,
+    let (config_path, path_source) = match cli.config {
+        Some(p) => (p, PathSource::Flag),
+        None => Config::locate()?,
     };
+    if cli.which {
+        let mut out = io::stdout().lock();
+        return cli::ignore_broken_pipe(cli::which(&config_path, path_source, cli.json, &mut out));
+    }
+
+    // The doctor reports a file that doesn't parse instead of stopping at it.
+    if let Some(Command::Doctor) = cli.command {
+        let mut out = io::stdout().lock();
+        return match cli::doctor(&config_path, cli.strict, cli.json, &mut out) {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                let _ = out.flush();
+                std::process::exit(1);
+            }
+            Err(e) => cli::ignore_broken_pipe(Err(e)),
+        };
+    }
+
+    let mut config = Config::load_with(&config_path, cli.strict)?;
+    config.set_backup_retention(cli.backups);
+    config.set_read_only(cli.read_only);
+    // Preferences that don't load are reported by the TUI further down.
+    let format = Prefs::default_path()
+        .and_then(|path| Prefs::load(&path))
+        .map(|prefs| prefs.format)
+        .unwrap_or_default();
+    config.set_format(format.clone());
+
+    // A schema that can't be loaded shouldn't lock anyone out of their settings,
+    // so the TUI reports it instead of exiting; scripts get a hard error.
+    let schema_status = match &cli.schema {
+        Some(source) => match schema::load(source) {
+            Ok(defs) => {
+                let added = settings::register_schema(defs);
+                Some(format!("Loaded {added} additional settings from schema"))
+            }
+            Err(e) if cli.command.is_some() => return Err(e),
+            Err(e) => Some(format!("Schema unavailable: {e:#}")),
+        },
+        None => None,
+    };
+
+    if let Some(command) = cli.command {
+        let mut out = io::stdout().lock();
+        let result = match command {
+            Command::Get { key } => cli::get(&config, &key, cli.json, &mut out),
+            Command::Set { key, value } => cli::set(&mut config, &key, &value, cli.json, &mut out),
+            Command::Unset { key } => cli::unset(&mut config, &key, cli.json, &mut out),
+            Command::Edit { key } if io::stdin().is_terminal() => {
+                let editor = Prefs::default_path()
+                    .and_then(|path| Prefs::load(&path))
+                    .ok()
+                    .and_then(|prefs| prefs.editor);
+                cli::edit_in_editor(&mut config, &key, editor.as_deref(), cli.json, &mut out)
+            }
+            Command::Edit { key } => cli::edit_piped(
+                &mut config,
+                &key,
+                || Ok(io::read_to_string(io::stdin())?),
+                cli.json,
+                &mut out,
+            ),
+            Command::List { section } => cli::list(&config, section.as_deref(), cli.json, &mut out),
+            Command::Migrate { dry_run } => cli::migrate(&mut config, dry_run, cli.json, &mut out),
+            Command::Diff {
+                against,
+                format,
+                color,
+            } => {
+                let against = if against == "defaults" {
+                    cli::Against::Defaults
+                } else {
+                    let path = PathBuf::from(&against);
+                    if !path.exists() {
+                        anyhow::bail!("{} does not exist", path.display());
+                    }
+                    cli::Against::File(Box::new(Config::load_with(&path, cli.strict)?))
+                };
+                let color = match color.as_str() {
+                    "always" => true,
+                    "never" => false,
+                    "auto" => out.is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+                    other => {
+                        anyhow::bail!("unknown --color '{other}' (use auto, always, or never)")
+                    }
+                };
+                cli::diff(&config, &against, format, color, cli.json, &mut out)
+            }
+            Command::Sync { action } => {
+                let (push, remote, force) = match action {
+                    SyncCommand::Push { remote, force } => (true, remote, force),
+                    SyncCommand::Pull { remote, force } => (false, remote, force),
+                };
+                let remote = match remote {
+                    Some(remote) => remote,
+                    None => Prefs::load(&Prefs::default_path()?)?
+                        .sync_remote
+                        .context("no remote given; pass --remote or set sync_remote in ~/.config/volt/config.toml")?,
+                };
+                let remote = sync::Remote::parse(&remote)?;
+                let state = sync::State::default_path()?;
+                cli::sync(
+                    &mut config,
+                    &remote,
+                    &state,
+                    push,
+                    force,
+                    cli.json,
+                    &mut out,
+                )
+            }
+            Command::Profile { action } => {
+                let dir = profile::default_dir()?;
+                match action {
+                    ProfileCommand::Save { name, sections } => {
+                        cli::profile_save(&config, &dir, &name, &sections, cli.json, &mut out)
+                    }
+                    ProfileCommand::Load { name } => {
+                        cli::profile_load(&mut config, &dir, &name, cli.json, &mut out)
+                    }
+                    ProfileCommand::List => cli::profile_list(&dir, cli.json, &mut out),
+                }
+            }
+            Command::ImportMcp {
+                from,
+                path,
+                servers,
+                overwrite,
+            } => {
+                let source = Source::from_name(&from).with_context(|| {
+                    format!("unknown source '{from}' (use claude, cursor, or vscode)")
+                })?;
+                let path = match path.or_else(|| source.default_path()) {
+                    Some(path) => path,
+                    None => anyhow::bail!("no default config path for {}", source.label()),
+                };
+                cli::import_mcp(
+                    &mut config,
+                    source,
+                    &path,
+                    &servers,
+                    overwrite,
+                    cli.json,
+                    &mut out,
+                )
+            }
+            Command::Script { file } => {
+                let text = if file.as_os_str() == "-" {
+                    io::read_to_string(io::stdin())?
+                } else {
+                    std::fs::read_to_string(&file)
+                        .with_context(|| format!("failed to read {}", file.display()))?
+                };
+                cli::script(&mut config, &text, cli.json, &mut out)
+            }
+            Command::Schema => cli::schema(cli.json, &mut out),
+            Command::Export { redact } => cli::export(&config, redact, &mut out),
+            Command::Doctor => unreachable!("the doctor runs before the config is loaded"),
+            Command::Validate => match cli::validate(&config, cli.json, &mut out) {
+                Ok(true) => Ok(()),
+                Ok(false) => {
+                    let _ = out.flush();
+                    std::process::exit(1);
+                }
+                Err(e) => Err(e),
+            },
+        };
+        return cli::ignore_broken_pipe(result);
+    }
 
-    let config = Config::load(&config_path)?;
+    config.set_env_overrides(std::env::vars());
     let mut app = App::new(config);
+    app.refresh_git();
+    let project_path = std::env::current_dir()?.join(".amp").join("settings.json");
+    if project_path.exists() && project_path != config_path {
+        let mut project = Config::load_with(&project_path, cli.strict)?;
+        project.set_backup_retention(cli.backups);
+        project.set_format(format);
+        project.set_read_only(cli.read_only);
+        project.set_env_overrides(std::env::vars());
+        app.attach_project(project);
+    }
+    if schema_status.is_some() {
+        app.status_message = schema_status;
+    }
+    // Like a bad schema, a broken preferences file falls back to defaults
+    // rather than keeping volt from starting.
+    let prefs = Prefs::default_path().and_then(|path| {
+        let prefs = Prefs::load(&path)?;
+        app.prefs_path = Some(path);
+        Ok(prefs)
+    });
+    match prefs {
+        Ok(prefs) => {
+            app.keymap = prefs.keymap;
+            app.theme = prefs.theme;
+            app.sidebar = prefs.sidebar;
+            app.autosave = prefs.autosave;
+            app.editor = prefs.editor;
+            app.nested_keys = prefs.nested_keys;
+            if let Some(url) = prefs.registry_url {
+                app.registry_url = url;
+            }
+        }
+        Err(e) => app.status_message = Some(format!("Preferences ignored: {e:#}")),
+    }
+    if let Some(name) = &cli.theme {
+        app.theme = Theme::by_name(name)?;
+    }
+    app.show_stats = cli.stats;
+    app.autosave |= cli.autosave;
+    if let Some(path) = &cli.action_log {
+        app.action_log = Some(ActionLog::open(path)?);
+    }
+    let session_path = Session::default_path().ok();
+    if let Some(path) = session_path.as_deref() {
+        match Session::load(path) {
+            Ok(session) if cli.fresh => app.restore_history(&session),
+            Ok(session) => app.restore_session(&session),
+            Err(e) => app.status_message = Some(format!("Last session ignored: {e:#}")),
+        }
+    }
+    if let Some(key) = &cli.key {
+        if !app.go_to_key(key) {
+            bail!("no setting '{key}' (it isn't a known key or in the file)");
+        }
+    }
+    if let Some(label) = &cli.section {
+        let section =
+            Section::from_label(label).with_context(|| format!("unknown section '{label}'"))?;
+        app.go_to_section(section);
+    }
+    let first_edit = if cli.edit {
+        app.dispatch(Action::OpenEditor)
+    } else {
+        None
+    };
+    app.offer_scaffold();
 
     // Set up terminal
     enable_raw_mode()?;
     io::stdout().execute(EnterAlternateScreen)?;
+    io::stdout().execute(EnableMouseCapture)?;
+    io::stdout().execute(EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_loop(&mut terminal, &mut app);
+    let result = run_loop(&mut terminal, &mut app, first_edit);
 
     // Restore terminal
     disable_raw_mode()?;
+    io::stdout().execute(DisableBracketedPaste)?;
+    io::stdout().execute(DisableMouseCapture)?;
     io::stdout().execute(LeaveAlternateScreen)?;
 
+    // Another file opened with Ctrl+O has sections of its own; don't
+    // carry them over to the settings file.
+    if let Some(path) = session_path.filter(|_| app.is_settings_file()) {
+        if let Err(e) = app.session().save(&path) {
+            eprintln!("volt: couldn't remember this session: {e:#}");
+        }
+    }
+
     result
 }
 
-fn run_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
+/// How often the loop wakes without input, to notice changes made to the
+/// file by other programs and expire old status messages.
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+fn run_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    first_edit: Option<EditorRequest>,
+) -> Result<()> {
+    let events = Events::start(TICK_INTERVAL);
+    app.tasks = events.tasks();
+    if let Some(req) = first_edit {
+        edit_with_input_paused(terminal, app, &events, &req)?;
+    }
     loop {
+        let size = terminal.size()?;
+        app.layout = ui::screen_layout(Rect::new(0, 0, size.width, size.height), app);
+        app.keep_selection_visible();
+        app.log_status();
+        app.note_changes(Instant::now());
         terminal.draw(|frame| ui::render(frame, app))?;
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind != KeyEventKind::Press {
-                continue;
-            }
+        let editor_req = match events.next()? {
+            AppEvent::Input(event) => match event? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    // Clear status message on any key press
+                    app.status_message = None;
 
-            // Clear status message on any key press
-            app.status_message = None;
-
-            if app.is_editing() {
-                let editor_req = handle_modal_input(app, key.code);
-                if let Some(req) = editor_req {
-                    run_editor(terminal, app, &req)?;
+                    if app.is_editing() {
+                        handle_modal_input(app, key.code, key.modifiers)
+                    } else {
+                        handle_normal_input(app, key.code, key.modifiers)
+                    }
                 }
-            } else {
-                let editor_req = handle_normal_input(app, key.code, key.modifiers);
-                if let Some(req) = editor_req {
-                    run_editor(terminal, app, &req)?;
+                Event::Mouse(mouse) => handle_mouse(app, mouse),
+                Event::Paste(text) => {
+                    app.status_message = None;
+                    app.insert_pasted(&text);
+                    None
                 }
+                _ => None,
+            },
+            AppEvent::Tick => {
+                app.tick(Instant::now());
+                None
+            }
+            AppEvent::Task(result) => {
+                app.finish_task(result);
+                None
+            }
+        };
+        if let Some(req) = editor_req {
+            edit_with_input_paused(terminal, app, &events, &req)?;
+        }
+        if let Some(path) = app.take_file_edit() {
+            app.note_changes(Instant::now());
+            app.flush_autosave();
+            events.pause_input();
+            let result = suspend_tui(terminal, || editor::open(&path, app.editor.as_deref()));
+            events.resume_input();
+            match result? {
+                Ok(()) => app.finish_file_edit(),
+                Err(e) => app.status_message = Some(format!("Editor error: {e}")),
             }
         }
 
         if app.should_quit {
             return Ok(());
         }
+        app.check_external_change();
     }
 }
 
-/// Suspends the TUI, runs `$EDITOR`, and applies the result.
-fn run_editor(
+/// Runs `$EDITOR` for a request with terminal input handed over to it,
+/// saving first if autosave has changes waiting.
+fn edit_with_input_paused(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
-    request: &EditorRequest,
+    events: &Events,
+    req: &EditorRequest,
 ) -> Result<()> {
-    // Suspend TUI
+    app.note_changes(Instant::now());
+    app.flush_autosave();
+    events.pause_input();
+    let result = run_editor(terminal, app, req);
+    events.resume_input();
+    result
+}
+
+/// Leaves the alternate screen and raw mode while `run` has the terminal,
+/// then restores the TUI.
+fn suspend_tui<T>(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    run: impl FnOnce() -> T,
+) -> Result<T> {
     disable_raw_mode()?;
+    io::stdout().execute(DisableBracketedPaste)?;
+    io::stdout().execute(DisableMouseCapture)?;
     io::stdout().execute(LeaveAlternateScreen)?;
 
-    let result = editor::edit_value_in_editor(&request.value);
+    let result = run();
 
-    // Restore TUI
     enable_raw_mode()?;
     io::stdout().execute(EnterAlternateScreen)?;
+    io::stdout().execute(EnableMouseCapture)?;
+    io::stdout().execute(EnableBracketedPaste)?;
     terminal.clear()?;
+    Ok(result)
+}
 
-    match result {
-        Ok(edited) => app.apply_editor_result(request, edited),
+/// Suspends the TUI, runs `$EDITOR`, and applies the result. Reopening an
+/// edit that didn't parse resumes from the kept draft. The caller pauses
+/// terminal input first so the editor gets every key.
+fn run_editor(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    request: &EditorRequest,
+) -> Result<()> {
+    let session = match app.take_editor_draft() {
+        Some(session) => session,
+        None => match EditSession::new(&request.value) {
+            Ok(session) => session,
+            Err(e) => {
+                app.status_message = Some(format!("Editor error: {e}"));
+                return Ok(());
+            }
+        },
+    };
+
+    match suspend_tui(terminal, || session.edit(app.editor.as_deref()))? {
+        Ok(EditOutcome::Edited(edited)) => app.apply_editor_result(request, edited),
+        Ok(EditOutcome::Invalid(error)) => app.keep_invalid_edit(request.clone(), session, error),
         Err(e) => app.status_message = Some(format!("Editor error: {e}")),
     }
 
     Ok(())
 }
 
-fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
+/// Routes clicks and wheel events to the panel under the cursor. Overlays
+/// are keyboard-only, so mouse events are ignored while one is open.
+fn handle_mouse(app: &mut App, mouse: MouseEvent) -> Option<EditorRequest> {
+    if app.is_editing() {
+        return None;
+    }
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            app.status_message = None;
+            app.click(mouse.column, mouse.row, Instant::now())
+        }
+        MouseEventKind::ScrollDown => {
+            app.scroll(mouse.column, mouse.row, true);
+            None
+        }
+        MouseEventKind::ScrollUp => {
+            app.scroll(mouse.column, mouse.row, false);
+            None
+        }
+        _ => None,
+    }
+}
+
+fn handle_modal_input(
+    app: &mut App,
+    key: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EditorRequest> {
     match app.input_mode {
         InputMode::EditingValue => {
             match key {
                 KeyCode::Enter => app.commit_edit(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -131,11 +718,12 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             match key {
                 KeyCode::Enter => app.commit_key_name(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                KeyCode::Char('t') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.nested_keys = !app.nested_keys;
+                }
+                _ => {
+                    edit_prompt(app, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -149,15 +737,23 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             }
             None
         }
+        InputMode::SelectingEnum => {
+            match key {
+                KeyCode::Enter => app.commit_enum_selection(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.enum_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.enum_select_down(),
+                _ => {}
+            }
+            None
+        }
         InputMode::EnteringCustomValue => {
             match key {
                 KeyCode::Enter => app.commit_custom_value(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -165,11 +761,9 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             match key {
                 KeyCode::Enter => app.commit_permission_tool(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                _ => {
+                    edit_prompt(app, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -187,11 +781,9 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             match key {
                 KeyCode::Enter => app.commit_delegate_to(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -207,11 +799,9 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             match key {
                 KeyCode::Enter => return app.commit_mcp_server_name(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                _ => {
+                    edit_prompt(app, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -219,11 +809,9 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             match key {
                 KeyCode::Enter => app.commit_mcp_match_field(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                _ => {
+                    edit_prompt(app, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -231,11 +819,9 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             match key {
                 KeyCode::Enter => app.commit_mcp_match_value(),
                 KeyCode::Esc => app.cancel_edit(),
-                KeyCode::Backspace => {
-                    app.edit_buffer.pop();
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
                 }
-                KeyCode::Char(c) => app.edit_buffer.push(c),
-                _ => {}
             }
             None
         }
@@ -257,73 +843,542 @@ fn handle_modal_input(app: &mut App, key: KeyCode) -> Option<EditorRequest> {
             }
             _ => None,
         },
-        InputMode::Normal => None,
-    }
-}
-
-fn handle_normal_input(
-    app: &mut App,
-    key: KeyCode,
-    modifiers: KeyModifiers,
-) -> Option<EditorRequest> {
-    match key {
-        KeyCode::Char('q') => {
-            app.should_quit = true;
+        InputMode::ConfirmReopenEditor => match key {
+            KeyCode::Char('y') | KeyCode::Enter => app.confirm_reopen_editor(),
+            KeyCode::Char('n') | KeyCode::Esc => {
+                app.decline_reopen_editor();
+                None
+            }
+            _ => None,
+        },
+        InputMode::SelectingCopyFormat => {
+            match key {
+                KeyCode::Enter => app.commit_copy(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.copy_format_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.copy_format_down(),
+                _ => {}
+            }
             None
         }
-        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-            app.should_quit = true;
+        InputMode::SelectingImportSource => {
+            match key {
+                KeyCode::Enter => app.commit_import_source(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.import_source_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.import_source_down(),
+                _ => {}
+            }
             None
         }
-        KeyCode::Up | KeyCode::Char('k') => {
-            app.move_up();
+        InputMode::SelectingImportServers => {
+            match key {
+                KeyCode::Enter => app.commit_mcp_import(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Char(' ') => app.toggle_import_candidate(),
+                KeyCode::Up | KeyCode::Char('k') => app.import_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.import_select_down(),
+                _ => {}
+            }
             None
         }
-        KeyCode::Down | KeyCode::Char('j') => {
-            app.move_down();
+        InputMode::SelectingDisabledTools => {
+            match key {
+                KeyCode::Enter => app.commit_tool_picker(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Char(' ') => app.toggle_tool_choice(),
+                KeyCode::Char('a') => app.start_add_custom_tool(),
+                KeyCode::Up | KeyCode::Char('k') => app.tool_choice_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.tool_choice_down(),
+                _ => {}
+            }
             None
         }
-        KeyCode::Tab | KeyCode::BackTab => {
-            app.toggle_focus();
+        InputMode::EnteringCustomTool => {
+            match key {
+                KeyCode::Enter => app.commit_custom_tool(),
+                KeyCode::Esc => app.cancel_custom_tool(),
+                _ => edit_prompt(app, key, modifiers),
+            }
             None
         }
-        KeyCode::Enter => {
-            if app.focus == Focus::Settings {
-                app.activate_setting()
-            } else {
-                app.toggle_focus();
-                None
+        InputMode::ConfirmScaffold => {
+            match key {
+                KeyCode::Char('y') | KeyCode::Enter => app.confirm_scaffold(),
+                KeyCode::Char('n') | KeyCode::Esc => app.decline_scaffold(),
+                _ => {}
             }
+            None
         }
-        KeyCode::Char('e') => {
-            if app.focus == Focus::Settings {
-                app.force_editor()
-            } else {
-                None
+        InputMode::SelectingFile => {
+            match key {
+                KeyCode::Enter => app.commit_file_selection(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.file_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.file_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::EditingNote => {
+            match key {
+                KeyCode::Enter => app.commit_note(),
+                KeyCode::Esc => app.cancel_edit(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::SelectingBackup => {
+            match key {
+                KeyCode::Enter => app.commit_backup_restore(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.backup_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.backup_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::EditingServer => {
+            let on_transport = app
+                .server_form
+                .as_ref()
+                .is_some_and(|f| f.field == server_form::Field::Transport);
+            let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+            match key {
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Enter => app.commit_server_form(),
+                KeyCode::Char('e') if ctrl => return app.server_form_to_editor(),
+                KeyCode::Char('a') if ctrl => app.edit_server_form(ServerForm::add_arg),
+                KeyCode::Char('n') if ctrl => app.edit_server_form(ServerForm::add_pair),
+                KeyCode::Char('d') if ctrl => app.edit_server_form(ServerForm::remove_item),
+                KeyCode::Tab | KeyCode::Down => app.edit_server_form(|f| f.move_focus(true)),
+                KeyCode::BackTab | KeyCode::Up => app.edit_server_form(|f| f.move_focus(false)),
+                KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') if on_transport => {
+                    app.edit_server_form(ServerForm::toggle_transport)
+                }
+                KeyCode::Backspace => app.edit_server_form(|f| {
+                    f.text_mut().map(textarea::pop_grapheme);
+                }),
+                KeyCode::Char(c) if !ctrl => app.edit_server_form(|f| {
+                    if let Some(text) = f.text_mut() {
+                        text.push(c);
+                    }
+                }),
+                _ => {}
             }
+            None
         }
-        KeyCode::Char('a') => {
-            if app.focus == Focus::Settings {
-                app.add_array_item();
+        InputMode::EditingRule => {
+            let on_action = app
+                .rule_form
+                .as_ref()
+                .is_some_and(|f| f.field == Field::Action);
+            let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+            match key {
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Enter => app.commit_rule_form(),
+                KeyCode::Char('e') if ctrl => return app.rule_form_to_editor(),
+                KeyCode::Char('a') if ctrl => app.edit_rule_form(RuleForm::add_match),
+                KeyCode::Char('d') if ctrl => app.edit_rule_form(RuleForm::remove_match),
+                KeyCode::Tab | KeyCode::Down => app.edit_rule_form(|f| f.move_focus(true)),
+                KeyCode::BackTab | KeyCode::Up => app.edit_rule_form(|f| f.move_focus(false)),
+                KeyCode::Left if on_action => app.edit_rule_form(|f| f.cycle_action(false)),
+                KeyCode::Right | KeyCode::Char(' ') if on_action => {
+                    app.edit_rule_form(|f| f.cycle_action(true))
+                }
+                KeyCode::Backspace => app.edit_rule_form(|f| {
+                    f.text_mut().map(textarea::pop_grapheme);
+                }),
+                KeyCode::Char(c) if !ctrl => app.edit_rule_form(|f| {
+                    if let Some(text) = f.text_mut() {
+                        text.push(c);
+                    }
+                }),
+                _ => {}
             }
             None
         }
-        KeyCode::Char('d') => {
-            if app.focus == Focus::Settings {
-                app.delete_array_item();
+        InputMode::TestingPermissions => {
+            match key {
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                    app.switch_permission_test_field()
+                }
+                KeyCode::Backspace => app.edit_permission_test(textarea::pop_grapheme),
+                KeyCode::Char(c) => app.edit_permission_test(|s| s.push(c)),
+                _ => {}
             }
             None
         }
-        KeyCode::Char('r') => {
-            if app.focus == Focus::Settings {
-                app.reset_setting();
+        InputMode::EditingEnv => {
+            match key {
+                KeyCode::Esc | KeyCode::Char('q') => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.env_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.env_select_down(),
+                KeyCode::Enter | KeyCode::Char('e') => app.start_edit_env(),
+                KeyCode::Char('a') => app.start_add_env(),
+                KeyCode::Char('d') => app.delete_env(),
+                KeyCode::Char('s') => app.reveal_secrets = !app.reveal_secrets,
+                _ => {}
             }
             None
         }
-        KeyCode::Char('s') if modifiers.contains(KeyModifiers::CONTROL) => {
-            app.save();
+        InputMode::EnteringEnvKey | InputMode::EnteringEnvValue => {
+            match key {
+                KeyCode::Enter if app.input_mode == InputMode::EnteringEnvKey => {
+                    app.commit_env_key()
+                }
+                KeyCode::Enter => app.commit_env_value(),
+                KeyCode::Esc => app.cancel_env_input(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::EditingObject => {
+            match key {
+                KeyCode::Esc => app.object_back(),
+                KeyCode::Char('q') => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.object_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.object_select_down(),
+                KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Right => {
+                    app.change_object_value(true)
+                }
+                KeyCode::Left => app.change_object_value(false),
+                KeyCode::Char('a') => app.start_add_object_entry(),
+                KeyCode::Char('d') => app.delete_object_entry(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::BrowsingTree => {
+            match key {
+                KeyCode::Esc | KeyCode::Char('q') => app.close_tree(),
+                KeyCode::Up | KeyCode::Char('k') => app.tree_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.tree_select_down(),
+                KeyCode::Right | KeyCode::Char('l') => app.tree_expand(),
+                KeyCode::Left | KeyCode::Char('h') => app.tree_collapse(),
+                KeyCode::Enter | KeyCode::Char(' ') => app.tree_activate(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::EditingTreeValue => {
+            match key {
+                KeyCode::Enter => app.commit_tree_value(),
+                KeyCode::Esc => app.cancel_tree_input(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::EnteringObjectKey | InputMode::EnteringObjectValue => {
+            match key {
+                KeyCode::Enter if app.input_mode == InputMode::EnteringObjectKey => {
+                    app.commit_object_key()
+                }
+                KeyCode::Enter => app.commit_object_value(),
+                KeyCode::Esc => app.cancel_object_input(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::SelectingProfile => {
+            match key {
+                KeyCode::Enter => app.commit_profile_selection(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.profile_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.profile_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::EnteringExportPath => {
+            match key {
+                KeyCode::Enter => app.commit_export_path(),
+                KeyCode::Esc => app.cancel_edit(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::EnteringCommitMessage => {
+            match key {
+                KeyCode::Enter => app.commit_git_message(),
+                KeyCode::Esc => app.cancel_edit(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::EnteringProfileName => {
+            match key {
+                KeyCode::Enter => app.commit_profile_name(),
+                KeyCode::Esc => app.cancel_edit(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::SelectingPreset => {
+            match key {
+                KeyCode::Enter => app.commit_preset_selection(false),
+                KeyCode::Char('r') => app.commit_preset_selection(true),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.preset_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.preset_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::ConfirmReset => {
+            match key {
+                KeyCode::Char('y') | KeyCode::Enter => app.confirm_reset(false),
+                KeyCode::Char('a') => app.confirm_reset(true),
+                KeyCode::Char('n') | KeyCode::Esc => app.cancel_edit(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::ViewingTrash => {
+            match key {
+                KeyCode::Enter => app.commit_trash_restore(),
+                KeyCode::Esc | KeyCode::Char('q') => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.trash_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.trash_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::ViewingRecent => {
+            match key {
+                KeyCode::Enter => app.commit_recent(),
+                KeyCode::Esc | KeyCode::Char('q') => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.recent_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.recent_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::BrowsingRegistry => {
+            match key {
+                KeyCode::Enter => app.commit_registry_selection(),
+                KeyCode::Char('r') => app.refresh_registry(),
+                KeyCode::Esc | KeyCode::Char('q') => app.cancel_edit(),
+                KeyCode::Up | KeyCode::Char('k') => app.registry_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.registry_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::EnteringPresetName => {
+            match key {
+                KeyCode::Enter => app.commit_preset_name(),
+                KeyCode::Esc => app.cancel_edit(),
+                _ => {
+                    edit_text(&mut app.edit_buffer, key, modifiers);
+                }
+            }
+            None
+        }
+        InputMode::ViewingMessages => {
+            match key {
+                KeyCode::Up | KeyCode::Char('k') => app.messages_scroll_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.messages_scroll_down(),
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => app.cancel_edit(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::ViewingDiff => {
+            match key {
+                KeyCode::Up | KeyCode::Char('k') => app.diff_scroll_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.diff_scroll_down(),
+                KeyCode::Char('s') if !app.diff_from_head => {
+                    app.cancel_edit();
+                    app.save();
+                }
+                KeyCode::Char('c') if app.diff_from_head => {
+                    app.cancel_edit();
+                    app.start_git_commit();
+                }
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => app.cancel_edit(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::EditingJson => {
+            match key {
+                KeyCode::Char('s') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.commit_json_edit();
+                }
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Enter => app.edit_json(TextArea::newline),
+                KeyCode::Backspace => app.edit_json(TextArea::backspace),
+                KeyCode::Delete => app.edit_json(TextArea::delete),
+                KeyCode::Left => app.edit_json(TextArea::move_left),
+                KeyCode::Right => app.edit_json(TextArea::move_right),
+                KeyCode::Up => app.edit_json(TextArea::move_up),
+                KeyCode::Down => app.edit_json(TextArea::move_down),
+                KeyCode::Home => app.edit_json(TextArea::move_home),
+                KeyCode::End => app.edit_json(TextArea::move_end),
+                KeyCode::Tab => app.edit_json(|t| {
+                    t.insert_char(' ');
+                    t.insert_char(' ');
+                }),
+                KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.edit_json(|t| t.insert_char(c));
+                }
+                _ => {}
+            }
+            None
+        }
+        InputMode::ConfirmQuit => {
+            match key {
+                KeyCode::Char('s' | 'y') | KeyCode::Enter => app.save_and_quit(),
+                KeyCode::Char('d' | 'n') => app.discard_and_quit(),
+                KeyCode::Char('c') | KeyCode::Esc => app.cancel_edit(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::ResolvingExternalChange => {
+            match key {
+                KeyCode::Char('r') => app.reload_from_disk(),
+                KeyCode::Char('m') => app.merge_from_disk(),
+                KeyCode::Char('k') | KeyCode::Esc => app.keep_local_changes(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::ResolvingConflicts => {
+            match key {
+                KeyCode::Enter => app.commit_merge(),
+                KeyCode::Esc => {
+                    for (_, side) in &mut app.merge_conflicts {
+                        *side = MergeSide::Mine;
+                    }
+                    app.commit_merge();
+                }
+                KeyCode::Left | KeyCode::Char('h') => app.pick_conflict_side(MergeSide::Theirs),
+                KeyCode::Right | KeyCode::Char('l') => app.pick_conflict_side(MergeSide::Mine),
+                KeyCode::Up | KeyCode::Char('k') => app.conflict_select_up(),
+                KeyCode::Down | KeyCode::Char('j') => app.conflict_select_down(),
+                _ => {}
+            }
+            None
+        }
+        InputMode::Searching => {
+            match key {
+                KeyCode::Enter => app.commit_search(),
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up => app.search_select_up(),
+                KeyCode::Down => app.search_select_down(),
+                _ => {
+                    if edit_text(&mut app.edit_buffer, key, modifiers) {
+                        app.update_search();
+                    }
+                }
+            }
+            None
+        }
+        InputMode::CommandPalette => {
+            match key {
+                KeyCode::Enter => {
+                    if let Some(action) = app.commit_palette() {
+                        return app.dispatch(action);
+                    }
+                }
+                KeyCode::Esc => app.cancel_edit(),
+                KeyCode::Up => app.palette_select_up(),
+                KeyCode::Down => app.palette_select_down(),
+                _ => {
+                    if edit_text(&mut app.edit_buffer, key, modifiers) {
+                        app.update_palette();
+                    }
+                }
+            }
+            None
+        }
+        InputMode::ViewingReport => {
+            if matches!(key, KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q')) {
+                app.cancel_edit();
+            }
+            None
+        }
+        InputMode::Normal => None,
+    }
+}
+
+/// Handles a key at a prompt with completion and history: `Tab` and
+/// `Shift+Tab` complete, `↑`/`↓` recall earlier entries, and anything else
+/// edits the text.
+fn edit_prompt(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
+    match key {
+        KeyCode::Tab => app.complete_prompt(true),
+        KeyCode::BackTab => app.complete_prompt(false),
+        KeyCode::Up => app.recall_prompt(true),
+        KeyCode::Down => app.recall_prompt(false),
+        _ => {
+            edit_text(&mut app.edit_buffer, key, modifiers);
+        }
+    }
+}
+
+/// Applies a key to a one-line text prompt: typing inserts at the cursor,
+/// and the arrows, Home/End, and Ctrl+arrows (by word) move it. Returns
+/// whether the key could have changed the text.
+fn edit_text(input: &mut TextInput, key: KeyCode, modifiers: KeyModifiers) -> bool {
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+    match key {
+        KeyCode::Char(c) if !ctrl => {
+            input.insert_char(c);
+            return true;
+        }
+        KeyCode::Backspace => {
+            input.backspace();
+            return true;
+        }
+        KeyCode::Delete => {
+            input.delete();
+            return true;
+        }
+        KeyCode::Left if ctrl => input.move_word_left(),
+        KeyCode::Right if ctrl => input.move_word_right(),
+        KeyCode::Left => input.move_left(),
+        KeyCode::Right => input.move_right(),
+        KeyCode::Home => input.move_home(),
+        KeyCode::End => input.move_end(),
+        _ => {}
+    }
+    false
+}
+
+fn handle_normal_input(
+    app: &mut App,
+    key: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EditorRequest> {
+    app.pending_keys
+        .push(KeyBinding::from_event(key, modifiers));
+    match app.keymap.lookup_sequence(&app.pending_keys) {
+        Lookup::Action(action) => {
+            app.pending_keys.clear();
+            app.dispatch(action)
+        }
+        Lookup::Prefix => None,
+        Lookup::Unbound => {
+            app.pending_keys.clear();
             None
         }
-        _ => None,
     }
 }
@@ -0,0 +1,362 @@
+//! The pattern syntax Amp uses in permission rules: `tool` names and
+//! `matches` values are globs, unless wrapped in slashes (`/^git /`), which
+//! makes them regular expressions.
+//!
+//! Globs support `*` (any run of characters), `?` (one character), `[abc]`,
+//! `[a-z]` and `[!abc]` classes, and `\` to escape the next character.
+
+use serde_json::Value;
+
+/// One piece of a parsed glob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Star,
+    Any,
+    Literal(char),
+    /// A character class: whether it's negated, and its inclusive ranges.
+    Class(bool, Vec<(char, char)>),
+}
+
+/// Returns the expression inside a `/…/` pattern, or `None` for a glob.
+pub fn regex_source(pattern: &str) -> Option<&str> {
+    (pattern.len() >= 2 && pattern.starts_with('/') && pattern.ends_with('/'))
+        .then(|| &pattern[1..pattern.len() - 1])
+}
+
+/// Checks a pattern's syntax, returning what's wrong with it.
+pub fn check(pattern: &str) -> Result<(), String> {
+    match regex_source(pattern) {
+        Some(source) => check_regex(source),
+        None => parse_glob(pattern).map(|_| ()),
+    }
+}
+
+/// Checks every string pattern inside a `matches` value; arrays and objects
+/// are checked element by element.
+pub fn check_value(pattern: &Value) -> Result<(), String> {
+    match pattern {
+        Value::String(s) => check(s).map_err(|e| format!("\"{s}\": {e}")),
+        Value::Array(items) => items.iter().try_for_each(check_value),
+        Value::Object(map) => map.values().try_for_each(check_value),
+        _ => Ok(()),
+    }
+}
+
+/// Describes the first invalid pattern in a permission rule's `tool` or
+/// `matches`, if any.
+pub fn rule_error(rule: &Value) -> Option<String> {
+    if let Some(tool) = rule.get("tool").and_then(Value::as_str) {
+        if let Err(e) = check(tool) {
+            return Some(format!("tool \"{tool}\": {e}"));
+        }
+    }
+    let matches = rule.get("matches")?.as_object()?;
+    matches
+        .iter()
+        .find_map(|(name, pattern)| check_value(pattern).err().map(|e| format!("{name} {e}")))
+}
+
+/// Matches text against a glob. Invalid globs match nothing.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let Ok(tokens) = parse_glob(pattern) else {
+        return false;
+    };
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Where to resume after the last `*`: token index past it, and the text
+    // index it currently absorbs up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        let step = match tokens.get(pi) {
+            Some(Token::Star) => {
+                backtrack = Some((pi + 1, ti));
+                pi += 1;
+                continue;
+            }
+            Some(Token::Any) => true,
+            Some(Token::Literal(c)) => *c == t[ti],
+            Some(Token::Class(negated, ranges)) => {
+                ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&t[ti])) != *negated
+            }
+            None => false,
+        };
+        if step {
+            pi += 1;
+            ti += 1;
+            continue;
+        }
+        match backtrack {
+            Some((star_pi, star_ti)) => {
+                pi = star_pi;
+                ti = star_ti + 1;
+                backtrack = Some((star_pi, star_ti + 1));
+            }
+            None => return false,
+        }
+    }
+    tokens[pi..].iter().all(|t| *t == Token::Star)
+}
+
+fn parse_glob(pattern: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        tokens.push(match chars[i] {
+            '*' => Token::Star,
+            '?' => Token::Any,
+            '\\' => {
+                i += 1;
+                Token::Literal(*chars.get(i).ok_or("trailing backslash")?)
+            }
+            '[' => {
+                let (token, end) = parse_class(&chars, i)?;
+                i = end;
+                token
+            }
+            ']' => return Err("unmatched ]".to_string()),
+            c => Token::Literal(c),
+        });
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+/// Parses the class opened at `start`, returning it and the index of its
+/// closing `]`. A `]` right after the opening (or after `!`) is a literal.
+fn parse_class(chars: &[char], start: usize) -> Result<(Token, usize), String> {
+    let mut i = start + 1;
+    let negated = matches!(chars.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let first = i;
+    loop {
+        let lo = match chars.get(i) {
+            None => return Err("unclosed [".to_string()),
+            Some(']') if i > first => return Ok((Token::Class(negated, ranges), i)),
+            Some('\\') => {
+                i += 1;
+                *chars.get(i).ok_or("unclosed [")?
+            }
+            Some(&c) => c,
+        };
+        match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some('-'), Some(&hi)) if hi != ']' => {
+                if hi < lo {
+                    return Err(format!("range {lo}-{hi} is out of order"));
+                }
+                ranges.push((lo, hi));
+                i += 3;
+            }
+            _ => {
+                ranges.push((lo, lo));
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Checks the syntax of a JavaScript regular expression, which is what Amp
+/// evaluates `/…/` patterns with. This catches the mistakes that make Amp
+/// reject a pattern, not every corner of the grammar.
+fn check_regex(source: &str) -> Result<(), String> {
+    if source.is_empty() {
+        return Err("empty regex".to_string());
+    }
+    let chars: Vec<char> = source.chars().collect();
+    let mut groups = 0usize;
+    // Whether the previous item can take a quantifier.
+    let mut repeatable = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                if i == chars.len() {
+                    return Err("trailing backslash".to_string());
+                }
+                repeatable = true;
+            }
+            '[' => {
+                i = regex_class_end(&chars, i)?;
+                repeatable = true;
+            }
+            '(' => {
+                if chars.get(i + 1) == Some(&'?') {
+                    let rest: String = chars[i + 2..].iter().take(2).collect();
+                    if rest.starts_with([':', '=', '!']) || rest == "<=" || rest == "<!" {
+                        i += if rest.starts_with('<') { 3 } else { 2 };
+                    } else if rest.starts_with('<') {
+                        let close = chars[i..].iter().position(|&c| c == '>');
+                        i += close.ok_or("unclosed group name")?;
+                    } else {
+                        return Err("invalid group".to_string());
+                    }
+                }
+                groups += 1;
+                repeatable = false;
+            }
+            ')' => {
+                groups = groups.checked_sub(1).ok_or("unmatched )")?;
+                repeatable = true;
+            }
+            '|' | '^' | '$' => repeatable = false,
+            '*' | '+' | '?' => {
+                if !repeatable {
+                    return Err(format!("nothing for {} to repeat", chars[i]));
+                }
+                if chars.get(i + 1) == Some(&'?') {
+                    i += 1;
+                }
+                repeatable = false;
+            }
+            '{' => match braced_quantifier(&chars[i..]) {
+                Some((len, min, max)) => {
+                    if !repeatable {
+                        return Err("nothing for {} to repeat".to_string());
+                    }
+                    if let Some(max) = max.filter(|&max| max < min) {
+                        return Err(format!("{{{min},{max}}} is out of order"));
+                    }
+                    i += len - 1;
+                    if chars.get(i + 1) == Some(&'?') {
+                        i += 1;
+                    }
+                    repeatable = false;
+                }
+                // Not a quantifier, so JavaScript reads a literal brace.
+                None => repeatable = true,
+            },
+            _ => repeatable = true,
+        }
+        i += 1;
+    }
+    if groups > 0 {
+        return Err("unclosed (".to_string());
+    }
+    Ok(())
+}
+
+/// Returns the index of the `]` closing the class opened at `start`.
+fn regex_class_end(chars: &[char], start: usize) -> Result<usize, String> {
+    let mut i = start + 1;
+    if chars.get(i) == Some(&'^') {
+        i += 1;
+    }
+    let mut prev: Option<char> = None;
+    while i < chars.len() {
+        match chars[i] {
+            ']' => return Ok(i),
+            '\\' => {
+                i += 1;
+                prev = None;
+            }
+            '-' if prev.is_some() && chars.get(i + 1).is_some_and(|&c| c != ']' && c != '\\') => {
+                let (lo, hi) = (prev.unwrap(), chars[i + 1]);
+                if hi < lo {
+                    return Err(format!("range {lo}-{hi} is out of order"));
+                }
+                i += 1;
+                prev = None;
+            }
+            c => prev = Some(c),
+        }
+        i += 1;
+    }
+    Err("unclosed [".to_string())
+}
+
+/// Parses `{n}`, `{n,}` or `{n,m}` at the start of `chars`, returning its
+/// length and bounds.
+fn braced_quantifier(chars: &[char]) -> Option<(usize, u32, Option<u32>)> {
+    let close = chars.iter().position(|&c| c == '}')?;
+    let inner: String = chars[1..close].iter().collect();
+    let (min, max) = match inner.split_once(',') {
+        Some((min, "")) => (min.parse().ok()?, None),
+        Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+        None => {
+            let n = inner.parse().ok()?;
+            (n, Some(n))
+        }
+    };
+    Some((close + 1, min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("git *", "git push --force"));
+        assert!(!glob_match("git *", "gitk"));
+        assert!(glob_match("mcp__*__read", "mcp__gh__read"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("Bash", "bash"));
+    }
+
+    #[test]
+    fn test_glob_classes_and_escapes() {
+        assert!(glob_match("[Bb]ash", "bash"));
+        assert!(glob_match("file[0-9]", "file7"));
+        assert!(!glob_match("file[!0-9]", "file7"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match(r"what\?", "what?"));
+        assert!(!glob_match(r"what\?", "whats"));
+        assert!(!glob_match("[a", "[a"));
+    }
+
+    #[test]
+    fn test_check_glob() {
+        assert_eq!(check("git *"), Ok(()));
+        assert_eq!(check("[a"), Err("unclosed [".to_string()));
+        assert_eq!(check("a]"), Err("unmatched ]".to_string()));
+        assert_eq!(check(r"a\"), Err("trailing backslash".to_string()));
+        assert_eq!(check("[z-a]"), Err("range z-a is out of order".to_string()));
+    }
+
+    #[test]
+    fn test_check_regex() {
+        for ok in [
+            "/^git (status|log)$/",
+            r"/\d+\.\d+/",
+            "/a{2,3}?b{1,}c{4}/",
+            "/(?:x)(?<name>y)(?=z)(?<!w)/",
+            "/[a-z\\]]+/",
+            "/{}/",
+        ] {
+            assert_eq!(check(ok), Ok(()), "{ok}");
+        }
+        assert_eq!(check("/(a/"), Err("unclosed (".to_string()));
+        assert_eq!(check("/a)/"), Err("unmatched )".to_string()));
+        assert_eq!(check("/*a/"), Err("nothing for * to repeat".to_string()));
+        assert_eq!(check("/a|+/"), Err("nothing for + to repeat".to_string()));
+        assert_eq!(check("/[a/"), Err("unclosed [".to_string()));
+        assert_eq!(check("/a{3,1}/"), Err("{3,1} is out of order".to_string()));
+        assert_eq!(check("/(?x)/"), Err("invalid group".to_string()));
+        assert_eq!(check("//"), Err("empty regex".to_string()));
+    }
+
+    #[test]
+    fn test_rule_error() {
+        assert_eq!(
+            rule_error(&json!({"tool": "Bash", "matches": {"cmd": "git *"}})),
+            None
+        );
+        assert_eq!(
+            rule_error(&json!({"tool": "mcp__[gh"})),
+            Some("tool \"mcp__[gh\": unclosed [".to_string())
+        );
+        assert_eq!(
+            rule_error(&json!({"matches": {"cmd": ["ls", "/(/"]}})),
+            Some("cmd \"/(/\": unclosed (".to_string())
+        );
+    }
+}
@@ -0,0 +1,620 @@
+//! A minimal MCP client for checking that a configured server starts and
+//! answers: it performs the `initialize` handshake and lists the server's
+//! tools, over stdio for local servers or HTTP (via `curl`) for remote ones.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+use crate::amp;
+
+/// The protocol revision volt offers during the handshake.
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// How long a server gets to answer the whole handshake.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long a health check waits for `--version` or an HTTP response.
+pub const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The outcome of a quick health check, which stops short of the handshake
+/// `probe` performs: a local server's command runs with `--version`, and a
+/// remote server's URL answers at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    /// It runs or answers, with what was found.
+    Up(String),
+    /// It can't be started or reached, and why.
+    Down(String),
+    /// It couldn't be told either way, and why.
+    Unknown(String),
+}
+
+impl Health {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Health::Up(_) => "✓",
+            Health::Down(_) => "✗",
+            Health::Unknown(_) => "?",
+        }
+    }
+
+    pub fn detail(&self) -> &str {
+        match self {
+            Health::Up(detail) | Health::Down(detail) | Health::Unknown(detail) => detail,
+        }
+    }
+}
+
+/// Checks a server entry from `amp.mcpServers` without speaking MCP to it.
+pub fn check_health(server: &Value, timeout: Duration) -> Health {
+    if let Some(command) = server.get("command").and_then(Value::as_str) {
+        let Some(path) = amp::find_on_path(command) else {
+            return Health::Down(format!("`{command}` is not on PATH"));
+        };
+        let mut version = Command::new(&path);
+        version.arg("--version");
+        match run_with_timeout(&mut version, timeout) {
+            Ok(Some(output)) if output.status.success() => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                match stdout.lines().map(str::trim).find(|l| !l.is_empty()) {
+                    Some(line) => Health::Up(format!("{} ({line})", path.display())),
+                    None => Health::Up(path.display().to_string()),
+                }
+            }
+            Ok(Some(output)) => Health::Unknown(format!(
+                "{} found, but `{command} --version` exited with {}",
+                path.display(),
+                output.status
+            )),
+            Ok(None) => Health::Unknown(format!(
+                "{} found, but `{command} --version` didn't finish",
+                path.display()
+            )),
+            Err(e) => Health::Down(format!("{e:#}")),
+        }
+    } else if let Some(url) = server.get("url").and_then(Value::as_str) {
+        let Some(curl) = amp::find_on_path("curl") else {
+            return Health::Unknown("checking a remote server requires curl".into());
+        };
+        let mut request = Command::new(curl);
+        request
+            .args(["-sS", "-o", "/dev/null", "-w", "%{http_code}", "--max-time"])
+            .arg(timeout.as_secs().to_string())
+            .arg(url);
+        match run_with_timeout(&mut request, timeout + Duration::from_secs(1)) {
+            // Any response means it's reachable; MCP endpoints often turn
+            // down a plain GET.
+            Ok(Some(output)) if output.status.success() => Health::Up(format!(
+                "{url} answered (HTTP {})",
+                String::from_utf8_lossy(&output.stdout).trim()
+            )),
+            Ok(Some(output)) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Health::Down(last_line(&stderr).unwrap_or("curl failed").to_string())
+            }
+            Ok(None) => Health::Down(format!("{url} didn't answer")),
+            Err(e) => Health::Unknown(format!("{e:#}")),
+        }
+    } else {
+        Health::Down("server has neither a command nor a url".into())
+    }
+}
+
+/// Runs a command with no input, returning its output, or `None` if it was
+/// killed for taking longer than `timeout`. Both pipes are drained while it
+/// runs, so a chatty command doesn't stall on a full pipe.
+fn run_with_timeout(cmd: &mut Command, timeout: Duration) -> Result<Option<Output>> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("starting the command")?;
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+/// Reads a pipe to the end on its own thread.
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// What a server reported about itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    /// `serverInfo.name` and `serverInfo.version`, when given.
+    pub server: Option<String>,
+    pub protocol_version: String,
+    pub tools: Vec<String>,
+}
+
+impl Probe {
+    /// Lines for the results overlay.
+    pub fn report_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(server) = &self.server {
+            lines.push(format!("Server: {server}"));
+        }
+        lines.push(format!("Protocol: {}", self.protocol_version));
+        lines.push(format!("Tools: {}", self.tools.len()));
+        lines.extend(self.tools.iter().map(|t| format!("  {t}")));
+        lines
+    }
+}
+
+/// Connects to a server entry from `amp.mcpServers` and performs the
+/// handshake, giving up after `timeout`.
+pub fn probe(server: &Value, timeout: Duration) -> Result<Probe> {
+    if let Some(command) = server.get("command").and_then(Value::as_str) {
+        probe_stdio(command, server, timeout)
+    } else if let Some(url) = server.get("url").and_then(Value::as_str) {
+        probe_http(url, server, timeout)
+    } else {
+        bail!("server has neither a command nor a url");
+    }
+}
+
+fn initialize_request() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {"name": "volt", "version": env!("CARGO_PKG_VERSION")},
+        },
+    })
+}
+
+fn initialized_notification() -> Value {
+    json!({"jsonrpc": "2.0", "method": "notifications/initialized"})
+}
+
+fn tools_list_request() -> Value {
+    json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list", "params": {}})
+}
+
+/// Unwraps a JSON-RPC response, turning an `error` member into an error.
+fn result_of(response: &Value) -> Result<&Value> {
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error");
+        bail!("server returned an error: {message}");
+    }
+    response
+        .get("result")
+        .context("response has neither a result nor an error")
+}
+
+/// Builds the probe from the `initialize` and `tools/list` results.
+fn finish(init: &Value, tools: &Value) -> Result<Probe> {
+    let init = result_of(init)?;
+    let info = init.get("serverInfo");
+    let name = info.and_then(|i| i.get("name")).and_then(Value::as_str);
+    let version = info.and_then(|i| i.get("version")).and_then(Value::as_str);
+    let server = match (name, version) {
+        (Some(name), Some(version)) => Some(format!("{name} {version}")),
+        (Some(name), None) => Some(name.to_string()),
+        _ => None,
+    };
+    let tools = result_of(tools)?
+        .get("tools")
+        .and_then(Value::as_array)
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|t| t.get("name").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Probe {
+        server,
+        protocol_version: init
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string(),
+        tools,
+    })
+}
+
+/// A spawned stdio server, killed when dropped.
+struct StdioServer {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    stderr: Receiver<String>,
+    deadline: Instant,
+}
+
+impl StdioServer {
+    fn send(&mut self, message: &Value) -> Result<()> {
+        writeln!(self.stdin, "{message}")
+            .and_then(|()| self.stdin.flush())
+            .context("writing to the server's stdin")
+    }
+
+    /// Waits for the response with the given id, skipping notifications,
+    /// server requests, and non-JSON log output.
+    fn response(&mut self, id: u64) -> Result<Value> {
+        loop {
+            let remaining = self.deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(remaining) {
+                Ok(line) => {
+                    let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                        continue;
+                    };
+                    if message.get("id") == Some(&json!(id)) && message.get("method").is_none() {
+                        return Ok(message);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => bail!("timed out waiting for the server"),
+                Err(RecvTimeoutError::Disconnected) => {
+                    let status = self.child.wait().ok();
+                    let stderr = self.stderr.recv_timeout(Duration::from_millis(200));
+                    let mut message = match status {
+                        Some(status) => format!("server exited ({status})"),
+                        None => "server closed its output".to_string(),
+                    };
+                    if let Some(last) = stderr.ok().as_deref().and_then(last_line) {
+                        message.push_str(": ");
+                        message.push_str(last);
+                    }
+                    bail!(message);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for StdioServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn last_line(text: &str) -> Option<&str> {
+    text.lines().map(str::trim).rfind(|l| !l.is_empty())
+}
+
+fn probe_stdio(command: &str, server: &Value, timeout: Duration) -> Result<Probe> {
+    let args: Vec<&str> = server
+        .get("args")
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let env = server
+        .get("env")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter_map(|(k, v)| v.as_str().map(|v| (k, v)));
+
+    let mut child = Command::new(command)
+        .args(&args)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("starting '{command}'"))?;
+
+    let stdin = child.stdin.take().context("no stdin")?;
+    let stdout = child.stdout.take().context("no stdout")?;
+    let mut stderr = child.stderr.take().context("no stderr")?;
+
+    let (line_tx, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if line_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let (err_tx, stderr_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        let _ = err_tx.send(text);
+    });
+
+    let mut server = StdioServer {
+        child,
+        stdin,
+        lines,
+        stderr: stderr_rx,
+        deadline: Instant::now() + timeout,
+    };
+    server.send(&initialize_request())?;
+    let init = server.response(1)?;
+    result_of(&init)?;
+    server.send(&initialized_notification())?;
+    server.send(&tools_list_request())?;
+    let tools = server.response(2)?;
+    finish(&init, &tools)
+}
+
+/// One HTTP exchange's response body and `Mcp-Session-Id` header.
+struct HttpResponse {
+    body: Option<Value>,
+    session: Option<String>,
+}
+
+fn probe_http(url: &str, server: &Value, timeout: Duration) -> Result<Probe> {
+    let curl = amp::find_on_path("curl").context("testing a remote server requires curl")?;
+    let headers: Vec<String> = server
+        .get("headers")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter_map(|(k, v)| v.as_str().map(|v| format!("{k}: {v}")))
+        .collect();
+    let deadline = Instant::now() + timeout;
+
+    let post = |message: &Value, session: Option<&str>| -> Result<HttpResponse> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            bail!("timed out waiting for the server");
+        }
+        // Headers (often bearer tokens) go on stdin and the message in a temp
+        // file, keeping both out of `ps`.
+        let mut body = tempfile::NamedTempFile::new().context("creating a temp file")?;
+        body.write_all(message.to_string().as_bytes())
+            .with_context(|| format!("writing {}", body.path().display()))?;
+        let mut stdin_headers = format!(
+            "Content-Type: application/json\n\
+             Accept: application/json, text/event-stream\n\
+             MCP-Protocol-Version: {PROTOCOL_VERSION}\n"
+        );
+        if let Some(session) = session {
+            stdin_headers.push_str(&format!("Mcp-Session-Id: {session}\n"));
+        }
+        for header in &headers {
+            stdin_headers.push_str(header);
+            stdin_headers.push('\n');
+        }
+        let mut child = Command::new(&curl)
+            .args(["-sS", "-X", "POST", "-D", "-", "--max-time"])
+            .arg(format!("{:.1}", remaining.as_secs_f64()))
+            .args(["-H", "@-", "--data-binary"])
+            .arg(format!("@{}", body.path().display()))
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("running curl for {url}"))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(stdin_headers.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("{}", last_line(&stderr).unwrap_or("curl failed"));
+        }
+        parse_http_response(&String::from_utf8_lossy(&output.stdout))
+    };
+
+    let init = post(&initialize_request(), None)?;
+    let session = init.session.clone();
+    let init = init.body.context("server sent no initialize response")?;
+    result_of(&init)?;
+    post(&initialized_notification(), session.as_deref())?;
+    let tools = post(&tools_list_request(), session.as_deref())?
+        .body
+        .context("server sent no tools/list response")?;
+    finish(&init, &tools)
+}
+
+/// Splits curl's `-D -` output into headers and body, reading the JSON-RPC
+/// message from either a plain JSON body or an SSE stream.
+fn parse_http_response(raw: &str) -> Result<HttpResponse> {
+    // Skip interim responses such as `100 Continue`.
+    let mut rest = raw;
+    let (head, body) = loop {
+        let (head, body) = rest
+            .split_once("\r\n\r\n")
+            .or_else(|| rest.split_once("\n\n"))
+            .unwrap_or((rest, ""));
+        if head.split_whitespace().nth(1) == Some("100") {
+            rest = body;
+            continue;
+        }
+        break (head, body);
+    };
+
+    let mut lines = head.lines();
+    let status: u16 = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|s| s.parse().ok())
+        .context("malformed HTTP response")?;
+    let session = lines.find_map(|l| {
+        let (name, value) = l.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("mcp-session-id")
+            .then(|| value.trim().to_string())
+    });
+    if !(200..300).contains(&status) {
+        bail!("HTTP {status}");
+    }
+
+    let body = body.trim();
+    let message = if body.is_empty() {
+        None
+    } else if let Ok(value) = serde_json::from_str(body) {
+        Some(value)
+    } else {
+        body.lines()
+            .filter_map(|l| l.strip_prefix("data:"))
+            .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+            .find(|m| m.get("id").is_some())
+    };
+    Ok(HttpResponse {
+        body: message,
+        session,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_json_and_sse() {
+        let json = "HTTP/1.1 200 OK\r\nMcp-Session-Id: abc\r\n\r\n{\"id\":1,\"result\":{}}";
+        let response = parse_http_response(json).unwrap();
+        assert_eq!(response.session.as_deref(), Some("abc"));
+        assert_eq!(response.body, Some(json!({"id": 1, "result": {}})));
+
+        let sse = "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n\
+                   Content-Type: text/event-stream\r\n\r\n\
+                   event: message\ndata: {\"method\":\"notifications/log\"}\n\n\
+                   event: message\ndata: {\"id\":2,\"result\":{\"tools\":[]}}\n\n";
+        let response = parse_http_response(sse).unwrap();
+        assert_eq!(response.session, None);
+        assert_eq!(
+            response.body,
+            Some(json!({"id": 2, "result": {"tools": []}}))
+        );
+
+        assert!(parse_http_response("HTTP/1.1 202 Accepted\r\n\r\n")
+            .unwrap()
+            .body
+            .is_none());
+        assert!(parse_http_response("HTTP/1.1 401 Unauthorized\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn test_finish_reports_error_responses() {
+        let init = json!({"id": 1, "error": {"code": -32600, "message": "bad version"}});
+        let err = finish(&init, &json!({})).unwrap_err();
+        assert!(err.to_string().contains("bad version"));
+    }
+
+    #[test]
+    fn test_probe_without_command_or_url() {
+        assert!(probe(&json!({"args": []}), PROBE_TIMEOUT).is_err());
+    }
+
+    #[cfg(unix)]
+    fn sh(script: &str) -> Value {
+        json!({"command": "sh", "args": ["-c", script], "env": {"GREETING": "hi"}})
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_stdio_handshake() {
+        let server = sh(r#"read init
+echo "starting up, $GREETING"
+echo '{"jsonrpc":"2.0","method":"notifications/message","params":{}}'
+echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","serverInfo":{"name":"fake","version":"1.0"},"capabilities":{}}}'
+read initialized
+read list
+echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"read"},{"name":"write"}]}}'"#);
+        let probe = probe(&server, PROBE_TIMEOUT).unwrap();
+        assert_eq!(
+            probe,
+            Probe {
+                server: Some("fake 1.0".to_string()),
+                protocol_version: "2025-06-18".to_string(),
+                tools: vec!["read".to_string(), "write".to_string()],
+            }
+        );
+        assert_eq!(probe.report_lines()[2], "Tools: 2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_stdio_failures() {
+        let err = probe(&sh("echo 'missing API key' >&2; exit 3"), PROBE_TIMEOUT).unwrap_err();
+        assert!(err.to_string().contains("missing API key"), "{err}");
+
+        let err = probe(&sh("sleep 5"), Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+
+        let missing = json!({"command": "/definitely/not/a/server"});
+        assert!(probe(&missing, PROBE_TIMEOUT).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_health() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = |name: &str, body: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            json!({"command": path})
+        };
+
+        let health = check_health(&script("ok", "echo 'srv 1.2.3'"), HEALTH_TIMEOUT);
+        assert!(
+            matches!(&health, Health::Up(d) if d.ends_with("(srv 1.2.3)")),
+            "{health:?}"
+        );
+        // More output than a pipe holds doesn't stall it into a timeout.
+        let health = check_health(
+            &script("chatty", "echo 'big 2.0'; head -c 300000 /dev/zero"),
+            HEALTH_TIMEOUT,
+        );
+        assert!(
+            matches!(&health, Health::Up(d) if d.ends_with("(big 2.0)")),
+            "{health:?}"
+        );
+        let health = check_health(&script("no-version", "exit 2"), HEALTH_TIMEOUT);
+        assert!(
+            matches!(&health, Health::Unknown(d) if d.contains("exited with")),
+            "{health:?}"
+        );
+        let health = check_health(&script("slow", "sleep 5"), Duration::from_millis(200));
+        assert!(
+            matches!(&health, Health::Unknown(d) if d.contains("didn't finish")),
+            "{health:?}"
+        );
+        assert_eq!(
+            check_health(
+                &json!({"command": "/definitely/not/a/server"}),
+                HEALTH_TIMEOUT
+            ),
+            Health::Down("`/definitely/not/a/server` is not on PATH".into())
+        );
+        assert_eq!(check_health(&json!({}), HEALTH_TIMEOUT).symbol(), "✗");
+    }
+}
@@ -0,0 +1,212 @@
+//! Importing MCP server definitions from other tools' config files into
+//! `amp.mcpServers`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use crate::jsonc;
+
+/// A tool whose MCP server list can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Claude,
+    Cursor,
+    VsCode,
+}
+
+impl Source {
+    pub const ALL: &[Source] = &[Source::Claude, Source::Cursor, Source::VsCode];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Claude => "Claude Desktop",
+            Source::Cursor => "Cursor",
+            Source::VsCode => "VS Code",
+        }
+    }
+
+    /// Parses the `--from` names accepted by `volt import-mcp`.
+    pub fn from_name(name: &str) -> Option<Source> {
+        match name.to_ascii_lowercase().as_str() {
+            "claude" | "claude-desktop" => Some(Source::Claude),
+            "cursor" => Some(Source::Cursor),
+            "vscode" | "code" => Some(Source::VsCode),
+            _ => None,
+        }
+    }
+
+    /// Where the tool keeps its user-wide MCP config on this OS.
+    pub fn default_path(self) -> Option<PathBuf> {
+        match self {
+            Source::Claude => {
+                let dir = if cfg!(target_os = "macos") {
+                    dirs::home_dir()?
+                        .join("Library")
+                        .join("Application Support")
+                } else {
+                    dirs::config_dir()?
+                };
+                Some(dir.join("Claude").join("claude_desktop_config.json"))
+            }
+            Source::Cursor => Some(dirs::home_dir()?.join(".cursor").join("mcp.json")),
+            Source::VsCode => {
+                let dir = if cfg!(target_os = "macos") {
+                    dirs::home_dir()?
+                        .join("Library")
+                        .join("Application Support")
+                } else {
+                    dirs::config_dir()?
+                };
+                Some(dir.join("Code").join("User").join("mcp.json"))
+            }
+        }
+    }
+}
+
+/// Reads a config file and returns its servers converted to Amp's format.
+pub fn read(source: Source, path: &Path) -> Result<Vec<(String, Value)>> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    parse(source, &text).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Extracts and converts the servers from a config file's text. Claude
+/// Desktop and Cursor keep them under `mcpServers`; VS Code uses `servers`
+/// in `mcp.json` and `mcp.servers` in its settings.
+pub fn parse(source: Source, text: &str) -> Result<Vec<(String, Value)>> {
+    let root = jsonc::parse(text)?;
+    let servers = match source {
+        Source::Claude | Source::Cursor => root.get("mcpServers"),
+        Source::VsCode => root
+            .get("servers")
+            .or_else(|| root.get("mcp").and_then(|mcp| mcp.get("servers")))
+            .or_else(|| root.get("mcp.servers")),
+    };
+    let Some(servers) = servers.and_then(Value::as_object) else {
+        bail!("no MCP servers found for {}", source.label());
+    };
+    Ok(servers
+        .iter()
+        .filter_map(|(name, server)| convert(server).map(|s| (name.clone(), s)))
+        .collect())
+}
+
+/// Keeps the fields Amp understands: `command`/`args`/`env` for local
+/// servers and `url`/`headers` for remote ones. Entries with neither a
+/// command nor a URL are dropped.
+fn convert(server: &Value) -> Option<Value> {
+    let server = server.as_object()?;
+    let fields: &[&str] = if server.get("command").is_some_and(Value::is_string) {
+        &["command", "args", "env"]
+    } else if server.get("url").is_some_and(Value::is_string) {
+        &["url", "headers"]
+    } else {
+        return None;
+    };
+    let converted: Map<String, Value> = fields
+        .iter()
+        .filter_map(|&f| server.get(f).map(|v| (f.to_string(), v.clone())))
+        .collect();
+    Some(Value::Object(converted))
+}
+
+/// Adds servers to `amp.mcpServers`, replacing any with the same name.
+pub fn merge(config: &mut Config, servers: &[(String, Value)]) {
+    let mut existing = config
+        .get("amp.mcpServers")
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    for (name, server) in servers {
+        existing.insert(name.clone(), server.clone());
+    }
+    config.set("amp.mcpServers", Value::Object(existing));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_claude_and_cursor() {
+        let text = r#"{
+            "mcpServers": {
+                "fs": {"command": "npx", "args": ["-y", "fs"], "env": {"A": "1"}, "disabled": false},
+                "remote": {"url": "https://mcp.example.com", "headers": {"X": "y"}, "type": "http"},
+                "broken": {"args": []}
+            },
+            "globalShortcut": "Ctrl+Space"
+        }"#;
+        for source in [Source::Claude, Source::Cursor] {
+            let servers = parse(source, text).unwrap();
+            assert_eq!(
+                servers,
+                vec![
+                    (
+                        "fs".to_string(),
+                        json!({"command": "npx", "args": ["-y", "fs"], "env": {"A": "1"}})
+                    ),
+                    (
+                        "remote".to_string(),
+                        json!({"url": "https://mcp.example.com", "headers": {"X": "y"}})
+                    ),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_vscode_layouts() {
+        let mcp_json = r#"{
+            // VS Code allows comments
+            "inputs": [],
+            "servers": {"gh": {"type": "stdio", "command": "gh-mcp"}},
+        }"#;
+        assert_eq!(
+            parse(Source::VsCode, mcp_json).unwrap(),
+            vec![("gh".to_string(), json!({"command": "gh-mcp"}))]
+        );
+        let settings = r#"{"mcp": {"servers": {"gh": {"command": "gh-mcp"}}}}"#;
+        assert_eq!(parse(Source::VsCode, settings).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_without_servers_fails() {
+        assert!(parse(Source::Cursor, "{}").is_err());
+        assert!(parse(Source::VsCode, r#"{"mcpServers": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Source::from_name("Claude"), Some(Source::Claude));
+        assert_eq!(Source::from_name("vscode"), Some(Source::VsCode));
+        assert_eq!(Source::from_name("zed"), None);
+    }
+
+    #[test]
+    fn test_merge_replaces_same_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(
+            &path,
+            r#"{"amp.mcpServers": {"a": {"command": "old"}, "b": {"command": "b"}}}"#,
+        )
+        .unwrap();
+        let mut config = Config::load(&path).unwrap();
+        merge(
+            &mut config,
+            &[
+                ("a".to_string(), json!({"command": "new"})),
+                ("c".to_string(), json!({"url": "https://c"})),
+            ],
+        );
+        assert_eq!(
+            config.get("amp.mcpServers"),
+            json!({"a": {"command": "new"}, "b": {"command": "b"}, "c": {"url": "https://c"}})
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! A history of the messages shown in the status line, so ones that were
+//! cleared by the next key press can be reviewed later.
+
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+/// How many messages are kept; older ones are dropped first.
+pub const CAPACITY: usize = 200;
+
+/// How serious a message is, judged from its wording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Classifies a status message. Status messages are plain strings, so
+    /// this goes by the words failures and cautions are phrased with.
+    pub fn of(text: &str) -> Self {
+        let lower = text.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| lower.contains(w));
+        if has(&[
+            "failed",
+            "error",
+            "invalid",
+            "can't",
+            "cannot",
+            "unavailable",
+            "rejected",
+        ]) {
+            Severity::Error
+        } else if has(&[
+            "ignored",
+            "deprecated",
+            "wrong type",
+            "changed on disk",
+            "conflict",
+            "not ",
+            "no ",
+            "skipped",
+        ]) {
+            Severity::Warning
+        } else {
+            Severity::Info
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// One logged status message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub at: SystemTime,
+    pub severity: Severity,
+    pub text: String,
+}
+
+/// The most recent status messages, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct MessageLog {
+    entries: VecDeque<Message>,
+}
+
+impl MessageLog {
+    /// Records a message, dropping the oldest once the log is full.
+    pub fn push(&mut self, text: &str, at: SystemTime) {
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Message {
+            at,
+            severity: Severity::of(text),
+            text: text.to_string(),
+        });
+    }
+
+    /// The messages, newest first.
+    pub fn newest_first(&self) -> impl Iterator<Item = &Message> {
+        self.entries.iter().rev()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(Severity::of("Saved settings.json"), Severity::Info);
+        assert_eq!(
+            Severity::of("Save failed: permission denied"),
+            Severity::Error
+        );
+        assert_eq!(
+            Severity::of("Can't turn \"x\" into a number"),
+            Severity::Error
+        );
+        assert_eq!(
+            Severity::of("Preferences ignored: bad key"),
+            Severity::Warning
+        );
+        assert_eq!(Severity::of("No servers selected."), Severity::Warning);
+    }
+
+    #[test]
+    fn test_log_drops_oldest() {
+        let mut log = MessageLog::default();
+        for i in 0..CAPACITY + 5 {
+            log.push(&format!("message {i}"), SystemTime::UNIX_EPOCH);
+        }
+        assert_eq!(log.len(), CAPACITY);
+        let newest: Vec<&str> = log.newest_first().map(|m| m.text.as_str()).collect();
+        assert_eq!(newest[0], format!("message {}", CAPACITY + 4));
+        assert_eq!(newest[CAPACITY - 1], "message 5");
+    }
+}
@@ -0,0 +1,124 @@
+//! Free-text notes attached to individual settings, stored in a hidden sidecar
+//! file next to the settings file so Amp never sees them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Notes for one settings file, keyed by setting name.
+#[derive(Debug, Clone, Default)]
+pub struct Notes {
+    /// Path to the sidecar file.
+    path: PathBuf,
+    notes: BTreeMap<String, String>,
+}
+
+impl Notes {
+    /// Returns the sidecar path for a settings file, e.g.
+    /// `~/.config/amp/.settings.json.notes`.
+    pub fn sidecar_path(settings: &Path) -> PathBuf {
+        let name = settings
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "settings.json".to_string());
+        settings.with_file_name(format!(".{name}.notes"))
+    }
+
+    /// Loads the notes for a settings file, or returns an empty set if there are none.
+    pub fn load(settings: &Path) -> Result<Self> {
+        let path = Self::sidecar_path(settings);
+        let notes = if path.exists() {
+            let contents =
+                fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("parsing {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, notes })
+    }
+
+    /// Returns the note for a key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.notes.get(key).map(String::as_str)
+    }
+
+    /// Sets the note for a key. An empty note removes it.
+    pub fn set(&mut self, key: &str, note: &str) {
+        let note = note.trim();
+        if note.is_empty() {
+            self.notes.remove(key);
+        } else {
+            self.notes.insert(key.to_string(), note.to_string());
+        }
+    }
+
+    /// Writes the notes to the sidecar file, deleting it when no notes remain.
+    pub fn save(&self) -> Result<()> {
+        if self.notes.is_empty() {
+            if self.path.exists() {
+                fs::remove_file(&self.path)
+                    .with_context(|| format!("removing {}", self.path.display()))?;
+            }
+            return Ok(());
+        }
+        let json = serde_json::to_string_pretty(&self.notes).context("serializing notes")?;
+        fs::write(&self.path, json + "\n")
+            .with_context(|| format!("writing {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path_is_hidden() {
+        assert_eq!(
+            Notes::sidecar_path(Path::new("/home/u/.config/amp/settings.json")),
+            PathBuf::from("/home/u/.config/amp/.settings.json.notes")
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+
+        let mut notes = Notes::load(&settings).unwrap();
+        assert!(notes.get("amp.showCosts").is_none());
+        notes.set("amp.showCosts", "  off for demos, see TICKET-12 ");
+        notes.save().unwrap();
+
+        let reloaded = Notes::load(&settings).unwrap();
+        assert_eq!(
+            reloaded.get("amp.showCosts"),
+            Some("off for demos, see TICKET-12")
+        );
+    }
+
+    #[test]
+    fn test_empty_note_removes_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+
+        let mut notes = Notes::load(&settings).unwrap();
+        notes.set("amp.showCosts", "note");
+        notes.save().unwrap();
+        assert!(Notes::sidecar_path(&settings).exists());
+
+        notes.set("amp.showCosts", "");
+        notes.save().unwrap();
+        assert!(!Notes::sidecar_path(&settings).exists());
+    }
+
+    #[test]
+    fn test_load_invalid_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        fs::write(Notes::sidecar_path(&settings), "nope").unwrap();
+        assert!(Notes::load(&settings).is_err());
+    }
+}
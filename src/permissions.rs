@@ -0,0 +1,390 @@
+//! Evaluates `amp.permissions` rules against a tool call the way Amp does:
+//! rules are tried in order and the first whose tool and argument patterns
+//! match decides the action.
+
+use serde_json::{Map, Value};
+
+use crate::matcher;
+
+/// Why a rule did or didn't apply to the call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleOutcome {
+    Matched,
+    /// The rule's `tool` pattern doesn't match the tool name.
+    OtherTool,
+    /// The named argument is missing or doesn't match its pattern.
+    ArgumentDiffers(String),
+    /// The rule only applies in another context (e.g. subagents).
+    OtherContext(String),
+    /// A pattern in the rule isn't valid glob or regex syntax.
+    InvalidPattern(String),
+    /// The rule uses something the tester can't evaluate.
+    Unsupported(String),
+    /// The rule isn't an object with a `tool`.
+    Malformed,
+}
+
+/// The result of testing a call against every rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// One outcome per rule, in order, up to and including the first match.
+    pub checks: Vec<RuleOutcome>,
+    /// Index of the deciding rule.
+    pub matched: Option<usize>,
+    /// The deciding rule's `action`, with its `to` program for delegation.
+    pub decision: Option<String>,
+}
+
+/// Tests a tool call against rules in order, stopping at the first match.
+/// Rules scoped to a `context` other than the main thread are skipped.
+pub fn evaluate(rules: &[Value], tool: &str, args: &Map<String, Value>) -> Evaluation {
+    let mut checks = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        let outcome = check_rule(rule, tool, args);
+        let matched = outcome == RuleOutcome::Matched;
+        checks.push(outcome);
+        if matched {
+            let action = rule.get("action").and_then(Value::as_str).unwrap_or("?");
+            let decision = match rule.get("to").and_then(Value::as_str) {
+                Some(to) if action == "delegate" => format!("delegate to {to}"),
+                _ => action.to_string(),
+            };
+            return Evaluation {
+                checks,
+                matched: Some(i),
+                decision: Some(decision),
+            };
+        }
+    }
+    Evaluation {
+        checks,
+        matched: None,
+        decision: None,
+    }
+}
+
+fn check_rule(rule: &Value, tool: &str, args: &Map<String, Value>) -> RuleOutcome {
+    let Some(pattern) = rule.get("tool").and_then(Value::as_str) else {
+        return RuleOutcome::Malformed;
+    };
+    if let Some(error) = matcher::rule_error(rule) {
+        return RuleOutcome::InvalidPattern(error);
+    }
+    if !matcher::glob_match(pattern, tool) {
+        return RuleOutcome::OtherTool;
+    }
+    if let Some(context) = rule.get("context").and_then(Value::as_str) {
+        if context != "thread" {
+            return RuleOutcome::OtherContext(context.to_string());
+        }
+    }
+    let Some(matches) = rule.get("matches").and_then(Value::as_object) else {
+        return RuleOutcome::Matched;
+    };
+    for (name, pattern) in matches {
+        if let Some(regex) = find_regex(pattern) {
+            return RuleOutcome::Unsupported(format!("regex {regex} on \"{name}\""));
+        }
+        if !args
+            .get(name)
+            .is_some_and(|actual| value_matches(pattern, actual))
+        {
+            return RuleOutcome::ArgumentDiffers(name.clone());
+        }
+    }
+    RuleOutcome::Matched
+}
+
+/// Finds a regex pattern anywhere in a `matches` value.
+fn find_regex(pattern: &Value) -> Option<&str> {
+    match pattern {
+        Value::String(s) => matcher::regex_source(s).map(|_| s.as_str()),
+        Value::Array(items) => items.iter().find_map(find_regex),
+        Value::Object(map) => map.values().find_map(find_regex),
+        _ => None,
+    }
+}
+
+/// Matches an argument against a pattern: strings are globs, arrays match
+/// if any element does, objects match field by field, and anything else
+/// must be equal.
+fn value_matches(pattern: &Value, actual: &Value) -> bool {
+    match (pattern, actual) {
+        (Value::String(p), Value::String(a)) => matcher::glob_match(p, a),
+        (Value::String(p), other) => matcher::glob_match(p, &other.to_string()),
+        (Value::Array(options), _) => options.iter().any(|p| value_matches(p, actual)),
+        (Value::Object(fields), Value::Object(actual)) => fields
+            .iter()
+            .all(|(k, p)| actual.get(k).is_some_and(|a| value_matches(p, a))),
+        _ => pattern == actual,
+    }
+}
+
+/// The `amp.mcpPermissions` rules whose `matches` all fit a server entry
+/// from `amp.mcpServers` (its `command`, `args`, `url`, …), in order, so the
+/// first one decides. Rules with regex patterns aren't evaluated.
+pub fn mcp_rules_for(rules: &[Value], server: &Value) -> Vec<usize> {
+    let applies = |rule: &Value| {
+        let Some(matches) = rule.get("matches").and_then(Value::as_object) else {
+            return false;
+        };
+        !matches.is_empty()
+            && matcher::rule_error(rule).is_none()
+            && matches.iter().all(|(field, pattern)| {
+                find_regex(pattern).is_none()
+                    && server
+                        .get(field)
+                        .is_some_and(|actual| value_matches(pattern, actual))
+            })
+    };
+    (0..rules.len()).filter(|&i| applies(&rules[i])).collect()
+}
+
+/// Why a rule can never decide a call: an earlier rule matches every call
+/// it would.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shadowing {
+    /// The rule is identical to the earlier one at this index.
+    DuplicateOf(usize),
+    /// The earlier rule at this index is at least as broad.
+    ShadowedBy(usize),
+}
+
+impl Shadowing {
+    pub fn describe(self) -> String {
+        match self {
+            Shadowing::DuplicateOf(i) => format!("duplicates rule {i}"),
+            Shadowing::ShadowedBy(i) => {
+                format!("never applies: rule {i} matches every call it would")
+            }
+        }
+    }
+}
+
+/// Finds, for each rule, an earlier rule that decides every call it would.
+/// Coverage is judged conservatively: a glob covers another pattern only if
+/// they're equal, it's all `*`, it's plain text then `*` and the other starts
+/// with that text, or the other is plain text it matches. Other overlapping
+/// globs, and regexes, aren't reported.
+pub fn shadowing(rules: &[Value]) -> Vec<Option<Shadowing>> {
+    (0..rules.len())
+        .map(|i| {
+            let earlier = &rules[..i];
+            if let Some(j) = earlier.iter().position(|r| *r == rules[i]) {
+                return Some(Shadowing::DuplicateOf(j));
+            }
+            earlier
+                .iter()
+                .position(|r| rule_covers(r, &rules[i]))
+                .map(Shadowing::ShadowedBy)
+        })
+        .collect()
+}
+
+/// Whether every call `later` matches is matched by `earlier` too.
+fn rule_covers(earlier: &Value, later: &Value) -> bool {
+    let tools = (
+        earlier.get("tool").and_then(Value::as_str),
+        later.get("tool").and_then(Value::as_str),
+    );
+    let (Some(tool), Some(later_tool)) = tools else {
+        return false;
+    };
+    if matcher::rule_error(earlier).is_some() || matcher::rule_error(later).is_some() {
+        return false;
+    }
+    let context = earlier.get("context");
+    if context.is_some() && context != later.get("context") {
+        return false;
+    }
+    if !pattern_covers(tool, later_tool) {
+        return false;
+    }
+    let Some(matches) = earlier.get("matches").and_then(Value::as_object) else {
+        return true;
+    };
+    let later_matches = later.get("matches").and_then(Value::as_object);
+    matches.iter().all(|(name, pattern)| {
+        later_matches
+            .and_then(|m| m.get(name))
+            .is_some_and(|later| value_covers(pattern, later))
+    })
+}
+
+/// Whether a `matches` value accepts everything another one does.
+fn value_covers(pattern: &Value, later: &Value) -> bool {
+    if pattern == later {
+        return true;
+    }
+    match (pattern, later) {
+        (_, Value::Array(options)) => options.iter().all(|l| value_covers(pattern, l)),
+        (Value::Array(options), _) => options.iter().any(|p| value_covers(p, later)),
+        (Value::String(p), Value::String(l)) => pattern_covers(p, l),
+        (Value::Object(fields), Value::Object(later)) => fields
+            .iter()
+            .all(|(k, p)| later.get(k).is_some_and(|l| value_covers(p, l))),
+        (_, Value::Object(_)) => false,
+        // A number, boolean or null only matches itself.
+        _ => value_matches(pattern, later),
+    }
+}
+
+/// Whether a glob matches everything another pattern does.
+fn pattern_covers(pattern: &str, later: &str) -> bool {
+    if pattern == later {
+        return true;
+    }
+    if matcher::regex_source(pattern).is_some() || matcher::regex_source(later).is_some() {
+        return false;
+    }
+    let is_plain = |s: &str| !s.contains(['*', '?', '[', '\\']);
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        if is_plain(prefix) && later.starts_with(prefix) {
+            return true;
+        }
+    }
+    pattern.chars().all(|c| c == '*') || (is_plain(later) && matcher::glob_match(pattern, later))
+}
+
+/// Turns what the user typed as arguments into an argument map: a JSON
+/// object is used as is, and for `Bash` plain text is the command.
+pub fn parse_args(tool: &str, text: &str) -> Result<Map<String, Value>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(Map::new());
+    }
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(map)) => Ok(map),
+        _ if tool == "Bash" => {
+            let mut map = Map::new();
+            map.insert("cmd".to_string(), Value::String(text.to_string()));
+            Ok(map)
+        }
+        _ => Err("arguments must be a JSON object, e.g. {\"path\": \"src/*\"}".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = vec![
+            json!({"tool": "Bash", "matches": {"cmd": "git push*"}, "action": "ask"}),
+            json!({"tool": "Bash", "matches": {"cmd": ["git *", "ls *"]}, "action": "allow"}),
+            json!({"tool": "*", "action": "delegate", "to": "guard"}),
+        ];
+        let args = parse_args("Bash", "git status").unwrap();
+        let eval = evaluate(&rules, "Bash", &args);
+        assert_eq!(eval.matched, Some(1));
+        assert_eq!(eval.decision.as_deref(), Some("allow"));
+        assert_eq!(
+            eval.checks,
+            vec![
+                RuleOutcome::ArgumentDiffers("cmd".to_string()),
+                RuleOutcome::Matched
+            ]
+        );
+
+        let eval = evaluate(&rules, "Read", &Map::new());
+        assert_eq!(eval.matched, Some(2));
+        assert_eq!(eval.decision.as_deref(), Some("delegate to guard"));
+        assert_eq!(eval.checks[0], RuleOutcome::OtherTool);
+    }
+
+    #[test]
+    fn test_skipped_rules_and_no_match() {
+        let rules = vec![
+            json!({"tool": "Bash", "context": "subagent", "action": "reject"}),
+            json!({"tool": "Bash", "matches": {"cmd": "/^rm /"}, "action": "reject"}),
+            json!("not a rule"),
+            json!({"tool": "Bash", "matches": {"cmd": "[rm"}, "action": "reject"}),
+        ];
+        let args = parse_args("Bash", r#"{"cmd": "rm -rf /"}"#).unwrap();
+        let eval = evaluate(&rules, "Bash", &args);
+        assert_eq!(eval.matched, None);
+        assert_eq!(
+            eval.checks,
+            vec![
+                RuleOutcome::OtherContext("subagent".to_string()),
+                RuleOutcome::Unsupported("regex /^rm / on \"cmd\"".to_string()),
+                RuleOutcome::Malformed,
+                RuleOutcome::InvalidPattern("cmd \"[rm\": unclosed [".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_string_arguments() {
+        let rules = vec![
+            json!({"tool": "edit_file", "matches": {"line": 3, "opts": {"dry": true}}, "action": "allow"}),
+        ];
+        let args =
+            parse_args("edit_file", r#"{"line": 3, "opts": {"dry": true, "x": 1}}"#).unwrap();
+        assert_eq!(evaluate(&rules, "edit_file", &args).matched, Some(0));
+        assert!(parse_args("edit_file", "not json").is_err());
+    }
+
+    #[test]
+    fn test_mcp_rules_for() {
+        let rules = vec![
+            json!({"matches": {"command": "npx", "args": "*server-github*"}, "action": "allow"}),
+            json!({"matches": {"url": "https://*.example.com/*"}, "action": "reject"}),
+            json!({"matches": {"command": "*"}, "action": "ask"}),
+            json!({"matches": {"command": "/^npx$/"}, "action": "ask"}),
+            json!({"action": "allow"}),
+        ];
+        let github =
+            json!({"command": "npx", "args": ["-y", "@modelcontextprotocol/server-github"]});
+        assert_eq!(mcp_rules_for(&rules, &github), vec![0, 2]);
+        let remote = json!({"url": "https://mcp.example.com/sse"});
+        assert_eq!(mcp_rules_for(&rules, &remote), vec![1]);
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let rules = vec![
+            json!({"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"}),
+            json!({"tool": "Bash", "matches": {"cmd": ["git status", "git log*"]}, "action": "ask"}),
+            json!({"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"}),
+            json!({"tool": "mcp__*", "context": "subagent", "action": "reject"}),
+            json!({"tool": "mcp__github__*", "action": "allow"}),
+            json!({"tool": "mcp__github__pr", "context": "subagent", "action": "ask"}),
+            json!({"tool": "Read", "matches": {"path": "src/*"}, "action": "allow"}),
+            json!({"tool": "Read", "action": "allow"}),
+            json!({"tool": "*", "action": "ask"}),
+            json!({"tool": "edit_file", "matches": {"line": 3}, "action": "allow"}),
+        ];
+        assert_eq!(
+            shadowing(&rules),
+            vec![
+                None,
+                Some(Shadowing::ShadowedBy(0)),
+                Some(Shadowing::DuplicateOf(0)),
+                None,
+                // The subagent-only rule doesn't cover the main thread.
+                None,
+                Some(Shadowing::ShadowedBy(3)),
+                None,
+                // Narrower rules above don't cover a broader one.
+                None,
+                None,
+                Some(Shadowing::ShadowedBy(8)),
+            ]
+        );
+
+        // Overlapping globs and regexes aren't judged.
+        let rules = vec![
+            json!({"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"}),
+            json!({"tool": "Bash", "matches": {"cmd": "git push*"}, "action": "ask"}),
+            json!({"tool": "Bash", "matches": {"cmd": "*push"}, "action": "ask"}),
+            json!({"tool": "Bash", "matches": {"cmd": "/^git /"}, "action": "ask"}),
+        ];
+        assert_eq!(
+            shadowing(&rules),
+            vec![None, Some(Shadowing::ShadowedBy(0)), None, None]
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! volt's own preferences, read from `~/.config/volt/config.toml`.
+//!
+//! ```toml
+//! theme = "solarized"
+//! autosave = true         # save a few seconds after each change
+//! editor = "code --wait"  # instead of $VISUAL/$EDITOR; `{file}` marks the path
+//! nested_keys = true      # `a.b.c` added in Advanced goes inside object `a.b`
+//! registry_url = "https://example.com/mcp-catalog.json"  # for refreshing `g r`
+//! sync_remote = "gist:0123abcd"  # for `volt sync`; also an https:// URL or a path
+//!
+//! [format]                # how settings files are saved; by default each keeps its own
+//! indent = 4              # spaces per level, or "tab"; reindents the whole file
+//! sort_keys = true        # put top-level keys in order by name
+//! trailing_newline = true
+//!
+//! [sidebar]               # written by volt when the sidebar is resized
+//! width = 22
+//! collapsed = false
+//!
+//! [keys]
+//! move_up = "up"          # arrow-only navigation
+//! move_down = "down"
+//! save = ["ctrl+s", "f2"]
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use toml::{Table, Value};
+
+use crate::config::Format;
+use crate::keymap::{Action, Keymap};
+use crate::theme::Theme;
+
+/// Preferences for volt itself, as opposed to the Amp settings it edits.
+#[derive(Debug, Clone, Default)]
+pub struct Prefs {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub sidebar: Sidebar,
+    pub autosave: bool,
+    /// The command line values are edited with, ahead of `$VISUAL` and
+    /// `$EDITOR`.
+    pub editor: Option<String>,
+    /// Whether dotted keys added in Advanced start out going inside the
+    /// object setting a prefix of them names.
+    pub nested_keys: bool,
+    /// Where the MCP server catalog is refreshed from, instead of volt's
+    /// repository.
+    pub registry_url: Option<String>,
+    /// Where `volt sync` pushes to and pulls from without `--remote`.
+    pub sync_remote: Option<String>,
+    /// How settings files are laid out when saved.
+    pub format: Format,
+}
+
+/// How the section sidebar is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sidebar {
+    /// Width in columns when expanded.
+    pub width: u16,
+    /// Whether only each section's first letter is shown.
+    pub collapsed: bool,
+}
+
+impl Sidebar {
+    pub const DEFAULT_WIDTH: u16 = 18;
+    pub const MIN_WIDTH: u16 = 12;
+    pub const MAX_WIDTH: u16 = 40;
+    /// Borders, the split-view marker, and one letter.
+    const COLLAPSED_WIDTH: u16 = 5;
+
+    /// The columns the sidebar takes up on screen.
+    pub fn shown_width(self) -> u16 {
+        if self.collapsed {
+            Self::COLLAPSED_WIDTH
+        } else {
+            self.width
+        }
+    }
+
+    /// Widens or narrows the sidebar by one column, expanding it first if
+    /// it was collapsed.
+    pub fn resize(&mut self, wider: bool) {
+        if self.collapsed {
+            self.collapsed = false;
+            return;
+        }
+        self.width = if wider {
+            (self.width + 1).min(Self::MAX_WIDTH)
+        } else {
+            (self.width - 1).max(Self::MIN_WIDTH)
+        };
+    }
+}
+
+impl Default for Sidebar {
+    fn default() -> Self {
+        Self {
+            width: Self::DEFAULT_WIDTH,
+            collapsed: false,
+        }
+    }
+}
+
+impl Prefs {
+    /// Returns the path volt reads its preferences from.
+    pub fn default_path() -> Result<PathBuf> {
+        let home = dirs::home_dir().context("could not determine home directory")?;
+        Ok(home.join(".config").join("volt").join("config.toml"))
+    }
+
+    /// Loads preferences from a file; a missing file means all defaults.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Parses the TOML text of a preferences file.
+    pub fn parse(text: &str) -> Result<Self> {
+        let table: Table = text.parse()?;
+        let mut prefs = Self::default();
+
+        if let Some(theme) = table.get("theme") {
+            let Some(name) = theme.as_str() else {
+                bail!("theme must be a string");
+            };
+            prefs.theme = Theme::by_name(name)?;
+        }
+
+        if let Some(autosave) = table.get("autosave") {
+            let Some(autosave) = autosave.as_bool() else {
+                bail!("autosave must be true or false");
+            };
+            prefs.autosave = autosave;
+        }
+
+        if let Some(url) = table.get("registry_url") {
+            let url = url
+                .as_str()
+                .filter(|u| u.starts_with("https://") || u.starts_with("http://"));
+            let Some(url) = url else {
+                bail!("registry_url must be an http:// or https:// URL");
+            };
+            prefs.registry_url = Some(url.to_string());
+        }
+
+        if let Some(remote) = table.get("sync_remote") {
+            let Some(remote) = remote.as_str() else {
+                bail!("sync_remote must be a string such as \"gist:<id>\"");
+            };
+            crate::sync::Remote::parse(remote).context("sync_remote")?;
+            prefs.sync_remote = Some(remote.to_string());
+        }
+
+        if let Some(nested) = table.get("nested_keys") {
+            let Some(nested) = nested.as_bool() else {
+                bail!("nested_keys must be true or false");
+            };
+            prefs.nested_keys = nested;
+        }
+
+        if let Some(editor) = table.get("editor") {
+            let words = editor.as_str().map(shell_words::split);
+            let Some(Ok(words)) = words else {
+                bail!("editor must be a command line such as \"code --wait\"");
+            };
+            if words.is_empty() {
+                bail!("editor must not be empty");
+            }
+            prefs.editor = editor.as_str().map(str::to_string);
+        }
+
+        if let Some(format) = table.get("format") {
+            let Some(format) = format.as_table() else {
+                bail!("[format] must be a table");
+            };
+            if let Some(indent) = format.get("indent") {
+                prefs.format.indent = Some(match indent {
+                    Value::Integer(n @ 1..=8) => " ".repeat(*n as usize),
+                    Value::String(s) if s == "tab" => "\t".to_string(),
+                    _ => bail!("format indent must be a number of spaces from 1 to 8, or \"tab\""),
+                });
+            }
+            if let Some(sort) = format.get("sort_keys") {
+                let Some(sort) = sort.as_bool() else {
+                    bail!("format sort_keys must be true or false");
+                };
+                prefs.format.sort_keys = sort;
+            }
+            if let Some(newline) = format.get("trailing_newline") {
+                let Some(newline) = newline.as_bool() else {
+                    bail!("format trailing_newline must be true or false");
+                };
+                prefs.format.trailing_newline = Some(newline);
+            }
+        }
+
+        if let Some(sidebar) = table.get("sidebar") {
+            let Some(sidebar) = sidebar.as_table() else {
+                bail!("[sidebar] must be a table");
+            };
+            if let Some(width) = sidebar.get("width") {
+                let range = i64::from(Sidebar::MIN_WIDTH)..=i64::from(Sidebar::MAX_WIDTH);
+                match width.as_integer() {
+                    Some(w) if range.contains(&w) => prefs.sidebar.width = w as u16,
+                    _ => bail!(
+                        "sidebar width must be a number from {} to {}",
+                        Sidebar::MIN_WIDTH,
+                        Sidebar::MAX_WIDTH
+                    ),
+                }
+            }
+            if let Some(collapsed) = sidebar.get("collapsed") {
+                let Some(collapsed) = collapsed.as_bool() else {
+                    bail!("sidebar collapsed must be true or false");
+                };
+                prefs.sidebar.collapsed = collapsed;
+            }
+        }
+
+        if let Some(keys) = table.get("keys") {
+            let Some(keys) = keys.as_table() else {
+                bail!("[keys] must be a table");
+            };
+            for (name, value) in keys {
+                let action =
+                    Action::from_name(name).with_context(|| format!("unknown action '{name}'"))?;
+                let specs: Vec<String> = match value {
+                    Value::String(s) => vec![s.clone()],
+                    Value::Array(items) => items
+                        .iter()
+                        .map(|v| v.as_str().map(str::to_string))
+                        .collect::<Option<_>>()
+                        .with_context(|| format!("keys for '{name}' must be strings"))?,
+                    _ => bail!("keys for '{name}' must be a string or an array of strings"),
+                };
+                prefs.keymap.bind(action, &specs)?;
+            }
+        }
+
+        Ok(prefs)
+    }
+}
+
+/// Records the sidebar layout in the preferences file, replacing any
+/// earlier `[sidebar]` table and leaving the rest of the file as written.
+/// A file that doesn't parse is left alone.
+pub fn save_sidebar(path: &Path, sidebar: Sidebar) -> Result<()> {
+    let text = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?
+    } else {
+        String::new()
+    };
+    text.parse::<Table>()
+        .with_context(|| format!("parsing {}", path.display()))?;
+
+    // Drop the old table: its header and the lines up to the next header.
+    let mut kept: Vec<&str> = Vec::new();
+    let mut in_sidebar = false;
+    for line in text.lines() {
+        if let Some(header) = line.trim_start().strip_prefix('[') {
+            in_sidebar = header.split(']').next().map(str::trim) == Some("sidebar");
+        }
+        if !in_sidebar {
+            kept.push(line);
+        }
+    }
+    while kept.last().is_some_and(|l| l.trim().is_empty()) {
+        kept.pop();
+    }
+    let mut out = kept.join("\n");
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    out.push_str(&format!(
+        "[sidebar]\nwidth = {}\ncollapsed = {}\n",
+        sidebar.width, sidebar.collapsed
+    ));
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    fs::write(path, out).with_context(|| format!("writing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn test_parse_keys() {
+        let prefs = Prefs::parse(
+            r#"
+            [keys]
+            move_up = "up"
+            save = ["ctrl+s", "f2"]
+            "#,
+        )
+        .unwrap();
+        let keymap = &prefs.keymap;
+        assert_eq!(keymap.lookup(KeyCode::Char('k'), KeyModifiers::NONE), None);
+        assert_eq!(
+            keymap.lookup(KeyCode::F(2), KeyModifiers::NONE),
+            Some(Action::Save)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('j'), KeyModifiers::NONE),
+            Some(Action::MoveDown)
+        );
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        assert_eq!(Prefs::parse("").unwrap().theme, Theme::dark());
+        assert_eq!(
+            Prefs::parse("theme = \"light\"").unwrap().theme,
+            Theme::light()
+        );
+        assert!(Prefs::parse("theme = \"neon\"").is_err());
+        assert!(Prefs::parse("theme = 3").is_err());
+    }
+
+    #[test]
+    fn test_parse_autosave() {
+        assert!(!Prefs::parse("").unwrap().autosave);
+        assert!(Prefs::parse("autosave = true").unwrap().autosave);
+        assert!(Prefs::parse("autosave = \"yes\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_registry_url() {
+        assert_eq!(Prefs::parse("").unwrap().registry_url, None);
+        assert_eq!(
+            Prefs::parse("registry_url = \"https://example.com/c.json\"")
+                .unwrap()
+                .registry_url
+                .as_deref(),
+            Some("https://example.com/c.json")
+        );
+        assert!(Prefs::parse("registry_url = \"c.json\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(Prefs::parse("").unwrap().format, Format::default());
+        let prefs =
+            Prefs::parse("[format]\nindent = 4\nsort_keys = true\ntrailing_newline = false")
+                .unwrap();
+        assert_eq!(
+            prefs.format,
+            Format {
+                indent: Some("    ".into()),
+                sort_keys: true,
+                trailing_newline: Some(false),
+            }
+        );
+        let tabs = Prefs::parse("[format]\nindent = \"tab\"").unwrap();
+        assert_eq!(tabs.format.indent.as_deref(), Some("\t"));
+        assert!(Prefs::parse("[format]\nindent = 0").is_err());
+        assert!(Prefs::parse("[format]\nindent = \"spaces\"").is_err());
+        assert!(Prefs::parse("format = 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_sync_remote() {
+        assert_eq!(
+            Prefs::parse("sync_remote = \"gist:0123abcd\"")
+                .unwrap()
+                .sync_remote
+                .as_deref(),
+            Some("gist:0123abcd")
+        );
+        assert!(Prefs::parse("sync_remote = \"gist:\"").is_err());
+        assert!(Prefs::parse("sync_remote = 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_nested_keys() {
+        assert!(!Prefs::parse("").unwrap().nested_keys);
+        assert!(Prefs::parse("nested_keys = true").unwrap().nested_keys);
+        assert!(Prefs::parse("nested_keys = 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_editor() {
+        assert_eq!(Prefs::parse("").unwrap().editor, None);
+        assert_eq!(
+            Prefs::parse("editor = \"subl -w {file}\"").unwrap().editor,
+            Some("subl -w {file}".to_string())
+        );
+        assert!(Prefs::parse("editor = \"vim 'x\"").is_err());
+        assert!(Prefs::parse("editor = \" \"").is_err());
+        assert!(Prefs::parse("editor = 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Prefs::parse("[keys]\nfly = \"x\"").is_err());
+        assert!(Prefs::parse("[keys]\nsave = 1").is_err());
+        assert!(Prefs::parse("[keys]\nsave = [\"ctrl+s\", 2]").is_err());
+        assert!(Prefs::parse("keys = 1").is_err());
+        assert!(Prefs::parse("not toml = = =").is_err());
+    }
+
+    #[test]
+    fn test_load_missing_file_uses_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefs = Prefs::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(
+            prefs.keymap.lookup(KeyCode::Char('q'), KeyModifiers::NONE),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn test_parse_sidebar() {
+        let prefs = Prefs::parse("[sidebar]\nwidth = 24\ncollapsed = true").unwrap();
+        assert_eq!(
+            prefs.sidebar,
+            Sidebar {
+                width: 24,
+                collapsed: true
+            }
+        );
+        assert_eq!(prefs.sidebar.shown_width(), 5);
+        assert!(Prefs::parse("[sidebar]\nwidth = 200").is_err());
+        assert!(Prefs::parse("[sidebar]\ncollapsed = 1").is_err());
+    }
+
+    #[test]
+    fn test_save_sidebar_keeps_the_rest_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("volt").join("config.toml");
+        save_sidebar(&path, Sidebar::default()).unwrap();
+        assert_eq!(Prefs::load(&path).unwrap().sidebar, Sidebar::default());
+
+        fs::write(
+            &path,
+            "theme = \"light\" # mine\n\n[sidebar]\nwidth = 30\n\n[keys]\nsave = \"f2\"\n",
+        )
+        .unwrap();
+        let sidebar = Sidebar {
+            width: 14,
+            collapsed: true,
+        };
+        save_sidebar(&path, sidebar).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("theme = \"light\" # mine\n"));
+        assert_eq!(text.matches("[sidebar]").count(), 1);
+        let prefs = Prefs::load(&path).unwrap();
+        assert_eq!(prefs.sidebar, sidebar);
+        assert_eq!(prefs.theme, Theme::light());
+        assert_eq!(
+            prefs.keymap.lookup(KeyCode::F(2), KeyModifiers::NONE),
+            Some(Action::Save)
+        );
+
+        fs::write(&path, "not toml = = =").unwrap();
+        assert!(save_sidebar(&path, sidebar).is_err());
+    }
+
+    #[test]
+    fn test_sidebar_resize_stays_in_range() {
+        let mut sidebar = Sidebar {
+            width: Sidebar::MIN_WIDTH,
+            collapsed: true,
+        };
+        sidebar.resize(false);
+        assert!(!sidebar.collapsed);
+        sidebar.resize(false);
+        assert_eq!(sidebar.width, Sidebar::MIN_WIDTH);
+        sidebar.resize(true);
+        assert_eq!(sidebar.width, Sidebar::MIN_WIDTH + 1);
+    }
+}
@@ -0,0 +1,227 @@
+//! Named bundles of `amp.permissions` rules, picked from the preset picker
+//! to append to the rules or replace them. A few ship with volt; more can
+//! be defined in `~/.config/volt/presets/`, one JSON file per preset, named
+//! after it:
+//!
+//! ```json
+//! {
+//!   "description": "Ask before any shell command",
+//!   "rules": [{ "tool": "Bash", "action": "ask" }]
+//! }
+//! ```
+//!
+//! A preset file with a built-in preset's name replaces it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+/// A named set of permission rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub description: String,
+    pub rules: Vec<Value>,
+    /// Whether it was read from the presets directory.
+    pub user: bool,
+}
+
+/// Returns the directory user presets are stored in.
+pub fn default_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("could not determine home directory")?;
+    Ok(home.join(".config").join("volt").join("presets"))
+}
+
+/// The presets volt ships with.
+pub fn builtin() -> Vec<Preset> {
+    let preset = |name: &str, description: &str, rules: Value| Preset {
+        name: name.to_string(),
+        description: description.to_string(),
+        rules: rules.as_array().cloned().unwrap_or_default(),
+        user: false,
+    };
+    vec![
+        preset(
+            "read-only",
+            "Read and search only; no edits or shell commands",
+            json!([
+                {"tool": "Read", "action": "allow"},
+                {"tool": "Grep", "action": "allow"},
+                {"tool": "glob", "action": "allow"},
+                {"tool": "finder", "action": "allow"},
+                {"tool": "edit_file", "action": "reject"},
+                {"tool": "create_file", "action": "reject"},
+                {"tool": "format_file", "action": "reject"},
+                {"tool": "undo_edit", "action": "reject"},
+                {"tool": "Bash", "action": "reject"}
+            ]),
+        ),
+        preset(
+            "no-network",
+            "No web tools or network commands in the shell",
+            json!([
+                {"tool": "web_search", "action": "reject"},
+                {"tool": "read_web_page", "action": "reject"},
+                {
+                    "tool": "Bash",
+                    "matches": {"cmd": ["curl *", "wget *", "ssh *", "scp *", "rsync *", "nc *"]},
+                    "action": "reject"
+                }
+            ]),
+        ),
+        preset(
+            "git-only",
+            "Only git in the shell, and pushes ask first",
+            json!([
+                {"tool": "Bash", "matches": {"cmd": "git push*"}, "action": "ask"},
+                {"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"},
+                {"tool": "Bash", "action": "reject"}
+            ]),
+        ),
+    ]
+}
+
+fn path_for(dir: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(|c: char| c == '/' || c == '\\' || c.is_control())
+    {
+        bail!("invalid preset name '{name}'");
+    }
+    Ok(dir.join(format!("{name}.json")))
+}
+
+/// Reads one preset file.
+fn load(path: &Path) -> Result<Preset> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let root: Value =
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    let Some(rules) = root.get("rules").and_then(Value::as_array) else {
+        bail!("{} has no \"rules\" array", path.display());
+    };
+    if let Some(i) = rules
+        .iter()
+        .position(|r| !r.get("tool").is_some_and(Value::is_string))
+    {
+        bail!("rule {i} in {} has no \"tool\"", path.display());
+    }
+    Ok(Preset {
+        name: path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        description: root
+            .get("description")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        rules: rules.clone(),
+        user: true,
+    })
+}
+
+/// Returns the built-in presets followed by the user's, sorted by name,
+/// with a message for each preset file that couldn't be read.
+pub fn load_all(dir: Option<&Path>) -> (Vec<Preset>, Vec<String>) {
+    let mut presets = builtin();
+    let mut errors = Vec::new();
+    let entries = dir.and_then(|dir| fs::read_dir(dir).ok());
+    let mut paths: Vec<PathBuf> = entries
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        match load(&path) {
+            Ok(preset) => {
+                presets.retain(|p| p.name != preset.name);
+                presets.push(preset);
+            }
+            Err(e) => errors.push(format!("{e:#}")),
+        }
+    }
+    presets.sort_by(|a, b| a.user.cmp(&b.user).then_with(|| a.name.cmp(&b.name)));
+    (presets, errors)
+}
+
+/// Saves rules as a user preset, replacing any with the same name.
+pub fn save(dir: &Path, name: &str, rules: &[Value]) -> Result<PathBuf> {
+    let path = path_for(dir, name)?;
+    let mut root = Map::new();
+    root.insert("description".to_string(), Value::String(String::new()));
+    root.insert("rules".to_string(), Value::Array(rules.to_vec()));
+    fs::create_dir_all(dir).with_context(|| format!("creating directory {}", dir.display()))?;
+    let json = serde_json::to_string_pretty(&Value::Object(root))?;
+    fs::write(&path, json + "\n").with_context(|| format!("writing {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{matcher, permissions, tools};
+
+    #[test]
+    fn test_builtin_presets_are_sound() {
+        for preset in builtin() {
+            assert!(
+                permissions::shadowing(&preset.rules)
+                    .iter()
+                    .all(Option::is_none),
+                "{} has shadowed rules",
+                preset.name
+            );
+            for rule in &preset.rules {
+                assert_eq!(matcher::rule_error(rule), None);
+                let tool = rule["tool"].as_str().unwrap();
+                assert!(tools::is_known_tool(tool, &[]), "{tool}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_all_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("git-only.json"),
+            r#"{"rules": [{"tool": "Bash", "action": "ask"}]}"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join("broken.json"), r#"{"rules": [{}]}"#).unwrap();
+        save(
+            dir.path(),
+            "mine",
+            &[json!({"tool": "Read", "action": "allow"})],
+        )
+        .unwrap();
+        assert!(save(dir.path(), "../up", &[]).is_err());
+
+        let (presets, errors) = load_all(Some(dir.path()));
+        let names: Vec<(&str, bool)> = presets.iter().map(|p| (p.name.as_str(), p.user)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("no-network", false),
+                ("read-only", false),
+                ("git-only", true),
+                ("mine", true)
+            ]
+        );
+        assert_eq!(
+            presets[2].rules,
+            vec![json!({"tool": "Bash", "action": "ask"})]
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("rule 0 in "), "{}", errors[0]);
+
+        let (presets, errors) = load_all(None);
+        let names: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["git-only", "no-network", "read-only"]);
+        assert!(errors.is_empty());
+    }
+}
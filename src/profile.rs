@@ -0,0 +1,237 @@
+//! Named snapshots of the settings, stored in `~/.config/volt/profiles/` so
+//! a whole setup (or just some sections of it) can be switched at once.
+//!
+//! A profile is a JSON file holding the settings it restores. A partial
+//! profile also lists the sections it covers; loading it only replaces the
+//! keys in those sections.
+//!
+//! ```json
+//! {
+//!   "sections": ["MCPs"],
+//!   "settings": { "amp.mcpServers": { "github": { "command": "gh-mcp" } } }
+//! }
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use crate::settings::{self, Section};
+
+/// A loaded profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// The sections a partial profile covers; `None` for a full snapshot.
+    pub sections: Option<Vec<Section>>,
+    pub settings: Map<String, Value>,
+}
+
+/// Returns the directory profiles are stored in.
+pub fn default_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("could not determine home directory")?;
+    Ok(home.join(".config").join("volt").join("profiles"))
+}
+
+/// Returns the names of the saved profiles, sorted.
+pub fn list(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| p.file_stem()?.to_str().map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+fn path_for(dir: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(|c: char| c == '/' || c == '\\' || c.is_control())
+    {
+        bail!("invalid profile name '{name}'");
+    }
+    Ok(dir.join(format!("{name}.json")))
+}
+
+/// The section a key is shown in; keys volt doesn't know are Advanced.
+fn section_of(key: &str) -> Section {
+    settings::section_for_key(key).unwrap_or(Section::Advanced)
+}
+
+/// Snapshots the settings into a profile, limited to `sections` when given,
+/// replacing any profile with the same name.
+pub fn save(
+    dir: &Path,
+    name: &str,
+    config: &Config,
+    sections: Option<&[Section]>,
+) -> Result<PathBuf> {
+    let path = path_for(dir, name)?;
+    let settings: Map<String, Value> = config
+        .entries()
+        .filter(|(key, _)| sections.is_none_or(|s| s.contains(&section_of(key))))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    let mut root = Map::new();
+    if let Some(sections) = sections {
+        let labels = sections
+            .iter()
+            .map(|s| Value::String(s.label().to_string()));
+        root.insert("sections".to_string(), Value::Array(labels.collect()));
+    }
+    root.insert("settings".to_string(), Value::Object(settings));
+
+    fs::create_dir_all(dir).with_context(|| format!("creating directory {}", dir.display()))?;
+    let json = serde_json::to_string_pretty(&Value::Object(root))?;
+    fs::write(&path, json + "\n").with_context(|| format!("writing {}", path.display()))?;
+    Ok(path)
+}
+
+/// Reads a saved profile.
+pub fn load(dir: &Path, name: &str) -> Result<Profile> {
+    let path = path_for(dir, name)?;
+    if !path.exists() {
+        bail!("no profile named '{name}'");
+    }
+    let text = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let root: Value =
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+
+    let sections = match root.get("sections") {
+        None => None,
+        Some(Value::Array(labels)) => Some(
+            labels
+                .iter()
+                .map(|l| {
+                    l.as_str()
+                        .and_then(Section::from_label)
+                        .with_context(|| format!("unknown section {l} in {}", path.display()))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        Some(_) => bail!("\"sections\" in {} must be an array", path.display()),
+    };
+    let Some(settings) = root.get("settings").and_then(Value::as_object) else {
+        bail!("{} has no \"settings\" object", path.display());
+    };
+    Ok(Profile {
+        sections,
+        settings: settings.clone(),
+    })
+}
+
+impl Profile {
+    /// Replaces the settings the profile covers: everything for a full
+    /// profile, or only its sections' keys for a partial one.
+    pub fn apply(&self, config: &mut Config) {
+        let covered = |key: &str| {
+            self.sections
+                .as_ref()
+                .is_none_or(|s| s.contains(&section_of(key)))
+        };
+        let stale: Vec<String> = config
+            .entries()
+            .map(|(key, _)| key.clone())
+            .filter(|key| covered(key) && !self.settings.contains_key(key))
+            .collect();
+        for key in stale {
+            config.remove(&key);
+        }
+        for (key, value) in &self.settings {
+            if covered(key) && config.get_raw(key) != Some(value) {
+                config.set(key, value.clone());
+            }
+        }
+    }
+
+    /// Describes what the profile covers, e.g. "all settings" or "MCPs, Tools".
+    pub fn scope_label(&self) -> String {
+        match &self.sections {
+            None => "all settings".to_string(),
+            Some(sections) => sections
+                .iter()
+                .map(|s| s.label())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config_with(dir: &Path, contents: &str) -> Config {
+        let path = dir.join("settings.json");
+        fs::write(&path, contents).unwrap();
+        Config::load(&path).unwrap()
+    }
+
+    #[test]
+    fn test_full_profile_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = dir.path().join("profiles");
+        let work = config_with(
+            dir.path(),
+            r#"{"amp.terminal.theme": "nord", "amp.mcpServers": {"a": {"command": "a"}}}"#,
+        );
+        save(&profiles, "work", &work, None).unwrap();
+        assert_eq!(list(&profiles), vec!["work"]);
+
+        let mut home = config_with(dir.path(), r#"{"custom.key": 1}"#);
+        let profile = load(&profiles, "work").unwrap();
+        assert_eq!(profile.scope_label(), "all settings");
+        profile.apply(&mut home);
+        assert!(home.is_dirty());
+        assert_eq!(home.get_raw("custom.key"), None);
+        assert_eq!(home.get("amp.terminal.theme"), "nord");
+        assert_eq!(home.get("amp.mcpServers"), json!({"a": {"command": "a"}}));
+    }
+
+    #[test]
+    fn test_partial_profile_only_touches_its_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = dir.path().join("profiles");
+        let work = config_with(
+            dir.path(),
+            r#"{"amp.terminal.theme": "nord", "amp.mcpServers": {"a": {"command": "a"}}}"#,
+        );
+        save(&profiles, "mcp", &work, Some(&[Section::Mcps])).unwrap();
+
+        let mut home = config_with(
+            dir.path(),
+            r#"{"amp.terminal.theme": "light", "amp.mcpPermissions": []}"#,
+        );
+        let profile = load(&profiles, "mcp").unwrap();
+        assert_eq!(profile.sections, Some(vec![Section::Mcps]));
+        assert!(!profile.settings.contains_key("amp.terminal.theme"));
+        profile.apply(&mut home);
+        assert_eq!(home.get("amp.terminal.theme"), "light");
+        assert_eq!(home.get_raw("amp.mcpPermissions"), None);
+        assert_eq!(home.get("amp.mcpServers"), json!({"a": {"command": "a"}}));
+    }
+
+    #[test]
+    fn test_bad_names_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_with(dir.path(), "{}");
+        assert!(save(dir.path(), "../escape", &config, None).is_err());
+        assert!(save(dir.path(), "", &config, None).is_err());
+        assert!(load(dir.path(), "missing").is_err());
+        fs::write(
+            dir.path().join("odd.json"),
+            r#"{"sections": ["Nope"], "settings": {}}"#,
+        )
+        .unwrap();
+        assert!(load(dir.path(), "odd").is_err());
+    }
+}
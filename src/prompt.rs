@@ -0,0 +1,194 @@
+//! Help for the one-line prompts: `Tab` completion from known candidates,
+//! and `↑`/`↓` recall of what was entered earlier in the session.
+
+use crate::app::InputMode;
+
+/// Entries accepted at each prompt this session, and where `↑`/`↓`
+/// browsing of them stands.
+#[derive(Debug, Default)]
+pub struct PromptHistory {
+    /// Oldest first; an entry typed again moves to the end.
+    entries: Vec<(InputMode, String)>,
+    browsing: Option<Browsing>,
+}
+
+/// A recalled entry on screen, and what had been typed before recalling.
+#[derive(Debug)]
+struct Browsing {
+    mode: InputMode,
+    index: usize,
+    draft: String,
+    shown: String,
+}
+
+impl PromptHistory {
+    /// Remembers an entry accepted at a prompt.
+    pub fn record(&mut self, mode: &InputMode, entry: &str) {
+        self.browsing = None;
+        let entry = entry.trim();
+        if entry.is_empty() {
+            return;
+        }
+        self.entries.retain(|(m, e)| !(m == mode && e == entry));
+        self.entries.push((mode.clone(), entry.to_string()));
+    }
+
+    fn list(&self, mode: &InputMode) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|(m, _)| m == mode)
+            .map(|(_, e)| e.as_str())
+            .collect()
+    }
+
+    /// The browsing in progress, unless `current` was edited since the
+    /// entry was recalled (or it belongs to another prompt).
+    fn browsing(&self, mode: &InputMode, current: &str) -> Option<&Browsing> {
+        self.browsing
+            .as_ref()
+            .filter(|b| b.mode == *mode && b.shown == current)
+    }
+
+    /// The entry before the one shown, starting from the newest. `None`
+    /// when there's nothing older.
+    pub fn older(&mut self, mode: &InputMode, current: &str) -> Option<String> {
+        let list = self.list(mode);
+        let (index, draft) = match self.browsing(mode, current) {
+            Some(b) => (b.index.checked_sub(1)?, b.draft.clone()),
+            None => (list.len().checked_sub(1)?, current.to_string()),
+        };
+        let shown = list[index].to_string();
+        self.browsing = Some(Browsing {
+            mode: mode.clone(),
+            index,
+            draft,
+            shown: shown.clone(),
+        });
+        Some(shown)
+    }
+
+    /// The entry after the one shown, or what was typed before browsing
+    /// once past the newest. `None` when not browsing.
+    pub fn newer(&mut self, mode: &InputMode, current: &str) -> Option<String> {
+        let b = self.browsing(mode, current)?;
+        let list = self.list(mode);
+        if b.index + 1 >= list.len() {
+            return self.browsing.take().map(|b| b.draft);
+        }
+        let index = b.index + 1;
+        let shown = list[index].to_string();
+        let b = self.browsing.as_mut()?;
+        b.index = index;
+        b.shown = shown.clone();
+        Some(shown)
+    }
+}
+
+/// Candidates matching what was typed when `Tab` was first pressed, and
+/// which one is shown. Further presses cycle through them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    mode: InputMode,
+    matches: Vec<String>,
+    index: usize,
+}
+
+impl Completion {
+    /// Collects the candidates that start with `typed`, ignoring case,
+    /// sorted and without repeats. `None` if nothing matches.
+    pub fn start(
+        mode: &InputMode,
+        typed: &str,
+        candidates: impl IntoIterator<Item = String>,
+        forward: bool,
+    ) -> Option<Self> {
+        let typed = typed.to_lowercase();
+        let mut matches: Vec<String> = candidates
+            .into_iter()
+            .filter(|c| c.to_lowercase().starts_with(&typed))
+            .collect();
+        matches.sort_by_key(|c| c.to_lowercase());
+        matches.dedup();
+        let index = if forward {
+            0
+        } else {
+            matches.len().checked_sub(1)?
+        };
+        (!matches.is_empty()).then(|| Self {
+            mode: mode.clone(),
+            matches,
+            index,
+        })
+    }
+
+    /// Whether `current` is still the match this completion put there.
+    pub fn continues(&self, mode: &InputMode, current: &str) -> bool {
+        self.mode == *mode && self.current() == current
+    }
+
+    /// Moves to the next match, or the previous one, wrapping around.
+    pub fn step(&mut self, forward: bool) {
+        let len = self.matches.len();
+        self.index = if forward {
+            (self.index + 1) % len
+        } else {
+            (self.index + len - 1) % len
+        };
+    }
+
+    pub fn current(&self) -> &str {
+        &self.matches[self.index]
+    }
+
+    /// The shown match's one-based position, and how many there are.
+    pub fn position(&self) -> (usize, usize) {
+        (self.index + 1, self.matches.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_browsing() {
+        let mode = InputMode::EnteringPermissionTool;
+        let mut history = PromptHistory::default();
+        assert_eq!(history.older(&mode, "x"), None);
+        history.record(&mode, "Bash");
+        history.record(&mode, "Read");
+        history.record(&InputMode::EnteringKeyName, "amp.x");
+        history.record(&mode, "Bash ");
+
+        assert_eq!(history.older(&mode, "gr").as_deref(), Some("Bash"));
+        assert_eq!(history.older(&mode, "Bash").as_deref(), Some("Read"));
+        assert_eq!(history.older(&mode, "Read"), None);
+        assert_eq!(history.newer(&mode, "Read").as_deref(), Some("Bash"));
+        assert_eq!(history.newer(&mode, "Bash").as_deref(), Some("gr"));
+        assert_eq!(history.newer(&mode, "gr"), None);
+
+        // Editing a recalled entry starts over from the newest.
+        history.older(&mode, "");
+        assert_eq!(history.older(&mode, "Bas").as_deref(), Some("Bash"));
+        assert_eq!(history.newer(&mode, "Bash").as_deref(), Some("Bas"));
+    }
+
+    #[test]
+    fn test_completion_cycles_through_matches() {
+        let mode = InputMode::EnteringKeyName;
+        let candidates =
+            ["amp.tools.disable", "amp.showCosts", "amp.todos", "git"].map(String::from);
+        let mut completion = Completion::start(&mode, "AMP.T", candidates.clone(), true).unwrap();
+        assert_eq!(completion.current(), "amp.todos");
+        assert!(completion.continues(&mode, "amp.todos"));
+        assert!(!completion.continues(&mode, "amp.todo"));
+        completion.step(true);
+        assert_eq!(completion.position(), (2, 2));
+        completion.step(true);
+        assert_eq!(completion.current(), "amp.todos");
+
+        let backward = Completion::start(&mode, "amp.", candidates.clone(), false).unwrap();
+        assert_eq!(backward.current(), "amp.tools.disable");
+        assert_eq!(Completion::start(&mode, "zz", candidates, true), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! A catalog of popular MCP servers with the command, args, and env
+//! variables they're usually run with, so adding one is picking it from a
+//! list and filling in its env values.
+//!
+//! A catalog ships inside volt (`registry.json`). Refreshing downloads the
+//! latest one and caches it in `~/.config/volt/registry.json`, which is
+//! used from then on:
+//!
+//! ```json
+//! { "servers": [
+//!   { "name": "github", "description": "…", "command": "npx",
+//!     "args": ["-y", "@modelcontextprotocol/server-github"],
+//!     "env": ["GITHUB_PERSONAL_ACCESS_TOKEN"] },
+//!   { "name": "linear", "description": "…", "url": "https://mcp.linear.app/sse" }
+//! ] }
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use crate::schema;
+use crate::server_form::{Pair, ServerForm, Transport};
+
+/// The catalog volt was built with.
+const BUNDLED: &str = include_str!("registry.json");
+
+/// Where refreshing downloads the catalog from, unless `registry_url` is
+/// set in the preferences.
+pub const DEFAULT_URL: &str = "https://raw.githubusercontent.com/hqnna/volt/main/src/registry.json";
+
+/// A server in the catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub description: String,
+    /// How it's launched locally; `None` for a remote server.
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    /// Env variables it needs values for.
+    pub env: Vec<String>,
+    /// Where a remote server is reached.
+    pub url: Option<String>,
+}
+
+impl Entry {
+    /// The command line or URL, for showing in the browser.
+    pub fn summary(&self) -> String {
+        match (&self.command, &self.url) {
+            (Some(command), _) => std::iter::once(command.as_str())
+                .chain(self.args.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" "),
+            (None, Some(url)) => url.clone(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// A new-server form filled in from the entry, to be added as `name`,
+    /// with its env variables' values left to fill in.
+    pub fn to_form(&self, name: String) -> ServerForm {
+        let mut form = ServerForm::new(name);
+        match &self.command {
+            Some(command) => {
+                form.command = command.clone();
+                form.args = self.args.clone();
+                form.env = self
+                    .env
+                    .iter()
+                    .map(|name| Pair {
+                        name: name.clone(),
+                        value: String::new(),
+                    })
+                    .collect();
+            }
+            None => {
+                form.transport = Transport::Http;
+                form.url = self.url.clone().unwrap_or_default();
+            }
+        }
+        form
+    }
+}
+
+/// Returns the file a refreshed catalog is cached in.
+pub fn default_cache() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("could not determine home directory")?;
+    Ok(home.join(".config").join("volt").join("registry.json"))
+}
+
+/// Parses a catalog.
+pub fn parse(text: &str) -> Result<Vec<Entry>> {
+    let root: Value = serde_json::from_str(text)?;
+    let Some(servers) = root.get("servers").and_then(Value::as_array) else {
+        bail!("catalog has no \"servers\" array");
+    };
+    servers
+        .iter()
+        .enumerate()
+        .map(|(i, server)| {
+            let text = |field: &str| {
+                server
+                    .get(field)
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            };
+            let list = |field: &str| -> Vec<String> {
+                server
+                    .get(field)
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            };
+            let Some(name) = text("name") else {
+                bail!("server {i} has no \"name\"");
+            };
+            let (command, url) = (text("command"), text("url"));
+            if command.is_none() && url.is_none() {
+                bail!("server {name} has neither a \"command\" nor a \"url\"");
+            }
+            Ok(Entry {
+                name,
+                description: text("description").unwrap_or_default(),
+                command,
+                args: list("args"),
+                env: list("env"),
+                url,
+            })
+        })
+        .collect()
+}
+
+/// Loads the cached catalog if there is one, or else the bundled one. A
+/// cache that can't be read is reported and the bundled catalog used.
+pub fn load(cache: Option<&Path>) -> (Vec<Entry>, Option<String>) {
+    let bundled = || parse(BUNDLED).unwrap_or_default();
+    let Some(cache) = cache.filter(|c| c.exists()) else {
+        return (bundled(), None);
+    };
+    let cached = fs::read_to_string(cache)
+        .with_context(|| format!("reading {}", cache.display()))
+        .and_then(|text| parse(&text).with_context(|| format!("parsing {}", cache.display())));
+    match cached {
+        Ok(entries) => (entries, None),
+        Err(e) => (bundled(), Some(format!("{e:#}"))),
+    }
+}
+
+/// Downloads the catalog from `url` and caches it, returning its entries.
+pub fn refresh(url: &str, cache: &Path) -> Result<Vec<Entry>> {
+    let text = schema::fetch(url)?;
+    let entries = parse(&text).with_context(|| format!("parsing the catalog from {url}"))?;
+    if let Some(dir) = cache.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating directory {}", dir.display()))?;
+    }
+    fs::write(cache, text).with_context(|| format!("writing {}", cache.display()))?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bundled_catalog() {
+        let entries = parse(BUNDLED).unwrap();
+        assert!(entries.iter().any(|e| e.name == "filesystem"));
+        let github = entries.iter().find(|e| e.name == "github").unwrap();
+        assert_eq!(
+            github.summary(),
+            "npx -y @modelcontextprotocol/server-github"
+        );
+
+        let form = github.to_form("github".into());
+        assert_eq!(form.first_error(), None);
+        assert_eq!(
+            form.to_config(),
+            json!({
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-github"],
+                "env": {"GITHUB_PERSONAL_ACCESS_TOKEN": ""}
+            })
+        );
+        let linear = entries.iter().find(|e| e.name == "linear").unwrap();
+        assert_eq!(
+            linear.to_form("linear".into()).to_config(),
+            json!({"url": "https://mcp.linear.app/sse"})
+        );
+    }
+
+    #[test]
+    fn test_load_prefers_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("registry.json");
+        assert_eq!(load(Some(&cache)).0, parse(BUNDLED).unwrap());
+
+        fs::write(
+            &cache,
+            r#"{"servers": [{"name": "x", "command": "x-mcp"}]}"#,
+        )
+        .unwrap();
+        let (entries, error) = load(Some(&cache));
+        assert_eq!(entries.len(), 1);
+        assert_eq!(error, None);
+
+        fs::write(&cache, r#"{"servers": [{"name": "x"}]}"#).unwrap();
+        let (entries, error) = load(Some(&cache));
+        assert_eq!(entries, parse(BUNDLED).unwrap());
+        assert!(error
+            .unwrap()
+            .ends_with("server x has neither a \"command\" nor a \"url\""));
+    }
+}
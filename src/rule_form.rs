@@ -0,0 +1,319 @@
+//! The form for editing one `amp.permissions` rule field by field: its tool
+//! pattern, action, delegate program, and argument constraints.
+
+use serde_json::{Map, Value};
+
+use crate::app::PermissionLevel;
+use crate::matcher;
+
+/// A field of the form that can have focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Tool,
+    Action,
+    /// Only shown for `delegate` rules.
+    To,
+    /// The argument name of the nth `matches` constraint.
+    MatchName(usize),
+    /// The pattern of the nth `matches` constraint.
+    MatchPattern(usize),
+}
+
+/// One `matches` constraint as typed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchRow {
+    pub name: String,
+    /// A glob, a `/regex/`, or JSON such as `["git *", "ls *"]`.
+    pub pattern: String,
+}
+
+/// A permission rule being edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleForm {
+    /// Where the rule sits in `amp.permissions`.
+    pub index: usize,
+    pub tool: String,
+    pub action: String,
+    pub to: String,
+    pub matches: Vec<MatchRow>,
+    pub field: Field,
+    /// Keys the form doesn't cover (e.g. `context`), written back unchanged.
+    extra: Map<String, Value>,
+}
+
+impl RuleForm {
+    /// Loads a rule object into the form.
+    pub fn new(index: usize, rule: &Map<String, Value>) -> Self {
+        let text = |key: &str| {
+            rule.get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let matches = rule
+            .get("matches")
+            .and_then(Value::as_object)
+            .map(|m| {
+                m.iter()
+                    .map(|(name, pattern)| MatchRow {
+                        name: name.clone(),
+                        pattern: match pattern {
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        },
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut extra = rule.clone();
+        for key in ["tool", "action", "to", "matches"] {
+            extra.shift_remove(key);
+        }
+        let action = text("action");
+        Self {
+            index,
+            tool: text("tool"),
+            action: if action.is_empty() {
+                PermissionLevel::Ask.label().to_string()
+            } else {
+                action
+            },
+            to: text("to"),
+            matches,
+            field: Field::Tool,
+            extra,
+        }
+    }
+
+    fn is_delegate(&self) -> bool {
+        self.action == PermissionLevel::Delegate.label()
+    }
+
+    /// The fields in display order.
+    pub fn fields(&self) -> Vec<Field> {
+        let mut fields = vec![Field::Tool, Field::Action];
+        if self.is_delegate() {
+            fields.push(Field::To);
+        }
+        for i in 0..self.matches.len() {
+            fields.push(Field::MatchName(i));
+            fields.push(Field::MatchPattern(i));
+        }
+        fields
+    }
+
+    /// Moves focus to the next field, or the previous one, wrapping around.
+    pub fn move_focus(&mut self, forward: bool) {
+        let fields = self.fields();
+        let pos = fields.iter().position(|f| *f == self.field).unwrap_or(0);
+        let len = fields.len();
+        self.field = fields[if forward {
+            (pos + 1) % len
+        } else {
+            (pos + len - 1) % len
+        }];
+    }
+
+    /// The text of the focused field, if it's typed rather than picked.
+    pub fn text_mut(&mut self) -> Option<&mut String> {
+        match self.field {
+            Field::Tool => Some(&mut self.tool),
+            Field::Action => None,
+            Field::To => Some(&mut self.to),
+            Field::MatchName(i) => self.matches.get_mut(i).map(|m| &mut m.name),
+            Field::MatchPattern(i) => self.matches.get_mut(i).map(|m| &mut m.pattern),
+        }
+    }
+
+    /// Steps the action through ask/allow/reject/delegate. An action Amp
+    /// doesn't know steps to the first one.
+    pub fn cycle_action(&mut self, forward: bool) {
+        let labels: Vec<&str> = PermissionLevel::ALL.iter().map(|l| l.label()).collect();
+        let len = labels.len();
+        let next = match labels.iter().position(|l| *l == self.action) {
+            Some(pos) if forward => (pos + 1) % len,
+            Some(pos) => (pos + len - 1) % len,
+            None => 0,
+        };
+        self.action = labels[next].to_string();
+    }
+
+    /// Adds an empty argument constraint and focuses its name.
+    pub fn add_match(&mut self) {
+        self.matches.push(MatchRow::default());
+        self.field = Field::MatchName(self.matches.len() - 1);
+    }
+
+    /// Removes the focused argument constraint.
+    pub fn remove_match(&mut self) {
+        let (Field::MatchName(i) | Field::MatchPattern(i)) = self.field else {
+            return;
+        };
+        self.matches.remove(i);
+        self.field = if i < self.matches.len() {
+            Field::MatchName(i)
+        } else if i > 0 {
+            Field::MatchPattern(i - 1)
+        } else {
+            Field::Action
+        };
+    }
+
+    /// What's wrong with a field's current value, if anything.
+    pub fn error(&self, field: Field) -> Option<String> {
+        match field {
+            Field::Tool if self.tool.trim().is_empty() => Some("required".into()),
+            Field::Tool => matcher::check(self.tool.trim()).err(),
+            Field::Action => None,
+            Field::To if self.is_delegate() && self.to.trim().is_empty() => {
+                Some("required to delegate".into())
+            }
+            Field::To => None,
+            Field::MatchName(i) => {
+                let name = self.matches.get(i)?.name.trim();
+                if name.is_empty() {
+                    Some("required".into())
+                } else if self.matches[..i].iter().any(|m| m.name.trim() == name) {
+                    Some("already constrained above".into())
+                } else {
+                    None
+                }
+            }
+            Field::MatchPattern(i) => {
+                let pattern = self.matches.get(i)?.pattern.trim();
+                if pattern.is_empty() {
+                    return Some("required".into());
+                }
+                match parse_pattern(pattern) {
+                    Value::String(s) => matcher::check(&s).err(),
+                    other => matcher::check_value(&other).err(),
+                }
+            }
+        }
+    }
+
+    /// The first invalid field and its error.
+    pub fn first_error(&self) -> Option<(Field, String)> {
+        self.fields()
+            .into_iter()
+            .find_map(|f| self.error(f).map(|e| (f, e)))
+    }
+
+    /// Builds the rule as it stands, valid or not.
+    pub fn to_rule(&self) -> Value {
+        let mut rule = self.extra.clone();
+        rule.insert("tool".into(), Value::String(self.tool.trim().into()));
+        rule.insert("action".into(), Value::String(self.action.clone()));
+        if self.is_delegate() {
+            rule.insert("to".into(), Value::String(self.to.trim().into()));
+        }
+        if !self.matches.is_empty() {
+            let matches = self
+                .matches
+                .iter()
+                .map(|m| (m.name.trim().to_string(), parse_pattern(m.pattern.trim())))
+                .collect();
+            rule.insert("matches".into(), Value::Object(matches));
+        }
+        Value::Object(rule)
+    }
+}
+
+/// Reads a typed pattern: anything that parses as JSON (an array of
+/// alternatives, a number, a quoted string) is kept as that JSON, and
+/// everything else is a glob or `/regex/` string.
+fn parse_pattern(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn form(rule: Value) -> RuleForm {
+        RuleForm::new(0, rule.as_object().unwrap())
+    }
+
+    #[test]
+    fn test_round_trip_keeps_unedited_keys() {
+        let rule = json!({
+            "tool": "Bash",
+            "action": "delegate",
+            "to": "guard",
+            "context": "thread",
+            "matches": {"cmd": ["git *", "ls *"], "timeout": 30}
+        });
+        let form = form(rule.clone());
+        assert_eq!(form.matches[0].pattern, r#"["git *","ls *"]"#);
+        assert_eq!(form.to_rule(), rule);
+        assert_eq!(form.first_error(), None);
+    }
+
+    #[test]
+    fn test_navigation_and_action_cycling() {
+        let mut form = form(json!({"tool": "Bash", "action": "allow"}));
+        assert_eq!(form.fields(), vec![Field::Tool, Field::Action]);
+        form.move_focus(true);
+        assert_eq!(form.field, Field::Action);
+        assert!(form.text_mut().is_none());
+        form.cycle_action(true);
+        form.cycle_action(true);
+        assert_eq!(form.action, "delegate");
+        assert_eq!(form.fields(), vec![Field::Tool, Field::Action, Field::To]);
+        form.move_focus(true);
+        assert_eq!(form.field, Field::To);
+        form.move_focus(true);
+        assert_eq!(form.field, Field::Tool);
+        form.move_focus(false);
+        assert_eq!(form.field, Field::To);
+
+        form.cycle_action(false);
+        assert!(form.to_rule().get("to").is_none());
+    }
+
+    #[test]
+    fn test_adding_and_removing_matches() {
+        let mut form = form(json!({"tool": "Bash", "action": "ask"}));
+        form.add_match();
+        assert_eq!(form.field, Field::MatchName(0));
+        form.text_mut().unwrap().push_str("cmd");
+        form.move_focus(true);
+        form.text_mut().unwrap().push_str("rm *");
+        form.add_match();
+        form.text_mut().unwrap().push_str("cmd");
+        assert_eq!(
+            form.first_error(),
+            Some((Field::MatchName(1), "already constrained above".into()))
+        );
+        form.remove_match();
+        assert_eq!(form.field, Field::MatchPattern(0));
+        assert_eq!(
+            form.to_rule(),
+            json!({"tool": "Bash", "action": "ask", "matches": {"cmd": "rm *"}})
+        );
+        form.remove_match();
+        assert_eq!(form.field, Field::Action);
+        assert!(form.to_rule().get("matches").is_none());
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let mut form = form(json!({"action": "delegate"}));
+        assert_eq!(form.error(Field::Tool).as_deref(), Some("required"));
+        assert_eq!(
+            form.error(Field::To).as_deref(),
+            Some("required to delegate")
+        );
+        form.tool = "mcp__[x".into();
+        assert_eq!(form.error(Field::Tool).as_deref(), Some("unclosed ["));
+        form.matches.push(MatchRow {
+            name: "cmd".into(),
+            pattern: r#"["ok", "/(/"]"#.into(),
+        });
+        assert_eq!(
+            form.error(Field::MatchPattern(0)).as_deref(),
+            Some(r#""/(/": unclosed ("#)
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Loading setting definitions from Amp's published JSON Schema, so keys added
+//! after this volt release still get proper types, enums, and defaults.
+
+use std::fs;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::amp;
+use crate::settings::{self, NumberSpec, SettingDef, SettingType};
+
+/// Reads a schema from a file path or an `http(s)://` URL and returns the
+/// setting definitions it describes.
+pub fn load(source: &str) -> Result<Vec<SettingDef>> {
+    let text = if source.starts_with("http://") || source.starts_with("https://") {
+        fetch(source)?
+    } else {
+        fs::read_to_string(source).with_context(|| format!("reading {source}"))?
+    };
+    parse(&text).with_context(|| format!("parsing schema {source}"))
+}
+
+/// Downloads a URL with `curl`, which is available nearly everywhere and saves
+/// volt from carrying an HTTP/TLS stack.
+pub fn fetch(url: &str) -> Result<String> {
+    let curl =
+        amp::find_on_path("curl").with_context(|| format!("fetching {url} requires curl"))?;
+    let output = Command::new(curl)
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .with_context(|| format!("running curl for {url}"))?;
+    if !output.status.success() {
+        bail!(
+            "fetching {url} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("{url} is not UTF-8"))
+}
+
+/// Extracts setting definitions from a JSON Schema's top-level `properties`.
+/// Properties whose type volt can't edit are skipped.
+pub fn parse(text: &str) -> Result<Vec<SettingDef>> {
+    let schema: Value = serde_json::from_str(text)?;
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        bail!("schema has no top-level \"properties\" object");
+    };
+    Ok(properties
+        .iter()
+        .filter_map(|(key, prop)| prop.as_object().and_then(|p| definition(key, p)))
+        .collect())
+}
+
+/// Converts one schema property into a setting definition.
+fn definition(key: &str, prop: &Map<String, Value>) -> Option<SettingDef> {
+    let options: Vec<&'static str> = prop
+        .get("enum")
+        .and_then(Value::as_array)
+        .map(|values| values.iter().filter_map(Value::as_str).map(leak).collect())
+        .unwrap_or_default();
+
+    let setting_type = match prop.get("type").and_then(Value::as_str)? {
+        "boolean" => SettingType::Boolean,
+        "string" if !options.is_empty() => SettingType::StringEnum,
+        "string" => SettingType::String,
+        "number" | "integer" => SettingType::Number,
+        "object" => SettingType::Object,
+        "array" => {
+            let item_type = prop
+                .get("items")
+                .and_then(|items| items.get("type"))
+                .and_then(Value::as_str);
+            if item_type == Some("object") {
+                SettingType::ArrayObject
+            } else {
+                SettingType::ArrayString
+            }
+        }
+        _ => return None,
+    };
+
+    let default = prop
+        .get("default")
+        .cloned()
+        .unwrap_or_else(|| match setting_type {
+            SettingType::Boolean => Value::Bool(false),
+            SettingType::String | SettingType::StringEnum => Value::String(String::new()),
+            SettingType::Number => Value::Number(0.into()),
+            SettingType::ArrayString | SettingType::ArrayObject => Value::Array(vec![]),
+            SettingType::Object => Value::Object(Map::new()),
+        });
+
+    let description = prop
+        .get("description")
+        .or_else(|| prop.get("markdownDescription"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    Some(SettingDef {
+        key: leak(key),
+        description: leak(description),
+        setting_type,
+        default,
+        enum_options: (!options.is_empty()).then(|| &*Box::leak(options.into_boxed_slice())),
+        allows_custom: false,
+        sensitive: setting_type == SettingType::String
+            && (prop.get("format").and_then(Value::as_str) == Some("password")
+                || settings::looks_secret(key)),
+        object_schema: None,
+        number: (setting_type == SettingType::Number).then(|| number_spec(prop)),
+    })
+}
+
+/// Reads `minimum`, `maximum`, and `multipleOf` (as the step) off a number
+/// property.
+fn number_spec(prop: &Map<String, Value>) -> NumberSpec {
+    let bound = |name| prop.get(name).and_then(Value::as_f64);
+    NumberSpec {
+        min: bound("minimum"),
+        max: bound("maximum"),
+        step: bound("multipleOf")
+            .filter(|step| *step > 0.0)
+            .unwrap_or(1.0),
+        unit: None,
+    }
+}
+
+/// Schema definitions live for the whole run, so their strings are leaked to
+/// fit the `'static` lifetimes of the built-in definitions.
+fn leak(s: &str) -> &'static str {
+    Box::leak(s.to_string().into_boxed_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "properties": {
+            "amp.new.flag": { "type": "boolean", "default": true, "description": "A new flag." },
+            "amp.new.mode": { "type": "string", "enum": ["fast", "slow"], "default": "fast" },
+            "amp.new.path": { "type": "string" },
+            "amp.new.limit": { "type": "integer", "default": 5, "minimum": 1, "maximum": 10 },
+            "amp.new.list": { "type": "array", "items": { "type": "string" } },
+            "amp.new.rules": { "type": "array", "items": { "type": "object" } },
+            "amp.new.map": { "type": "object" },
+            "amp.new.weird": { "type": ["string", "null"] },
+            "amp.new.untyped": {}
+        }
+    }"#;
+
+    fn find<'a>(defs: &'a [SettingDef], key: &str) -> Option<&'a SettingDef> {
+        defs.iter().find(|d| d.key == key)
+    }
+
+    #[test]
+    fn test_parse_types() {
+        let defs = parse(SCHEMA).unwrap();
+        let ty = |key| find(&defs, key).map(|d| d.setting_type);
+        assert_eq!(ty("amp.new.flag"), Some(SettingType::Boolean));
+        assert_eq!(ty("amp.new.mode"), Some(SettingType::StringEnum));
+        assert_eq!(ty("amp.new.path"), Some(SettingType::String));
+        assert_eq!(ty("amp.new.limit"), Some(SettingType::Number));
+        assert_eq!(ty("amp.new.list"), Some(SettingType::ArrayString));
+        assert_eq!(ty("amp.new.rules"), Some(SettingType::ArrayObject));
+        assert_eq!(ty("amp.new.map"), Some(SettingType::Object));
+        assert_eq!(ty("amp.new.weird"), None);
+        assert_eq!(ty("amp.new.untyped"), None);
+    }
+
+    #[test]
+    fn test_parse_defaults_and_enums() {
+        let defs = parse(SCHEMA).unwrap();
+        let mode = find(&defs, "amp.new.mode").unwrap();
+        assert_eq!(mode.enum_options, Some(&["fast", "slow"][..]));
+        assert_eq!(mode.default, Value::String("fast".into()));
+
+        assert_eq!(find(&defs, "amp.new.limit").unwrap().default, 5);
+        let limit = find(&defs, "amp.new.limit").unwrap().number_spec();
+        assert_eq!(
+            (limit.min, limit.max, limit.step),
+            (Some(1.0), Some(10.0), 1.0)
+        );
+        assert_eq!(
+            find(&defs, "amp.new.path").unwrap().default,
+            Value::String(String::new())
+        );
+        assert!(find(&defs, "amp.new.flag").unwrap().enum_options.is_none());
+        assert_eq!(
+            find(&defs, "amp.new.flag").unwrap().description,
+            "A new flag."
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_schema() {
+        assert!(parse("[]").is_err());
+        assert!(parse("{\"type\": \"object\"}").is_err());
+        assert!(parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_load_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schema.json");
+        fs::write(&path, SCHEMA).unwrap();
+        let defs = load(path.to_str().unwrap()).unwrap();
+        assert_eq!(defs.len(), 7);
+        assert!(load(dir.path().join("missing.json").to_str().unwrap()).is_err());
+    }
+}
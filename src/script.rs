@@ -0,0 +1,256 @@
+//! Declarative changes for `volt script`: a JSON list of operations applied
+//! to the settings in order, all or nothing.
+//!
+//! ```json
+//! [
+//!   {"op": "set", "key": "amp.showCosts", "value": false},
+//!   {"op": "unset", "key": "amp.updates.mode"},
+//!   {"op": "add-array-item", "key": "amp.tools.disable", "item": "browser_navigate"},
+//!   {"op": "add-mcp-server", "name": "github", "server": {"command": "npx"}}
+//! ]
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::lint::{self, Severity};
+use crate::settings::{self, SettingType};
+
+/// One change to make.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Op {
+    /// Stores a value, given as JSON of the key's type.
+    Set { key: String, value: Value },
+    /// Removes a key so it falls back to its default; a no-op if unset.
+    Unset { key: String },
+    /// Appends to an array setting, creating it if unset.
+    AddArrayItem { key: String, item: Value },
+    /// Adds a server to `amp.mcpServers`; an existing name is an error
+    /// unless `overwrite` is set.
+    AddMcpServer {
+        name: String,
+        server: Value,
+        #[serde(default)]
+        overwrite: bool,
+    },
+}
+
+impl Op {
+    /// The setting the operation changes.
+    fn key(&self) -> &str {
+        match self {
+            Op::Set { key, .. } | Op::Unset { key } | Op::AddArrayItem { key, .. } => key,
+            Op::AddMcpServer { .. } => "amp.mcpServers",
+        }
+    }
+}
+
+/// Parses a script: a JSON array of operations.
+pub fn parse(text: &str) -> Result<Vec<Op>> {
+    let ops: Vec<Op> = serde_json::from_str(text).context("expected a JSON array of operations")?;
+    Ok(ops)
+}
+
+/// Applies each operation in turn and returns a line describing each. The
+/// first one that fails stops the script, as does a change that leaves a
+/// touched setting invalid; either way `config` may be partly changed and
+/// shouldn't be saved.
+pub fn apply(config: &mut Config, ops: &[Op]) -> Result<Vec<String>> {
+    let mut summary = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        let line = apply_one(config, op).with_context(|| format!("operation {}", i + 1))?;
+        summary.push(line);
+    }
+
+    let touched: Vec<&str> = ops.iter().map(Op::key).collect();
+    let errors: Vec<String> = lint::lint(config)
+        .into_iter()
+        .filter(|f| f.severity == Severity::Error)
+        .filter(|f| touched.iter().any(|key| is_under(&f.path, key)))
+        .map(|f| format!("{}: {}", f.path, f.message))
+        .collect();
+    if !errors.is_empty() {
+        bail!("the result isn't valid:\n  {}", errors.join("\n  "));
+    }
+    Ok(summary)
+}
+
+fn apply_one(config: &mut Config, op: &Op) -> Result<String> {
+    Ok(match op {
+        Op::Set { key, value } => {
+            Config::validate_value(key, value)?;
+            config.set(key, value.clone());
+            format!("set {key} = {value}")
+        }
+        Op::Unset { key } => {
+            if config.get_raw(key).is_none() {
+                return Ok(format!("{key} was not set"));
+            }
+            config.remove(key);
+            format!("unset {key}")
+        }
+        Op::AddArrayItem { key, item } => {
+            let is_array = match settings::get_setting_def(key) {
+                Some(def) => matches!(
+                    def.setting_type,
+                    SettingType::ArrayString | SettingType::ArrayObject
+                ),
+                None => config.get_raw(key).is_none_or(Value::is_array),
+            };
+            if !is_array {
+                bail!("'{key}' is not an array setting");
+            }
+            let mut items = match config.get_raw(key) {
+                Some(Value::Array(items)) => items.clone(),
+                Some(_) => bail!("'{key}' holds a value that isn't an array"),
+                None => Vec::new(),
+            };
+            items.push(item.clone());
+            let value = Value::Array(items);
+            Config::validate_value(key, &value)?;
+            let count = value.as_array().map_or(0, Vec::len);
+            config.set(key, value);
+            format!("added an item to {key} (now {count})")
+        }
+        Op::AddMcpServer {
+            name,
+            server,
+            overwrite,
+        } => {
+            if !server.is_object() {
+                bail!("server '{name}' must be an object");
+            }
+            let mut servers = config
+                .get("amp.mcpServers")
+                .as_object()
+                .cloned()
+                .unwrap_or_default();
+            let replaced = servers.contains_key(name);
+            if replaced && !overwrite {
+                bail!("MCP server '{name}' already exists (set \"overwrite\": true to replace it)");
+            }
+            servers.insert(name.clone(), server.clone());
+            config.set("amp.mcpServers", Value::Object(servers));
+            if replaced {
+                format!("replaced MCP server {name}")
+            } else {
+                format!("added MCP server {name}")
+            }
+        }
+    })
+}
+
+/// Whether a lint finding's path is the key or something inside it.
+fn is_under(path: &str, key: &str) -> bool {
+    path.strip_prefix(key)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_config(contents: &str) -> (tempfile::TempDir, Config) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, contents).unwrap();
+        let config = Config::load(&path).unwrap();
+        (dir, config)
+    }
+
+    #[test]
+    fn test_parse() {
+        let ops = parse(
+            r#"[{"op": "set", "key": "amp.showCosts", "value": false},
+                {"op": "add-mcp-server", "name": "gh", "server": {"command": "npx"}}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            ops[1],
+            Op::AddMcpServer {
+                name: "gh".into(),
+                server: json!({"command": "npx"}),
+                overwrite: false
+            }
+        );
+        assert!(parse(r#"[{"op": "frobnicate", "key": "x"}]"#).is_err());
+        assert!(parse(r#"[{"op": "unset", "key": "x", "value": 1}]"#).is_err());
+        assert!(parse(r#"{"op": "unset", "key": "x"}"#).is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let (_dir, mut config) = temp_config(r#"{"amp.updates.mode": "auto"}"#);
+        let ops = vec![
+            Op::Set {
+                key: "amp.showCosts".into(),
+                value: json!(false),
+            },
+            Op::Unset {
+                key: "amp.updates.mode".into(),
+            },
+            Op::Unset {
+                key: "amp.skills.path".into(),
+            },
+            Op::AddArrayItem {
+                key: "amp.tools.disable".into(),
+                item: json!("Read"),
+            },
+            Op::AddMcpServer {
+                name: "gh".into(),
+                server: json!({"command": "npx"}),
+                overwrite: false,
+            },
+        ];
+        let summary = apply(&mut config, &ops).unwrap();
+        assert_eq!(
+            summary,
+            [
+                "set amp.showCosts = false",
+                "unset amp.updates.mode",
+                "amp.skills.path was not set",
+                "added an item to amp.tools.disable (now 1)",
+                "added MCP server gh",
+            ]
+        );
+        assert_eq!(config.get("amp.tools.disable"), json!(["Read"]));
+        assert_eq!(config.get("amp.mcpServers")["gh"]["command"], "npx");
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let (_dir, mut config) = temp_config(r#"{"amp.mcpServers": {"gh": {"command": "npx"}}}"#);
+        let set_wrong_type = [Op::Set {
+            key: "amp.showCosts".into(),
+            value: json!("yes"),
+        }];
+        let err = apply(&mut config, &set_wrong_type).unwrap_err();
+        assert!(format!("{err:#}").starts_with("operation 1: expected"));
+
+        let duplicate = [Op::AddMcpServer {
+            name: "gh".into(),
+            server: json!({"command": "uvx"}),
+            overwrite: false,
+        }];
+        assert!(apply(&mut config, &duplicate).is_err());
+
+        let not_array = [Op::AddArrayItem {
+            key: "amp.showCosts".into(),
+            item: json!(1),
+        }];
+        assert!(apply(&mut config, &not_array).is_err());
+
+        // Each op is fine on its own, but the server it adds isn't.
+        let invalid = [Op::AddMcpServer {
+            name: "bad".into(),
+            server: json!({"command": 3}),
+            overwrite: false,
+        }];
+        let err = apply(&mut config, &invalid).unwrap_err();
+        assert!(err.to_string().contains("amp.mcpServers.bad"), "{err}");
+    }
+}
@@ -0,0 +1,342 @@
+//! The guided form for creating an MCP server: pick a transport, then fill
+//! in a command with its args and env, or a URL with its headers.
+
+use serde_json::{Map, Value};
+
+/// How Amp talks to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// Spawn a local command and speak MCP over its stdin/stdout.
+    Stdio,
+    /// Connect to a remote server over streamable HTTP or SSE.
+    Http,
+}
+
+impl Transport {
+    pub fn label(self) -> &'static str {
+        match self {
+            Transport::Stdio => "stdio (local command)",
+            Transport::Http => "HTTP/SSE (remote URL)",
+        }
+    }
+}
+
+/// A field of the form that can have focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Transport,
+    Command,
+    Arg(usize),
+    EnvName(usize),
+    EnvValue(usize),
+    Url,
+    HeaderName(usize),
+    HeaderValue(usize),
+}
+
+/// A name/value pair in `env` or `headers`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pair {
+    pub name: String,
+    pub value: String,
+}
+
+/// A new MCP server being filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerForm {
+    /// The name it will be added under in `amp.mcpServers`.
+    pub name: String,
+    pub transport: Transport,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: Vec<Pair>,
+    pub url: String,
+    pub headers: Vec<Pair>,
+    pub field: Field,
+}
+
+impl ServerForm {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            transport: Transport::Stdio,
+            command: String::new(),
+            args: Vec::new(),
+            env: Vec::new(),
+            url: String::new(),
+            headers: Vec::new(),
+            field: Field::Transport,
+        }
+    }
+
+    /// The fields in display order; only the chosen transport's are shown.
+    pub fn fields(&self) -> Vec<Field> {
+        let mut fields = vec![Field::Transport];
+        match self.transport {
+            Transport::Stdio => {
+                fields.push(Field::Command);
+                fields.extend((0..self.args.len()).map(Field::Arg));
+                for i in 0..self.env.len() {
+                    fields.push(Field::EnvName(i));
+                    fields.push(Field::EnvValue(i));
+                }
+            }
+            Transport::Http => {
+                fields.push(Field::Url);
+                for i in 0..self.headers.len() {
+                    fields.push(Field::HeaderName(i));
+                    fields.push(Field::HeaderValue(i));
+                }
+            }
+        }
+        fields
+    }
+
+    /// Moves focus to the next field, or the previous one, wrapping around.
+    pub fn move_focus(&mut self, forward: bool) {
+        let fields = self.fields();
+        let pos = fields.iter().position(|f| *f == self.field).unwrap_or(0);
+        let len = fields.len();
+        self.field = fields[if forward {
+            (pos + 1) % len
+        } else {
+            (pos + len - 1) % len
+        }];
+    }
+
+    /// Switches between stdio and HTTP. What was typed for the other
+    /// transport is kept in case the user switches back.
+    pub fn toggle_transport(&mut self) {
+        self.transport = match self.transport {
+            Transport::Stdio => Transport::Http,
+            Transport::Http => Transport::Stdio,
+        };
+    }
+
+    /// The text of the focused field, if it's typed rather than picked.
+    pub fn text_mut(&mut self) -> Option<&mut String> {
+        match self.field {
+            Field::Transport => None,
+            Field::Command => Some(&mut self.command),
+            Field::Arg(i) => self.args.get_mut(i),
+            Field::EnvName(i) => self.env.get_mut(i).map(|p| &mut p.name),
+            Field::EnvValue(i) => self.env.get_mut(i).map(|p| &mut p.value),
+            Field::Url => Some(&mut self.url),
+            Field::HeaderName(i) => self.headers.get_mut(i).map(|p| &mut p.name),
+            Field::HeaderValue(i) => self.headers.get_mut(i).map(|p| &mut p.value),
+        }
+    }
+
+    /// Adds an argument after the focused one (or at the end) and focuses it.
+    /// Remote servers take no arguments.
+    pub fn add_arg(&mut self) {
+        if self.transport != Transport::Stdio {
+            return;
+        }
+        let at = match self.field {
+            Field::Arg(i) => i + 1,
+            _ => self.args.len(),
+        };
+        self.args.insert(at, String::new());
+        self.field = Field::Arg(at);
+    }
+
+    /// Adds an env variable (stdio) or header (HTTP) and focuses its name.
+    pub fn add_pair(&mut self) {
+        match self.transport {
+            Transport::Stdio => {
+                self.env.push(Pair::default());
+                self.field = Field::EnvName(self.env.len() - 1);
+            }
+            Transport::Http => {
+                self.headers.push(Pair::default());
+                self.field = Field::HeaderName(self.headers.len() - 1);
+            }
+        }
+    }
+
+    /// Removes the focused argument, env variable, or header. Focus stays
+    /// on the item that slides into its place, or else moves back a field.
+    pub fn remove_item(&mut self) {
+        let first = match self.field {
+            Field::Arg(i) => Field::Arg(i),
+            Field::EnvName(i) | Field::EnvValue(i) => Field::EnvName(i),
+            Field::HeaderName(i) | Field::HeaderValue(i) => Field::HeaderName(i),
+            _ => return,
+        };
+        let start = self.fields().iter().position(|f| *f == first).unwrap_or(1);
+        let slid_in = match first {
+            Field::Arg(i) => {
+                self.args.remove(i);
+                i < self.args.len()
+            }
+            Field::EnvName(i) => {
+                self.env.remove(i);
+                i < self.env.len()
+            }
+            Field::HeaderName(i) => {
+                self.headers.remove(i);
+                i < self.headers.len()
+            }
+            _ => false,
+        };
+        self.field = if slid_in {
+            first
+        } else {
+            self.fields()[start - 1]
+        };
+    }
+
+    /// What's wrong with a field's current value, if anything.
+    pub fn error(&self, field: Field) -> Option<String> {
+        let pair_name_error = |pairs: &[Pair], i: usize| {
+            let name = pairs.get(i)?.name.trim();
+            if name.is_empty() {
+                Some("required".to_string())
+            } else if pairs[..i].iter().any(|p| p.name.trim() == name) {
+                Some("already set above".to_string())
+            } else {
+                None
+            }
+        };
+        match field {
+            Field::Command if self.command.trim().is_empty() => Some("required".into()),
+            Field::Arg(i) if self.args.get(i)?.is_empty() => Some("empty".into()),
+            Field::EnvName(i) => pair_name_error(&self.env, i),
+            Field::Url if self.url.trim().is_empty() => Some("required".into()),
+            Field::Url => {
+                let url = self.url.trim();
+                let scheme = url.starts_with("http://") || url.starts_with("https://");
+                (!scheme || url.contains(char::is_whitespace))
+                    .then(|| "must be an http:// or https:// URL".into())
+            }
+            Field::HeaderName(i) => pair_name_error(&self.headers, i),
+            _ => None,
+        }
+    }
+
+    /// The first invalid field and its error.
+    pub fn first_error(&self) -> Option<(Field, String)> {
+        self.fields()
+            .into_iter()
+            .find_map(|f| self.error(f).map(|e| (f, e)))
+    }
+
+    /// Builds the server config for the chosen transport, valid or not.
+    pub fn to_config(&self) -> Value {
+        let pairs = |pairs: &[Pair]| {
+            pairs
+                .iter()
+                .map(|p| (p.name.trim().to_string(), Value::String(p.value.clone())))
+                .collect::<Map<String, Value>>()
+        };
+        let mut config = Map::new();
+        match self.transport {
+            Transport::Stdio => {
+                config.insert("command".into(), Value::String(self.command.trim().into()));
+                if !self.args.is_empty() {
+                    let args = self.args.iter().cloned().map(Value::String).collect();
+                    config.insert("args".into(), Value::Array(args));
+                }
+                if !self.env.is_empty() {
+                    config.insert("env".into(), Value::Object(pairs(&self.env)));
+                }
+            }
+            Transport::Http => {
+                config.insert("url".into(), Value::String(self.url.trim().into()));
+                if !self.headers.is_empty() {
+                    config.insert("headers".into(), Value::Object(pairs(&self.headers)));
+                }
+            }
+        }
+        Value::Object(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn type_text(form: &mut ServerForm, text: &str) {
+        form.text_mut().unwrap().push_str(text);
+    }
+
+    #[test]
+    fn test_stdio_server() {
+        let mut form = ServerForm::new("gh".into());
+        assert_eq!(
+            form.first_error(),
+            Some((Field::Command, "required".into()))
+        );
+        form.move_focus(true);
+        type_text(&mut form, "npx");
+        form.add_arg();
+        type_text(&mut form, "-y");
+        form.add_arg();
+        type_text(&mut form, "@modelcontextprotocol/server-github");
+        form.add_pair();
+        type_text(&mut form, "GITHUB_TOKEN");
+        form.move_focus(true);
+        type_text(&mut form, "ghp_x");
+        assert_eq!(form.first_error(), None);
+        assert_eq!(
+            form.to_config(),
+            json!({
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-github"],
+                "env": {"GITHUB_TOKEN": "ghp_x"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_http_server() {
+        let mut form = ServerForm::new("remote".into());
+        form.toggle_transport();
+        assert_eq!(form.fields(), vec![Field::Transport, Field::Url]);
+        form.add_arg();
+        assert!(form.args.is_empty());
+        form.field = Field::Url;
+        type_text(&mut form, "example.com/mcp");
+        assert_eq!(
+            form.first_error(),
+            Some((Field::Url, "must be an http:// or https:// URL".into()))
+        );
+        form.url = "https://example.com/mcp".into();
+        form.add_pair();
+        type_text(&mut form, "Authorization");
+        form.add_pair();
+        type_text(&mut form, "Authorization");
+        assert_eq!(
+            form.first_error(),
+            Some((Field::HeaderName(1), "already set above".into()))
+        );
+        form.remove_item();
+        assert_eq!(form.field, Field::HeaderValue(0));
+        assert_eq!(
+            form.to_config(),
+            json!({"url": "https://example.com/mcp", "headers": {"Authorization": ""}})
+        );
+    }
+
+    #[test]
+    fn test_removing_items_moves_focus() {
+        let mut form = ServerForm::new("s".into());
+        form.command = "srv".into();
+        form.field = Field::Command;
+        form.add_arg();
+        form.add_arg();
+        form.add_arg();
+        form.field = Field::Arg(1);
+        form.remove_item();
+        assert_eq!((form.args.len(), form.field), (2, Field::Arg(1)));
+        form.remove_item();
+        assert_eq!(form.field, Field::Arg(0));
+        form.remove_item();
+        assert_eq!(form.field, Field::Command);
+        form.add_pair();
+        form.remove_item();
+        assert_eq!(form.field, Field::Command);
+    }
+}
@@ -0,0 +1,109 @@
+//! Where the user left off, remembered in `~/.config/volt/state.json` so
+//! the next launch starts there, along with the keys pinned to Favorites
+//! and when each key was last changed.
+//! The sidebar's layout is a preference and lives in `config.toml` instead.
+//!
+//! ```json
+//! {
+//!   "section": "Tools",
+//!   "key": "amp.tools.stopTimeout",
+//!   "row": 2,
+//!   "settings_focused": true,
+//!   "filters": { "General": "modified" },
+//!   "collapsed_groups": ["Git"],
+//!   "favorites": ["amp.showCosts", "amp.tools.disable"],
+//!   "modified": { "amp.showCosts": 1760486400 }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::SettingsFilter;
+
+/// The UI state saved on exit.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// The selected section's label.
+    pub section: Option<String>,
+    /// The selected setting's key, so it's found again if rows moved.
+    pub key: Option<String>,
+    /// The selected row, for when there's no key or it's gone.
+    pub row: usize,
+    pub settings_focused: bool,
+    /// Each section's filter, by section label; unfiltered ones are left out.
+    pub filters: BTreeMap<String, SettingsFilter>,
+    /// The groups of General collapsed to their header.
+    pub collapsed_groups: Vec<String>,
+    /// The keys pinned to Favorites, in the order they were pinned.
+    pub favorites: Vec<String>,
+    /// When each key was last saved with a change, in seconds since the
+    /// Unix epoch.
+    pub modified: BTreeMap<String, u64>,
+}
+
+impl Session {
+    /// Returns the path the session is kept at.
+    pub fn default_path() -> Result<PathBuf> {
+        let home = dirs::home_dir().context("could not determine home directory")?;
+        Ok(home.join(".config").join("volt").join("state.json"))
+    }
+
+    /// Loads a saved session; a missing file means a fresh one.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text + "\n").with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("volt").join("state.json");
+        assert_eq!(Session::load(&path).unwrap(), Session::default());
+
+        let session = Session {
+            section: Some("Tools".into()),
+            key: Some("amp.tools.stopTimeout".into()),
+            row: 2,
+            settings_focused: true,
+            filters: BTreeMap::from([("General".into(), SettingsFilter::Modified)]),
+            collapsed_groups: vec!["Git".into()],
+            favorites: vec!["amp.showCosts".into()],
+            modified: BTreeMap::from([("amp.showCosts".into(), 1_760_486_400)]),
+        };
+        session.save(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), session);
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains(r#""General": "modified""#), "{text}");
+
+        fs::write(&path, r#"{"section": "MCPs", "extra": 1}"#).unwrap();
+        assert_eq!(
+            Session::load(&path).unwrap().section.as_deref(),
+            Some("MCPs")
+        );
+        fs::write(&path, "not json").unwrap();
+        assert!(Session::load(&path).is_err());
+    }
+}
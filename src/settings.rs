@@ -1,6 +1,8 @@
 //! Setting definitions and schema for known Amp settings.
 
-use serde_json::Value;
+use std::sync::OnceLock;
+
+use serde_json::{json, Value};
 
 /// The type of a setting value.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -14,47 +16,338 @@ pub enum SettingType {
     Object,
 }
 
+impl SettingType {
+    /// Returns the type's name as shown in the help pane.
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingType::Boolean => "boolean",
+            SettingType::String => "string",
+            SettingType::Number => "number",
+            SettingType::StringEnum => "string (enum)",
+            SettingType::ArrayString => "array<string>",
+            SettingType::ArrayObject => "array<object>",
+            SettingType::Object => "object",
+        }
+    }
+}
+
 /// Definition of a known Amp setting.
 #[derive(Debug, Clone)]
 pub struct SettingDef {
     pub key: &'static str,
+    /// What the setting does, shown in the help pane.
+    pub description: &'static str,
     pub setting_type: SettingType,
     pub default: Value,
     /// For enum types, the list of valid options.
     pub enum_options: Option<&'static [&'static str]>,
     /// Whether the user may enter a custom value beyond the enum options.
     pub allows_custom: bool,
+    /// Whether the value is a credential, masked on screen until revealed.
+    pub sensitive: bool,
+    /// For object types edited with a form, what the object holds.
+    pub object_schema: Option<ObjectSchema>,
+    /// For number types, the allowed range, step, and unit.
+    pub number: Option<NumberSpec>,
+}
+
+impl SettingDef {
+    /// The range, step, and unit of a number setting; any number with a
+    /// step of one when none are declared.
+    pub fn number_spec(&self) -> NumberSpec {
+        self.number.unwrap_or_default()
+    }
+}
+
+/// What a number setting accepts and how it reads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberSpec {
+    /// The smallest allowed value.
+    pub min: Option<f64>,
+    /// The largest allowed value.
+    pub max: Option<f64>,
+    /// How far `+`/`-` move the value.
+    pub step: f64,
+    /// What the number counts, shown after it, e.g. "seconds".
+    pub unit: Option<&'static str>,
+}
+
+impl Default for NumberSpec {
+    fn default() -> Self {
+        Self {
+            min: None,
+            max: None,
+            step: 1.0,
+            unit: None,
+        }
+    }
+}
+
+impl NumberSpec {
+    /// Explains why `n` is out of range, if it is.
+    pub fn check(&self, n: f64) -> Result<(), String> {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) if n < min || n > max => Err(format!(
+                "must be from {} to {}",
+                format_number(min),
+                format_number(max)
+            )),
+            (Some(min), _) if n < min => Err(format!("must be at least {}", format_number(min))),
+            (_, Some(max)) if n > max => Err(format!("must be at most {}", format_number(max))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The value one step up or down from `n`, kept within range.
+    pub fn stepped(&self, n: f64, up: bool) -> f64 {
+        let next = if up { n + self.step } else { n - self.step };
+        // Keep steps like 0.1 from piling up float noise.
+        let mut next = (next * 1e9).round() / 1e9;
+        if let Some(min) = self.min {
+            next = next.max(min);
+        }
+        if let Some(max) = self.max {
+            next = next.min(max);
+        }
+        next
+    }
+
+    /// Describes the unit and range for a prompt, e.g. "seconds, at least 1".
+    pub fn describe(&self) -> Option<String> {
+        let range = match (self.min, self.max) {
+            (Some(min), Some(max)) => {
+                Some(format!("{} to {}", format_number(min), format_number(max)))
+            }
+            (Some(min), None) => Some(format!("at least {}", format_number(min))),
+            (None, Some(max)) => Some(format!("at most {}", format_number(max))),
+            (None, None) => None,
+        };
+        let parts: Vec<String> = self
+            .unit
+            .map(str::to_string)
+            .into_iter()
+            .chain(range)
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// Reads a typed number: `300`, `1.5`, `1e3`, or `10_000`. Whole values
+/// are kept as integers, so `1e3` is stored as `1000`.
+pub fn parse_number(text: &str) -> Option<serde_json::Number> {
+    let text = text.trim().replace('_', "");
+    if let Ok(n) = text.parse::<i64>() {
+        return Some(n.into());
+    }
+    number_from_f64(text.parse().ok()?)
+}
+
+/// Turns a float into a JSON number, as an integer when it's whole.
+pub fn number_from_f64(n: f64) -> Option<serde_json::Number> {
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        Some((n as i64).into())
+    } else {
+        serde_json::Number::from_f64(n)
+    }
+}
+
+/// Formats a number without a trailing `.0`.
+pub fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        format!("{}", n as i64)
+    } else {
+        format!("{n}")
+    }
+}
+
+/// What an object setting holds, so it can be edited field by field
+/// rather than as raw JSON. Object fields can have schemas of their own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectSchema {
+    /// Fixed, named fields.
+    Fields(Vec<FieldDef>),
+    /// Keys chosen by the user, each holding a value shaped like `value`.
+    Map {
+        /// What each key names, e.g. "origin".
+        key_label: &'static str,
+        /// An example key, shown while there are none.
+        key_example: &'static str,
+        value: Box<FieldDef>,
+    },
+}
+
+impl ObjectSchema {
+    /// The field stored under `key`, if the schema knows it.
+    pub fn field(&self, key: &str) -> Option<&FieldDef> {
+        match self {
+            ObjectSchema::Fields(fields) => fields.iter().find(|f| f.name == key),
+            ObjectSchema::Map { value, .. } => Some(value),
+        }
+    }
+
+    /// The schema of the object found by following `path` down from here.
+    pub fn descend(&self, path: &[String]) -> Option<&ObjectSchema> {
+        path.iter()
+            .try_fold(self, |schema, key| schema.field(key)?.object.as_ref())
+    }
+}
+
+/// One field of an object setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+    pub name: &'static str,
+    /// What the field does, shown while it's selected.
+    pub description: &'static str,
+    pub field_type: SettingType,
+    pub enum_options: Option<&'static [&'static str]>,
+    /// What Amp uses when the field is absent, and what a new map entry
+    /// starts as.
+    pub default: Value,
+    /// For object fields, what they hold; such fields open as a panel of
+    /// their own.
+    pub object: Option<ObjectSchema>,
+}
+
+impl FieldDef {
+    fn new(
+        name: &'static str,
+        field_type: SettingType,
+        default: Value,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            field_type,
+            enum_options: None,
+            default,
+            object: None,
+        }
+    }
+
+    /// Whether `value` has this field's type (and, for enums, is one of
+    /// its options).
+    pub fn accepts(&self, value: &Value) -> bool {
+        match self.field_type {
+            SettingType::Boolean => value.is_boolean(),
+            SettingType::String => value.is_string(),
+            SettingType::Number => value.is_number(),
+            SettingType::StringEnum => value
+                .as_str()
+                .is_some_and(|s| self.enum_options.unwrap_or_default().contains(&s)),
+            SettingType::ArrayString => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string)),
+            SettingType::ArrayObject => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_object)),
+            SettingType::Object => value.is_object(),
+        }
+    }
+}
+
+/// A map of names to strings, like an MCP server's `env` or `headers`.
+fn string_map(key_label: &'static str, key_example: &'static str) -> ObjectSchema {
+    ObjectSchema::Map {
+        key_label,
+        key_example,
+        value: Box::new(FieldDef::new(
+            "value",
+            SettingType::String,
+            Value::String(String::new()),
+            "",
+        )),
+    }
+}
+
+/// The fields of one `amp.mcpServers` entry.
+fn mcp_server_schema() -> ObjectSchema {
+    let map = |name, label, example, description| FieldDef {
+        object: Some(string_map(label, example)),
+        ..FieldDef::new(name, SettingType::Object, json!({}), description)
+    };
+    ObjectSchema::Fields(vec![
+        FieldDef::new(
+            "command",
+            SettingType::String,
+            Value::Null,
+            "Program to run for a local (stdio) server.",
+        ),
+        FieldDef::new(
+            "args",
+            SettingType::ArrayString,
+            json!([]),
+            "Arguments passed to the command.",
+        ),
+        map(
+            "env",
+            "variable",
+            "GITHUB_TOKEN",
+            "Environment variables set for the command.",
+        ),
+        FieldDef::new(
+            "url",
+            SettingType::String,
+            Value::Null,
+            "Endpoint of a remote (HTTP/SSE) server, instead of a command.",
+        ),
+        map(
+            "headers",
+            "header",
+            "Authorization",
+            "HTTP headers sent to a remote server.",
+        ),
+    ])
 }
 
 /// Which section a setting belongs to.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Section {
+    /// The keys the user pinned, from any section.
+    Favorites,
     General,
     Permissions,
     Tools,
     Mcps,
+    Experimental,
     Advanced,
 }
 
 impl Section {
     pub const ALL: &[Section] = &[
+        Section::Favorites,
         Section::General,
         Section::Permissions,
         Section::Tools,
         Section::Mcps,
+        Section::Experimental,
         Section::Advanced,
     ];
 
     pub fn label(self) -> &'static str {
         match self {
+            Section::Favorites => "Favorites",
             Section::General => "General",
             Section::Permissions => "Permissions",
             Section::Tools => "Tools",
             Section::Mcps => "MCPs",
+            Section::Experimental => "Experimental",
             Section::Advanced => "Advanced",
         }
     }
 
+    /// Looks up a section by its label, case-insensitively (`mcp` is accepted for MCPs).
+    pub fn from_label(label: &str) -> Option<Section> {
+        let label = label.to_ascii_lowercase();
+        if label == "mcp" {
+            return Some(Section::Mcps);
+        }
+        Section::ALL
+            .iter()
+            .copied()
+            .find(|s| s.label().to_ascii_lowercase() == label)
+    }
+
     /// Returns whether this section has exactly one setting (rendered as a full editor).
     pub fn is_single_key(self) -> bool {
         matches!(self, Section::Permissions)
@@ -88,145 +381,448 @@ const UPDATE_MODE_OPTIONS: &[&str] = &["auto", "warn", "disabled"];
 /// Deep reasoning effort options.
 const DEEP_REASONING_OPTIONS: &[&str] = &["medium", "high", "xhigh"];
 
-/// All known Amp settings with their definitions.
+/// What a redacted secret is replaced with.
+pub const REDACTED: &str = "<redacted>";
+
+/// A value with its secrets replaced by [`REDACTED`]: the whole value of a
+/// sensitive setting, and strings under secret-looking keys at any depth,
+/// in objects and arrays alike, such as an MCP server's `GITHUB_TOKEN` env
+/// variable. Everything else, and the structure, is kept.
+pub fn redact(key: &str, value: &Value) -> Value {
+    let sensitive = get_setting_def(key).is_some_and(|def| def.sensitive);
+    match value {
+        Value::String(_) if sensitive || looks_secret(key) => Value::String(REDACTED.into()),
+        Value::Object(map) => {
+            Value::Object(map.iter().map(|(k, v)| (k.clone(), redact(k, v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| redact(key, v)).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Whether a key's name suggests its value is a credential, such as
+/// `GITHUB_TOKEN`, `amp.bitbucketToken`, or `privateKey`. `KEY` and `AUTH`
+/// only count as words of their own, so `API_KEY` and `x-auth` do but
+/// `HOTKEY` and `GIT_AUTHOR_NAME` don't.
+pub fn looks_secret(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    ["TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL"]
+        .iter()
+        .any(|word| upper.contains(word))
+        || words(key).iter().any(|word| {
+            matches!(
+                word.as_str(),
+                "KEY" | "KEYS" | "APIKEY" | "PAT" | "AUTH" | "AUTHORIZATION"
+            )
+        })
+}
+
+/// Splits a name into upper-cased words at punctuation and camelCase humps:
+/// `amp.privateKey` becomes `AMP`, `PRIVATE`, `KEY`.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        let boundary = !c.is_ascii_alphanumeric() || (prev_lower && c.is_ascii_uppercase());
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_ascii_alphanumeric() {
+            word.push(c.to_ascii_uppercase());
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// How a deprecated key's value carries over to its replacement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// The value moves unchanged.
+    Rename,
+    /// A boolean flips, for keys whose meaning was inverted
+    /// (e.g. `hideCosts` became `showCosts`).
+    Invert,
+}
+
+/// A key Amp no longer reads, and what replaced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub old: &'static str,
+    /// The replacement key, or `None` if the setting was dropped.
+    pub new: Option<&'static str>,
+    pub transform: Transform,
+}
+
+/// Settings Amp has renamed or retired, oldest first.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        old: "amp.thinking.enabled",
+        new: Some("amp.anthropic.thinking.enabled"),
+        transform: Transform::Rename,
+    },
+    Migration {
+        old: "amp.hideCosts",
+        new: Some("amp.showCosts"),
+        transform: Transform::Invert,
+    },
+    Migration {
+        old: "amp.notifications.system.enabled",
+        new: Some("amp.notifications.enabled"),
+        transform: Transform::Rename,
+    },
+    Migration {
+        old: "amp.tools.disabled",
+        new: Some("amp.tools.disable"),
+        transform: Transform::Rename,
+    },
+    Migration {
+        old: "amp.terminal.loadProfile",
+        new: Some("amp.terminal.commands.nodeSpawn.loadProfile"),
+        transform: Transform::Rename,
+    },
+    Migration {
+        old: "amp.todos.enabled",
+        new: None,
+        transform: Transform::Rename,
+    },
+];
+
+/// Returns the migration for a deprecated key.
+pub fn migration_for(key: &str) -> Option<&'static Migration> {
+    MIGRATIONS.iter().find(|m| m.old == key)
+}
+
+impl Migration {
+    /// Converts the old value for the new key, or explains why it can't be.
+    pub fn apply(&self, value: &Value) -> Result<Value, String> {
+        match self.transform {
+            Transform::Rename => Ok(value.clone()),
+            Transform::Invert => value
+                .as_bool()
+                .map(|b| Value::Bool(!b))
+                .ok_or_else(|| "expected a boolean to invert".to_string()),
+        }
+    }
+
+    /// Describes where the key went, e.g. "replaced by amp.showCosts (inverted)".
+    pub fn describe(&self) -> String {
+        match (self.new, self.transform) {
+            (None, _) => "no longer used".to_string(),
+            (Some(new), Transform::Rename) => format!("renamed to {new}"),
+            (Some(new), Transform::Invert) => format!("replaced by {new} (inverted)"),
+        }
+    }
+}
+
+/// Definitions loaded from an external schema at startup.
+static SCHEMA_SETTINGS: OnceLock<Vec<SettingDef>> = OnceLock::new();
+
+/// Registers definitions loaded from Amp's schema. Keys volt already knows keep
+/// their built-in definitions. Returns how many new keys were added; only the
+/// first registration takes effect.
+pub fn register_schema(defs: Vec<SettingDef>) -> usize {
+    let merged = merge(builtin_settings(), defs);
+    let added = merged.len();
+    if SCHEMA_SETTINGS.set(merged).is_err() {
+        return 0;
+    }
+    added
+}
+
+/// Returns the schema definitions whose keys aren't built in.
+fn merge(builtin: Vec<SettingDef>, schema: Vec<SettingDef>) -> Vec<SettingDef> {
+    let mut merged: Vec<SettingDef> = Vec::new();
+    for def in schema {
+        let known = builtin.iter().chain(&merged).any(|b| b.key == def.key);
+        if !known {
+            merged.push(def);
+        }
+    }
+    merged
+}
+
+/// All known Amp settings with their definitions: the built-ins followed by any
+/// registered from a schema.
 pub fn known_settings() -> Vec<SettingDef> {
+    let mut defs = builtin_settings();
+    if let Some(extra) = SCHEMA_SETTINGS.get() {
+        defs.extend(extra.iter().cloned());
+    }
+    defs
+}
+
+/// The settings volt ships with.
+fn builtin_settings() -> Vec<SettingDef> {
     vec![
         // General
         SettingDef {
             key: "amp.anthropic.thinking.enabled",
+            description: "Enable extended thinking for Anthropic models.",
             setting_type: SettingType::Boolean,
             default: Value::Bool(true),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.showCosts",
+            description: "Show the cost of threads and messages.",
             setting_type: SettingType::Boolean,
             default: Value::Bool(true),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.notifications.enabled",
+            description: "Notify you when the agent finishes or needs input.",
             setting_type: SettingType::Boolean,
             default: Value::Bool(true),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.git.commit.ampThread.enabled",
+            description: "Add an Amp-Thread trailer linking the thread to commits the agent makes.",
             setting_type: SettingType::Boolean,
             default: Value::Bool(true),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.git.commit.coauthor.enabled",
+            description: "Add Amp as a co-author on commits the agent makes.",
             setting_type: SettingType::Boolean,
             default: Value::Bool(true),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.tab.clipboard.enabled",
+            description: "Let Amp Tab use clipboard contents as completion context.",
             setting_type: SettingType::Boolean,
             default: Value::Bool(true),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.bitbucketToken",
+            description: "Personal access token used to access Bitbucket Enterprise.",
             setting_type: SettingType::String,
             default: Value::String(String::new()),
             enum_options: None,
             allows_custom: false,
+            sensitive: true,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.skills.path",
+            description: "Extra directory to load agent skills from.",
             setting_type: SettingType::String,
             default: Value::String(String::new()),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.terminal.theme",
+            description: "Color theme for the Amp CLI. Themes in the themes directory next to settings.json can be used too.",
             setting_type: SettingType::StringEnum,
             default: Value::String(String::new()),
             enum_options: Some(THEME_OPTIONS),
             allows_custom: true,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.terminal.commands.nodeSpawn.loadProfile",
+            description: "When to load your shell profile before running commands: always, never, or once a day.",
             setting_type: SettingType::StringEnum,
             default: Value::String(String::new()),
             enum_options: Some(LOAD_PROFILE_OPTIONS),
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.updates.mode",
+            description: "How the CLI handles new versions: update automatically, only warn, or do nothing.",
             setting_type: SettingType::StringEnum,
             default: Value::String(String::new()),
             enum_options: Some(UPDATE_MODE_OPTIONS),
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.internal.deepReasoningEffort",
+            description: "Reasoning effort used by deep mode.",
             setting_type: SettingType::StringEnum,
             default: Value::String(String::new()),
             enum_options: Some(DEEP_REASONING_OPTIONS),
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.defaultVisibility",
+            description: "Default thread visibility, keyed by repository origin.",
             setting_type: SettingType::Object,
             default: Value::Object(serde_json::Map::new()),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: Some(ObjectSchema::Map {
+                key_label: "origin",
+                key_example: "github.com/org/repo",
+                value: Box::new(FieldDef {
+                    enum_options: Some(&["private", "team", "public"]),
+                    ..FieldDef::new(
+                        "visibility",
+                        SettingType::StringEnum,
+                        Value::String("private".into()),
+                        "Who can see threads started in repositories from this origin.",
+                    )
+                }),
+            }),
+            number: None,
         },
         SettingDef {
             key: "amp.fuzzy.alwaysIncludePaths",
+            description: "Glob patterns always included in file mentions, even when ignored by git.",
             setting_type: SettingType::ArrayString,
             default: Value::Array(vec![]),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
+        },
+        // Experimental
+        SettingDef {
+            key: "amp.experimental.modes",
+            description: "Experimental agent modes to make available, such as bombadil.",
+            setting_type: SettingType::ArrayString,
+            default: Value::Array(vec![]),
+            enum_options: None,
+            allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
+        },
+        SettingDef {
+            key: "amp.subagents.enabled",
+            description: "Let the agent hand tasks off to subagents that work in parallel.",
+            setting_type: SettingType::Boolean,
+            default: Value::Bool(false),
+            enum_options: None,
+            allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         // Permissions
         SettingDef {
             key: "amp.permissions",
+            description: "Rules that allow, reject, ask about, or delegate tool calls before they run.",
             setting_type: SettingType::ArrayObject,
             default: Value::Array(vec![]),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         // Tools
         SettingDef {
             key: "amp.tools.disable",
+            description: "Tools to disable; glob patterns are allowed.",
             setting_type: SettingType::ArrayString,
             default: Value::Array(vec![]),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
         SettingDef {
             key: "amp.tools.stopTimeout",
+            description: "Seconds before a long-running tool call is stopped.",
             setting_type: SettingType::Number,
             default: Value::Number(serde_json::Number::from(300)),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: Some(NumberSpec {
+                min: Some(1.0),
+                max: None,
+                step: 10.0,
+                unit: Some("seconds"),
+            }),
         },
         // MCPs
         SettingDef {
             key: "amp.mcpServers",
+            description: "MCP servers to start, keyed by name.",
             setting_type: SettingType::Object,
             default: Value::Object(serde_json::Map::new()),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: Some(ObjectSchema::Map {
+                key_label: "server",
+                key_example: "github",
+                value: Box::new(FieldDef {
+                    object: Some(mcp_server_schema()),
+                    ..FieldDef::new(
+                        "server",
+                        SettingType::Object,
+                        json!({}),
+                        "How to start or reach the server.",
+                    )
+                }),
+            }),
+            number: None,
         },
         SettingDef {
             key: "amp.mcpPermissions",
+            description: "Rules that allow or reject MCP servers by command or URL.",
             setting_type: SettingType::ArrayObject,
             default: Value::Array(vec![]),
             enum_options: None,
             allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
         },
     ]
 }
@@ -237,16 +833,86 @@ pub fn section_for_key(key: &str) -> Option<Section> {
         "amp.permissions" => Some(Section::Permissions),
         "amp.tools.disable" | "amp.tools.stopTimeout" => Some(Section::Tools),
         "amp.mcpServers" | "amp.mcpPermissions" => Some(Section::Mcps),
+        "amp.experimental.modes" | "amp.subagents.enabled" => Some(Section::Experimental),
         k if known_settings().iter().any(|s| s.key == k) => Some(Section::General),
         _ => None,
     }
 }
 
+/// The environment variable that overrides a setting: the key without its
+/// `amp.` prefix in upper snake case, after `AMP_`, e.g. `AMP_TOOLS_STOP_TIMEOUT`
+/// for `amp.tools.stopTimeout`.
+pub fn env_var(key: &str) -> String {
+    let mut var = String::from("AMP");
+    for segment in key.strip_prefix("amp.").unwrap_or(key).split('.') {
+        var.push('_');
+        let mut prev_lower = false;
+        for c in segment.chars() {
+            if c.is_ascii_uppercase() && prev_lower {
+                var.push('_');
+            }
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+            var.push(c.to_ascii_uppercase());
+        }
+    }
+    var
+}
+
+/// The group a setting is listed under in General: the segment after
+/// `amp.`, capitalized, when more segments follow it, e.g. "Git" for
+/// `amp.git.commit.coauthor.enabled`. Keys like `amp.showCosts` have none.
+pub fn group_for_key(key: &str) -> Option<String> {
+    let (prefix, _) = key.strip_prefix("amp.")?.split_once('.')?;
+    let mut chars = prefix.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
 /// Returns the setting definition for a known key.
 pub fn get_setting_def(key: &str) -> Option<SettingDef> {
     known_settings().into_iter().find(|s| s.key == key)
 }
 
+/// Splits a dotted key typed in Advanced into the setting that holds it
+/// and the path inside, when a prefix of it names an object: an object
+/// setting Amp knows, or a key `holds_object` reports as one in the file.
+/// The longest such prefix wins. Map keys in a schema take the rest whole,
+/// as they may have dots in them (repository names do); other levels split
+/// at each dot. `None` means the key stands on its own.
+pub fn split_nested_key(
+    key: &str,
+    holds_object: impl Fn(&str) -> bool,
+) -> Option<(String, Vec<String>)> {
+    if get_setting_def(key).is_some() || holds_object(key) {
+        return None;
+    }
+    let segments: Vec<&str> = key.split('.').collect();
+    (1..segments.len()).rev().find_map(|split| {
+        let prefix = segments[..split].join(".");
+        let def = get_setting_def(&prefix);
+        let is_object = match &def {
+            Some(def) => def.setting_type == SettingType::Object,
+            None => holds_object(&prefix),
+        };
+        let schema = def.and_then(|def| def.object_schema);
+        is_object.then(|| (prefix, nested_path(schema.as_ref(), &segments[split..])))
+    })
+}
+
+fn nested_path(schema: Option<&ObjectSchema>, rest: &[&str]) -> Vec<String> {
+    match (schema, rest) {
+        (_, []) => Vec::new(),
+        (Some(ObjectSchema::Map { .. }), _) => vec![rest.join(".")],
+        (Some(schema), [first, tail @ ..]) => {
+            let nested = schema.field(first).and_then(|f| f.object.as_ref());
+            let mut path = vec![first.to_string()];
+            path.extend(nested_path(nested, tail));
+            path
+        }
+        (None, _) => rest.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
 /// Returns all known setting keys for a given section.
 pub fn settings_for_section(section: Section) -> Vec<SettingDef> {
     known_settings()
@@ -259,15 +925,153 @@ pub fn settings_for_section(section: Section) -> Vec<SettingDef> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_env_var() {
+        assert_eq!(env_var("amp.showCosts"), "AMP_SHOW_COSTS");
+        assert_eq!(env_var("amp.tools.stopTimeout"), "AMP_TOOLS_STOP_TIMEOUT");
+        assert_eq!(
+            env_var("amp.git.commit.ampThread.enabled"),
+            "AMP_GIT_COMMIT_AMP_THREAD_ENABLED"
+        );
+    }
+
+    #[test]
+    fn test_group_for_key() {
+        assert_eq!(
+            group_for_key("amp.git.commit.coauthor.enabled").as_deref(),
+            Some("Git")
+        );
+        assert_eq!(
+            group_for_key("amp.notifications.enabled").as_deref(),
+            Some("Notifications")
+        );
+        assert_eq!(group_for_key("amp.showCosts"), None);
+        assert_eq!(group_for_key("editor.fontSize"), None);
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact("amp.bitbucketToken", &json!("abc")), json!(REDACTED));
+        assert_eq!(
+            redact(
+                "amp.mcpServers",
+                &json!({"gh": {"command": "npx", "env": {"GITHUB_TOKEN": "ghp", "DEBUG": "1"}}})
+            ),
+            json!({"gh": {"command": "npx", "env": {"GITHUB_TOKEN": REDACTED, "DEBUG": "1"}}})
+        );
+        assert_eq!(redact("amp.showCosts", &json!(true)), json!(true));
+        assert_eq!(
+            redact(
+                "amp.mcpServers",
+                &json!({"ssh": {"args": ["-p", "22"], "env": {"privateKey": "k", "accessKey": "a", "sshKey": "s", "STRIPE_KEY": "sk", "HOTKEY": "h", "GIT_AUTHOR_NAME": "me"}}})
+            ),
+            json!({"ssh": {"args": ["-p", "22"], "env": {"privateKey": REDACTED, "accessKey": REDACTED, "sshKey": REDACTED, "STRIPE_KEY": REDACTED, "HOTKEY": "h", "GIT_AUTHOR_NAME": "me"}}})
+        );
+        // Objects inside arrays are redacted too.
+        assert_eq!(
+            redact(
+                "my.tool.servers",
+                &json!([{"name": "a", "apiKey": "k"}, {"headers": [{"Authorization": "Bearer x"}]}])
+            ),
+            json!([{"name": "a", "apiKey": REDACTED}, {"headers": [{"Authorization": REDACTED}]}])
+        );
+        assert_eq!(
+            redact("my.apiKeys", &json!(["k1", "k2"])),
+            json!([REDACTED, REDACTED])
+        );
+    }
+
+    #[test]
+    fn test_looks_secret() {
+        for key in [
+            "GITHUB_TOKEN",
+            "API_KEY",
+            "OPENAI_API_KEY",
+            "DB_PASSWORD",
+            "GH_PAT",
+            "x-auth",
+            "amp.bitbucketToken",
+            "privateKey",
+            "accessKey",
+            "sshKey",
+            "STRIPE_KEY",
+            "Authorization",
+            "BASIC_AUTH",
+            "proxyAuth",
+        ] {
+            assert!(looks_secret(key), "{key}");
+        }
+        for key in [
+            "DEBUG",
+            "PATH",
+            "KEYBOARD_LAYOUT",
+            "LOG_LEVEL",
+            "HOTKEY",
+            "MONKEY",
+            "GIT_AUTHOR_NAME",
+            "GIT_AUTHOR_EMAIL",
+            "COAUTHOR",
+            "amp.authorName",
+        ] {
+            assert!(!looks_secret(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn test_split_nested_key() {
+        let in_file = |key: &str| key == "my.tool" || key == "my.tool.opts";
+        assert_eq!(
+            split_nested_key("amp.defaultVisibility.github.com/acme", in_file),
+            Some((
+                "amp.defaultVisibility".to_string(),
+                vec!["github.com/acme".to_string()]
+            ))
+        );
+        assert_eq!(
+            split_nested_key("my.tool.opts.deep.level", in_file),
+            Some((
+                "my.tool.opts".to_string(),
+                vec!["deep".to_string(), "level".to_string()]
+            ))
+        );
+        assert_eq!(split_nested_key("amp.showCosts", in_file), None);
+        assert_eq!(split_nested_key("my.tool", in_file), None);
+        assert_eq!(split_nested_key("amp.foo.bar.baz", in_file), None);
+    }
+
+    #[test]
+    fn test_migrations() {
+        for m in MIGRATIONS {
+            assert!(get_setting_def(m.old).is_none(), "{} is still known", m.old);
+            if let Some(new) = m.new {
+                assert!(get_setting_def(new).is_some(), "{new} is unknown");
+            }
+        }
+        let hide = migration_for("amp.hideCosts").unwrap();
+        assert_eq!(hide.apply(&Value::Bool(true)), Ok(Value::Bool(false)));
+        assert!(hide.apply(&Value::String("yes".into())).is_err());
+        assert_eq!(hide.describe(), "replaced by amp.showCosts (inverted)");
+        assert!(migration_for("amp.showCosts").is_none());
+    }
+
     #[test]
     fn test_section_labels() {
         assert_eq!(Section::General.label(), "General");
         assert_eq!(Section::Permissions.label(), "Permissions");
         assert_eq!(Section::Tools.label(), "Tools");
         assert_eq!(Section::Mcps.label(), "MCPs");
+        assert_eq!(Section::Experimental.label(), "Experimental");
         assert_eq!(Section::Advanced.label(), "Advanced");
     }
 
+    #[test]
+    fn test_section_from_label() {
+        assert_eq!(Section::from_label("general"), Some(Section::General));
+        assert_eq!(Section::from_label("MCPs"), Some(Section::Mcps));
+        assert_eq!(Section::from_label("mcp"), Some(Section::Mcps));
+        assert_eq!(Section::from_label("nope"), None);
+    }
+
     #[test]
     fn test_section_for_known_keys() {
         assert_eq!(section_for_key("amp.showCosts"), Some(Section::General));
@@ -277,11 +1081,15 @@ mod tests {
         );
         assert_eq!(section_for_key("amp.tools.disable"), Some(Section::Tools));
         assert_eq!(section_for_key("amp.mcpServers"), Some(Section::Mcps));
+        assert_eq!(
+            section_for_key("amp.experimental.modes"),
+            Some(Section::Experimental)
+        );
     }
 
     #[test]
     fn test_section_for_unknown_key() {
-        assert_eq!(section_for_key("amp.experimental.modes"), None);
+        assert_eq!(section_for_key("amp.experimental.labs"), None);
         assert_eq!(section_for_key("some.random.key"), None);
     }
 
@@ -315,7 +1123,7 @@ mod tests {
     #[test]
     fn test_all_sections_covered() {
         for section in Section::ALL {
-            if *section != Section::Advanced {
+            if !matches!(section, Section::Favorites | Section::Advanced) {
                 assert!(
                     !settings_for_section(*section).is_empty(),
                     "Section {:?} has no settings",
@@ -336,6 +1144,17 @@ mod tests {
         assert!(update.enum_options.unwrap().contains(&"auto"));
     }
 
+    #[test]
+    fn test_builtins_have_descriptions() {
+        for def in builtin_settings() {
+            assert!(
+                !def.description.is_empty(),
+                "{} has no description",
+                def.key
+            );
+        }
+    }
+
     #[test]
     fn test_no_duplicate_keys() {
         let settings = known_settings();
@@ -345,6 +1164,61 @@ mod tests {
         assert_eq!(keys.len(), settings.len(), "Duplicate keys found");
     }
 
+    #[test]
+    fn test_merge_skips_builtin_and_duplicate_keys() {
+        let def = |key: &'static str| SettingDef {
+            key,
+            description: "",
+            setting_type: SettingType::Boolean,
+            default: Value::Bool(false),
+            enum_options: None,
+            allows_custom: false,
+            sensitive: false,
+            object_schema: None,
+            number: None,
+        };
+        let merged = merge(
+            builtin_settings(),
+            vec![def("amp.showCosts"), def("amp.new"), def("amp.new")],
+        );
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].key, "amp.new");
+    }
+
+    #[test]
+    fn test_number_spec() {
+        let spec = get_setting_def("amp.tools.stopTimeout")
+            .unwrap()
+            .number_spec();
+        assert_eq!(spec.check(0.0), Err("must be at least 1".into()));
+        assert!(spec.check(300.0).is_ok());
+        assert_eq!(spec.stepped(300.0, true), 310.0);
+        assert_eq!(spec.stepped(5.0, false), 1.0);
+        assert_eq!(spec.describe().as_deref(), Some("seconds, at least 1"));
+
+        let tenths = NumberSpec {
+            min: Some(0.0),
+            max: Some(1.0),
+            step: 0.1,
+            unit: None,
+        };
+        assert_eq!(tenths.stepped(0.2, true), 0.3);
+        assert_eq!(tenths.stepped(1.0, true), 1.0);
+        assert_eq!(tenths.check(2.0), Err("must be from 0 to 1".into()));
+        assert_eq!(NumberSpec::default().describe(), None);
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number(" 300 "), Some(300.into()));
+        assert_eq!(parse_number("1e3"), Some(1000.into()));
+        assert_eq!(parse_number("10_000"), Some(10_000.into()));
+        assert_eq!(parse_number("1.5").and_then(|n| n.as_f64()), Some(1.5));
+        assert_eq!(parse_number("abc"), None);
+        assert_eq!(format_number(2.0), "2");
+        assert_eq!(format_number(2.5), "2.5");
+    }
+
     #[test]
     fn test_is_single_key() {
         assert!(Section::Permissions.is_single_key());
@@ -362,4 +1236,35 @@ mod tests {
         assert!(!Section::Tools.is_split_panel());
         assert!(!Section::Advanced.is_split_panel());
     }
+
+    #[test]
+    fn test_object_schema_descend_and_accepts() {
+        let schema = get_setting_def("amp.mcpServers")
+            .unwrap()
+            .object_schema
+            .unwrap();
+        let path = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let env = schema.descend(&path(&["github", "env"])).unwrap();
+        assert!(matches!(
+            env,
+            ObjectSchema::Map {
+                key_label: "variable",
+                ..
+            }
+        ));
+        assert!(env.field("ANY").unwrap().accepts(&json!("x")));
+        assert!(schema.descend(&path(&["github", "command"])).is_none());
+
+        let server = schema.field("github").unwrap();
+        let args = server.object.as_ref().unwrap().field("args").unwrap();
+        assert!(args.accepts(&json!(["-y"])));
+        assert!(!args.accepts(&json!([1])));
+        let visibility = get_setting_def("amp.defaultVisibility")
+            .unwrap()
+            .object_schema
+            .unwrap();
+        let value = visibility.field("github.com/a/b").unwrap();
+        assert!(value.accepts(&json!("team")));
+        assert!(!value.accepts(&json!("everyone")));
+    }
 }
@@ -0,0 +1,432 @@
+//! Sharing one settings file across machines through a remote copy: a
+//! GitHub gist, an HTTPS endpoint that answers GET and PUT, or a path such
+//! as a synced folder.
+//!
+//! The content hash of what was last pushed or pulled is kept for each
+//! remote in `~/.config/volt/sync.json`. A push refuses to overwrite a
+//! remote that changed since then, and a pull refuses to overwrite a local
+//! file that did, unless forced:
+//!
+//! ```json
+//! { "gist:0123abcd": "9f1c2e3a4b5d6e7f" }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+use crate::amp;
+use crate::config::{self, Config};
+
+/// The file a gist keeps the settings in.
+const GIST_FILE: &str = "settings.json";
+
+/// Where settings are synced to and from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Remote {
+    /// A GitHub gist by id, reached with `$GITHUB_TOKEN` (or `$GH_TOKEN`).
+    Gist(String),
+    /// A URL that returns the file on GET and replaces it on PUT, with
+    /// `$VOLT_SYNC_TOKEN` as a bearer token when set.
+    Url(String),
+    /// A file, e.g. in a folder another tool keeps in sync.
+    Path(PathBuf),
+}
+
+impl Remote {
+    /// Parses `gist:<id>`, an `http(s)://` URL, or a path. A leading `~/` is
+    /// expanded and a relative path made absolute, so the remote (and the
+    /// state kept for it) doesn't depend on where volt runs from.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if let Some(id) = spec.strip_prefix("gist:") {
+            if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+                bail!("'{spec}' is not a gist id; use gist:<id> as in the gist's URL");
+            }
+            return Ok(Remote::Gist(id.to_string()));
+        }
+        if spec.starts_with("https://") || spec.starts_with("http://") {
+            return Ok(Remote::Url(spec.to_string()));
+        }
+        if spec.is_empty() {
+            bail!("no sync remote given");
+        }
+        let path = config::expand_home(spec);
+        let path =
+            std::path::absolute(&path).with_context(|| format!("resolving {}", path.display()))?;
+        Ok(Remote::Path(path))
+    }
+
+    /// The remote's copy of the settings; `None` if there isn't one yet.
+    fn fetch(&self) -> Result<Option<String>> {
+        match self {
+            Remote::Path(path) if !path.exists() => Ok(None),
+            Remote::Path(path) => fs::read_to_string(path)
+                .map(Some)
+                .with_context(|| format!("reading {}", path.display())),
+            Remote::Url(url) => request("GET", url, &bearer("VOLT_SYNC_TOKEN"), None),
+            Remote::Gist(id) => {
+                let url = format!("https://api.github.com/gists/{id}");
+                let Some(body) = request("GET", &url, &gist_headers(), None)? else {
+                    bail!("gist {id} doesn't exist, or the token can't see it");
+                };
+                let gist: Value =
+                    serde_json::from_str(&body).context("parsing the gist API's answer")?;
+                match gist_file(&gist) {
+                    Some(file) if file["truncated"] == true => {
+                        let raw = file["raw_url"]
+                            .as_str()
+                            .context("gist file has no raw_url")?;
+                        request("GET", raw, &[], None)
+                    }
+                    Some(file) => Ok(file["content"].as_str().map(str::to_string)),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Replaces the remote's copy with `text`.
+    fn store(&self, text: &str) -> Result<()> {
+        match self {
+            Remote::Path(path) => {
+                if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                    fs::create_dir_all(dir)
+                        .with_context(|| format!("creating directory {}", dir.display()))?;
+                }
+                fs::write(path, text).with_context(|| format!("writing {}", path.display()))
+            }
+            Remote::Url(url) => {
+                request("PUT", url, &bearer("VOLT_SYNC_TOKEN"), Some(text))?;
+                Ok(())
+            }
+            Remote::Gist(id) => {
+                if gist_headers().is_empty() {
+                    bail!("pushing to a gist requires $GITHUB_TOKEN (or $GH_TOKEN) with the gist scope");
+                }
+                let url = format!("https://api.github.com/gists/{id}");
+                let body = json!({"files": {GIST_FILE: {"content": text}}}).to_string();
+                if request("PATCH", &url, &gist_headers(), Some(&body))?.is_none() {
+                    bail!("gist {id} doesn't exist, or the token can't see it");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Remote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Remote::Gist(id) => write!(f, "gist:{id}"),
+            Remote::Url(url) => f.write_str(url),
+            Remote::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// The gist file holding the settings: `settings.json`, or the only file.
+fn gist_file(gist: &Value) -> Option<&Value> {
+    let files = gist.get("files")?.as_object()?;
+    files
+        .get(GIST_FILE)
+        .or_else(|| (files.len() == 1).then(|| files.values().next()).flatten())
+}
+
+fn bearer(var: &str) -> Vec<String> {
+    std::env::var(var)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(|token| format!("Authorization: Bearer {token}"))
+        .into_iter()
+        .collect()
+}
+
+fn gist_headers() -> Vec<String> {
+    let mut headers = bearer("GITHUB_TOKEN");
+    if headers.is_empty() {
+        headers = bearer("GH_TOKEN");
+    }
+    headers
+}
+
+/// Sends a request with `curl`, like `schema::fetch`, returning the body,
+/// or `None` for a 404. Headers go to curl on stdin and the body through a
+/// temp file, so tokens never show up in `ps`.
+fn request(
+    method: &str,
+    url: &str,
+    headers: &[String],
+    body: Option<&str>,
+) -> Result<Option<String>> {
+    let curl =
+        amp::find_on_path("curl").with_context(|| format!("syncing with {url} requires curl"))?;
+    let mut cmd = Command::new(curl);
+    cmd.args(["--silent", "--show-error", "--location", "-X", method])
+        .args(["-w", "\n%{http_code}", "-H", "@-"]);
+    let mut stdin_headers = String::from("Accept: application/vnd.github+json\n");
+    for header in headers {
+        stdin_headers.push_str(header);
+        stdin_headers.push('\n');
+    }
+    let body_file = match body {
+        Some(body) => {
+            let mut file = tempfile::NamedTempFile::new().context("creating a temp file")?;
+            file.write_all(body.as_bytes())
+                .with_context(|| format!("writing {}", file.path().display()))?;
+            stdin_headers.push_str("Content-Type: application/json\n");
+            cmd.arg("--data-binary")
+                .arg(format!("@{}", file.path().display()));
+            Some(file)
+        }
+        None => None,
+    };
+    let mut child = cmd
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running curl for {url}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(stdin_headers.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    drop(body_file);
+    if !output.status.success() {
+        bail!(
+            "{method} {url} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let text = String::from_utf8(output.stdout).with_context(|| format!("{url} is not UTF-8"))?;
+    let (body, code) = text.rsplit_once('\n').unwrap_or(("", &text));
+    match code.trim() {
+        "404" => Ok(None),
+        code if code.starts_with('2') => Ok(Some(body.to_string())),
+        code => bail!("{method} {url} failed with HTTP {code}"),
+    }
+}
+
+/// The hash of what was last synced with each remote.
+#[derive(Debug, Default)]
+pub struct State {
+    path: PathBuf,
+    synced: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Returns the path the sync state is kept at.
+    pub fn default_path() -> Result<PathBuf> {
+        let home = dirs::home_dir().context("could not determine home directory")?;
+        Ok(home.join(".config").join("volt").join("sync.json"))
+    }
+
+    /// Loads the sync state; a missing file means nothing was synced yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let synced = if path.exists() {
+            let text =
+                fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+            serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            synced,
+        })
+    }
+
+    fn record(&mut self, remote: &Remote, text: &str) -> Result<()> {
+        self.synced.insert(remote.to_string(), config::hash(text));
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let text = serde_json::to_string_pretty(&self.synced)?;
+        fs::write(&self.path, text + "\n")
+            .with_context(|| format!("writing {}", self.path.display()))
+    }
+
+    /// Whether `text` is what was last synced with `remote`.
+    fn is_synced(&self, remote: &Remote, text: &str) -> bool {
+        self.synced.get(&remote.to_string()) == Some(&config::hash(text))
+    }
+}
+
+/// What a push or pull did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pushed,
+    Pulled,
+    /// Both sides already had the same contents.
+    UpToDate,
+}
+
+impl Outcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::Pushed => "pushed",
+            Outcome::Pulled => "pulled",
+            Outcome::UpToDate => "up to date",
+        }
+    }
+}
+
+/// Copies the settings file to the remote. A remote that changed since it
+/// was last synced from here is only overwritten with `force`.
+pub fn push(config: &Config, remote: &Remote, state: &mut State, force: bool) -> Result<Outcome> {
+    if !config.exists() {
+        bail!(
+            "{} doesn't exist yet; there's nothing to push",
+            config.path().display()
+        );
+    }
+    let local = config.read_disk()?;
+    let theirs = remote.fetch()?;
+    if theirs.as_deref() == Some(local.as_str()) {
+        state.record(remote, &local)?;
+        return Ok(Outcome::UpToDate);
+    }
+    if let Some(theirs) = &theirs {
+        if !force && !state.is_synced(remote, theirs) {
+            bail!(
+                "{remote} has changes not synced here (content hash {}); pull them first, or push with --force to overwrite them",
+                config::hash(theirs)
+            );
+        }
+    }
+    remote.store(&local)?;
+    state.record(remote, &local)?;
+    Ok(Outcome::Pushed)
+}
+
+/// Replaces the settings file with the remote's copy, keeping a backup. A
+/// local file that changed since it was last synced is only overwritten
+/// with `force`.
+pub fn pull(
+    config: &mut Config,
+    remote: &Remote,
+    state: &mut State,
+    force: bool,
+) -> Result<Outcome> {
+    let Some(theirs) = remote.fetch()? else {
+        bail!("{remote} has no settings yet; push some first");
+    };
+    let local = config.exists().then(|| config.read_disk()).transpose()?;
+    if local.as_deref() == Some(theirs.as_str()) {
+        state.record(remote, &theirs)?;
+        return Ok(Outcome::UpToDate);
+    }
+    if let Some(local) = &local {
+        if !force && !state.is_synced(remote, local) {
+            bail!(
+                "{} has changes not synced to {remote} (content hash {}); push them first, or pull with --force to overwrite them (a backup is kept)",
+                config.path().display(),
+                config::hash(local)
+            );
+        }
+    }
+    config.overwrite(&theirs)?;
+    state.record(remote, &theirs)?;
+    Ok(Outcome::Pulled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote() {
+        assert_eq!(
+            Remote::parse("gist:0123abcd").unwrap(),
+            Remote::Gist("0123abcd".into())
+        );
+        assert!(Remote::parse("gist:").is_err());
+        assert!(Remote::parse("gist:../x").is_err());
+        assert_eq!(
+            Remote::parse("https://example.com/amp.json").unwrap(),
+            Remote::Url("https://example.com/amp.json".into())
+        );
+        let home = dirs::home_dir().unwrap();
+        let path = Remote::parse("~/Sync/amp.json").unwrap();
+        assert_eq!(path, Remote::Path(home.join("Sync").join("amp.json")));
+        assert_eq!(
+            path.to_string(),
+            home.join("Sync").join("amp.json").display().to_string()
+        );
+        assert_eq!(
+            Remote::parse("shared/amp.json").unwrap(),
+            Remote::Path(std::env::current_dir().unwrap().join("shared/amp.json"))
+        );
+        assert!(Remote::parse(" ").is_err());
+    }
+
+    #[test]
+    fn test_gist_file() {
+        let gist = json!({"files": {"notes.md": {}, "settings.json": {"content": "{}"}}});
+        assert_eq!(gist_file(&gist).unwrap()["content"], "{}");
+        let only = json!({"files": {"amp.json": {"content": "{}"}}});
+        assert_eq!(gist_file(&only).unwrap()["content"], "{}");
+        assert!(gist_file(&json!({"files": {"a": {}, "b": {}}})).is_none());
+    }
+
+    #[test]
+    fn test_push_and_pull() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = Remote::Path(dir.path().join("shared").join("settings.json"));
+        let laptop_path = dir.path().join("laptop.json");
+        let desktop_path = dir.path().join("desktop.json");
+        fs::write(&laptop_path, "{\"amp.showCosts\": false}\n").unwrap();
+        let mut laptop = Config::load(&laptop_path).unwrap();
+        let mut laptop_state = State::load(&dir.path().join("laptop-sync.json")).unwrap();
+        let mut desktop = Config::load(&desktop_path).unwrap();
+        let mut desktop_state = State::load(&dir.path().join("desktop-sync.json")).unwrap();
+
+        assert!(pull(&mut desktop, &remote, &mut desktop_state, false).is_err());
+        let pushed = push(&laptop, &remote, &mut laptop_state, false).unwrap();
+        assert_eq!(pushed, Outcome::Pushed);
+        assert_eq!(
+            push(&laptop, &remote, &mut laptop_state, false).unwrap(),
+            Outcome::UpToDate
+        );
+
+        // A fresh machine with no file takes the remote's.
+        assert_eq!(
+            pull(&mut desktop, &remote, &mut desktop_state, false).unwrap(),
+            Outcome::Pulled
+        );
+        assert_eq!(desktop.get("amp.showCosts"), false);
+
+        // The desktop pushes a change; the laptop's next push would lose it.
+        desktop.set("amp.showCosts", Value::Bool(true));
+        desktop.save().unwrap();
+        push(&desktop, &remote, &mut desktop_state, false).unwrap();
+        laptop.set("amp.bitbucketToken", Value::String("x".into()));
+        laptop.save().unwrap();
+        let err = push(&laptop, &remote, &mut laptop_state, false).unwrap_err();
+        assert!(err.to_string().contains("pull them first"), "{err}");
+
+        // Pulling would lose the laptop's unpushed change too.
+        let err = pull(&mut laptop, &remote, &mut laptop_state, false).unwrap_err();
+        assert!(err.to_string().contains("push them first"), "{err}");
+        pull(&mut laptop, &remote, &mut laptop_state, true).unwrap();
+        assert_eq!(laptop.get("amp.showCosts"), true);
+        assert!(laptop.get_raw("amp.bitbucketToken").is_none());
+        assert!(!crate::backup::list(&laptop_path).is_empty());
+
+        // The state survives a restart.
+        let mut reloaded = State::load(&dir.path().join("laptop-sync.json")).unwrap();
+        laptop.set("amp.showCosts", Value::Bool(false));
+        laptop.save().unwrap();
+        assert_eq!(
+            push(&laptop, &remote, &mut reloaded, false).unwrap(),
+            Outcome::Pushed
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! Minimal text buffers with a cursor: a single line for prompts, and
+//! multiple lines backing the inline JSON editor.
+
+use std::ops::Deref;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// One line of text being typed into a prompt, with a cursor that counts
+/// grapheme clusters, so an emoji or a letter with a combining accent moves
+/// and deletes as one. Reads go through `Deref`; changes go through methods
+/// so the cursor stays on a cluster boundary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextInput {
+    text: String,
+    cursor: usize,
+}
+
+impl TextInput {
+    /// Replaces the text, with the cursor at its end.
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    /// Returns the text, leaving the input empty.
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    /// The cursor's position in grapheme clusters from the start.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn byte_at(&self, col: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .nth(col)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+
+    fn len(&self) -> usize {
+        self.text.graphemes(true).count()
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.insert(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Inserts pasted text at the cursor. Line breaks become spaces, since
+    /// a prompt is one line.
+    pub fn insert_str(&mut self, text: &str) {
+        self.insert(&single_line(text));
+    }
+
+    fn insert(&mut self, text: &str) {
+        let at = self.byte_at(self.cursor);
+        self.text.insert_str(at, text);
+        // Counted again rather than added: a typed combining mark joins the
+        // cluster before it.
+        self.cursor = self.text[..at + text.len()].graphemes(true).count();
+    }
+
+    /// Deletes the cluster before the cursor.
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.remove_at_cursor();
+        }
+    }
+
+    /// Deletes the cluster under the cursor.
+    pub fn delete(&mut self) {
+        if self.cursor < self.len() {
+            self.remove_at_cursor();
+        }
+    }
+
+    fn remove_at_cursor(&mut self) {
+        let start = self.byte_at(self.cursor);
+        let end = self.byte_at(self.cursor + 1);
+        self.text.replace_range(start..end, "");
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.len();
+    }
+
+    /// Moves to the start of the word before the cursor, skipping any
+    /// separators first.
+    pub fn move_word_left(&mut self) {
+        let clusters: Vec<&str> = self.text.graphemes(true).collect();
+        let mut col = self.cursor;
+        while col > 0 && !is_word(clusters[col - 1]) {
+            col -= 1;
+        }
+        while col > 0 && is_word(clusters[col - 1]) {
+            col -= 1;
+        }
+        self.cursor = col;
+    }
+
+    /// Moves past the end of the word after the cursor, skipping any
+    /// separators first.
+    pub fn move_word_right(&mut self) {
+        let clusters: Vec<&str> = self.text.graphemes(true).collect();
+        let mut col = self.cursor;
+        while col < clusters.len() && !is_word(clusters[col]) {
+            col += 1;
+        }
+        while col < clusters.len() && is_word(clusters[col]) {
+            col += 1;
+        }
+        self.cursor = col;
+    }
+}
+
+impl Deref for TextInput {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.text
+    }
+}
+
+impl PartialEq<&str> for TextInput {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+/// Whether a cluster belongs to a word for word-wise movement. Dots,
+/// slashes, dashes and the like separate words, so `amp.tools.disable`
+/// is three.
+fn is_word(cluster: &str) -> bool {
+    cluster
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Flattens pasted text onto one line: a trailing line break is dropped and
+/// the others become spaces.
+pub fn single_line(text: &str) -> String {
+    text.trim_end_matches(['\r', '\n'])
+        .replace("\r\n", " ")
+        .replace(['\r', '\n'], " ")
+}
+
+/// Removes the last grapheme cluster of `text`, for fields edited only at
+/// their end.
+pub fn pop_grapheme(text: &mut String) {
+    if let Some((at, _)) = text.grapheme_indices(true).next_back() {
+        text.truncate(at);
+    }
+}
+
+/// Lines of text plus a cursor position. The column counts characters, not
+/// bytes, so multi-byte text edits correctly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextArea {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl TextArea {
+    /// Creates a buffer holding `text`, with the cursor at the start.
+    pub fn new(text: &str) -> Self {
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Self {
+            lines,
+            row: 0,
+            col: 0,
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Returns the cursor as (row, column), both zero-based.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    /// Byte offset of the cursor within the current line.
+    fn byte_col(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len())
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let at = self.byte_col();
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+    }
+
+    /// Inserts pasted text at the cursor. Its line breaks split lines as
+    /// they are, without the indentation `newline` adds.
+    pub fn insert_str(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let at = self.byte_col();
+        let rest = self.lines[self.row].split_off(at);
+        let mut pasted = text.split('\n');
+        if let Some(first) = pasted.next() {
+            self.lines[self.row].push_str(first);
+        }
+        for line in pasted {
+            self.row += 1;
+            self.lines.insert(self.row, line.to_string());
+        }
+        self.col = self.line_len(self.row);
+        self.lines[self.row].push_str(&rest);
+    }
+
+    /// Splits the line at the cursor, carrying over the current indentation.
+    pub fn newline(&mut self) {
+        let at = self.byte_col();
+        let rest = self.lines[self.row].split_off(at);
+        let indent: String = self.lines[self.row]
+            .chars()
+            .take_while(|c| *c == ' ')
+            .collect();
+        self.col = indent.chars().count();
+        self.row += 1;
+        self.lines.insert(self.row, indent + &rest);
+    }
+
+    /// Deletes the character before the cursor, joining lines at a line start.
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.byte_col();
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    /// Deletes the character under the cursor, joining lines at a line end.
+    pub fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            let at = self.byte_col();
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.line_len(self.row));
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.line_len(self.row));
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.col = self.line_len(self.row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_input_edits_at_the_cursor() {
+        let mut input = TextInput::default();
+        input.set("amp.tols");
+        assert_eq!(input.cursor(), 8);
+        input.move_left();
+        input.move_left();
+        input.insert_char('o');
+        assert_eq!(input, "amp.tools");
+        input.move_home();
+        input.delete();
+        input.insert_char('A');
+        assert_eq!(input, "Amp.tools");
+        input.move_end();
+        input.backspace();
+        assert_eq!((input.as_str(), input.cursor()), ("Amp.tool", 8));
+        input.move_right();
+        assert_eq!(input.cursor(), 8);
+        assert_eq!(input.take(), "Amp.tool");
+        assert_eq!((input.as_str(), input.cursor()), ("", 0));
+    }
+
+    #[test]
+    fn test_text_input_word_movement() {
+        let mut input = TextInput::default();
+        input.set("git push --force");
+        input.move_word_left();
+        assert_eq!(input.cursor(), 11);
+        input.move_word_left();
+        assert_eq!(input.cursor(), 4);
+        input.move_word_left();
+        input.move_word_left();
+        assert_eq!(input.cursor(), 0);
+        input.move_word_right();
+        assert_eq!(input.cursor(), 3);
+        input.move_word_right();
+        assert_eq!(input.cursor(), 8);
+    }
+
+    #[test]
+    fn test_text_input_multibyte() {
+        let mut input = TextInput::default();
+        input.set("café");
+        input.move_left();
+        input.insert_char('ü');
+        assert_eq!(input, "cafüé");
+        input.delete();
+        assert_eq!(input, "cafü");
+    }
+
+    #[test]
+    fn test_text_input_graphemes() {
+        let mut input = TextInput::default();
+        input.set("hi 👍🏽 e\u{301}");
+        assert_eq!(input.cursor(), 6);
+        input.backspace();
+        assert_eq!(input, "hi 👍🏽 ");
+        input.move_left();
+        input.backspace();
+        assert_eq!((input.as_str(), input.cursor()), ("hi  ", 3));
+        input.insert_char('e');
+        input.insert_char('\u{301}');
+        assert_eq!((input.as_str(), input.cursor()), ("hi e\u{301} ", 4));
+    }
+
+    #[test]
+    fn test_text_input_paste_is_one_line() {
+        let mut input = TextInput::default();
+        input.set("ab");
+        input.move_left();
+        input.insert_str("x\r\ny\n");
+        assert_eq!((input.as_str(), input.cursor()), ("ax yb", 4));
+
+        let mut field = String::from("ok👍🏽");
+        pop_grapheme(&mut field);
+        assert_eq!(field, "ok");
+    }
+
+    #[test]
+    fn test_paste_into_text_area() {
+        let mut t = TextArea::new("[\n  1\n]");
+        t.move_down();
+        t.move_end();
+        t.insert_str(",\n  2\r\n");
+        assert_eq!(t.text(), "[\n  1,\n  2\n\n]");
+        assert_eq!(t.cursor(), (3, 0));
+    }
+
+    #[test]
+    fn test_insert_and_newline_keeps_indent() {
+        let mut t = TextArea::new("{\n  \"a\": 1\n}");
+        t.move_down();
+        t.move_end();
+        t.insert_char(',');
+        t.newline();
+        for c in "\"b\": 2".chars() {
+            t.insert_char(c);
+        }
+        assert_eq!(t.text(), "{\n  \"a\": 1,\n  \"b\": 2\n}");
+        assert_eq!(t.cursor(), (2, 8));
+    }
+
+    #[test]
+    fn test_backspace_and_delete_join_lines() {
+        let mut t = TextArea::new("ab\ncd");
+        t.move_down();
+        t.backspace();
+        assert_eq!(t.text(), "abcd");
+        assert_eq!(t.cursor(), (0, 2));
+        t.move_end();
+        t.delete();
+        assert_eq!(t.text(), "abcd");
+        t.move_home();
+        t.delete();
+        assert_eq!(t.text(), "bcd");
+    }
+
+    #[test]
+    fn test_cursor_clamps_and_wraps() {
+        let mut t = TextArea::new("long line\nx");
+        t.move_end();
+        t.move_down();
+        assert_eq!(t.cursor(), (1, 1));
+        t.move_right();
+        assert_eq!(t.cursor(), (1, 1));
+        t.move_home();
+        t.move_left();
+        assert_eq!(t.cursor(), (0, 9));
+        t.move_right();
+        assert_eq!(t.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn test_multibyte_characters() {
+        let mut t = TextArea::new("\"é\"");
+        t.move_right();
+        t.move_right();
+        t.insert_char('ü');
+        assert_eq!(t.text(), "\"éü\"");
+        t.backspace();
+        t.backspace();
+        assert_eq!(t.text(), "\"\"");
+    }
+
+    #[test]
+    fn test_empty_text() {
+        let mut t = TextArea::new("");
+        assert_eq!(t.lines().len(), 1);
+        t.backspace();
+        t.delete();
+        assert_eq!(t.text(), "");
+    }
+}
@@ -0,0 +1,265 @@
+//! Color themes for the volt UI.
+
+use anyhow::{bail, Result};
+use ratatui::style::{Color, Modifier, Style};
+
+/// Named styles used throughout the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Regular text.
+    pub text: Style,
+    /// Secondary text: hints, column headers, defaults, empty-state messages.
+    pub muted: Style,
+    /// Borders of unfocused panels.
+    pub border: Style,
+    /// Borders of the focused panel and other highlights.
+    pub accent: Style,
+    /// The selected row in the focused list.
+    pub selected: Style,
+    /// The selected section in the sidebar while it isn't focused.
+    pub selected_unfocused: Style,
+    /// Setting values.
+    pub value: Style,
+    /// Warnings such as permission rules that can never match.
+    pub error: Style,
+    /// Borders of popups and overlays.
+    pub popup_border: Style,
+    /// The selected row in a popup.
+    pub popup_selected: Style,
+    /// The status message bar.
+    pub status: Style,
+    /// Added lines in the diff view.
+    pub added: Style,
+    /// Removed lines in the diff view.
+    pub removed: Style,
+    /// Marker for values supplied by the project settings file.
+    pub project: Style,
+    /// Marker for values supplied by the global settings file.
+    pub global: Style,
+    /// Object keys in highlighted JSON.
+    pub json_key: Style,
+    /// Strings in highlighted JSON.
+    pub json_string: Style,
+    /// Numbers in highlighted JSON.
+    pub json_number: Style,
+    /// `true`, `false`, and `null` in highlighted JSON.
+    pub json_literal: Style,
+}
+
+impl Theme {
+    /// Names of the built-in themes.
+    pub const NAMES: &[&str] = &["dark", "light", "solarized", "high-contrast"];
+
+    /// Looks up a built-in theme by name.
+    pub fn by_name(name: &str) -> Result<Theme> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            "solarized" => Ok(Self::solarized()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            _ => bail!(
+                "unknown theme '{name}' (available: {})",
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+
+    /// The original volt colors, for dark terminal backgrounds.
+    pub fn dark() -> Theme {
+        Theme {
+            name: "dark",
+            text: fg(Color::White),
+            muted: fg(Color::DarkGray),
+            border: fg(Color::DarkGray),
+            accent: fg(Color::Cyan),
+            selected: on(Color::Black, Color::Cyan),
+            selected_unfocused: on(Color::White, Color::DarkGray),
+            value: fg(Color::Yellow),
+            error: fg(Color::Red),
+            popup_border: fg(Color::Yellow),
+            popup_selected: on(Color::Black, Color::Yellow),
+            status: Style::default().fg(Color::Black).bg(Color::Yellow),
+            added: fg(Color::Green),
+            removed: fg(Color::Red),
+            project: fg(Color::Magenta),
+            global: fg(Color::Blue),
+            json_key: fg(Color::Cyan),
+            json_string: fg(Color::Green),
+            json_number: fg(Color::Magenta),
+            json_literal: fg(Color::Blue),
+        }
+    }
+
+    /// For light terminal backgrounds, where white text and yellow values
+    /// are unreadable.
+    pub fn light() -> Theme {
+        let orange = Color::Indexed(130);
+        Theme {
+            name: "light",
+            text: fg(Color::Black),
+            muted: fg(Color::DarkGray),
+            border: fg(Color::Gray),
+            accent: fg(Color::Blue),
+            selected: on(Color::White, Color::Blue),
+            selected_unfocused: on(Color::Black, Color::Gray),
+            value: fg(orange),
+            error: fg(Color::Red),
+            popup_border: fg(Color::Magenta),
+            popup_selected: on(Color::White, Color::Magenta),
+            status: Style::default().fg(Color::White).bg(Color::Blue),
+            added: fg(Color::Green),
+            removed: fg(Color::Red),
+            project: fg(Color::Magenta),
+            global: fg(Color::Blue),
+            json_key: fg(Color::Blue),
+            json_string: fg(Color::Green),
+            json_number: fg(Color::Magenta),
+            json_literal: fg(orange),
+        }
+    }
+
+    /// Ethan Schoonover's Solarized palette (dark variant).
+    pub fn solarized() -> Theme {
+        let base03 = Color::Rgb(0x00, 0x2b, 0x36);
+        let base02 = Color::Rgb(0x07, 0x36, 0x42);
+        let base01 = Color::Rgb(0x58, 0x6e, 0x75);
+        let base0 = Color::Rgb(0x83, 0x94, 0x96);
+        let yellow = Color::Rgb(0xb5, 0x89, 0x00);
+        let orange = Color::Rgb(0xcb, 0x4b, 0x16);
+        let red = Color::Rgb(0xdc, 0x32, 0x2f);
+        let magenta = Color::Rgb(0xd3, 0x36, 0x82);
+        let blue = Color::Rgb(0x26, 0x8b, 0xd2);
+        let cyan = Color::Rgb(0x2a, 0xa1, 0x98);
+        let green = Color::Rgb(0x85, 0x99, 0x00);
+        Theme {
+            name: "solarized",
+            text: fg(base0),
+            muted: fg(base01),
+            border: fg(base01),
+            accent: fg(cyan),
+            selected: on(base03, cyan),
+            selected_unfocused: on(base0, base02),
+            value: fg(yellow),
+            error: fg(red),
+            popup_border: fg(orange),
+            popup_selected: on(base03, orange),
+            status: Style::default().fg(base03).bg(yellow),
+            added: fg(green),
+            removed: fg(red),
+            project: fg(magenta),
+            global: fg(blue),
+            json_key: fg(blue),
+            json_string: fg(cyan),
+            json_number: fg(magenta),
+            json_literal: fg(orange),
+        }
+    }
+
+    /// Bright colors and bold borders for maximum legibility.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: "high-contrast",
+            text: fg(Color::White),
+            muted: fg(Color::Gray),
+            border: fg(Color::White),
+            accent: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            selected: on(Color::Black, Color::LightYellow),
+            selected_unfocused: on(Color::Black, Color::White),
+            value: fg(Color::LightCyan),
+            error: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            popup_border: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            popup_selected: on(Color::Black, Color::LightYellow),
+            status: Style::default()
+                .fg(Color::Black)
+                .bg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            added: fg(Color::LightGreen),
+            removed: fg(Color::LightRed),
+            project: fg(Color::LightMagenta),
+            global: fg(Color::LightBlue),
+            json_key: fg(Color::LightCyan),
+            json_string: fg(Color::LightGreen),
+            json_number: fg(Color::LightMagenta),
+            json_literal: fg(Color::LightYellow),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// The main colors of one of Amp's built-in terminal themes, for previewing
+/// `amp.terminal.theme` values. These are volt's own approximations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmpPalette {
+    pub background: Color,
+    pub foreground: Color,
+    /// Red, green, yellow, and blue, in that order.
+    pub accents: [Color; 4],
+}
+
+/// Looks up the palette of a built-in Amp theme. Themes from the themes
+/// directory have none, since volt doesn't read their files.
+pub fn amp_palette(name: &str) -> Option<AmpPalette> {
+    let rgb = |hex: u32| Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8);
+    let palette = |background, foreground, accents: [u32; 4]| AmpPalette {
+        background: rgb(background),
+        foreground: rgb(foreground),
+        accents: accents.map(rgb),
+    };
+    Some(match name {
+        // Whatever the terminal's own colors are.
+        "terminal" => AmpPalette {
+            background: Color::Reset,
+            foreground: Color::Reset,
+            accents: [Color::Red, Color::Green, Color::Yellow, Color::Blue],
+        },
+        "dark" => palette(0x181818, 0xd4d4d4, [0xf14c4c, 0x23d18b, 0xf5f543, 0x3b8eea]),
+        "light" => palette(0xffffff, 0x333333, [0xcd3131, 0x00bc00, 0x949800, 0x0451a5]),
+        "catppuccin-mocha" => palette(0x1e1e2e, 0xcdd6f4, [0xf38ba8, 0xa6e3a1, 0xf9e2af, 0x89b4fa]),
+        "solarized-dark" => palette(0x002b36, 0x839496, [0xdc322f, 0x859900, 0xb58900, 0x268bd2]),
+        "solarized-light" => palette(0xfdf6e3, 0x657b83, [0xdc322f, 0x859900, 0xb58900, 0x268bd2]),
+        "gruvbox-dark-hard" => {
+            palette(0x1d2021, 0xebdbb2, [0xfb4934, 0xb8bb26, 0xfabd2f, 0x83a598])
+        }
+        "nord" => palette(0x2e3440, 0xd8dee9, [0xbf616a, 0xa3be8c, 0xebcb8b, 0x81a1c1]),
+        _ => return None,
+    })
+}
+
+fn fg(color: Color) -> Style {
+    Style::default().fg(color)
+}
+
+/// A bold highlight: `fg` text on a `bg` background.
+fn on(fg: Color, bg: Color) -> Style {
+    Style::default().fg(fg).bg(bg).add_modifier(Modifier::BOLD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_name_resolves() {
+        for name in Theme::NAMES {
+            assert_eq!(Theme::by_name(name).unwrap().name, *name);
+        }
+        assert!(Theme::by_name("neon").is_err());
+        assert_eq!(Theme::default(), Theme::dark());
+    }
+
+    #[test]
+    fn test_amp_palettes() {
+        let def = crate::settings::get_setting_def("amp.terminal.theme").unwrap();
+        for name in def.enum_options.unwrap() {
+            assert_eq!(amp_palette(name).is_some(), *name != "Custom", "{name}");
+        }
+        let nord = amp_palette("nord").unwrap();
+        assert_eq!(nord.background, Color::Rgb(0x2e, 0x34, 0x40));
+    }
+}
@@ -0,0 +1,89 @@
+//! Catalog of Amp's built-in tool names, used to sanity-check permission rules
+//! and to offer in the `amp.tools.disable` checklist.
+
+/// Tools that ship with Amp itself.
+pub const BUILTIN_TOOLS: &[&str] = &[
+    "Bash",
+    "create_file",
+    "edit_file",
+    "finder",
+    "format_file",
+    "get_diagnostics",
+    "glob",
+    "Grep",
+    "librarian",
+    "look_at",
+    "mermaid",
+    "oracle",
+    "Read",
+    "read_mcp_resource",
+    "read_web_page",
+    "skill",
+    "Task",
+    "todo_read",
+    "todo_write",
+    "undo_edit",
+    "web_search",
+];
+
+/// Prefix Amp puts in front of tools exposed by MCP servers (`mcp__<server>__<tool>`).
+const MCP_TOOL_PREFIX: &str = "mcp__";
+
+/// Returns whether a permission rule's tool name can match anything: a built-in
+/// tool, a tool from one of the configured MCP servers, or a glob pattern.
+pub fn is_known_tool(name: &str, mcp_servers: &[String]) -> bool {
+    if name.contains('*') || name.contains('?') {
+        return true;
+    }
+    if BUILTIN_TOOLS.contains(&name) {
+        return true;
+    }
+    name.strip_prefix(MCP_TOOL_PREFIX)
+        .and_then(|rest| rest.split_once("__"))
+        .is_some_and(|(server, _)| mcp_servers.iter().any(|s| s == server))
+}
+
+/// The server an `mcp__<server>__<tool>` name belongs to, unless the server
+/// part is a pattern.
+pub fn mcp_server_of(tool: &str) -> Option<&str> {
+    let (server, _) = tool.strip_prefix(MCP_TOOL_PREFIX)?.split_once("__")?;
+    (!server.is_empty() && !server.contains(['*', '?', '['])).then_some(server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_tools_are_known() {
+        assert!(is_known_tool("Bash", &[]));
+        assert!(is_known_tool("edit_file", &[]));
+    }
+
+    #[test]
+    fn test_globs_are_known() {
+        assert!(is_known_tool("*", &[]));
+        assert!(is_known_tool("mcp__*", &[]));
+    }
+
+    #[test]
+    fn test_mcp_tools_require_configured_server() {
+        let servers = vec!["github".to_string()];
+        assert!(is_known_tool("mcp__github__create_issue", &servers));
+        assert!(!is_known_tool("mcp__gitlab__create_issue", &servers));
+        assert!(!is_known_tool("mcp__github", &servers));
+    }
+
+    #[test]
+    fn test_mcp_server_of() {
+        assert_eq!(mcp_server_of("mcp__github__create_issue"), Some("github"));
+        assert_eq!(mcp_server_of("mcp__*__read"), None);
+        assert_eq!(mcp_server_of("Bash"), None);
+    }
+
+    #[test]
+    fn test_unknown_tool() {
+        assert!(!is_known_tool("bash", &[]));
+        assert!(!is_known_tool("Bassh", &[]));
+    }
+}
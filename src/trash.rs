@@ -0,0 +1,139 @@
+//! Things removed in the TUI this session — keys, MCP servers, and array
+//! items such as permission rules — kept so they can be put back until
+//! volt exits. Nothing here is written to disk.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+use crate::config::Config;
+
+/// What was removed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Removed {
+    /// A whole key and the value it had.
+    Key { key: String, value: Value },
+    /// A server from `amp.mcpServers`.
+    Server { name: String, config: Value },
+    /// An item of an array setting, and where it was.
+    Item {
+        key: String,
+        index: usize,
+        value: Value,
+    },
+}
+
+/// A removal, and the settings file it was made in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trashed {
+    pub path: PathBuf,
+    pub removed: Removed,
+}
+
+impl Trashed {
+    /// One line describing what was removed, for the trash overlay.
+    pub fn describe(&self) -> String {
+        match &self.removed {
+            Removed::Key { key, value } => format!("{key} = {value}"),
+            Removed::Server { name, config } => format!("MCP server {name}: {config}"),
+            Removed::Item { key, index, value } => format!("{key}[{index}]: {value}"),
+        }
+    }
+
+    /// Puts the removed thing back into `config`, returning a status
+    /// message. An item goes back where it was, or at the end of a shorter
+    /// array; a server whose name was taken again isn't overwritten.
+    pub fn restore(&self, config: &mut Config) -> Result<String> {
+        if config.path() != self.path {
+            bail!(
+                "it was removed from {}; open that file to restore it",
+                self.path.display()
+            );
+        }
+        Ok(match &self.removed {
+            Removed::Key { key, value } => {
+                config.set(key, value.clone());
+                format!("Restored {key}")
+            }
+            Removed::Server {
+                name,
+                config: server,
+            } => {
+                let mut servers = config
+                    .get("amp.mcpServers")
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default();
+                if servers.contains_key(name) {
+                    bail!("a server named '{name}' exists again");
+                }
+                servers.insert(name.clone(), server.clone());
+                config.set("amp.mcpServers", Value::Object(servers));
+                format!("Restored server '{name}'")
+            }
+            Removed::Item { key, index, value } => {
+                let mut items = config.get(key).as_array().cloned().unwrap_or_default();
+                let index = (*index).min(items.len());
+                items.insert(index, value.clone());
+                config.set(key, Value::Array(items));
+                format!("Restored item {index} of {key}")
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"amp.tools.disable": ["Bash"], "amp.mcpServers": {"a": {"command": "a"}}}"#,
+        )
+        .unwrap();
+        let mut config = Config::load(&path).unwrap();
+        let trashed = |removed| Trashed {
+            path: path.clone(),
+            removed,
+        };
+
+        let item = trashed(Removed::Item {
+            key: "amp.tools.disable".into(),
+            index: 0,
+            value: json!("Read"),
+        });
+        assert_eq!(item.describe(), r#"amp.tools.disable[0]: "Read""#);
+        assert_eq!(
+            item.restore(&mut config).unwrap(),
+            "Restored item 0 of amp.tools.disable"
+        );
+        assert_eq!(config.get("amp.tools.disable"), json!(["Read", "Bash"]));
+
+        let server = trashed(Removed::Server {
+            name: "a".into(),
+            config: json!({"command": "old"}),
+        });
+        assert!(server.restore(&mut config).is_err());
+        config.set("amp.mcpServers", json!({}));
+        server.restore(&mut config).unwrap();
+        assert_eq!(
+            config.get("amp.mcpServers"),
+            json!({"a": {"command": "old"}})
+        );
+
+        let key = Trashed {
+            path: dir.path().join("other.json"),
+            removed: Removed::Key {
+                key: "amp.showCosts".into(),
+                value: json!(false),
+            },
+        };
+        assert!(key.restore(&mut config).is_err());
+    }
+}
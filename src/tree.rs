@@ -0,0 +1,183 @@
+//! An object or array setting opened in place as an indented tree, with
+//! branches expanded and collapsed one at a time, like `jless`.
+
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+/// One step down from a container to a child.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// The way from the setting's value down to a node.
+pub type Path = Vec<Step>;
+
+/// A visible line of the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeRow {
+    pub path: Path,
+    /// The key or `[index]` the node sits under.
+    pub label: String,
+    pub value: Value,
+    pub expanded: bool,
+}
+
+impl TreeRow {
+    /// How far the row is indented, starting at 1 for the setting's children.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Whether the node is an object or array with something in it.
+    pub fn is_branch(&self) -> bool {
+        children(&self.value).next().is_some()
+    }
+}
+
+/// The tree open under a setting, and where its cursor is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeView {
+    pub key: String,
+    expanded: BTreeSet<Path>,
+    pub selected: usize,
+}
+
+impl TreeView {
+    /// Opens `key`'s value with only its top level shown.
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            expanded: BTreeSet::new(),
+            selected: 0,
+        }
+    }
+
+    /// The lines shown for `value`, depth first, below expanded nodes only.
+    pub fn rows(&self, value: &Value) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        self.push_children(value, &mut Vec::new(), &mut rows);
+        rows
+    }
+
+    fn push_children(&self, value: &Value, path: &mut Path, rows: &mut Vec<TreeRow>) {
+        for (step, child) in children(value) {
+            let label = match &step {
+                Step::Key(key) => key.clone(),
+                Step::Index(i) => format!("[{i}]"),
+            };
+            path.push(step);
+            let expanded = self.expanded.contains(path);
+            rows.push(TreeRow {
+                path: path.clone(),
+                label,
+                value: child.clone(),
+                expanded,
+            });
+            if expanded {
+                self.push_children(child, path, rows);
+            }
+            path.pop();
+        }
+    }
+
+    pub fn expand(&mut self, path: &Path) {
+        self.expanded.insert(path.clone());
+    }
+
+    /// Folds a node, and everything open below it.
+    pub fn collapse(&mut self, path: &Path) {
+        self.expanded.retain(|p| !p.starts_with(path));
+    }
+}
+
+/// A container's children in order, with the step to each.
+fn children(value: &Value) -> Box<dyn Iterator<Item = (Step, &Value)> + '_> {
+    match value {
+        Value::Object(map) => Box::new(map.iter().map(|(k, v)| (Step::Key(k.clone()), v))),
+        Value::Array(items) => Box::new(items.iter().enumerate().map(|(i, v)| (Step::Index(i), v))),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+/// Replaces the node at `path`. Returns false, changing nothing, if the
+/// path doesn't lead anywhere in `root`.
+pub fn set(root: &mut Value, path: &[Step], new: Value) -> bool {
+    let mut node = root;
+    for step in path {
+        let next = match (step, node) {
+            (Step::Key(key), Value::Object(map)) => map.get_mut(key),
+            (Step::Index(i), Value::Array(items)) => items.get_mut(*i),
+            _ => None,
+        };
+        let Some(next) = next else {
+            return false;
+        };
+        node = next;
+    }
+    *node = new;
+    true
+}
+
+/// Writes a path the way `volt validate` names one: `.name` for keys and `[i]`
+/// for indexes.
+pub fn describe(key: &str, path: &[Step]) -> String {
+    path.iter().fold(key.to_string(), |mut out, step| {
+        match step {
+            Step::Key(k) => {
+                out.push('.');
+                out.push_str(k);
+            }
+            Step::Index(i) => out.push_str(&format!("[{i}]")),
+        }
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn labels(tree: &TreeView, value: &Value) -> Vec<String> {
+        tree.rows(value)
+            .iter()
+            .map(|r| format!("{}{}", "  ".repeat(r.depth() - 1), r.label))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_and_collapse() {
+        let value = json!({"a": {"b": [1, {"c": true}]}, "d": "x"});
+        let mut tree = TreeView::new("amp.x");
+        assert_eq!(labels(&tree, &value), ["a", "d"]);
+
+        let a = vec![Step::Key("a".into())];
+        let b = vec![Step::Key("a".into()), Step::Key("b".into())];
+        tree.expand(&a);
+        tree.expand(&b);
+        assert_eq!(
+            labels(&tree, &value),
+            ["a", "  b", "    [0]", "    [1]", "d"]
+        );
+        assert!(tree.rows(&value)[3].is_branch());
+        assert!(!tree.rows(&value)[2].is_branch());
+
+        // Folding a node forgets what was open inside it.
+        tree.collapse(&a);
+        tree.expand(&a);
+        assert_eq!(labels(&tree, &value), ["a", "  b", "d"]);
+    }
+
+    #[test]
+    fn test_set_and_describe() {
+        let mut value = json!({"a": [1, {"c": true}]});
+        let path = vec![Step::Key("a".into()), Step::Index(1), Step::Key("c".into())];
+        assert!(set(&mut value, &path, json!(false)));
+        assert_eq!(value, json!({"a": [1, {"c": false}]}));
+        assert!(!set(&mut value, &[Step::Index(0)], json!(1)));
+        assert_eq!(describe("amp.x", &path), "amp.x.a[1].c");
+    }
+}
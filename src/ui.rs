@@ -1,58 +1,337 @@
 //! UI rendering for the Volt TUI.
 
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Modifier, Style};
+use std::time::{Duration, SystemTime};
+
+use ratatui::layout::{Constraint, Direction, Layout, Position, Rect};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Row, Table};
+use ratatui::widgets::{
+    Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Scrollbar,
+    ScrollbarOrientation, ScrollbarState, Table, TableState, Wrap,
+};
 use ratatui::Frame;
 use serde_json::Value;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::app::{
-    App, CustomKeyType, Focus, InputMode, McpFocus, McpPermissionLevel, PermissionLevel,
-    SettingEntry,
+    expanded_headers_through, section_tabs, App, CopyFormat, CustomKeyType, Focus, InputMode,
+    KeyState, ListArea, McpFocus, McpPermissionLevel, MergeSide, PaletteCommand, PaneState,
+    PermissionLevel, ResetScope, Scope, ScreenLayout, SettingEntry, SettingsFilter, SplitSide,
 };
-use crate::settings::{Section, SettingType};
+use crate::backup;
+use crate::diff::DiffKind;
+use crate::keymap::{self, Action, Keymap};
+use crate::matcher;
+use crate::mcp_client::Health;
+use crate::mcp_import::Source;
+use crate::messages::Severity;
+use crate::permissions::{RuleOutcome, Shadowing};
+use crate::rule_form::Field;
+use crate::server_form::{self, ServerForm};
+use crate::settings::{self, ObjectSchema, Section, SettingType};
+use crate::textarea::TextInput;
+use crate::theme::{self, Theme};
+use crate::tree::TreeRow;
 
-/// Sidebar width in columns.
-const SIDEBAR_WIDTH: u16 = 18;
+/// Below this width the sidebar becomes a row of tabs above the settings.
+const STACKED_WIDTH: u16 = 60;
+/// Settings panes narrower than this show each value after its key rather
+/// than in a column of its own.
+const SINGLE_COLUMN_WIDTH: u16 = 50;
+/// The smallest screen volt draws on; below it only a warning is shown.
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 10;
 
-/// Renders the full application UI.
-pub fn render(frame: &mut Frame, app: &App) {
-    let status_rows = if app.status_message.is_some() { 2 } else { 1 };
+/// The top-level regions of the screen.
+struct Areas {
+    sidebar: Rect,
+    sidebar_stacked: bool,
+    settings: Rect,
+    preview: Option<Rect>,
+    help: Option<Rect>,
+    note: Option<Rect>,
+    bottom_bar: Rect,
+}
+
+/// Splits the screen into sidebar, settings area, optional preview, help,
+/// and note panes, and the bottom bar.
+fn areas(area: Rect, app: &App, has_preview: bool, has_help: bool, has_note: bool) -> Areas {
+    let status_rows = if status_line(app).is_some() { 2 } else { 1 };
     let rows = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(1), Constraint::Length(status_rows)])
-        .split(frame.area());
+        .split(area);
+
+    let stacked = area.width < STACKED_WIDTH;
+    let columns = if stacked {
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(rows[0])
+    } else {
+        Layout::horizontal([
+            Constraint::Length(app.sidebar.shown_width()),
+            Constraint::Min(1),
+        ])
+        .split(rows[0])
+    };
+
+    let mut constraints = vec![Constraint::Min(1)];
+    if has_help {
+        constraints.push(Constraint::Length(HELP_PANE_HEIGHT));
+    }
+    if has_note {
+        constraints.push(Constraint::Length(3));
+    }
+    let panes = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(columns[1]);
+
+    let (settings, preview) = if has_preview {
+        let halves = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(panes[0]);
+        (halves[0], Some(halves[1]))
+    } else {
+        (panes[0], None)
+    };
+
+    Areas {
+        sidebar: columns[0],
+        sidebar_stacked: stacked,
+        settings,
+        preview,
+        help: has_help.then(|| panes[1]),
+        note: has_note.then(|| panes[panes.len() - 1]),
+        bottom_bar: rows[1],
+    }
+}
 
-    let columns = Layout::default()
+/// Returns the (left, right) halves of the settings area in split view.
+fn split_halves(area: Rect) -> (Rect, Rect) {
+    let halves = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(1)])
-        .split(rows[0]);
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    (halves[0], halves[1])
+}
+
+/// Returns the (configs, permissions) halves of the MCPs panel.
+fn mcp_halves(area: Rect) -> (Rect, Rect) {
+    let halves = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    (halves[0], halves[1])
+}
+
+/// Returns whether an array of items is rendered as a table with a header row.
+fn has_header(items: &Value) -> bool {
+    items
+        .as_array()
+        .is_some_and(|items| !collect_object_columns(items).is_empty())
+}
 
-    render_sidebar(frame, app, columns[0]);
-    render_settings_panel(frame, app, columns[1]);
-    render_bottom_bar(frame, app, rows[1]);
+/// Returns the area of a bordered list panel, skipping the header row if any.
+fn list_area(area: Rect, header: bool) -> ListArea {
+    let header = u16::from(header);
+    ListArea {
+        area,
+        first_row: area.y + 1 + header,
+        visible_rows: area.height.saturating_sub(2 + header),
+    }
+}
+
+/// Computes where the panels are drawn, for mouse hit-testing and scrolling.
+pub fn screen_layout(area: Rect, app: &App) -> ScreenLayout {
+    let key = app.selected_key();
+    let has_note = key.as_deref().is_some_and(|k| app.notes.get(k).is_some());
+    let has_preview = key.is_some() && app.show_preview;
+    let areas = areas(
+        area,
+        app,
+        has_preview,
+        key.is_some() && app.show_help,
+        has_note,
+    );
+
+    let (pane, other_pane) = match app.split {
+        None => (areas.settings, None),
+        Some(_) => {
+            let (left, right) = split_halves(areas.settings);
+            match app.split_side {
+                SplitSide::Left => (left, Some(right)),
+                SplitSide::Right => (right, Some(left)),
+            }
+        }
+    };
+
+    let section = app.current_section();
+    let (list, mcp_permissions) = if section.is_split_panel() {
+        let (configs, permissions) = mcp_halves(pane);
+        let header = has_header(&app.config.get("amp.mcpPermissions"));
+        (
+            list_area(configs, false),
+            Some(list_area(permissions, header)),
+        )
+    } else if section.is_single_key() {
+        let header = app
+            .selected_key()
+            .is_some_and(|key| has_header(&app.config.get(&key)));
+        (list_area(pane, header), None)
+    } else {
+        (list_area(pane, false), None)
+    };
+
+    ScreenLayout {
+        sidebar: areas.sidebar,
+        sidebar_stacked: areas.sidebar_stacked,
+        pane,
+        other_pane,
+        list,
+        mcp_permissions,
+        value_overflow: selected_value_overflow(app, pane),
+    }
+}
+
+/// Renders the full application UI.
+pub fn render(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        render_too_small(frame, app);
+        return;
+    }
+    let key = app.selected_key();
+    let note = key
+        .as_deref()
+        .and_then(|key| app.notes.get(key).map(str::to_string));
+    let preview = key.clone().filter(|_| app.show_preview);
+    let help = key.filter(|_| app.show_help);
+    let areas = areas(
+        frame.area(),
+        app,
+        preview.is_some(),
+        help.is_some(),
+        note.is_some(),
+    );
+
+    if areas.sidebar_stacked {
+        render_section_tabs(frame, app, areas.sidebar);
+    } else {
+        render_sidebar(frame, app, areas.sidebar);
+    }
+    render_settings_area(frame, app, areas.settings);
+    if let (Some(key), Some(area)) = (&preview, areas.preview) {
+        render_preview_pane(frame, app, key, area);
+    }
+    if let (Some(key), Some(area)) = (&help, areas.help) {
+        render_help_pane(frame, app, key, area);
+    }
+    if let (Some(note), Some(area)) = (&note, areas.note) {
+        render_note_pane(frame, &app.theme, note, area);
+    }
+    render_bottom_bar(frame, app, areas.bottom_bar);
 
     if app.is_editing() {
         render_edit_overlay(frame, app);
     }
 }
 
-/// Renders the sidebar with section tabs.
-fn render_sidebar(frame: &mut Frame, app: &App, area: Rect) {
-    let title = if app.config.is_dirty() {
+/// Lays out and draws the app off-screen, with no terminal involved, so
+/// tests can check what a screen of the given size shows.
+#[cfg(test)]
+pub fn render_to_buffer(app: &mut App, width: u16, height: u16) -> ratatui::buffer::Buffer {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let mut terminal =
+        Terminal::new(TestBackend::new(width, height)).expect("test backend can't fail");
+    app.layout = screen_layout(Rect::new(0, 0, width, height), app);
+    terminal
+        .draw(|frame| render(frame, app))
+        .expect("test backend can't fail");
+    terminal.backend().buffer().clone()
+}
+
+/// Asks for a bigger terminal instead of drawing a squashed UI.
+fn render_too_small(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let text = format!(
+        "Terminal too small ({}×{}). volt needs at least {MIN_WIDTH}×{MIN_HEIGHT}; q quits.",
+        area.width, area.height
+    );
+    let p = Paragraph::new(text)
+        .style(app.theme.error)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(p, area);
+}
+
+/// Renders the sections as a row of tabs, for terminals too narrow for the
+/// sidebar.
+fn render_section_tabs(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let block = Block::default()
+        .title(volt_title(app))
+        .borders(Borders::ALL)
+        .border_style(if app.focus == Focus::Sidebar {
+            theme.accent
+        } else {
+            theme.border
+        });
+    frame.render_widget(block, area);
+
+    for (i, (section, tab)) in Section::ALL.iter().zip(section_tabs(area)).enumerate() {
+        let style = if i == app.selected_section {
+            if app.focus == Focus::Sidebar {
+                theme.selected
+            } else {
+                theme.selected_unfocused
+            }
+        } else {
+            theme.text
+        };
+        let is_other_pane = app.split.is_some_and(|p| p.section == i);
+        let marker = if is_other_pane { "▸" } else { " " };
+        let label = clip(
+            &format!("{marker}{}", section.label()),
+            usize::from(tab.width),
+            0,
+        );
+        frame.render_widget(Paragraph::new(label).style(style), tab);
+    }
+}
+
+/// The title over the sections, marking a read-only file, unsaved changes,
+/// or changes waiting to be autosaved.
+fn volt_title(app: &App) -> &'static str {
+    if app.macros.is_recording() {
+        " Volt [recording] "
+    } else if app.config.is_read_only() {
+        " Volt [read-only] "
+    } else if app.autosave_pending() {
+        " Volt [saving…] "
+    } else if app.config.is_dirty() {
         " Volt [modified] "
     } else {
         " Volt "
+    }
+}
+
+/// Renders the sidebar with section tabs.
+fn render_sidebar(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let collapsed = app.sidebar.collapsed;
+    let title = match (collapsed, app.config.is_read_only(), app.config.is_dirty()) {
+        (false, _, _) => volt_title(app),
+        (true, false, true) => "V*",
+        (true, _, _) => "V",
     };
     let block = Block::default()
         .title(title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(if app.focus == Focus::Sidebar {
-            Color::Cyan
+        .border_style(if app.focus == Focus::Sidebar {
+            theme.accent
         } else {
-            Color::DarkGray
-        }));
+            theme.border
+        });
 
     let items: Vec<ListItem> = Section::ALL
         .iter()
@@ -60,20 +339,22 @@ fn render_sidebar(frame: &mut Frame, app: &App, area: Rect) {
         .map(|(i, section)| {
             let style = if i == app.selected_section {
                 if app.focus == Focus::Sidebar {
-                    Style::default()
-                        .fg(Color::Black)
-                        .bg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD)
+                    theme.selected
                 } else {
-                    Style::default()
-                        .fg(Color::White)
-                        .bg(Color::DarkGray)
-                        .add_modifier(Modifier::BOLD)
+                    theme.selected_unfocused
                 }
             } else {
-                Style::default().fg(Color::White)
+                theme.text
+            };
+            let is_other_pane = app.split.is_some_and(|p| p.section == i);
+            let marker = if is_other_pane { "▸" } else { " " };
+            let label = section.label();
+            let label = if collapsed {
+                label.chars().next().map(String::from).unwrap_or_default()
+            } else {
+                format!("{label} ")
             };
-            ListItem::new(format!(" {} ", section.label())).style(style)
+            ListItem::new(format!("{marker}{label}")).style(style)
         })
         .collect();
 
@@ -81,108 +362,663 @@ fn render_sidebar(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(list, area);
 }
 
+/// What one settings pane shows: a section plus its selection state.
+struct PaneView {
+    section: Section,
+    selected_setting: usize,
+    mcp_focus: McpFocus,
+    selected_mcp_permission: usize,
+    setting_offset: usize,
+    mcp_permission_offset: usize,
+    /// Whether this pane has keyboard focus (and shows its selection).
+    focused: bool,
+}
+
+impl PaneView {
+    fn new(state: PaneState, focused: bool) -> Self {
+        Self {
+            section: Section::ALL[state.section],
+            selected_setting: state.selected_setting,
+            mcp_focus: state.mcp_focus,
+            selected_mcp_permission: state.selected_mcp_permission,
+            setting_offset: state.setting_offset,
+            mcp_permission_offset: state.mcp_permission_offset,
+            focused,
+        }
+    }
+}
+
+/// Renders the settings area: one pane, or two side by side in split view.
+fn render_settings_area(frame: &mut Frame, app: &App, area: Rect) {
+    let active = PaneView::new(app.pane_state(), app.focus == Focus::Settings);
+    let Some(other) = app.split else {
+        render_settings_panel(frame, app, &active, area);
+        return;
+    };
+    let other = PaneView::new(other, false);
+
+    let (left_area, right_area) = split_halves(area);
+    let (left, right) = match app.split_side {
+        SplitSide::Left => (&active, &other),
+        SplitSide::Right => (&other, &active),
+    };
+    render_settings_panel(frame, app, left, left_area);
+    render_settings_panel(frame, app, right, right_area);
+}
+
+/// Height of the help pane, including its border.
+const HELP_PANE_HEIGHT: u16 = 6;
+
+/// Renders a description of the selected setting: what it does, its type,
+/// default, and allowed values.
+fn render_help_pane(frame: &mut Frame, app: &App, key: &str, area: Rect) {
+    let theme = &app.theme;
+    let block = Block::default()
+        .title(format!(" {key} "))
+        .borders(Borders::ALL)
+        .border_style(theme.border);
+
+    let label = theme.muted;
+    let mut lines = match settings::get_setting_def(key) {
+        Some(def) => {
+            let mut details = vec![
+                Span::styled(" Type: ", label),
+                Span::raw(def.setting_type.label()),
+                Span::styled("  Default: ", label),
+                Span::raw(format_json_compact(&def.default)),
+            ];
+            let options = app.enum_options(&def);
+            if !options.is_empty() {
+                details.push(Span::styled("  Allowed: ", label));
+                details.push(Span::raw(options.join(", ")));
+            }
+            if let Some(range) = def.number.and_then(|spec| spec.describe()) {
+                details.push(Span::styled("  Range: ", label));
+                details.push(Span::raw(range));
+            }
+            let description = if def.description.is_empty() {
+                "No description available."
+            } else {
+                def.description
+            };
+            vec![Line::from(format!(" {description}")), Line::from(details)]
+        }
+        None => vec![Line::from(
+            " Not a known Amp setting; volt preserves it as-is.",
+        )],
+    };
+    if let Some(source) = app.provenance(key) {
+        lines.push(Line::from(vec![
+            Span::styled(" From: ", label),
+            Span::raw(source),
+        ]));
+    }
+
+    let p = Paragraph::new(lines)
+        .style(theme.text)
+        .wrap(Wrap { trim: false })
+        .block(block);
+    frame.render_widget(p, area);
+}
+
+/// Renders the selected setting's value as pretty-printed, highlighted
+/// JSON. A value that isn't set shows its default.
+fn render_preview_pane(frame: &mut Frame, app: &App, key: &str, area: Rect) {
+    let theme = &app.theme;
+    let title = if app.config.get_raw(key).is_some() {
+        format!(" {key} ")
+    } else {
+        format!(" {key} (default) ")
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(theme.border);
+    let pretty = serde_json::to_string_pretty(&app.config.get(key)).unwrap_or_default();
+    let lines: Vec<Line> = pretty
+        .lines()
+        .map(|line| highlight_json_line(line, theme))
+        .collect();
+    frame.render_widget(Paragraph::new(lines).style(theme.text).block(block), area);
+}
+
+/// Colors one line of pretty-printed JSON: keys, strings, numbers, and
+/// literals each get their theme style, punctuation stays plain.
+fn highlight_json_line<'a>(line: &'a str, theme: &Theme) -> Line<'a> {
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let style = match c {
+            '"' => {
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        '"' if !escaped => break,
+                        '\\' => escaped = !escaped,
+                        _ => escaped = false,
+                    }
+                }
+                let rest = &line[chars.peek().map_or(line.len(), |&(i, _)| i)..];
+                if rest.trim_start().starts_with(':') {
+                    theme.json_key
+                } else {
+                    theme.json_string
+                }
+            }
+            '-' | '0'..='9' => {
+                while chars
+                    .next_if(|&(_, c)| c.is_ascii_digit() || "+-.eE".contains(c))
+                    .is_some()
+                {}
+                theme.json_number
+            }
+            'a'..='z' => {
+                while chars.next_if(|&(_, c)| c.is_ascii_lowercase()).is_some() {}
+                theme.json_literal
+            }
+            _ => continue,
+        };
+        let end = chars.peek().map_or(line.len(), |&(i, _)| i);
+        if plain_start < start {
+            spans.push(Span::raw(&line[plain_start..start]));
+        }
+        spans.push(Span::styled(&line[start..end], style));
+        plain_start = end;
+    }
+    if plain_start < line.len() {
+        spans.push(Span::raw(&line[plain_start..]));
+    }
+    Line::from(spans)
+}
+
+/// Renders the note attached to the selected setting.
+fn render_note_pane(frame: &mut Frame, theme: &Theme, note: &str, area: Rect) {
+    let block = Block::default()
+        .title(" Note ")
+        .borders(Borders::ALL)
+        .border_style(theme.border);
+    let p = Paragraph::new(format!(" {note}"))
+        .style(theme.text)
+        .block(block);
+    frame.render_widget(p, area);
+}
+
 /// Renders the settings panel for the current section.
-fn render_settings_panel(frame: &mut Frame, app: &App, area: Rect) {
-    let section = app.current_section();
+fn render_settings_panel(frame: &mut Frame, app: &App, view: &PaneView, area: Rect) {
+    let theme = &app.theme;
+    let section = view.section;
+    let title = if app.has_project() {
+        format!(" {} [{}] ", section.label(), app.scope.label())
+    } else if app.is_settings_file() {
+        format!(" {} ", section.label())
+    } else {
+        let file = app
+            .config
+            .path()
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!(" {} — {} ", section.label(), file)
+    };
     let block = Block::default()
-        .title(format!(" {} ", section.label()))
+        .title(title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(if app.focus == Focus::Settings {
-            Color::Cyan
+        .border_style(if view.focused {
+            theme.accent
         } else {
-            Color::DarkGray
-        }));
+            theme.border
+        });
 
     if section.is_single_key() {
-        render_single_key_panel(frame, app, area, block);
+        render_single_key_panel(frame, app, view, area, block);
         return;
     }
 
     if section.is_split_panel() {
-        render_mcp_split_panel(frame, app, area);
+        render_mcp_split_panel(frame, app, view, area);
         return;
     }
 
-    let entries = app.current_settings();
+    let entries = app.shown_settings(section);
+    let filter = app.filter(section);
+    let block = match filter.label() {
+        Some(label) => {
+            block.title_top(Line::styled(format!(" {label} "), theme.muted).right_aligned())
+        }
+        None => block,
+    };
 
     if entries.is_empty() {
-        let help = if section == Section::Advanced {
-            "No custom keys. Press 'a' to add one."
-        } else {
-            "No settings in this section."
+        let cycle = app.keymap.label(Action::CycleFilter);
+        let help = match filter {
+            SettingsFilter::Modified => {
+                format!(
+                    "No settings in this section are set. Press '{cycle}' to change the filter."
+                )
+            }
+            SettingsFilter::Defaults => {
+                format!(
+                    "Every setting in this section is set. Press '{cycle}' to change the filter."
+                )
+            }
+            SettingsFilter::All if section == Section::Advanced => {
+                "No custom keys. Press 'a' to add one.".to_string()
+            }
+            SettingsFilter::All if section == Section::Favorites => format!(
+                "No favorites yet. Press '{}' on any setting to pin it here.",
+                app.keymap.label(Action::ToggleFavorite)
+            ),
+            SettingsFilter::All => "No settings in this section.".to_string(),
         };
-        let p = Paragraph::new(help)
-            .style(Style::default().fg(Color::DarkGray))
-            .block(block);
+        let p = Paragraph::new(help).style(theme.muted).block(block);
         frame.render_widget(p, area);
         return;
     }
 
-    let selected_style = Style::default()
-        .fg(Color::Black)
-        .bg(Color::Cyan)
-        .add_modifier(Modifier::BOLD);
+    let selected_style = theme.selected;
+    let single_column = area.width < SINGLE_COLUMN_WIDTH;
+    let widths = if single_column {
+        vec![area.width.saturating_sub(2)]
+    } else {
+        settings_column_widths(app, &entries, area)
+    };
 
-    let rows: Vec<Row> = entries
+    let headers = app.group_headers(section);
+    let mut rows: Vec<Row> = entries
         .iter()
         .enumerate()
         .map(|(i, entry)| {
-            let is_selected = app.focus == Focus::Settings && i == app.selected_setting;
+            let is_selected = view.focused && i == view.selected_setting && app.tree.is_none();
             let base = if is_selected {
                 selected_style
             } else {
                 Style::default()
             };
+            // A collapsed group's first setting shows as its header.
+            if let Some(header) = headers.iter().find(|h| h.collapsed && h.first == i) {
+                let text = format!("▸ {} ({})", header.name, header.count);
+                return group_row(app, text, is_selected, widths[0]);
+            }
+            let display = entry_display(app, entry);
             let value_style = if is_selected {
                 base
+            } else if display.flagged {
+                theme.error
             } else {
-                Style::default().fg(Color::Yellow)
+                theme.value
             };
-
-            let (key, value_display, modified) = match entry {
-                SettingEntry::Known(def) => {
-                    let value = app.config.get(def.key);
-                    let display = format_value(def.setting_type, &value);
-                    let modified = app.config.get_raw(def.key).is_some();
-                    (def.key.to_string(), display, modified)
-                }
-                SettingEntry::Unknown(key) => {
-                    let value = app.config.get(key);
-                    let display = format_json_compact(&value);
-                    (key.clone(), display, true)
-                }
-            };
-
-            let key_style = if modified {
+            let key_style = if display.state == KeyState::Unset {
+                base
+            } else {
                 base.add_modifier(Modifier::BOLD)
+            };
+            let marker = match display.state {
+                KeyState::Unset => Span::raw(" "),
+                KeyState::SetToDefault => Span::styled("○", base.patch(theme.muted)),
+                KeyState::Changed => Span::styled("●", base.patch(theme.accent)),
+            };
+            // Only the selected row scrolls sideways; the rest show their start.
+            let skip = if is_selected {
+                app.value_scroll_of(&display.key)
             } else {
-                base
+                0
             };
 
-            Row::new(vec![
-                Line::from(Span::styled(format!(" {key}"), key_style)),
-                Line::from(Span::styled(value_display, value_style)),
-            ])
-            .style(base)
+            let scope = app
+                .has_project()
+                .then(|| match app.key_scope(&display.key) {
+                    Some(Scope::Project) => Span::styled("P", base.patch(theme.project)),
+                    Some(Scope::Global) => Span::styled("G", base.patch(theme.global)),
+                    None => Span::raw(" "),
+                });
+            let (key_width, value_width) = if single_column {
+                single_column_widths(app, &display.key, widths[0])
+            } else {
+                (
+                    usize::from(widths[0]),
+                    usize::from(widths[widths.len() - 1]),
+                )
+            };
+            let key_text = if single_column {
+                short_key(&display.key)
+            } else {
+                &display.key
+            };
+            let key_text = clip(key_text, key_width.saturating_sub(1), 0);
+            let value = Span::styled(clip(&display.value, value_width, skip), value_style);
+            let key = Span::styled(key_text, key_style);
+            let cells = if single_column {
+                // Key, scope, and value share one column, two spaces apart.
+                let gap = " ".repeat(key_width - key.content.chars().count() + 1);
+                let mut spans = vec![marker, key, Span::raw(gap)];
+                if let Some(scope) = scope {
+                    spans.extend([scope, Span::raw(" ")]);
+                }
+                spans.push(value);
+                vec![Line::from(spans)]
+            } else {
+                let mut cells = vec![Line::from(vec![marker, key])];
+                cells.extend(scope.map(Line::from));
+                cells.push(Line::from(value));
+                cells
+            };
+            Row::new(cells).style(base)
         })
         .collect();
+    for header in headers.iter().rev().filter(|h| !h.collapsed) {
+        let text = format!("▾ {}", header.name);
+        rows.insert(header.first, group_row(app, text, false, widths[0]));
+    }
+
+    // An open tree sits under its setting's row and takes the selection.
+    let mut tree_selected = None;
+    let mut block = block;
+    if let Some(tree) = app.tree.as_ref().filter(|_| view.focused) {
+        let at = entries
+            .iter()
+            .position(|e| match e {
+                SettingEntry::Known(def) => def.key == tree.key,
+                SettingEntry::Unknown(key) => *key == tree.key,
+            })
+            .map(|at| at + expanded_headers_through(&headers, at));
+        if let Some(at) = at {
+            let tree_rows = app
+                .tree_rows()
+                .iter()
+                .enumerate()
+                .map(|(i, row)| tree_row(app, row, i == tree.selected, &widths, single_column))
+                .collect::<Vec<_>>();
+            tree_selected = Some(at + 1 + tree.selected);
+            rows.splice(at + 1..at + 1, tree_rows);
+            block = block.title_bottom(Line::styled(
+                " ←/→ fold · Enter edit · Esc close ",
+                theme.muted,
+            ));
+        }
+    }
 
-    let table = Table::new(rows, [Constraint::Fill(1), Constraint::Min(16)])
+    let count = rows.len();
+    let table = Table::new(rows, widths.into_iter().map(Constraint::Length))
         .block(block)
         .row_highlight_style(selected_style)
-        .column_spacing(2);
+        .column_spacing(COLUMN_SPACING);
+
+    let mut state = TableState::new()
+        .with_offset(view.setting_offset)
+        .with_selected(tree_selected);
+    frame.render_stateful_widget(table, area, &mut state);
+    render_scrollbar(
+        frame,
+        theme,
+        list_area(area, false),
+        count,
+        view.setting_offset,
+    );
+}
+
+/// Space between table columns.
+const COLUMN_SPACING: u16 = 2;
+
+/// A group's header row in the settings table, in the key column.
+fn group_row<'a>(app: &App, text: String, selected: bool, width: u16) -> Row<'a> {
+    let (base, style) = if selected {
+        (app.theme.selected, app.theme.selected)
+    } else {
+        (Style::default(), app.theme.muted)
+    };
+    let text = clip(&text, usize::from(width), 0);
+    Row::new(vec![Line::from(Span::styled(
+        text,
+        style.add_modifier(Modifier::BOLD),
+    ))])
+    .style(base)
+}
+
+/// A line of an open tree in the settings table: the node's key indented
+/// under its parent, with `▸`/`▾` on branches, and its value as JSON (an
+/// object as its key count) unless it's unfolded below.
+fn tree_row<'a>(
+    app: &App,
+    row: &TreeRow,
+    selected: bool,
+    widths: &[u16],
+    single_column: bool,
+) -> Row<'a> {
+    let theme = &app.theme;
+    let base = if selected {
+        theme.selected
+    } else {
+        Style::default()
+    };
+    let fold = match (row.is_branch(), row.expanded) {
+        (false, _) => "  ",
+        (true, false) => "▸ ",
+        (true, true) => "▾ ",
+    };
+    let label = format!(" {}{fold}{}", "  ".repeat(row.depth() - 1), row.label);
+    let value = match &row.value {
+        _ if row.expanded => String::new(),
+        Value::String(text) if settings::looks_secret(&row.label) => app.mask(text).into_owned(),
+        value @ Value::Object(_) => format_value(SettingType::Object, value),
+        value => value.to_string(),
+    };
+    let value_style = if selected { base } else { theme.value };
+
+    let (key_width, value_width) = if single_column {
+        let total = usize::from(widths[0]);
+        let key_width = label.chars().count().min(total / 2) + 1;
+        (key_width, total.saturating_sub(key_width + 1))
+    } else {
+        (
+            usize::from(widths[0]),
+            usize::from(widths[widths.len() - 1]),
+        )
+    };
+    let key = Span::styled(clip(&label, key_width.saturating_sub(1), 0), base);
+    let value = Span::styled(clip(&value, value_width, 0), value_style);
+    let cells = if single_column {
+        let gap = " ".repeat(key_width - key.content.chars().count() + 1);
+        vec![Line::from(vec![key, Span::raw(gap), value])]
+    } else {
+        let mut cells = vec![Line::from(key)];
+        if app.has_project() {
+            cells.push(Line::default());
+        }
+        cells.push(Line::from(value));
+        cells
+    };
+    Row::new(cells).style(base)
+}
+
+/// How a row of the settings table reads.
+struct EntryDisplay {
+    key: String,
+    value: String,
+    /// Whether the key is set in the file (shown bold and marked) and
+    /// whether that differs from the default.
+    state: KeyState,
+    /// Whether the value has the wrong type or the key is deprecated.
+    flagged: bool,
+}
+
+/// Describes a settings table row: the key, its value as text, and how to
+/// style them.
+fn entry_display(app: &App, entry: &SettingEntry) -> EntryDisplay {
+    let (key, value, flagged) = match entry {
+        SettingEntry::Known(def) if app.has_wrong_type(def.key) => {
+            // Showing the raw value beats rendering it as the wrong widget.
+            let raw = app.config.get(def.key);
+            let display = format!("⚠ {}", format_json_compact(&raw));
+            (def.key.to_string(), display, true)
+        }
+        SettingEntry::Known(def) => {
+            let env = app.config.env_override(def.key);
+            let value = env.map_or_else(|| app.effective_value(def.key), |env| env.value.clone());
+            let mut display = format_value(def.setting_type, &value);
+            if env.is_none()
+                && def.setting_type == SettingType::Boolean
+                && app.uses_default(def.key)
+            {
+                // Tell an unset key apart from one set to the same value.
+                let mark = if value.as_bool().unwrap_or(false) {
+                    "✓"
+                } else {
+                    "✗"
+                };
+                display = format!("(default: {mark})");
+            }
+            if let Some(unit) = def.number.and_then(|spec| spec.unit) {
+                display = format!("{display} {unit}");
+            }
+            if def.sensitive {
+                display = app.mask(value.as_str().unwrap_or_default()).into_owned();
+                if display.is_empty() {
+                    display = "(empty)".to_string();
+                }
+            }
+            if env.is_some() {
+                display = format!("{display} (env)");
+            }
+            (def.key.to_string(), display, false)
+        }
+        SettingEntry::Unknown(key) if settings::migration_for(key).is_some() => {
+            let raw = app.config.get(key);
+            let display = format!("⚠ deprecated: {}", format_json_compact(&raw));
+            (key.clone(), display, true)
+        }
+        SettingEntry::Unknown(key) => {
+            let value = app.config.get(key);
+            let display = match value.as_str() {
+                Some(text) if app.is_sensitive(key) => app.mask(text).into_owned(),
+                _ => format_json_compact(&value),
+            };
+            (key.clone(), display, false)
+        }
+    };
+    EntryDisplay {
+        key,
+        value,
+        state: app.key_state(entry),
+        flagged,
+    }
+}
+
+/// The widths of the settings table's columns (key, scope if shown, value)
+/// inside the bordered `area`. Keys get the room their longest one needs, up
+/// to half the width, and values the rest.
+fn settings_column_widths(app: &App, entries: &[SettingEntry], area: Rect) -> Vec<u16> {
+    let inner = area.width.saturating_sub(2);
+    let longest = entries
+        .iter()
+        .map(|e| match e {
+            SettingEntry::Known(def) => def.key.chars().count(),
+            SettingEntry::Unknown(key) => key.chars().count(),
+        })
+        .max()
+        .unwrap_or(0);
+    let key_width = u16::try_from(longest + 1)
+        .unwrap_or(u16::MAX)
+        .min(inner / 2);
+    let mut constraints = vec![Constraint::Length(key_width)];
+    // The scope column only appears when there are layers to tell apart.
+    if app.has_project() {
+        constraints.push(Constraint::Length(1));
+    }
+    constraints.push(Constraint::Fill(1));
+    Layout::horizontal(constraints)
+        .spacing(COLUMN_SPACING)
+        .split(Rect::new(0, 0, inner, 1))
+        .iter()
+        .map(|r| r.width)
+        .collect()
+}
+
+/// The widths of a single-column row's key and value: the key (without its
+/// `amp.` prefix) takes what it needs, up to half the row.
+fn single_column_widths(app: &App, key: &str, inner: u16) -> (usize, usize) {
+    let inner = usize::from(inner);
+    let key_width = (short_key(key).chars().count() + 1).min(inner / 2);
+    let scope_width = if app.has_project() { 2 } else { 0 };
+    let value_width = inner.saturating_sub(key_width + 2 + scope_width);
+    (key_width, value_width)
+}
+
+/// A key as shown where space is tight: `amp.` goes without saying.
+fn short_key(key: &str) -> &str {
+    key.strip_prefix("amp.").unwrap_or(key)
+}
+
+/// How many characters of the selected settings row's value don't fit its
+/// column, which bounds how far it can scroll sideways.
+fn selected_value_overflow(app: &App, pane: Rect) -> usize {
+    let section = app.current_section();
+    if section.is_single_key() || section.is_split_panel() {
+        return 0;
+    }
+    let entries = app.shown_settings(section);
+    let Some(entry) = entries.get(app.selected_setting) else {
+        return 0;
+    };
+    let display = entry_display(app, entry);
+    let width = if pane.width < SINGLE_COLUMN_WIDTH {
+        single_column_widths(app, &display.key, pane.width.saturating_sub(2)).1
+    } else {
+        let widths = settings_column_widths(app, &entries, pane);
+        usize::from(widths[widths.len() - 1])
+    };
+    let len = display.value.chars().count();
+    // Once scrolled, the leading `…` takes a column, so the last character
+    // comes into view one step later.
+    if len > width {
+        len - width + 1
+    } else {
+        0
+    }
+}
 
-    frame.render_widget(table, area);
+/// Fits text into `width` columns after skipping its first `skip`
+/// characters, marking each cut end with `…`.
+fn clip(text: &str, width: usize, skip: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= width && skip == 0 {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let skip = skip.min(chars.len().saturating_sub(1));
+    let mut out = String::new();
+    let mut room = width;
+    if skip > 0 {
+        out.push('…');
+        room -= 1;
+    }
+    let rest = &chars[skip..];
+    if rest.len() <= room {
+        out.extend(rest);
+    } else if room > 0 {
+        out.extend(&rest[..room - 1]);
+        out.push('…');
+    }
+    out
 }
 
 /// Renders a single-key section where the right panel shows array items directly.
-fn render_single_key_panel(frame: &mut Frame, app: &App, area: Rect, block: Block) {
-    let entries = app.current_settings();
+fn render_single_key_panel(
+    frame: &mut Frame,
+    app: &App,
+    view: &PaneView,
+    area: Rect,
+    block: Block,
+) {
+    let theme = &app.theme;
+    let entries = app.settings_for(view.section);
     let def = match entries.first() {
         Some(SettingEntry::Known(def)) => def,
         _ => {
             let p = Paragraph::new("No settings in this section.")
-                .style(Style::default().fg(Color::DarkGray))
+                .style(theme.muted)
                 .block(block);
             frame.render_widget(p, area);
             return;
@@ -192,18 +1028,23 @@ fn render_single_key_panel(frame: &mut Frame, app: &App, area: Rect, block: Bloc
     let value = app.config.get(def.key);
     let items = value.as_array().cloned().unwrap_or_default();
 
+    if app.has_wrong_type(def.key) {
+        let p = Paragraph::new(format!(" ⚠ {}", format_json_compact(&value)))
+            .style(theme.error)
+            .block(block);
+        frame.render_widget(p, area);
+        return;
+    }
+
     if items.is_empty() {
         let p = Paragraph::new(" Empty. Press 'a' to add an item, 'e' to open in $EDITOR.")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         frame.render_widget(p, area);
         return;
     }
 
-    let selected_style = Style::default()
-        .fg(Color::Black)
-        .bg(Color::Cyan)
-        .add_modifier(Modifier::BOLD);
+    let selected_style = theme.selected;
 
     // Collect all unique keys across objects to build columns.
     let columns = collect_object_columns(&items);
@@ -214,129 +1055,186 @@ fn render_single_key_panel(frame: &mut Frame, app: &App, area: Rect, block: Bloc
             .iter()
             .enumerate()
             .map(|(i, item)| {
-                let is_selected = app.focus == Focus::Settings && i == app.selected_setting;
+                let is_selected = view.focused && i == view.selected_setting;
                 let style = if is_selected {
                     selected_style
                 } else {
-                    Style::default().fg(Color::White)
+                    theme.text
                 };
                 ListItem::new(format!(" {}", format_json_compact(item))).style(style)
             })
             .collect();
         let list = List::new(list_items).block(block);
-        frame.render_widget(list, area);
+        let mut state = ListState::default().with_offset(view.setting_offset);
+        frame.render_stateful_widget(list, area, &mut state);
+        render_scrollbar(
+            frame,
+            theme,
+            list_area(area, false),
+            items.len(),
+            view.setting_offset,
+        );
         return;
     }
 
+    // Permission rules get a leading marker column flagging unknown tools,
+    // and rules an earlier one shadows.
+    let show_markers = def.key == "amp.permissions";
+    let shadowing = if show_markers {
+        app.rule_shadowing()
+    } else {
+        Vec::new()
+    };
+    let mut constraints: Vec<Constraint> = Vec::new();
+    if show_markers {
+        constraints.push(Constraint::Length(1));
+    }
+    constraints.extend(columns.iter().map(|_| Constraint::Fill(1)));
+    let widths: Vec<u16> = Layout::horizontal(constraints)
+        .spacing(COLUMN_SPACING)
+        .split(Rect::new(0, 0, area.width.saturating_sub(2), 1))
+        .iter()
+        .map(|r| r.width)
+        .collect();
+    let column_widths = &widths[usize::from(show_markers)..];
+
     // Build header row.
-    let header = Row::new(
-        columns
-            .iter()
-            .map(|col| {
-                Line::from(Span::styled(
-                    col.as_str(),
-                    Style::default().fg(Color::DarkGray),
-                ))
-            })
-            .collect::<Vec<_>>(),
-    );
+    let mut header_cells: Vec<Line> = Vec::new();
+    if show_markers {
+        header_cells.push(Line::from(""));
+    }
+    header_cells.extend(columns.iter().map(|col| {
+        if show_markers && app.rule_sort == Some(col.as_str()) {
+            Line::from(Span::styled(format!("{col} ▾"), theme.accent))
+        } else {
+            Line::from(Span::styled(col.as_str(), theme.muted))
+        }
+    }));
+    let header = Row::new(header_cells);
 
-    // Build data rows.
-    let rows: Vec<Row> = items
-        .iter()
-        .enumerate()
-        .map(|(i, item)| {
-            let is_selected = app.focus == Focus::Settings && i == app.selected_setting;
+    // Build data rows, in the order the rules table is sorted by.
+    let order: Vec<usize> = if show_markers {
+        app.rule_rows()
+    } else {
+        (0..items.len()).collect()
+    };
+    let rows: Vec<Row> = order
+        .into_iter()
+        .map(|i| {
+            let item = &items[i];
+            let is_selected = view.focused && i == view.selected_setting;
             let base = if is_selected {
                 selected_style
             } else {
                 Style::default()
             };
-            let value_style = if is_selected {
-                base
-            } else {
-                Style::default().fg(Color::Yellow)
-            };
-            let cells: Vec<Line> = columns
-                .iter()
-                .map(|col| {
-                    let text = item.get(col).map(format_cell_value).unwrap_or_default();
-                    Line::from(Span::styled(text, value_style))
-                })
-                .collect();
+            let value_style = if is_selected { base } else { theme.value };
+            let mut cells: Vec<Line> = Vec::new();
+            if show_markers {
+                let marker = if app.permission_rule_has_unknown_tool(item) {
+                    "⚠"
+                } else {
+                    match shadowing.get(i).copied().flatten() {
+                        Some(Shadowing::DuplicateOf(_)) => "=",
+                        Some(Shadowing::ShadowedBy(_)) => "↑",
+                        None => "",
+                    }
+                };
+                let marker_style = if is_selected { base } else { theme.error };
+                cells.push(Line::from(Span::styled(marker, marker_style)));
+            }
+            cells.extend(columns.iter().zip(column_widths).map(|(col, &width)| {
+                let text = item.get(col).map(format_cell_value).unwrap_or_default();
+                let text = clip(&text, usize::from(width), 0);
+                let style = if !is_selected && has_bad_pattern(item, col) {
+                    theme.error
+                } else {
+                    value_style
+                };
+                Line::from(Span::styled(text, style))
+            }));
             Row::new(cells).style(base)
         })
         .collect();
 
-    let widths: Vec<Constraint> = columns.iter().map(|_| Constraint::Fill(1)).collect();
-    let table = Table::new(rows, widths)
+    let table = Table::new(rows, widths.iter().copied().map(Constraint::Length))
         .header(header)
         .block(block)
-        .column_spacing(2);
+        .column_spacing(COLUMN_SPACING);
 
-    frame.render_widget(table, area);
+    let mut state = TableState::new().with_offset(view.setting_offset);
+    frame.render_stateful_widget(table, area, &mut state);
+    render_scrollbar(
+        frame,
+        theme,
+        list_area(area, true),
+        items.len(),
+        view.setting_offset,
+    );
 }
 
 /// Renders the MCPs section as a split panel: top for configs, bottom for permissions.
-fn render_mcp_split_panel(frame: &mut Frame, app: &App, area: Rect) {
-    let halves = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(area);
-
-    render_mcp_configs_panel(frame, app, halves[0]);
-    render_mcp_permissions_panel(frame, app, halves[1]);
+fn render_mcp_split_panel(frame: &mut Frame, app: &App, view: &PaneView, area: Rect) {
+    let (configs, permissions) = mcp_halves(area);
+    render_mcp_configs_panel(frame, app, view, configs);
+    render_mcp_permissions_panel(frame, app, view, permissions);
 }
 
 /// Renders the top half: MCP server configs (amp.mcpServers) as per-server rows.
-fn render_mcp_configs_panel(frame: &mut Frame, app: &App, area: Rect) {
-    let is_focused = app.focus == Focus::Settings && app.mcp_focus == McpFocus::Configs;
+fn render_mcp_configs_panel(frame: &mut Frame, app: &App, view: &PaneView, area: Rect) {
+    let theme = &app.theme;
+    let is_focused = view.focused && view.mcp_focus == McpFocus::Configs;
     let block = Block::default()
-        .title(" MCP Configs ")
+        .title(format!(" MCP Configs ({}) ", app.mcp_config_count()))
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(if is_focused {
-            Color::Cyan
+        .border_style(if is_focused {
+            theme.accent
         } else {
-            Color::DarkGray
-        }));
+            theme.border
+        });
 
     let server_names = app.mcp_server_names();
     let servers = app.config.get("amp.mcpServers");
 
     if server_names.is_empty() {
         let p = Paragraph::new(" No servers. Press 'a' to add one, 'e' to open in $EDITOR.")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         frame.render_widget(p, area);
         return;
     }
 
-    let selected_style = Style::default()
-        .fg(Color::Black)
-        .bg(Color::Cyan)
-        .add_modifier(Modifier::BOLD);
+    let selected_style = theme.selected;
 
     let rows: Vec<Row> = server_names
         .iter()
         .enumerate()
         .map(|(i, name)| {
-            let is_selected = is_focused && i == app.selected_setting;
+            let is_selected = is_focused && i == view.selected_setting;
             let base = if is_selected {
                 selected_style
             } else {
                 Style::default()
             };
-            let value_style = if is_selected {
-                base
-            } else {
-                Style::default().fg(Color::Yellow)
-            };
+            let value_style = if is_selected { base } else { theme.value };
 
-            let config_display = servers.get(name).map(format_cell_value).unwrap_or_default();
+            let config_display = servers
+                .get(name)
+                .map(|server| format_cell_value(&mask_secrets(app, name, server)))
+                .unwrap_or_default();
+            let (symbol, symbol_style) = match app.mcp_server_health(name) {
+                Some(health @ Health::Up(_)) => (health.symbol(), theme.added),
+                Some(health @ Health::Down(_)) => (health.symbol(), theme.error),
+                _ => ("?", theme.muted),
+            };
 
             Row::new(vec![
                 Line::from(Span::styled(
-                    format!(" {name}"),
+                    format!(" {symbol}"),
+                    if is_selected { base } else { symbol_style },
+                )),
+                Line::from(Span::styled(
+                    name.as_str(),
                     base.add_modifier(Modifier::BOLD),
                 )),
                 Line::from(Span::styled(config_display, value_style)),
@@ -345,40 +1243,72 @@ fn render_mcp_configs_panel(frame: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
 
-    let table = Table::new(rows, [Constraint::Fill(1), Constraint::Fill(2)])
-        .block(block)
-        .column_spacing(2);
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+        ],
+    )
+    .block(block)
+    .column_spacing(1);
 
-    frame.render_widget(table, area);
+    let mut state = TableState::new().with_offset(view.setting_offset);
+    frame.render_stateful_widget(table, area, &mut state);
+    render_scrollbar(
+        frame,
+        theme,
+        list_area(area, false),
+        server_names.len(),
+        view.setting_offset,
+    );
 }
 
 /// Renders the bottom half: MCP permissions (amp.mcpPermissions) as a table.
-fn render_mcp_permissions_panel(frame: &mut Frame, app: &App, area: Rect) {
-    let is_focused = app.focus == Focus::Settings && app.mcp_focus == McpFocus::Permissions;
+fn render_mcp_permissions_panel(frame: &mut Frame, app: &App, view: &PaneView, area: Rect) {
+    let theme = &app.theme;
+    let is_focused = view.focused && view.mcp_focus == McpFocus::Permissions;
+    // Rules that apply to the server selected above are marked.
+    let (title, applying) = match app.selected_server_rules() {
+        Some((name, rules)) => (
+            format!(
+                " MCP Permissions ({}) · {name}: {} ",
+                app.mcp_permission_item_count(),
+                match rules.len() {
+                    0 => "no rules apply".to_string(),
+                    1 => "1 rule applies".to_string(),
+                    n => format!("{n} rules apply"),
+                }
+            ),
+            rules,
+        ),
+        None => (
+            format!(" MCP Permissions ({}) ", app.mcp_permission_item_count()),
+            Vec::new(),
+        ),
+    };
     let block = Block::default()
-        .title(" MCP Permissions ")
+        .title(title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(if is_focused {
-            Color::Cyan
+        .border_style(if is_focused {
+            theme.accent
         } else {
-            Color::DarkGray
-        }));
+            theme.border
+        });
 
     let value = app.config.get("amp.mcpPermissions");
     let items = value.as_array().cloned().unwrap_or_default();
 
     if items.is_empty() {
         let p = Paragraph::new(" Empty. Press 'a' to add an item, 'e' to open in $EDITOR.")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         frame.render_widget(p, area);
         return;
     }
 
-    let selected_style = Style::default()
-        .fg(Color::Black)
-        .bg(Color::Cyan)
-        .add_modifier(Modifier::BOLD);
+    let selected_style = theme.selected;
 
     let columns = collect_object_columns(&items);
 
@@ -387,29 +1317,46 @@ fn render_mcp_permissions_panel(frame: &mut Frame, app: &App, area: Rect) {
             .iter()
             .enumerate()
             .map(|(i, item)| {
-                let is_selected = is_focused && i == app.selected_mcp_permission;
+                let is_selected = is_focused && i == view.selected_mcp_permission;
                 let style = if is_selected {
                     selected_style
                 } else {
-                    Style::default().fg(Color::White)
+                    theme.text
                 };
                 ListItem::new(format!(" {}", format_json_compact(item))).style(style)
             })
             .collect();
         let list = List::new(list_items).block(block);
-        frame.render_widget(list, area);
+        let mut state = ListState::default().with_offset(view.mcp_permission_offset);
+        frame.render_stateful_widget(list, area, &mut state);
+        render_scrollbar(
+            frame,
+            theme,
+            list_area(area, false),
+            items.len(),
+            view.mcp_permission_offset,
+        );
         return;
     }
 
+    // An index, the flattened matches, the action, then any other fields.
+    let extra: Vec<&String> = columns
+        .iter()
+        .filter(|col| !matches!(col.as_str(), "matches" | "action"))
+        .collect();
+    let index_width = items.len().to_string().len();
+    let mut widths = vec![
+        Constraint::Length(index_width as u16 + 1),
+        Constraint::Fill(3),
+        Constraint::Length(7),
+    ];
+    widths.extend(extra.iter().map(|_| Constraint::Fill(1)));
+
     let header = Row::new(
-        columns
-            .iter()
-            .map(|col| {
-                Line::from(Span::styled(
-                    col.as_str(),
-                    Style::default().fg(Color::DarkGray),
-                ))
-            })
+        ["#", "matches", "action"]
+            .into_iter()
+            .chain(extra.iter().map(|col| col.as_str()))
+            .map(|col| Line::from(Span::styled(col, theme.muted)))
             .collect::<Vec<_>>(),
     );
 
@@ -417,35 +1364,134 @@ fn render_mcp_permissions_panel(frame: &mut Frame, app: &App, area: Rect) {
         .iter()
         .enumerate()
         .map(|(i, item)| {
-            let is_selected = is_focused && i == app.selected_mcp_permission;
+            let is_selected = is_focused && i == view.selected_mcp_permission;
             let base = if is_selected {
                 selected_style
             } else {
                 Style::default()
             };
-            let value_style = if is_selected {
-                base
+            let value_style = if is_selected { base } else { theme.value };
+            let applies = applying.contains(&i);
+            let index_style = match (is_selected, applies) {
+                (true, _) => base,
+                (false, true) => theme.accent,
+                (false, false) => theme.muted,
+            };
+            let matches_style = if !is_selected && has_bad_pattern(item, "matches") {
+                theme.error
             } else {
-                Style::default().fg(Color::Yellow)
+                value_style
             };
-            let cells: Vec<Line> = columns
-                .iter()
-                .map(|col| {
-                    let text = item.get(col).map(format_cell_value).unwrap_or_default();
-                    Line::from(Span::styled(text, value_style))
-                })
-                .collect();
+            let mut cells = vec![
+                Line::from(Span::styled(
+                    format!("{}{:>index_width$}", if applies { "▸" } else { " " }, i + 1),
+                    index_style,
+                )),
+                Line::from(Span::styled(
+                    item.get("matches")
+                        .map(summarize_matches)
+                        .unwrap_or_default(),
+                    matches_style,
+                )),
+                Line::from(Span::styled(
+                    item.get("action")
+                        .map(format_cell_value)
+                        .unwrap_or_default(),
+                    value_style,
+                )),
+            ];
+            cells.extend(extra.iter().map(|col| {
+                let text = item
+                    .get(col.as_str())
+                    .map(format_cell_value)
+                    .unwrap_or_default();
+                Line::from(Span::styled(text, value_style))
+            }));
             Row::new(cells).style(base)
         })
         .collect();
 
-    let widths: Vec<Constraint> = columns.iter().map(|_| Constraint::Fill(1)).collect();
     let table = Table::new(rows, widths)
         .header(header)
         .block(block)
         .column_spacing(2);
 
-    frame.render_widget(table, area);
+    let mut state = TableState::new().with_offset(view.mcp_permission_offset);
+    frame.render_stateful_widget(table, area, &mut state);
+    render_scrollbar(
+        frame,
+        theme,
+        list_area(area, true),
+        items.len(),
+        view.mcp_permission_offset,
+    );
+}
+
+/// Summarizes an MCP permission's `matches` object as `field=value`
+/// pairs, naming nested fields by their dotted path. An empty object
+/// matches every server.
+fn summarize_matches(matches: &Value) -> String {
+    fn flatten(prefix: &str, value: &Value, parts: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    let path = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{prefix}.{key}")
+                    };
+                    flatten(&path, value, parts);
+                }
+            }
+            Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(format_cell_value).collect();
+                parts.push(format!("{prefix}=[{}]", items.join(", ")));
+            }
+            other if prefix.is_empty() => parts.push(format_cell_value(other)),
+            other => parts.push(format!("{prefix}={}", format_cell_value(other))),
+        }
+    }
+    let mut parts = Vec::new();
+    flatten("", matches, &mut parts);
+    if parts.is_empty() {
+        return "(any server)".to_string();
+    }
+    parts.join(", ")
+}
+
+/// Returns whether a permission rule's `tool` or `matches` cell holds an
+/// invalid glob or regex.
+fn has_bad_pattern(rule: &Value, column: &str) -> bool {
+    match (column, rule.get(column)) {
+        ("tool", Some(Value::String(tool))) => matcher::check(tool).is_err(),
+        ("matches", Some(matches)) => matcher::check_value(matches).is_err(),
+        _ => false,
+    }
+}
+
+/// Draws a scrollbar over the right border of a list panel when its items
+/// don't all fit.
+fn render_scrollbar(frame: &mut Frame, theme: &Theme, list: ListArea, count: usize, offset: usize) {
+    let visible = usize::from(list.visible_rows);
+    if count <= visible {
+        return;
+    }
+    let track = Rect::new(
+        list.area.x,
+        list.first_row,
+        list.area.width,
+        list.visible_rows,
+    );
+    // The scrollbar's position spans 0..=count-visible so the thumb reaches the end.
+    let mut state = ScrollbarState::new(count - visible).position(offset);
+    frame.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .style(theme.muted),
+        track,
+        &mut state,
+    );
 }
 
 /// Collects unique object field names from an array of values.
@@ -476,6 +1522,32 @@ fn column_priority(name: &str) -> u8 {
     }
 }
 
+/// Masks string values stored under secret-looking keys, such as an MCP
+/// server's `GITHUB_TOKEN` env variable, at any depth.
+fn mask_secrets(app: &App, key: &str, value: &Value) -> Value {
+    match value {
+        Value::String(text) if settings::looks_secret(key) => {
+            Value::String(app.mask(text).into_owned())
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), mask_secrets(app, k, v)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Whether a default says nothing worth showing: null, or empty.
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Object(map) => map.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
 /// Formats a cell value for display in object tables.
 /// Produces compact, human-readable output for nested objects and arrays.
 fn format_cell_value(value: &Value) -> String {
@@ -529,13 +1601,7 @@ fn format_value(setting_type: SettingType, value: &Value) -> String {
             }
         }
         SettingType::Number => match value.as_f64() {
-            Some(n) => {
-                if n.fract() == 0.0 {
-                    format!("{}", n as i64)
-                } else {
-                    format!("{}", n)
-                }
-            }
+            Some(n) => settings::format_number(n),
             None => "0".to_string(),
         },
         SettingType::ArrayString => {
@@ -591,9 +1657,31 @@ fn format_json_compact(value: &Value) -> String {
     }
 }
 
+/// The message for the second bottom row: the status message, or else what's
+/// wrong with the selected setting's type, key, or permission rule.
+fn status_line(app: &App) -> Option<(String, Style)> {
+    if !app.pending_keys.is_empty() {
+        let typed = keymap::sequence_label(&app.pending_keys);
+        return Some((format!("{typed} …"), app.theme.muted));
+    }
+    match &app.status_message {
+        Some(msg) => Some((msg.clone(), app.theme.status)),
+        None => app
+            .selected_type_issue()
+            .or_else(|| app.selected_deprecation())
+            .or_else(|| {
+                app.selected_pattern_error()
+                    .map(|e| format!("Invalid pattern in {e}"))
+            })
+            .or_else(|| app.selected_rule_shadowing())
+            .map(|msg| (msg, app.theme.error))
+            .or_else(|| app.selected_mcp_health().map(|msg| (msg, app.theme.muted))),
+    }
+}
+
 /// Renders the bottom bar area (help line + optional status message).
 fn render_bottom_bar(frame: &mut Frame, app: &App, area: Rect) {
-    if let Some(ref msg) = app.status_message {
+    if let Some((msg, style)) = status_line(app) {
         let rows = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(1), Constraint::Length(1)])
@@ -601,34 +1689,87 @@ fn render_bottom_bar(frame: &mut Frame, app: &App, area: Rect) {
 
         render_help_line(frame, app, rows[0]);
 
-        let bar =
-            Paragraph::new(msg.as_str()).style(Style::default().fg(Color::Black).bg(Color::Yellow));
+        let bar = Paragraph::new(msg).style(style);
         frame.render_widget(bar, rows[1]);
     } else {
         render_help_line(frame, app, area);
     }
 }
 
-/// Renders the help/description line.
+/// Renders the help/description line, with the git badge and the stats
+/// segment on the right when there are any.
 fn render_help_line(frame: &mut Frame, app: &App, area: Rect) {
-    let text = if app.focus == Focus::Settings {
+    let theme = &app.theme;
+    let git = app
+        .git_status
+        .filter(|status| status.is_committable())
+        .map(|status| format!("git: {}", status.label()));
+    let stats = app.show_stats.then(|| format_stats(app, SystemTime::now()));
+    let segment: Vec<String> = git.into_iter().chain(stats).collect();
+    let area = if !segment.is_empty() {
+        let stats = segment.join(" · ");
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(1),
+                Constraint::Length(stats.chars().count() as u16 + 1),
+            ])
+            .split(area);
+        let segment = Paragraph::new(stats).style(theme.accent);
+        frame.render_widget(segment, columns[1]);
+        columns[0]
+    } else {
+        area
+    };
+
+    let hints: &[(&[Action], &str)] = if app.focus == Focus::Settings {
         let section = app.current_section();
         if section == Section::Advanced {
-            " Enter: edit | a: add key | r: remove | e: $EDITOR | Tab: sidebar".to_string()
+            &[
+                (&[Action::Activate], "edit"),
+                (&[Action::AddItem], "add key"),
+                (&[Action::Reset], "remove"),
+                (&[Action::OpenEditor], "$EDITOR"),
+                (&[Action::ToggleFocus], "sidebar"),
+            ]
         } else if section.is_split_panel() {
             match app.mcp_focus {
-                McpFocus::Configs => {
-                    " Enter: edit | a: add | d: delete | e: $EDITOR | ↓: permissions | Tab: sidebar"
-                        .to_string()
-                }
-                McpFocus::Permissions => {
-                    " Enter: edit | a: add | d: delete | e: $EDITOR | r: reset | Tab: sidebar"
-                        .to_string()
-                }
+                McpFocus::Configs => &[
+                    (&[Action::Activate], "edit"),
+                    (&[Action::AddItem], "add"),
+                    (&[Action::DeleteItem], "delete"),
+                    (&[Action::Duplicate], "duplicate"),
+                    (&[Action::EditEnv], "env"),
+                    (&[Action::TestMcp], "test"),
+                    (&[Action::OpenEditor], "$EDITOR"),
+                    (&[Action::SwitchMcpPanel], "permissions"),
+                    (&[Action::ToggleFocus], "sidebar"),
+                ],
+                McpFocus::Permissions => &[
+                    (&[Action::Activate], "edit"),
+                    (&[Action::AddItem], "add"),
+                    (&[Action::DeleteItem], "delete"),
+                    (&[Action::MoveItemUp, Action::MoveItemDown], "move"),
+                    (&[Action::Duplicate], "duplicate"),
+                    (&[Action::OpenEditor], "$EDITOR"),
+                    (&[Action::Reset], "reset"),
+                    (&[Action::SwitchMcpPanel], "configs"),
+                    (&[Action::ToggleFocus], "sidebar"),
+                ],
             }
         } else if section.is_single_key() {
-            " Enter: edit item | a: add | d: delete | e: $EDITOR | r: reset | Tab: sidebar"
-                .to_string()
+            &[
+                (&[Action::Activate], "edit item"),
+                (&[Action::AddItem], "add"),
+                (&[Action::DeleteItem], "delete"),
+                (&[Action::MoveItemUp, Action::MoveItemDown], "move"),
+                (&[Action::Duplicate], "duplicate"),
+                (&[Action::SortRows], "sort"),
+                (&[Action::TestPermissions], "test"),
+                (&[Action::OpenEditor], "$EDITOR"),
+                (&[Action::Reset], "reset"),
+                (&[Action::ToggleFocus], "sidebar"),
+            ]
         } else {
             let entries = app.current_settings();
             let is_array = entries.get(app.selected_setting).is_some_and(|e| {
@@ -639,78 +1780,1488 @@ fn render_help_line(frame: &mut Frame, app: &App, area: Rect) {
                 )
             });
             if is_array {
-                " Enter: toggle/edit | a: add | d: delete | r: reset | e: $EDITOR | Tab: sidebar"
-                    .to_string()
+                &[
+                    (&[Action::Activate], "toggle/edit"),
+                    (&[Action::AddItem], "add"),
+                    (&[Action::DeleteItem], "delete"),
+                    (&[Action::Reset], "reset"),
+                    (&[Action::OpenEditor], "$EDITOR"),
+                    (&[Action::ToggleFocus], "sidebar"),
+                ]
             } else {
-                " Enter: toggle/edit | r: reset | e: $EDITOR | Tab: sidebar".to_string()
+                &[
+                    (&[Action::Activate], "toggle/edit"),
+                    (&[Action::Reset], "reset"),
+                    (&[Action::OpenEditor], "$EDITOR"),
+                    (&[Action::EditNote], "note"),
+                    (&[Action::ToggleFocus], "sidebar"),
+                ]
             }
         }
     } else {
-        " ↑↓: navigate | Enter/Tab: settings | Ctrl+S: save | q: quit".to_string()
+        &[
+            (&[Action::MoveUp, Action::MoveDown], "navigate"),
+            (&[Action::Activate, Action::ToggleFocus], "settings"),
+            (&[Action::ToggleSplit], "split"),
+            (&[Action::SwapPane], "swap pane"),
+            (&[Action::SwitchFile], "open file"),
+            (&[Action::CheckWithAmp], "check with amp"),
+            (&[Action::Save], "save"),
+            (&[Action::Quit], "quit"),
+        ]
     };
+    let text = key_hints(&app.keymap, hints);
 
-    let bar = Paragraph::new(text).style(Style::default().fg(Color::DarkGray));
+    let bar = Paragraph::new(text).style(theme.muted);
     frame.render_widget(bar, area);
 }
 
+/// Builds the stats footer segment: explicit key count, file size, and last save.
+fn format_stats(app: &App, now: SystemTime) -> String {
+    let count = app.config.explicit_count();
+    let keys = if count == 1 { "key" } else { "keys" };
+    match app.config.disk_info() {
+        Some(info) => format!(
+            "{count} {keys} set · {} · saved {}",
+            format_size(info.size),
+            format_age(now.duration_since(info.modified).unwrap_or_default())
+        ),
+        None => format!("{count} {keys} set · not saved yet"),
+    }
+}
+
+/// Formats a byte count for humans.
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Formats status bar hints like ` Enter: edit | a: add` from the actions'
+/// current key bindings.
+fn key_hints(keymap: &Keymap, hints: &[(&[Action], &str)]) -> String {
+    let parts: Vec<String> = hints
+        .iter()
+        .map(|(actions, what)| {
+            let keys: Vec<String> = actions.iter().map(|&a| keymap.label(a)).collect();
+            format!("{}: {what}", keys.join("/"))
+        })
+        .collect();
+    format!(" {}", parts.join(" | "))
+}
+
+/// Formats an elapsed duration as a coarse "time ago" string.
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        "just now".to_string()
+    } else if secs < 60 * 60 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 24 * 60 * 60 {
+        format!("{}h ago", secs / (60 * 60))
+    } else {
+        format!("{}d ago", secs / (24 * 60 * 60))
+    }
+}
+
 /// Renders the appropriate edit overlay based on input mode.
 fn render_edit_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
     match app.input_mode {
         InputMode::SelectingType => render_type_select_overlay(frame, app),
+        InputMode::SelectingEnum => render_enum_select_overlay(frame, app),
         InputMode::SelectingPermissionLevel => render_permission_level_overlay(frame, app),
         InputMode::SelectingMcpPermissionLevel => {
             render_mcp_permission_level_overlay(frame, app);
         }
         InputMode::ConfirmAdvancedEdit | InputMode::ConfirmMcpEdit => {
-            render_confirm_editor_overlay(frame);
+            render_confirm_overlay(
+                frame,
+                theme,
+                " Open Editor? (y/n) ",
+                " y: open in $EDITOR  n: skip",
+            );
+        }
+        InputMode::ConfirmReopenEditor => {
+            let error = app
+                .editor_draft
+                .as_ref()
+                .map(|d| d.error.as_str())
+                .unwrap_or_default();
+            render_confirm_overlay(
+                frame,
+                theme,
+                " Invalid JSON. Reopen editor? (y/n) ",
+                &format!(" {error}"),
+            );
+        }
+        InputMode::ConfirmScaffold => {
+            render_confirm_overlay(
+                frame,
+                theme,
+                " No settings file found. Create one? (y/n) ",
+                " y: scaffold commented defaults  n: start empty",
+            );
+        }
+        InputMode::ConfirmQuit => {
+            render_confirm_overlay(
+                frame,
+                theme,
+                " Quit with unsaved changes? ",
+                " s: save and quit  d: discard  c: cancel",
+            );
+        }
+        InputMode::ConfirmReset => {
+            if let Some(scope) = app.pending_reset {
+                let (known, unknown) = app.reset_candidates(scope);
+                let title = match scope {
+                    ResetScope::Section(section) => {
+                        format!(" Reset {} setting(s) in {}? ", known.len(), section.label())
+                    }
+                    ResetScope::All => format!(" Reset all {} setting(s)? ", known.len()),
+                };
+                let body = if unknown.is_empty() {
+                    " y: reset  n: cancel".to_string()
+                } else {
+                    format!(
+                        " y: reset  a: also remove {} unknown key(s)  n: cancel",
+                        unknown.len()
+                    )
+                };
+                render_confirm_overlay(frame, theme, &title, &body);
+            }
+        }
+        InputMode::ResolvingExternalChange => {
+            render_confirm_overlay(
+                frame,
+                theme,
+                " Settings file changed on disk ",
+                " r: reload (discard local)  m: merge  k: keep local",
+            );
+        }
+        InputMode::ResolvingConflicts => render_merge_overlay(frame, app),
+        InputMode::SelectingFile => render_file_select_overlay(frame, app),
+        InputMode::SelectingCopyFormat => render_copy_overlay(frame, app),
+        InputMode::SelectingImportSource => render_import_source_overlay(frame, app),
+        InputMode::SelectingImportServers => render_import_servers_overlay(frame, app),
+        InputMode::SelectingDisabledTools => render_tool_picker_overlay(frame, app),
+        InputMode::ViewingReport => render_report_overlay(frame, app),
+        InputMode::Searching => render_search_overlay(frame, app),
+        InputMode::CommandPalette => render_palette_overlay(frame, app),
+        InputMode::SelectingBackup => render_backup_select_overlay(frame, app),
+        InputMode::SelectingProfile => render_profile_select_overlay(frame, app),
+        InputMode::SelectingPreset => render_preset_select_overlay(frame, app),
+        InputMode::BrowsingRegistry => render_registry_overlay(frame, app),
+        InputMode::ViewingTrash => render_trash_overlay(frame, app),
+        InputMode::ViewingRecent => render_recent_overlay(frame, app),
+        InputMode::EditingEnv => render_env_overlay(frame, app),
+        InputMode::EditingObject => render_object_form_overlay(frame, app),
+        InputMode::TestingPermissions => render_permission_test_overlay(frame, app),
+        InputMode::EditingRule => render_rule_form_overlay(frame, app),
+        InputMode::EditingServer => render_server_form_overlay(frame, app),
+        InputMode::EnteringEnvKey | InputMode::EnteringEnvValue => {
+            render_env_overlay(frame, app);
+            render_text_input_overlay(frame, app);
         }
-        InputMode::Normal => {}
+        InputMode::ViewingDiff => render_diff_overlay(frame, app),
+        InputMode::ViewingMessages => render_messages_overlay(frame, app),
+        InputMode::EditingJson => render_json_editor_overlay(frame, app),
+        // The tree is drawn in place in the settings table.
+        InputMode::Normal | InputMode::BrowsingTree => {}
         _ => render_text_input_overlay(frame, app),
     }
 }
 
-/// Renders a text input overlay for inline editing, key name entry, or custom value entry.
-fn render_text_input_overlay(frame: &mut Frame, app: &App) {
+/// Renders a text input overlay for inline editing, key name entry, or custom value entry.
+fn render_text_input_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let width = 50.min(area.width.saturating_sub(4));
+    let height = 3;
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let title = match app.input_mode {
+        InputMode::EnteringKeyName => " Enter Key Name (Enter to confirm, Esc to cancel) ",
+        InputMode::EnteringCustomValue => " Enter Value (Enter to save, Esc to cancel) ",
+        InputMode::EnteringPermissionTool => " Enter Tool Name (Enter to confirm, Esc to cancel) ",
+        InputMode::EnteringDelegateTo => " Enter Program Name (Enter to confirm, Esc to cancel) ",
+        InputMode::EnteringMcpServerName => " Enter Server Name (Enter to confirm, Esc to cancel) ",
+        InputMode::EnteringMcpMatchField => {
+            " Enter Match Field e.g. command, url (Enter to confirm, Esc to cancel) "
+        }
+        InputMode::EnteringMcpMatchValue => " Enter Match Value (Enter to confirm, Esc to cancel) ",
+        InputMode::EditingNote => " Note (Enter to save, empty to remove, Esc to cancel) ",
+        InputMode::EnteringProfileName => " Save Profile As (Enter to save, Esc to cancel) ",
+        InputMode::EnteringExportPath => {
+            " Export Redacted Settings To (Enter to write, Esc to cancel) "
+        }
+        InputMode::EnteringCommitMessage => " Commit Message (Enter to commit, Esc to cancel) ",
+        InputMode::EnteringPresetName => " Save Preset As (Enter to save, Esc to cancel) ",
+        InputMode::EnteringEnvKey => " Variable Name (Enter to confirm, Esc to cancel) ",
+        InputMode::EnteringEnvValue => " Variable Value (Enter to save, Esc to cancel) ",
+        InputMode::EnteringCustomTool => " Tool Name (Enter to add, Esc to go back) ",
+        InputMode::EnteringObjectKey => " New Entry Key (Enter to add, Esc to go back) ",
+        InputMode::EnteringObjectValue => " Entry Value (Enter to save, Esc to go back) ",
+        InputMode::EditingTreeValue => " Value (Enter to save, Esc to go back) ",
+        _ => " Edit Value (Enter to save, Esc to cancel) ",
+    };
+
+    let mut block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+    if let Some(error) = app.pattern_input_error() {
+        block = block
+            .title_bottom(Span::styled(format!(" {error} "), theme.error))
+            .border_style(theme.error);
+    }
+    match app.number_prompt() {
+        Some((_, Some(error))) => {
+            block = block
+                .title_bottom(Span::styled(format!(" {error} "), theme.error))
+                .border_style(theme.error);
+        }
+        Some((spec, None)) => {
+            if let Some(hint) = spec.describe() {
+                block = block.title_bottom(Span::styled(format!(" {hint} "), theme.muted));
+            }
+        }
+        None => {}
+    }
+    if app.input_mode == InputMode::EnteringKeyName {
+        // Leave room for the completion hint on the right.
+        let room = usize::from(width).saturating_sub(32);
+        let nested = match app.nested_key_target(app.edit_buffer.trim()) {
+            Some((top, path)) => clip(&format!("→ {} in {top}", path.join(".")), room, 0),
+            None if app.nested_keys => "Ctrl+T nested: on".to_string(),
+            None => "Ctrl+T nested: off".to_string(),
+        };
+        block = block.title_bottom(Span::styled(format!(" {nested} "), theme.muted));
+    }
+    if app.prompt_has_assist() {
+        let hint = match app.completion_position() {
+            Some((shown, of)) => format!(" {shown}/{of} · Tab next "),
+            None => " Tab completes · ↑↓ history ".to_string(),
+        };
+        block = block.title_bottom(Line::styled(hint, theme.muted).right_aligned());
+    }
+
+    // Typing a credential shouldn't put it on screen either.
+    let sensitive = app.input_mode == InputMode::EditingValue
+        && app.selected_key().is_some_and(|key| app.is_sensitive(&key))
+        || app.input_mode == InputMode::EnteringEnvValue
+            && app
+                .pending_env_key
+                .as_deref()
+                .is_some_and(settings::looks_secret);
+    render_prompt(
+        frame,
+        popup_area,
+        block,
+        &app.edit_buffer,
+        sensitive && !app.reveal_secrets,
+        theme.text,
+    );
+}
+
+/// Renders a prompt's text in `block` with the terminal cursor on it,
+/// scrolling sideways so the cursor stays in view. Masked text shows a `•`
+/// per character.
+fn render_prompt(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    input: &TextInput,
+    masked: bool,
+    style: Style,
+) {
+    let inner = block.inner(area);
+    let (shown, col) = prompt_view(input, masked, usize::from(inner.width).max(1));
+    frame.render_widget(Paragraph::new(shown).style(style).block(block), area);
+    if !inner.is_empty() {
+        let col = u16::try_from(col).unwrap_or(u16::MAX);
+        frame.set_cursor_position(Position::new(inner.x.saturating_add(col), inner.y));
+    }
+}
+
+/// The part of a prompt's text that fits in `width` columns with the cursor
+/// in view, and the cursor's column within it. Wide characters such as CJK
+/// and most emoji take two columns.
+fn prompt_view(input: &TextInput, masked: bool, width: usize) -> (String, usize) {
+    let clusters: Vec<&str> = input
+        .graphemes(true)
+        .map(|c| if masked { "•" } else { c })
+        .collect();
+    let widths: Vec<usize> = clusters.iter().map(|c| c.width()).collect();
+    let cursor = input.cursor();
+    let before = |skip: usize| widths[skip..cursor].iter().sum::<usize>();
+    // Leave a column after the cursor for it to sit in.
+    let mut skip = 0;
+    while skip < cursor && before(skip) + 1 > width {
+        skip += 1;
+    }
+    let mut used = 0;
+    let shown = clusters[skip..]
+        .iter()
+        .zip(&widths[skip..])
+        .take_while(|(_, w)| {
+            used += **w;
+            used <= width
+        })
+        .map(|(c, _)| *c)
+        .collect();
+    (shown, before(skip))
+}
+
+/// Renders the type selection overlay for choosing a custom key value type.
+fn render_type_select_overlay(frame: &mut Frame, app: &App) {
+    let labels: Vec<Line> = CustomKeyType::ALL
+        .iter()
+        .map(|t| Line::from(format!("  {}", t.label())))
+        .collect();
+    render_select_overlay(
+        frame,
+        &app.theme,
+        " Select Type (Enter to confirm, Esc to cancel) ",
+        &labels,
+        app.selected_type,
+    );
+}
+
+/// Renders the picker of a StringEnum setting's options; `●` marks the
+/// current value. Amp's built-in themes get a swatch of their colors.
+fn render_enum_select_overlay(frame: &mut Frame, app: &App) {
+    let Some((key, options)) = &app.pending_enum else {
+        return;
+    };
+    let current = app.effective_value(key);
+    let current = current.as_str().unwrap_or("");
+    let name_width = options.iter().map(|o| o.chars().count()).max().unwrap_or(0);
+    let labels: Vec<Line> = options
+        .iter()
+        .map(|o| {
+            let mark = if o == current { "●" } else { " " };
+            let mut spans = vec![Span::raw(format!(" {mark} {o:<name_width$}"))];
+            if let Some(palette) = (key == "amp.terminal.theme")
+                .then(|| theme::amp_palette(o))
+                .flatten()
+            {
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(
+                    " Aa ",
+                    Style::default()
+                        .fg(palette.foreground)
+                        .bg(palette.background),
+                ));
+                for color in palette.accents {
+                    spans.push(Span::styled("██", Style::default().fg(color)));
+                }
+            }
+            Line::from(spans)
+        })
+        .collect();
+    render_select_overlay(
+        frame,
+        &app.theme,
+        &format!(" {key} (Enter to pick, Esc to cancel) "),
+        &labels,
+        app.selected_option,
+    );
+}
+
+/// Renders a centered list to pick one line from, scrolled to keep the
+/// selection in view.
+fn render_select_overlay(
+    frame: &mut Frame,
+    theme: &Theme,
+    title: &str,
+    labels: &[Line],
+    selected: usize,
+) {
+    let area = frame.area();
+    let widest = labels.iter().map(Line::width).max().unwrap_or(0);
+    let width = (widest.max(title.chars().count()) as u16 + 2)
+        .max(40)
+        .min(area.width.saturating_sub(4));
+    let height = (labels.len() as u16 + 2).min(area.height.saturating_sub(2)); // +2 for border
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let items: Vec<ListItem> = labels
+        .iter()
+        .map(|l| ListItem::new(l.clone()).style(theme.text))
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(theme.popup_selected);
+    let mut state = ListState::default().with_selected(Some(selected));
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the merge view: each key both sides changed, with the value it
+/// had when loaded, the file's, and the local one, the picked side marked.
+fn render_merge_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (app.merge_conflicts.len() as u16 + 3).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Changed on disk and here (←/→ pick, Enter to merge) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let show = |value: &Option<Value>| match value {
+        Some(value) => format_json_compact(value),
+        None => "(unset)".to_string(),
+    };
+    let rows: Vec<Row> = app
+        .merge_conflicts
+        .iter()
+        .enumerate()
+        .map(|(i, (conflict, side))| {
+            let base = if i == app.selected_conflict {
+                theme.popup_selected
+            } else {
+                theme.text
+            };
+            let pick = |this: MergeSide, value: &Option<Value>| {
+                if *side == this {
+                    Span::styled(format!("● {}", show(value)), base.patch(theme.accent))
+                } else {
+                    Span::styled(format!("  {}", show(value)), base.patch(theme.muted))
+                }
+            };
+            Row::new(vec![
+                Line::from(Span::raw(format!(" {}", conflict.key))),
+                Line::from(Span::styled(show(&conflict.base), base.patch(theme.muted))),
+                Line::from(pick(MergeSide::Theirs, &conflict.theirs)),
+                Line::from(pick(MergeSide::Mine, &conflict.mine)),
+            ])
+            .style(base)
+        })
+        .collect();
+    let header = Row::new(vec![" Key", "Base", "  Theirs", "  Mine"]).style(theme.muted);
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(header)
+    .block(block)
+    .style(theme.text);
+    frame.render_widget(table, popup_area);
+}
+
+/// Renders a yes/no confirmation overlay.
+fn render_confirm_overlay(frame: &mut Frame, theme: &Theme, title: &str, body: &str) {
+    let area = frame.area();
+    let width = (title.chars().count().max(body.chars().count()) as u16 + 2)
+        .max(40)
+        .min(area.width.saturating_sub(4));
+    let height = 3;
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let text = Paragraph::new(body).style(theme.text).block(block);
+
+    frame.render_widget(text, popup_area);
+}
+
+/// Renders the permission level selection overlay.
+fn render_permission_level_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let item_count = PermissionLevel::ALL.len() as u16;
+    let width = 50.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Select Permission (Enter to confirm, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let selected_style = theme.popup_selected;
+
+    let items: Vec<ListItem> = PermissionLevel::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, level)| {
+            let style = if i == app.selected_permission_level {
+                selected_style
+            } else {
+                theme.text
+            };
+            ListItem::new(format!("  {}", level.label())).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(block);
+    frame.render_widget(list, popup_area);
+}
+
+/// Renders the MCP permission level selection overlay (allow/reject only).
+fn render_mcp_permission_level_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let item_count = McpPermissionLevel::ALL.len() as u16;
+    let width = 50.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Select Action (Enter to confirm, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let selected_style = theme.popup_selected;
+
+    let items: Vec<ListItem> = McpPermissionLevel::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, level)| {
+            let style = if i == app.selected_mcp_permission_level {
+                selected_style
+            } else {
+                theme.text
+            };
+            ListItem::new(format!("  {}", level.label())).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(block);
+    frame.render_widget(list, popup_area);
+}
+
+/// Renders the file switcher overlay listing Amp's config files.
+fn render_file_select_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let item_count = app.file_candidates.len() as u16;
+    let width = 60.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Open File (Enter to open, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let selected_style = theme.popup_selected;
+
+    let base_dir = app.settings_path.parent();
+    let items: Vec<ListItem> = app
+        .file_candidates
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let style = if i == app.selected_file {
+                selected_style
+            } else {
+                theme.text
+            };
+            let display = base_dir
+                .and_then(|dir| path.strip_prefix(dir).ok())
+                .unwrap_or(path);
+            ListItem::new(format!("  {}", display.display())).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(block);
+    frame.render_widget(list, popup_area);
+}
+
+/// Renders the picker of what to copy, with a one-line preview of each.
+fn render_copy_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = (CopyFormat::ALL.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Copy (Enter to copy, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let items: Vec<ListItem> = CopyFormat::ALL
+        .iter()
+        .map(|&format| {
+            let preview = app
+                .copy_text(format)
+                .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+                .unwrap_or_default();
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("  {:<19}", format.label())),
+                Span::styled(preview, theme.muted),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.selected_copy_format));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the picker of tools to import MCP servers from, showing where
+/// each one's config is expected.
+fn render_import_source_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = (Source::ALL.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Import MCP Servers From (Enter to read, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let items: Vec<ListItem> = Source::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let selected = i == app.selected_import_source;
+            let path = app.import_paths.get(i).cloned().flatten();
+            let found = path.as_ref().is_some_and(|p| p.exists());
+            let detail = match &path {
+                Some(p) if found => format!("  {}", p.display()),
+                Some(p) => format!("  {} (not found)", p.display()),
+                None => "  (no known location)".to_string(),
+            };
+            let (label_style, detail_style) = if selected {
+                (theme.popup_selected, theme.popup_selected)
+            } else {
+                (theme.text, theme.muted)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("  {:<15}", source.label()), label_style),
+                Span::styled(detail, detail_style),
+            ]))
+            .style(if selected {
+                theme.popup_selected
+            } else {
+                Style::default()
+            })
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), popup_area);
+}
+
+/// Renders the checklist of MCP servers offered for import.
+fn render_import_servers_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let item_count = app.import_candidates.len() as u16;
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Import (Space to toggle, Enter to import, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let items: Vec<ListItem> = app
+        .import_candidates
+        .iter()
+        .map(|c| {
+            let mark = if c.checked { "[x]" } else { "[ ]" };
+            let target = c
+                .server
+                .get("command")
+                .or_else(|| c.server.get("url"))
+                .map(format_cell_value)
+                .unwrap_or_default();
+            let note = if c.exists {
+                "  (replaces existing)"
+            } else {
+                ""
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(" {mark} {}  ", c.name)),
+                Span::styled(format!("{target}{note}"), theme.muted),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.selected_import));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the checklist of tools for `amp.tools.disable`.
+fn render_tool_picker_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let item_count = app.tool_choices.len() as u16;
+    let width = 60.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let checked = app.tool_choices.iter().filter(|c| c.checked).count();
+    let block = Block::default()
+        .title(" Disabled Tools (Space to toggle, a to add, Enter to apply, Esc to cancel) ")
+        .title_bottom(Line::styled(format!(" {checked} disabled "), theme.muted).right_aligned())
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let items: Vec<ListItem> = app
+        .tool_choices
+        .iter()
+        .map(|c| {
+            let mark = if c.checked { "[x]" } else { "[ ]" };
+            let note = if c.custom { "  (custom)" } else { "" };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(" {mark} {}", c.name)),
+                Span::styled(note, theme.muted),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.selected_tool_choice));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the diff of unsaved changes, colored like a unified diff.
+fn render_diff_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let width = area.width.saturating_sub(8).max(20).min(area.width);
+    let height = area.height.saturating_sub(4).max(3).min(area.height);
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let title = if app.diff_from_head {
+        " Changes Since the Last Commit (c to commit, ↑↓ to scroll, Esc to close) "
+    } else {
+        " Unsaved Changes (s to save, ↑↓ to scroll, Esc to close) "
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let lines: Vec<Line> = app
+        .diff
+        .iter()
+        .skip(app.diff_scroll)
+        .map(|line| {
+            let (prefix, style) = match line.kind {
+                DiffKind::Same => (" ", theme.text),
+                DiffKind::Added => ("+", theme.added),
+                DiffKind::Removed => ("-", theme.removed),
+                DiffKind::Gap => (" ", theme.muted),
+            };
+            Line::from(Span::styled(format!("{prefix} {}", line.text), style))
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// Renders the status message history, newest first, with each message's
+/// age and severity.
+fn render_messages_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let width = area.width.saturating_sub(8).max(20).min(area.width);
+    let height = area.height.saturating_sub(4).max(3).min(area.height);
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(format!(
+            " Messages ({}) (↑↓ to scroll, Esc to close) ",
+            app.messages.len()
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let now = SystemTime::now();
+    let lines: Vec<Line> = if app.messages.is_empty() {
+        vec![Line::from(Span::styled("No messages yet.", theme.muted))]
+    } else {
+        app.messages
+            .newest_first()
+            .skip(app.messages_scroll)
+            .map(|message| {
+                let style = match message.severity {
+                    Severity::Info => theme.text,
+                    Severity::Warning => theme.status,
+                    Severity::Error => theme.error,
+                };
+                let age = format_age(now.duration_since(message.at).unwrap_or_default());
+                Line::from(vec![
+                    Span::styled(format!("{age:>9} "), theme.muted),
+                    Span::styled(format!("{:<7} ", message.severity.label()), style),
+                    Span::styled(message.text.clone(), style),
+                ])
+            })
+            .collect()
+    };
+
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// Renders the inline JSON editor, marking the line of any parse error and
+/// placing the terminal cursor at the edit position.
+fn render_json_editor_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let Some(edit) = &app.json_edit else {
+        return;
+    };
+    let area = frame.area();
+    let width = (area.width * 4 / 5)
+        .max(40)
+        .min(area.width.saturating_sub(4));
+    let height = (area.height * 4 / 5)
+        .max(8)
+        .min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let target = match (&edit.request.object_key, edit.request.array_index) {
+        (Some(name), _) => format!("{}.{name}", edit.request.key),
+        (None, Some(i)) => format!("{}[{i}]", edit.request.key),
+        (None, None) => edit.request.key.clone(),
+    };
+    let (status, status_style) = match &edit.error {
+        Some(err) => (format!(" {} ", err.message), theme.error),
+        None => (" Valid JSON ".to_string(), theme.muted),
+    };
+    let block = Block::default()
+        .title(format!(" {target} (Ctrl+S to apply, Esc to cancel) "))
+        .title_bottom(Line::from(Span::styled(status, status_style)))
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    // Scroll just far enough to keep the cursor's line in view.
+    let visible = usize::from(height.saturating_sub(2)).max(1);
+    let (row, col) = edit.text.cursor();
+    let first = (row + 1).saturating_sub(visible);
+    let error_line = edit.error.as_ref().map(|e| e.line);
+
+    let lines: Vec<Line> = edit
+        .text
+        .lines()
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(i, text)| {
+            let style = if Some(i) == error_line {
+                theme.error
+            } else {
+                theme.text
+            };
+            Line::from(Span::styled(text.as_str(), style))
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+
+    let col = u16::try_from(col).unwrap_or(u16::MAX);
+    let cursor_x = (popup_area.x + 1)
+        .saturating_add(col)
+        .min(popup_area.right().saturating_sub(2));
+    let cursor_y = popup_area.y + 1 + (row - first) as u16;
+    frame.set_cursor_position(Position::new(cursor_x, cursor_y));
+}
+
+/// Renders the backup picker overlay.
+fn render_backup_select_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let item_count = app.backup_candidates.len() as u16;
+    let width = 60.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Restore Backup (Enter to restore, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let selected_style = theme.popup_selected;
+
+    let items: Vec<ListItem> = app
+        .backup_candidates
+        .iter()
+        .map(|path| ListItem::new(format!("  {} UTC", backup::label(path))))
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.selected_backup));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(selected_style);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the new MCP server form. Values of secret-looking env variables
+/// and headers are masked unless secrets are revealed.
+fn render_server_form_overlay(frame: &mut Frame, app: &App) {
+    use server_form::Field;
+
+    let theme = &app.theme;
+    let Some(form) = &app.server_form else {
+        return;
+    };
+    let area = frame.area();
+    let fields = form.fields();
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (fields.len() as u16 + 6).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(format!(
+            " New MCP Server '{}' (Enter to add, Esc to cancel) ",
+            form.name
+        ))
+        .title_bottom(server_form_hints(form))
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let masked = |name: &str, value: &str| {
+        if !app.reveal_secrets && settings::looks_secret(name) {
+            "•".repeat(value.chars().count())
+        } else {
+            value.to_string()
+        }
+    };
+    let mut lines = Vec::new();
+    for field in fields {
+        let focused = field == form.field;
+        let (label, text) = match field {
+            Field::Transport => (
+                "Transport".to_string(),
+                format!("◂ {} ▸", form.transport.label()),
+            ),
+            Field::Command => ("Command".to_string(), form.command.clone()),
+            Field::Arg(i) => (format!("  arg {}", i + 1), form.args[i].clone()),
+            Field::EnvName(i) => {
+                if i == 0 {
+                    lines.push(Line::from(Span::styled(" Env", theme.muted)));
+                }
+                ("  name".to_string(), form.env[i].name.clone())
+            }
+            Field::EnvValue(i) => (
+                "  value".to_string(),
+                masked(&form.env[i].name, &form.env[i].value),
+            ),
+            Field::Url => ("URL".to_string(), form.url.clone()),
+            Field::HeaderName(i) => {
+                if i == 0 {
+                    lines.push(Line::from(Span::styled(" Headers", theme.muted)));
+                }
+                ("  name".to_string(), form.headers[i].name.clone())
+            }
+            Field::HeaderValue(i) => (
+                "  value".to_string(),
+                masked(&form.headers[i].name, &form.headers[i].value),
+            ),
+        };
+        let cursor = if focused && field != Field::Transport {
+            "▏"
+        } else {
+            ""
+        };
+        let mut spans = vec![
+            Span::styled(
+                format!(" {label:<10}"),
+                if focused { theme.accent } else { theme.muted },
+            ),
+            Span::styled(
+                format!("{text}{cursor}"),
+                if focused {
+                    theme.popup_selected
+                } else {
+                    theme.text
+                },
+            ),
+        ];
+        if let Some(error) = form.error(field) {
+            spans.push(Span::styled(format!("  {error}"), theme.error));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let text = Paragraph::new(lines).block(block);
+    frame.render_widget(text, popup_area);
+}
+
+/// The key hints under the server form, for the transport being filled in.
+fn server_form_hints(form: &ServerForm) -> &'static str {
+    match form.transport {
+        server_form::Transport::Stdio => {
+            " Tab next · ←/→ transport · Ctrl+A arg · Ctrl+N env · Ctrl+D remove · Ctrl+E $EDITOR "
+        }
+        server_form::Transport::Http => {
+            " Tab next · ←/→ transport · Ctrl+N header · Ctrl+D remove · Ctrl+E $EDITOR "
+        }
+    }
+}
+
+/// Renders the permission rule form: one line per field, with the focused
+/// field highlighted and each field's validation error beside it.
+fn render_rule_form_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let Some(form) = &app.rule_form else {
+        return;
+    };
+    let area = frame.area();
+    let fields = form.fields();
+    let width = 72.min(area.width.saturating_sub(4));
+    let height = (fields.len() as u16 + 6).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(format!(
+            " Edit Permission Rule {} (Enter to save, Esc to cancel) ",
+            form.index
+        ))
+        .title_bottom(
+            " Tab next field · ←/→ action · Ctrl+A/Ctrl+D add/remove match · Ctrl+E $EDITOR ",
+        )
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let mut lines = Vec::new();
+    for field in fields {
+        let focused = field == form.field;
+        let (label, text) = match field {
+            Field::Tool => ("Tool".to_string(), form.tool.clone()),
+            Field::Action => ("Action".to_string(), format!("◂ {} ▸", form.action)),
+            Field::To => ("To".to_string(), form.to.clone()),
+            Field::MatchName(i) => {
+                if i == 0 {
+                    lines.push(Line::from(Span::styled(" Matches", theme.muted)));
+                }
+                ("  arg".to_string(), form.matches[i].name.clone())
+            }
+            Field::MatchPattern(i) => ("  =".to_string(), form.matches[i].pattern.clone()),
+        };
+        let cursor = if focused && field != Field::Action {
+            "▏"
+        } else {
+            ""
+        };
+        let mut spans = vec![
+            Span::styled(
+                format!(" {label:<8}"),
+                if focused { theme.accent } else { theme.muted },
+            ),
+            Span::styled(
+                format!("{text}{cursor}"),
+                if focused {
+                    theme.popup_selected
+                } else {
+                    theme.text
+                },
+            ),
+        ];
+        if let Some(error) = form.error(field) {
+            spans.push(Span::styled(format!("  {error}"), theme.error));
+        }
+        lines.push(Line::from(spans));
+    }
+    if form.matches.is_empty() {
+        lines.push(Line::from(Span::styled(
+            " No argument constraints; the rule applies to every call. Ctrl+A adds one.",
+            theme.muted,
+        )));
+    }
+
+    let text = Paragraph::new(lines).block(block);
+    frame.render_widget(text, popup_area);
+}
+
+/// Renders the permission tester: the tool and argument fields, the
+/// decision, and why each rule before the deciding one was passed over.
+fn render_permission_test_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let Some(test) = &app.permission_test else {
+        return;
+    };
+    let area = frame.area();
+    let rules = app.config.get("amp.permissions");
+    let rules = rules.as_array().map(Vec::as_slice).unwrap_or_default();
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (rules.len() as u16 + 8).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Test Permissions ")
+        .title_bottom(" Tab to switch field, Esc to close ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let field = |label: &str, text: &str, active: bool| {
+        let style = if active { theme.accent } else { theme.muted };
+        let cursor = if active { "▏" } else { "" };
+        Line::from(vec![
+            Span::styled(format!(" {label:<6}"), style),
+            Span::styled(format!("{text}{cursor}"), theme.text),
+        ])
+    };
+    let mut lines = vec![
+        field("Tool", &test.tool, !test.editing_args),
+        field("Args", &test.args, test.editing_args),
+        Line::from(""),
+    ];
+
+    match app.permission_test_result() {
+        Some(Ok(eval)) => {
+            lines.push(match (&eval.matched, &eval.decision) {
+                (Some(i), Some(decision)) => Line::from(vec![
+                    Span::styled(" → ", theme.accent),
+                    Span::styled(decision.clone(), theme.value.add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("  (rule {i})"), theme.muted),
+                ]),
+                _ => Line::from(Span::styled(
+                    " → no rule matches; Amp uses its built-in default",
+                    theme.muted,
+                )),
+            });
+            lines.push(Line::from(""));
+            for (i, (rule, outcome)) in rules.iter().zip(&eval.checks).enumerate() {
+                let (mark, reason, style) = match outcome {
+                    RuleOutcome::Matched => ("✓", "matches".to_string(), theme.added),
+                    RuleOutcome::OtherTool => ("·", "different tool".to_string(), theme.muted),
+                    RuleOutcome::ArgumentDiffers(name) => {
+                        ("·", format!("\"{name}\" doesn't match"), theme.muted)
+                    }
+                    RuleOutcome::OtherContext(context) => {
+                        ("·", format!("only for {context}"), theme.muted)
+                    }
+                    RuleOutcome::InvalidPattern(error) => ("!", error.clone(), theme.error),
+                    RuleOutcome::Unsupported(what) => {
+                        ("?", format!("can't evaluate {what}"), theme.error)
+                    }
+                    RuleOutcome::Malformed => ("!", "not a valid rule".to_string(), theme.error),
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(format!(" {mark} {i:>2} "), style),
+                    Span::styled(format_json_compact(rule), theme.text),
+                    Span::styled(format!("  {reason}"), style),
+                ]));
+            }
+        }
+        Some(Err(message)) => {
+            lines.push(Line::from(Span::styled(format!(" {message}"), theme.error)))
+        }
+        None => {}
+    }
+
+    let text = Paragraph::new(lines).block(block);
+    frame.render_widget(text, popup_area);
+}
+
+/// Renders the env variables of an MCP server as a table, masking values
+/// whose names look like secrets unless revealed.
+fn render_env_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let entries = app.env_entries();
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = (entries.len().max(1) as u16 + 4).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let server = app.env_server.as_deref().unwrap_or_default();
+    let reveal = if app.reveal_secrets { "hide" } else { "show" };
+    let block = Block::default()
+        .title(format!(" env: {server} "))
+        .title_bottom(format!(
+            " a add · Enter edit · d delete · s {reveal} secrets · Esc close "
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    if entries.is_empty() {
+        let empty = Paragraph::new("  No variables. Press a to add one.")
+            .style(theme.muted)
+            .block(block);
+        frame.render_widget(empty, popup_area);
+        return;
+    }
+
+    let key_width = entries
+        .iter()
+        .map(|(k, _)| k.chars().count())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = entries
+        .iter()
+        .map(|(key, value)| {
+            let shown = if settings::looks_secret(key) {
+                app.mask(value).into_owned()
+            } else {
+                value.clone()
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("  {key:<key_width$}  ")),
+                Span::styled(shown, theme.value),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.selected_env));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the object shown in the object form: a schema's fields, or a
+/// map's entries, one row per key. Object fields open as nested panels.
+fn render_object_form_overlay(frame: &mut Frame, app: &App) {
+    let Some(form) = &app.object_form else {
+        return;
+    };
+    let theme = &app.theme;
     let area = frame.area();
-    let width = 50.min(area.width.saturating_sub(4));
-    let height = 3;
+    let rows = app.object_rows();
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (rows.len().max(1) as u16 + 4).min(area.height.saturating_sub(2));
     let x = (area.width.saturating_sub(width)) / 2;
     let y = (area.height.saturating_sub(height)) / 2;
     let popup_area = Rect::new(x, y, width, height);
 
     frame.render_widget(Clear, popup_area);
 
-    let title = match app.input_mode {
-        InputMode::EnteringKeyName => " Enter Key Name (Enter to confirm, Esc to cancel) ",
-        InputMode::EnteringCustomValue => " Enter Value (Enter to save, Esc to cancel) ",
-        InputMode::EnteringPermissionTool => " Enter Tool Name (Enter to confirm, Esc to cancel) ",
-        InputMode::EnteringDelegateTo => " Enter Program Name (Enter to confirm, Esc to cancel) ",
-        InputMode::EnteringMcpServerName => " Enter Server Name (Enter to confirm, Esc to cancel) ",
-        InputMode::EnteringMcpMatchField => {
-            " Enter Match Field e.g. command, url (Enter to confirm, Esc to cancel) "
-        }
-        InputMode::EnteringMcpMatchValue => " Enter Match Value (Enter to confirm, Esc to cancel) ",
-        _ => " Edit Value (Enter to save, Esc to cancel) ",
+    let title = std::iter::once(form.key)
+        .chain(form.path.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" › ");
+    let is_map = matches!(form.schema_here(), Some(ObjectSchema::Map { .. }));
+    let hints = if is_map {
+        " a add · Enter change · d delete · Esc back "
+    } else {
+        " Enter change · d reset · Esc back "
     };
+    let mut block = Block::default()
+        .title(format!(" {title} "))
+        .title(Line::from(hints).right_aligned())
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+    let description = rows
+        .get(form.selected)
+        .and_then(|row| row.field.as_ref())
+        .map(|field| field.description)
+        .filter(|d| !d.is_empty());
+    if let Some(description) = description {
+        block = block.title_bottom(Line::styled(format!(" {description} "), theme.muted));
+    }
+
+    if rows.is_empty() {
+        let text = match form.schema_here() {
+            Some(ObjectSchema::Map {
+                key_label,
+                key_example,
+                ..
+            }) => format!("  No entries. Press a to add one per {key_label} (e.g. {key_example})."),
+            _ => "  No fields.".to_string(),
+        };
+        let empty = Paragraph::new(text).style(theme.muted).block(block);
+        frame.render_widget(empty, popup_area);
+        return;
+    }
+
+    let key_width = rows
+        .iter()
+        .map(|row| row.key.chars().count())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| {
+            let nested = row.field.as_ref().is_some_and(|f| f.object.is_some());
+            let marker = if nested { " ›" } else { "" };
+            let value = match (&row.value, &row.field) {
+                (Some(value), _) => {
+                    let value = mask_secrets(app, &row.key, value);
+                    Span::styled(
+                        format!("{}{marker}", format_cell_value(&value)),
+                        theme.value,
+                    )
+                }
+                (None, Some(field)) if is_blank(&field.default) => {
+                    Span::styled(format!("(not set){marker}"), theme.muted)
+                }
+                (None, Some(field)) => Span::styled(
+                    format!("(default {}){marker}", format_cell_value(&field.default)),
+                    theme.muted,
+                ),
+                (None, None) => Span::raw(""),
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("  {:<key_width$}  ", row.key)),
+                value,
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(form.selected));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the profile picker, with a final row for saving a new profile.
+fn render_profile_select_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let item_count = app.profile_candidates.len() as u16 + 1;
+    let width = 60.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
 
     let block = Block::default()
-        .title(title)
+        .title(" Profiles (Enter to load, Esc to cancel) ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow));
+        .border_style(theme.popup_border);
 
-    let input = Paragraph::new(app.edit_buffer.as_str())
-        .style(Style::default().fg(Color::White))
-        .block(block);
+    let mut items: Vec<ListItem> = app
+        .profile_candidates
+        .iter()
+        .map(|name| ListItem::new(format!("  {name}")))
+        .collect();
+    items.push(ListItem::new(Span::styled(
+        "  + Save current settings as a new profile…",
+        theme.muted,
+    )));
 
-    frame.render_widget(input, popup_area);
+    let mut state = ListState::default();
+    state.select(Some(app.selected_profile));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
 }
 
-/// Renders the type selection overlay for choosing a custom key value type.
-fn render_type_select_overlay(frame: &mut Frame, app: &App) {
+fn render_preset_select_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
     let area = frame.area();
-    let item_count = CustomKeyType::ALL.len() as u16;
-    let width = 40.min(area.width.saturating_sub(4));
-    let height = (item_count + 2).min(area.height.saturating_sub(2)); // +2 for border
+    let item_count = app.preset_candidates.len() as u16 + 1;
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (item_count + 2).min(area.height.saturating_sub(2));
     let x = (area.width.saturating_sub(width)) / 2;
     let y = (area.height.saturating_sub(height)) / 2;
     let popup_area = Rect::new(x, y, width, height);
@@ -718,37 +3269,55 @@ fn render_type_select_overlay(frame: &mut Frame, app: &App) {
     frame.render_widget(Clear, popup_area);
 
     let block = Block::default()
-        .title(" Select Type (Enter to confirm, Esc to cancel) ")
+        .title(" Permission presets (Enter to append, r to replace, Esc to cancel) ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow));
-
-    let selected_style = Style::default()
-        .fg(Color::Black)
-        .bg(Color::Yellow)
-        .add_modifier(Modifier::BOLD);
+        .border_style(theme.popup_border);
 
-    let items: Vec<ListItem> = CustomKeyType::ALL
+    let name_width = app
+        .preset_candidates
         .iter()
-        .enumerate()
-        .map(|(i, t)| {
-            let style = if i == app.selected_type {
-                selected_style
+        .map(|p| p.name.width())
+        .max()
+        .unwrap_or(0);
+    let mut items: Vec<ListItem> = app
+        .preset_candidates
+        .iter()
+        .map(|preset| {
+            let rules = match preset.rules.len() {
+                1 => "1 rule".to_string(),
+                n => format!("{n} rules"),
+            };
+            let description = if preset.user && preset.description.is_empty() {
+                "yours".to_string()
             } else {
-                Style::default().fg(Color::White)
+                preset.description.clone()
             };
-            ListItem::new(format!("  {}", t.label())).style(style)
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("  {:<name_width$}  ", preset.name)),
+                Span::styled(format!("{rules:>8}  {description}"), theme.muted),
+            ]))
         })
         .collect();
+    items.push(ListItem::new(Span::styled(
+        "  + Save current rules as a new preset…",
+        theme.muted,
+    )));
 
-    let list = List::new(items).block(block);
-    frame.render_widget(list, popup_area);
+    let mut state = ListState::default();
+    state.select(Some(app.selected_preset));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
 }
 
-/// Renders the "Open Editor?" confirmation overlay.
-fn render_confirm_editor_overlay(frame: &mut Frame) {
+/// Renders what was deleted this session, newest first.
+fn render_trash_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
     let area = frame.area();
-    let width = 40.min(area.width.saturating_sub(4));
-    let height = 3;
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (app.trash.len() as u16 + 2).min(area.height.saturating_sub(2));
     let x = (area.width.saturating_sub(width)) / 2;
     let y = (area.height.saturating_sub(height)) / 2;
     let popup_area = Rect::new(x, y, width, height);
@@ -756,98 +3325,726 @@ fn render_confirm_editor_overlay(frame: &mut Frame) {
     frame.render_widget(Clear, popup_area);
 
     let block = Block::default()
-        .title(" Open Editor? (y/n) ")
+        .title(" Deleted this session (Enter to restore, Esc to cancel) ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow));
+        .border_style(theme.popup_border);
+    let text_width = usize::from(width.saturating_sub(4));
+    let items: Vec<ListItem> = app
+        .trash
+        .iter()
+        .rev()
+        .map(|trashed| ListItem::new(format!(" {}", clip(&trashed.describe(), text_width, 0))))
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.selected_trash));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
 
-    let text = Paragraph::new(" y: open in $EDITOR  n: skip")
-        .style(Style::default().fg(Color::White))
-        .block(block);
+/// Renders the recently changed settings: each key, when it was saved (or
+/// that it isn't yet), and its value now.
+fn render_recent_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let changes = app.recent_changes();
+    let area = frame.area();
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (changes.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
 
-    frame.render_widget(text, popup_area);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Recently changed (Enter to go to, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+    let key_width = changes
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(36);
+    let now = SystemTime::now();
+    let items: Vec<ListItem> = changes
+        .iter()
+        .map(|(key, at)| {
+            let when = match at {
+                Some(at) => format_age(now.duration_since(*at).unwrap_or_default()),
+                None => "unsaved".to_string(),
+            };
+            let value = match app.config.get_raw(key) {
+                Some(value) => format_cell_value(value),
+                None => "(unset)".to_string(),
+            };
+            let value_width = usize::from(width).saturating_sub(key_width + 17);
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(" {:<key_width$}  ", clip(key, key_width, 0))),
+                Span::styled(format!("{when:<9}  "), theme.muted),
+                Span::raw(clip(&value, value_width, 0)),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.selected_recent));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, popup_area, &mut state);
 }
 
-/// Renders the permission level selection overlay.
-fn render_permission_level_overlay(frame: &mut Frame, app: &App) {
+/// Renders the MCP server catalog: a list of servers, and the command line
+/// and env variables of the selected one underneath.
+fn render_registry_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
     let area = frame.area();
-    let item_count = PermissionLevel::ALL.len() as u16;
-    let width = 50.min(area.width.saturating_sub(4));
-    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = (app.registry.len() as u16 + 5).min(area.height.saturating_sub(2));
     let x = (area.width.saturating_sub(width)) / 2;
     let y = (area.height.saturating_sub(height)) / 2;
     let popup_area = Rect::new(x, y, width, height);
 
     frame.render_widget(Clear, popup_area);
 
+    let title = if app.registry_refreshing {
+        " MCP catalog (refreshing…) "
+    } else {
+        " MCP catalog (Enter to add, r to refresh, Esc to cancel) "
+    };
     let block = Block::default()
-        .title(" Select Permission (Enter to confirm, Esc to cancel) ")
+        .title(title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow));
+        .border_style(theme.popup_border);
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+    let rows = Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).split(inner);
 
-    let selected_style = Style::default()
-        .fg(Color::Black)
-        .bg(Color::Yellow)
-        .add_modifier(Modifier::BOLD);
-
-    let items: Vec<ListItem> = PermissionLevel::ALL
+    let servers = app.config.get("amp.mcpServers");
+    let name_width = app
+        .registry
         .iter()
-        .enumerate()
-        .map(|(i, level)| {
-            let style = if i == app.selected_permission_level {
-                selected_style
+        .map(|e| e.name.width())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = app
+        .registry
+        .iter()
+        .map(|entry| {
+            let added = if servers.get(&entry.name).is_some() {
+                " (added)"
             } else {
-                Style::default().fg(Color::White)
+                ""
             };
-            ListItem::new(format!("  {}", level.label())).style(style)
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(" {:<name_width$}  ", entry.name)),
+                Span::styled(format!("{}{added}", entry.description), theme.muted),
+            ]))
         })
         .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.selected_registry));
+    let list = List::new(items)
+        .style(theme.text)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, rows[0], &mut state);
 
-    let list = List::new(items).block(block);
-    frame.render_widget(list, popup_area);
+    let Some(entry) = app.registry.get(app.selected_registry) else {
+        return;
+    };
+    let env = if entry.env.is_empty() {
+        "no env variables needed".to_string()
+    } else {
+        format!("env: {}", entry.env.join(", "))
+    };
+    let details = Paragraph::new(vec![
+        Line::from(Span::styled(format!(" {}", entry.summary()), theme.value)),
+        Line::from(Span::styled(format!(" {env}"), theme.muted)),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::TOP)
+            .border_style(theme.border),
+    );
+    frame.render_widget(details, rows[1]);
 }
 
-/// Renders the MCP permission level selection overlay (allow/reject only).
-fn render_mcp_permission_level_overlay(frame: &mut Frame, app: &App) {
+/// Maximum number of search results shown at once.
+const SEARCH_RESULT_ROWS: u16 = 12;
+
+/// Renders the search prompt with its ranked results underneath.
+fn render_search_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
     let area = frame.area();
-    let item_count = McpPermissionLevel::ALL.len() as u16;
-    let width = 50.min(area.width.saturating_sub(4));
-    let height = (item_count + 2).min(area.height.saturating_sub(2));
+    let width = 60.min(area.width.saturating_sub(4));
+    let height = (3 + SEARCH_RESULT_ROWS + 2).min(area.height.saturating_sub(2));
     let x = (area.width.saturating_sub(width)) / 2;
     let y = (area.height.saturating_sub(height)) / 2;
     let popup_area = Rect::new(x, y, width, height);
 
     frame.render_widget(Clear, popup_area);
 
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(popup_area);
+
     let block = Block::default()
-        .title(" Select Action (Enter to confirm, Esc to cancel) ")
+        .title(" Search (Enter to jump, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+    render_prompt(frame, rows[0], block, &app.edit_buffer, false, theme.text);
+
+    let block = Block::default()
+        .title(format!(" {} matches ", app.search_results.len()))
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow));
+        .border_style(theme.popup_border);
 
-    let selected_style = Style::default()
-        .fg(Color::Black)
-        .bg(Color::Yellow)
-        .add_modifier(Modifier::BOLD);
+    let selected_style = theme.popup_selected;
 
-    let items: Vec<ListItem> = McpPermissionLevel::ALL
+    let items: Vec<ListItem> = app
+        .search_results
         .iter()
-        .enumerate()
-        .map(|(i, level)| {
-            let style = if i == app.selected_mcp_permission_level {
-                selected_style
-            } else {
-                Style::default().fg(Color::White)
+        .map(|hit| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("  {}", hit.key), theme.text),
+                Span::styled(format!("  {}", hit.section.label()), theme.muted),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    if !app.search_results.is_empty() {
+        state.select(Some(app.selected_search_result));
+    }
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(selected_style);
+    frame.render_stateful_widget(list, rows[1], &mut state);
+}
+
+/// Renders the command palette: the query, and the matching commands with
+/// the keys bound to each.
+fn render_palette_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let area = frame.area();
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = (3 + SEARCH_RESULT_ROWS + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(popup_area);
+
+    let block = Block::default()
+        .title(" Command (Enter to run, Esc to cancel) ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+    render_prompt(frame, rows[0], block, &app.edit_buffer, false, theme.text);
+
+    let block = Block::default()
+        .title(format!(" {} commands ", app.palette_results.len()))
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let items: Vec<ListItem> = app
+        .palette_results
+        .iter()
+        .map(|command| {
+            let keys = match command {
+                PaletteCommand::Run(action) => app.keymap.label(*action),
+                PaletteCommand::GoTo(_) => String::new(),
             };
-            ListItem::new(format!("  {}", level.label())).style(style)
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("  {}", command.label()), theme.text),
+                Span::styled(format!("  {keys}"), theme.muted),
+            ]))
         })
         .collect();
 
-    let list = List::new(items).block(block);
-    frame.render_widget(list, popup_area);
+    let mut state = ListState::default();
+    if !app.palette_results.is_empty() {
+        state.select(Some(app.selected_palette_result));
+    }
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(theme.popup_selected);
+    frame.render_stateful_widget(list, rows[1], &mut state);
+}
+
+/// Renders a read-only report overlay.
+fn render_report_overlay(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let Some(report) = &app.report else {
+        return;
+    };
+    let area = frame.area();
+    let longest = report
+        .lines
+        .iter()
+        .map(|l| l.chars().count())
+        .chain(std::iter::once(report.title.chars().count()))
+        .max()
+        .unwrap_or(0) as u16;
+    let width = (longest + 4).max(40).min(area.width.saturating_sub(4));
+    // Lines too long for the screen wrap, so count the rows they take.
+    let text_width = usize::from(width.saturating_sub(2)).max(1);
+    let rows: usize = report
+        .lines
+        .iter()
+        .map(|l| (l.chars().count() + 1).div_ceil(text_width).max(1))
+        .sum();
+    let height = (rows as u16 + 2).min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(width)) / 2;
+    let y = (area.height.saturating_sub(height)) / 2;
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(report.title.as_str())
+        .title_bottom(" Esc to close ")
+        .borders(Borders::ALL)
+        .border_style(theme.popup_border);
+
+    let lines: Vec<Line> = report
+        .lines
+        .iter()
+        .map(|l| Line::from(format!(" {l}")))
+        .collect();
+    let text = Paragraph::new(lines)
+        .style(theme.text)
+        .wrap(Wrap { trim: false })
+        .block(block);
+
+    frame.render_widget(text, popup_area);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Config;
+    use insta::assert_snapshot;
+
+    fn test_app() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"amp.showCosts": true, "amp.mcpServers": {"gh": {"command": "npx"}},
+                "amp.mcpPermissions": [{"matches": {"command": "npx"}, "action": "allow"}]}"#,
+        )
+        .unwrap();
+        (dir, App::new(Config::load(&path).unwrap()))
+    }
+
+    /// Draws the app on a screen of the given size and returns its rows.
+    fn render_at(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let buffer = render_to_buffer(app, width, height);
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    /// The screen's text at 80×24, for golden renders. Styles aren't
+    /// captured; trailing spaces are dropped.
+    fn screen(app: &mut App) -> String {
+        render_at(app, 80, 24)
+            .iter()
+            .map(|row| row.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// An app on a settings file with something in every section.
+    fn snapshot_app() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            serde_json::to_string_pretty(&serde_json::json!({
+                "amp.showCosts": false,
+                "amp.terminal.theme": "dark",
+                "amp.defaultVisibility": {"github.com/acme": "team"},
+                "amp.permissions": [
+                    {"tool": "Bash", "matches": {"cmd": "git *"}, "action": "allow"},
+                    {"tool": "edit_file", "action": "ask"}
+                ],
+                "amp.tools.disable": ["Read"],
+                "amp.mcpServers": {
+                    "github": {
+                        "command": "npx",
+                        "args": ["-y", "@modelcontextprotocol/server-github"],
+                        "env": {"GITHUB_TOKEN": "ghp_secret"}
+                    }
+                },
+                "amp.mcpPermissions": [{"matches": {"command": "npx"}, "action": "allow"}],
+                "my.custom": 3
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        let mut app = App::new(Config::load(&path).unwrap());
+        app.focus = Focus::Settings;
+        (dir, app)
+    }
+
+    fn select(app: &mut App, section: Section, key: &str) {
+        app.selected_section = Section::ALL.iter().position(|s| *s == section).unwrap();
+        app.selected_setting = app
+            .current_settings()
+            .iter()
+            .position(|e| match e {
+                SettingEntry::Known(def) => def.key == key,
+                SettingEntry::Unknown(k) => k == key,
+            })
+            .unwrap_or(0);
+    }
+
+    #[test]
+    fn test_snapshot_sections() {
+        for &section in Section::ALL {
+            let (_dir, mut app) = snapshot_app();
+            select(&mut app, section, "");
+            let name = format!("section_{}", section.label().to_lowercase());
+            assert_snapshot!(name, screen(&mut app));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_layouts() {
+        let (_dir, mut app) = snapshot_app();
+        select(&mut app, Section::General, "amp.showCosts");
+        app.show_help = true;
+        assert_snapshot!("help_pane", screen(&mut app));
+
+        let (_dir, mut app) = snapshot_app();
+        app.config
+            .set_env_overrides([("AMP_SHOW_COSTS".to_string(), "true".to_string())]);
+        select(&mut app, Section::General, "amp.showCosts");
+        app.show_help = true;
+        assert_snapshot!("env_override", screen(&mut app));
+
+        let (_dir, mut app) = snapshot_app();
+        select(
+            &mut app,
+            Section::General,
+            "amp.git.commit.coauthor.enabled",
+        );
+        app.dispatch(Action::ToggleGroup);
+        assert_snapshot!("collapsed_group", screen(&mut app));
+
+        let (_dir, mut app) = snapshot_app();
+        select(&mut app, Section::General, "amp.showCosts");
+        app.dispatch(Action::ToggleFavorite);
+        select(&mut app, Section::Tools, "amp.tools.disable");
+        app.dispatch(Action::ToggleFavorite);
+        app.go_to_section(Section::Favorites);
+        assert_snapshot!("favorites", screen(&mut app));
+
+        let (_dir, mut app) = snapshot_app();
+        app.toggle_split();
+        assert_snapshot!("split_view", screen(&mut app));
+
+        let (_dir, mut app) = snapshot_app();
+        app.toggle_sidebar();
+        assert_snapshot!("collapsed_sidebar", screen(&mut app));
+
+        let (_dir, mut app) = snapshot_app();
+        let narrow = render_at(&mut app, 44, 16).join("\n");
+        assert_snapshot!("narrow", narrow);
+
+        let (_dir, mut app) = snapshot_app();
+        select(&mut app, Section::Permissions, "amp.permissions");
+        app.show_preview = true;
+        assert_snapshot!("preview_pane", screen(&mut app));
+
+        let (_dir, mut app) = snapshot_app();
+        app.config.set(
+            "amp.defaultVisibility",
+            serde_json::json!({"github.com/acme": "team", "nested": {"a": [1, true]}}),
+        );
+        select(&mut app, Section::General, "amp.defaultVisibility");
+        app.dispatch(Action::ExpandTree);
+        app.tree_select_down();
+        app.tree_expand();
+        app.tree_expand();
+        assert_snapshot!("tree_view", screen(&mut app));
+    }
+
+    #[test]
+    fn test_highlight_json_line() {
+        let theme = Theme::dark();
+        let styled = |line: &'static str| -> Vec<(String, Style)> {
+            highlight_json_line(line, &theme)
+                .spans
+                .into_iter()
+                .map(|s| (s.content.into_owned(), s.style))
+                .collect()
+        };
+        assert_eq!(
+            styled(r#"  "a \"b\"": "x:y","#),
+            [
+                ("  ".to_string(), Style::default()),
+                (r#""a \"b\"""#.to_string(), theme.json_key),
+                (": ".to_string(), Style::default()),
+                (r#""x:y""#.to_string(), theme.json_string),
+                (",".to_string(), Style::default()),
+            ]
+        );
+        assert_eq!(
+            styled("[-1.5e3, true, null]")
+                .into_iter()
+                .filter(|(_, style)| *style != Style::default())
+                .collect::<Vec<_>>(),
+            [
+                ("-1.5e3".to_string(), theme.json_number),
+                ("true".to_string(), theme.json_literal),
+                ("null".to_string(), theme.json_literal),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_overlays() {
+        type Setup = fn(&mut App);
+        let overlays: &[(&str, Setup)] = &[
+            ("edit_value", |app| {
+                select(app, Section::General, "amp.skills.path");
+                app.activate_setting();
+            }),
+            ("enum_select", |app| {
+                select(app, Section::General, "amp.terminal.theme");
+                app.activate_setting();
+            }),
+            ("object_form", |app| {
+                select(app, Section::General, "amp.defaultVisibility");
+                app.activate_setting();
+            }),
+            ("search", |app| {
+                app.start_search();
+                app.edit_buffer.set("cost");
+                app.update_search();
+            }),
+            ("command_palette", |app| {
+                app.start_palette();
+                app.edit_buffer.set("mcp");
+                app.update_palette();
+            }),
+            ("diff", |app| {
+                app.config.set("amp.showCosts", Value::Bool(true));
+                app.show_diff();
+            }),
+            ("confirm_quit", |app| {
+                app.config.set("amp.showCosts", Value::Bool(true));
+                app.request_quit();
+            }),
+            ("copy_format", |app| {
+                select(app, Section::General, "amp.showCosts");
+                app.start_copy();
+            }),
+            ("merge_conflicts", |app| {
+                app.config.set("amp.terminal.theme", "light".into());
+                app.config.set("amp.tools.disable", serde_json::json!([]));
+                let path = app.config.path().to_path_buf();
+                let mut disk: Value =
+                    serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+                disk["amp.terminal.theme"] = "solarized".into();
+                disk.as_object_mut()
+                    .unwrap()
+                    .shift_remove("amp.tools.disable");
+                std::fs::write(&path, disk.to_string()).unwrap();
+                app.merge_from_disk();
+                app.conflict_select_down();
+                app.pick_conflict_side(MergeSide::Theirs);
+            }),
+            ("confirm_reset_all", |app| {
+                app.start_reset(ResetScope::All);
+            }),
+            ("trash", |app| {
+                select(app, Section::Advanced, "my.custom");
+                app.reset_setting();
+                select(app, Section::General, "amp.showCosts");
+                app.reset_setting();
+                app.start_trash();
+            }),
+            ("recent", |app| {
+                let hours_ago = |h: u64| SystemTime::now() - Duration::from_secs(h * 60 * 60);
+                app.modified.insert("amp.showCosts".into(), hours_ago(2));
+                app.modified.insert("amp.permissions".into(), hours_ago(26));
+                app.config
+                    .set("amp.tools.stopTimeout", serde_json::json!(60));
+                app.start_recent();
+            }),
+            ("registry", |app| {
+                app.registry_cache = None;
+                app.start_registry();
+                app.registry_select_down();
+            }),
+            ("preset_picker", |app| {
+                app.presets_dir = None;
+                app.start_preset_picker();
+                app.preset_select_down();
+            }),
+            ("json_editor", |app| {
+                select(app, Section::Permissions, "");
+                app.start_json_edit();
+            }),
+            ("rule_form", |app| {
+                select(app, Section::Permissions, "");
+                app.activate_setting();
+            }),
+            ("permission_test", |app| {
+                select(app, Section::Permissions, "");
+                app.start_permission_test();
+            }),
+            ("tool_picker", |app| {
+                select(app, Section::Tools, "amp.tools.disable");
+                app.activate_setting();
+            }),
+            ("mcp_server_form", |app| {
+                select(app, Section::Mcps, "");
+                app.activate_setting();
+            }),
+            ("mcp_env", |app| {
+                select(app, Section::Mcps, "");
+                app.start_env_edit();
+            }),
+            ("mcp_server_name", |app| {
+                select(app, Section::Mcps, "");
+                app.add_array_item();
+            }),
+            ("mcp_import", |app| {
+                app.import_paths = vec![
+                    Some("/home/me/.config/Claude/claude_desktop_config.json".into()),
+                    Some("/home/me/.cursor/mcp.json".into()),
+                    None,
+                ];
+                select(app, Section::Mcps, "");
+                app.start_mcp_import();
+            }),
+            ("custom_key", |app| {
+                select(app, Section::Advanced, "");
+                app.add_array_item();
+            }),
+            ("note", |app| {
+                select(app, Section::General, "amp.showCosts");
+                app.start_edit_note();
+            }),
+            ("report", |app| {
+                app.show_report(" Report ", vec!["first line".into(), "second".into()]);
+            }),
+        ];
+        for (name, setup) in overlays {
+            let (_dir, mut app) = snapshot_app();
+            setup(&mut app);
+            assert!(app.is_editing(), "{name} didn't open");
+            assert_snapshot!(format!("overlay_{name}"), screen(&mut app));
+        }
+    }
+
+    #[test]
+    fn test_every_section_renders_at_small_sizes() {
+        let (_dir, mut app) = test_app();
+        for (width, height) in [(20, 5), (30, 10), (45, 16), (59, 24), (80, 24), (140, 40)] {
+            for section in 0..Section::ALL.len() {
+                app.selected_section = section;
+                app.show_help = section % 2 == 0;
+                render_at(&mut app, width, height);
+            }
+            app.start_search();
+            render_at(&mut app, width, height);
+            app.cancel_edit();
+            app.show_messages();
+            render_at(&mut app, width, height);
+            app.cancel_edit();
+        }
+    }
+
+    #[test]
+    fn test_modified_markers_and_filter() {
+        let (_dir, mut app) = test_app();
+        app.config
+            .set("amp.notifications.enabled", Value::Bool(false));
+        let row = |rows: &[String], key: &str| rows.iter().find(|r| r.contains(key)).cloned();
+        let rows = render_at(&mut app, 100, 20);
+        assert!(row(&rows, "amp.showCosts")
+            .unwrap()
+            .contains("│○amp.showCosts"));
+        assert!(row(&rows, "amp.notifications.enabled")
+            .unwrap()
+            .contains("│●amp.notifications.enabled"));
+        assert!(row(&rows, "amp.skills.path")
+            .unwrap()
+            .contains("│ amp.skills.path"));
+
+        app.cycle_filter();
+        let rows = render_at(&mut app, 100, 20);
+        assert!(rows[0].contains(" modified only "));
+        assert!(row(&rows, "amp.skills.path").is_none());
+        assert!(row(&rows, "amp.showCosts").is_some());
+
+        app.cycle_filter();
+        let rows = render_at(&mut app, 100, 20);
+        assert!(rows[0].contains(" defaults only "));
+        assert!(row(&rows, "amp.skills.path").is_some());
+        assert!(row(&rows, "amp.showCosts").is_none());
+    }
+
+    #[test]
+    fn test_narrow_terminal_stacks_sections_over_settings() {
+        let (_dir, mut app) = test_app();
+        let rows = render_at(&mut app, 44, 20);
+        assert!(app.layout.sidebar_stacked);
+        assert!(rows[1].contains("Favo… Gene… Perm…"));
+        // Keys lose their `amp.` prefix and values follow on the same line.
+        assert!(rows.iter().any(|r| r.contains("○showCosts  [✓]")));
+
+        let tabs = section_tabs(app.layout.sidebar);
+        let pos = Position::new(tabs[3].x + 1, tabs[3].y);
+        assert_eq!(app.layout.section_at(pos), Some(3));
+
+        render_at(&mut app, 100, 20);
+        assert!(!app.layout.sidebar_stacked);
+        assert_eq!(app.layout.section_at(Position::new(2, 2)), Some(1));
+    }
+
+    #[test]
+    fn test_tiny_terminal_shows_a_warning() {
+        let (_dir, mut app) = test_app();
+        let rows = render_at(&mut app, 25, 6);
+        assert!(rows[0].starts_with("Terminal too small"));
+        assert!(rows[1].starts_with("(25×6)"));
+    }
+
+    #[test]
+    fn test_clip() {
+        assert_eq!(clip("short", 10, 0), "short");
+        assert_eq!(clip("abcdefghij", 6, 0), "abcde…");
+        assert_eq!(clip("abcdefghij", 6, 3), "…defg…");
+        assert_eq!(clip("abcdefghij", 6, 5), "…fghij");
+        assert_eq!(clip("abcdefghij", 6, 50), "…j");
+        assert_eq!(clip("abc", 0, 0), "");
+    }
+
+    #[test]
+    fn test_prompt_view_scrolls_by_display_width() {
+        let mut input = TextInput::default();
+        input.set("ab日本語");
+        assert_eq!(prompt_view(&input, false, 20), ("ab日本語".into(), 8));
+        assert_eq!(prompt_view(&input, false, 6), ("本語".into(), 4));
+        assert_eq!(prompt_view(&input, true, 4), ("•••".into(), 3));
+        input.move_home();
+        assert_eq!(prompt_view(&input, false, 5), ("ab日".into(), 0));
+    }
 
     #[test]
     fn test_format_value_boolean() {
@@ -919,6 +4116,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(5)), "just now");
+        assert_eq!(format_age(Duration::from_secs(150)), "2m ago");
+        assert_eq!(format_age(Duration::from_secs(2 * 3600)), "2h ago");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400)), "3d ago");
+    }
+
+    #[test]
+    fn test_summarize_matches() {
+        assert_eq!(
+            summarize_matches(&serde_json::json!({"command": "npx", "args": ["-y", "srv"]})),
+            "command=npx, args=[-y, srv]"
+        );
+        assert_eq!(
+            summarize_matches(&serde_json::json!({"url": "https://*", "env": {"MODE": "ro"}})),
+            "url=https://*, env.MODE=ro"
+        );
+        assert_eq!(summarize_matches(&serde_json::json!({})), "(any server)");
+    }
+
     #[test]
     fn test_collect_object_columns() {
         let mut obj1 = serde_json::Map::new();